@@ -0,0 +1,211 @@
+//! Object-safe archive interface
+//!
+//! [`ArchiveReader`](super::ArchiveReader) is generic over its entry type
+//! and has generic methods, so it cannot be used as a trait object. The
+//! [`Archive`] trait wraps every reader behind one dynamic interface:
+//! [`ArchiveFormat::open`] sniffs the format of a file and returns a
+//! `Box<dyn Archive>` whose entries can be listed and streamed without
+//! matching on the format.
+//!
+//! Entry names are always `/`-separated. Lookups accept either separator.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::{
+    ArchiveFormat, ArchiverError, ArchiverResult, ExtractOptions, ExtractReport, Operation,
+};
+
+/// Format-independent metadata of an archive entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveEntryInfo {
+    /// File name (relative path within archive, `/`-separated)
+    pub name: String,
+    /// Uncompressed size in bytes
+    pub size: u64,
+    /// Whether the entry is stored compressed
+    pub compressed: bool,
+}
+
+/// Dynamic interface over any supported archive
+pub trait Archive: Send + Sync {
+    /// Get the format of the archive
+    fn format(&self) -> ArchiveFormat;
+
+    /// Get the archive path
+    fn path(&self) -> &Path;
+
+    /// List the entries of the archive
+    fn list_entries(&self) -> Vec<ArchiveEntryInfo>;
+
+    /// Open a streaming reader over the decoded contents of an entry
+    fn open_entry(&self, name: &str) -> ArchiverResult<Box<dyn Read + Send + '_>>;
+
+    /// Read the decoded contents of an entry into memory
+    fn read_entry(&self, name: &str) -> ArchiverResult<Vec<u8>> {
+        let mut data = Vec::new();
+        self.open_entry(name)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Check whether the archive contains an entry
+    fn contains(&self, name: &str) -> bool {
+        let name = normalize_name(name);
+        self.list_entries().iter().any(|e| e.name == name)
+    }
+
+    /// Select the entries an extraction would write, without writing them
+    fn plan_extract(
+        &self,
+        output_dir: &Path,
+        options: &ExtractOptions,
+    ) -> ArchiverResult<ExtractReport> {
+        options.plan(self.list_entries(), output_dir)
+    }
+
+    /// Extract the entries selected by `options`, streaming each one to disk
+    ///
+    /// Progress is reported per entry. Files written before a cancellation
+    /// are removed.
+    fn extract_with(
+        &self,
+        output_dir: &Path,
+        options: &ExtractOptions,
+        operation: &Operation,
+    ) -> ArchiverResult<ExtractReport> {
+        let report = self.plan_extract(output_dir, options)?;
+        operation.begin(report.entries.len(), report.total_bytes);
+
+        let result = report.entries.iter().try_for_each(|entry| {
+            operation.check()?;
+
+            let output_path = safe_output_path(output_dir, &entry.name);
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut reader = self.open_entry(&entry.name)?;
+            operation.track_output(output_path.clone());
+            let mut writer = BufWriter::new(File::create(&output_path)?);
+            io::copy(&mut reader, &mut writer)?;
+            writer.flush()?;
+
+            operation.entry_done(&entry.name, entry.size)
+        });
+
+        operation.finish(result)?;
+        Ok(report)
+    }
+
+    /// Extract all entries to a directory, streaming each one to disk
+    fn extract_to(&self, output_dir: &Path) -> ArchiverResult<usize> {
+        let options = ExtractOptions::default();
+        let report = self.extract_with(output_dir, &options, &Operation::new())?;
+        Ok(report.entries.len())
+    }
+}
+
+/// Normalize an entry name to `/` separators
+pub fn normalize_name(name: &str) -> String {
+    name.replace('\\', "/")
+}
+
+/// Join an entry name onto an output directory without escaping it
+///
+/// Names are rewritten rather than refused: root and `..` components are
+/// dropped, and `:` (drive letters, NTFS streams) is replaced with `_`, so
+/// `C:\..\..\Windows\evil.dll` ends up as `C_/Windows/evil.dll` under
+/// `base_dir`.
+pub fn safe_output_path(base_dir: &Path, name: &str) -> PathBuf {
+    let mut path = base_dir.to_path_buf();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." | ".." => {}
+            part => path.push(part.replace(':', "_")),
+        }
+    }
+    path
+}
+
+/// Find an entry by name, ignoring the path separator style
+pub(crate) fn find_entry<'a, E>(
+    entries: &'a [E],
+    name: &str,
+    entry_name: impl Fn(&E) -> &str,
+) -> ArchiverResult<&'a E> {
+    let wanted = normalize_name(name);
+    entries
+        .iter()
+        .find(|e| normalize_name(entry_name(e)) == wanted)
+        .ok_or_else(|| ArchiverError::FileNotFound(name.to_string()))
+}
+
+/// Check that `size` bytes at `offset` lie within a file of `file_len` bytes
+pub(crate) fn check_bounds(
+    name: &str,
+    offset: u64,
+    size: u64,
+    file_len: u64,
+) -> ArchiverResult<()> {
+    let end = offset.checked_add(size);
+    if end.is_none_or(|end| end > file_len) {
+        return Err(ArchiverError::InvalidFormat(format!(
+            "Entry {} exceeds archive size",
+            name
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_entry_separators() {
+        let names = ["Data\\Map001.rxdata", "Graphics/Pictures/title.png"];
+
+        let found = find_entry(&names, "Data/Map001.rxdata", |n| n).unwrap();
+        assert_eq!(*found, "Data\\Map001.rxdata");
+
+        let found = find_entry(&names, "Graphics\\Pictures\\title.png", |n| n).unwrap();
+        assert_eq!(*found, "Graphics/Pictures/title.png");
+
+        assert!(matches!(
+            find_entry(&names, "Data/Map002.rxdata", |n| n),
+            Err(ArchiverError::FileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_safe_output_path() {
+        let base = Path::new("/out");
+        assert_eq!(
+            safe_output_path(base, "Data\\Map001.rxdata"),
+            PathBuf::from("/out/Data/Map001.rxdata")
+        );
+        assert_eq!(
+            safe_output_path(base, "../../etc/passwd"),
+            PathBuf::from("/out/etc/passwd")
+        );
+        assert_eq!(
+            safe_output_path(base, "/abs/./file.txt"),
+            PathBuf::from("/out/abs/file.txt")
+        );
+        assert_eq!(
+            safe_output_path(base, "C:\\..\\Windows\\evil.dll"),
+            PathBuf::from("/out/C_/Windows/evil.dll")
+        );
+    }
+
+    #[test]
+    fn test_check_bounds() {
+        assert!(check_bounds("a", 10, 10, 20).is_ok());
+        assert!(check_bounds("a", 10, 11, 20).is_err());
+        assert!(check_bounds("a", u64::MAX, 1, 20).is_err());
+    }
+}
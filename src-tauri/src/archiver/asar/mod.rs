@@ -0,0 +1,193 @@
+//! ASAR Archive module for Electron apps
+//!
+//! Electron-packaged games (including MV/MZ games wrapped in Electron) ship
+//! their sources in `resources/app.asar`.
+//!
+//! ## Archive Format
+//!
+//! - Bytes 0-7: Pickle holding the header size: `u32 4` + `u32 header_size`
+//! - Next `header_size` bytes: Pickle holding the JSON header:
+//!   `u32 payload_size` + `u32 json_len` + JSON (padded to 4 bytes)
+//! - Remaining bytes: File contents, addressed by `offset` relative to
+//!   the end of the header
+//!
+//! The JSON header is a tree of `files` objects. File nodes carry `size`,
+//! `offset` (as a string), and optionally `executable`, `unpacked` and
+//! `integrity`. Unpacked files are stored next to the archive in
+//! `app.asar.unpacked/` instead of inside it.
+
+mod reader;
+mod writer;
+
+pub use reader::AsarReader;
+pub use writer::AsarWriter;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::archiver::safe_output_path;
+
+/// Block size used for integrity block hashes (4 MiB)
+pub const INTEGRITY_BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Hash algorithm used for integrity entries
+pub const INTEGRITY_ALGORITHM: &str = "SHA256";
+
+/// Suffix of the directory holding unpacked files
+pub const UNPACKED_SUFFIX: &str = ".unpacked";
+
+/// Integrity information of a file entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AsarIntegrity {
+    /// Hash algorithm (always "SHA256")
+    pub algorithm: String,
+    /// Hex hash of the whole file
+    pub hash: String,
+    /// Size of each hashed block
+    pub block_size: usize,
+    /// Hex hashes of each block
+    pub blocks: Vec<String>,
+}
+
+impl AsarIntegrity {
+    /// Compute integrity information for file contents
+    pub fn compute(data: &[u8]) -> Self {
+        let blocks = if data.is_empty() {
+            vec![sha256_hex(data)]
+        } else {
+            data.chunks(INTEGRITY_BLOCK_SIZE).map(sha256_hex).collect()
+        };
+
+        Self {
+            algorithm: INTEGRITY_ALGORITHM.to_string(),
+            hash: sha256_hex(data),
+            block_size: INTEGRITY_BLOCK_SIZE,
+            blocks,
+        }
+    }
+
+    /// Check file contents against this integrity information
+    pub fn verify(&self, data: &[u8]) -> bool {
+        if !self.algorithm.eq_ignore_ascii_case(INTEGRITY_ALGORITHM) {
+            return false;
+        }
+        if !self.hash.eq_ignore_ascii_case(&sha256_hex(data)) {
+            return false;
+        }
+        if self.block_size == 0 || self.blocks.is_empty() {
+            return true;
+        }
+
+        let blocks: Vec<String> = if data.is_empty() {
+            vec![sha256_hex(data)]
+        } else {
+            data.chunks(self.block_size).map(sha256_hex).collect()
+        };
+        blocks.len() == self.blocks.len()
+            && blocks
+                .iter()
+                .zip(&self.blocks)
+                .all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+}
+
+/// File entry in an ASAR archive
+#[derive(Debug, Clone)]
+pub struct AsarEntry {
+    /// File name (relative path within archive, `/`-separated)
+    pub name: String,
+    /// File size in bytes
+    pub size: u64,
+    /// Offset of the file data, relative to the end of the header
+    pub offset: u64,
+    /// Whether the file is stored in the `.unpacked` directory
+    pub unpacked: bool,
+    /// Whether the file is executable
+    pub executable: bool,
+    /// Integrity hashes, if present
+    pub integrity: Option<AsarIntegrity>,
+    /// Link target for symbolic links
+    pub link: Option<String>,
+}
+
+impl AsarEntry {
+    /// Get the output path for extraction
+    pub fn output_path(&self, base_dir: &Path) -> PathBuf {
+        safe_output_path(base_dir, &self.name)
+    }
+
+    /// Check if the entry is a symbolic link
+    pub fn is_link(&self) -> bool {
+        self.link.is_some()
+    }
+}
+
+/// Get the `.unpacked` directory for an archive path
+pub fn unpacked_dir(archive_path: &Path) -> PathBuf {
+    let mut name = archive_path.as_os_str().to_os_string();
+    name.push(UNPACKED_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Compute a lowercase hex SHA-256 digest
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Check whether a file looks like an ASAR archive
+pub fn is_asar_archive<P: AsRef<Path>>(path: P) -> bool {
+    let mut header = [0u8; 16];
+    let read = File::open(path).and_then(|mut file| file.read_exact(&mut header));
+    if read.is_err() {
+        return false;
+    }
+
+    let size_pickle = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let header_size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let payload_size = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    let json_len = u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+
+    size_pickle == 4
+        && header_size >= 8
+        && payload_size == header_size - 4
+        && json_len <= payload_size - 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrity_round_trip() {
+        let data = b"console.log('hello');";
+        let integrity = AsarIntegrity::compute(data);
+
+        assert_eq!(integrity.algorithm, "SHA256");
+        assert_eq!(integrity.blocks.len(), 1);
+        assert_eq!(integrity.blocks[0], integrity.hash);
+        assert!(integrity.verify(data));
+        assert!(!integrity.verify(b"console.log('bye');"));
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_unpacked_dir() {
+        let dir = unpacked_dir(Path::new("/game/resources/app.asar"));
+        assert_eq!(dir, PathBuf::from("/game/resources/app.asar.unpacked"));
+    }
+}
@@ -0,0 +1,486 @@
+//! ASAR Archive Reader (Unpacker)
+
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom, Take};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::{unpacked_dir, AsarEntry, AsarIntegrity};
+use crate::archiver::archive::{check_bounds, find_entry};
+use crate::archiver::{
+    Archive, ArchiveEntryInfo, ArchiveFormat, ArchiveReader, ArchiverError, ArchiverResult,
+};
+
+/// Maximum accepted JSON header size (64 MiB)
+const MAX_HEADER_SIZE: u32 = 64 * 1024 * 1024;
+
+/// ASAR Archive Reader for unpacking Electron `app.asar` files
+pub struct AsarReader {
+    /// Path to the archive file
+    path: PathBuf,
+    /// Offset of the file data (end of the header)
+    data_offset: u64,
+    /// File entries in the archive
+    entries: Vec<AsarEntry>,
+}
+
+impl AsarReader {
+    /// Read the JSON header and return it with the data offset
+    fn read_header(path: &Path) -> ArchiverResult<(Value, u64)> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        // Size pickle: payload size (always 4) + header size
+        let size_pickle = read_u32(&mut reader)?;
+        let header_size = read_u32(&mut reader)?;
+        if size_pickle != 4 || !(8..=MAX_HEADER_SIZE).contains(&header_size) {
+            return Err(ArchiverError::InvalidFormat(
+                "Not an ASAR archive".to_string(),
+            ));
+        }
+
+        let data_offset = 8 + header_size as u64;
+        if data_offset > file_len {
+            return Err(ArchiverError::InvalidFormat(
+                "ASAR header exceeds file size".to_string(),
+            ));
+        }
+
+        // Header pickle: payload size + string length + JSON
+        let mut header = vec![0u8; header_size as usize];
+        reader.read_exact(&mut header)?;
+
+        let json_len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let json = header.get(8..8 + json_len).ok_or_else(|| {
+            ArchiverError::InvalidFormat("ASAR header string is truncated".to_string())
+        })?;
+
+        let value = serde_json::from_slice(json).map_err(|e| {
+            ArchiverError::InvalidFormat(format!("Invalid ASAR header JSON: {}", e))
+        })?;
+
+        Ok((value, data_offset))
+    }
+
+    /// Flatten the header tree into file entries
+    pub(crate) fn parse_header(header: &Value) -> ArchiverResult<Vec<AsarEntry>> {
+        let mut entries = Vec::new();
+        Self::parse_directory(header, "", &mut entries)?;
+        Ok(entries)
+    }
+
+    /// Parse the `files` object of a directory node
+    fn parse_directory(
+        node: &Value,
+        prefix: &str,
+        entries: &mut Vec<AsarEntry>,
+    ) -> ArchiverResult<()> {
+        let files = node
+            .get("files")
+            .and_then(Value::as_object)
+            .ok_or_else(|| {
+                ArchiverError::InvalidFormat(format!("Directory without files: {}", prefix))
+            })?;
+
+        for (name, child) in files {
+            if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+                return Err(ArchiverError::InvalidFormat(format!(
+                    "Invalid entry name in {}: {}",
+                    prefix, name
+                )));
+            }
+
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+
+            if child.get("files").is_some() {
+                Self::parse_directory(child, &path, entries)?;
+            } else {
+                entries.push(Self::parse_file(child, path)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse a file or link node
+    fn parse_file(node: &Value, name: String) -> ArchiverResult<AsarEntry> {
+        let link = node.get("link").and_then(Value::as_str).map(str::to_string);
+        let unpacked = node
+            .get("unpacked")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let executable = node
+            .get("executable")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        let size = match node.get("size") {
+            Some(size) => size.as_u64().ok_or_else(|| {
+                ArchiverError::InvalidFormat(format!("Invalid size for {}", name))
+            })?,
+            None if link.is_some() => 0,
+            None => {
+                return Err(ArchiverError::InvalidFormat(format!(
+                    "Missing size for {}",
+                    name
+                )))
+            }
+        };
+
+        // Offsets are stored as strings to survive JSON number precision
+        let offset = match node.get("offset") {
+            Some(Value::String(s)) => s.parse().map_err(|_| {
+                ArchiverError::InvalidFormat(format!("Invalid offset for {}", name))
+            })?,
+            Some(Value::Number(n)) => n.as_u64().ok_or_else(|| {
+                ArchiverError::InvalidFormat(format!("Invalid offset for {}", name))
+            })?,
+            Some(_) => {
+                return Err(ArchiverError::InvalidFormat(format!(
+                    "Invalid offset for {}",
+                    name
+                )))
+            }
+            None if unpacked || link.is_some() => 0,
+            None => {
+                return Err(ArchiverError::InvalidFormat(format!(
+                    "Missing offset for {}",
+                    name
+                )))
+            }
+        };
+
+        let integrity = match node.get("integrity") {
+            Some(value) => Some(
+                serde_json::from_value::<AsarIntegrity>(value.clone()).map_err(|e| {
+                    ArchiverError::InvalidFormat(format!("Invalid integrity for {}: {}", name, e))
+                })?,
+            ),
+            None => None,
+        };
+
+        Ok(AsarEntry {
+            name,
+            size,
+            offset,
+            unpacked,
+            executable,
+            integrity,
+            link,
+        })
+    }
+
+    /// Extract a single entry to a byte vector
+    pub fn extract_to_memory(&self, entry: &AsarEntry) -> ArchiverResult<Vec<u8>> {
+        // Allocate only once the entry is known to fit in the file it is
+        // read from; the header size alone is not trusted
+        let mut reader = self.entry_reader(entry)?;
+        let mut data = Vec::with_capacity(reader.limit() as usize);
+        reader.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Open a streaming reader over an entry
+    pub fn entry_reader(&self, entry: &AsarEntry) -> ArchiverResult<Take<BufReader<File>>> {
+        if entry.is_link() {
+            return Err(ArchiverError::InvalidFormat(format!(
+                "{} is a symbolic link",
+                entry.name
+            )));
+        }
+
+        if entry.unpacked {
+            let path = entry.output_path(&unpacked_dir(&self.path));
+            if !path.exists() {
+                return Err(ArchiverError::FileNotFound(
+                    path.to_string_lossy().to_string(),
+                ));
+            }
+            let file = File::open(path)?;
+            let len = file.metadata()?.len();
+            return Ok(BufReader::new(file).take(len));
+        }
+
+        let file = File::open(&self.path)?;
+        let offset = self.data_offset.checked_add(entry.offset).ok_or_else(|| {
+            ArchiverError::InvalidFormat(format!("Entry {} exceeds archive size", entry.name))
+        })?;
+        check_bounds(&entry.name, offset, entry.size, file.metadata()?.len())?;
+
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(offset))?;
+        Ok(reader.take(entry.size))
+    }
+
+    /// Check an entry against its integrity hashes
+    ///
+    /// Entries without integrity information are considered valid.
+    pub fn verify_entry(&self, entry: &AsarEntry) -> ArchiverResult<bool> {
+        match &entry.integrity {
+            Some(integrity) => Ok(integrity.verify(&self.extract_to_memory(entry)?)),
+            None => Ok(true),
+        }
+    }
+
+    /// Get the archive path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the names of entries stored in the `.unpacked` directory
+    pub fn unpacked_names(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|e| e.unpacked)
+            .map(|e| e.name.as_str())
+    }
+}
+
+impl ArchiveReader for AsarReader {
+    type Entry = AsarEntry;
+
+    fn open<P: AsRef<Path>>(path: P) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+
+        let (header, data_offset) = Self::read_header(path)?;
+        let entries = Self::parse_header(&header)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            data_offset,
+            entries,
+        })
+    }
+
+    fn entries(&self) -> &[AsarEntry] {
+        &self.entries
+    }
+
+    fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> ArchiverResult<usize> {
+        let output_dir = output_dir.as_ref();
+        let mut count = 0;
+
+        // Symbolic links are not recreated
+        for entry in self.entries.iter().filter(|e| !e.is_link()) {
+            self.extract_entry(&entry.name, output_dir)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn extract_entry<P: AsRef<Path>>(&self, entry_name: &str, output_dir: P) -> ArchiverResult<()> {
+        let output_dir = output_dir.as_ref();
+
+        // Find the entry
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.name == entry_name)
+            .ok_or_else(|| ArchiverError::FileNotFound(entry_name.to_string()))?;
+
+        // Extract to memory
+        let data = self.extract_to_memory(entry)?;
+
+        // Write to file
+        let output_path = entry.output_path(output_dir);
+
+        // Create parent directories
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&output_path, data)?;
+
+        Ok(())
+    }
+}
+
+impl Archive for AsarReader {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Asar
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn list_entries(&self) -> Vec<ArchiveEntryInfo> {
+        // Symbolic links have no contents of their own
+        self.entries
+            .iter()
+            .filter(|e| !e.is_link())
+            .map(|e| ArchiveEntryInfo {
+                name: e.name.clone(),
+                size: e.size,
+                compressed: false,
+            })
+            .collect()
+    }
+
+    fn open_entry(&self, name: &str) -> ArchiverResult<Box<dyn Read + Send + '_>> {
+        let entry = find_entry(&self.entries, name, |e| &e.name)?;
+        Ok(Box::new(self.entry_reader(entry)?))
+    }
+}
+
+/// Read a little-endian u32
+fn read_u32<R: Read>(reader: &mut R) -> ArchiverResult<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Build an archive by hand from a JSON header and data
+    fn build_archive(header: &str, data: &[u8]) -> Vec<u8> {
+        let json = header.as_bytes();
+        let padded = json.len().div_ceil(4) * 4;
+        let payload_size = 4 + padded as u32;
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&4u32.to_le_bytes());
+        out.extend_from_slice(&(payload_size + 4).to_le_bytes());
+        out.extend_from_slice(&payload_size.to_le_bytes());
+        out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        out.extend_from_slice(json);
+        out.resize(out.len() + padded - json.len(), 0);
+        out.extend_from_slice(data);
+        out
+    }
+
+    #[test]
+    fn test_read_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("app.asar");
+
+        let main_js = b"require('./game');";
+        let integrity = serde_json::to_string(&AsarIntegrity::compute(main_js)).unwrap();
+        let header = format!(
+            r#"{{"files":{{"main.js":{{"size":18,"offset":"0","integrity":{}}},"www":{{"files":{{"data":{{"files":{{"System.json":{{"size":2,"offset":"18"}}}}}}}}}},"native.node":{{"size":3,"unpacked":true}},"current":{{"link":"www"}}}}}}"#,
+            integrity
+        );
+        fs::write(
+            &archive_path,
+            build_archive(&header, b"require('./game');{}"),
+        )
+        .unwrap();
+        fs::create_dir_all(temp_dir.path().join("app.asar.unpacked")).unwrap();
+        fs::write(
+            temp_dir.path().join("app.asar.unpacked/native.node"),
+            b"bin",
+        )
+        .unwrap();
+
+        let reader = AsarReader::open(&archive_path).unwrap();
+        assert_eq!(reader.entries().len(), 4);
+
+        let main = reader
+            .entries()
+            .iter()
+            .find(|e| e.name == "main.js")
+            .unwrap();
+        assert_eq!(reader.extract_to_memory(main).unwrap(), main_js);
+        assert!(reader.verify_entry(main).unwrap());
+
+        let system = reader
+            .entries()
+            .iter()
+            .find(|e| e.name == "www/data/System.json")
+            .unwrap();
+        assert_eq!(reader.extract_to_memory(system).unwrap(), b"{}");
+
+        let mut streamed = String::new();
+        Archive::open_entry(&reader, "www\\data\\System.json")
+            .unwrap()
+            .read_to_string(&mut streamed)
+            .unwrap();
+        assert_eq!(streamed, "{}");
+
+        assert_eq!(
+            reader.unpacked_names().collect::<Vec<_>>(),
+            vec!["native.node"]
+        );
+
+        let output_dir = temp_dir.path().join("out");
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 3);
+        assert_eq!(fs::read(output_dir.join("native.node")).unwrap(), b"bin");
+        assert!(!output_dir.join("current").exists());
+    }
+
+    #[test]
+    fn test_integrity_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("app.asar");
+
+        let integrity = serde_json::to_string(&AsarIntegrity::compute(b"original")).unwrap();
+        let header = format!(
+            r#"{{"files":{{"a.js":{{"size":8,"offset":"0","integrity":{}}}}}}}"#,
+            integrity
+        );
+        fs::write(&archive_path, build_archive(&header, b"modified")).unwrap();
+
+        let reader = AsarReader::open(&archive_path).unwrap();
+        assert!(!reader.verify_entry(&reader.entries()[0]).unwrap());
+    }
+
+    #[test]
+    fn test_reject_traversal_names() {
+        let header: Value = serde_json::from_str(r#"{"files":{"..":{"files":{}}}}"#).unwrap();
+        let result = AsarReader::parse_header(&header);
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_entry_past_end() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("app.asar");
+        fs::write(
+            &archive_path,
+            build_archive(r#"{"files":{"a.js":{"size":100,"offset":"0"}}}"#, b"short"),
+        )
+        .unwrap();
+
+        let reader = AsarReader::open(&archive_path).unwrap();
+        let result = reader.extract_to_memory(&reader.entries()[0]);
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_read_invalid_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("bad.asar");
+        fs::write(&archive_path, b"PK\x03\x04 not an asar").unwrap();
+
+        let result = AsarReader::open(&archive_path);
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_entry_huge_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("app.asar");
+        fs::write(
+            &archive_path,
+            build_archive(
+                r#"{"files":{"a.js":{"size":4611686018427387904,"offset":"0"}}}"#,
+                b"short",
+            ),
+        )
+        .unwrap();
+
+        let reader = AsarReader::open(&archive_path).unwrap();
+        let result = reader.extract_to_memory(&reader.entries()[0]);
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+    }
+}
@@ -0,0 +1,452 @@
+//! ASAR Archive Writer (Repacker)
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use super::{unpacked_dir, AsarIntegrity};
+use crate::archiver::{ArchiveWriter, ArchiverError, ArchiverResult, Operation};
+
+/// File entry to be packed
+#[derive(Debug)]
+struct PackEntry {
+    /// Name in the archive (with forward slashes)
+    archive_name: String,
+    /// File data
+    data: Vec<u8>,
+    /// Whether the file is executable
+    executable: bool,
+}
+
+/// ASAR Archive Writer for creating Electron `app.asar` files
+pub struct AsarWriter {
+    /// Files to pack
+    entries: Vec<PackEntry>,
+    /// Names of files to store in the `.unpacked` directory
+    unpacked: HashSet<String>,
+    /// Extensions of files to store in the `.unpacked` directory
+    unpack_extensions: Vec<String>,
+    /// Whether to write integrity hashes
+    integrity: bool,
+}
+
+impl AsarWriter {
+    /// Enable or disable integrity hashes (enabled by default)
+    pub fn with_integrity(mut self, integrity: bool) -> Self {
+        self.integrity = integrity;
+        self
+    }
+
+    /// Store files with these extensions unpacked (e.g. `node`, `dll`)
+    pub fn with_unpacked_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.unpack_extensions = extensions
+            .into_iter()
+            .map(|e| e.as_ref().trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Store a file unpacked, e.g. to preserve the layout of an existing archive
+    pub fn mark_unpacked(&mut self, archive_name: &str) {
+        self.unpacked.insert(archive_name.replace('\\', "/"));
+    }
+
+    /// Check whether an entry is stored unpacked
+    fn is_unpacked(&self, entry: &PackEntry) -> bool {
+        if self.unpacked.contains(&entry.archive_name) {
+            return true;
+        }
+
+        match entry.archive_name.rsplit_once('.') {
+            Some((_, ext)) => self.unpack_extensions.contains(&ext.to_lowercase()),
+            None => false,
+        }
+    }
+
+    /// Insert a file node into the header tree
+    fn insert_node(root: &mut Map<String, Value>, name: &str, node: Value) -> ArchiverResult<()> {
+        let mut parts: Vec<&str> = name.split('/').filter(|p| !p.is_empty()).collect();
+        let file_name = parts.pop().ok_or_else(|| {
+            ArchiverError::InvalidFormat(format!("Invalid archive name: {}", name))
+        })?;
+
+        let mut current = root;
+        for part in parts {
+            let dir = current
+                .entry(part.to_string())
+                .or_insert_with(|| json!({ "files": {} }));
+            current = dir
+                .get_mut("files")
+                .and_then(Value::as_object_mut)
+                .ok_or_else(|| {
+                    ArchiverError::InvalidFormat(format!("{} is both a file and a directory", name))
+                })?;
+        }
+
+        if current.contains_key(file_name) {
+            return Err(ArchiverError::InvalidFormat(format!(
+                "Duplicate archive name: {}",
+                name
+            )));
+        }
+        current.insert(file_name.to_string(), node);
+
+        Ok(())
+    }
+
+    /// Encode the JSON header as the two pickles preceding the data
+    fn encode_header(json: &[u8]) -> Vec<u8> {
+        let padded = json.len().div_ceil(4) * 4;
+        let payload_size = (4 + padded) as u32;
+
+        let mut out = Vec::with_capacity(16 + padded);
+        out.extend_from_slice(&4u32.to_le_bytes());
+        out.extend_from_slice(&(payload_size + 4).to_le_bytes());
+        out.extend_from_slice(&payload_size.to_le_bytes());
+        out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        out.extend_from_slice(json);
+        out.resize(out.len() + padded - json.len(), 0);
+        out
+    }
+
+    /// Write the archive and its unpacked files, counting entries as they
+    /// are written
+    fn write_archive(&self, output_path: &Path, operation: &Operation) -> ArchiverResult<()> {
+        let unpacked_root = unpacked_dir(output_path);
+
+        // Build the header tree and assign offsets
+        let mut root = Map::new();
+        let mut packed = Vec::new();
+        let mut offset = 0u64;
+
+        for entry in &self.entries {
+            operation.check()?;
+            let unpacked = self.is_unpacked(entry);
+
+            let mut node = Map::new();
+            node.insert("size".to_string(), json!(entry.data.len()));
+            if unpacked {
+                node.insert("unpacked".to_string(), json!(true));
+            } else {
+                node.insert("offset".to_string(), json!(offset.to_string()));
+                offset += entry.data.len() as u64;
+                packed.push(entry);
+            }
+            if entry.executable {
+                node.insert("executable".to_string(), json!(true));
+            }
+            if self.integrity {
+                let integrity = serde_json::to_value(AsarIntegrity::compute(&entry.data))
+                    .map_err(|e| ArchiverError::InvalidFormat(e.to_string()))?;
+                node.insert("integrity".to_string(), integrity);
+            }
+
+            Self::insert_node(&mut root, &entry.archive_name, Value::Object(node))?;
+
+            if unpacked {
+                let path = unpacked_root.join(&entry.archive_name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                operation.track_output(path.clone());
+                fs::write(path, &entry.data)?;
+                operation.entry_done(&entry.archive_name, entry.data.len() as u64)?;
+            }
+        }
+
+        let header = json!({ "files": root });
+        let json =
+            serde_json::to_vec(&header).map_err(|e| ArchiverError::InvalidFormat(e.to_string()))?;
+
+        let file = File::create(output_path)?;
+        operation.track_output(output_path.to_path_buf());
+        let mut writer = BufWriter::new(file);
+
+        writer.write_all(&Self::encode_header(&json))?;
+        for entry in packed {
+            writer.write_all(&entry.data)?;
+            operation.entry_done(&entry.archive_name, entry.data.len() as u64)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl ArchiveWriter for AsarWriter {
+    fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            unpacked: HashSet::new(),
+            unpack_extensions: Vec::new(),
+            integrity: true,
+        }
+    }
+
+    fn add_file<P: AsRef<Path>>(&mut self, path: P, archive_name: &str) -> ArchiverResult<()> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Err(ArchiverError::FileNotFound(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+
+        let mut file = File::open(path)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        #[cfg(unix)]
+        let executable = {
+            use std::os::unix::fs::PermissionsExt;
+            file.metadata()?.permissions().mode() & 0o111 != 0
+        };
+        #[cfg(not(unix))]
+        let executable = false;
+
+        // ASAR uses forward slashes for paths
+        let normalized_name = archive_name.replace('\\', "/");
+
+        self.entries.push(PackEntry {
+            archive_name: normalized_name,
+            data,
+            executable,
+        });
+
+        Ok(())
+    }
+
+    fn add_directory<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+    ) -> ArchiverResult<usize> {
+        self.add_directory_with(dir, base_path, &Operation::new())
+    }
+
+    fn add_directory_with<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+        operation: &Operation,
+    ) -> ArchiverResult<usize> {
+        let dir = dir.as_ref();
+
+        if !dir.exists() {
+            return Err(ArchiverError::FileNotFound(
+                dir.to_string_lossy().to_string(),
+            ));
+        }
+
+        // List the files first so progress has totals
+        let mut files = Vec::new();
+        let mut total_bytes = 0;
+        for entry in walkdir::WalkDir::new(dir)
+            .follow_links(true)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+
+            if path.is_file() {
+                // Calculate archive name
+                let relative_path = path.strip_prefix(dir).map_err(|e| {
+                    ArchiverError::InvalidFormat(format!("Failed to get relative path: {}", e))
+                })?;
+
+                let relative_name = relative_path.to_string_lossy().replace('\\', "/");
+                let archive_name = match base_path {
+                    Some(base) => format!("{}/{}", base.trim_end_matches('/'), relative_name),
+                    None => relative_name,
+                };
+
+                total_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                files.push((path.to_path_buf(), archive_name));
+            }
+        }
+
+        operation.begin(files.len(), total_bytes);
+        for (path, archive_name) in &files {
+            self.add_file(path, archive_name)?;
+            let size = self.entries.last().map_or(0, |e| e.data.len() as u64);
+            operation.entry_done(archive_name, size)?;
+        }
+
+        Ok(files.len())
+    }
+
+    fn write<P: AsRef<Path>>(self, output_path: P) -> ArchiverResult<()> {
+        self.write_with(output_path, &Operation::new())
+    }
+
+    fn write_with<P: AsRef<Path>>(
+        self,
+        output_path: P,
+        operation: &Operation,
+    ) -> ArchiverResult<()> {
+        let total_bytes = self.entries.iter().map(|e| e.data.len() as u64).sum();
+        operation.begin(self.entries.len(), total_bytes);
+
+        let result = self.write_archive(output_path.as_ref(), operation);
+        operation.finish(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{is_asar_archive, AsarReader};
+    use super::*;
+    use crate::archiver::ArchiveReader;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pack_directory_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let source_dir = temp_dir.path().join("app");
+        fs::create_dir_all(source_dir.join("www/data")).unwrap();
+        fs::write(
+            source_dir.join("package.json"),
+            r#"{"main":"www/index.html"}"#,
+        )
+        .unwrap();
+        fs::write(source_dir.join("www/index.html"), "<html></html>").unwrap();
+        fs::write(
+            source_dir.join("www/data/Map001.json"),
+            r#"{"displayName":"마을"}"#,
+        )
+        .unwrap();
+        fs::write(source_dir.join("greenworks.node"), b"\x7fELF").unwrap();
+
+        let archive_path = temp_dir.path().join("app.asar");
+        let mut writer = AsarWriter::new().with_unpacked_extensions([".node"]);
+        assert_eq!(writer.add_directory(&source_dir, None).unwrap(), 4);
+        writer.write(&archive_path).unwrap();
+
+        assert!(is_asar_archive(&archive_path));
+        assert!(temp_dir
+            .path()
+            .join("app.asar.unpacked/greenworks.node")
+            .exists());
+
+        let reader = AsarReader::open(&archive_path).unwrap();
+        assert_eq!(reader.entries().len(), 4);
+        assert_eq!(
+            reader.unpacked_names().collect::<Vec<_>>(),
+            vec!["greenworks.node"]
+        );
+        for entry in reader.entries() {
+            assert!(entry.integrity.is_some());
+            assert!(reader.verify_entry(entry).unwrap(), "{}", entry.name);
+        }
+
+        let output_dir = temp_dir.path().join("out");
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 4);
+        for name in [
+            "package.json",
+            "www/index.html",
+            "www/data/Map001.json",
+            "greenworks.node",
+        ] {
+            assert_eq!(
+                fs::read(output_dir.join(name)).unwrap(),
+                fs::read(source_dir.join(name)).unwrap(),
+                "mismatch in {}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_mark_unpacked_without_integrity() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("lib.dll"), b"MZ").unwrap();
+
+        let archive_path = temp_dir.path().join("out").join("app.asar");
+        fs::create_dir_all(archive_path.parent().unwrap()).unwrap();
+
+        let mut writer = AsarWriter::new().with_integrity(false);
+        writer
+            .add_file(temp_dir.path().join("lib.dll"), "bin\\lib.dll")
+            .unwrap();
+        writer.mark_unpacked("bin/lib.dll");
+        writer.write(&archive_path).unwrap();
+
+        let reader = AsarReader::open(&archive_path).unwrap();
+        let entry = &reader.entries()[0];
+        assert_eq!(entry.name, "bin/lib.dll");
+        assert!(entry.unpacked);
+        assert!(entry.integrity.is_none());
+        assert_eq!(reader.extract_to_memory(entry).unwrap(), b"MZ");
+    }
+
+    #[test]
+    fn test_file_directory_conflict() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("a.js");
+        fs::write(&source, "x").unwrap();
+
+        let mut writer = AsarWriter::new();
+        writer.add_file(&source, "lib").unwrap();
+        writer.add_file(&source, "lib/a.js").unwrap();
+
+        let result = writer.write(temp_dir.path().join("app.asar"));
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_header_padding() {
+        let header = AsarWriter::encode_header(b"{\"files\":{}}");
+        assert_eq!(header.len() % 4, 0);
+        assert_eq!(&header[..4], &4u32.to_le_bytes());
+        assert_eq!(
+            u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize,
+            header.len() - 8
+        );
+    }
+
+    #[test]
+    fn test_cancelled_write_removes_output() {
+        use crate::archiver::{ArchiveProgress, CancellationToken};
+
+        let temp_dir = TempDir::new().unwrap();
+        let source_dir = temp_dir.path().join("app");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(source_dir.join("addon.node"), b"\x7fELF").unwrap();
+        for i in 0..3 {
+            fs::write(source_dir.join(format!("file{}.js", i)), b"1;").unwrap();
+        }
+
+        let mut writer = AsarWriter::new().with_unpacked_extensions(["node"]);
+        writer.add_directory(&source_dir, None).unwrap();
+
+        // Cancel once the unpacked file and one packed file are written
+        let token = CancellationToken::new();
+        let sink_token = token.clone();
+        let operation = Operation::new()
+            .with_token(token)
+            .with_sink(move |p: &ArchiveProgress| {
+                if p.entries_done == 2 {
+                    sink_token.cancel();
+                }
+            });
+        let archive_path = temp_dir.path().join("app.asar");
+        assert!(matches!(
+            writer.write_with(&archive_path, &operation),
+            Err(ArchiverError::Cancelled)
+        ));
+        assert!(!archive_path.exists());
+        assert!(!temp_dir
+            .path()
+            .join("app.asar.unpacked/addon.node")
+            .exists());
+    }
+}
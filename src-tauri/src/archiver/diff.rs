@@ -0,0 +1,299 @@
+//! Archive comparison
+//!
+//! [`diff`] compares two archives, or an archive and an unpacked folder,
+//! entry by entry. Entries with different sizes are modified; entries with
+//! the same size are compared by the SHA-256 hash of their contents, so
+//! an update can be checked without extracting either side.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::{normalize_name, Archive, ArchiveFormat, ArchiverError, ArchiverResult, OpenOptions};
+
+/// How an entry differs between the old and new side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveChangeKind {
+    /// Only in the new side
+    Added,
+    /// Only in the old side
+    Removed,
+    /// In both sides with different contents
+    Modified,
+}
+
+/// Entry that differs between the old and new side
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveChange {
+    /// Entry name (`/`-separated)
+    pub name: String,
+    /// Kind of change
+    pub kind: ArchiveChangeKind,
+    /// Size in the old side, if present
+    pub old_size: Option<u64>,
+    /// Size in the new side, if present
+    pub new_size: Option<u64>,
+}
+
+/// Result of comparing two archives or folders
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveDiff {
+    /// Changed entries, sorted by name
+    pub changes: Vec<ArchiveChange>,
+    /// Number of entries identical on both sides
+    pub unchanged: usize,
+}
+
+impl ArchiveDiff {
+    /// Check whether both sides have the same entries and contents
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Get the changes of one kind
+    pub fn of_kind(&self, kind: ArchiveChangeKind) -> impl Iterator<Item = &ArchiveChange> {
+        self.changes.iter().filter(move |c| c.kind == kind)
+    }
+}
+
+/// One side of a comparison
+pub enum DiffSource {
+    /// Archive of any supported format
+    Archive(Box<dyn Archive>),
+    /// Unpacked folder
+    Directory(PathBuf),
+}
+
+impl DiffSource {
+    /// Open a folder as is, or a file as an archive
+    pub fn open<P: AsRef<Path>>(path: P, options: &OpenOptions) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            Ok(DiffSource::Directory(path.to_path_buf()))
+        } else {
+            let archive = ArchiveFormat::open_with(path, options)?;
+            Ok(DiffSource::Archive(archive))
+        }
+    }
+
+    /// List the entries with their sizes, keyed by `/`-separated name
+    fn list(&self) -> ArchiverResult<BTreeMap<String, u64>> {
+        match self {
+            DiffSource::Archive(archive) => Ok(archive
+                .list_entries()
+                .into_iter()
+                .map(|e| (e.name, e.size))
+                .collect()),
+            DiffSource::Directory(dir) => {
+                let mut files = BTreeMap::new();
+                for entry in walkdir::WalkDir::new(dir)
+                    .follow_links(true)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_file())
+                {
+                    let relative = entry.path().strip_prefix(dir).map_err(|e| {
+                        ArchiverError::InvalidFormat(format!("Failed to get relative path: {}", e))
+                    })?;
+                    let name = normalize_name(&relative.to_string_lossy());
+                    files.insert(name, entry.metadata().map_err(io::Error::from)?.len());
+                }
+                Ok(files)
+            }
+        }
+    }
+
+    /// Hash the contents of an entry
+    fn hash(&self, name: &str) -> ArchiverResult<[u8; 32]> {
+        match self {
+            DiffSource::Archive(archive) => hash_reader(archive.open_entry(name)?),
+            DiffSource::Directory(dir) => hash_reader(BufReader::new(File::open(dir.join(name))?)),
+        }
+    }
+}
+
+/// Compare two archives or folders entry by entry
+pub fn diff(old: &DiffSource, new: &DiffSource) -> ArchiverResult<ArchiveDiff> {
+    let old_entries = old.list()?;
+    let new_entries = new.list()?;
+
+    let mut changes = Vec::new();
+    let mut same_size = Vec::new();
+
+    for (name, &old_size) in &old_entries {
+        match new_entries.get(name) {
+            None => changes.push(ArchiveChange {
+                name: name.clone(),
+                kind: ArchiveChangeKind::Removed,
+                old_size: Some(old_size),
+                new_size: None,
+            }),
+            Some(&new_size) if new_size != old_size => changes.push(ArchiveChange {
+                name: name.clone(),
+                kind: ArchiveChangeKind::Modified,
+                old_size: Some(old_size),
+                new_size: Some(new_size),
+            }),
+            Some(_) => same_size.push((name, old_size)),
+        }
+    }
+
+    for (name, &new_size) in &new_entries {
+        if !old_entries.contains_key(name) {
+            changes.push(ArchiveChange {
+                name: name.clone(),
+                kind: ArchiveChangeKind::Added,
+                old_size: None,
+                new_size: Some(new_size),
+            });
+        }
+    }
+
+    // Hashing dominates, so compare the remaining entries in parallel
+    let modified: Vec<ArchiveChange> = same_size
+        .par_iter()
+        .map(|&(name, size)| -> ArchiverResult<Option<ArchiveChange>> {
+            if old.hash(name)? == new.hash(name)? {
+                return Ok(None);
+            }
+            Ok(Some(ArchiveChange {
+                name: name.clone(),
+                kind: ArchiveChangeKind::Modified,
+                old_size: Some(size),
+                new_size: Some(size),
+            }))
+        })
+        .filter_map(Result::transpose)
+        .collect::<ArchiverResult<_>>()?;
+
+    let unchanged = same_size.len() - modified.len();
+    changes.extend(modified);
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(ArchiveDiff { changes, unchanged })
+}
+
+/// Compare two archives or folders given by path
+///
+/// Both archives are opened with the same `options`.
+pub fn diff_paths<P: AsRef<Path>, Q: AsRef<Path>>(
+    old: P,
+    new: Q,
+    options: &OpenOptions,
+) -> ArchiverResult<ArchiveDiff> {
+    diff(
+        &DiffSource::open(old, options)?,
+        &DiffSource::open(new, options)?,
+    )
+}
+
+/// Hash everything a reader yields
+fn hash_reader(mut reader: impl Read) -> ArchiverResult<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archiver::asar::AsarWriter;
+    use crate::archiver::ArchiveWriter;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Pack files into an ASAR archive
+    fn pack(dir: &Path, archive_name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let source_dir = dir.join(format!("{}_source", archive_name));
+        let mut writer = AsarWriter::new();
+        for (name, data) in files {
+            let source = source_dir.join(name);
+            fs::create_dir_all(source.parent().unwrap()).unwrap();
+            fs::write(&source, data).unwrap();
+            writer.add_file(&source, name).unwrap();
+        }
+
+        let path = dir.join(archive_name);
+        writer.write(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_diff_archives() {
+        let temp_dir = TempDir::new().unwrap();
+        let old = pack(
+            temp_dir.path(),
+            "old.asar",
+            &[
+                ("data/Actors.json", b"[1]"),
+                ("data/Map001.json", b"map"),
+                ("data/Map002.json", b"two"),
+                ("data/Old.json", b"old"),
+            ],
+        );
+        let new = pack(
+            temp_dir.path(),
+            "new.asar",
+            &[
+                ("data/Actors.json", b"[1]"),
+                ("data/Map001.json", b"MAP"),
+                ("data/Map002.json", b"two, longer"),
+                ("data/New.json", b"new"),
+            ],
+        );
+
+        let diff = diff_paths(&old, &new, &OpenOptions::default()).unwrap();
+        assert_eq!(diff.unchanged, 1);
+
+        let names = |kind| {
+            diff.of_kind(kind)
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(ArchiveChangeKind::Added), vec!["data/New.json"]);
+        assert_eq!(names(ArchiveChangeKind::Removed), vec!["data/Old.json"]);
+        assert_eq!(
+            names(ArchiveChangeKind::Modified),
+            vec!["data/Map001.json", "data/Map002.json"]
+        );
+
+        assert!(diff_paths(&old, &old, &OpenOptions::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_diff_archive_against_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = pack(
+            temp_dir.path(),
+            "app.asar",
+            &[("data/Actors.json", b"[1]"), ("data/Map001.json", b"map")],
+        );
+
+        let unpacked = temp_dir.path().join("unpacked");
+        fs::create_dir_all(unpacked.join("data")).unwrap();
+        fs::write(unpacked.join("data/Actors.json"), b"[1]").unwrap();
+        fs::write(unpacked.join("data/Map001.json"), b"MAP").unwrap();
+
+        let diff = diff_paths(&archive, &unpacked, &OpenOptions::default()).unwrap();
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(
+            diff.changes,
+            vec![ArchiveChange {
+                name: "data/Map001.json".to_string(),
+                kind: ArchiveChangeKind::Modified,
+                old_size: Some(3),
+                new_size: Some(3),
+            }]
+        );
+    }
+}
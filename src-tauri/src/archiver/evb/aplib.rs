@@ -0,0 +1,309 @@
+//! aPLib decompression for compressed EVB files
+
+use crate::archiver::{ArchiverError, ArchiverResult};
+
+/// Most output bytes reserved up front per compressed byte
+///
+/// Unpacked sizes come from the archive, so a corrupt one must not make
+/// the reader allocate gigabytes before any data is checked.
+const PREALLOCATION_RATIO: usize = 8;
+
+/// Get the capacity to reserve for `size` bytes unpacked from `packed_len`
+pub(super) fn initial_capacity(size: usize, packed_len: usize) -> usize {
+    size.min(packed_len.saturating_mul(PREALLOCATION_RATIO))
+}
+
+/// Bit and byte reader over a compressed block
+struct BitReader<'a> {
+    /// Compressed data
+    src: &'a [u8],
+    /// Position of the next byte in `src`
+    pos: usize,
+    /// Current tag byte, consumed from the most significant bit
+    tag: u8,
+    /// Bits left in `tag`
+    bits: u8,
+}
+
+impl BitReader<'_> {
+    fn byte(&mut self) -> ArchiverResult<u8> {
+        let byte = *self.src.get(self.pos).ok_or_else(truncated)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn bit(&mut self) -> ArchiverResult<usize> {
+        if self.bits == 0 {
+            self.tag = self.byte()?;
+            self.bits = 8;
+        }
+        self.bits -= 1;
+        let bit = (self.tag >> 7) as usize;
+        self.tag <<= 1;
+        Ok(bit)
+    }
+
+    /// Read an Elias gamma-like number (at least 2)
+    fn gamma(&mut self) -> ArchiverResult<usize> {
+        let mut value = 1usize;
+        loop {
+            value = value
+                .checked_mul(2)
+                .ok_or_else(|| corrupt("gamma value overflow"))?
+                + self.bit()?;
+            if self.bit()? == 0 {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+/// Decompress an aPLib block of known unpacked size
+pub(super) fn decompress(src: &[u8], size: usize) -> ArchiverResult<Vec<u8>> {
+    let mut out = Vec::with_capacity(initial_capacity(size, src.len()));
+    if size == 0 {
+        return Ok(out);
+    }
+
+    let mut reader = BitReader {
+        src,
+        pos: 0,
+        tag: 0,
+        bits: 0,
+    };
+    // Offset of the previous match, reused by the "repeat" code
+    let mut last_offset = 0usize;
+    // Whether the previous token was a match, which changes offset coding
+    let mut after_match = false;
+
+    out.push(reader.byte()?);
+
+    loop {
+        if reader.bit()? == 0 {
+            // 0: literal byte
+            out.push(reader.byte()?);
+            after_match = false;
+        } else if reader.bit()? == 0 {
+            // 10: match with a gamma-coded offset
+            let high = reader.gamma()?;
+            if !after_match && high == 2 {
+                let len = reader.gamma()?;
+                copy_match(&mut out, last_offset, len, size)?;
+            } else {
+                let high = high - if after_match { 2 } else { 3 };
+                let offset = (high << 8) + reader.byte()? as usize;
+                let mut len = reader.gamma()?;
+                if offset >= 32000 {
+                    len += 1;
+                }
+                if offset >= 1280 {
+                    len += 1;
+                }
+                if offset < 128 {
+                    len += 2;
+                }
+                copy_match(&mut out, offset, len, size)?;
+                last_offset = offset;
+            }
+            after_match = true;
+        } else if reader.bit()? == 0 {
+            // 110: short match, or the end of the block
+            let byte = reader.byte()? as usize;
+            let offset = byte >> 1;
+            if offset == 0 {
+                break;
+            }
+            copy_match(&mut out, offset, 2 + (byte & 1), size)?;
+            last_offset = offset;
+            after_match = true;
+        } else {
+            // 111: single byte from a 4-bit offset, or a zero byte
+            let mut offset = 0;
+            for _ in 0..4 {
+                offset = (offset << 1) + reader.bit()?;
+            }
+            if offset == 0 {
+                out.push(0);
+            } else {
+                copy_match(&mut out, offset, 1, size)?;
+            }
+            after_match = false;
+        }
+
+        if out.len() > size {
+            return Err(corrupt("block is larger than its stated size"));
+        }
+    }
+
+    if out.len() != size {
+        return Err(corrupt("block is smaller than its stated size"));
+    }
+    Ok(out)
+}
+
+/// Copy `len` bytes from `offset` bytes back, possibly overlapping
+fn copy_match(out: &mut Vec<u8>, offset: usize, len: usize, size: usize) -> ArchiverResult<()> {
+    if offset == 0 || offset > out.len() {
+        return Err(corrupt("match offset before the start of the block"));
+    }
+    if out.len() + len > size {
+        return Err(corrupt("block is larger than its stated size"));
+    }
+
+    let start = out.len() - offset;
+    for i in 0..len {
+        out.push(out[start + i]);
+    }
+    Ok(())
+}
+
+fn truncated() -> ArchiverError {
+    corrupt("unexpected end of block")
+}
+
+fn corrupt(reason: &str) -> ArchiverError {
+    ArchiverError::InvalidFormat(format!("Corrupt aPLib data: {}", reason))
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Token of a hand-built aPLib stream
+    pub(in crate::archiver::evb) enum Token {
+        Literal(u8),
+        /// Match with a gamma-coded offset
+        Match(usize, usize),
+        /// Short match (offset 1..=127, length 2 or 3)
+        Short(usize, usize),
+        /// Single byte from a 4-bit offset (0 for a zero byte)
+        Nibble(usize),
+    }
+
+    /// Minimal aPLib encoder for building test blocks
+    #[derive(Default)]
+    struct BitWriter {
+        out: Vec<u8>,
+        tag_pos: usize,
+        bits: u8,
+    }
+
+    impl BitWriter {
+        fn bit(&mut self, bit: usize) {
+            if self.bits == 0 {
+                self.tag_pos = self.out.len();
+                self.out.push(0);
+                self.bits = 8;
+            }
+            self.bits -= 1;
+            self.out[self.tag_pos] |= (bit as u8) << self.bits;
+        }
+
+        fn gamma(&mut self, value: usize) {
+            let width = usize::BITS - value.leading_zeros();
+            for i in (0..width - 1).rev() {
+                self.bit((value >> i) & 1);
+                self.bit(usize::from(i > 0));
+            }
+        }
+    }
+
+    /// Encode a first byte and a list of tokens into an aPLib block
+    pub(in crate::archiver::evb) fn encode(first: u8, tokens: &[Token]) -> Vec<u8> {
+        let mut writer = BitWriter {
+            out: vec![first],
+            ..Default::default()
+        };
+        let mut after_match = false;
+
+        for token in tokens {
+            match *token {
+                Token::Literal(byte) => {
+                    writer.bit(0);
+                    writer.out.push(byte);
+                    after_match = false;
+                }
+                Token::Match(offset, len) => {
+                    writer.bit(1);
+                    writer.bit(0);
+                    writer.gamma((offset >> 8) + if after_match { 2 } else { 3 });
+                    writer.out.push(offset as u8);
+                    let extra = usize::from(offset >= 32000)
+                        + usize::from(offset >= 1280)
+                        + if offset < 128 { 2 } else { 0 };
+                    writer.gamma(len - extra);
+                    after_match = true;
+                }
+                Token::Short(offset, len) => {
+                    writer.bit(1);
+                    writer.bit(1);
+                    writer.bit(0);
+                    writer.out.push((offset << 1 | (len - 2)) as u8);
+                    after_match = true;
+                }
+                Token::Nibble(offset) => {
+                    for _ in 0..3 {
+                        writer.bit(1);
+                    }
+                    for i in (0..4).rev() {
+                        writer.bit((offset >> i) & 1);
+                    }
+                    after_match = false;
+                }
+            }
+        }
+
+        writer.bit(1);
+        writer.bit(1);
+        writer.bit(0);
+        writer.out.push(0);
+        writer.out
+    }
+
+    #[test]
+    fn test_decompress_tokens() {
+        let block = encode(
+            b'a',
+            &[
+                Token::Literal(b'b'),
+                Token::Literal(b'c'),
+                Token::Match(3, 9),
+                Token::Short(2, 3),
+                Token::Nibble(4),
+                Token::Nibble(0),
+            ],
+        );
+        assert_eq!(decompress(&block, 17).unwrap(), b"abcabcabcabcbcbc\0");
+    }
+
+    #[test]
+    fn test_decompress_long_offset() {
+        let mut tokens: Vec<Token> = (1..=299u16).map(|i| Token::Literal(i as u8)).collect();
+        tokens.push(Token::Match(300, 5));
+        let block = encode(0, &tokens);
+
+        let data = decompress(&block, 305).unwrap();
+        assert_eq!(&data[300..], &[0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_decompress_corrupt() {
+        let block = encode(b'a', &[Token::Short(5, 2)]);
+        assert!(decompress(&block, 3).is_err());
+
+        let block = encode(b'a', &[Token::Literal(b'b')]);
+        assert!(decompress(&block, 3).is_err());
+        assert!(decompress(&block, 1).is_err());
+        assert!(decompress(&block[..2], 2).is_err());
+    }
+
+    #[test]
+    fn test_huge_size_is_not_preallocated() {
+        assert_eq!(initial_capacity(100, 20), 100);
+        assert_eq!(initial_capacity(u32::MAX as usize, 16), 128);
+
+        // Fails on the data instead of reserving 4 GiB first
+        let block = encode(b'a', &[Token::Literal(b'b')]);
+        assert!(decompress(&block, u32::MAX as usize).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! Enigma Virtual Box module
+//!
+//! Enigma Virtual Box (EVB) packs a game folder into its executable, so a
+//! VX Ace or MV game may ship as a lone `Game.exe`. The virtual files are
+//! stored in the PE file after the loader's own sections, usually in a
+//! section named `.enigma1`/`.enigma2` or appended to the end of the file:
+//!
+//! ```text
+//! Header  "EVB\0", 60 reserved bytes
+//! Node    u32 size     length of the rest of the node record
+//!         u64 reserved
+//!         u32 children number of child nodes following the record
+//!         name         UTF-16LE, NUL-terminated (not on the root node)
+//!         u8  type     2 = file, 3 = folder (not on the root node)
+//!         file only:   u16 reserved, u32 size, u32 reserved,
+//!                      3 × u64 file times, u32 stored size, ...
+//! Data    file contents, in the same order as the file nodes
+//! ```
+//!
+//! Nodes are stored depth-first. Files whose stored size differs from their
+//! size are compressed: the stored data is a sequence of aPLib blocks, each
+//! preceded by its packed and unpacked sizes (`u32` each).
+//!
+//! Files of the application folder live under `%DEFAULT FOLDER%`, which is
+//! mapped to the root of the extracted tree. Other special folders (such as
+//! `%SYSTEM FOLDER%`) keep their name as the first path component.
+
+mod aplib;
+mod reader;
+
+pub use reader::EvbReader;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::archiver::safe_output_path;
+
+/// Magic at the start of the embedded filesystem
+pub const MAGIC: &[u8; 4] = b"EVB\0";
+
+/// Root folder of the packed application
+pub const DEFAULT_FOLDER: &str = "%DEFAULT FOLDER%";
+
+/// File entry in an EVB package
+#[derive(Debug, Clone)]
+pub struct EvbEntry {
+    /// File name (relative path within the package, `/`-separated)
+    pub name: String,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// Size of the stored data in bytes
+    pub stored_size: u64,
+    /// Whether the stored data is aPLib-compressed
+    pub compressed: bool,
+    /// Offset of the stored data in the executable
+    pub offset: u64,
+}
+
+impl EvbEntry {
+    /// Get the output path for extraction
+    pub fn output_path(&self, base_dir: &Path) -> PathBuf {
+        safe_output_path(base_dir, &self.name)
+    }
+}
+
+/// Check whether a file is an executable packed with Enigma Virtual Box
+pub fn is_evb_package<P: AsRef<Path>>(path: P) -> bool {
+    fs::File::open(path)
+        .ok()
+        .and_then(|mut file| reader::locate(&mut file).ok())
+        .flatten()
+        .is_some()
+}
+
+/// Find the EVB-packed executable of a game folder
+pub fn find_package<P: AsRef<Path>>(game_dir: P) -> Option<PathBuf> {
+    let mut executables: Vec<PathBuf> = fs::read_dir(game_dir.as_ref())
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| e.eq_ignore_ascii_case("exe"))
+        })
+        .collect();
+    executables.sort();
+
+    executables.into_iter().find(|p| is_evb_package(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// File or folder of a fixture package
+    pub(super) enum Node<'a> {
+        File(&'a str, &'a [u8]),
+        /// Compressed file: name, size and (unpacked size, aPLib data) blocks
+        Packed(&'a str, usize, Vec<(usize, Vec<u8>)>),
+        Folder(&'a str, Vec<Node<'a>>),
+    }
+
+    fn push_record(out: &mut Vec<u8>, children: u32, named: Option<(&str, u8, &[u8])>) {
+        let mut record = vec![0u8; 8];
+        record.extend_from_slice(&children.to_le_bytes());
+        if let Some((name, kind, extra)) = named {
+            for unit in name.encode_utf16() {
+                record.extend_from_slice(&unit.to_le_bytes());
+            }
+            record.extend_from_slice(&[0, 0, kind]);
+            record.extend_from_slice(extra);
+        }
+        out.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        out.extend_from_slice(&record);
+    }
+
+    fn push_node(tree: &mut Vec<u8>, data: &mut Vec<u8>, node: &Node) {
+        let (name, size, stored) = match node {
+            Node::Folder(name, children) => {
+                push_record(tree, children.len() as u32, Some((name, 3, &[0; 25])));
+                for child in children {
+                    push_node(tree, data, child);
+                }
+                return;
+            }
+            Node::File(name, contents) => (name, contents.len(), contents.to_vec()),
+            Node::Packed(name, size, blocks) => {
+                let mut stored = Vec::new();
+                for (unpacked, block) in blocks {
+                    stored.extend_from_slice(&(block.len() as u32).to_le_bytes());
+                    stored.extend_from_slice(&(*unpacked as u32).to_le_bytes());
+                    stored.extend_from_slice(block);
+                }
+                (name, *size, stored)
+            }
+        };
+
+        let mut extra = vec![0u8; 2];
+        extra.extend_from_slice(&(size as u32).to_le_bytes());
+        extra.extend_from_slice(&[0; 28]);
+        extra.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+        extra.extend_from_slice(&[0; 8]);
+        push_record(tree, 0, Some((name, 2, &extra)));
+        data.extend_from_slice(&stored);
+    }
+
+    /// Build an EVB filesystem image
+    pub(super) fn build_filesystem(roots: &[Node]) -> Vec<u8> {
+        let mut tree = MAGIC.to_vec();
+        tree.extend_from_slice(&[0; 60]);
+        push_record(&mut tree, roots.len() as u32, None);
+
+        let mut data = Vec::new();
+        for node in roots {
+            push_node(&mut tree, &mut data, node);
+        }
+        tree.extend(data);
+        tree
+    }
+
+    /// Build a minimal PE executable with the filesystem in `.enigma1`
+    ///
+    /// The section starts with a stray magic that must not be mistaken for
+    /// the filesystem.
+    pub(super) fn build_executable(filesystem: &[u8]) -> Vec<u8> {
+        let mut section = MAGIC.to_vec();
+        section.extend_from_slice(&[0xFF; 12]);
+        section.extend_from_slice(filesystem);
+
+        let mut exe = vec![0u8; 0x200];
+        exe[0..2].copy_from_slice(b"MZ");
+        exe[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        exe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        exe[0x46..0x48].copy_from_slice(&2u16.to_le_bytes());
+
+        let sections = [
+            (&b".text\0\0\0"[..], 0x200u32, 0x200u32),
+            (&b".enigma1"[..], 0x400, section.len() as u32),
+        ];
+        for (i, (name, offset, size)) in sections.iter().enumerate() {
+            let header = 0x58 + i * 40;
+            exe[header..header + 8].copy_from_slice(name);
+            exe[header + 16..header + 20].copy_from_slice(&size.to_le_bytes());
+            exe[header + 20..header + 24].copy_from_slice(&offset.to_le_bytes());
+        }
+
+        exe.extend_from_slice(&[0x90; 0x200]);
+        exe.extend(section);
+        exe
+    }
+
+    #[test]
+    fn test_find_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let game_dir = temp_dir.path();
+
+        fs::write(game_dir.join("Launcher.exe"), build_executable(b"")).unwrap();
+        assert!(find_package(game_dir).is_none());
+
+        let filesystem = build_filesystem(&[Node::Folder(
+            DEFAULT_FOLDER,
+            vec![Node::File("Game.ini", b"[Game]")],
+        )]);
+        fs::write(game_dir.join("Game.exe"), build_executable(&filesystem)).unwrap();
+
+        assert_eq!(find_package(game_dir), Some(game_dir.join("Game.exe")));
+        assert!(!is_evb_package(game_dir.join("Launcher.exe")));
+    }
+}
@@ -0,0 +1,583 @@
+//! Enigma Virtual Box Package Reader (Unpacker)
+
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::{aplib, EvbEntry, DEFAULT_FOLDER, MAGIC};
+use crate::archiver::archive::{check_bounds, find_entry};
+use crate::archiver::{
+    Archive, ArchiveEntryInfo, ArchiveFormat, ArchiveReader, ArchiverError, ArchiverResult,
+};
+
+/// Size of the filesystem header
+const HEADER_SIZE: u64 = 64;
+
+/// Largest node record accepted
+const MAX_RECORD_SIZE: u32 = 0x1_0000;
+
+/// Node type of a file
+const NODE_FILE: u8 = 2;
+
+/// Node type of a folder
+const NODE_FOLDER: u8 = 3;
+
+/// Chunk size used when scanning for the magic
+const SCAN_CHUNK_SIZE: usize = 1 << 20;
+
+/// Enigma Virtual Box Package Reader for packed executables
+pub struct EvbReader {
+    /// Path to the executable
+    path: PathBuf,
+    /// Offset of the filesystem header in the executable
+    header_offset: u64,
+    /// File entries in the package
+    entries: Vec<EvbEntry>,
+}
+
+impl EvbReader {
+    /// Extract a single entry to a byte vector
+    pub fn extract_to_memory(&self, entry: &EvbEntry) -> ArchiverResult<Vec<u8>> {
+        let mut stored = Vec::with_capacity(entry.stored_size as usize);
+        self.stored_reader(entry)?.read_to_end(&mut stored)?;
+
+        if !entry.compressed {
+            return Ok(stored);
+        }
+        decompress_blocks(&entry.name, &stored, entry.size)
+    }
+
+    /// Open a streaming reader over an entry
+    ///
+    /// Stored files are read straight from the executable; compressed
+    /// files are decompressed into memory first.
+    pub fn entry_reader(&self, entry: &EvbEntry) -> ArchiverResult<Box<dyn Read + Send>> {
+        if entry.compressed {
+            return Ok(Box::new(Cursor::new(self.extract_to_memory(entry)?)));
+        }
+        Ok(Box::new(self.stored_reader(entry)?))
+    }
+
+    /// Open a reader over the stored data of an entry
+    fn stored_reader(&self, entry: &EvbEntry) -> ArchiverResult<std::io::Take<BufReader<File>>> {
+        let file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+        check_bounds(&entry.name, entry.offset, entry.stored_size, file_len)?;
+
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        Ok(reader.take(entry.stored_size))
+    }
+
+    /// Get the executable path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Offset of the filesystem header in the executable
+    pub fn header_offset(&self) -> u64 {
+        self.header_offset
+    }
+}
+
+impl ArchiveReader for EvbReader {
+    type Entry = EvbEntry;
+
+    fn open<P: AsRef<Path>>(path: P) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+
+        let (header_offset, entries) = locate(&mut file)?.ok_or_else(|| {
+            ArchiverError::InvalidFormat(format!(
+                "No Enigma Virtual Box filesystem found: {}",
+                path.display()
+            ))
+        })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            header_offset,
+            entries,
+        })
+    }
+
+    fn entries(&self) -> &[EvbEntry] {
+        &self.entries
+    }
+
+    fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> ArchiverResult<usize> {
+        let output_dir = output_dir.as_ref();
+        let mut count = 0;
+
+        for entry in &self.entries {
+            let output_path = entry.output_path(output_dir);
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut output = File::create(&output_path)?;
+            std::io::copy(&mut self.entry_reader(entry)?, &mut output)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn extract_entry<P: AsRef<Path>>(&self, entry_name: &str, output_dir: P) -> ArchiverResult<()> {
+        let output_dir = output_dir.as_ref();
+
+        // Find the entry
+        let entry = find_entry(&self.entries, entry_name, |e| &e.name)?;
+
+        // Extract to memory
+        let data = self.extract_to_memory(entry)?;
+
+        // Write to file
+        let output_path = entry.output_path(output_dir);
+
+        // Create parent directories
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&output_path, data)?;
+
+        Ok(())
+    }
+}
+
+impl Archive for EvbReader {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Evb
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn list_entries(&self) -> Vec<ArchiveEntryInfo> {
+        self.entries
+            .iter()
+            .map(|e| ArchiveEntryInfo {
+                name: e.name.clone(),
+                size: e.size,
+                compressed: e.compressed,
+            })
+            .collect()
+    }
+
+    fn open_entry(&self, name: &str) -> ArchiverResult<Box<dyn Read + Send + '_>> {
+        let entry = find_entry(&self.entries, name, |e| &e.name)?;
+        self.entry_reader(entry)
+    }
+}
+
+/// Find and parse the EVB filesystem of a PE executable
+///
+/// Scanning starts at the `.enigma*` sections, or at the last section if
+/// there are none, and runs to the end of the file so appended data is
+/// found too. Every magic found is tried until one parses, since the
+/// loader code may contain the magic as well. Returns `None` for
+/// executables without a filesystem, and an error for non-PE files.
+pub(super) fn locate(file: &mut File) -> ArchiverResult<Option<(u64, Vec<EvbEntry>)>> {
+    let file_len = file.metadata()?.len();
+    let mut offset = scan_start(file)?;
+    let mut chunk = vec![0u8; SCAN_CHUNK_SIZE];
+
+    while offset < file_len {
+        file.seek(SeekFrom::Start(offset))?;
+        let len = read_full(file, &mut chunk)?;
+        if len < MAGIC.len() {
+            break;
+        }
+
+        let chunk_start = offset;
+        for pos in 0..=len - MAGIC.len() {
+            if &chunk[pos..pos + MAGIC.len()] != MAGIC {
+                continue;
+            }
+
+            let header_offset = chunk_start + pos as u64;
+            file.seek(SeekFrom::Start(header_offset))?;
+            if let Ok(entries) =
+                parse_filesystem(&mut BufReader::new(&mut *file), header_offset, file_len)
+            {
+                return Ok(Some((header_offset, entries)));
+            }
+        }
+
+        // Overlap chunks so a magic across the boundary is not missed
+        offset = chunk_start + (len - (MAGIC.len() - 1)) as u64;
+    }
+
+    Ok(None)
+}
+
+/// Find where the filesystem may start from the PE section table
+fn scan_start(file: &mut File) -> ArchiverResult<u64> {
+    let not_pe = || ArchiverError::InvalidFormat("Not a PE executable".to_string());
+
+    let mut dos_header = [0u8; 64];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut dos_header).map_err(|_| not_pe())?;
+    if &dos_header[0..2] != b"MZ" {
+        return Err(not_pe());
+    }
+
+    let pe_offset = u32_at(&dos_header, 0x3C) as u64;
+    let mut pe_header = [0u8; 24];
+    file.seek(SeekFrom::Start(pe_offset))?;
+    file.read_exact(&mut pe_header).map_err(|_| not_pe())?;
+    if &pe_header[0..4] != b"PE\0\0" {
+        return Err(not_pe());
+    }
+
+    let section_count = u16::from_le_bytes([pe_header[6], pe_header[7]]) as usize;
+    let optional_size = u16::from_le_bytes([pe_header[20], pe_header[21]]) as u64;
+    let table_offset = pe_offset + 24 + optional_size;
+
+    let mut table = vec![0u8; section_count * 40];
+    file.seek(SeekFrom::Start(table_offset))?;
+    file.read_exact(&mut table).map_err(|_| not_pe())?;
+
+    let mut enigma_start = None::<u64>;
+    let mut last_start = table_offset + table.len() as u64;
+    for section in table.chunks_exact(40) {
+        let raw_offset = u32_at(section, 20) as u64;
+        if section.starts_with(b".enigma") {
+            enigma_start = Some(enigma_start.map_or(raw_offset, |s| s.min(raw_offset)));
+        }
+        last_start = last_start.max(raw_offset);
+    }
+
+    Ok(enigma_start.unwrap_or(last_start))
+}
+
+/// Parse the node tree following a magic at `header_offset`
+fn parse_filesystem<R: Read>(
+    reader: &mut R,
+    header_offset: u64,
+    file_len: u64,
+) -> ArchiverResult<Vec<EvbEntry>> {
+    let mut header = [0u8; HEADER_SIZE as usize];
+    reader.read_exact(&mut header)?;
+    let mut position = header_offset + HEADER_SIZE;
+
+    let root = read_record(reader, &mut position)?;
+    let root_children = u32_at(&root, 8);
+
+    // Folders still being filled: (path, children left)
+    let mut stack: Vec<(String, u32)> = vec![(String::new(), root_children)];
+    // Files in tree order: (name, size, stored size)
+    let mut files = Vec::new();
+
+    while let Some((folder, children_left)) = stack.last_mut() {
+        if *children_left == 0 {
+            stack.pop();
+            continue;
+        }
+        *children_left -= 1;
+        let folder = folder.clone();
+
+        let record = read_record(reader, &mut position)?;
+        let children = u32_at(&record, 8);
+        let (name, kind_offset) = read_name(&record)?;
+        let path = child_path(&folder, &name, stack.len() == 1)?;
+
+        match record.get(kind_offset) {
+            Some(&NODE_FOLDER) => stack.push((path, children)),
+            Some(&NODE_FILE) => {
+                let data = &record[kind_offset + 1..];
+                if children != 0 || data.len() < 38 {
+                    return Err(invalid_node(&path));
+                }
+                files.push((path, u32_at(data, 2) as u64, u32_at(data, 34) as u64));
+            }
+            _ => return Err(invalid_node(&path)),
+        }
+    }
+
+    // File data follows the tree in the same order
+    let mut entries = Vec::with_capacity(files.len());
+    for (name, size, stored_size) in files {
+        check_bounds(&name, position, stored_size, file_len)?;
+        entries.push(EvbEntry {
+            name,
+            size,
+            stored_size,
+            compressed: stored_size != size,
+            offset: position,
+        });
+        position += stored_size;
+    }
+
+    Ok(entries)
+}
+
+/// Read one node record, without its size prefix
+fn read_record<R: Read>(reader: &mut R, position: &mut u64) -> ArchiverResult<Vec<u8>> {
+    let mut size = [0u8; 4];
+    reader.read_exact(&mut size)?;
+    let size = u32::from_le_bytes(size);
+    if !(12..=MAX_RECORD_SIZE).contains(&size) {
+        return Err(ArchiverError::InvalidFormat(format!(
+            "Invalid EVB node size: {}",
+            size
+        )));
+    }
+
+    let mut record = vec![0u8; size as usize];
+    reader.read_exact(&mut record)?;
+    *position += 4 + size as u64;
+    Ok(record)
+}
+
+/// Read the NUL-terminated UTF-16 name of a record
+///
+/// Returns the name and the offset of the node type following it.
+fn read_name(record: &[u8]) -> ArchiverResult<(String, usize)> {
+    let mut units = Vec::new();
+    let mut pos = 12;
+    loop {
+        let unit = record
+            .get(pos..pos + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .ok_or_else(|| {
+                ArchiverError::InvalidFormat("Unterminated EVB node name".to_string())
+            })?;
+        pos += 2;
+        if unit == 0 {
+            break;
+        }
+        units.push(unit);
+    }
+
+    let name = String::from_utf16(&units)
+        .map_err(|_| ArchiverError::InvalidFormat("Invalid EVB node name".to_string()))?;
+    Ok((name, pos))
+}
+
+/// Join a node name to its folder path
+///
+/// `%DEFAULT FOLDER%` at the top level maps to the root.
+fn child_path(folder: &str, name: &str, top_level: bool) -> ArchiverResult<String> {
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(ArchiverError::InvalidFormat(format!(
+            "Invalid EVB node name: {}",
+            name
+        )));
+    }
+
+    Ok(if top_level && name.eq_ignore_ascii_case(DEFAULT_FOLDER) {
+        String::new()
+    } else if folder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", folder, name)
+    })
+}
+
+/// Decompress the aPLib blocks of a compressed entry
+fn decompress_blocks(name: &str, mut stored: &[u8], size: u64) -> ArchiverResult<Vec<u8>> {
+    let size_hint = usize::try_from(size).unwrap_or(usize::MAX);
+    let mut data = Vec::with_capacity(aplib::initial_capacity(size_hint, stored.len()));
+
+    while (data.len() as u64) < size {
+        if stored.len() < 8 {
+            return Err(ArchiverError::InvalidFormat(format!(
+                "Truncated compressed data: {}",
+                name
+            )));
+        }
+        let packed_len = u32_at(stored, 0) as usize;
+        let unpacked_len = u32_at(stored, 4) as usize;
+        let block = stored.get(8..8 + packed_len).ok_or_else(|| {
+            ArchiverError::InvalidFormat(format!("Truncated compressed data: {}", name))
+        })?;
+
+        if data.len() as u64 + unpacked_len as u64 > size {
+            return Err(ArchiverError::InvalidFormat(format!(
+                "Compressed data larger than the file: {}",
+                name
+            )));
+        }
+        data.extend(aplib::decompress(block, unpacked_len)?);
+        stored = &stored[8 + packed_len..];
+    }
+
+    Ok(data)
+}
+
+/// Read into `buf` until it is full or the input ends
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> ArchiverResult<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn invalid_node(name: &str) -> ArchiverError {
+    ArchiverError::InvalidFormat(format!("Invalid EVB node: {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::aplib::tests::{encode, Token};
+    use super::super::tests::{build_executable, build_filesystem, Node};
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_package(dir: &Path, roots: &[Node]) -> PathBuf {
+        let path = dir.join("Game.exe");
+        fs::write(&path, build_executable(&build_filesystem(roots))).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let packed = encode(
+            b'x',
+            &[
+                Token::Literal(b'y'),
+                Token::Match(2, 10),
+                Token::Literal(b'!'),
+            ],
+        );
+        let path = write_package(
+            temp_dir.path(),
+            &[
+                Node::Folder(
+                    DEFAULT_FOLDER,
+                    vec![
+                        Node::File("Game.rgss3a", b"RGSSAD\0\x03"),
+                        Node::Folder(
+                            "Data",
+                            vec![
+                                Node::File("Scripts.rvdata2", b"\x04\x08["),
+                                Node::Packed("Map001.rvdata2", 13, vec![(13, packed)]),
+                            ],
+                        ),
+                        Node::File("Game.ini", b"[Game]"),
+                    ],
+                ),
+                Node::Folder("%SYSTEM FOLDER%", vec![Node::File("d3dx9.dll", b"MZ")]),
+            ],
+        );
+
+        let reader = EvbReader::open(&path).unwrap();
+        let names: Vec<&str> = reader.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Game.rgss3a",
+                "Data/Scripts.rvdata2",
+                "Data/Map001.rvdata2",
+                "Game.ini",
+                "%SYSTEM FOLDER%/d3dx9.dll",
+            ]
+        );
+        assert!(reader.entries()[2].compressed);
+        assert!(!reader.entries()[1].compressed);
+
+        assert_eq!(
+            reader.read_entry("Data\\Map001.rvdata2").unwrap(),
+            b"xyxyxyxyxyxy!"
+        );
+        assert_eq!(reader.read_entry("Game.ini").unwrap(), b"[Game]");
+
+        let output_dir = temp_dir.path().join("out");
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 5);
+        assert_eq!(
+            fs::read(output_dir.join("Data/Scripts.rvdata2")).unwrap(),
+            b"\x04\x08["
+        );
+        assert_eq!(
+            fs::read(output_dir.join("Game.rgss3a")).unwrap(),
+            b"RGSSAD\0\x03"
+        );
+    }
+
+    #[test]
+    fn test_read_multi_block() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = encode(b'a', &[Token::Short(1, 3)]);
+        let second = encode(b'b', &[Token::Nibble(0)]);
+        let path = write_package(
+            temp_dir.path(),
+            &[Node::Folder(
+                DEFAULT_FOLDER,
+                vec![Node::Packed(
+                    "System.json",
+                    6,
+                    vec![(4, first), (2, second)],
+                )],
+            )],
+        );
+
+        let reader = EvbReader::open(&path).unwrap();
+        assert_eq!(reader.read_entry("System.json").unwrap(), b"aaaab\0");
+    }
+
+    #[test]
+    fn test_read_truncated() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = write_package(
+            temp_dir.path(),
+            &[Node::Folder(
+                DEFAULT_FOLDER,
+                vec![Node::File("Game.rgss3a", &[0; 64])],
+            )],
+        );
+
+        let mut data = fs::read(&path).unwrap();
+        data.truncate(data.len() - 10);
+        fs::write(&path, data).unwrap();
+        assert!(matches!(
+            EvbReader::open(&path),
+            Err(ArchiverError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_separator_in_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = write_package(
+            temp_dir.path(),
+            &[Node::Folder(
+                DEFAULT_FOLDER,
+                vec![Node::File("..\\Game.ini", b"[Game]")],
+            )],
+        );
+
+        assert!(matches!(
+            EvbReader::open(&path),
+            Err(ArchiverError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_not_pe() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Game.exe");
+        fs::write(&path, b"EVB\0 not an executable").unwrap();
+
+        assert!(matches!(
+            EvbReader::open(&path),
+            Err(ArchiverError::InvalidFormat(_))
+        ));
+    }
+}
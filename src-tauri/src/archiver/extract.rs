@@ -0,0 +1,225 @@
+//! Selective extraction
+//!
+//! [`ExtractOptions`] narrows an extraction down to the entries matching a
+//! set of include/exclude globs, and decides what happens to files that
+//! already exist in the output directory.
+//! [`Archive::plan_extract`](super::Archive::plan_extract) runs the same
+//! selection without writing anything, as a dry run.
+//!
+//! Globs are matched case-insensitively against `/`-separated entry names.
+//! `*` stays within one directory and `**` crosses directories, so
+//! `Data/**` selects everything under `Data` and `Graphics/Pictures/*` only
+//! the files directly inside it.
+
+use std::path::Path;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{normalize_name, safe_output_path, ArchiveEntryInfo, ArchiverError, ArchiverResult};
+
+/// What to do with files that already exist in the output directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    /// Replace existing files
+    #[default]
+    Overwrite,
+    /// Leave existing files untouched
+    SkipExisting,
+}
+
+/// Options for extracting part of an archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExtractOptions {
+    /// Globs of entries to extract (all entries if empty)
+    pub include: Vec<String>,
+    /// Globs of entries to leave out, applied after `include`
+    pub exclude: Vec<String>,
+    /// What to do with files that already exist
+    pub overwrite: OverwritePolicy,
+}
+
+impl ExtractOptions {
+    /// Select only the entries matching the given globs
+    pub fn include<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Leave out the entries matching the given globs
+    pub fn exclude<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set what to do with files that already exist
+    pub fn with_overwrite(mut self, overwrite: OverwritePolicy) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Compile the include/exclude globs
+    pub fn filter(&self) -> ArchiverResult<ExtractFilter> {
+        Ok(ExtractFilter {
+            include: build_glob_set(&self.include)?,
+            exclude: build_glob_set(&self.exclude)?,
+        })
+    }
+
+    /// Select the entries to extract into `output_dir`
+    pub fn plan(
+        &self,
+        entries: Vec<ArchiveEntryInfo>,
+        output_dir: &Path,
+    ) -> ArchiverResult<ExtractReport> {
+        let filter = self.filter()?;
+        let mut report = ExtractReport::default();
+
+        for entry in entries {
+            if !filter.matches(&entry.name) {
+                continue;
+            }
+
+            if self.overwrite == OverwritePolicy::SkipExisting
+                && safe_output_path(output_dir, &entry.name).exists()
+            {
+                report.skipped += 1;
+                continue;
+            }
+
+            report.total_bytes += entry.size;
+            report.entries.push(entry);
+        }
+
+        Ok(report)
+    }
+}
+
+/// Compiled include/exclude globs
+#[derive(Debug, Clone)]
+pub struct ExtractFilter {
+    /// Entries to extract (all entries if `None`)
+    include: Option<GlobSet>,
+    /// Entries to leave out
+    exclude: Option<GlobSet>,
+}
+
+impl ExtractFilter {
+    /// Check whether an entry name passes the filter
+    pub fn matches(&self, name: &str) -> bool {
+        let name = normalize_name(name);
+        let included = self.include.as_ref().is_none_or(|set| set.is_match(&name));
+        let excluded = self.exclude.as_ref().is_some_and(|set| set.is_match(&name));
+        included && !excluded
+    }
+}
+
+/// Entries selected for extraction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractReport {
+    /// Entries extracted (or to be extracted, for a dry run)
+    pub entries: Vec<ArchiveEntryInfo>,
+    /// Total size of `entries` in bytes
+    pub total_bytes: u64,
+    /// Matching entries skipped because the output file already exists
+    pub skipped: usize,
+}
+
+/// Compile globs into a set, or `None` if there are none
+fn build_glob_set(patterns: &[String]) -> ArchiverResult<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(&normalize_name(pattern))
+            .case_insensitive(true)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| {
+                ArchiverError::InvalidFormat(format!("Invalid glob {}: {}", pattern, e))
+            })?;
+        builder.add(glob);
+    }
+
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| ArchiverError::InvalidFormat(format!("Invalid glob set: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn entry(name: &str, size: u64) -> ArchiveEntryInfo {
+        ArchiveEntryInfo {
+            name: name.to_string(),
+            size,
+            compressed: false,
+        }
+    }
+
+    #[test]
+    fn test_filter_globs() {
+        let filter = ExtractOptions::default()
+            .include(["Data/**", "Graphics/Pictures/*"])
+            .exclude(["**/*.png~"])
+            .filter()
+            .unwrap();
+
+        assert!(filter.matches("Data/Map001.rvdata2"));
+        assert!(filter.matches("data\\sub\\Map001.rvdata2"));
+        assert!(filter.matches("Graphics/Pictures/title.png"));
+        assert!(!filter.matches("Graphics/Pictures/old/title.png"));
+        assert!(!filter.matches("Graphics/Pictures/title.png~"));
+        assert!(!filter.matches("Audio/BGM/theme.ogg"));
+
+        let all = ExtractOptions::default().filter().unwrap();
+        assert!(all.matches("Audio/BGM/theme.ogg"));
+
+        assert!(ExtractOptions::default()
+            .include(["Data/["])
+            .filter()
+            .is_err());
+    }
+
+    #[test]
+    fn test_plan_skip_existing() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("Data")).unwrap();
+        fs::write(temp_dir.path().join("Data/Actors.rvdata2"), b"old").unwrap();
+
+        let entries = vec![
+            entry("Data/Actors.rvdata2", 10),
+            entry("Data/Map001.rvdata2", 20),
+            entry("Audio/BGM/theme.ogg", 30),
+        ];
+
+        let options = ExtractOptions::default().include(["Data/**"]);
+        let report = options.plan(entries.clone(), temp_dir.path()).unwrap();
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(report.total_bytes, 30);
+        assert_eq!(report.skipped, 0);
+
+        let options = options.with_overwrite(OverwritePolicy::SkipExisting);
+        let report = options.plan(entries, temp_dir.path()).unwrap();
+        assert_eq!(report.entries, vec![entry("Data/Map001.rvdata2", 20)]);
+        assert_eq!(report.total_bytes, 20);
+        assert_eq!(report.skipped, 1);
+    }
+}
@@ -2,9 +2,38 @@
 //!
 //! This module provides functionality for unpacking and repacking
 //! game archive formats. Currently supports RGSS archives used by
-//! RPG Maker XP, VX, and VX Ace.
+//! RPG Maker XP, VX, and VX Ace, XP3 archives used by KiriKiri, ASAR
+//! archives used by Electron apps, and NW.js packages (`package.nw` or a zip
+//! appended to the executable). Executables packed with Enigma Virtual Box
+//! are read by [`evb`], Wolf RPG Editor `.wolf` archives by [`wolf`] and
+//! Ren'Py `.rpa` archives by [`rpa`].
+//! Encrypted RPG Maker MV/MZ assets are handled by [`rpgmv`].
+//!
+//! [`ArchiveFormat::open`] opens any supported archive as a `dyn` [`Archive`]
+//! ([`ArchiveFormat::open_with`] with format settings such as the XP3
+//! cipher), and [`vfs::GameVfs`] overlays a game folder on top of its archives.
+//! [`ExtractOptions`] selects part of an archive to extract, and
+//! [`diff::diff_paths`] compares two archives or an archive and a folder.
+//! Long operations report progress and can be cancelled through an
+//! [`Operation`].
 
+mod archive;
+pub mod asar;
+pub mod diff;
+pub mod evb;
+mod extract;
+pub mod nwjs;
+mod progress;
 pub mod rgss;
+pub mod rpa;
+pub mod rpgmv;
+pub mod vfs;
+pub mod wolf;
+pub mod xp3;
+
+pub use archive::{normalize_name, safe_output_path, Archive, ArchiveEntryInfo};
+pub use extract::{ExtractFilter, ExtractOptions, ExtractReport, OverwritePolicy};
+pub use progress::{ArchiveProgress, CancellationToken, Operation, ProgressSink};
 
 use std::io;
 use std::path::Path;
@@ -32,6 +61,9 @@ pub enum ArchiverError {
 
     #[error("File not found: {0}")]
     FileNotFound(String),
+
+    #[error("Operation cancelled")]
+    Cancelled,
 }
 
 /// Trait for archive readers (unpackers)
@@ -50,6 +82,22 @@ pub trait ArchiveReader {
     /// Extract all files to a directory
     fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> ArchiverResult<usize>;
 
+    /// Extract all files, reporting progress and stopping when cancelled
+    ///
+    /// Files written before a cancellation are removed.
+    fn extract_all_with<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        operation: &Operation,
+    ) -> ArchiverResult<usize>
+    where
+        Self: Archive + Sized,
+    {
+        let options = ExtractOptions::default();
+        let report = Archive::extract_with(self, output_dir.as_ref(), &options, operation)?;
+        Ok(report.entries.len())
+    }
+
     /// Extract a single entry by name
     fn extract_entry<P: AsRef<Path>>(&self, entry_name: &str, output_dir: P) -> ArchiverResult<()>;
 }
@@ -63,11 +111,59 @@ pub trait ArchiveWriter {
     fn add_file<P: AsRef<Path>>(&mut self, path: P, archive_name: &str) -> ArchiverResult<()>;
 
     /// Add files from a directory recursively
-    fn add_directory<P: AsRef<Path>>(&mut self, dir: P, base_path: Option<&str>)
-        -> ArchiverResult<usize>;
+    fn add_directory<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+    ) -> ArchiverResult<usize>;
+
+    /// Add files from a directory, reporting progress and stopping when
+    /// cancelled
+    ///
+    /// The default only checks for cancellation before and after adding
+    /// the files; the writers of this module report every file.
+    fn add_directory_with<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+        operation: &Operation,
+    ) -> ArchiverResult<usize> {
+        operation.check()?;
+        let count = self.add_directory(dir, base_path)?;
+        operation.check()?;
+        Ok(count)
+    }
 
     /// Write the archive to a file
     fn write<P: AsRef<Path>>(self, output_path: P) -> ArchiverResult<()>;
+
+    /// Write the archive, reporting progress and stopping when cancelled
+    ///
+    /// The writers of this module report every entry, and a cancelled
+    /// write removes the partial archive. The default only checks for
+    /// cancellation before writing.
+    fn write_with<P: AsRef<Path>>(self, output_path: P, operation: &Operation) -> ArchiverResult<()>
+    where
+        Self: Sized,
+    {
+        operation.check()?;
+        self.write(output_path)
+    }
+}
+
+/// Format-specific settings for opening an archive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// Cipher of XP3 archive contents
+    pub xp3_cipher: xp3::Xp3CipherScheme,
+}
+
+impl OpenOptions {
+    /// Set the cipher of XP3 archive contents
+    pub fn with_xp3_cipher(mut self, scheme: xp3::Xp3CipherScheme) -> Self {
+        self.xp3_cipher = scheme;
+        self
+    }
 }
 
 /// Archive format detection
@@ -75,6 +171,18 @@ pub trait ArchiveWriter {
 pub enum ArchiveFormat {
     /// RGSS archive (RPG Maker XP/VX/VX Ace)
     Rgss(rgss::RgssVersion),
+    /// XP3 archive (KiriKiri)
+    Xp3,
+    /// ASAR archive (Electron)
+    Asar,
+    /// Zip package, standalone or appended to an executable (NW.js)
+    NwPackage,
+    /// Executable packed with Enigma Virtual Box
+    Evb,
+    /// DxLib archive (Wolf RPG Editor)
+    Wolf,
+    /// RPA archive (Ren'Py)
+    Rpa,
 }
 
 impl ArchiveFormat {
@@ -84,6 +192,32 @@ impl ArchiveFormat {
             return Some(ArchiveFormat::Rgss(version));
         }
 
+        if xp3::is_xp3_archive(&path) {
+            return Some(ArchiveFormat::Xp3);
+        }
+
+        if asar::is_asar_archive(&path) {
+            return Some(ArchiveFormat::Asar);
+        }
+
+        if wolf::is_wolf_archive(&path) {
+            return Some(ArchiveFormat::Wolf);
+        }
+
+        if rpa::is_rpa_archive(&path) {
+            return Some(ArchiveFormat::Rpa);
+        }
+
+        // Any zip, with or without an executable prefix
+        if nwjs::is_nw_package(&path) {
+            return Some(ArchiveFormat::NwPackage);
+        }
+
+        // Scans the executable, so only after the cheaper checks
+        if evb::is_evb_package(&path) {
+            return Some(ArchiveFormat::Evb);
+        }
+
         None
     }
 
@@ -93,9 +227,66 @@ impl ArchiveFormat {
             "rgssad" => Some(ArchiveFormat::Rgss(rgss::RgssVersion::V1)),
             "rgss2a" => Some(ArchiveFormat::Rgss(rgss::RgssVersion::V1)),
             "rgss3a" => Some(ArchiveFormat::Rgss(rgss::RgssVersion::V3)),
+            "xp3" => Some(ArchiveFormat::Xp3),
+            "asar" => Some(ArchiveFormat::Asar),
+            "nw" => Some(ArchiveFormat::NwPackage),
+            "wolf" => Some(ArchiveFormat::Wolf),
+            "rpa" => Some(ArchiveFormat::Rpa),
             _ => None,
         }
     }
+
+    /// Open an archive of any supported format
+    ///
+    /// The format is sniffed from the file contents, not the extension.
+    /// XP3 archives are opened without a cipher; use [`Self::open_with`]
+    /// for encrypted archives.
+    pub fn open<P: AsRef<Path>>(path: P) -> ArchiverResult<Box<dyn Archive>> {
+        Self::open_with(path, &OpenOptions::default())
+    }
+
+    /// Open an archive of any supported format with format settings
+    pub fn open_with<P: AsRef<Path>>(
+        path: P,
+        options: &OpenOptions,
+    ) -> ArchiverResult<Box<dyn Archive>> {
+        let path = path.as_ref();
+
+        if !path.is_file() {
+            return Err(ArchiverError::FileNotFound(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+
+        let format = Self::detect(path).ok_or_else(|| {
+            ArchiverError::InvalidFormat(format!("Unknown archive format: {}", path.display()))
+        })?;
+        format.open_as_with(path, options)
+    }
+
+    /// Open an archive as this format
+    pub fn open_as<P: AsRef<Path>>(self, path: P) -> ArchiverResult<Box<dyn Archive>> {
+        self.open_as_with(path, &OpenOptions::default())
+    }
+
+    /// Open an archive as this format with format settings
+    pub fn open_as_with<P: AsRef<Path>>(
+        self,
+        path: P,
+        options: &OpenOptions,
+    ) -> ArchiverResult<Box<dyn Archive>> {
+        Ok(match self {
+            ArchiveFormat::Rgss(_) => Box::new(rgss::RgssReader::open(path)?),
+            ArchiveFormat::Xp3 => {
+                Box::new(xp3::Xp3Reader::open(path)?.with_cipher(options.xp3_cipher.cipher()))
+            }
+            ArchiveFormat::Asar => Box::new(asar::AsarReader::open(path)?),
+            ArchiveFormat::NwPackage => Box::new(nwjs::NwReader::open(path)?),
+            ArchiveFormat::Evb => Box::new(evb::EvbReader::open(path)?),
+            ArchiveFormat::Wolf => Box::new(wolf::WolfReader::open(path)?),
+            ArchiveFormat::Rpa => Box::new(rpa::RpaReader::open(path)?),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -116,6 +307,153 @@ mod tests {
             ArchiveFormat::from_extension("rgss3a"),
             Some(ArchiveFormat::Rgss(rgss::RgssVersion::V3))
         ));
+        assert_eq!(
+            ArchiveFormat::from_extension("XP3"),
+            Some(ArchiveFormat::Xp3)
+        );
+        assert_eq!(
+            ArchiveFormat::from_extension("asar"),
+            Some(ArchiveFormat::Asar)
+        );
+        assert_eq!(
+            ArchiveFormat::from_extension("nw"),
+            Some(ArchiveFormat::NwPackage)
+        );
+        assert_eq!(
+            ArchiveFormat::from_extension("wolf"),
+            Some(ArchiveFormat::Wolf)
+        );
+        assert_eq!(
+            ArchiveFormat::from_extension("rpa"),
+            Some(ArchiveFormat::Rpa)
+        );
         assert!(ArchiveFormat::from_extension("zip").is_none());
     }
+
+    #[test]
+    fn test_detect_xp3() {
+        use std::io::Write;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(xp3::MAGIC).unwrap();
+        file.write_all(&0u64.to_le_bytes()).unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(ArchiveFormat::detect(file.path()), Some(ArchiveFormat::Xp3));
+    }
+
+    #[test]
+    fn test_detect_asar() {
+        use crate::archiver::ArchiveWriter;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("main.js");
+        std::fs::write(&source, "console.log(1);").unwrap();
+
+        let archive_path = temp_dir.path().join("app.asar");
+        let mut writer = asar::AsarWriter::new();
+        writer.add_file(&source, "main.js").unwrap();
+        writer.write(&archive_path).unwrap();
+
+        assert_eq!(
+            ArchiveFormat::detect(&archive_path),
+            Some(ArchiveFormat::Asar)
+        );
+    }
+
+    #[test]
+    fn test_open_dynamic() {
+        use crate::archiver::ArchiveWriter;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("Map001.rvdata2");
+        std::fs::write(&source, b"\x04\x08map data").unwrap();
+
+        let rgss_path = temp_dir.path().join("Game.rgss3a");
+        let mut writer = rgss::RgssWriter::for_version(rgss::RgssVersion::V3);
+        writer.add_file(&source, "Data\\Map001.rvdata2").unwrap();
+        writer.write(&rgss_path).unwrap();
+
+        let asar_path = temp_dir.path().join("app.asar");
+        let mut writer = asar::AsarWriter::new();
+        writer.add_file(&source, "Data/Map001.rvdata2").unwrap();
+        writer.write(&asar_path).unwrap();
+
+        let nw_path = temp_dir.path().join("package.nw");
+        let mut writer = nwjs::NwWriter::new();
+        writer.add_file(&source, "Data/Map001.rvdata2").unwrap();
+        writer.write(&nw_path).unwrap();
+
+        for (path, format) in [
+            (&rgss_path, ArchiveFormat::Rgss(rgss::RgssVersion::V3)),
+            (&asar_path, ArchiveFormat::Asar),
+            (&nw_path, ArchiveFormat::NwPackage),
+        ] {
+            let archive = ArchiveFormat::open(path).unwrap();
+            assert_eq!(archive.format(), format);
+            assert_eq!(archive.path(), path.as_path());
+
+            let entries = archive.list_entries();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].name, "Data/Map001.rvdata2");
+            assert_eq!(entries[0].size, 10);
+
+            assert!(archive.contains("Data\\Map001.rvdata2"));
+            assert_eq!(
+                archive.read_entry("Data/Map001.rvdata2").unwrap(),
+                b"\x04\x08map data"
+            );
+
+            let output_dir = temp_dir.path().join(format!("{:?}", format));
+            assert_eq!(archive.extract_to(&output_dir).unwrap(), 1);
+            assert_eq!(
+                std::fs::read(output_dir.join("Data/Map001.rvdata2")).unwrap(),
+                b"\x04\x08map data"
+            );
+        }
+    }
+
+    #[test]
+    fn test_open_with_xp3_cipher() {
+        use crate::archiver::ArchiveWriter;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("startup.tjs");
+        std::fs::write(&source, "Scripts.execStorage(\"system.tjs\");").unwrap();
+
+        let scheme = xp3::Xp3CipherScheme::HashXor { shift: 8 };
+        let archive_path = temp_dir.path().join("data.xp3");
+        let mut writer = xp3::Xp3Writer::new().with_cipher(scheme.cipher());
+        writer.add_file(&source, "startup.tjs").unwrap();
+        writer.write(&archive_path).unwrap();
+
+        let plain = ArchiveFormat::open(&archive_path).unwrap();
+        assert_ne!(
+            plain.read_entry("startup.tjs").unwrap(),
+            std::fs::read(&source).unwrap()
+        );
+
+        let options = OpenOptions::default().with_xp3_cipher(scheme);
+        let archive = ArchiveFormat::open_with(&archive_path, &options).unwrap();
+        assert_eq!(
+            archive.read_entry("startup.tjs").unwrap(),
+            std::fs::read(&source).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_open_unknown() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("readme.txt");
+        std::fs::write(&path, "not an archive").unwrap();
+
+        assert!(matches!(
+            ArchiveFormat::open(&path),
+            Err(ArchiverError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            ArchiveFormat::open(temp_dir.path().join("missing.xp3")),
+            Err(ArchiverError::FileNotFound(_))
+        ));
+    }
 }
@@ -0,0 +1,112 @@
+//! NW.js package module
+//!
+//! NW.js games ship their app folder (`package.json`, `www/`, ...) either
+//! as a standalone `package.nw` zip, or as a zip appended to the NW.js
+//! executable (`nw.exe` / `Game.exe`). In the latter case the file starts
+//! with the executable "stub" and the zip's central directory is found by
+//! scanning from the end of the file.
+//!
+//! Repacking writes a fresh zip, optionally prefixed with the original stub,
+//! so the game can be launched the same way as before.
+
+mod reader;
+mod writer;
+
+pub use reader::NwReader;
+pub use writer::NwWriter;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::archiver::safe_output_path;
+
+/// Name of the standalone package file
+pub const PACKAGE_NW: &str = "package.nw";
+
+/// File entry in an NW.js package
+#[derive(Debug, Clone)]
+pub struct NwEntry {
+    /// File name (relative path within package, `/`-separated)
+    pub name: String,
+    /// Uncompressed size in bytes
+    pub size: u64,
+    /// Stored size in bytes
+    pub compressed_size: u64,
+    /// Whether the file is compressed
+    pub compressed: bool,
+    /// Compression method of the file
+    pub method: zip::CompressionMethod,
+    /// Index of the file in the zip central directory
+    pub index: usize,
+    /// Offset of the stored data in the package file
+    pub data_start: u64,
+}
+
+impl NwEntry {
+    /// Get the output path for extraction
+    pub fn output_path(&self, base_dir: &Path) -> PathBuf {
+        safe_output_path(base_dir, &self.name)
+    }
+}
+
+/// Check whether a file is a zip, with or without an executable prefix
+pub fn is_nw_package<P: AsRef<Path>>(path: P) -> bool {
+    fs::File::open(path)
+        .ok()
+        .and_then(|file| zip::ZipArchive::new(file).ok())
+        .is_some()
+}
+
+/// Find the NW.js package of a game folder
+///
+/// Prefers `package.nw`, then executables with an appended zip.
+pub fn find_package<P: AsRef<Path>>(game_dir: P) -> Option<PathBuf> {
+    let game_dir = game_dir.as_ref();
+
+    let package = game_dir.join(PACKAGE_NW);
+    if package.is_file() && is_nw_package(&package) {
+        return Some(package);
+    }
+
+    let mut executables: Vec<PathBuf> = fs::read_dir(game_dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| e.eq_ignore_ascii_case("exe"))
+        })
+        .collect();
+    executables.sort();
+
+    executables.into_iter().find(|p| is_nw_package(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archiver::ArchiveWriter;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let game_dir = temp_dir.path();
+
+        fs::write(game_dir.join("Launcher.exe"), b"MZ plain executable").unwrap();
+        assert!(find_package(game_dir).is_none());
+
+        let source = game_dir.join("package.json");
+        fs::write(&source, r#"{"main":"index.html"}"#).unwrap();
+
+        let mut writer = NwWriter::new();
+        writer.add_file(&source, "package.json").unwrap();
+        writer = writer.with_stub(b"MZ nw stub".to_vec());
+        writer.write(game_dir.join("Game.exe")).unwrap();
+
+        assert_eq!(find_package(game_dir), Some(game_dir.join("Game.exe")));
+        assert!(!is_nw_package(game_dir.join("Launcher.exe")));
+    }
+}
@@ -0,0 +1,364 @@
+//! NW.js Package Reader (Unpacker)
+
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use flate2::read::DeflateDecoder;
+use zip::{CompressionMethod, ZipArchive};
+
+use super::NwEntry;
+use crate::archiver::archive::{check_bounds, find_entry};
+use crate::archiver::{
+    Archive, ArchiveEntryInfo, ArchiveFormat, ArchiveReader, ArchiverError, ArchiverResult,
+};
+
+/// Most bytes reserved per compressed byte before inflating an entry
+///
+/// Both sizes come from the zip directory; the buffer still grows as needed
+/// for files that compress better than this.
+const PREALLOCATION_RATIO: u64 = 8;
+
+/// NW.js Package Reader for `package.nw` and exe-appended zips
+pub struct NwReader {
+    /// Path to the package file
+    path: PathBuf,
+    /// Size of the executable stub preceding the zip (0 for `package.nw`)
+    stub_len: u64,
+    /// File entries in the package
+    entries: Vec<NwEntry>,
+}
+
+impl NwReader {
+    /// Open the zip archive of the package
+    fn open_zip(path: &Path) -> ArchiverResult<ZipArchive<BufReader<File>>> {
+        let file = File::open(path)?;
+        ZipArchive::new(BufReader::new(file)).map_err(zip_error)
+    }
+
+    /// Extract a single entry to a byte vector
+    pub fn extract_to_memory(&self, entry: &NwEntry) -> ArchiverResult<Vec<u8>> {
+        let mut archive = Self::open_zip(&self.path)?;
+        let mut file = archive.by_index(entry.index).map_err(zip_error)?;
+
+        let packed_len = entry.compressed_size.min(fs::metadata(&self.path)?.len());
+        let capacity = entry
+            .size
+            .min(packed_len.saturating_mul(PREALLOCATION_RATIO));
+        let mut data = Vec::with_capacity(capacity as usize);
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Open a streaming reader over an entry
+    ///
+    /// Stored and deflated files are read straight from the package; other
+    /// compression methods are decoded into memory first. Unlike
+    /// [`extract_to_memory`](Self::extract_to_memory), CRCs are not checked.
+    pub fn entry_reader(&self, entry: &NwEntry) -> ArchiverResult<Box<dyn Read + Send>> {
+        let stream = match entry.method {
+            CompressionMethod::Stored | CompressionMethod::Deflated => {
+                let file = File::open(&self.path)?;
+                let file_len = file.metadata()?.len();
+                check_bounds(
+                    &entry.name,
+                    entry.data_start,
+                    entry.compressed_size,
+                    file_len,
+                )?;
+
+                let mut reader = BufReader::new(file);
+                reader.seek(SeekFrom::Start(entry.data_start))?;
+                reader.take(entry.compressed_size)
+            }
+            _ => return Ok(Box::new(Cursor::new(self.extract_to_memory(entry)?))),
+        };
+
+        if entry.method == CompressionMethod::Deflated {
+            Ok(Box::new(DeflateDecoder::new(stream).take(entry.size)))
+        } else {
+            Ok(Box::new(stream))
+        }
+    }
+
+    /// Get the package path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the executable stub preceding the zip
+    pub fn stub_len(&self) -> u64 {
+        self.stub_len
+    }
+
+    /// Check if the zip is appended to an executable
+    pub fn has_stub(&self) -> bool {
+        self.stub_len > 0
+    }
+
+    /// Read the executable stub preceding the zip
+    pub fn read_stub(&self) -> ArchiverResult<Vec<u8>> {
+        let file = File::open(&self.path)?;
+        let mut stub = Vec::with_capacity(self.stub_len as usize);
+        file.take(self.stub_len).read_to_end(&mut stub)?;
+        Ok(stub)
+    }
+}
+
+impl ArchiveReader for NwReader {
+    type Entry = NwEntry;
+
+    fn open<P: AsRef<Path>>(path: P) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+        let mut archive = Self::open_zip(path)?;
+
+        let mut entries = Vec::new();
+        // The first local header marks the end of the stub. Offsets are
+        // absolute, whether the zip was written with relative or adjusted
+        // offsets.
+        let mut first_header = None::<u64>;
+
+        for index in 0..archive.len() {
+            let file = archive.by_index_raw(index).map_err(zip_error)?;
+            first_header =
+                Some(first_header.map_or(file.header_start(), |h| h.min(file.header_start())));
+
+            if file.is_dir() {
+                continue;
+            }
+
+            // Reject names escaping the output directory
+            let name = file
+                .enclosed_name()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .ok_or_else(|| {
+                    ArchiverError::InvalidFormat(format!("Unsafe entry name: {}", file.name()))
+                })?;
+
+            entries.push(NwEntry {
+                name,
+                size: file.size(),
+                compressed_size: file.compressed_size(),
+                compressed: file.compression() != CompressionMethod::Stored,
+                method: file.compression(),
+                index,
+                data_start: file.data_start(),
+            });
+        }
+
+        let stub_len = match first_header {
+            Some(offset) => offset,
+            None => archive.offset(),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            stub_len,
+            entries,
+        })
+    }
+
+    fn entries(&self) -> &[NwEntry] {
+        &self.entries
+    }
+
+    fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> ArchiverResult<usize> {
+        let output_dir = output_dir.as_ref();
+        let mut archive = Self::open_zip(&self.path)?;
+        let mut count = 0;
+
+        // Stream entries from one open archive instead of reopening per entry
+        for entry in &self.entries {
+            let mut file = archive.by_index(entry.index).map_err(zip_error)?;
+            let output_path = entry.output_path(output_dir);
+
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut output = File::create(&output_path)?;
+            std::io::copy(&mut file, &mut output)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn extract_entry<P: AsRef<Path>>(&self, entry_name: &str, output_dir: P) -> ArchiverResult<()> {
+        let output_dir = output_dir.as_ref();
+
+        // Find the entry
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.name == entry_name)
+            .ok_or_else(|| ArchiverError::FileNotFound(entry_name.to_string()))?;
+
+        // Extract to memory
+        let data = self.extract_to_memory(entry)?;
+
+        // Write to file
+        let output_path = entry.output_path(output_dir);
+
+        // Create parent directories
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&output_path, data)?;
+
+        Ok(())
+    }
+}
+
+impl Archive for NwReader {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::NwPackage
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn list_entries(&self) -> Vec<ArchiveEntryInfo> {
+        self.entries
+            .iter()
+            .map(|e| ArchiveEntryInfo {
+                name: e.name.clone(),
+                size: e.size,
+                compressed: e.compressed,
+            })
+            .collect()
+    }
+
+    fn open_entry(&self, name: &str) -> ArchiverResult<Box<dyn Read + Send + '_>> {
+        let entry = find_entry(&self.entries, name, |e| &e.name)?;
+        self.entry_reader(entry)
+    }
+}
+
+/// Convert a zip error into an archiver error
+pub(super) fn zip_error(error: zip::result::ZipError) -> ArchiverError {
+    match error {
+        zip::result::ZipError::Io(e) => ArchiverError::Io(e),
+        zip::result::ZipError::FileNotFound => ArchiverError::FileNotFound("zip entry".to_string()),
+        e => ArchiverError::InvalidFormat(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use tempfile::TempDir;
+    use zip::write::FileOptions;
+
+    fn build_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_read_package_nw() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("package.nw");
+        fs::write(
+            &path,
+            build_zip(&[
+                ("package.json", br#"{"main":"www/index.html"}"#),
+                ("www/data/Map001.json", br#"{"events":[]}"#),
+            ]),
+        )
+        .unwrap();
+
+        let reader = NwReader::open(&path).unwrap();
+        assert!(!reader.has_stub());
+        assert_eq!(reader.entries().len(), 2);
+        assert_eq!(reader.entries()[1].name, "www/data/Map001.json");
+        assert_eq!(
+            reader.extract_to_memory(&reader.entries()[1]).unwrap(),
+            br#"{"events":[]}"#
+        );
+    }
+
+    #[test]
+    fn test_entry_reader_stored_and_deflated() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Game.exe");
+        let script = b"Scene_Title.prototype.start = function() {};\n".repeat(50);
+
+        let mut stub = Cursor::new(b"MZ stub".to_vec());
+        stub.set_position(7);
+        let mut writer = zip::ZipWriter::new(stub);
+        for method in [CompressionMethod::Stored, CompressionMethod::Deflated] {
+            let options = FileOptions::default().compression_method(method);
+            writer
+                .start_file(format!("{:?}.js", method), options)
+                .unwrap();
+            writer.write_all(&script).unwrap();
+        }
+        fs::write(&path, writer.finish().unwrap().into_inner()).unwrap();
+
+        let reader = NwReader::open(&path).unwrap();
+        assert!(reader.has_stub());
+        for entry in reader.entries() {
+            let mut data = Vec::new();
+            reader
+                .entry_reader(entry)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            assert_eq!(data, script, "{}", entry.name);
+        }
+        assert!(reader.entries()[1].compressed);
+    }
+
+    #[test]
+    fn test_read_exe_appended_zip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Game.exe");
+
+        let stub = b"MZ\x90\0 fake nw.exe stub".repeat(10);
+        let mut data = stub.clone();
+        data.extend(build_zip(&[
+            ("package.json", b"{}"),
+            ("www/js/main.js", b"main();"),
+        ]));
+        fs::write(&path, data).unwrap();
+
+        let reader = NwReader::open(&path).unwrap();
+        assert_eq!(reader.stub_len(), stub.len() as u64);
+        assert_eq!(reader.read_stub().unwrap(), stub);
+
+        let output_dir = temp_dir.path().join("out");
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 2);
+        assert_eq!(
+            fs::read(output_dir.join("www/js/main.js")).unwrap(),
+            b"main();"
+        );
+    }
+
+    #[test]
+    fn test_read_unsafe_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("package.nw");
+        fs::write(&path, build_zip(&[("../evil.js", b"x")])).unwrap();
+
+        let result = NwReader::open(&path);
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_read_not_zip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Game.exe");
+        fs::write(&path, b"MZ no zip here").unwrap();
+
+        let result = NwReader::open(&path);
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+    }
+}
@@ -0,0 +1,344 @@
+//! NW.js Package Writer (Repacker)
+
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Read, Write};
+use std::path::Path;
+
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::reader::zip_error;
+use super::NwReader;
+use crate::archiver::{ArchiveReader, ArchiveWriter, ArchiverError, ArchiverResult, Operation};
+
+/// File entry to be packed
+#[derive(Debug)]
+struct PackEntry {
+    /// Name in the package (with forward slashes)
+    archive_name: String,
+    /// File data
+    data: Vec<u8>,
+}
+
+/// NW.js Package Writer for `package.nw` and exe-appended zips
+pub struct NwWriter {
+    /// Files to pack
+    entries: Vec<PackEntry>,
+    /// Executable stub written before the zip
+    stub: Option<Vec<u8>>,
+    /// Whether to deflate files
+    compress: bool,
+}
+
+impl NwWriter {
+    /// Enable or disable deflate compression (enabled by default)
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Write the zip after an executable stub
+    pub fn with_stub(mut self, stub: Vec<u8>) -> Self {
+        self.stub = Some(stub);
+        self
+    }
+
+    /// Take the executable stub from an existing executable
+    ///
+    /// If the executable already has a zip appended, only the part before
+    /// the zip is kept, so the package is replaced rather than stacked.
+    pub fn with_stub_from<P: AsRef<Path>>(self, exe_path: P) -> ArchiverResult<Self> {
+        let exe_path = exe_path.as_ref();
+        if !exe_path.exists() {
+            return Err(ArchiverError::FileNotFound(
+                exe_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        let stub = match NwReader::open(exe_path) {
+            Ok(reader) => reader.read_stub()?,
+            Err(ArchiverError::InvalidFormat(_)) => std::fs::read(exe_path)?,
+            Err(e) => return Err(e),
+        };
+
+        Ok(self.with_stub(stub))
+    }
+
+    /// Build the zip in memory, counting entries as they are compressed
+    fn build_zip(&self, operation: &Operation) -> ArchiverResult<Vec<u8>> {
+        let method = if self.compress {
+            CompressionMethod::Deflated
+        } else {
+            CompressionMethod::Stored
+        };
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for entry in &self.entries {
+            let options = FileOptions::default()
+                .compression_method(method)
+                .large_file(entry.data.len() as u64 >= u32::MAX as u64);
+            zip.start_file(entry.archive_name.as_str(), options)
+                .map_err(zip_error)?;
+            zip.write_all(&entry.data)?;
+            operation.entry_done(&entry.archive_name, entry.data.len() as u64)?;
+        }
+
+        Ok(zip.finish().map_err(zip_error)?.into_inner())
+    }
+
+    /// Write the stub and the zip
+    fn write_package(&self, output_path: &Path, operation: &Operation) -> ArchiverResult<()> {
+        // Build the zip first so a failure does not leave a truncated exe
+        let zip = self.build_zip(operation)?;
+
+        let file = File::create(output_path)?;
+        operation.track_output(output_path.to_path_buf());
+        let mut writer = BufWriter::new(file);
+
+        // Offsets in the zip stay relative to its start, like `copy /b nw.exe+app.nw`
+        if let Some(stub) = &self.stub {
+            writer.write_all(stub)?;
+        }
+        writer.write_all(&zip)?;
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl ArchiveWriter for NwWriter {
+    fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            stub: None,
+            compress: true,
+        }
+    }
+
+    fn add_file<P: AsRef<Path>>(&mut self, path: P, archive_name: &str) -> ArchiverResult<()> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Err(ArchiverError::FileNotFound(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+
+        let mut file = File::open(path)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        // Zip uses forward slashes for paths
+        let normalized_name = archive_name.replace('\\', "/");
+
+        self.entries.push(PackEntry {
+            archive_name: normalized_name,
+            data,
+        });
+
+        Ok(())
+    }
+
+    fn add_directory<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+    ) -> ArchiverResult<usize> {
+        self.add_directory_with(dir, base_path, &Operation::new())
+    }
+
+    fn add_directory_with<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+        operation: &Operation,
+    ) -> ArchiverResult<usize> {
+        let dir = dir.as_ref();
+
+        if !dir.exists() {
+            return Err(ArchiverError::FileNotFound(
+                dir.to_string_lossy().to_string(),
+            ));
+        }
+
+        // List the files first so progress has totals
+        let mut files = Vec::new();
+        let mut total_bytes = 0;
+        for entry in walkdir::WalkDir::new(dir)
+            .follow_links(true)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+
+            if path.is_file() {
+                // Calculate archive name
+                let relative_path = path.strip_prefix(dir).map_err(|e| {
+                    ArchiverError::InvalidFormat(format!("Failed to get relative path: {}", e))
+                })?;
+
+                let relative_name = relative_path.to_string_lossy().replace('\\', "/");
+                let archive_name = match base_path {
+                    Some(base) => format!("{}/{}", base.trim_end_matches('/'), relative_name),
+                    None => relative_name,
+                };
+
+                total_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                files.push((path.to_path_buf(), archive_name));
+            }
+        }
+
+        operation.begin(files.len(), total_bytes);
+        for (path, archive_name) in &files {
+            self.add_file(path, archive_name)?;
+            let size = self.entries.last().map_or(0, |e| e.data.len() as u64);
+            operation.entry_done(archive_name, size)?;
+        }
+
+        Ok(files.len())
+    }
+
+    fn write<P: AsRef<Path>>(self, output_path: P) -> ArchiverResult<()> {
+        self.write_with(output_path, &Operation::new())
+    }
+
+    fn write_with<P: AsRef<Path>>(
+        self,
+        output_path: P,
+        operation: &Operation,
+    ) -> ArchiverResult<()> {
+        let total_bytes = self.entries.iter().map(|e| e.data.len() as u64).sum();
+        operation.begin(self.entries.len(), total_bytes);
+
+        let result = self.write_package(output_path.as_ref(), operation);
+        operation.finish(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn create_app(dir: &Path) {
+        fs::create_dir_all(dir.join("www/data")).unwrap();
+        fs::write(dir.join("package.json"), r#"{"main":"www/index.html"}"#).unwrap();
+        fs::write(dir.join("www/index.html"), "<html></html>").unwrap();
+        fs::write(
+            dir.join("www/data/System.json"),
+            r#"{"gameTitle":"번역된 게임"}"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_pack_package_nw_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let app_dir = temp_dir.path().join("app");
+        create_app(&app_dir);
+
+        let package = temp_dir.path().join("package.nw");
+        let mut writer = NwWriter::new();
+        assert_eq!(writer.add_directory(&app_dir, None).unwrap(), 3);
+        writer.write(&package).unwrap();
+
+        let reader = NwReader::open(&package).unwrap();
+        assert!(!reader.has_stub());
+
+        let output_dir = temp_dir.path().join("out");
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 3);
+        for name in ["package.json", "www/index.html", "www/data/System.json"] {
+            assert_eq!(
+                fs::read(output_dir.join(name)).unwrap(),
+                fs::read(app_dir.join(name)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_reappend_to_exe_stub() {
+        let temp_dir = TempDir::new().unwrap();
+        let app_dir = temp_dir.path().join("app");
+        create_app(&app_dir);
+
+        // Original exe with an old package appended
+        let stub = b"MZ\x90\0 nw.exe stub".repeat(8);
+        let original = temp_dir.path().join("Game.exe");
+        let mut writer = NwWriter::new().with_stub(stub.clone());
+        writer
+            .add_file(app_dir.join("package.json"), "package.json")
+            .unwrap();
+        writer.write(&original).unwrap();
+
+        // Repack the translated app onto the same stub
+        let repacked = temp_dir.path().join("Game_translated.exe");
+        let mut writer = NwWriter::new()
+            .with_compression(false)
+            .with_stub_from(&original)
+            .unwrap();
+        writer.add_directory(&app_dir, None).unwrap();
+        writer.write(&repacked).unwrap();
+
+        let reader = NwReader::open(&repacked).unwrap();
+        assert_eq!(reader.read_stub().unwrap(), stub);
+        assert_eq!(reader.entries().len(), 3);
+        assert!(reader.entries().iter().all(|e| !e.compressed));
+
+        let system = reader
+            .entries()
+            .iter()
+            .find(|e| e.name == "www/data/System.json")
+            .unwrap();
+        assert_eq!(
+            reader.extract_to_memory(system).unwrap(),
+            fs::read(app_dir.join("www/data/System.json")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_stub_from_plain_exe() {
+        let temp_dir = TempDir::new().unwrap();
+        let exe = temp_dir.path().join("nw.exe");
+        fs::write(&exe, b"MZ plain nw.exe").unwrap();
+
+        let writer = NwWriter::new().with_stub_from(&exe).unwrap();
+        assert_eq!(writer.stub.as_deref(), Some(&b"MZ plain nw.exe"[..]));
+    }
+
+    #[test]
+    fn test_add_missing_file() {
+        let mut writer = NwWriter::new();
+        let result = writer.add_file("/nonexistent/package.json", "package.json");
+        assert!(matches!(result, Err(ArchiverError::FileNotFound(_))));
+    }
+
+    #[test]
+    fn test_cancelled_write_removes_output() {
+        use crate::archiver::{ArchiveProgress, CancellationToken};
+
+        let temp_dir = TempDir::new().unwrap();
+        let app_dir = temp_dir.path().join("app");
+        create_app(&app_dir);
+
+        let mut writer = NwWriter::new();
+        writer.add_directory(&app_dir, None).unwrap();
+
+        let token = CancellationToken::new();
+        let sink_token = token.clone();
+        let operation = Operation::new()
+            .with_token(token)
+            .with_sink(move |p: &ArchiveProgress| {
+                if p.entries_done == 1 {
+                    sink_token.cancel();
+                }
+            });
+        let package_path = temp_dir.path().join("package.nw");
+        assert!(matches!(
+            writer.write_with(&package_path, &operation),
+            Err(ArchiverError::Cancelled)
+        ));
+        assert!(!package_path.exists());
+    }
+}
@@ -0,0 +1,226 @@
+//! Progress reporting and cancellation for long archive operations
+//!
+//! An [`Operation`] is passed to extraction and packing. It counts the
+//! entries and bytes processed, forwards every update to an optional
+//! [`ProgressSink`], and carries a [`CancellationToken`] that is checked
+//! between entries. A cancelled operation fails with
+//! [`ArchiverError::Cancelled`] after removing the output it wrote.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use super::{ArchiverError, ArchiverResult};
+
+/// Snapshot of the progress of an operation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveProgress {
+    /// Entries processed so far
+    pub entries_done: usize,
+    /// Entries to process in total
+    pub entries_total: usize,
+    /// Bytes processed so far
+    pub bytes_done: u64,
+    /// Bytes to process in total
+    pub bytes_total: u64,
+    /// Name of the entry just processed
+    pub current: String,
+}
+
+/// Receiver of progress updates
+///
+/// Updates may come from several threads at once.
+pub trait ProgressSink: Send + Sync {
+    /// Handle a progress update
+    fn report(&self, progress: &ArchiveProgress);
+}
+
+impl<F: Fn(&ArchiveProgress) + Send + Sync> ProgressSink for F {
+    fn report(&self, progress: &ArchiveProgress) {
+        self(progress)
+    }
+}
+
+/// Shared flag to stop an operation from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of every operation using this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Check whether cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Progress counters, sink and cancellation token of one operation
+#[derive(Default)]
+pub struct Operation {
+    /// Receiver of progress updates
+    sink: Option<Arc<dyn ProgressSink>>,
+    /// Cancellation flag
+    token: CancellationToken,
+    /// Entries to process in total
+    entries_total: AtomicUsize,
+    /// Bytes to process in total
+    bytes_total: AtomicU64,
+    /// Entries processed so far
+    entries_done: AtomicUsize,
+    /// Bytes processed so far
+    bytes_done: AtomicU64,
+    /// Output files written so far, removed if the operation is cancelled
+    written: Mutex<Vec<PathBuf>>,
+}
+
+impl Operation {
+    /// Create an operation without a sink that is never cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward progress updates to a sink
+    pub fn with_sink(mut self, sink: impl ProgressSink + 'static) -> Self {
+        self.sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Stop the operation when the token is cancelled
+    pub fn with_token(mut self, token: CancellationToken) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Get the cancellation token
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Set the totals and reset the counters
+    pub fn begin(&self, entries_total: usize, bytes_total: u64) {
+        self.entries_total.store(entries_total, Ordering::Relaxed);
+        self.bytes_total.store(bytes_total, Ordering::Relaxed);
+        self.entries_done.store(0, Ordering::Relaxed);
+        self.bytes_done.store(0, Ordering::Relaxed);
+    }
+
+    /// Fail with [`ArchiverError::Cancelled`] if cancellation was requested
+    pub fn check(&self) -> ArchiverResult<()> {
+        if self.token.is_cancelled() {
+            return Err(ArchiverError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Count a finished entry, report it and check for cancellation
+    pub fn entry_done(&self, name: &str, bytes: u64) -> ArchiverResult<()> {
+        let entries_done = self.entries_done.fetch_add(1, Ordering::Relaxed) + 1;
+        let bytes_done = self.bytes_done.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+        if let Some(sink) = &self.sink {
+            sink.report(&ArchiveProgress {
+                entries_done,
+                entries_total: self.entries_total.load(Ordering::Relaxed),
+                bytes_done,
+                bytes_total: self.bytes_total.load(Ordering::Relaxed),
+                current: name.to_string(),
+            });
+        }
+
+        self.check()
+    }
+
+    /// Remember an output file so it can be removed on cancellation
+    pub fn track_output(&self, path: PathBuf) {
+        if let Ok(mut written) = self.written.lock() {
+            written.push(path);
+        }
+    }
+
+    /// End a step, removing the tracked output if `result` is a cancellation
+    pub fn finish<T>(&self, result: ArchiverResult<T>) -> ArchiverResult<T> {
+        let written = match self.written.lock() {
+            Ok(mut written) => std::mem::take(&mut *written),
+            Err(_) => Vec::new(),
+        };
+
+        if matches!(result, Err(ArchiverError::Cancelled)) {
+            for path in written {
+                let _ = fs::remove_file(path);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_progress_and_cancel() {
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let sink_updates = Arc::clone(&updates);
+        let token = CancellationToken::new();
+
+        let operation = Operation::new()
+            .with_sink(move |p: &ArchiveProgress| sink_updates.lock().unwrap().push(p.clone()))
+            .with_token(token.clone());
+        operation.begin(2, 30);
+
+        assert!(operation.entry_done("a", 10).is_ok());
+        token.cancel();
+        assert!(matches!(
+            operation.entry_done("b", 20),
+            Err(ArchiverError::Cancelled)
+        ));
+
+        let updates = updates.lock().unwrap();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(
+            updates[1],
+            ArchiveProgress {
+                entries_done: 2,
+                entries_total: 2,
+                bytes_done: 30,
+                bytes_total: 30,
+                current: "b".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_finish_removes_output_on_cancel() {
+        let temp_dir = TempDir::new().unwrap();
+        let kept = temp_dir.path().join("kept.txt");
+        let removed = temp_dir.path().join("removed.txt");
+        fs::write(&kept, b"kept").unwrap();
+        fs::write(&removed, b"removed").unwrap();
+
+        // Output of a finished step is kept when a later step is cancelled
+        let operation = Operation::new();
+        operation.track_output(kept.clone());
+        assert!(operation.finish(Ok(())).is_ok());
+
+        operation.track_output(removed.clone());
+        assert!(operation
+            .finish::<()>(Err(ArchiverError::Cancelled))
+            .is_err());
+
+        assert!(kept.exists());
+        assert!(!removed.exists());
+    }
+}
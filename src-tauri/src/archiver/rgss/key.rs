@@ -123,21 +123,28 @@ impl RgssKey {
     /// Decrypt file content
     /// Each 4 bytes are XORed with the key, then the key is stepped
     pub fn decrypt_content(&mut self, data: &[u8]) -> Vec<u8> {
-        let mut result = Vec::with_capacity(data.len());
-        let mut j = 0;
+        let mut result = data.to_vec();
+        self.apply_content(&mut result);
+        result
+    }
 
-        for &byte in data {
-            let shift = j * 8;
-            result.push(byte ^ ((self.state >> shift) & 0xFF) as u8);
+    /// Decrypt or encrypt file content in place
+    ///
+    /// Works a 32-bit little-endian word at a time. The key is stepped after
+    /// every full word; a trailing partial word uses the current state.
+    pub fn apply_content(&mut self, data: &mut [u8]) {
+        let mut words = data.chunks_exact_mut(4);
 
-            j += 1;
-            if j == 4 {
-                j = 0;
-                self.step();
-            }
+        for word in &mut words {
+            let value = u32::from_le_bytes([word[0], word[1], word[2], word[3]]) ^ self.state;
+            word.copy_from_slice(&value.to_le_bytes());
+            self.step();
         }
 
-        result
+        let key_bytes = self.state.to_le_bytes();
+        for (byte, key) in words.into_remainder().iter_mut().zip(key_bytes) {
+            *byte ^= key;
+        }
     }
 
     /// Encrypt file content
@@ -192,4 +199,27 @@ mod tests {
 
         assert_eq!(decrypted, data);
     }
+
+    #[test]
+    fn test_apply_content_matches_bytewise() {
+        let data: Vec<u8> = (0..=40u8).map(|i| i.wrapping_mul(37)).collect();
+
+        for len in 0..data.len() {
+            let mut expected = Vec::with_capacity(len);
+            let mut key = RgssKey::with_state(RgssVersion::V3, 0x12345678);
+            for (i, &byte) in data[..len].iter().enumerate() {
+                expected.push(byte ^ (key.current() >> ((i % 4) * 8)) as u8);
+                if i % 4 == 3 {
+                    key.step();
+                }
+            }
+
+            let mut actual = data[..len].to_vec();
+            let mut word_key = RgssKey::with_state(RgssVersion::V3, 0x12345678);
+            word_key.apply_content(&mut actual);
+
+            assert_eq!(actual, expected);
+            assert_eq!(word_key.current(), key.current());
+        }
+    }
 }
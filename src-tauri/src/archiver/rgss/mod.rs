@@ -15,30 +15,85 @@
 //! The encryption uses a simple LCG (Linear Congruential Generator):
 //! - V1: state = state * 7 + 3, initial state = 0xDEADCAFE
 //! - V3: state = state * 9 + 3, initial state from archive
+//!
+//! Entry names are stored as raw bytes: UTF-8 in most VX Ace games and
+//! Shift-JIS in Japanese XP/VX games. See [`RgssNameEncoding`].
+//!
+//! Existing archives can be patched in place with [`RgssPatcher`], which
+//! only rewrites the entries that changed.
+//!
+//! Damaged or tampered archives can be inspected with
+//! [`RgssReader::verify`] and opened with [`RgssReadOptions`].
 
 mod key;
+mod patch;
 mod reader;
+mod verify;
 mod version;
 mod writer;
 
 pub use key::RgssKey;
-pub use reader::RgssReader;
+pub use patch::{RgssChangeKind, RgssEntryChange, RgssPatchReport, RgssPatcher};
+pub use reader::{RgssEntryReader, RgssReader};
+pub use verify::{RgssIssue, RgssIssueKind, RgssReadOptions, RgssReport};
 pub use version::RgssVersion;
 pub use writer::RgssWriter;
 
 use std::path::PathBuf;
 
+use crate::archiver::safe_output_path;
+
 /// Magic bytes for RGSS archives
 pub const MAGIC: &[u8; 7] = b"RGSSAD\0";
 
 /// Initial key for V1 archives (XP/VX)
 pub const V1_INITIAL_KEY: u32 = 0xDEADCAFE;
 
+/// Encoding of entry names in an RGSS archive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RgssNameEncoding {
+    /// UTF-8 (VX Ace and most non-Japanese games)
+    #[default]
+    Utf8,
+    /// Shift-JIS (Japanese XP/VX games)
+    ShiftJis,
+}
+
+impl RgssNameEncoding {
+    /// Decode a raw name, trying UTF-8 first and falling back to Shift-JIS
+    ///
+    /// Returns `None` if the name is valid in neither encoding.
+    pub fn decode(raw: &[u8]) -> Option<(String, Self)> {
+        if let Ok(name) = std::str::from_utf8(raw) {
+            return Some((name.to_string(), RgssNameEncoding::Utf8));
+        }
+
+        encoding_rs::SHIFT_JIS
+            .decode_without_bom_handling_and_without_replacement(raw)
+            .map(|name| (name.into_owned(), RgssNameEncoding::ShiftJis))
+    }
+
+    /// Encode a name, returning `None` if it cannot be represented
+    pub fn encode(&self, name: &str) -> Option<Vec<u8>> {
+        match self {
+            RgssNameEncoding::Utf8 => Some(name.as_bytes().to_vec()),
+            RgssNameEncoding::ShiftJis => {
+                let (bytes, _, had_errors) = encoding_rs::SHIFT_JIS.encode(name);
+                (!had_errors).then(|| bytes.into_owned())
+            }
+        }
+    }
+}
+
 /// File entry in an RGSS archive
 #[derive(Debug, Clone)]
 pub struct RgssEntry {
     /// File name (relative path within archive)
     pub name: String,
+    /// Name bytes as stored, written back as is when repacking
+    pub raw_name: Vec<u8>,
+    /// Encoding the name was stored in
+    pub encoding: RgssNameEncoding,
     /// File size in bytes
     pub size: u32,
     /// Offset in the archive file
@@ -49,10 +104,10 @@ pub struct RgssEntry {
 
 impl RgssEntry {
     /// Get the output path for extraction
+    ///
+    /// Names that would escape `base_dir` are rewritten to stay inside it.
     pub fn output_path(&self, base_dir: &std::path::Path) -> PathBuf {
-        // Convert backslashes to forward slashes and normalize the path
-        let normalized_name = self.name.replace('\\', "/");
-        base_dir.join(normalized_name)
+        safe_output_path(base_dir, &self.name)
     }
 }
 
@@ -65,6 +120,8 @@ mod tests {
     fn test_entry_output_path() {
         let entry = RgssEntry {
             name: "Data\\Map001.rxdata".to_string(),
+            raw_name: b"Data\\Map001.rxdata".to_vec(),
+            encoding: RgssNameEncoding::Utf8,
             size: 1024,
             offset: 0,
             key: 0,
@@ -74,4 +131,36 @@ mod tests {
         assert!(output.to_string_lossy().contains("Data"));
         assert!(output.to_string_lossy().contains("Map001.rxdata"));
     }
+
+    #[test]
+    fn test_entry_output_path_escape() {
+        let entry = RgssEntry {
+            name: "..\\..\\Game.exe".to_string(),
+            raw_name: b"..\\..\\Game.exe".to_vec(),
+            encoding: RgssNameEncoding::Utf8,
+            size: 0,
+            offset: 0,
+            key: 0,
+        };
+
+        let output = entry.output_path(Path::new("/output"));
+        assert_eq!(output, Path::new("/output/Game.exe"));
+    }
+
+    #[test]
+    fn test_name_encoding() {
+        let (name, encoding) = RgssNameEncoding::decode(b"Data\\Map001.rxdata").unwrap();
+        assert_eq!(name, "Data\\Map001.rxdata");
+        assert_eq!(encoding, RgssNameEncoding::Utf8);
+
+        let raw = RgssNameEncoding::ShiftJis
+            .encode("Graphics\\Pictures\\タイトル.png")
+            .unwrap();
+        let (name, encoding) = RgssNameEncoding::decode(&raw).unwrap();
+        assert_eq!(name, "Graphics\\Pictures\\タイトル.png");
+        assert_eq!(encoding, RgssNameEncoding::ShiftJis);
+
+        assert!(RgssNameEncoding::decode(b"\xff\xfe\x80").is_none());
+        assert!(RgssNameEncoding::ShiftJis.encode("한국어").is_none());
+    }
 }
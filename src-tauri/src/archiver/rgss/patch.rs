@@ -0,0 +1,651 @@
+//! Incremental RGSS repacking
+//!
+//! [`RgssPatcher`] rewrites an existing archive with a set of replaced or
+//! added entries. The payloads of all other entries are streamed from the
+//! source archive without being decrypted. V3 entries carry their own key.
+//! V1 keys are chained through the record headers, but only the header
+//! lengths advance the chain: existing entries keep their name bytes as
+//! stored, even when they decode in no known encoding, and added entries go
+//! last, so untouched payloads keep their key. A payload is only
+//! re-encrypted if its key does change.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::{
+    RgssEntry, RgssEntryReader, RgssKey, RgssNameEncoding, RgssReader, RgssVersion, MAGIC,
+    V1_INITIAL_KEY,
+};
+use crate::archiver::{normalize_name, ArchiveReader, ArchiverError, ArchiverResult, Operation};
+
+/// How an entry differs from the source archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RgssChangeKind {
+    /// Entry did not exist in the source archive
+    Added,
+    /// Entry data was replaced
+    Modified,
+}
+
+/// Entry changed by a patch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RgssEntryChange {
+    /// File name (relative path within archive)
+    pub name: String,
+    /// Kind of change
+    pub kind: RgssChangeKind,
+    /// Size in the source archive, if the entry existed
+    pub old_size: Option<u32>,
+    /// Size in the patched archive
+    pub new_size: u32,
+}
+
+/// Result of writing a patched archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RgssPatchReport {
+    /// Added and modified entries, in archive order
+    pub changes: Vec<RgssEntryChange>,
+    /// Unchanged entries copied byte for byte
+    pub copied: usize,
+    /// Unchanged entries re-encrypted with a new key
+    pub reencrypted: usize,
+}
+
+/// Payload of an entry in the patched archive
+enum Payload<'a> {
+    /// Encrypted payload of a source entry
+    Source(&'a RgssEntry),
+    /// New plain data
+    Data(&'a [u8]),
+}
+
+/// Entry of the patched archive
+struct PatchRecord<'a> {
+    /// Name, for progress reports
+    archive_name: &'a str,
+    /// Encoded name
+    name: Vec<u8>,
+    /// Plain data size
+    size: u32,
+    /// Where the data comes from
+    payload: Payload<'a>,
+}
+
+/// Rewrites an RGSS archive with replaced or added entries
+pub struct RgssPatcher {
+    /// Archive being patched
+    source: RgssReader,
+    /// Replacement data by archive name (with backslashes), in insertion order
+    replacements: Vec<(String, Vec<u8>)>,
+}
+
+impl RgssPatcher {
+    /// Create a patcher over an opened archive
+    pub fn new(source: RgssReader) -> Self {
+        Self {
+            source,
+            replacements: Vec::new(),
+        }
+    }
+
+    /// Open an archive to patch
+    pub fn open<P: AsRef<Path>>(path: P) -> ArchiverResult<Self> {
+        Ok(Self::new(RgssReader::open(path)?))
+    }
+
+    /// Get the archive being patched
+    pub fn source(&self) -> &RgssReader {
+        &self.source
+    }
+
+    /// Replace or add an entry with in-memory data
+    pub fn replace(&mut self, archive_name: &str, data: Vec<u8>) {
+        let archive_name = archive_name.replace('/', "\\");
+
+        match self
+            .replacements
+            .iter_mut()
+            .find(|(name, _)| *name == archive_name)
+        {
+            Some((_, existing)) => *existing = data,
+            None => self.replacements.push((archive_name, data)),
+        }
+    }
+
+    /// Replace or add an entry with the contents of a file
+    pub fn replace_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        archive_name: &str,
+    ) -> ArchiverResult<()> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Err(ArchiverError::FileNotFound(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+
+        self.replace(archive_name, fs::read(path)?);
+        Ok(())
+    }
+
+    /// Replace or add every file of a directory
+    ///
+    /// Archive names are built the same way as
+    /// [`RgssWriter::add_directory`](crate::archiver::ArchiveWriter::add_directory).
+    pub fn replace_directory<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+    ) -> ArchiverResult<usize> {
+        let dir = dir.as_ref();
+        let mut count = 0;
+
+        if !dir.exists() {
+            return Err(ArchiverError::FileNotFound(
+                dir.to_string_lossy().to_string(),
+            ));
+        }
+
+        for entry in walkdir::WalkDir::new(dir)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+
+            if path.is_file() {
+                let relative_path = path.strip_prefix(dir).map_err(|e| {
+                    ArchiverError::InvalidFormat(format!("Failed to get relative path: {}", e))
+                })?;
+
+                let relative_name = relative_path.to_string_lossy().replace('/', "\\");
+                let archive_name = match base_path {
+                    Some(base) => format!("{}\\{}", base, relative_name),
+                    None => relative_name,
+                };
+
+                self.replace_file(path, &archive_name)?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// List the entries the patch would add or modify, without writing
+    ///
+    /// Replacements identical to the source entry are not reported.
+    pub fn diff(&self) -> ArchiverResult<Vec<RgssEntryChange>> {
+        Ok(self.plan()?.1)
+    }
+
+    /// Build the record list of the patched archive and the changes made
+    fn plan(&self) -> ArchiverResult<(Vec<PatchRecord<'_>>, Vec<RgssEntryChange>)> {
+        let mut records = Vec::new();
+        let mut changes = Vec::new();
+        let mut used = vec![false; self.replacements.len()];
+
+        for entry in self.source.entries() {
+            let name = entry.raw_name.clone();
+            let wanted = normalize_name(&entry.name);
+            let replacement = self
+                .replacements
+                .iter()
+                .position(|(name, _)| normalize_name(name) == wanted);
+
+            let Some(index) = replacement else {
+                records.push(PatchRecord {
+                    archive_name: &entry.name,
+                    name,
+                    size: entry.size,
+                    payload: Payload::Source(entry),
+                });
+                continue;
+            };
+            used[index] = true;
+
+            let data = &self.replacements[index].1;
+            let size = data_size(&entry.name, data)?;
+            if size == entry.size && self.source.extract_to_memory(entry)? == *data {
+                records.push(PatchRecord {
+                    archive_name: &entry.name,
+                    name,
+                    size,
+                    payload: Payload::Source(entry),
+                });
+                continue;
+            }
+
+            changes.push(RgssEntryChange {
+                name: entry.name.clone(),
+                kind: RgssChangeKind::Modified,
+                old_size: Some(entry.size),
+                new_size: size,
+            });
+            records.push(PatchRecord {
+                archive_name: &entry.name,
+                name,
+                size,
+                payload: Payload::Data(data),
+            });
+        }
+
+        // New entries use the encoding of the existing names
+        let encoding = self.source.name_encoding();
+        for ((archive_name, data), _) in self
+            .replacements
+            .iter()
+            .zip(&used)
+            .filter(|(_, used)| !**used)
+        {
+            let size = data_size(archive_name, data)?;
+            changes.push(RgssEntryChange {
+                name: archive_name.clone(),
+                kind: RgssChangeKind::Added,
+                old_size: None,
+                new_size: size,
+            });
+            records.push(PatchRecord {
+                archive_name,
+                name: encode_name(archive_name, encoding)?,
+                size,
+                payload: Payload::Data(data),
+            });
+        }
+
+        Ok((records, changes))
+    }
+
+    /// Write the patched archive
+    ///
+    /// `output_path` may be the source archive itself: the archive is written
+    /// to a temporary file next to it and moved into place on success.
+    pub fn write<P: AsRef<Path>>(&self, output_path: P) -> ArchiverResult<RgssPatchReport> {
+        self.write_with(output_path, &Operation::new())
+    }
+
+    /// Write the patched archive, reporting progress and stopping when
+    /// cancelled
+    ///
+    /// A cancelled write leaves `output_path` as it was.
+    pub fn write_with<P: AsRef<Path>>(
+        &self,
+        output_path: P,
+        operation: &Operation,
+    ) -> ArchiverResult<RgssPatchReport> {
+        let output_path = output_path.as_ref();
+        let (records, changes) = self.plan()?;
+
+        let total_bytes = records.iter().map(|r| r.size as u64).sum();
+        operation.begin(records.len(), total_bytes);
+
+        let temp_path = temp_path(output_path);
+        let result = match self.source.version() {
+            RgssVersion::V1 => self.write_v1(&temp_path, &records, operation),
+            RgssVersion::V3 => self.write_v3(&temp_path, &records, operation),
+        };
+
+        let (copied, reencrypted) = match result {
+            Ok(counts) => counts,
+            Err(e) => {
+                let _ = fs::remove_file(&temp_path);
+                return Err(e);
+            }
+        };
+        fs::rename(&temp_path, output_path)?;
+
+        Ok(RgssPatchReport {
+            changes,
+            copied,
+            reencrypted,
+        })
+    }
+
+    /// Write a V1 archive, returning the copied and re-encrypted counts
+    fn write_v1(
+        &self,
+        path: &Path,
+        records: &[PatchRecord],
+        operation: &Operation,
+    ) -> ArchiverResult<(usize, usize)> {
+        let mut source = BufReader::new(File::open(self.source.path())?);
+        let mut writer = BufWriter::new(File::create(path)?);
+        let mut counts = (0, 0);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[RgssVersion::V1.header_byte()])?;
+
+        let mut key = RgssKey::with_state(RgssVersion::V1, V1_INITIAL_KEY);
+
+        for record in records {
+            let encrypted_len = key.encrypt_int(record.name.len() as u32);
+            writer.write_all(&encrypted_len.to_le_bytes())?;
+            writer.write_all(&key.encrypt_string_v1(&record.name))?;
+            let encrypted_size = key.encrypt_int(record.size);
+            writer.write_all(&encrypted_size.to_le_bytes())?;
+
+            self.write_payload(&mut source, &mut writer, record, key.current(), &mut counts)?;
+            operation.entry_done(record.archive_name, record.size as u64)?;
+        }
+
+        writer.flush()?;
+        Ok(counts)
+    }
+
+    /// Write a V3 archive, returning the copied and re-encrypted counts
+    fn write_v3(
+        &self,
+        path: &Path,
+        records: &[PatchRecord],
+        operation: &Operation,
+    ) -> ArchiverResult<(usize, usize)> {
+        let mut source = BufReader::new(File::open(self.source.path())?);
+        let mut writer = BufWriter::new(File::create(path)?);
+        let mut counts = (0, 0);
+
+        // Keep the initial key of the source archive
+        let mut initial_key_bytes = [0u8; 4];
+        source.seek(SeekFrom::Start(8))?;
+        source.read_exact(&mut initial_key_bytes)?;
+        let initial_key = u32::from_le_bytes(initial_key_bytes);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[RgssVersion::V3.header_byte()])?;
+        writer.write_all(&initial_key_bytes)?;
+
+        let mut key = RgssKey::with_state(RgssVersion::V3, initial_key);
+        key.step();
+
+        // Header (8) + Initial Key (4) + Entry headers + End marker (4)
+        let headers_size: u64 = records.iter().map(|r| 16 + r.name.len() as u64).sum();
+        let mut current_offset = 8 + 4 + headers_size + 4;
+
+        // Source entries keep their key, new data gets one like RgssWriter
+        let mut layout = Vec::with_capacity(records.len());
+        for record in records {
+            let offset = u32::try_from(current_offset)
+                .map_err(|_| ArchiverError::InvalidFormat("Archive exceeds 4 GiB".to_string()))?;
+            let file_key = match record.payload {
+                Payload::Source(entry) => entry.key,
+                Payload::Data(_) => initial_key.wrapping_mul(offset.wrapping_add(1)),
+            };
+            layout.push((offset, file_key));
+            current_offset += record.size as u64;
+        }
+
+        for (record, (offset, file_key)) in records.iter().zip(&layout) {
+            writer.write_all(&key.encrypt_int(*offset).to_le_bytes())?;
+            writer.write_all(&key.encrypt_int(record.size).to_le_bytes())?;
+            writer.write_all(&key.encrypt_int(*file_key).to_le_bytes())?;
+            writer.write_all(&key.encrypt_int(record.name.len() as u32).to_le_bytes())?;
+            writer.write_all(&key.encrypt_string_v3(&record.name))?;
+        }
+
+        writer.write_all(&key.encrypt_int(0).to_le_bytes())?;
+
+        for (record, (_, file_key)) in records.iter().zip(&layout) {
+            self.write_payload(&mut source, &mut writer, record, *file_key, &mut counts)?;
+            operation.entry_done(record.archive_name, record.size as u64)?;
+        }
+
+        writer.flush()?;
+        Ok(counts)
+    }
+
+    /// Write the encrypted payload of a record with the given key
+    fn write_payload<R: Read + Seek, W: Write>(
+        &self,
+        source: &mut R,
+        writer: &mut W,
+        record: &PatchRecord,
+        file_key: u32,
+        counts: &mut (usize, usize),
+    ) -> ArchiverResult<()> {
+        let version = self.source.version();
+
+        let entry = match record.payload {
+            Payload::Data(data) => {
+                let mut key = RgssKey::with_state(version, file_key);
+                writer.write_all(&key.encrypt_content(data))?;
+                return Ok(());
+            }
+            Payload::Source(entry) => entry,
+        };
+
+        source.seek(SeekFrom::Start(entry.offset))?;
+        let mut encrypted = source.take(entry.size as u64);
+
+        let written = if entry.key == file_key {
+            counts.0 += 1;
+            io::copy(&mut encrypted, writer)?
+        } else {
+            // The content cipher is a plain XOR stream, so decrypting with the
+            // old key and encrypting with the new one can be chained
+            counts.1 += 1;
+            let decrypted =
+                RgssEntryReader::new(encrypted, RgssKey::with_state(version, entry.key));
+            let mut reencrypted =
+                RgssEntryReader::new(decrypted, RgssKey::with_state(version, file_key));
+            io::copy(&mut reencrypted, writer)?
+        };
+
+        if written != entry.size as u64 {
+            return Err(ArchiverError::InvalidFormat(format!(
+                "Entry {} exceeds archive size",
+                entry.name
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Encode an entry name for the patched archive
+fn encode_name(name: &str, encoding: RgssNameEncoding) -> ArchiverResult<Vec<u8>> {
+    encoding.encode(name).ok_or_else(|| {
+        ArchiverError::InvalidFormat(format!("Cannot encode {} as {:?}", name, encoding))
+    })
+}
+
+/// Get the size of entry data, which RGSS stores as 32 bits
+fn data_size(name: &str, data: &[u8]) -> ArchiverResult<u32> {
+    u32::try_from(data.len())
+        .map_err(|_| ArchiverError::InvalidFormat(format!("Entry {} exceeds 4 GiB", name)))
+}
+
+/// Get the temporary path a patched archive is written to
+fn temp_path(output_path: &Path) -> PathBuf {
+    let mut file_name = output_path
+        .file_name()
+        .map(|n| n.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".patching");
+    output_path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archiver::rgss::RgssWriter;
+    use crate::archiver::ArchiveWriter;
+    use tempfile::TempDir;
+
+    /// Create an archive with entries `a`, `b` and `c`
+    fn create_archive(dir: &Path, version: RgssVersion) -> PathBuf {
+        let source_dir = dir.join("source");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(source_dir.join("a.txt"), b"first entry").unwrap();
+        fs::write(source_dir.join("b.txt"), b"second").unwrap();
+        fs::write(source_dir.join("c.txt"), b"third entry data").unwrap();
+
+        let archive_path = dir.join("Game.rgssad");
+        let mut writer = RgssWriter::for_version(version).with_v3_key(0x12345678);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            writer
+                .add_file(source_dir.join(name), &format!("Data\\{}", name))
+                .unwrap();
+        }
+        writer.write(&archive_path).unwrap();
+
+        archive_path
+    }
+
+    fn read_all(path: &Path) -> Vec<(String, Vec<u8>)> {
+        let reader = RgssReader::open(path).unwrap();
+        reader
+            .entries()
+            .iter()
+            .map(|e| (e.name.clone(), reader.extract_to_memory(e).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_patch_v1_keeps_untouched_payloads() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = create_archive(temp_dir.path(), RgssVersion::V1);
+
+        let mut patcher = RgssPatcher::open(&archive_path).unwrap();
+        patcher.replace("Data/b.txt", b"second, but longer".to_vec());
+        patcher.replace("Data/d.txt", b"new".to_vec());
+
+        let output_path = temp_dir.path().join("Patched.rgssad");
+        let report = patcher.write(&output_path).unwrap();
+
+        assert_eq!(
+            report.changes,
+            vec![
+                RgssEntryChange {
+                    name: "Data\\b.txt".to_string(),
+                    kind: RgssChangeKind::Modified,
+                    old_size: Some(6),
+                    new_size: 18,
+                },
+                RgssEntryChange {
+                    name: "Data\\d.txt".to_string(),
+                    kind: RgssChangeKind::Added,
+                    old_size: None,
+                    new_size: 3,
+                },
+            ]
+        );
+        assert_eq!(report.copied, 2);
+        assert_eq!(report.reencrypted, 0);
+
+        assert_eq!(
+            read_all(&output_path),
+            vec![
+                ("Data\\a.txt".to_string(), b"first entry".to_vec()),
+                ("Data\\b.txt".to_string(), b"second, but longer".to_vec()),
+                ("Data\\c.txt".to_string(), b"third entry data".to_vec()),
+                ("Data\\d.txt".to_string(), b"new".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_patch_v3_in_place() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = create_archive(temp_dir.path(), RgssVersion::V3);
+
+        let mut patcher = RgssPatcher::open(&archive_path).unwrap();
+        patcher.replace("Data\\a.txt", b"patched first entry".to_vec());
+
+        let report = patcher.write(&archive_path).unwrap();
+        assert_eq!(report.changes.len(), 1);
+        assert_eq!(report.copied, 2);
+        assert_eq!(report.reencrypted, 0);
+        assert!(!temp_path(&archive_path).exists());
+
+        let entries = read_all(&archive_path);
+        assert_eq!(entries[0].1, b"patched first entry");
+        assert_eq!(entries[1].1, b"second");
+        assert_eq!(entries[2].1, b"third entry data");
+    }
+
+    #[test]
+    fn test_cancelled_patch_keeps_archive() {
+        use crate::archiver::{ArchiveProgress, CancellationToken};
+
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = create_archive(temp_dir.path(), RgssVersion::V3);
+        let original = fs::read(&archive_path).unwrap();
+
+        let mut patcher = RgssPatcher::open(&archive_path).unwrap();
+        patcher.replace("Data\\a.txt", b"patched first entry".to_vec());
+
+        let token = CancellationToken::new();
+        let sink_token = token.clone();
+        let operation = Operation::new()
+            .with_token(token)
+            .with_sink(move |p: &ArchiveProgress| {
+                assert_eq!(p.entries_total, 3);
+                if p.entries_done == 2 {
+                    sink_token.cancel();
+                }
+            });
+
+        assert!(matches!(
+            patcher.write_with(&archive_path, &operation),
+            Err(ArchiverError::Cancelled)
+        ));
+        assert!(!temp_path(&archive_path).exists());
+        assert_eq!(fs::read(&archive_path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_identical_replacement_is_not_a_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = create_archive(temp_dir.path(), RgssVersion::V3);
+        let original = fs::read(&archive_path).unwrap();
+
+        let mut patcher = RgssPatcher::open(&archive_path).unwrap();
+        patcher.replace("Data/c.txt", b"third entry data".to_vec());
+        assert!(patcher.diff().unwrap().is_empty());
+
+        let report = patcher.write(&archive_path).unwrap();
+        assert!(report.changes.is_empty());
+        assert_eq!(report.copied, 3);
+        assert_eq!(fs::read(&archive_path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_patch_v1_keeps_undecodable_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("Game.rgssad");
+
+        // The first name is valid in no encoding, and must be written back
+        // as stored so the key chain of later payloads does not shift
+        let mut archive = MAGIC.to_vec();
+        archive.push(RgssVersion::V1.header_byte());
+        let mut key = RgssKey::with_state(RgssVersion::V1, V1_INITIAL_KEY);
+        for (name, data) in [(&b"\xff\xfe"[..], &b"odd"[..]), (b"b.txt", b"untouched")] {
+            archive.extend(key.encrypt_int(name.len() as u32).to_le_bytes());
+            archive.extend(key.encrypt_string_v1(name));
+            archive.extend(key.encrypt_int(data.len() as u32).to_le_bytes());
+            archive
+                .extend(RgssKey::with_state(RgssVersion::V1, key.current()).encrypt_content(data));
+        }
+        fs::write(&archive_path, archive).unwrap();
+
+        let mut patcher = RgssPatcher::open(&archive_path).unwrap();
+        patcher.replace("c.txt", b"added".to_vec());
+        let report = patcher.write(&archive_path).unwrap();
+
+        assert_eq!(report.copied, 2);
+        assert_eq!(report.reencrypted, 0);
+        let reader = RgssReader::open(&archive_path).unwrap();
+        assert_eq!(reader.entries()[0].raw_name, b"\xff\xfe");
+        let entries = read_all(&archive_path);
+        assert_eq!(entries[0].1, b"odd");
+        assert_eq!(entries[1], ("b.txt".to_string(), b"untouched".to_vec()));
+        assert_eq!(entries[2], ("c.txt".to_string(), b"added".to_vec()));
+    }
+}
@@ -1,11 +1,34 @@
 //! RGSS Archive Reader (Unpacker)
+//!
+//! Entries are extracted in parallel: every entry carries its own key, and
+//! all workers share one archive handle through positional reads.
 
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::{BufReader, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Take, Write};
+use std::path::{Path, PathBuf};
 
-use super::{RgssEntry, RgssKey, RgssVersion, V1_INITIAL_KEY};
-use crate::archiver::{ArchiveReader, ArchiverError, ArchiverResult};
+use rayon::prelude::*;
+
+use super::verify::{check_name, find_overlaps, IssueLog};
+use super::{
+    RgssEntry, RgssIssue, RgssIssueKind, RgssKey, RgssNameEncoding, RgssReadOptions, RgssReport,
+    RgssVersion, V1_INITIAL_KEY,
+};
+use crate::archiver::archive::{check_bounds, find_entry};
+use crate::archiver::{
+    normalize_name, Archive, ArchiveEntryInfo, ArchiveFormat, ArchiveReader, ArchiverError,
+    ArchiverResult, ExtractOptions, ExtractReport, Operation,
+};
+
+/// Longest accepted entry name in bytes
+const MAX_NAME_LEN: u32 = 1024;
+
+/// Size of the chunks entries are decrypted in (a multiple of 4)
+const EXTRACT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Whether output paths differing only in case name the same file
+const CASE_INSENSITIVE_OUTPUT: bool = cfg!(any(windows, target_os = "macos"));
 
 /// RGSS Archive Reader for unpacking .rgssad, .rgss2a, and .rgss3a files
 pub struct RgssReader {
@@ -15,12 +38,53 @@ pub struct RgssReader {
     version: RgssVersion,
     /// File entries in the archive
     entries: Vec<RgssEntry>,
+    /// Problems found while reading the archive
+    issues: Vec<RgssIssue>,
 }
 
 impl RgssReader {
+    /// Open an archive with options for damaged or modified archives
+    pub fn open_with<P: AsRef<Path>>(path: P, options: RgssReadOptions) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+        let mut log = IssueLog::new(&options);
+
+        // Detect version
+        let version = if options.tolerant_magic {
+            let (version, modified) = RgssVersion::detect_tolerant(path)?;
+            if modified {
+                log.push(RgssIssue::new(0, None, RgssIssueKind::ModifiedMagic))?;
+            }
+            version
+        } else {
+            RgssVersion::detect(path)?
+        };
+
+        // Read entries based on version
+        let entries = match version {
+            RgssVersion::V1 => Self::read_v1(path, &mut log)?,
+            RgssVersion::V3 => Self::read_v3(path, &mut log)?,
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            version,
+            entries,
+            issues: log.issues,
+        })
+    }
+
+    /// Check an archive and report every problem found
+    ///
+    /// Reads in salvage mode with a tolerant magic, so the report covers the
+    /// whole archive instead of stopping at the first damaged record.
+    pub fn verify<P: AsRef<Path>>(path: P) -> ArchiverResult<RgssReport> {
+        Self::open_with(path, RgssReadOptions::salvage())?.report()
+    }
+
     /// Read a V1 format archive (RPG Maker XP/VX)
-    fn read_v1(path: &Path) -> ArchiverResult<Vec<RgssEntry>> {
+    fn read_v1(path: &Path, log: &mut IssueLog) -> ArchiverResult<Vec<RgssEntry>> {
         let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
         let mut reader = BufReader::new(file);
         let mut entries = Vec::new();
 
@@ -30,40 +94,58 @@ impl RgssReader {
         // Initialize key
         let mut key = RgssKey::with_state(RgssVersion::V1, V1_INITIAL_KEY);
 
+        // Records are chained through the key, so reading stops at the
+        // first structurally damaged record even when salvaging
         loop {
             // Check if we've reached the end of the file
-            let current_pos = reader.stream_position()?;
-            let end_pos = reader.seek(SeekFrom::End(0))?;
-
-            if current_pos >= end_pos {
+            let record_offset = reader.stream_position()?;
+            if record_offset >= file_len {
                 break;
             }
 
-            reader.seek(SeekFrom::Start(current_pos))?;
-
             // Read encrypted name length
             let mut len_bytes = [0u8; 4];
-            if reader.read_exact(&mut len_bytes).is_err() {
+            if !read_exact_or_eof(&mut reader, &mut len_bytes)? {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    None,
+                    RgssIssueKind::TruncatedRecord,
+                ))?;
                 break;
             }
-            let name_len = key.decrypt_int(u32::from_le_bytes(len_bytes)) as usize;
+            let name_len = key.decrypt_int(u32::from_le_bytes(len_bytes));
 
             // Sanity check for name length
-            if name_len == 0 || name_len > 1024 {
+            if name_len == 0 || name_len > MAX_NAME_LEN {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    None,
+                    RgssIssueKind::BadNameLength { length: name_len },
+                ))?;
                 break;
             }
 
             // Read encrypted name
-            let mut name_bytes = vec![0u8; name_len];
-            if reader.read_exact(&mut name_bytes).is_err() {
+            let mut name_bytes = vec![0u8; name_len as usize];
+            if !read_exact_or_eof(&mut reader, &mut name_bytes)? {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    None,
+                    RgssIssueKind::TruncatedRecord,
+                ))?;
                 break;
             }
             let decrypted_name = key.decrypt_string_v1(&name_bytes);
-            let name = String::from_utf8_lossy(&decrypted_name).to_string();
+            let (name, encoding, name_issue) = decode_name(&decrypted_name);
 
             // Read encrypted size
             let mut size_bytes = [0u8; 4];
-            if reader.read_exact(&mut size_bytes).is_err() {
+            if !read_exact_or_eof(&mut reader, &mut size_bytes)? {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    Some(&name),
+                    RgssIssueKind::TruncatedRecord,
+                ))?;
                 break;
             }
             let size = key.decrypt_int(u32::from_le_bytes(size_bytes));
@@ -74,11 +156,29 @@ impl RgssReader {
             // Save the current key state for this file
             let file_key = key.current();
 
+            if offset + size as u64 > file_len {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    Some(&name),
+                    RgssIssueKind::DataPastEof {
+                        data_offset: offset,
+                        size,
+                    },
+                ))?;
+                break;
+            }
+
             // Skip file data
             reader.seek(SeekFrom::Current(size as i64))?;
 
+            if let Some(kind) = name_issue {
+                log.push(RgssIssue::new(record_offset, Some(&name), kind))?;
+            }
+
             entries.push(RgssEntry {
                 name,
+                raw_name: decrypted_name,
+                encoding,
                 size,
                 offset,
                 key: file_key,
@@ -89,8 +189,9 @@ impl RgssReader {
     }
 
     /// Read a V3 format archive (RPG Maker VX Ace)
-    fn read_v3(path: &Path) -> ArchiverResult<Vec<RgssEntry>> {
+    fn read_v3(path: &Path, log: &mut IssueLog) -> ArchiverResult<Vec<RgssEntry>> {
         let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
         let mut reader = BufReader::new(file);
         let mut entries = Vec::new();
 
@@ -99,7 +200,10 @@ impl RgssReader {
 
         // Read initial key
         let mut initial_key_bytes = [0u8; 4];
-        reader.read_exact(&mut initial_key_bytes)?;
+        if !read_exact_or_eof(&mut reader, &mut initial_key_bytes)? {
+            log.push(RgssIssue::new(8, None, RgssIssueKind::TruncatedRecord))?;
+            return Ok(entries);
+        }
         let initial_key = u32::from_le_bytes(initial_key_bytes);
 
         // Initialize and step key
@@ -107,9 +211,16 @@ impl RgssReader {
         key.step();
 
         loop {
+            let record_offset = reader.stream_position()?;
+
             // Read encrypted offset
             let mut offset_bytes = [0u8; 4];
-            if reader.read_exact(&mut offset_bytes).is_err() {
+            if !read_exact_or_eof(&mut reader, &mut offset_bytes)? {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    None,
+                    RgssIssueKind::TruncatedRecord,
+                ))?;
                 break;
             }
             let offset = key.decrypt_int(u32::from_le_bytes(offset_bytes));
@@ -119,52 +230,197 @@ impl RgssReader {
                 break;
             }
 
-            // Read encrypted size
-            let mut size_bytes = [0u8; 4];
-            reader.read_exact(&mut size_bytes)?;
-            let size = key.decrypt_int(u32::from_le_bytes(size_bytes));
-
-            // Read encrypted file key
-            let mut file_key_bytes = [0u8; 4];
-            reader.read_exact(&mut file_key_bytes)?;
-            let file_key = key.decrypt_int(u32::from_le_bytes(file_key_bytes));
-
-            // Read encrypted name length
-            let mut name_len_bytes = [0u8; 4];
-            reader.read_exact(&mut name_len_bytes)?;
-            let name_len = key.decrypt_int(u32::from_le_bytes(name_len_bytes)) as usize;
+            // Read encrypted size, file key and name length
+            let mut record = [0u8; 12];
+            if !read_exact_or_eof(&mut reader, &mut record)? {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    None,
+                    RgssIssueKind::TruncatedRecord,
+                ))?;
+                break;
+            }
+            let size = key.decrypt_int(u32::from_le_bytes([
+                record[0], record[1], record[2], record[3],
+            ]));
+            let file_key = key.decrypt_int(u32::from_le_bytes([
+                record[4], record[5], record[6], record[7],
+            ]));
+            let name_len = key.decrypt_int(u32::from_le_bytes([
+                record[8], record[9], record[10], record[11],
+            ]));
+
+            // The name length decides where the next record starts
+            if name_len == 0 || name_len > MAX_NAME_LEN {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    None,
+                    RgssIssueKind::BadNameLength { length: name_len },
+                ))?;
+                break;
+            }
 
             // Read encrypted name
-            let mut name_bytes = vec![0u8; name_len];
-            reader.read_exact(&mut name_bytes)?;
+            let mut name_bytes = vec![0u8; name_len as usize];
+            if !read_exact_or_eof(&mut reader, &mut name_bytes)? {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    None,
+                    RgssIssueKind::TruncatedRecord,
+                ))?;
+                break;
+            }
             let decrypted_name = key.decrypt_string_v3(&name_bytes);
-            let name = String::from_utf8_lossy(&decrypted_name).to_string();
+            let (name, encoding, name_issue) = decode_name(&decrypted_name);
+
+            // Records are independent, so a bad data range only skips this entry
+            if offset as u64 + size as u64 > file_len {
+                log.push(RgssIssue::new(
+                    record_offset,
+                    Some(&name),
+                    RgssIssueKind::DataPastEof {
+                        data_offset: offset as u64,
+                        size,
+                    },
+                ))?;
+                continue;
+            }
+
+            if let Some(kind) = name_issue {
+                log.push(RgssIssue::new(record_offset, Some(&name), kind))?;
+            }
 
             entries.push(RgssEntry {
                 name,
+                raw_name: decrypted_name,
+                encoding,
                 size,
                 offset: offset as u64,
                 key: file_key,
             });
         }
 
+        for issue in find_overlaps(&entries) {
+            log.push(issue)?;
+        }
+
         Ok(entries)
     }
 
     /// Extract a single entry to a byte vector
     pub fn extract_to_memory(&self, entry: &RgssEntry) -> ArchiverResult<Vec<u8>> {
         let file = File::open(&self.path)?;
-        let mut reader = BufReader::new(file);
+        check_bounds(
+            &entry.name,
+            entry.offset,
+            entry.size as u64,
+            file.metadata()?.len(),
+        )?;
 
-        reader.seek(SeekFrom::Start(entry.offset))?;
+        let mut data = vec![0u8; entry.size as usize];
+        read_exact_at(&file, &mut data, entry.offset)?;
+
+        let mut key = RgssKey::with_state(self.version, entry.key);
+        key.apply_content(&mut data);
+
+        Ok(data)
+    }
+
+    /// Extract entries in parallel, sharing one archive handle
+    ///
+    /// Returns the number of files written.
+    fn extract_parallel<'a>(
+        &self,
+        entries: impl Iterator<Item = &'a RgssEntry>,
+        output_dir: &Path,
+        operation: &Operation,
+    ) -> ArchiverResult<usize> {
+        let file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+
+        // When entries share an output file (paths differing only in case on
+        // Windows and macOS), the last one wins, as it would when extracting
+        // one after another
+        let mut last_by_path = HashMap::new();
+        for entry in entries {
+            let key = output_key(entry.output_path(output_dir), CASE_INSENSITIVE_OUTPUT);
+            last_by_path.insert(key, entry);
+        }
+        let targets: Vec<&RgssEntry> = last_by_path.into_values().collect();
+
+        let total_bytes = targets.iter().map(|e| e.size as u64).sum();
+        operation.begin(targets.len(), total_bytes);
 
-        let mut encrypted_data = vec![0u8; entry.size as usize];
-        reader.read_exact(&mut encrypted_data)?;
+        let result = targets.par_iter().try_for_each(|entry| {
+            self.extract_entry_at(&file, file_len, entry, output_dir, operation)?;
+            operation.entry_done(&entry.name, entry.size as u64)
+        });
+        operation.finish(result)?;
+
+        Ok(targets.len())
+    }
+
+    /// Decrypt an entry to a file, reading from a shared archive handle
+    fn extract_entry_at(
+        &self,
+        file: &File,
+        file_len: u64,
+        entry: &RgssEntry,
+        output_dir: &Path,
+        operation: &Operation,
+    ) -> ArchiverResult<()> {
+        check_bounds(&entry.name, entry.offset, entry.size as u64, file_len)?;
+        operation.check()?;
+
+        let output_path = entry.output_path(output_dir);
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        operation.track_output(output_path.clone());
+        let mut writer = BufWriter::new(File::create(&output_path)?);
 
         let mut key = RgssKey::with_state(self.version, entry.key);
-        let decrypted_data = key.decrypt_content(&encrypted_data);
+        let mut buf = vec![0u8; EXTRACT_CHUNK_SIZE.min(entry.size as usize)];
+        let mut offset = entry.offset;
+        let mut remaining = entry.size as usize;
+
+        // Chunks are a multiple of 4 bytes, so the key carries over cleanly
+        while remaining > 0 {
+            operation.check()?;
+
+            let chunk = &mut buf[..remaining.min(EXTRACT_CHUNK_SIZE)];
+            read_exact_at(file, chunk, offset)?;
+            key.apply_content(chunk);
+            writer.write_all(chunk)?;
+
+            offset += chunk.len() as u64;
+            remaining -= chunk.len();
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Open a streaming reader that decrypts an entry on the fly
+    pub fn entry_reader(
+        &self,
+        entry: &RgssEntry,
+    ) -> ArchiverResult<RgssEntryReader<Take<BufReader<File>>>> {
+        let file = File::open(&self.path)?;
+        check_bounds(
+            &entry.name,
+            entry.offset,
+            entry.size as u64,
+            file.metadata()?.len(),
+        )?;
+
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(entry.offset))?;
 
-        Ok(decrypted_data)
+        Ok(RgssEntryReader::new(
+            reader.take(entry.size as u64),
+            RgssKey::with_state(self.version, entry.key),
+        ))
     }
 
     /// Get the archive version
@@ -176,28 +432,44 @@ impl RgssReader {
     pub fn path(&self) -> &Path {
         &self.path
     }
+
+    /// Get the encoding used by the entry names
+    ///
+    /// Archives with any Shift-JIS name are reported as Shift-JIS, so the
+    /// writer can repack them with the same names.
+    pub fn name_encoding(&self) -> RgssNameEncoding {
+        if self
+            .entries
+            .iter()
+            .any(|e| e.encoding == RgssNameEncoding::ShiftJis)
+        {
+            RgssNameEncoding::ShiftJis
+        } else {
+            RgssNameEncoding::Utf8
+        }
+    }
+
+    /// Get the problems found while reading the archive
+    pub fn issues(&self) -> &[RgssIssue] {
+        &self.issues
+    }
+
+    /// Build a verification report from the problems found while reading
+    pub fn report(&self) -> ArchiverResult<RgssReport> {
+        Ok(RgssReport {
+            version: self.version.header_byte(),
+            file_size: fs::metadata(&self.path)?.len(),
+            entry_count: self.entries.len(),
+            issues: self.issues.clone(),
+        })
+    }
 }
 
 impl ArchiveReader for RgssReader {
     type Entry = RgssEntry;
 
     fn open<P: AsRef<Path>>(path: P) -> ArchiverResult<Self> {
-        let path = path.as_ref();
-
-        // Detect version
-        let version = RgssVersion::detect(path)?;
-
-        // Read entries based on version
-        let entries = match version {
-            RgssVersion::V1 => Self::read_v1(path)?,
-            RgssVersion::V3 => Self::read_v3(path)?,
-        };
-
-        Ok(Self {
-            path: path.to_path_buf(),
-            version,
-            entries,
-        })
+        Self::open_with(path, RgssReadOptions::default())
     }
 
     fn entries(&self) -> &[RgssEntry] {
@@ -205,15 +477,7 @@ impl ArchiveReader for RgssReader {
     }
 
     fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> ArchiverResult<usize> {
-        let output_dir = output_dir.as_ref();
-        let mut count = 0;
-
-        for entry in &self.entries {
-            self.extract_entry(&entry.name, output_dir)?;
-            count += 1;
-        }
-
-        Ok(count)
+        self.extract_parallel(self.entries.iter(), output_dir.as_ref(), &Operation::new())
     }
 
     fn extract_entry<P: AsRef<Path>>(&self, entry_name: &str, output_dir: P) -> ArchiverResult<()> {
@@ -226,20 +490,164 @@ impl ArchiveReader for RgssReader {
             .find(|e| e.name == entry_name)
             .ok_or_else(|| ArchiverError::FileNotFound(entry_name.to_string()))?;
 
-        // Extract to memory
-        let data = self.extract_to_memory(entry)?;
+        let file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+        self.extract_entry_at(&file, file_len, entry, output_dir, &Operation::new())
+    }
+}
 
-        // Write to file
-        let output_path = entry.output_path(output_dir);
+/// Decode an entry name, returning the problem with it if any
+///
+/// Names valid in neither encoding are kept lossily so they can still be
+/// reported and extracted.
+fn decode_name(raw: &[u8]) -> (String, RgssNameEncoding, Option<RgssIssueKind>) {
+    match RgssNameEncoding::decode(raw) {
+        Some((name, encoding)) => {
+            let issue = check_name(&name);
+            (name, encoding, issue)
+        }
+        None => (
+            String::from_utf8_lossy(raw).into_owned(),
+            RgssNameEncoding::Utf8,
+            Some(RgssIssueKind::UndecodableName),
+        ),
+    }
+}
 
-        // Create parent directories
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)?;
+/// Get the key identifying the file an output path is written to
+fn output_key(path: PathBuf, case_insensitive: bool) -> PathBuf {
+    if case_insensitive {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path
+    }
+}
+
+/// Read exactly `buf.len()` bytes at `offset` without moving a file cursor
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+/// Read exactly `buf.len()` bytes at `offset` without moving a file cursor
+#[cfg(windows)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.seek_read(&mut buf[filled..], offset + filled as u64) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
         }
+    }
+    Ok(())
+}
 
-        fs::write(&output_path, data)?;
+/// Read exactly `buf.len()` bytes, returning `false` at the end of the file
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
 
-        Ok(())
+impl Archive for RgssReader {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Rgss(self.version)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn list_entries(&self) -> Vec<ArchiveEntryInfo> {
+        self.entries
+            .iter()
+            .map(|e| ArchiveEntryInfo {
+                name: normalize_name(&e.name),
+                size: e.size as u64,
+                compressed: false,
+            })
+            .collect()
+    }
+
+    fn open_entry(&self, name: &str) -> ArchiverResult<Box<dyn Read + Send + '_>> {
+        let entry = find_entry(&self.entries, name, |e| &e.name)?;
+        Ok(Box::new(self.entry_reader(entry)?))
+    }
+
+    fn extract_with(
+        &self,
+        output_dir: &Path,
+        options: &ExtractOptions,
+        operation: &Operation,
+    ) -> ArchiverResult<ExtractReport> {
+        let report = self.plan_extract(output_dir, options)?;
+
+        let selected: HashSet<&str> = report.entries.iter().map(|e| e.name.as_str()).collect();
+        let entries = self
+            .entries
+            .iter()
+            .filter(|e| selected.contains(normalize_name(&e.name).as_str()));
+        self.extract_parallel(entries, output_dir, operation)?;
+
+        Ok(report)
+    }
+}
+
+/// Streaming decryptor for the contents of an RGSS entry
+///
+/// Equivalent to [`RgssKey::decrypt_content`], but works on data of any
+/// size without buffering it.
+pub struct RgssEntryReader<R> {
+    /// Encrypted data
+    inner: R,
+    /// Key for the current 4-byte block
+    key: RgssKey,
+    /// Position within the current 4-byte block
+    position: usize,
+}
+
+impl<R: Read> RgssEntryReader<R> {
+    /// Wrap encrypted data, starting with the entry's key
+    pub fn new(inner: R, key: RgssKey) -> Self {
+        Self {
+            inner,
+            key,
+            position: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for RgssEntryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let data = &mut buf[..read];
+
+        // Finish a word left over from the previous read byte by byte
+        let mut start = 0;
+        while self.position != 0 && start < data.len() {
+            data[start] ^= (self.key.current() >> (self.position * 8)) as u8;
+            start += 1;
+
+            self.position += 1;
+            if self.position == 4 {
+                self.position = 0;
+                self.key.step();
+            }
+        }
+
+        if start < data.len() {
+            let rest = &mut data[start..];
+            self.key.apply_content(rest);
+            self.position = rest.len() % 4;
+        }
+
+        Ok(read)
     }
 }
 
@@ -251,6 +659,8 @@ mod tests {
     fn test_rgss_entry_output_path() {
         let entry = RgssEntry {
             name: "Data\\Scripts.rxdata".to_string(),
+            raw_name: b"Data\\Scripts.rxdata".to_vec(),
+            encoding: RgssNameEncoding::Utf8,
             size: 1024,
             offset: 0,
             key: 0,
@@ -259,4 +669,322 @@ mod tests {
         let output = entry.output_path(Path::new("/tmp/output"));
         assert!(output.to_string_lossy().contains("Data"));
     }
+
+    #[test]
+    fn test_entry_reader_matches_decrypt_content() {
+        let data: Vec<u8> = (0..1021u32).map(|i| (i * 31 % 251) as u8).collect();
+        let key = RgssKey::with_state(RgssVersion::V3, 0x1234_5678);
+        let mut whole = key;
+        let expected = whole.decrypt_content(&data);
+
+        // Read in odd-sized chunks to cross the 4-byte key blocks
+        let mut reader = RgssEntryReader::new(&data[..], key);
+        let mut output = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let read = reader.read(&mut buf).unwrap();
+            if read == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..read]);
+        }
+
+        assert_eq!(output, expected);
+    }
+
+    /// Pack small files into an archive and return its path
+    fn pack(dir: &Path, version: RgssVersion, files: &[(&str, &[u8])]) -> std::path::PathBuf {
+        use crate::archiver::rgss::RgssWriter;
+        use crate::archiver::ArchiveWriter;
+
+        let mut writer = RgssWriter::for_version(version);
+        for (name, data) in files {
+            let source = dir.join(name.replace('\\', "_"));
+            fs::write(&source, data).unwrap();
+            writer.add_file(&source, name).unwrap();
+        }
+
+        let path = dir.join(format!("Game.{}", version.extension()));
+        writer.write(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_truncated_v1_strict_and_salvage() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = pack(
+            temp_dir.path(),
+            RgssVersion::V1,
+            &[
+                ("Data\\Actors.rxdata", b"actors"),
+                ("Data\\Map001.rxdata", b"map one"),
+            ],
+        );
+
+        let data = fs::read(&path).unwrap();
+        fs::write(&path, &data[..data.len() - 3]).unwrap();
+
+        assert!(matches!(
+            RgssReader::open(&path),
+            Err(ArchiverError::InvalidFormat(_))
+        ));
+
+        let reader = RgssReader::open_with(&path, RgssReadOptions::salvage()).unwrap();
+        assert_eq!(reader.entries().len(), 1);
+        assert_eq!(
+            reader.extract_to_memory(&reader.entries()[0]).unwrap(),
+            b"actors"
+        );
+        assert_eq!(reader.issues().len(), 1);
+        assert_eq!(
+            reader.issues()[0].name.as_deref(),
+            Some("Data\\Map001.rxdata")
+        );
+        assert!(matches!(
+            reader.issues()[0].kind,
+            RgssIssueKind::DataPastEof { size: 7, .. }
+        ));
+
+        let report = RgssReader::verify(&path).unwrap();
+        assert_eq!(report.version, 1);
+        assert_eq!(report.entry_count, 1);
+        assert!(report.has_fatal_issues());
+    }
+
+    #[test]
+    fn test_v3_salvage_skips_damaged_record() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = pack(
+            temp_dir.path(),
+            RgssVersion::V3,
+            &[
+                ("Data\\Actors.rvdata2", b"actors"),
+                ("Data\\Items.rvdata2", b"items"),
+                ("Data\\Map001.rvdata2", b"map one"),
+            ],
+        );
+
+        // Rewrite the size of the second record to point past the end
+        let mut data = fs::read(&path).unwrap();
+        let le =
+            |b: &[u8], pos: usize| u32::from_le_bytes([b[pos], b[pos + 1], b[pos + 2], b[pos + 3]]);
+        let mut key = RgssKey::with_state(RgssVersion::V3, le(&data, 8));
+        key.step();
+        let mut pos = 12;
+        for record in 0..2 {
+            key.decrypt_int(le(&data, pos));
+            if record == 1 {
+                let size = key.current() ^ 0x7fff_ffff;
+                data[pos + 4..pos + 8].copy_from_slice(&size.to_le_bytes());
+            }
+            key.decrypt_int(le(&data, pos + 4));
+            key.decrypt_int(le(&data, pos + 8));
+            let name_len = key.decrypt_int(le(&data, pos + 12)) as usize;
+            pos += 16 + name_len;
+        }
+        fs::write(&path, data).unwrap();
+
+        assert!(RgssReader::open(&path).is_err());
+
+        let reader = RgssReader::open_with(&path, RgssReadOptions::salvage()).unwrap();
+        let names: Vec<&str> = reader.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Data\\Actors.rvdata2", "Data\\Map001.rvdata2"]);
+        assert_eq!(
+            reader.extract_to_memory(&reader.entries()[1]).unwrap(),
+            b"map one"
+        );
+        assert_eq!(reader.issues().len(), 1);
+        assert_eq!(
+            reader.issues()[0].name.as_deref(),
+            Some("Data\\Items.rvdata2")
+        );
+    }
+
+    #[test]
+    fn test_tolerant_magic() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = pack(
+            temp_dir.path(),
+            RgssVersion::V3,
+            &[("Data\\System.rvdata2", b"sys")],
+        );
+
+        let mut data = fs::read(&path).unwrap();
+        data[..6].copy_from_slice(b"HAYOAD");
+        fs::write(&path, data).unwrap();
+
+        assert!(RgssReader::open(&path).is_err());
+
+        let options = RgssReadOptions {
+            tolerant_magic: true,
+            ..Default::default()
+        };
+        let reader = RgssReader::open_with(&path, options).unwrap();
+        assert_eq!(reader.entries().len(), 1);
+        assert_eq!(reader.issues()[0].kind, RgssIssueKind::ModifiedMagic);
+
+        let report = reader.report().unwrap();
+        assert!(!report.is_clean());
+        assert!(!report.has_fatal_issues());
+    }
+
+    #[test]
+    fn test_clean_archive_report() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = pack(
+            temp_dir.path(),
+            RgssVersion::V1,
+            &[("Data\\Scripts.rxdata", b"x")],
+        );
+
+        let report = RgssReader::verify(&path).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.entry_count, 1);
+        assert_eq!(report.file_size, fs::metadata(&path).unwrap().len());
+    }
+
+    #[test]
+    fn test_parallel_extract_matches_sequential() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let contents: Vec<Vec<u8>> = (0..24u32)
+            .map(|i| (0..i * 37 + 1).map(|j| (j * 7 + i) as u8).collect())
+            .collect();
+        let names: Vec<String> = (0..24).map(|i| format!("Data\\File{:02}.bin", i)).collect();
+        let files: Vec<(&str, &[u8])> = names
+            .iter()
+            .map(String::as_str)
+            .zip(contents.iter().map(Vec::as_slice))
+            .collect();
+
+        for version in [RgssVersion::V1, RgssVersion::V3] {
+            let path = pack(temp_dir.path(), version, &files);
+            let output_dir = temp_dir.path().join(format!("out_{}", version.extension()));
+
+            let reader = RgssReader::open(&path).unwrap();
+            assert_eq!(reader.extract_all(&output_dir).unwrap(), 24);
+
+            for (entry, expected) in reader.entries().iter().zip(&contents) {
+                assert_eq!(&reader.extract_to_memory(entry).unwrap(), expected);
+                assert_eq!(&fs::read(entry.output_path(&output_dir)).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_extract_all_counts_written_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = pack(
+            temp_dir.path(),
+            RgssVersion::V3,
+            &[
+                ("Data\\Map001.rvdata2", b"first"),
+                ("Data\\Map001.rvdata2", b"second"),
+                ("Data\\Map002.rvdata2", b"other"),
+            ],
+        );
+        let output_dir = temp_dir.path().join("out");
+
+        let reader = RgssReader::open(&path).unwrap();
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 2);
+        assert_eq!(
+            fs::read(output_dir.join("Data/Map001.rvdata2")).unwrap(),
+            b"second"
+        );
+    }
+
+    #[test]
+    fn test_output_key_folds_case() {
+        let entry = |name: &str| RgssEntry {
+            name: name.to_string(),
+            raw_name: name.as_bytes().to_vec(),
+            encoding: RgssNameEncoding::Utf8,
+            size: 0,
+            offset: 0,
+            key: 0,
+        };
+        let output_dir = Path::new("/tmp/output");
+        let upper = entry("Data\\Map001.rxdata").output_path(output_dir);
+        let lower = entry("data\\map001.rxdata").output_path(output_dir);
+
+        assert_ne!(
+            output_key(upper.clone(), false),
+            output_key(lower.clone(), false)
+        );
+        assert_eq!(output_key(upper, true), output_key(lower, true));
+    }
+
+    #[test]
+    fn test_extract_with_filter() {
+        use crate::archiver::OverwritePolicy;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = pack(
+            temp_dir.path(),
+            RgssVersion::V3,
+            &[
+                ("Data\\Actors.rvdata2", b"actors"),
+                ("Data\\Map001.rvdata2", b"map one"),
+                ("Graphics\\Pictures\\title.png", b"title"),
+                ("Audio\\BGM\\theme.ogg", b"theme"),
+            ],
+        );
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir_all(output_dir.join("Data")).unwrap();
+        fs::write(output_dir.join("Data/Actors.rvdata2"), b"translated").unwrap();
+
+        let reader = RgssReader::open(&path).unwrap();
+        let options = ExtractOptions::default()
+            .include(["Data/**", "Graphics/Pictures/*"])
+            .with_overwrite(OverwritePolicy::SkipExisting);
+
+        let plan = reader.plan_extract(&output_dir, &options).unwrap();
+        assert_eq!(plan.total_bytes, 12);
+        assert_eq!(plan.skipped, 1);
+        assert!(!output_dir.join("Data/Map001.rvdata2").exists());
+
+        let report = reader
+            .extract_with(&output_dir, &options, &Operation::new())
+            .unwrap();
+        assert_eq!(report, plan);
+        assert_eq!(
+            fs::read(output_dir.join("Data/Actors.rvdata2")).unwrap(),
+            b"translated"
+        );
+        assert_eq!(
+            fs::read(output_dir.join("Data/Map001.rvdata2")).unwrap(),
+            b"map one"
+        );
+        assert!(output_dir.join("Graphics/Pictures/title.png").exists());
+        assert!(!output_dir.join("Audio").exists());
+    }
+
+    #[test]
+    fn test_cancelled_extract_removes_output() {
+        use crate::archiver::{ArchiveProgress, CancellationToken};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let names: Vec<String> = (0..16).map(|i| format!("Data\\File{:02}.bin", i)).collect();
+        let files: Vec<(&str, &[u8])> = names.iter().map(|n| (n.as_str(), &b"data"[..])).collect();
+        let path = pack(temp_dir.path(), RgssVersion::V3, &files);
+        let output_dir = temp_dir.path().join("out");
+
+        let token = CancellationToken::new();
+        let sink_token = token.clone();
+        let operation = Operation::new()
+            .with_token(token)
+            .with_sink(move |_: &ArchiveProgress| sink_token.cancel());
+
+        let reader = RgssReader::open(&path).unwrap();
+        assert!(matches!(
+            reader.extract_all_with(&output_dir, &operation),
+            Err(ArchiverError::Cancelled)
+        ));
+
+        let written = walkdir::WalkDir::new(&output_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .count();
+        assert_eq!(written, 0);
+    }
 }
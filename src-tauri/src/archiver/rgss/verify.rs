@@ -0,0 +1,256 @@
+//! RGSS archive integrity checks
+//!
+//! Reading an RGSS archive collects every problem found in the entry
+//! table as an [`RgssIssue`]. Structural damage (truncated records,
+//! impossible name lengths, data past the end of the file) fails a normal
+//! open; with [`RgssReadOptions::salvage`] the damaged records are skipped
+//! instead and the remaining entries stay readable.
+
+use std::path::{Component, Path};
+
+use serde::Serialize;
+
+use super::RgssEntry;
+use crate::archiver::{ArchiverError, ArchiverResult};
+
+/// Options for opening RGSS archives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RgssReadOptions {
+    /// Skip damaged records and keep reading instead of failing
+    pub salvage: bool,
+    /// Accept a modified magic as long as the version byte is valid
+    pub tolerant_magic: bool,
+}
+
+impl RgssReadOptions {
+    /// Options that recover as much of a damaged archive as possible
+    pub fn salvage() -> Self {
+        Self {
+            salvage: true,
+            tolerant_magic: true,
+        }
+    }
+}
+
+/// Kind of problem found in an RGSS archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RgssIssueKind {
+    /// The header magic is not "RGSSAD\0"
+    ModifiedMagic,
+    /// A record ends before all of its fields could be read
+    TruncatedRecord,
+    /// A name length is zero or implausibly large
+    BadNameLength { length: u32 },
+    /// A name is empty after decoding, contains control characters, or
+    /// points outside the extraction directory
+    BadName,
+    /// A name is not valid in any supported encoding
+    UndecodableName,
+    /// The entry data extends past the end of the file
+    DataPastEof { data_offset: u64, size: u32 },
+    /// The entry data overlaps the data of another entry (V3)
+    OverlappingData { other: String },
+}
+
+impl RgssIssueKind {
+    /// Whether the record cannot be read at all
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            RgssIssueKind::TruncatedRecord
+                | RgssIssueKind::BadNameLength { .. }
+                | RgssIssueKind::DataPastEof { .. }
+        )
+    }
+}
+
+/// Problem found in an RGSS archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RgssIssue {
+    /// Offset of the damaged record in the archive
+    pub offset: u64,
+    /// Entry name, if it could be read
+    pub name: Option<String>,
+    /// What is wrong with the record
+    pub kind: RgssIssueKind,
+}
+
+impl RgssIssue {
+    /// Create an issue at a record offset
+    pub fn new(offset: u64, name: Option<&str>, kind: RgssIssueKind) -> Self {
+        Self {
+            offset,
+            name: name.map(str::to_string),
+            kind,
+        }
+    }
+
+    /// Convert the issue into an archiver error
+    pub fn into_error(self) -> ArchiverError {
+        let name = self.name.map(|n| format!(" ({})", n)).unwrap_or_default();
+        ArchiverError::InvalidFormat(format!(
+            "Damaged RGSS record at {:#x}{}: {:?}",
+            self.offset, name, self.kind
+        ))
+    }
+}
+
+/// Verification report of an RGSS archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RgssReport {
+    /// Archive version byte (1 or 3)
+    pub version: u8,
+    /// Size of the archive file in bytes
+    pub file_size: u64,
+    /// Number of readable entries
+    pub entry_count: usize,
+    /// Problems found, in the order they were found
+    pub issues: Vec<RgssIssue>,
+}
+
+impl RgssReport {
+    /// Check whether no problems were found
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Check whether any record had to be skipped
+    pub fn has_fatal_issues(&self) -> bool {
+        self.issues.iter().any(|i| i.kind.is_fatal())
+    }
+}
+
+/// Collects issues while reading, failing on fatal ones unless salvaging
+pub(super) struct IssueLog {
+    /// Whether damaged records are skipped
+    salvage: bool,
+    /// Issues found so far
+    pub(super) issues: Vec<RgssIssue>,
+}
+
+impl IssueLog {
+    pub(super) fn new(options: &RgssReadOptions) -> Self {
+        Self {
+            salvage: options.salvage,
+            issues: Vec::new(),
+        }
+    }
+
+    /// Record an issue; fatal issues are errors unless salvaging
+    pub(super) fn push(&mut self, issue: RgssIssue) -> ArchiverResult<()> {
+        if issue.kind.is_fatal() && !self.salvage {
+            return Err(issue.into_error());
+        }
+        self.issues.push(issue);
+        Ok(())
+    }
+}
+
+/// Check a decoded entry name, returning the problem if any
+pub(super) fn check_name(name: &str) -> Option<RgssIssueKind> {
+    let normalized = name.replace('\\', "/");
+    let escapes = Path::new(&normalized)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+
+    if name.trim().is_empty() || name.chars().any(char::is_control) || escapes {
+        return Some(RgssIssueKind::BadName);
+    }
+
+    None
+}
+
+/// Find entries whose data overlaps an earlier entry's data
+pub(super) fn find_overlaps(entries: &[RgssEntry]) -> Vec<RgssIssue> {
+    let mut sorted: Vec<&RgssEntry> = entries.iter().filter(|e| e.size > 0).collect();
+    sorted.sort_by_key(|e| e.offset);
+
+    let mut issues = Vec::new();
+    let mut previous: Option<&RgssEntry> = None;
+
+    for entry in sorted {
+        if let Some(prev) = previous {
+            if entry.offset < prev.offset + prev.size as u64 {
+                issues.push(RgssIssue::new(
+                    entry.offset,
+                    Some(&entry.name),
+                    RgssIssueKind::OverlappingData {
+                        other: prev.name.clone(),
+                    },
+                ));
+                // Keep the entry reaching furthest as the reference
+                if entry.offset + entry.size as u64 <= prev.offset + prev.size as u64 {
+                    continue;
+                }
+            }
+        }
+        previous = Some(entry);
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archiver::rgss::RgssNameEncoding;
+
+    fn entry(name: &str, offset: u64, size: u32) -> RgssEntry {
+        RgssEntry {
+            name: name.to_string(),
+            raw_name: name.as_bytes().to_vec(),
+            encoding: RgssNameEncoding::Utf8,
+            size,
+            offset,
+            key: 0,
+        }
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert_eq!(check_name("Data\\Map001.rvdata2"), None);
+        assert_eq!(check_name("..\\evil.dll"), Some(RgssIssueKind::BadName));
+        assert_eq!(check_name("/etc/passwd"), Some(RgssIssueKind::BadName));
+        assert_eq!(check_name("a\0b"), Some(RgssIssueKind::BadName));
+    }
+
+    #[test]
+    fn test_find_overlaps() {
+        let entries = vec![
+            entry("a", 100, 50),
+            entry("b", 150, 10),
+            entry("c", 155, 10),
+            entry("empty", 120, 0),
+        ];
+
+        let issues = find_overlaps(&entries);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].name.as_deref(), Some("c"));
+        assert_eq!(
+            issues[0].kind,
+            RgssIssueKind::OverlappingData {
+                other: "b".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_issue_log_strict_and_salvage() {
+        let fatal = RgssIssue::new(8, None, RgssIssueKind::TruncatedRecord);
+        let warning = RgssIssue::new(8, Some("a"), RgssIssueKind::UndecodableName);
+
+        let mut strict = IssueLog::new(&RgssReadOptions::default());
+        assert!(strict.push(warning.clone()).is_ok());
+        assert!(matches!(
+            strict.push(fatal.clone()),
+            Err(ArchiverError::InvalidFormat(_))
+        ));
+
+        let mut salvage = IssueLog::new(&RgssReadOptions::salvage());
+        assert!(salvage.push(fatal).is_ok());
+        assert_eq!(salvage.issues.len(), 1);
+    }
+}
@@ -45,6 +45,27 @@ impl RgssVersion {
         }
     }
 
+    /// Detect the RGSS version, accepting a modified magic
+    ///
+    /// Some games patch the "RGSSAD" magic to stop casual unpacking. The
+    /// version byte is still required to be valid. Returns the version and
+    /// whether the magic was modified.
+    pub fn detect_tolerant<P: AsRef<Path>>(path: P) -> Result<(Self, bool), ArchiverError> {
+        let mut file = File::open(&path)?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+
+        let modified = &header[0..7] != MAGIC;
+        match header[7] {
+            1 => Ok((RgssVersion::V1, modified)),
+            3 => Ok((RgssVersion::V3, modified)),
+            _ if modified => Err(ArchiverError::InvalidFormat(
+                "Not a valid RGSS archive: invalid magic and version".to_string(),
+            )),
+            v => Err(ArchiverError::UnsupportedVersion(v)),
+        }
+    }
+
     /// Get the file extension for this version
     pub fn extension(&self) -> &'static str {
         match self {
@@ -136,4 +157,25 @@ mod tests {
         let result = RgssVersion::detect(file.path());
         assert!(matches!(result, Err(ArchiverError::UnsupportedVersion(2))));
     }
+
+    #[test]
+    fn test_detect_tolerant() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"HAYOAD\0\x03").unwrap();
+        file.flush().unwrap();
+
+        assert!(RgssVersion::detect(file.path()).is_err());
+        assert_eq!(
+            RgssVersion::detect_tolerant(file.path()).unwrap(),
+            (RgssVersion::V3, true)
+        );
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"HAYOAD\0\x07").unwrap();
+        file.flush().unwrap();
+        assert!(matches!(
+            RgssVersion::detect_tolerant(file.path()),
+            Err(ArchiverError::InvalidFormat(_))
+        ));
+    }
 }
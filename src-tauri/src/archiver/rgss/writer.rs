@@ -4,8 +4,8 @@ use std::fs::File;
 use std::io::{BufWriter, Read, Write};
 use std::path::Path;
 
-use super::{RgssKey, RgssVersion, MAGIC, V1_INITIAL_KEY};
-use crate::archiver::{ArchiveWriter, ArchiverError, ArchiverResult};
+use super::{RgssKey, RgssNameEncoding, RgssVersion, MAGIC, V1_INITIAL_KEY};
+use crate::archiver::{ArchiveWriter, ArchiverError, ArchiverResult, Operation};
 
 /// File entry to be packed
 #[derive(Debug)]
@@ -24,6 +24,8 @@ pub struct RgssWriter {
     entries: Vec<PackEntry>,
     /// Initial key for V3 archives (optional, random if not set)
     v3_initial_key: Option<u32>,
+    /// Encoding of entry names
+    name_encoding: RgssNameEncoding,
 }
 
 impl RgssWriter {
@@ -33,6 +35,7 @@ impl RgssWriter {
             version,
             entries: Vec::new(),
             v3_initial_key: None,
+            name_encoding: RgssNameEncoding::default(),
         }
     }
 
@@ -43,8 +46,39 @@ impl RgssWriter {
         self
     }
 
+    /// Set the encoding of entry names (UTF-8 by default)
+    ///
+    /// Use [`RgssReader::name_encoding`](super::RgssReader::name_encoding)
+    /// to repack an archive with the encoding it was read with.
+    pub fn with_name_encoding(mut self, encoding: RgssNameEncoding) -> Self {
+        self.name_encoding = encoding;
+        self
+    }
+
+    /// Encode all entry names, failing before anything is written
+    fn encode_names(&self) -> ArchiverResult<Vec<Vec<u8>>> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                self.name_encoding
+                    .encode(&entry.archive_name)
+                    .ok_or_else(|| {
+                        ArchiverError::InvalidFormat(format!(
+                            "Cannot encode {} as {:?}",
+                            entry.archive_name, self.name_encoding
+                        ))
+                    })
+            })
+            .collect()
+    }
+
     /// Write V1 format archive
-    fn write_v1<P: AsRef<Path>>(&self, output_path: P) -> ArchiverResult<()> {
+    fn write_v1<P: AsRef<Path>>(
+        &self,
+        output_path: P,
+        names: &[Vec<u8>],
+        operation: &Operation,
+    ) -> ArchiverResult<()> {
         let file = File::create(output_path)?;
         let mut writer = BufWriter::new(file);
 
@@ -55,9 +89,8 @@ impl RgssWriter {
         // Initialize key
         let mut key = RgssKey::with_state(RgssVersion::V1, V1_INITIAL_KEY);
 
-        for entry in &self.entries {
+        for (entry, name_bytes) in self.entries.iter().zip(names) {
             // Encrypt and write name length
-            let name_bytes = entry.archive_name.as_bytes();
             let encrypted_len = key.encrypt_int(name_bytes.len() as u32);
             writer.write_all(&encrypted_len.to_le_bytes())?;
 
@@ -73,6 +106,8 @@ impl RgssWriter {
             let mut content_key = RgssKey::with_state(RgssVersion::V1, key.current());
             let encrypted_data = content_key.encrypt_content(&entry.data);
             writer.write_all(&encrypted_data)?;
+
+            operation.entry_done(&entry.archive_name, entry.data.len() as u64)?;
         }
 
         writer.flush()?;
@@ -80,7 +115,12 @@ impl RgssWriter {
     }
 
     /// Write V3 format archive
-    fn write_v3<P: AsRef<Path>>(&self, output_path: P) -> ArchiverResult<()> {
+    fn write_v3<P: AsRef<Path>>(
+        &self,
+        output_path: P,
+        names: &[Vec<u8>],
+        operation: &Operation,
+    ) -> ArchiverResult<()> {
         let file = File::create(output_path)?;
         let mut writer = BufWriter::new(file);
 
@@ -113,9 +153,9 @@ impl RgssWriter {
 
         // Calculate entry header sizes
         let mut entry_headers_size: u64 = 0;
-        for entry in &self.entries {
+        for name_bytes in names {
             // offset(4) + size(4) + key(4) + name_len(4) + name
-            entry_headers_size += 16 + entry.archive_name.len() as u64;
+            entry_headers_size += 16 + name_bytes.len() as u64;
         }
 
         // Calculate data offsets
@@ -132,9 +172,7 @@ impl RgssWriter {
         }
 
         // Write entry headers
-        for (i, entry) in self.entries.iter().enumerate() {
-            let name_bytes = entry.archive_name.as_bytes();
-
+        for (i, (entry, name_bytes)) in self.entries.iter().zip(names).enumerate() {
             // Encrypt and write offset
             let encrypted_offset = key.encrypt_int(entry_offsets[i]);
             writer.write_all(&encrypted_offset.to_le_bytes())?;
@@ -165,6 +203,8 @@ impl RgssWriter {
             let mut content_key = RgssKey::with_state(RgssVersion::V3, entry_keys[i]);
             let encrypted_data = content_key.encrypt_content(&entry.data);
             writer.write_all(&encrypted_data)?;
+
+            operation.entry_done(&entry.archive_name, entry.data.len() as u64)?;
         }
 
         writer.flush()?;
@@ -206,9 +246,17 @@ impl ArchiveWriter for RgssWriter {
         &mut self,
         dir: P,
         base_path: Option<&str>,
+    ) -> ArchiverResult<usize> {
+        self.add_directory_with(dir, base_path, &Operation::new())
+    }
+
+    fn add_directory_with<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+        operation: &Operation,
     ) -> ArchiverResult<usize> {
         let dir = dir.as_ref();
-        let mut count = 0;
 
         if !dir.exists() {
             return Err(ArchiverError::FileNotFound(
@@ -216,6 +264,9 @@ impl ArchiveWriter for RgssWriter {
             ));
         }
 
+        // List the files first so progress has totals
+        let mut files = Vec::new();
+        let mut total_bytes = 0;
         for entry in walkdir::WalkDir::new(dir)
             .follow_links(true)
             .into_iter()
@@ -238,27 +289,50 @@ impl ArchiveWriter for RgssWriter {
                     None => relative_path.to_string_lossy().replace('/', "\\"),
                 };
 
-                self.add_file(path, &archive_name)?;
-                count += 1;
+                total_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                files.push((path.to_path_buf(), archive_name));
             }
         }
 
-        Ok(count)
+        operation.begin(files.len(), total_bytes);
+        for (path, archive_name) in &files {
+            self.add_file(path, archive_name)?;
+            let size = self.entries.last().map_or(0, |e| e.data.len() as u64);
+            operation.entry_done(archive_name, size)?;
+        }
+
+        Ok(files.len())
     }
 
     fn write<P: AsRef<Path>>(self, output_path: P) -> ArchiverResult<()> {
-        match self.version {
-            RgssVersion::V1 => self.write_v1(output_path),
-            RgssVersion::V3 => self.write_v3(output_path),
-        }
+        self.write_with(output_path, &Operation::new())
+    }
+
+    fn write_with<P: AsRef<Path>>(
+        self,
+        output_path: P,
+        operation: &Operation,
+    ) -> ArchiverResult<()> {
+        let output_path = output_path.as_ref();
+        let names = self.encode_names()?;
+
+        let total_bytes = self.entries.iter().map(|e| e.data.len() as u64).sum();
+        operation.begin(self.entries.len(), total_bytes);
+        operation.track_output(output_path.to_path_buf());
+
+        let result = match self.version {
+            RgssVersion::V1 => self.write_v1(output_path, &names, operation),
+            RgssVersion::V3 => self.write_v3(output_path, &names, operation),
+        };
+        operation.finish(result)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::RgssReader;
     use super::*;
     use crate::archiver::ArchiveReader;
-    use super::super::RgssReader;
     use std::fs;
     use std::io::Write as IoWrite;
     use tempfile::TempDir;
@@ -322,4 +396,91 @@ mod tests {
         let extracted = reader.extract_to_memory(&reader.entries()[0]).unwrap();
         assert_eq!(extracted, b"Hello, RGSS3!");
     }
+
+    #[test]
+    fn test_shift_jis_names_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let test_file = temp_dir.path().join("title.png");
+        fs::write(&test_file, b"picture").unwrap();
+
+        for version in [RgssVersion::V1, RgssVersion::V3] {
+            let archive_path = temp_dir.path().join(format!("sjis_{:?}.rgssad", version));
+            let mut writer =
+                RgssWriter::for_version(version).with_name_encoding(RgssNameEncoding::ShiftJis);
+            writer
+                .add_file(&test_file, "Graphics\\Pictures\\タイトル.png")
+                .unwrap();
+            writer.write(&archive_path).unwrap();
+
+            let reader = RgssReader::open(&archive_path).unwrap();
+            let entry = &reader.entries()[0];
+            assert_eq!(entry.name, "Graphics\\Pictures\\タイトル.png");
+            assert_eq!(entry.encoding, RgssNameEncoding::ShiftJis);
+            assert_eq!(reader.name_encoding(), RgssNameEncoding::ShiftJis);
+            assert!(reader.issues().is_empty());
+            assert_eq!(reader.extract_to_memory(entry).unwrap(), b"picture");
+        }
+    }
+
+    #[test]
+    fn test_unencodable_name_fails() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let test_file = temp_dir.path().join("test.txt");
+        fs::write(&test_file, b"text").unwrap();
+
+        let mut writer =
+            RgssWriter::for_version(RgssVersion::V3).with_name_encoding(RgssNameEncoding::ShiftJis);
+        writer.add_file(&test_file, "Data\\한국어.txt").unwrap();
+
+        assert!(matches!(
+            writer.write(temp_dir.path().join("test.rgss3a")),
+            Err(ArchiverError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_cancelled_write_removes_archive() {
+        use crate::archiver::{ArchiveProgress, CancellationToken};
+        use std::sync::{Arc, Mutex};
+
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        for i in 0..4 {
+            fs::write(data_dir.join(format!("file{}.txt", i)), b"data").unwrap();
+        }
+
+        let mut writer = RgssWriter::for_version(RgssVersion::V3);
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let sink_updates = Arc::clone(&updates);
+        let operation = Operation::new()
+            .with_sink(move |p: &ArchiveProgress| sink_updates.lock().unwrap().push(p.clone()));
+        assert_eq!(
+            writer
+                .add_directory_with(&data_dir, Some("Data"), &operation)
+                .unwrap(),
+            4
+        );
+        assert_eq!(updates.lock().unwrap().last().unwrap().bytes_done, 16);
+
+        // Cancel after the second entry is written
+        let token = CancellationToken::new();
+        let sink_token = token.clone();
+        let operation = Operation::new()
+            .with_token(token)
+            .with_sink(move |p: &ArchiveProgress| {
+                if p.entries_done == 2 {
+                    sink_token.cancel();
+                }
+            });
+
+        let archive_path = temp_dir.path().join("test.rgss3a");
+        assert!(matches!(
+            writer.write_with(&archive_path, &operation),
+            Err(ArchiverError::Cancelled)
+        ));
+        assert!(!archive_path.exists());
+    }
 }
@@ -0,0 +1,208 @@
+//! Ren'Py archive module
+//!
+//! Ren'Py games ship their scripts, images and audio in `.rpa` archives in
+//! the `game` folder.
+//!
+//! ## Archive Format
+//!
+//! ```text
+//! Header      "RPA-3.0 <index offset:016x> <key:08x>\n"
+//!             or "RPA-2.0 <index offset:016x>\n"
+//! Data        file contents
+//! Index       zlib-compressed pickle of
+//!             {name: [(offset, length, prefix), ...]}
+//! ```
+//!
+//! In version 3.0, offsets and lengths are XORed with the key. The optional
+//! `prefix` holds the first bytes of the file, stored in the index instead
+//! of the data. The index pickle is read by a minimal loader (see
+//! [`pickle`]) that only accepts plain data, never calls.
+
+pub mod pickle;
+mod reader;
+
+pub use reader::RpaReader;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::archiver::safe_output_path;
+
+/// Magic of version 3.0 archives
+pub const MAGIC_V3: &[u8] = b"RPA-3.0 ";
+
+/// Magic of version 2.0 archives
+pub const MAGIC_V2: &[u8] = b"RPA-2.0 ";
+
+/// RPA archive version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpaVersion {
+    /// RPA-2.0, offsets stored in plain
+    V2,
+    /// RPA-3.0, offsets XORed with a key
+    V3,
+}
+
+/// File entry in an RPA archive
+#[derive(Debug, Clone)]
+pub struct RpaEntry {
+    /// File name (relative to the `game` folder, `/`-separated)
+    pub name: String,
+    /// Size in bytes, including the prefix
+    pub size: u64,
+    /// Offset of the data after the prefix
+    pub offset: u64,
+    /// First bytes of the file, stored in the index
+    pub prefix: Vec<u8>,
+}
+
+impl RpaEntry {
+    /// Get the output path for extraction
+    pub fn output_path(&self, base_dir: &Path) -> PathBuf {
+        safe_output_path(base_dir, &self.name)
+    }
+}
+
+/// Check whether a file is an RPA archive
+pub fn is_rpa_archive<P: AsRef<Path>>(path: P) -> bool {
+    let mut header = [0u8; 8];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .is_ok()
+        && (header == MAGIC_V3 || header == MAGIC_V2)
+}
+
+/// Find the archives of a Ren'Py game folder
+///
+/// Archives are read from `game/` in name order.
+pub fn find_archives(game_dir: &Path) -> Vec<PathBuf> {
+    let Ok(dir) = std::fs::read_dir(game_dir.join("game")) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<PathBuf> = dir
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension().is_some_and(|e| e.eq_ignore_ascii_case("rpa")) && is_rpa_archive(p)
+        })
+        .collect();
+    archives.sort();
+    archives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pickle::tests::dump;
+    use super::pickle::Value;
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::fs;
+    use std::io::Write;
+
+    /// File of a fixture archive
+    pub(super) struct TestFile<'a> {
+        pub name: &'a str,
+        pub data: &'a [u8],
+        /// Number of leading bytes stored in the index
+        pub prefix: usize,
+    }
+
+    /// Build an RPA archive, with a key for version 3.0
+    pub(super) fn build_archive(key: Option<u32>, files: &[TestFile]) -> Vec<u8> {
+        let header_len = 51;
+        let mut data = Vec::new();
+        let mut index = Vec::new();
+        let xor = key.unwrap_or(0) as i64;
+
+        for file in files {
+            let offset = (header_len + data.len()) as i64;
+            data.extend_from_slice(&file.data[file.prefix..]);
+            index.push((
+                Value::Str(file.name.to_string()),
+                Value::List(vec![Value::Tuple(vec![
+                    Value::Int(offset ^ xor),
+                    Value::Int(file.data.len() as i64 ^ xor),
+                    Value::Bytes(file.data[..file.prefix].to_vec()),
+                ])]),
+            ));
+        }
+
+        let index_offset = header_len + data.len();
+        let mut header = match key {
+            Some(key) => format!("RPA-3.0 {:016x} {:08x}\n", index_offset, key),
+            None => format!("RPA-2.0 {:016x}\n", index_offset),
+        }
+        .into_bytes();
+        header.resize(header_len, 0);
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&dump(&Value::Dict(index))).unwrap();
+
+        header.extend(data);
+        header.extend(encoder.finish().unwrap());
+        header
+    }
+
+    #[test]
+    fn test_is_rpa_archive() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let files = [TestFile {
+            name: "script.rpy",
+            data: b"label start:",
+            prefix: 0,
+        }];
+
+        let v3 = temp_dir.path().join("archive.rpa");
+        fs::write(&v3, build_archive(Some(0x42), &files)).unwrap();
+        assert!(is_rpa_archive(&v3));
+
+        let v2 = temp_dir.path().join("old.rpa");
+        fs::write(&v2, build_archive(None, &files)).unwrap();
+        assert!(is_rpa_archive(&v2));
+
+        let other = temp_dir.path().join("script.rpy");
+        fs::write(&other, b"RPA").unwrap();
+        assert!(!is_rpa_archive(&other));
+    }
+
+    #[test]
+    fn test_find_archives() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let game = root.join("game");
+        fs::create_dir_all(&game).unwrap();
+
+        let script = [TestFile {
+            name: "script.rpy",
+            data: b"label start:\n    \"Hello\"\n",
+            prefix: 0,
+        }];
+        let images = [TestFile {
+            name: "images/bg.png",
+            data: b"\x89PNG image",
+            prefix: 4,
+        }];
+        fs::write(
+            game.join("scripts.rpa"),
+            build_archive(Some(0xDEADBEEF), &script),
+        )
+        .unwrap();
+        fs::write(game.join("images.rpa"), build_archive(None, &images)).unwrap();
+        fs::write(game.join("broken.rpa"), b"not an archive").unwrap();
+
+        let archives = find_archives(root);
+        assert_eq!(
+            archives,
+            vec![game.join("images.rpa"), game.join("scripts.rpa")]
+        );
+
+        let vfs = crate::archiver::vfs::GameVfs::open_game_dir(root).unwrap();
+        assert_eq!(
+            vfs.read("game/script.rpy").unwrap(),
+            b"label start:\n    \"Hello\"\n"
+        );
+        assert_eq!(vfs.read("game/images/bg.png").unwrap(), b"\x89PNG image");
+    }
+}
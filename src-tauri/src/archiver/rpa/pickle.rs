@@ -0,0 +1,516 @@
+//! Minimal Python pickle loader for RPA indexes
+//!
+//! RPA indexes are a pickled `dict` of lists of tuples, written with
+//! protocol 2 by Ren'Py 7 (Python 2) and a higher protocol by Ren'Py 8.
+//! Only the opcodes needed for plain containers, numbers and strings are
+//! supported, plus the `_codecs.encode` call Python 3 emits for `bytes`
+//! pickled with protocol 2.
+
+use crate::archiver::{ArchiverError, ArchiverResult};
+
+/// Value of a pickle
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    None,
+    Bool(bool),
+    Int(i64),
+    /// Python 2 `str` or Python 3 `bytes`
+    Bytes(Vec<u8>),
+    /// Python `unicode` / `str`
+    Str(String),
+    List(Vec<Value>),
+    Tuple(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    /// Module and name of a global, only used as a call target
+    Global(String, String),
+}
+
+impl Value {
+    /// Get an integer
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(value) => Some(*value),
+            Value::Bool(value) => Some(*value as i64),
+            _ => None,
+        }
+    }
+
+    /// Get string contents as bytes (UTF-8 for `Str`)
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            Value::Str(text) => Some(text.as_bytes()),
+            _ => None,
+        }
+    }
+
+    /// Get the items of a list or tuple
+    pub fn as_items(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) | Value::Tuple(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Stack item: a value or a mark
+enum Item {
+    Value(Value),
+    Mark,
+}
+
+/// Pickle virtual machine
+struct Machine<'a> {
+    data: &'a [u8],
+    pos: usize,
+    stack: Vec<Item>,
+    memo: Vec<Option<Value>>,
+}
+
+impl<'a> Machine<'a> {
+    fn bytes(&mut self, len: usize) -> ArchiverResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| corrupt("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> ArchiverResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> ArchiverResult<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> ArchiverResult<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn line(&mut self) -> ArchiverResult<&'a str> {
+        let len = self.data[self.pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| corrupt("unterminated line"))?;
+        let line = self.bytes(len)?;
+        self.pos += 1;
+        std::str::from_utf8(line).map_err(|_| corrupt("invalid text argument"))
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(Item::Value(value));
+    }
+
+    fn pop(&mut self) -> ArchiverResult<Value> {
+        match self.stack.pop() {
+            Some(Item::Value(value)) => Ok(value),
+            _ => Err(corrupt("stack underflow")),
+        }
+    }
+
+    fn top(&mut self) -> ArchiverResult<&mut Value> {
+        match self.stack.last_mut() {
+            Some(Item::Value(value)) => Ok(value),
+            _ => Err(corrupt("stack underflow")),
+        }
+    }
+
+    /// Pop the values above the topmost mark, and the mark
+    fn pop_mark(&mut self) -> ArchiverResult<Vec<Value>> {
+        let mark = self
+            .stack
+            .iter()
+            .rposition(|item| matches!(item, Item::Mark))
+            .ok_or_else(|| corrupt("missing mark"))?;
+        let values = self
+            .stack
+            .drain(mark + 1..)
+            .map(|item| match item {
+                Item::Value(value) => value,
+                Item::Mark => unreachable!(),
+            })
+            .collect();
+        self.stack.pop();
+        Ok(values)
+    }
+
+    fn memo_put(&mut self, index: usize) -> ArchiverResult<()> {
+        let value = match self.stack.last() {
+            Some(Item::Value(value)) => value.clone(),
+            _ => return Err(corrupt("stack underflow")),
+        };
+        if index > self.data.len() {
+            return Err(corrupt("memo index out of range"));
+        }
+        if self.memo.len() <= index {
+            self.memo.resize(index + 1, None);
+        }
+        self.memo[index] = Some(value);
+        Ok(())
+    }
+
+    fn memo_get(&mut self, index: usize) -> ArchiverResult<()> {
+        let value = self
+            .memo
+            .get(index)
+            .cloned()
+            .flatten()
+            .ok_or_else(|| corrupt("missing memo entry"))?;
+        self.push(value);
+        Ok(())
+    }
+
+    fn string(&mut self, len: usize) -> ArchiverResult<Value> {
+        let bytes = self.bytes(len)?;
+        let text = std::str::from_utf8(bytes).map_err(|_| corrupt("invalid UTF-8 string"))?;
+        Ok(Value::Str(text.to_string()))
+    }
+
+    fn run(mut self) -> ArchiverResult<Value> {
+        loop {
+            let opcode = self.u8()?;
+            match opcode {
+                // PROTO
+                0x80 => {
+                    self.u8()?;
+                }
+                // FRAME
+                0x95 => {
+                    self.u64()?;
+                }
+                b'.' => return self.pop(),
+                b'(' => self.stack.push(Item::Mark),
+                b'N' => self.push(Value::None),
+                0x88 => self.push(Value::Bool(true)),
+                0x89 => self.push(Value::Bool(false)),
+
+                // Integers
+                b'J' => {
+                    let value = self.u32()? as i32;
+                    self.push(Value::Int(value as i64));
+                }
+                b'K' => {
+                    let value = self.u8()?;
+                    self.push(Value::Int(value as i64));
+                }
+                b'M' => {
+                    let b = self.bytes(2)?;
+                    self.push(Value::Int(u16::from_le_bytes([b[0], b[1]]) as i64));
+                }
+                0x8A => {
+                    let len = self.u8()? as usize;
+                    let bytes = self.bytes(len)?;
+                    if len > 8 {
+                        return Err(corrupt("integer too large"));
+                    }
+                    let mut value = 0i64;
+                    for (i, &byte) in bytes.iter().enumerate() {
+                        value |= (byte as i64) << (8 * i);
+                    }
+                    // Sign-extend
+                    if len > 0 && len < 8 && bytes[len - 1] & 0x80 != 0 {
+                        value -= 1i64 << (8 * len);
+                    }
+                    self.push(Value::Int(value));
+                }
+                b'I' | b'L' => {
+                    let line = self.line()?;
+                    let value = match line.trim_end_matches('L') {
+                        "00" => Value::Bool(false),
+                        "01" => Value::Bool(true),
+                        digits => {
+                            Value::Int(digits.parse().map_err(|_| corrupt("invalid integer"))?)
+                        }
+                    };
+                    self.push(value);
+                }
+
+                // Strings
+                b'X' => {
+                    let len = self.u32()? as usize;
+                    let value = self.string(len)?;
+                    self.push(value);
+                }
+                0x8C => {
+                    let len = self.u8()? as usize;
+                    let value = self.string(len)?;
+                    self.push(value);
+                }
+                0x8D => {
+                    let len = self.u64()? as usize;
+                    let value = self.string(len)?;
+                    self.push(value);
+                }
+                b'T' | b'B' => {
+                    let len = self.u32()? as usize;
+                    let bytes = self.bytes(len)?.to_vec();
+                    self.push(Value::Bytes(bytes));
+                }
+                b'U' | b'C' => {
+                    let len = self.u8()? as usize;
+                    let bytes = self.bytes(len)?.to_vec();
+                    self.push(Value::Bytes(bytes));
+                }
+                0x8E => {
+                    let len = self.u64()? as usize;
+                    let bytes = self.bytes(len)?.to_vec();
+                    self.push(Value::Bytes(bytes));
+                }
+
+                // Containers
+                b'}' => self.push(Value::Dict(Vec::new())),
+                b']' => self.push(Value::List(Vec::new())),
+                b')' => self.push(Value::Tuple(Vec::new())),
+                b't' => {
+                    let items = self.pop_mark()?;
+                    self.push(Value::Tuple(items));
+                }
+                0x85..=0x87 => {
+                    let len = (opcode - 0x84) as usize;
+                    let mut items = (0..len)
+                        .map(|_| self.pop())
+                        .collect::<ArchiverResult<Vec<_>>>()?;
+                    items.reverse();
+                    self.push(Value::Tuple(items));
+                }
+                b'a' => {
+                    let value = self.pop()?;
+                    match self.top()? {
+                        Value::List(items) => items.push(value),
+                        _ => return Err(corrupt("append to a non-list")),
+                    }
+                }
+                b'e' => {
+                    let values = self.pop_mark()?;
+                    match self.top()? {
+                        Value::List(items) => items.extend(values),
+                        _ => return Err(corrupt("append to a non-list")),
+                    }
+                }
+                b's' => {
+                    let value = self.pop()?;
+                    let key = self.pop()?;
+                    match self.top()? {
+                        Value::Dict(items) => items.push((key, value)),
+                        _ => return Err(corrupt("set item of a non-dict")),
+                    }
+                }
+                b'u' => {
+                    let values = self.pop_mark()?;
+                    if values.len() % 2 != 0 {
+                        return Err(corrupt("odd number of dict items"));
+                    }
+                    let mut values = values.into_iter();
+                    match self.top()? {
+                        Value::Dict(items) => {
+                            while let (Some(key), Some(value)) = (values.next(), values.next()) {
+                                items.push((key, value));
+                            }
+                        }
+                        _ => return Err(corrupt("set item of a non-dict")),
+                    }
+                }
+
+                // Memo
+                b'q' => {
+                    let index = self.u8()? as usize;
+                    self.memo_put(index)?;
+                }
+                b'r' => {
+                    let index = self.u32()? as usize;
+                    self.memo_put(index)?;
+                }
+                0x94 => {
+                    let index = self.memo.len();
+                    self.memo_put(index)?;
+                }
+                b'h' => {
+                    let index = self.u8()? as usize;
+                    self.memo_get(index)?;
+                }
+                b'j' => {
+                    let index = self.u32()? as usize;
+                    self.memo_get(index)?;
+                }
+
+                // Globals and calls
+                b'c' => {
+                    let module = self.line()?.to_string();
+                    let name = self.line()?.to_string();
+                    self.push(Value::Global(module, name));
+                }
+                0x93 => {
+                    let name = self.pop()?;
+                    let module = self.pop()?;
+                    match (module, name) {
+                        (Value::Str(module), Value::Str(name)) => {
+                            self.push(Value::Global(module, name))
+                        }
+                        _ => return Err(corrupt("invalid global")),
+                    }
+                }
+                b'R' => {
+                    let args = self.pop()?;
+                    let callable = self.pop()?;
+                    let value = reduce(callable, args)?;
+                    self.push(value);
+                }
+
+                other => {
+                    return Err(corrupt(&format!(
+                        "unsupported opcode {:#04x} at offset {:#x}",
+                        other,
+                        self.pos - 1
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// Evaluate the only call RPA indexes contain: `_codecs.encode(text, "latin1")`
+fn reduce(callable: Value, args: Value) -> ArchiverResult<Value> {
+    let is_encode = matches!(&callable, Value::Global(module, name)
+        if module == "_codecs" && name == "encode");
+    match (is_encode, args.as_items()) {
+        (true, Some([Value::Str(text), Value::Str(encoding)]))
+            if encoding.eq_ignore_ascii_case("latin1")
+                || encoding.eq_ignore_ascii_case("latin-1") =>
+        {
+            // Python 3 pickles bytes as their latin-1 decoding
+            text.chars()
+                .map(|c| u8::try_from(c as u32).map_err(|_| corrupt("invalid latin-1 text")))
+                .collect::<ArchiverResult<Vec<u8>>>()
+                .map(Value::Bytes)
+        }
+        _ => Err(corrupt("unsupported call")),
+    }
+}
+
+/// Load a pickled value
+pub fn load(data: &[u8]) -> ArchiverResult<Value> {
+    Machine {
+        data,
+        pos: 0,
+        stack: Vec::new(),
+        memo: Vec::new(),
+    }
+    .run()
+}
+
+fn corrupt(reason: &str) -> ArchiverError {
+    ArchiverError::InvalidFormat(format!("Invalid RPA index pickle: {}", reason))
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Pickle a value with protocol 2 opcodes
+    pub(in crate::archiver::rpa) fn dump(value: &Value) -> Vec<u8> {
+        let mut out = vec![0x80, 2];
+        write(&mut out, value);
+        out.push(b'.');
+        out
+    }
+
+    fn write(out: &mut Vec<u8>, value: &Value) {
+        match value {
+            Value::None => out.push(b'N'),
+            Value::Bool(true) => out.push(0x88),
+            Value::Bool(false) => out.push(0x89),
+            Value::Int(value) => match i32::try_from(*value) {
+                Ok(small) => {
+                    out.push(b'J');
+                    out.extend_from_slice(&small.to_le_bytes());
+                }
+                Err(_) => {
+                    out.extend_from_slice(&[0x8A, 8]);
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            },
+            Value::Bytes(bytes) => {
+                out.push(b'U');
+                out.push(bytes.len() as u8);
+                out.extend_from_slice(bytes);
+            }
+            Value::Str(text) => {
+                out.push(b'X');
+                out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                out.extend_from_slice(text.as_bytes());
+            }
+            Value::List(items) => {
+                out.extend_from_slice(b"](");
+                items.iter().for_each(|item| write(out, item));
+                out.push(b'e');
+            }
+            Value::Tuple(items) => {
+                out.push(b'(');
+                items.iter().for_each(|item| write(out, item));
+                out.push(b't');
+            }
+            Value::Dict(items) => {
+                out.extend_from_slice(b"}(");
+                for (key, value) in items {
+                    write(out, key);
+                    write(out, value);
+                }
+                out.push(b'u');
+            }
+            Value::Global(module, name) => {
+                out.push(b'c');
+                out.extend_from_slice(format!("{}\n{}\n", module, name).as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let value = Value::Dict(vec![(
+            Value::Str("images/bg.png".to_string()),
+            Value::List(vec![Value::Tuple(vec![
+                Value::Int(0x1_2345_6789),
+                Value::Int(-5),
+                Value::Bytes(b"\x89PNG".to_vec()),
+            ])]),
+        )]);
+        assert_eq!(load(&dump(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn test_python3_opcodes() {
+        // pickle.dumps({'a.rpy': [(1, 2, b'')]}, 2) on Python 3, memoized
+        let mut data = vec![0x80, 2, b'}', b'q', 0, 0x8C, 5];
+        data.extend_from_slice(b"a.rpy");
+        data.extend_from_slice(&[0x94, b']', 0x94, b'K', 1, b'K', 2]);
+        data.extend_from_slice(b"c_codecs\nencode\n");
+        data.extend_from_slice(&[0x8C, 0, 0x8C, 6]);
+        data.extend_from_slice(b"latin1");
+        data.extend_from_slice(&[0x86, b'R', 0x87, b'a', b's', b'.']);
+
+        let value = load(&data).unwrap();
+        assert_eq!(
+            value,
+            Value::Dict(vec![(
+                Value::Str("a.rpy".to_string()),
+                Value::List(vec![Value::Tuple(vec![
+                    Value::Int(1),
+                    Value::Int(2),
+                    Value::Bytes(Vec::new()),
+                ])]),
+            )])
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsupported() {
+        assert!(load(b"\x80\x02cos\nsystem\nX\x02\x00\x00\x00ls\x85R.").is_err());
+        assert!(load(b"\x80\x02}").is_err());
+        assert!(load(b"\x80\x02Z.").is_err());
+    }
+}
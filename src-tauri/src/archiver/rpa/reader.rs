@@ -0,0 +1,337 @@
+//! RPA Archive Reader (Unpacker)
+
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+
+use super::pickle::{self, Value};
+use super::{RpaEntry, RpaVersion};
+use crate::archiver::archive::{check_bounds, find_entry};
+use crate::archiver::{
+    normalize_name, Archive, ArchiveEntryInfo, ArchiveFormat, ArchiveReader, ArchiverError,
+    ArchiverResult,
+};
+
+/// Longest header line accepted
+const MAX_HEADER_LEN: u64 = 256;
+
+/// RPA Archive Reader for `.rpa` files
+pub struct RpaReader {
+    /// Path to the archive file
+    path: PathBuf,
+    /// Archive version
+    version: RpaVersion,
+    /// File entries in the archive
+    entries: Vec<RpaEntry>,
+}
+
+/// Parse the header line into the version, index offset and key
+fn parse_header(line: &str) -> ArchiverResult<(RpaVersion, u64, u64)> {
+    let invalid = || ArchiverError::InvalidFormat(format!("Invalid RPA header: {}", line));
+    let hex = |field: Option<&str>| {
+        field
+            .and_then(|f| u64::from_str_radix(f, 16).ok())
+            .ok_or_else(invalid)
+    };
+
+    let mut fields = line.split_ascii_whitespace();
+    let magic = fields.next().unwrap_or_default();
+    if magic == "RPA-3.0" {
+        let offset = hex(fields.next())?;
+        let key = hex(fields.next())?;
+        Ok((RpaVersion::V3, offset, key))
+    } else if magic == "RPA-2.0" {
+        Ok((RpaVersion::V2, hex(fields.next())?, 0))
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Read the entries of an unpickled index
+fn read_index(index: &Value, key: u64, file_len: u64) -> ArchiverResult<Vec<RpaEntry>> {
+    let invalid =
+        |name: &str| ArchiverError::InvalidFormat(format!("Invalid RPA index entry: {}", name));
+    let Value::Dict(items) = index else {
+        return Err(ArchiverError::InvalidFormat(
+            "RPA index is not a dictionary".to_string(),
+        ));
+    };
+
+    let mut entries = Vec::with_capacity(items.len());
+    for (name, chunks) in items {
+        let name = name
+            .as_bytes()
+            .and_then(|b| std::str::from_utf8(b).ok())
+            .map(normalize_name)
+            .ok_or_else(|| invalid("non-text name"))?;
+
+        // Ren'Py only ever writes one chunk per file
+        let chunk = chunks
+            .as_items()
+            .and_then(|c| c.first())
+            .and_then(Value::as_items)
+            .ok_or_else(|| invalid(&name))?;
+        let (offset, size, prefix) = match chunk {
+            [offset, size] => (offset, size, &[][..]),
+            [offset, size, prefix, ..] => (
+                offset,
+                size,
+                prefix.as_bytes().ok_or_else(|| invalid(&name))?,
+            ),
+            _ => return Err(invalid(&name)),
+        };
+        let offset = offset.as_int().ok_or_else(|| invalid(&name))? as u64 ^ key;
+        let size = size.as_int().ok_or_else(|| invalid(&name))? as u64 ^ key;
+
+        let stored = size
+            .checked_sub(prefix.len() as u64)
+            .ok_or_else(|| invalid(&name))?;
+        check_bounds(&name, offset, stored, file_len)?;
+
+        entries.push(RpaEntry {
+            name,
+            size,
+            offset,
+            prefix: prefix.to_vec(),
+        });
+    }
+
+    entries.sort_by_key(|e| e.offset);
+    Ok(entries)
+}
+
+impl RpaReader {
+    /// Extract a single entry to a byte vector
+    pub fn extract_to_memory(&self, entry: &RpaEntry) -> ArchiverResult<Vec<u8>> {
+        let mut data = Vec::with_capacity(entry.size as usize);
+        self.entry_reader(entry)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Open a streaming reader over an entry
+    pub fn entry_reader(&self, entry: &RpaEntry) -> ArchiverResult<Box<dyn Read + Send>> {
+        let file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+        let stored = entry.size - entry.prefix.len() as u64;
+        check_bounds(&entry.name, entry.offset, stored, file_len)?;
+
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        Ok(Box::new(
+            Cursor::new(entry.prefix.clone()).chain(reader.take(stored)),
+        ))
+    }
+
+    /// Get the archive path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the archive version
+    pub fn version(&self) -> RpaVersion {
+        self.version
+    }
+}
+
+impl ArchiveReader for RpaReader {
+    type Entry = RpaEntry;
+
+    fn open<P: AsRef<Path>>(path: P) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+
+        let mut header = Vec::new();
+        (&mut file).take(MAX_HEADER_LEN).read_to_end(&mut header)?;
+        let line_end = header
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| ArchiverError::InvalidFormat("Missing RPA header".to_string()))?;
+        let line = String::from_utf8_lossy(&header[..line_end]);
+        let (version, index_offset, key) = parse_header(&line)?;
+
+        if index_offset >= file_len {
+            return Err(ArchiverError::InvalidFormat(
+                "RPA index offset is out of range".to_string(),
+            ));
+        }
+        file.seek(SeekFrom::Start(index_offset))?;
+        let mut index = Vec::new();
+        ZlibDecoder::new(BufReader::new(file)).read_to_end(&mut index)?;
+
+        let entries = read_index(&pickle::load(&index)?, key, file_len)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            version,
+            entries,
+        })
+    }
+
+    fn entries(&self) -> &[RpaEntry] {
+        &self.entries
+    }
+
+    fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> ArchiverResult<usize> {
+        let output_dir = output_dir.as_ref();
+        let mut count = 0;
+
+        for entry in &self.entries {
+            let output_path = entry.output_path(output_dir);
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut output = File::create(&output_path)?;
+            std::io::copy(&mut self.entry_reader(entry)?, &mut output)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn extract_entry<P: AsRef<Path>>(&self, entry_name: &str, output_dir: P) -> ArchiverResult<()> {
+        let output_dir = output_dir.as_ref();
+
+        // Find the entry
+        let entry = find_entry(&self.entries, entry_name, |e| &e.name)?;
+
+        // Extract to memory
+        let data = self.extract_to_memory(entry)?;
+
+        // Write to file
+        let output_path = entry.output_path(output_dir);
+
+        // Create parent directories
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&output_path, data)?;
+
+        Ok(())
+    }
+}
+
+impl Archive for RpaReader {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Rpa
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn list_entries(&self) -> Vec<ArchiveEntryInfo> {
+        self.entries
+            .iter()
+            .map(|e| ArchiveEntryInfo {
+                name: e.name.clone(),
+                size: e.size,
+                compressed: false,
+            })
+            .collect()
+    }
+
+    fn open_entry(&self, name: &str) -> ArchiverResult<Box<dyn Read + Send + '_>> {
+        let entry = find_entry(&self.entries, name, |e| &e.name)?;
+        self.entry_reader(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{build_archive, TestFile};
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fixture_files() -> Vec<TestFile<'static>> {
+        vec![
+            TestFile {
+                name: "script.rpy",
+                data: b"label start:\n    e \"Hello\"\n",
+                prefix: 0,
+            },
+            TestFile {
+                name: "images/bg room.png",
+                data: b"\x89PNG\r\n\x1a\nroom",
+                prefix: 8,
+            },
+            TestFile {
+                name: "audio/empty.ogg",
+                data: b"",
+                prefix: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_read_v3() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive.rpa");
+        fs::write(&path, build_archive(Some(0x42424242), &fixture_files())).unwrap();
+
+        let reader = RpaReader::open(&path).unwrap();
+        assert_eq!(reader.version(), RpaVersion::V3);
+        assert_eq!(reader.entries().len(), 3);
+
+        for file in fixture_files() {
+            assert_eq!(
+                reader.read_entry(file.name).unwrap(),
+                file.data,
+                "{}",
+                file.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_v2_extract_all() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive.rpa");
+        fs::write(&path, build_archive(None, &fixture_files())).unwrap();
+
+        let reader = RpaReader::open(&path).unwrap();
+        assert_eq!(reader.version(), RpaVersion::V2);
+
+        let output_dir = temp_dir.path().join("out");
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 3);
+        assert_eq!(
+            fs::read(output_dir.join("images/bg room.png")).unwrap(),
+            b"\x89PNG\r\n\x1a\nroom"
+        );
+    }
+
+    #[test]
+    fn test_invalid_archive() {
+        assert!(parse_header("RPA-4.0 0000000000000033 00000000").is_err());
+        assert!(parse_header("RPA-3.0 zzzz 00000000").is_err());
+        assert_eq!(
+            parse_header("RPA-3.0 0000000000000033 0000abcd").unwrap(),
+            (RpaVersion::V3, 0x33, 0xabcd)
+        );
+
+        // Entry past the end of the file
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive.rpa");
+        let mut data = build_archive(Some(7), &fixture_files());
+        let key = 7u64;
+        let index = Value::Dict(vec![(
+            Value::Str("big.bin".to_string()),
+            Value::List(vec![Value::Tuple(vec![
+                Value::Int((51 ^ key) as i64),
+                Value::Int((1 << 20 ^ key) as i64),
+            ])]),
+        )]);
+        assert!(matches!(
+            read_index(&index, key, data.len() as u64),
+            Err(ArchiverError::InvalidFormat(_))
+        ));
+
+        data.truncate(60);
+        fs::write(&path, data).unwrap();
+        assert!(RpaReader::open(&path).is_err());
+    }
+}
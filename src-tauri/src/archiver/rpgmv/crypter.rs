@@ -0,0 +1,280 @@
+//! Decryption and re-encryption of MV/MZ asset files and folders
+
+use std::fs;
+use std::path::Path;
+
+use super::{decrypted_path, encrypted_path, is_image_asset, AssetStyle, RpgMvKey, RpgMvSystem};
+use crate::archiver::{ArchiverError, ArchiverResult};
+
+/// Decrypts and re-encrypts RPG Maker MV/MZ assets
+#[derive(Debug, Clone)]
+pub struct RpgMvCrypter {
+    /// Asset key
+    key: RpgMvKey,
+    /// Naming style for encrypted output
+    style: AssetStyle,
+    /// Whether to encrypt images when re-encrypting
+    encrypt_images: bool,
+    /// Whether to encrypt audio when re-encrypting
+    encrypt_audio: bool,
+}
+
+impl RpgMvCrypter {
+    /// Create a crypter that encrypts both images and audio
+    pub fn new(key: RpgMvKey, style: AssetStyle) -> Self {
+        Self {
+            key,
+            style,
+            encrypt_images: true,
+            encrypt_audio: true,
+        }
+    }
+
+    /// Create a crypter from the `System.json` settings of a game folder
+    pub fn from_game_dir<P: AsRef<Path>>(game_dir: P) -> ArchiverResult<Self> {
+        let game_dir = game_dir.as_ref();
+        let system = RpgMvSystem::from_game_dir(game_dir)?;
+
+        let key = system.key.ok_or_else(|| {
+            ArchiverError::DecryptionError("System.json has no encryption key".to_string())
+        })?;
+
+        Ok(Self {
+            key,
+            style: AssetStyle::detect(game_dir),
+            encrypt_images: system.has_encrypted_images,
+            encrypt_audio: system.has_encrypted_audio,
+        })
+    }
+
+    /// Get the asset key
+    pub fn key(&self) -> &RpgMvKey {
+        &self.key
+    }
+
+    /// Get the naming style for encrypted output
+    pub fn style(&self) -> AssetStyle {
+        self.style
+    }
+
+    /// Decrypt a single file
+    pub fn decrypt_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input: P,
+        output: Q,
+    ) -> ArchiverResult<()> {
+        let data = read_existing(input.as_ref())?;
+        let decrypted = self.key.decrypt(&data)?;
+        write_creating_dirs(output.as_ref(), &decrypted)
+    }
+
+    /// Encrypt a single file
+    pub fn encrypt_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input: P,
+        output: Q,
+    ) -> ArchiverResult<()> {
+        let data = read_existing(input.as_ref())?;
+        let encrypted = self.key.encrypt(&data);
+        write_creating_dirs(output.as_ref(), &encrypted)
+    }
+
+    /// Decrypt all encrypted assets in a directory recursively
+    ///
+    /// Decrypted files keep their relative paths under `output_dir`, with
+    /// plain extensions (`.rpgmvp` -> `.png`). Returns the number of files.
+    pub fn decrypt_directory<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input_dir: P,
+        output_dir: Q,
+    ) -> ArchiverResult<usize> {
+        let input_dir = input_dir.as_ref();
+        let output_dir = output_dir.as_ref();
+        let mut count = 0;
+
+        for path in walk_files(input_dir)? {
+            let relative = relative_path(&path, input_dir)?;
+            if let Some(output_relative) = decrypted_path(relative) {
+                self.decrypt_file(&path, output_dir.join(output_relative))?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Encrypt all plain assets in a directory recursively
+    ///
+    /// Only asset types enabled for this crypter are encrypted. Encrypted
+    /// files keep their relative paths under `output_dir`, with encrypted
+    /// extensions (`.png` -> `.rpgmvp`). Returns the number of files.
+    pub fn encrypt_directory<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input_dir: P,
+        output_dir: Q,
+    ) -> ArchiverResult<usize> {
+        let input_dir = input_dir.as_ref();
+        let output_dir = output_dir.as_ref();
+        let mut count = 0;
+
+        for path in walk_files(input_dir)? {
+            let enabled = if is_image_asset(&path) {
+                self.encrypt_images
+            } else {
+                self.encrypt_audio
+            };
+            if !enabled {
+                continue;
+            }
+
+            let relative = relative_path(&path, input_dir)?;
+            if let Some(output_relative) = encrypted_path(relative, self.style) {
+                self.encrypt_file(&path, output_dir.join(output_relative))?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+/// Read a file, reporting a missing file as `FileNotFound`
+fn read_existing(path: &Path) -> ArchiverResult<Vec<u8>> {
+    if !path.exists() {
+        return Err(ArchiverError::FileNotFound(
+            path.to_string_lossy().to_string(),
+        ));
+    }
+    Ok(fs::read(path)?)
+}
+
+/// Write a file, creating parent directories
+fn write_creating_dirs(path: &Path, data: &[u8]) -> ArchiverResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data)?;
+    Ok(())
+}
+
+/// Collect all files in a directory recursively
+fn walk_files(dir: &Path) -> ArchiverResult<Vec<std::path::PathBuf>> {
+    if !dir.exists() {
+        return Err(ArchiverError::FileNotFound(
+            dir.to_string_lossy().to_string(),
+        ));
+    }
+
+    Ok(walkdir::WalkDir::new(dir)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect())
+}
+
+/// Get the path of a file relative to a directory
+fn relative_path<'a>(path: &'a Path, dir: &Path) -> ArchiverResult<&'a Path> {
+    path.strip_prefix(dir)
+        .map_err(|e| ArchiverError::InvalidFormat(format!("Failed to get relative path: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const KEY_HEX: &str = "0123456789abcdef0123456789abcdef";
+
+    fn crypter(style: AssetStyle) -> RpgMvCrypter {
+        RpgMvCrypter::new(RpgMvKey::from_hex(KEY_HEX).unwrap(), style)
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let plain = temp_dir.path().join("Title.png");
+        fs::write(&plain, b"\x89PNG\r\n\x1a\n0123456789abcdef").unwrap();
+
+        let crypter = crypter(AssetStyle::Mv);
+        let encrypted = temp_dir.path().join("Title.rpgmvp");
+        crypter.encrypt_file(&plain, &encrypted).unwrap();
+
+        let decrypted = temp_dir.path().join("out/Title.png");
+        crypter.decrypt_file(&encrypted, &decrypted).unwrap();
+
+        assert_eq!(fs::read(&decrypted).unwrap(), fs::read(&plain).unwrap());
+    }
+
+    #[test]
+    fn test_directory_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        fs::create_dir_all(source.join("img/pictures")).unwrap();
+        fs::create_dir_all(source.join("audio/bgm")).unwrap();
+        fs::write(source.join("img/pictures/Title.png"), b"title image data").unwrap();
+        fs::write(source.join("audio/bgm/Theme.ogg"), b"OggS theme music").unwrap();
+        fs::write(source.join("readme.txt"), b"not an asset").unwrap();
+
+        let crypter = crypter(AssetStyle::Mz);
+        let encrypted_dir = temp_dir.path().join("encrypted");
+        assert_eq!(
+            crypter.encrypt_directory(&source, &encrypted_dir).unwrap(),
+            2
+        );
+        assert!(encrypted_dir.join("img/pictures/Title.png_").exists());
+        assert!(encrypted_dir.join("audio/bgm/Theme.ogg_").exists());
+        assert!(!encrypted_dir.join("readme.txt").exists());
+
+        let decrypted_dir = temp_dir.path().join("decrypted");
+        assert_eq!(
+            crypter
+                .decrypt_directory(&encrypted_dir, &decrypted_dir)
+                .unwrap(),
+            2
+        );
+        for name in ["img/pictures/Title.png", "audio/bgm/Theme.ogg"] {
+            assert_eq!(
+                fs::read(decrypted_dir.join(name)).unwrap(),
+                fs::read(source.join(name)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_game_dir_respects_flags() {
+        let temp_dir = TempDir::new().unwrap();
+        let game_dir = temp_dir.path();
+        fs::create_dir_all(game_dir.join("data")).unwrap();
+        fs::create_dir_all(game_dir.join("js")).unwrap();
+        fs::write(game_dir.join("js/rmmz_core.js"), b"").unwrap();
+        fs::write(
+            game_dir.join("data/System.json"),
+            format!(
+                r#"{{"encryptionKey":"{}","hasEncryptedImages":true,"hasEncryptedAudio":false}}"#,
+                KEY_HEX
+            ),
+        )
+        .unwrap();
+
+        let crypter = RpgMvCrypter::from_game_dir(game_dir).unwrap();
+        assert_eq!(crypter.style(), AssetStyle::Mz);
+
+        let source = temp_dir.path().join("translated");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("Title.png"), b"translated title").unwrap();
+        fs::write(source.join("Theme.ogg"), b"music").unwrap();
+
+        let output = temp_dir.path().join("output");
+        assert_eq!(crypter.encrypt_directory(&source, &output).unwrap(), 1);
+        assert!(output.join("Title.png_").exists());
+        assert!(!output.join("Theme.ogg_").exists());
+    }
+
+    #[test]
+    fn test_decrypt_missing_file() {
+        let result = crypter(AssetStyle::Mv).decrypt_file("/nonexistent/a.rpgmvp", "/tmp/a.png");
+        assert!(matches!(result, Err(ArchiverError::FileNotFound(_))));
+    }
+}
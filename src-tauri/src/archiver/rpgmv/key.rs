@@ -0,0 +1,178 @@
+//! RPG Maker MV/MZ asset key implementation
+//!
+//! The key is a 16-byte value stored in `System.json` as 32 hex digits.
+//! Only the first 16 bytes of each file are XORed with it; the rest of the
+//! file is stored as is behind the fake header.
+
+use super::{HEADER, HEADER_SIZE};
+use crate::archiver::{ArchiverError, ArchiverResult};
+
+/// First 16 bytes of every PNG file (signature + IHDR chunk header)
+const PNG_HEAD: [u8; HEADER_SIZE] = [
+    0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52,
+];
+
+/// RPG Maker MV/MZ asset decryption/encryption key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpgMvKey {
+    /// Key bytes
+    bytes: [u8; HEADER_SIZE],
+}
+
+impl RpgMvKey {
+    /// Create a key from raw bytes
+    pub fn new(bytes: [u8; HEADER_SIZE]) -> Self {
+        Self { bytes }
+    }
+
+    /// Parse a key from the `encryptionKey` hex string
+    pub fn from_hex(hex: &str) -> ArchiverResult<Self> {
+        let hex = hex.trim();
+        if hex.len() != HEADER_SIZE * 2 || !hex.is_ascii() {
+            return Err(ArchiverError::InvalidFormat(format!(
+                "Encryption key must be {} hex digits: {}",
+                HEADER_SIZE * 2,
+                hex
+            )));
+        }
+
+        let mut bytes = [0u8; HEADER_SIZE];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| {
+                ArchiverError::InvalidFormat(format!("Invalid hex in encryption key: {}", hex))
+            })?;
+        }
+
+        Ok(Self { bytes })
+    }
+
+    /// Recover the key from an encrypted PNG (`.rpgmvp` / `.png_`)
+    ///
+    /// PNG files always start with the same 16 bytes, so the key can be
+    /// derived when `System.json` no longer contains it.
+    pub fn from_encrypted_png(data: &[u8]) -> ArchiverResult<Self> {
+        Self::check_header(data)?;
+        if data.len() < HEADER_SIZE * 2 {
+            return Err(ArchiverError::DecryptionError(
+                "Encrypted image is too short to recover the key".to_string(),
+            ));
+        }
+
+        let mut bytes = [0u8; HEADER_SIZE];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = data[HEADER_SIZE + i] ^ PNG_HEAD[i];
+        }
+
+        Ok(Self { bytes })
+    }
+
+    /// Get the key as a lowercase hex string
+    pub fn to_hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Get the raw key bytes
+    pub fn bytes(&self) -> &[u8; HEADER_SIZE] {
+        &self.bytes
+    }
+
+    /// Decrypt an encrypted asset, validating the fake header
+    pub fn decrypt(&self, data: &[u8]) -> ArchiverResult<Vec<u8>> {
+        Self::check_header(data)?;
+
+        let mut output = data[HEADER_SIZE..].to_vec();
+        self.xor_head(&mut output);
+        Ok(output)
+    }
+
+    /// Encrypt a plain asset, prepending the fake header
+    pub fn encrypt(&self, data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(HEADER_SIZE + data.len());
+        output.extend_from_slice(HEADER);
+        output.extend_from_slice(data);
+        self.xor_head(&mut output[HEADER_SIZE..]);
+        output
+    }
+
+    /// Check whether data starts with the fake header
+    pub fn has_header(data: &[u8]) -> bool {
+        data.len() >= HEADER_SIZE && &data[..HEADER_SIZE] == HEADER
+    }
+
+    /// Validate the fake header of an encrypted asset
+    fn check_header(data: &[u8]) -> ArchiverResult<()> {
+        if !Self::has_header(data) {
+            return Err(ArchiverError::InvalidFormat(
+                "Missing RPGMV header".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// XOR the first 16 bytes with the key
+    fn xor_head(&self, data: &mut [u8]) {
+        for (byte, key) in data.iter_mut().zip(self.bytes.iter()) {
+            *byte ^= key;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_HEX: &str = "00112233445566778899aabbccddeeff";
+
+    fn png_data() -> Vec<u8> {
+        let mut data = PNG_HEAD.to_vec();
+        data.extend_from_slice(b"\0\0\0\x10\0\0\0\x10\x08\x06\0\0\0rest of image");
+        data
+    }
+
+    #[test]
+    fn test_from_hex() {
+        let key = RpgMvKey::from_hex(KEY_HEX).unwrap();
+        assert_eq!(key.bytes()[0], 0x00);
+        assert_eq!(key.bytes()[15], 0xFF);
+        assert_eq!(key.to_hex(), KEY_HEX);
+
+        assert!(RpgMvKey::from_hex("abcd").is_err());
+        assert!(RpgMvKey::from_hex("zz112233445566778899aabbccddeeff").is_err());
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_symmetry() {
+        let key = RpgMvKey::from_hex(KEY_HEX).unwrap();
+        let data = png_data();
+
+        let encrypted = key.encrypt(&data);
+        assert_eq!(&encrypted[..HEADER_SIZE], HEADER);
+        assert_eq!(encrypted.len(), data.len() + HEADER_SIZE);
+        // Only the first 16 bytes are encrypted
+        assert_eq!(&encrypted[HEADER_SIZE * 2..], &data[HEADER_SIZE..]);
+
+        assert_eq!(key.decrypt(&encrypted).unwrap(), data);
+    }
+
+    #[test]
+    fn test_recover_key_from_png() {
+        let key = RpgMvKey::from_hex(KEY_HEX).unwrap();
+        let encrypted = key.encrypt(&png_data());
+
+        assert_eq!(RpgMvKey::from_encrypted_png(&encrypted).unwrap(), key);
+    }
+
+    #[test]
+    fn test_decrypt_invalid_header() {
+        let key = RpgMvKey::from_hex(KEY_HEX).unwrap();
+
+        let result = key.decrypt(&png_data());
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+
+        // Files shorter than the encrypted block are still valid
+        assert_eq!(key.decrypt(&key.encrypt(b"tiny")).unwrap(), b"tiny");
+
+        let result = RpgMvKey::from_encrypted_png(HEADER);
+        assert!(matches!(result, Err(ArchiverError::DecryptionError(_))));
+    }
+}
@@ -0,0 +1,260 @@
+//! Encrypted asset module for RPG Maker MV and MZ
+//!
+//! MV/MZ deployments can encrypt images and audio with the key stored in
+//! `System.json` (`encryptionKey`, `hasEncryptedImages`, `hasEncryptedAudio`):
+//! - MV: `.rpgmvp` (png), `.rpgmvo` (ogg), `.rpgmvm` (m4a)
+//! - MZ: `.png_`, `.ogg_`, `.m4a_`
+//!
+//! ## File Format
+//!
+//! - Bytes 0-15: Fake header "RPGMV\0\0\0\0\x03\x01\0\0\0\0\0"
+//! - Bytes 16-31: First 16 bytes of the original file, XORed with the key
+//! - Remaining bytes: Rest of the original file, unencrypted
+
+mod crypter;
+mod key;
+
+pub use crypter::RpgMvCrypter;
+pub use key::RpgMvKey;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::{ArchiverError, ArchiverResult};
+
+/// Fake header prepended to encrypted files
+pub const HEADER: &[u8; 16] = b"RPGMV\0\0\0\0\x03\x01\0\0\0\0\0";
+
+/// Size of the fake header and of the encrypted block in bytes
+pub const HEADER_SIZE: usize = 16;
+
+/// Encrypted/plain extension pairs for MV
+const MV_EXTENSIONS: [(&str, &str); 3] = [("rpgmvp", "png"), ("rpgmvo", "ogg"), ("rpgmvm", "m4a")];
+
+/// Encrypted/plain extension pairs for MZ
+const MZ_EXTENSIONS: [(&str, &str); 3] = [("png_", "png"), ("ogg_", "ogg"), ("m4a_", "m4a")];
+
+/// Naming style of encrypted assets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetStyle {
+    /// RPG Maker MV (`.rpgmvp`, `.rpgmvo`, `.rpgmvm`)
+    Mv,
+    /// RPG Maker MZ (`.png_`, `.ogg_`, `.m4a_`)
+    Mz,
+}
+
+impl AssetStyle {
+    /// Detect the asset style of a deployed game folder
+    pub fn detect(game_dir: &Path) -> Self {
+        if game_dir.join("js").join("rmmz_core.js").exists() {
+            Self::Mz
+        } else {
+            Self::Mv
+        }
+    }
+
+    /// Encrypted/plain extension pairs for this style
+    fn extensions(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Mv => &MV_EXTENSIONS,
+            Self::Mz => &MZ_EXTENSIONS,
+        }
+    }
+}
+
+/// Get the plain extension for an encrypted asset extension
+pub fn decrypted_extension(ext: &str) -> Option<&'static str> {
+    let lower = ext.to_lowercase();
+    MV_EXTENSIONS
+        .iter()
+        .chain(MZ_EXTENSIONS.iter())
+        .find(|(encrypted, _)| *encrypted == lower)
+        .map(|(_, plain)| *plain)
+}
+
+/// Get the encrypted extension for a plain asset extension
+pub fn encrypted_extension(ext: &str, style: AssetStyle) -> Option<&'static str> {
+    let lower = ext.to_lowercase();
+    style
+        .extensions()
+        .iter()
+        .find(|(_, plain)| *plain == lower)
+        .map(|(encrypted, _)| *encrypted)
+}
+
+/// Get the path of the decrypted counterpart of an encrypted asset
+pub fn decrypted_path(path: &Path) -> Option<PathBuf> {
+    let ext = path.extension()?.to_str()?;
+    decrypted_extension(ext).map(|plain| path.with_extension(plain))
+}
+
+/// Get the path of the encrypted counterpart of a plain asset
+pub fn encrypted_path(path: &Path, style: AssetStyle) -> Option<PathBuf> {
+    let ext = path.extension()?.to_str()?;
+    encrypted_extension(ext, style).map(|encrypted| path.with_extension(encrypted))
+}
+
+/// Check if a path has an encrypted asset extension
+pub fn is_encrypted_asset(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(decrypted_extension)
+        .is_some()
+}
+
+/// Check if a path is an image asset (encrypted or plain)
+pub fn is_image_asset(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|ext| {
+            let plain = decrypted_extension(ext).unwrap_or(ext);
+            plain.eq_ignore_ascii_case("png")
+        })
+        .unwrap_or(false)
+}
+
+/// Encryption settings read from `System.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SystemJson {
+    #[serde(default)]
+    encryption_key: Option<String>,
+    #[serde(default)]
+    has_encrypted_images: bool,
+    #[serde(default)]
+    has_encrypted_audio: bool,
+}
+
+/// Encryption settings of a deployed MV/MZ game
+#[derive(Debug, Clone)]
+pub struct RpgMvSystem {
+    /// Asset key (None if the game is not encrypted)
+    pub key: Option<RpgMvKey>,
+    /// Whether images are encrypted
+    pub has_encrypted_images: bool,
+    /// Whether audio is encrypted
+    pub has_encrypted_audio: bool,
+}
+
+impl RpgMvSystem {
+    /// Load encryption settings from a `System.json` file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(ArchiverError::FileNotFound(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+
+        let content = fs::read_to_string(path)?;
+        // Some editors save System.json with a BOM
+        let content = content.trim_start_matches('\u{FEFF}');
+        let system: SystemJson = serde_json::from_str(content).map_err(|e| {
+            ArchiverError::InvalidFormat(format!("Failed to parse System.json: {}", e))
+        })?;
+
+        let key = match system.encryption_key.as_deref() {
+            Some(hex) if !hex.is_empty() => Some(RpgMvKey::from_hex(hex)?),
+            _ => None,
+        };
+
+        Ok(Self {
+            key,
+            has_encrypted_images: system.has_encrypted_images,
+            has_encrypted_audio: system.has_encrypted_audio,
+        })
+    }
+
+    /// Load encryption settings from a game folder (MV `www/data` or MZ `data`)
+    pub fn from_game_dir<P: AsRef<Path>>(game_dir: P) -> ArchiverResult<Self> {
+        let path = find_system_json(game_dir.as_ref()).ok_or_else(|| {
+            ArchiverError::FileNotFound(format!(
+                "System.json in {}",
+                game_dir.as_ref().to_string_lossy()
+            ))
+        })?;
+        Self::from_file(path)
+    }
+
+    /// Check if any assets are encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some() && (self.has_encrypted_images || self.has_encrypted_audio)
+    }
+}
+
+/// Find `System.json` in a game folder
+pub fn find_system_json(game_dir: &Path) -> Option<PathBuf> {
+    [
+        game_dir.join("www").join("data").join("System.json"),
+        game_dir.join("data").join("System.json"),
+    ]
+    .into_iter()
+    .find(|p| p.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_extension_mapping() {
+        assert_eq!(decrypted_extension("rpgmvp"), Some("png"));
+        assert_eq!(decrypted_extension("OGG_"), Some("ogg"));
+        assert_eq!(decrypted_extension("png"), None);
+
+        assert_eq!(encrypted_extension("png", AssetStyle::Mv), Some("rpgmvp"));
+        assert_eq!(encrypted_extension("m4a", AssetStyle::Mz), Some("m4a_"));
+        assert_eq!(encrypted_extension("txt", AssetStyle::Mz), None);
+
+        let path = Path::new("img/pictures/Title.rpgmvp");
+        assert!(is_encrypted_asset(path));
+        assert!(is_image_asset(path));
+        assert_eq!(
+            decrypted_path(path),
+            Some(PathBuf::from("img/pictures/Title.png"))
+        );
+        assert_eq!(
+            encrypted_path(Path::new("audio/bgm/Theme.ogg"), AssetStyle::Mz),
+            Some(PathBuf::from("audio/bgm/Theme.ogg_"))
+        );
+    }
+
+    #[test]
+    fn test_system_from_game_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path().join("www").join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::write(
+            data_dir.join("System.json"),
+            r#"{"gameTitle":"Test","encryptionKey":"d41d8cd98f00b204e9800998ecf8427e","hasEncryptedImages":true,"hasEncryptedAudio":false}"#,
+        )
+        .unwrap();
+
+        let system = RpgMvSystem::from_game_dir(temp_dir.path()).unwrap();
+        assert!(system.is_encrypted());
+        assert!(system.has_encrypted_images);
+        assert!(!system.has_encrypted_audio);
+        assert_eq!(
+            system.key.unwrap().to_hex(),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+        assert_eq!(
+            AssetStyle::detect(&temp_dir.path().join("www")),
+            AssetStyle::Mv
+        );
+    }
+
+    #[test]
+    fn test_system_without_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("System.json");
+        fs::write(&path, "\u{FEFF}{\"gameTitle\":\"Plain\"}").unwrap();
+
+        let system = RpgMvSystem::from_file(&path).unwrap();
+        assert!(system.key.is_none());
+        assert!(!system.is_encrypted());
+    }
+}
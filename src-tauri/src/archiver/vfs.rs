@@ -0,0 +1,352 @@
+//! Virtual filesystem over a game folder and its archives
+//!
+//! Many games keep their data inside an archive (`Game.rgss3a`,
+//! `package.nw`, `resources/app.asar`). [`GameVfs`] resolves paths relative
+//! to the game folder, reading loose files from disk first and falling back
+//! to mounted archives, so a project can be scanned without unpacking
+//! hundreds of MB of assets first.
+//!
+//! Paths are `/`-separated and relative to the game folder. Archive entries
+//! are matched case-insensitively, like the Windows filesystem the games
+//! run on.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use super::{
+    asar, normalize_name, nwjs, rpa, wolf, Archive, ArchiveFormat, ArchiverError, ArchiverResult,
+};
+
+/// Location of a file resolved through the virtual filesystem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VfsSource {
+    /// Loose file on disk
+    Loose(PathBuf),
+    /// Entry of a mounted archive
+    Archive {
+        /// Path of the archive file
+        archive: PathBuf,
+        /// Entry name within the archive
+        name: String,
+    },
+}
+
+/// Entry of a mounted archive, indexed by its virtual path
+#[derive(Debug, Clone)]
+struct MountedEntry {
+    /// Index of the archive in `archives`
+    archive: usize,
+    /// Entry name within the archive
+    name: String,
+    /// Virtual path with its original casing
+    path: String,
+}
+
+/// Read-only view of a game folder with archives mounted underneath
+pub struct GameVfs {
+    /// Game folder holding loose files
+    root: PathBuf,
+    /// Mounted archives, in mount order
+    archives: Vec<Box<dyn Archive>>,
+    /// Lowercase virtual path → mounted entry
+    index: HashMap<String, MountedEntry>,
+}
+
+impl GameVfs {
+    /// Create a filesystem over a game folder, without archives
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            archives: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Create a filesystem over a game folder and mount its data archives
+    ///
+    /// Mounts RGSS archives in the folder, the NW.js package,
+    /// `resources/app.asar` (at `resources/app`), Wolf RPG Editor
+    /// archives (see [`wolf::find_archives`]) and Ren'Py archives in `game`
+    /// (at `game`). XP3 archives are not
+    /// mounted because they may need a cipher; use [`mount`](Self::mount).
+    pub fn open_game_dir<P: AsRef<Path>>(root: P) -> ArchiverResult<Self> {
+        let mut vfs = Self::new(root);
+        let root = vfs.root.clone();
+
+        let mut rgss_archives: Vec<PathBuf> = fs::read_dir(&root)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.is_file()
+                    && p.extension()
+                        .and_then(|e| e.to_str())
+                        .and_then(ArchiveFormat::from_extension)
+                        .is_some_and(|f| matches!(f, ArchiveFormat::Rgss(_)))
+            })
+            .collect();
+        rgss_archives.sort();
+
+        for path in rgss_archives {
+            vfs.mount_path(&path, "")?;
+        }
+
+        if let Some(package) = nwjs::find_package(&root) {
+            vfs.mount(ArchiveFormat::NwPackage.open_as(&package)?, "");
+        }
+
+        let app_asar = root.join("resources").join("app.asar");
+        if asar::is_asar_archive(&app_asar) {
+            vfs.mount(ArchiveFormat::Asar.open_as(&app_asar)?, "resources/app");
+        }
+
+        for (path, prefix) in wolf::find_archives(&root) {
+            vfs.mount(ArchiveFormat::Wolf.open_as(&path)?, &prefix);
+        }
+
+        for path in rpa::find_archives(&root) {
+            vfs.mount(ArchiveFormat::Rpa.open_as(&path)?, "game");
+        }
+
+        Ok(vfs)
+    }
+
+    /// Mount an archive at a virtual directory (`""` for the game folder)
+    ///
+    /// Archives mounted earlier take priority over later ones.
+    pub fn mount(&mut self, archive: Box<dyn Archive>, prefix: &str) {
+        let prefix = normalize_name(prefix).trim_matches('/').to_string();
+        let archive_index = self.archives.len();
+
+        for entry in archive.list_entries() {
+            let path = if prefix.is_empty() {
+                entry.name.clone()
+            } else {
+                format!("{}/{}", prefix, entry.name)
+            };
+
+            self.index
+                .entry(path.to_lowercase())
+                .or_insert(MountedEntry {
+                    archive: archive_index,
+                    name: entry.name,
+                    path,
+                });
+        }
+
+        self.archives.push(archive);
+    }
+
+    /// Open an archive and mount it at a virtual directory
+    pub fn mount_path<P: AsRef<Path>>(&mut self, path: P, prefix: &str) -> ArchiverResult<()> {
+        let archive = ArchiveFormat::open(path)?;
+        self.mount(archive, prefix);
+        Ok(())
+    }
+
+    /// Get the game folder
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Iterate over the mounted archives
+    pub fn archives(&self) -> impl Iterator<Item = &dyn Archive> {
+        self.archives.iter().map(|a| a.as_ref())
+    }
+
+    /// Get the loose file path for a virtual path, if it stays inside the root
+    fn loose_path(&self, path: &str) -> Option<PathBuf> {
+        let relative = PathBuf::from(normalize_name(path));
+        let safe = relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+
+        safe.then(|| self.root.join(relative))
+    }
+
+    /// Resolve a virtual path, preferring loose files
+    pub fn resolve(&self, path: &str) -> Option<VfsSource> {
+        if let Some(loose) = self.loose_path(path).filter(|p| p.is_file()) {
+            return Some(VfsSource::Loose(loose));
+        }
+
+        let entry = self.index.get(&normalize_name(path).to_lowercase())?;
+        Some(VfsSource::Archive {
+            archive: self.archives[entry.archive].path().to_path_buf(),
+            name: entry.name.clone(),
+        })
+    }
+
+    /// Check whether a file exists on disk or in a mounted archive
+    pub fn exists(&self, path: &str) -> bool {
+        self.resolve(path).is_some()
+    }
+
+    /// Open a streaming reader over a file
+    pub fn open(&self, path: &str) -> ArchiverResult<Box<dyn Read + Send + '_>> {
+        if let Some(loose) = self.loose_path(path).filter(|p| p.is_file()) {
+            return Ok(Box::new(BufReader::new(File::open(loose)?)));
+        }
+
+        let entry = self
+            .index
+            .get(&normalize_name(path).to_lowercase())
+            .ok_or_else(|| ArchiverError::FileNotFound(path.to_string()))?;
+        self.archives[entry.archive].open_entry(&entry.name)
+    }
+
+    /// Read a file into memory
+    pub fn read(&self, path: &str) -> ArchiverResult<Vec<u8>> {
+        let mut data = Vec::new();
+        self.open(path)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Read a UTF-8 text file
+    pub fn read_to_string(&self, path: &str) -> ArchiverResult<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|_| ArchiverError::InvalidFormat(format!("{} is not valid UTF-8", path)))
+    }
+
+    /// List the files directly inside a virtual directory
+    ///
+    /// Returns sorted virtual paths. A loose file hides an archive entry
+    /// with the same path.
+    pub fn list_dir(&self, dir: &str) -> Vec<String> {
+        let dir = normalize_name(dir).trim_matches('/').to_string();
+        let join = |name: &str| {
+            if dir.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{}", dir, name)
+            }
+        };
+
+        let mut files: HashMap<String, String> = HashMap::new();
+
+        let dir_lower = dir.to_lowercase();
+        for (key, entry) in &self.index {
+            let parent = key.rsplit_once('/').map_or("", |(parent, _)| parent);
+            if parent == dir_lower {
+                files.insert(key.clone(), entry.path.clone());
+            }
+        }
+
+        if let Some(loose_dir) = self.loose_path(&dir) {
+            if let Ok(entries) = fs::read_dir(loose_dir) {
+                for entry in entries.flatten() {
+                    if !entry.path().is_file() {
+                        continue;
+                    }
+                    let path = join(&entry.file_name().to_string_lossy());
+                    files.insert(path.to_lowercase(), path);
+                }
+            }
+        }
+
+        let mut files: Vec<String> = files.into_values().collect();
+        files.sort();
+        files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archiver::{rgss, ArchiveWriter};
+    use tempfile::TempDir;
+
+    /// Game folder with `Data/` packed into `Game.rgss3a`
+    fn create_game() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let source_dir = temp_dir.path().join("source");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(source_dir.join("Map001.rvdata2"), b"packed map 1").unwrap();
+        fs::write(source_dir.join("Map002.rvdata2"), b"packed map 2").unwrap();
+
+        let mut writer = rgss::RgssWriter::for_version(rgss::RgssVersion::V3);
+        writer.add_directory(&source_dir, Some("Data")).unwrap();
+        writer.write(temp_dir.path().join("Game.rgss3a")).unwrap();
+        fs::remove_dir_all(&source_dir).unwrap();
+
+        temp_dir
+    }
+
+    #[test]
+    fn test_read_from_archive() {
+        let game = create_game();
+        let vfs = GameVfs::open_game_dir(game.path()).unwrap();
+
+        assert_eq!(vfs.archives().count(), 1);
+        assert_eq!(vfs.read("Data/Map001.rvdata2").unwrap(), b"packed map 1");
+        assert_eq!(vfs.read("data\\MAP002.rvdata2").unwrap(), b"packed map 2");
+        assert_eq!(
+            vfs.resolve("Data/Map001.rvdata2"),
+            Some(VfsSource::Archive {
+                archive: game.path().join("Game.rgss3a"),
+                name: "Data/Map001.rvdata2".to_string(),
+            })
+        );
+        assert!(matches!(
+            vfs.read("Data/Map003.rvdata2"),
+            Err(ArchiverError::FileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_loose_files_take_priority() {
+        let game = create_game();
+        fs::create_dir_all(game.path().join("Data")).unwrap();
+        fs::write(game.path().join("Data/Map002.rvdata2"), b"loose map 2").unwrap();
+        fs::write(game.path().join("Data/Map010.rvdata2"), b"loose map 10").unwrap();
+
+        let vfs = GameVfs::open_game_dir(game.path()).unwrap();
+        assert_eq!(vfs.read("Data/Map001.rvdata2").unwrap(), b"packed map 1");
+        assert_eq!(vfs.read("Data/Map002.rvdata2").unwrap(), b"loose map 2");
+        assert!(matches!(
+            vfs.resolve("Data/Map002.rvdata2"),
+            Some(VfsSource::Loose(_))
+        ));
+
+        assert_eq!(
+            vfs.list_dir("Data"),
+            vec![
+                "Data/Map001.rvdata2",
+                "Data/Map002.rvdata2",
+                "Data/Map010.rvdata2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mount_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("System.json");
+        fs::write(&source, r#"{"gameTitle":"Test"}"#).unwrap();
+
+        let resources = temp_dir.path().join("resources");
+        fs::create_dir_all(&resources).unwrap();
+        let mut writer = asar::AsarWriter::new();
+        writer.add_file(&source, "www/data/System.json").unwrap();
+        writer.write(resources.join("app.asar")).unwrap();
+
+        let vfs = GameVfs::open_game_dir(temp_dir.path()).unwrap();
+        assert_eq!(
+            vfs.read_to_string("resources/app/www/data/System.json")
+                .unwrap(),
+            r#"{"gameTitle":"Test"}"#
+        );
+        assert_eq!(
+            vfs.list_dir("resources/app/www/data"),
+            vec!["resources/app/www/data/System.json"]
+        );
+    }
+
+    #[test]
+    fn test_rejects_parent_paths() {
+        let game = create_game();
+        let vfs = GameVfs::new(game.path().join("Data"));
+        assert!(!vfs.exists("../Game.rgss3a"));
+    }
+}
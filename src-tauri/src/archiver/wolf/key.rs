@@ -0,0 +1,172 @@
+//! DxLib archive keys
+//!
+//! `.wolf` archives are DxLib archives (DXA) obfuscated with a 12-byte XOR
+//! key. The key byte applied to a byte depends on its position: header and
+//! table bytes use their offset from the start of the block, file data uses
+//! its offset plus the unpacked size of the file.
+
+use super::{DATA_START_V5, DATA_START_V6};
+
+/// Length of a DxLib archive key
+pub const KEY_LENGTH: usize = 12;
+
+/// 12-byte XOR key of a DxLib archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DxKey([u8; KEY_LENGTH]);
+
+/// Keys used by released Wolf RPG Editor versions, tried in order
+pub const KNOWN_KEYS: &[(&str, DxKey)] = &[
+    (
+        "Wolf RPG Editor 2.01",
+        DxKey([
+            0x0F, 0x53, 0xE1, 0x3E, 0x04, 0x37, 0x12, 0x17, 0x60, 0x0F, 0x53, 0xE1,
+        ]),
+    ),
+    (
+        "Wolf RPG Editor 2.10",
+        DxKey([
+            0x4C, 0xD9, 0x2A, 0xB7, 0x28, 0x9B, 0xAC, 0x07, 0x3E, 0x77, 0xEC, 0x4C,
+        ]),
+    ),
+    (
+        "Wolf RPG Editor 2.20",
+        DxKey([
+            0x38, 0x50, 0x40, 0x28, 0x72, 0x4F, 0x21, 0x70, 0x3B, 0x73, 0x35, 0x38,
+        ]),
+    ),
+];
+
+impl DxKey {
+    /// Create a key from its raw bytes
+    pub const fn new(bytes: [u8; KEY_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    /// Derive a key from a key string, like DxLib's `KeyCreate`
+    ///
+    /// `None` gives the default key used when no key string is set.
+    pub fn from_key_string(source: Option<&str>) -> Self {
+        let mut key = [0xAAu8; KEY_LENGTH];
+        if let Some(source) = source.map(str::as_bytes).filter(|s| !s.is_empty()) {
+            // Shorter strings are repeated to fill the key
+            for (i, byte) in key.iter_mut().enumerate() {
+                *byte = source[i % source.len()];
+            }
+        }
+
+        key[0] = !key[0];
+        key[1] = key[1].rotate_left(4);
+        key[2] ^= 0x8A;
+        key[3] = !key[3].rotate_left(4);
+        key[4] = !key[4];
+        key[5] ^= 0xAC;
+        key[6] = !key[6];
+        key[7] = !key[7].rotate_right(3);
+        key[8] = key[8].rotate_right(5);
+        key[9] ^= 0x7F;
+        key[10] = key[10].rotate_left(4) ^ 0xD6;
+        key[11] ^= 0xCC;
+        Self(key)
+    }
+
+    /// Get the raw key bytes
+    pub fn as_bytes(&self) -> &[u8; KEY_LENGTH] {
+        &self.0
+    }
+
+    /// XOR data in place, starting at key position `position`
+    pub fn apply(&self, data: &mut [u8], position: u64) {
+        let start = (position % KEY_LENGTH as u64) as usize;
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte ^= self.0[(start + i) % KEY_LENGTH];
+        }
+    }
+
+    /// Recover the key of a version 6 archive from its encrypted header
+    ///
+    /// The signature, the data start offset and the high halves of the
+    /// 64-bit table offsets are known, which covers every key byte. The
+    /// remaining known bytes are checked against the recovered key.
+    pub fn derive_v6(header: &[u8]) -> Option<Self> {
+        if header.len() < DATA_START_V6 as usize {
+            return None;
+        }
+
+        let mut key = [0u8; KEY_LENGTH];
+        // "DX", version 6
+        for (i, plain) in [b'D', b'X', 6, 0].into_iter().enumerate() {
+            key[i] = header[i] ^ plain;
+        }
+        // Low half of the data start offset
+        for (i, plain) in (DATA_START_V6 as u32).to_le_bytes().into_iter().enumerate() {
+            key[8 + i] = header[8 + i] ^ plain;
+        }
+        // High half of the file table offset
+        key[4..8].copy_from_slice(&header[28..32]);
+
+        let key = Self(key);
+        let mut plain = header[..DATA_START_V6 as usize].to_vec();
+        key.apply(&mut plain, 0);
+        let zero_high = |offset: usize| plain[offset..offset + 4] == [0; 4];
+        (zero_high(12) && zero_high(20) && zero_high(36)).then_some(key)
+    }
+
+    /// Check whether this key decrypts an archive header
+    pub fn matches_header(&self, header: &[u8]) -> bool {
+        if header.len() < 12 {
+            return false;
+        }
+
+        let mut plain = header[..12].to_vec();
+        self.apply(&mut plain, 0);
+        let data_start = u32::from_le_bytes([plain[8], plain[9], plain[10], plain[11]]) as u64;
+        match (&plain[0..2], u16::from_le_bytes([plain[2], plain[3]])) {
+            (b"DX", 5) => data_start == DATA_START_V5,
+            (b"DX", 6) => data_start == DATA_START_V6,
+            _ => false,
+        }
+    }
+
+    /// Find the key of an archive from its encrypted header
+    ///
+    /// Version 6 keys are recovered from the header itself; version 5
+    /// archives are tried against the known keys, the default key and no
+    /// key at all.
+    pub fn find(header: &[u8]) -> Option<Self> {
+        if let Some(key) = Self::derive_v6(header) {
+            return Some(key);
+        }
+
+        KNOWN_KEYS
+            .iter()
+            .map(|(_, key)| *key)
+            .chain([Self::from_key_string(None), Self([0; KEY_LENGTH])])
+            .find(|key| key.matches_header(header))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_roundtrip() {
+        let key = DxKey::from_key_string(Some("key"));
+        let mut data = b"Hello, DxLib archive".to_vec();
+        key.apply(&mut data, 7);
+        assert_ne!(&data[..], b"Hello, DxLib archive");
+        key.apply(&mut data, 7);
+        assert_eq!(&data[..], b"Hello, DxLib archive");
+    }
+
+    #[test]
+    fn test_from_key_string() {
+        // Short strings repeat; the default key is 0xAA before mixing
+        assert_eq!(
+            DxKey::from_key_string(Some("ab")),
+            DxKey::from_key_string(Some("abababababab"))
+        );
+        assert_eq!(DxKey::from_key_string(None).as_bytes()[0], !0xAA);
+        assert_eq!(DxKey::from_key_string(None).as_bytes()[11], 0xAA ^ 0xCC);
+    }
+}
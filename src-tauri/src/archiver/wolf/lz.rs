@@ -0,0 +1,202 @@
+//! DxLib archive LZ decompression
+//!
+//! Compressed data starts with the unpacked size (`u32`), the packed size
+//! including this header (`u32`) and a key code byte. The key code escapes
+//! back-references; a doubled key code stands for the key code byte itself.
+
+use crate::archiver::{ArchiverError, ArchiverResult};
+
+/// Size of the compressed data header
+const HEADER_SIZE: usize = 9;
+
+/// Shortest back-reference, subtracted from stored lengths
+const MIN_MATCH: usize = 4;
+
+/// Most bytes reserved per packed byte before decoding
+///
+/// Long back-references expand further; the output grows past this on
+/// demand rather than trusting the size in the header up front.
+const PREALLOCATION_RATIO: usize = 8;
+
+/// Decompress DxLib LZ data
+pub(super) fn decode(src: &[u8]) -> ArchiverResult<Vec<u8>> {
+    if src.len() < HEADER_SIZE {
+        return Err(corrupt("missing header"));
+    }
+
+    let size = u32::from_le_bytes([src[0], src[1], src[2], src[3]]) as usize;
+    let packed = u32::from_le_bytes([src[4], src[5], src[6], src[7]]) as usize;
+    let key_code = src[8];
+    let body = src
+        .get(HEADER_SIZE..packed.max(HEADER_SIZE))
+        .ok_or_else(|| corrupt("packed size exceeds the data"))?;
+
+    let mut out = Vec::with_capacity(size.min(packed.saturating_mul(PREALLOCATION_RATIO)));
+    let mut pos = 0;
+    let byte_at = |pos: usize| {
+        body.get(pos)
+            .copied()
+            .ok_or_else(|| corrupt("unexpected end"))
+    };
+
+    while pos < body.len() {
+        if out.len() >= size {
+            return Err(corrupt("data is larger than its stated size"));
+        }
+
+        let byte = body[pos];
+        if byte != key_code {
+            out.push(byte);
+            pos += 1;
+            continue;
+        }
+
+        let mut code = byte_at(pos + 1)?;
+        pos += 2;
+        if code == key_code {
+            out.push(key_code);
+            continue;
+        }
+        // Codes above the key code were shifted up to avoid it
+        if code > key_code {
+            code -= 1;
+        }
+
+        let mut len = (code >> 3) as usize;
+        if code & 0x04 != 0 {
+            len |= (byte_at(pos)? as usize) << 5;
+            pos += 1;
+        }
+        len += MIN_MATCH;
+
+        let index_size = (code & 0x03) as usize + 1;
+        if index_size > 3 {
+            return Err(corrupt("invalid offset size"));
+        }
+        let mut distance = 0usize;
+        for i in 0..index_size {
+            distance |= (byte_at(pos + i)? as usize) << (8 * i);
+        }
+        pos += index_size;
+        distance += 1;
+
+        if distance > out.len() {
+            return Err(corrupt("back-reference before the start of the data"));
+        }
+        if out.len() + len > size {
+            return Err(corrupt("data is larger than its stated size"));
+        }
+        let start = out.len() - distance;
+        for i in 0..len {
+            out.push(out[start + i]);
+        }
+    }
+
+    if out.len() != size {
+        return Err(corrupt("data size does not match the header"));
+    }
+    Ok(out)
+}
+
+fn corrupt(reason: &str) -> ArchiverError {
+    ArchiverError::InvalidFormat(format!("Corrupt DxLib compressed data: {}", reason))
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Token of a hand-built compressed stream
+    pub(in crate::archiver::wolf) enum Token<'a> {
+        Literal(&'a [u8]),
+        /// Back-reference: distance, length
+        Match(usize, usize),
+    }
+
+    /// Encode tokens with the given key code
+    pub(in crate::archiver::wolf) fn encode(key_code: u8, tokens: &[Token]) -> Vec<u8> {
+        let mut body = Vec::new();
+        let mut size = 0;
+
+        for token in tokens {
+            match *token {
+                Token::Literal(bytes) => {
+                    for &byte in bytes {
+                        body.push(byte);
+                        if byte == key_code {
+                            body.push(key_code);
+                        }
+                    }
+                    size += bytes.len();
+                }
+                Token::Match(distance, len) => {
+                    let stored_len = len - MIN_MATCH;
+                    let index = distance - 1;
+                    let index_size = match index {
+                        0..=0xFF => 0,
+                        0x100..=0xFFFF => 1,
+                        _ => 2,
+                    };
+
+                    let mut code = ((stored_len & 0x1F) << 3) as u8 | index_size as u8;
+                    if stored_len >= 0x20 {
+                        code |= 0x04;
+                    }
+                    if code >= key_code {
+                        code += 1;
+                    }
+
+                    body.push(key_code);
+                    body.push(code);
+                    if stored_len >= 0x20 {
+                        body.push((stored_len >> 5) as u8);
+                    }
+                    body.extend_from_slice(&index.to_le_bytes()[..index_size + 1]);
+                    size += len;
+                }
+            }
+        }
+
+        let mut out = (size as u32).to_le_bytes().to_vec();
+        out.extend_from_slice(&((body.len() + HEADER_SIZE) as u32).to_le_bytes());
+        out.push(key_code);
+        out.extend(body);
+        out
+    }
+
+    #[test]
+    fn test_decode_tokens() {
+        let data = encode(
+            0x20,
+            &[
+                Token::Literal(b"WOLF "),
+                Token::Match(5, 10),
+                Token::Literal(b"!"),
+            ],
+        );
+        assert_eq!(decode(&data).unwrap(), b"WOLF WOLF WOLF !");
+    }
+
+    #[test]
+    fn test_decode_long_match() {
+        let data = encode(0x00, &[Token::Literal(&[0, 1, 2]), Token::Match(3, 300)]);
+        let out = decode(&data).unwrap();
+        assert_eq!(out.len(), 303);
+        assert_eq!(&out[297..], &[0, 1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_decode_corrupt() {
+        let data = encode(0x20, &[Token::Literal(b"a"), Token::Match(4, 4)]);
+        assert!(decode(&data).is_err());
+
+        let mut data = encode(0x20, &[Token::Literal(b"abc")]);
+        data[0] = 4;
+        assert!(decode(&data).is_err());
+        data[0] = 2;
+        assert!(decode(&data).is_err());
+        data[0..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decode(&data).is_err());
+        assert!(decode(&data[..5]).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+//! Wolf RPG Editor archive module
+//!
+//! Wolf RPG Editor games pack their `Data` folder into `Data.wolf`, or each
+//! subfolder into its own archive (`Data/BasicData.wolf`,
+//! `Data/MapData.wolf`, ...). These are DxLib archives (DXA) version 5 or 6,
+//! obfuscated with a 12-byte XOR key (see [`key`]).
+//!
+//! ## Archive Format
+//!
+//! ```text
+//! Header      "DX", u16 version, u32 table size, data start,
+//!             name table offset, file table offset, directory table
+//!             offset, u32 code page
+//! Data        file contents
+//! Tables      name table, file table, directory table
+//! ```
+//!
+//! Offsets are `u32` in version 5 and `u64` in version 6. The file and
+//! directory table offsets are relative to the name table, and file data
+//! offsets to the data start. Each directory lists a run of file table
+//! entries; entries flagged as directories point back into the directory
+//! table. Files may be compressed with DxLib's LZ scheme (see [`lz`]).
+//!
+//! Newer archives (version 7 and later, Wolf RPG Editor 2.28+) use a
+//! different key schedule and are not supported.
+
+pub mod key;
+mod lz;
+mod reader;
+
+pub use key::DxKey;
+pub use reader::WolfReader;
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::archiver::safe_output_path;
+
+/// Size of a version 5 header, where file data starts
+pub const DATA_START_V5: u64 = 28;
+
+/// Size of a version 6 header, where file data starts
+pub const DATA_START_V6: u64 = 44;
+
+/// File attribute marking a directory entry
+pub const ATTRIBUTE_DIRECTORY: u64 = 0x10;
+
+/// File entry in a Wolf RPG archive
+#[derive(Debug, Clone)]
+pub struct WolfEntry {
+    /// File name (relative path within the archive, `/`-separated)
+    pub name: String,
+    /// Unpacked size in bytes
+    pub size: u64,
+    /// Stored size in bytes
+    pub stored_size: u64,
+    /// Whether the file is LZ-compressed
+    pub compressed: bool,
+    /// Offset of the stored data in the archive
+    pub offset: u64,
+}
+
+impl WolfEntry {
+    /// Get the output path for extraction
+    pub fn output_path(&self, base_dir: &Path) -> PathBuf {
+        safe_output_path(base_dir, &self.name)
+    }
+}
+
+/// Check whether a file is a DxLib archive with a supported key
+pub fn is_wolf_archive<P: AsRef<Path>>(path: P) -> bool {
+    let mut header = Vec::with_capacity(DATA_START_V6 as usize);
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    if file.take(DATA_START_V6).read_to_end(&mut header).is_err() {
+        return false;
+    }
+    DxKey::find(&header).is_some()
+}
+
+/// Find the readable archives of a game folder and their mount points
+///
+/// `Data.wolf` is mounted at `Data`, and `Data/<name>.wolf` at
+/// `Data/<name>`. Archives whose key is unknown are skipped.
+pub fn find_archives(game_dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut archives = Vec::new();
+
+    let data = game_dir.join("Data.wolf");
+    if is_wolf_archive(&data) {
+        archives.push((data, "Data".to_string()));
+    }
+
+    let Ok(dir) = fs::read_dir(game_dir.join("Data")) else {
+        return archives;
+    };
+    let mut nested: Vec<(PathBuf, String)> = dir
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("wolf"))
+                && is_wolf_archive(p)
+        })
+        .filter_map(|p| {
+            let stem = p.file_stem()?.to_str()?.to_string();
+            Some((p, format!("Data/{}", stem)))
+        })
+        .collect();
+    nested.sort();
+    archives.extend(nested);
+    archives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lz::tests::{encode, Token};
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// File of a fixture archive
+    pub(super) struct TestFile<'a> {
+        pub name: &'a str,
+        pub data: &'a [u8],
+        /// Store the data LZ-compressed (as literals)
+        pub compress: bool,
+    }
+
+    /// Node of the directory tree being written
+    #[derive(Default)]
+    struct TestDir<'a> {
+        files: Vec<&'a TestFile<'a>>,
+        dirs: BTreeMap<&'a str, TestDir<'a>>,
+    }
+
+    /// Tables and data of an archive being written
+    struct Builder {
+        wide: bool,
+        names: Vec<u8>,
+        files: Vec<u8>,
+        dirs: Vec<u8>,
+        data: Vec<u8>,
+        key: DxKey,
+    }
+
+    impl Builder {
+        fn int(&self, out: &mut Vec<u8>, value: u64) {
+            if self.wide {
+                out.extend_from_slice(&value.to_le_bytes());
+            } else {
+                out.extend_from_slice(&(value as u32).to_le_bytes());
+            }
+        }
+
+        fn file_head_size(&self) -> usize {
+            if self.wide {
+                64
+            } else {
+                44
+            }
+        }
+
+        fn dir_size(&self) -> usize {
+            if self.wide {
+                32
+            } else {
+                16
+            }
+        }
+
+        fn add_name(&mut self, name: &str) -> u64 {
+            let offset = self.names.len() as u64;
+            let units = if name.is_empty() {
+                0
+            } else {
+                name.len() / 4 + 1
+            };
+            let mut padded = name.as_bytes().to_vec();
+            padded.resize(units * 4, 0);
+
+            self.names.extend_from_slice(&(units as u16).to_le_bytes());
+            self.names.extend_from_slice(&[0, 0]);
+            self.names.extend(padded.to_ascii_uppercase());
+            self.names.extend(padded);
+            offset
+        }
+
+        /// Write a file head, returning its offset in the file table
+        fn add_file_head(
+            &mut self,
+            name: &str,
+            attributes: u64,
+            address: u64,
+            size: u64,
+            packed: Option<u64>,
+        ) -> u64 {
+            let offset = self.files.len() as u64;
+            let name_offset = self.add_name(name);
+            let mut head = Vec::new();
+            self.int(&mut head, name_offset);
+            self.int(&mut head, attributes);
+            head.extend_from_slice(&[0; 24]);
+            self.int(&mut head, address);
+            self.int(&mut head, size);
+            self.int(&mut head, packed.unwrap_or(u64::MAX));
+            self.files.extend(head);
+            offset
+        }
+
+        /// Write a directory and its contents, given its own file head
+        fn add_dir(&mut self, dir: &TestDir, head: u64, parent: u64) -> u64 {
+            let dir_offset = self.dirs.len() as u64;
+            self.dirs.resize(self.dirs.len() + self.dir_size(), 0);
+
+            // Children heads are contiguous; directories are patched after
+            let first = self.files.len() as u64;
+            let mut subdirs = Vec::new();
+            for (name, subdir) in &dir.dirs {
+                let child = self.add_file_head(name, ATTRIBUTE_DIRECTORY, 0, 0, None);
+                subdirs.push((child, subdir));
+            }
+            for file in &dir.files {
+                let name = file.name.rsplit('/').next().unwrap();
+                let address = self.data.len() as u64;
+                let mut stored = if file.compress {
+                    encode(0xFE, &[Token::Literal(file.data)])
+                } else {
+                    file.data.to_vec()
+                };
+                self.key.apply(&mut stored, file.data.len() as u64);
+                let packed = file.compress.then_some(stored.len() as u64);
+                self.data.extend(stored);
+                self.add_file_head(name, 0x20, address, file.data.len() as u64, packed);
+            }
+
+            let mut entry = Vec::new();
+            self.int(&mut entry, head);
+            self.int(&mut entry, parent);
+            self.int(&mut entry, (dir.dirs.len() + dir.files.len()) as u64);
+            self.int(&mut entry, first);
+            let start = dir_offset as usize;
+            self.dirs[start..start + entry.len()].copy_from_slice(&entry);
+
+            for (child, subdir) in subdirs {
+                let sub_offset = self.add_dir(subdir, child, dir_offset);
+                // Point the directory's file head at its directory entry
+                let field =
+                    child as usize + self.file_head_size() - if self.wide { 24 } else { 12 };
+                let mut address = Vec::new();
+                self.int(&mut address, sub_offset);
+                self.files[field..field + address.len()].copy_from_slice(&address);
+            }
+            dir_offset
+        }
+    }
+
+    /// Build a DxLib archive of the given version, encrypted with `key`
+    pub(super) fn build_archive(version: u16, key: DxKey, files: &[TestFile]) -> Vec<u8> {
+        let mut root = TestDir::default();
+        for file in files {
+            let mut dir = &mut root;
+            let mut parts: Vec<&str> = file.name.split('/').collect();
+            parts.pop();
+            for part in parts {
+                dir = dir.dirs.entry(part).or_default();
+            }
+            dir.files.push(file);
+        }
+
+        let wide = version >= 6;
+        let mut builder = Builder {
+            wide,
+            names: Vec::new(),
+            files: Vec::new(),
+            dirs: Vec::new(),
+            data: Vec::new(),
+            key,
+        };
+        let root_head = builder.add_file_head("", ATTRIBUTE_DIRECTORY, 0, 0, None);
+        builder.add_dir(&root, root_head, u64::MAX);
+
+        let data_start = if wide { DATA_START_V6 } else { DATA_START_V5 };
+        let table_start = data_start + builder.data.len() as u64;
+        let mut tables = builder.names.clone();
+        let file_table = tables.len() as u64;
+        tables.extend(&builder.files);
+        let dir_table = tables.len() as u64;
+        tables.extend(&builder.dirs);
+
+        let mut header = b"DX".to_vec();
+        header.extend_from_slice(&version.to_le_bytes());
+        header.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+        for value in [data_start, table_start, file_table, dir_table] {
+            builder.int(&mut header, value);
+        }
+        header.extend_from_slice(&932u32.to_le_bytes());
+        assert_eq!(header.len() as u64, data_start);
+
+        key.apply(&mut header, 0);
+        key.apply(&mut tables, 0);
+        header.extend(builder.data);
+        header.extend(tables);
+        header
+    }
+
+    #[test]
+    fn test_is_wolf_archive() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let files = [TestFile {
+            name: "BasicData/Game.dat",
+            data: b"game",
+            compress: false,
+        }];
+
+        let v6 = temp_dir.path().join("Data.wolf");
+        std::fs::write(
+            &v6,
+            build_archive(6, DxKey::from_key_string(Some("any key")), &files),
+        )
+        .unwrap();
+        assert!(is_wolf_archive(&v6));
+
+        let v5 = temp_dir.path().join("BasicData.wolf");
+        std::fs::write(&v5, build_archive(5, key::KNOWN_KEYS[1].1, &files)).unwrap();
+        assert!(is_wolf_archive(&v5));
+
+        // Version 5 keys cannot be recovered from the header
+        let unknown = temp_dir.path().join("MapData.wolf");
+        std::fs::write(
+            &unknown,
+            build_archive(5, DxKey::from_key_string(Some("any key")), &files),
+        )
+        .unwrap();
+        assert!(!is_wolf_archive(&unknown));
+
+        let other = temp_dir.path().join("readme.txt");
+        std::fs::write(&other, b"DX").unwrap();
+        assert!(!is_wolf_archive(&other));
+    }
+
+    #[test]
+    fn test_find_archives() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let key = DxKey::from_key_string(Some("game key"));
+        let game = [TestFile {
+            name: "Game.dat",
+            data: b"game",
+            compress: false,
+        }];
+        let map = [TestFile {
+            name: "Map001.mps",
+            data: b"map",
+            compress: true,
+        }];
+
+        fs::create_dir_all(root.join("Data")).unwrap();
+        fs::write(
+            root.join("Data/BasicData.wolf"),
+            build_archive(6, key, &game),
+        )
+        .unwrap();
+        fs::write(root.join("Data/MapData.wolf"), build_archive(6, key, &map)).unwrap();
+        fs::write(root.join("Data/Notes.txt"), b"not an archive").unwrap();
+
+        let archives = find_archives(root);
+        let prefixes: Vec<&str> = archives.iter().map(|(_, p)| p.as_str()).collect();
+        assert_eq!(prefixes, vec!["Data/BasicData", "Data/MapData"]);
+
+        let vfs = crate::archiver::vfs::GameVfs::open_game_dir(root).unwrap();
+        assert_eq!(vfs.read("Data/BasicData/Game.dat").unwrap(), b"game");
+        assert_eq!(vfs.read("Data/MapData/Map001.mps").unwrap(), b"map");
+    }
+}
@@ -0,0 +1,556 @@
+//! Wolf RPG Archive Reader (Unpacker)
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use encoding_rs::{SHIFT_JIS, UTF_8};
+
+use super::{lz, DxKey, WolfEntry, ATTRIBUTE_DIRECTORY, DATA_START_V6};
+use crate::archiver::archive::{check_bounds, find_entry};
+use crate::archiver::{
+    Archive, ArchiveEntryInfo, ArchiveFormat, ArchiveReader, ArchiverError, ArchiverResult,
+};
+
+/// Code page of UTF-8 file names
+const CODE_PAGE_UTF8: u32 = 65001;
+
+/// Stored size marking an uncompressed file
+const NOT_COMPRESSED_V5: u64 = u32::MAX as u64;
+
+/// Deepest directory nesting accepted
+const MAX_DEPTH: usize = 64;
+
+/// Wolf RPG Archive Reader for `.wolf` files
+pub struct WolfReader {
+    /// Path to the archive file
+    path: PathBuf,
+    /// Archive version (5 or 6)
+    version: u16,
+    /// Key the archive is encrypted with
+    key: DxKey,
+    /// File entries in the archive
+    entries: Vec<WolfEntry>,
+}
+
+/// Decrypted archive tables and their field width
+struct Tables {
+    /// Name, file and directory tables, in that order
+    data: Vec<u8>,
+    /// Whether offsets are 64-bit (version 6)
+    wide: bool,
+    /// Offset of the file table
+    file_table: u64,
+    /// Offset of the directory table
+    dir_table: u64,
+    /// Code page of the file names
+    code_page: u32,
+}
+
+fn offset_error() -> ArchiverError {
+    ArchiverError::InvalidFormat("Archive offset out of range".to_string())
+}
+
+/// Add an offset read from the archive to a position, failing on overflow
+fn offset_add(position: u64, offset: u64) -> ArchiverResult<u64> {
+    position.checked_add(offset).ok_or_else(offset_error)
+}
+
+/// Get the range of `len` bytes at an offset read from the archive
+fn range_at(offset: u64, len: usize) -> Option<std::ops::Range<usize>> {
+    let start = usize::try_from(offset).ok()?;
+    Some(start..start.checked_add(len)?)
+}
+
+impl Tables {
+    /// Size of an offset field
+    fn int_size(&self) -> usize {
+        if self.wide {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// Read an offset field at `offset` of a table
+    fn int(&self, offset: u64) -> ArchiverResult<u64> {
+        let bytes = range_at(offset, self.int_size())
+            .and_then(|range| self.data.get(range))
+            .ok_or_else(|| {
+                ArchiverError::InvalidFormat("Archive table out of range".to_string())
+            })?;
+
+        Ok(if self.wide {
+            u64::from_le_bytes(bytes.try_into().unwrap())
+        } else {
+            u32::from_le_bytes(bytes.try_into().unwrap()) as u64
+        })
+    }
+
+    /// Read the original-case name at `offset` of the name table
+    fn name(&self, offset: u64) -> ArchiverResult<String> {
+        let out_of_range = || ArchiverError::InvalidFormat("Name table out of range".to_string());
+        let units = range_at(offset, 2)
+            .and_then(|range| self.data.get(range))
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(out_of_range)?;
+
+        // Upper-case name for lookups, then the original name
+        let raw = offset_add(offset, 4 + units as u64 * 4)
+            .ok()
+            .and_then(|name_start| range_at(name_start, units * 4))
+            .and_then(|range| self.data.get(range))
+            .ok_or_else(out_of_range)?;
+        let raw = &raw[..raw.iter().position(|&b| b == 0).unwrap_or(raw.len())];
+
+        let encoding = if self.code_page == CODE_PAGE_UTF8 {
+            UTF_8
+        } else {
+            SHIFT_JIS
+        };
+        let (name, _, had_errors) = encoding.decode(raw);
+        if had_errors || name.contains(['/', '\\']) {
+            return Err(ArchiverError::InvalidFormat(format!(
+                "Invalid file name in archive: {}",
+                name
+            )));
+        }
+        Ok(name.into_owned())
+    }
+}
+
+impl WolfReader {
+    /// Open an archive with a given key instead of detecting it
+    pub fn open_with_key<P: AsRef<Path>>(path: P, key: DxKey) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+
+        let mut header = vec![0u8; DATA_START_V6 as usize];
+        let len = file.read(&mut header)?;
+        header.truncate(len);
+        key.apply(&mut header, 0);
+
+        if header.len() < 28 || &header[0..2] != b"DX" {
+            return Err(ArchiverError::InvalidFormat(
+                "Not a DxLib archive, or wrong key".to_string(),
+            ));
+        }
+        let version = u16::from_le_bytes([header[2], header[3]]);
+        let wide = match version {
+            5 => false,
+            6 => true,
+            v => {
+                return Err(ArchiverError::UnsupportedVersion(
+                    v.min(u8::MAX as u16) as u8
+                ))
+            }
+        };
+        if wide && header.len() < DATA_START_V6 as usize {
+            return Err(ArchiverError::InvalidFormat(
+                "Truncated archive header".to_string(),
+            ));
+        }
+
+        let u32_at = |o: usize| u32::from_le_bytes(header[o..o + 4].try_into().unwrap()) as u64;
+        let u64_at = |o: usize| u64::from_le_bytes(header[o..o + 8].try_into().unwrap());
+        let table_size = u32_at(4);
+        let (data_start, table_start, file_table, dir_table, code_page) = if wide {
+            (
+                u64_at(8),
+                u64_at(16),
+                u64_at(24),
+                u64_at(32),
+                u32_at(40) as u32,
+            )
+        } else {
+            (
+                u32_at(8),
+                u32_at(12),
+                u32_at(16),
+                u32_at(20),
+                u32_at(24) as u32,
+            )
+        };
+
+        check_bounds("archive tables", table_start, table_size, file_len)?;
+        let mut data = vec![0u8; table_size as usize];
+        file.seek(SeekFrom::Start(table_start))?;
+        file.read_exact(&mut data)?;
+        key.apply(&mut data, 0);
+
+        let tables = Tables {
+            data,
+            wide,
+            file_table,
+            dir_table,
+            code_page,
+        };
+        let entries = read_entries(&tables, data_start, file_len)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            version,
+            key,
+            entries,
+        })
+    }
+
+    /// Extract a single entry to a byte vector
+    pub fn extract_to_memory(&self, entry: &WolfEntry) -> ArchiverResult<Vec<u8>> {
+        let mut data = Vec::with_capacity(entry.stored_size as usize);
+        self.stored_reader(entry)?.read_to_end(&mut data)?;
+        self.key.apply(&mut data, entry.size);
+
+        if entry.compressed {
+            lz::decode(&data)
+        } else {
+            Ok(data)
+        }
+    }
+
+    /// Open a streaming reader over an entry
+    ///
+    /// Uncompressed files are decrypted while streaming; compressed files
+    /// are unpacked into memory first.
+    pub fn entry_reader(&self, entry: &WolfEntry) -> ArchiverResult<Box<dyn Read + Send>> {
+        if entry.compressed {
+            return Ok(Box::new(Cursor::new(self.extract_to_memory(entry)?)));
+        }
+
+        Ok(Box::new(KeyReader {
+            inner: self.stored_reader(entry)?,
+            key: self.key,
+            position: entry.size,
+        }))
+    }
+
+    /// Open a reader over the stored (encrypted) data of an entry
+    fn stored_reader(&self, entry: &WolfEntry) -> ArchiverResult<std::io::Take<BufReader<File>>> {
+        let file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+        check_bounds(&entry.name, entry.offset, entry.stored_size, file_len)?;
+
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        Ok(reader.take(entry.stored_size))
+    }
+
+    /// Get the archive path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the archive version (5 or 6)
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Get the key the archive is encrypted with
+    pub fn key(&self) -> DxKey {
+        self.key
+    }
+}
+
+/// Walk the directory tree and collect the file entries
+fn read_entries(tables: &Tables, data_start: u64, file_len: u64) -> ArchiverResult<Vec<WolfEntry>> {
+    let int_size = tables.int_size() as u64;
+    let head_size = int_size * 5 + 24;
+    let not_compressed = if tables.wide {
+        u64::MAX
+    } else {
+        NOT_COMPRESSED_V5
+    };
+
+    let mut entries = Vec::new();
+    let mut visited = HashSet::new();
+    // Directories to walk: (directory table offset, path, depth)
+    let mut pending = vec![(0u64, String::new(), 0usize)];
+
+    while let Some((dir_offset, dir_path, depth)) = pending.pop() {
+        if depth > MAX_DEPTH || !visited.insert(dir_offset) {
+            return Err(ArchiverError::InvalidFormat(
+                "Archive directory tree is cyclic or too deep".to_string(),
+            ));
+        }
+
+        let dir = offset_add(tables.dir_table, dir_offset)?;
+        let count = tables.int(offset_add(dir, int_size * 2)?)?;
+        let first = tables.int(offset_add(dir, int_size * 3)?)?;
+
+        for index in 0..count {
+            let head = index
+                .checked_mul(head_size)
+                .and_then(|n| n.checked_add(first))
+                .and_then(|n| n.checked_add(tables.file_table))
+                .ok_or_else(offset_error)?;
+            let name = tables.name(tables.int(head)?)?;
+            let attributes = tables.int(offset_add(head, int_size)?)?;
+            let address = tables.int(offset_add(head, int_size * 2 + 24)?)?;
+            let size = tables.int(offset_add(head, int_size * 3 + 24)?)?;
+            let packed = tables.int(offset_add(head, int_size * 4 + 24)?)?;
+
+            let path = if dir_path.is_empty() {
+                name
+            } else {
+                format!("{}/{}", dir_path, name)
+            };
+
+            if attributes & ATTRIBUTE_DIRECTORY != 0 {
+                pending.push((address, path, depth + 1));
+                continue;
+            }
+
+            let compressed = packed != not_compressed;
+            let stored_size = if compressed { packed } else { size };
+            let offset = offset_add(data_start, address)?;
+            check_bounds(&path, offset, stored_size, file_len)?;
+
+            entries.push(WolfEntry {
+                name: path,
+                size,
+                stored_size,
+                compressed,
+                offset,
+            });
+        }
+    }
+
+    entries.sort_by_key(|e| e.offset);
+    Ok(entries)
+}
+
+/// Reader decrypting file data on the fly
+struct KeyReader<R> {
+    inner: R,
+    key: DxKey,
+    /// Key position of the next byte
+    position: u64,
+}
+
+impl<R: Read> Read for KeyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.key.apply(&mut buf[..len], self.position);
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl ArchiveReader for WolfReader {
+    type Entry = WolfEntry;
+
+    fn open<P: AsRef<Path>>(path: P) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+        let mut header = Vec::with_capacity(DATA_START_V6 as usize);
+        File::open(path)?
+            .take(DATA_START_V6)
+            .read_to_end(&mut header)?;
+
+        let key = DxKey::find(&header).ok_or_else(|| {
+            ArchiverError::DecryptionError(format!(
+                "No known key matches the archive: {}",
+                path.display()
+            ))
+        })?;
+        Self::open_with_key(path, key)
+    }
+
+    fn entries(&self) -> &[WolfEntry] {
+        &self.entries
+    }
+
+    fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> ArchiverResult<usize> {
+        let output_dir = output_dir.as_ref();
+        let mut count = 0;
+
+        for entry in &self.entries {
+            let output_path = entry.output_path(output_dir);
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut output = File::create(&output_path)?;
+            std::io::copy(&mut self.entry_reader(entry)?, &mut output)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn extract_entry<P: AsRef<Path>>(&self, entry_name: &str, output_dir: P) -> ArchiverResult<()> {
+        let output_dir = output_dir.as_ref();
+
+        // Find the entry
+        let entry = find_entry(&self.entries, entry_name, |e| &e.name)?;
+
+        // Extract to memory
+        let data = self.extract_to_memory(entry)?;
+
+        // Write to file
+        let output_path = entry.output_path(output_dir);
+
+        // Create parent directories
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&output_path, data)?;
+
+        Ok(())
+    }
+}
+
+impl Archive for WolfReader {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Wolf
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn list_entries(&self) -> Vec<ArchiveEntryInfo> {
+        self.entries
+            .iter()
+            .map(|e| ArchiveEntryInfo {
+                name: e.name.clone(),
+                size: e.size,
+                compressed: e.compressed,
+            })
+            .collect()
+    }
+
+    fn open_entry(&self, name: &str) -> ArchiverResult<Box<dyn Read + Send + '_>> {
+        let entry = find_entry(&self.entries, name, |e| &e.name)?;
+        self.entry_reader(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::key::KNOWN_KEYS;
+    use super::super::tests::{build_archive, TestFile};
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fixture_files() -> Vec<TestFile<'static>> {
+        vec![
+            TestFile {
+                name: "BasicData/CommonEvent.dat",
+                data: b"\x00W\x00\x00OL\x00FC\x00common events",
+                compress: true,
+            },
+            TestFile {
+                name: "BasicData/Game.dat",
+                data: b"game settings",
+                compress: false,
+            },
+            TestFile {
+                name: "MapData/Map001.mps",
+                data: "マップ".as_bytes(),
+                compress: false,
+            },
+            TestFile {
+                name: "Title.txt",
+                data: b"\xFE\xFE escaped key code",
+                compress: true,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_read_v6_derived_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Data.wolf");
+        let key = DxKey::from_key_string(Some("unknown key"));
+        fs::write(&path, build_archive(6, key, &fixture_files())).unwrap();
+
+        let reader = WolfReader::open(&path).unwrap();
+        assert_eq!(reader.version(), 6);
+        assert_eq!(reader.key(), key);
+
+        let mut names: Vec<&str> = reader.entries().iter().map(|e| e.name.as_str()).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "BasicData/CommonEvent.dat",
+                "BasicData/Game.dat",
+                "MapData/Map001.mps",
+                "Title.txt",
+            ]
+        );
+
+        for file in fixture_files() {
+            assert_eq!(
+                reader.read_entry(file.name).unwrap(),
+                file.data,
+                "{}",
+                file.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_v5_known_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("BasicData.wolf");
+        fs::write(&path, build_archive(5, KNOWN_KEYS[2].1, &fixture_files())).unwrap();
+
+        let reader = WolfReader::open(&path).unwrap();
+        assert_eq!(reader.version(), 5);
+
+        let output_dir = temp_dir.path().join("out");
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 4);
+        assert_eq!(
+            fs::read(output_dir.join("BasicData/CommonEvent.dat")).unwrap(),
+            b"\x00W\x00\x00OL\x00FC\x00common events"
+        );
+        assert_eq!(
+            fs::read(output_dir.join("MapData/Map001.mps")).unwrap(),
+            "マップ".as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_read_v5_unknown_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Data.wolf");
+        let key = DxKey::from_key_string(Some("unknown key"));
+        fs::write(&path, build_archive(5, key, &fixture_files())).unwrap();
+
+        assert!(matches!(
+            WolfReader::open(&path),
+            Err(ArchiverError::DecryptionError(_))
+        ));
+
+        let reader = WolfReader::open_with_key(&path, key).unwrap();
+        assert_eq!(
+            reader.read_entry("Title.txt").unwrap(),
+            b"\xFE\xFE escaped key code"
+        );
+    }
+
+    #[test]
+    fn test_read_v6_overflowing_offsets() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("Data.wolf");
+        let key = DxKey::from_key_string(Some("unknown key"));
+
+        // Data start, then directory table offset
+        for field in [8, 32] {
+            let mut data = build_archive(6, key, &fixture_files());
+            key.apply(&mut data[..DATA_START_V6 as usize], 0);
+            data[field..field + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+            key.apply(&mut data[..DATA_START_V6 as usize], 0);
+            fs::write(&path, data).unwrap();
+
+            assert!(matches!(
+                WolfReader::open_with_key(&path, key),
+                Err(ArchiverError::InvalidFormat(_))
+            ));
+        }
+    }
+}
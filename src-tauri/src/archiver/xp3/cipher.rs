@@ -0,0 +1,357 @@
+//! XP3 content ciphers
+//!
+//! Commercial KiriKiri titles commonly install an extraction filter that
+//! obfuscates file contents. The filter runs on decompressed segment data
+//! and receives the entry hash (the `adlr` value) and the position of the
+//! data within the file, so the same interface covers both simple schemes
+//! shipped here and game-specific ones plugged in by callers.
+//!
+//! Data is encrypted before compression when packing, and decrypted after
+//! decompression when unpacking.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Xp3Entry, Xp3Reader};
+use crate::archiver::{ArchiveReader, ArchiverResult};
+
+/// Entry used to probe the cipher of an archive
+pub const PROBE_ENTRY: &str = "startup.tjs";
+
+/// Hash shifts tried when probing hash-keyed XOR ciphers
+const HASH_XOR_SHIFTS: [u8; 5] = [0, 8, 12, 16, 24];
+
+/// Minimum ratio of printable characters for text to count as readable
+const READABLE_RATIO: f64 = 0.95;
+
+/// A cipher applied to XP3 file contents
+pub trait Xp3Cipher: Send + Sync {
+    /// Scheme describing this cipher, for persisting the selection
+    fn scheme(&self) -> Xp3CipherScheme;
+
+    /// Decrypt data in place
+    ///
+    /// # Arguments
+    /// * `hash` - Adler-32 hash of the entry (from the `adlr` chunk)
+    /// * `offset` - Position of `data` within the decompressed file
+    /// * `data` - Data to decrypt
+    fn decrypt(&self, hash: u32, offset: u64, data: &mut [u8]);
+
+    /// Encrypt data in place
+    fn encrypt(&self, hash: u32, offset: u64, data: &mut [u8]) {
+        // XOR encryption is symmetric
+        self.decrypt(hash, offset, data);
+    }
+}
+
+/// Built-in cipher schemes that can be selected per project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Xp3CipherScheme {
+    /// Contents are stored in plain form
+    #[default]
+    None,
+    /// Every byte is XORed with a fixed key
+    FixedXor { key: u8 },
+    /// Every byte is XORed with one byte of the entry hash
+    HashXor { shift: u8 },
+}
+
+impl Xp3CipherScheme {
+    /// Create the cipher implementing this scheme
+    pub fn cipher(&self) -> Box<dyn Xp3Cipher> {
+        match *self {
+            Self::None => Box::new(NoCipher),
+            Self::FixedXor { key } => Box::new(FixedXorCipher::new(key)),
+            Self::HashXor { shift } => Box::new(HashXorCipher::new(shift)),
+        }
+    }
+
+    /// All schemes tried during detection, plain first
+    pub fn candidates() -> Vec<Self> {
+        let mut schemes = vec![Self::None];
+        schemes.extend(HASH_XOR_SHIFTS.iter().map(|&shift| Self::HashXor { shift }));
+        schemes.extend((1..=u8::MAX).map(|key| Self::FixedXor { key }));
+        schemes
+    }
+}
+
+impl std::fmt::Display for Xp3CipherScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::FixedXor { key } => write!(f, "Fixed XOR (0x{:02X})", key),
+            Self::HashXor { shift } => write!(f, "Hash XOR (>> {})", shift),
+        }
+    }
+}
+
+/// Cipher that leaves data untouched
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCipher;
+
+impl Xp3Cipher for NoCipher {
+    fn scheme(&self) -> Xp3CipherScheme {
+        Xp3CipherScheme::None
+    }
+
+    fn decrypt(&self, _hash: u32, _offset: u64, _data: &mut [u8]) {}
+}
+
+/// Cipher XORing every byte with a fixed key
+#[derive(Debug, Clone, Copy)]
+pub struct FixedXorCipher {
+    key: u8,
+}
+
+impl FixedXorCipher {
+    /// Create a cipher with the given key byte
+    pub fn new(key: u8) -> Self {
+        Self { key }
+    }
+}
+
+impl Xp3Cipher for FixedXorCipher {
+    fn scheme(&self) -> Xp3CipherScheme {
+        Xp3CipherScheme::FixedXor { key: self.key }
+    }
+
+    fn decrypt(&self, _hash: u32, _offset: u64, data: &mut [u8]) {
+        for byte in data {
+            *byte ^= self.key;
+        }
+    }
+}
+
+/// Cipher XORing every byte with `(hash >> shift) & 0xFF`
+#[derive(Debug, Clone, Copy)]
+pub struct HashXorCipher {
+    shift: u8,
+}
+
+impl HashXorCipher {
+    /// Create a cipher taking the key byte at `shift` bits of the hash
+    pub fn new(shift: u8) -> Self {
+        Self { shift }
+    }
+
+    /// Key byte for an entry hash
+    #[inline]
+    fn key(&self, hash: u32) -> u8 {
+        hash.checked_shr(self.shift as u32).unwrap_or(0) as u8
+    }
+}
+
+impl Xp3Cipher for HashXorCipher {
+    fn scheme(&self) -> Xp3CipherScheme {
+        Xp3CipherScheme::HashXor { shift: self.shift }
+    }
+
+    fn decrypt(&self, hash: u32, _offset: u64, data: &mut [u8]) {
+        let key = self.key(hash);
+        for byte in data {
+            *byte ^= key;
+        }
+    }
+}
+
+/// Detect the cipher of an archive by decrypting its `startup.tjs`
+///
+/// Falls back to the first script entry if there is no `startup.tjs`.
+/// Returns `None` if no known scheme yields readable text.
+pub fn detect_cipher(reader: &Xp3Reader) -> ArchiverResult<Option<Xp3CipherScheme>> {
+    let probe = reader
+        .entries()
+        .iter()
+        .find(|e| is_probe_entry(e))
+        .or_else(|| reader.script_entries().next());
+
+    let entry = match probe {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+
+    let raw = reader.extract_raw_to_memory(entry)?;
+    Ok(detect_scheme_for(&raw, entry.adler32.unwrap_or(0)))
+}
+
+/// Try every candidate scheme on raw data and return the most readable one
+pub fn detect_scheme_for(raw: &[u8], hash: u32) -> Option<Xp3CipherScheme> {
+    let mut best: Option<(Xp3CipherScheme, f64)> = None;
+
+    for scheme in Xp3CipherScheme::candidates() {
+        let mut data = raw.to_vec();
+        scheme.cipher().decrypt(hash, 0, &mut data);
+
+        let score = readability(&data);
+        // Keep the earliest candidate on ties so plain data wins
+        if score >= READABLE_RATIO && best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((scheme, score));
+        }
+    }
+
+    best.map(|(scheme, _)| scheme)
+}
+
+fn is_probe_entry(entry: &Xp3Entry) -> bool {
+    let name = entry.name.to_lowercase();
+    name == PROBE_ENTRY || name.ends_with(&format!("/{}", PROBE_ENTRY))
+}
+
+/// Check whether data decodes as mostly printable text
+///
+/// Accepts UTF-16LE (with BOM), UTF-8 and Shift-JIS, the encodings
+/// KiriKiri scripts are written in.
+pub fn is_readable_text(data: &[u8]) -> bool {
+    readability(data) >= READABLE_RATIO
+}
+
+/// Ratio of printable characters in decoded text, 0.0 if it does not decode
+fn readability(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let text = if let Some(body) = data.strip_prefix(&[0xFF, 0xFE]) {
+        let units: Vec<u16> = body
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        match String::from_utf16(&units) {
+            Ok(text) => text,
+            Err(_) => return 0.0,
+        }
+    } else {
+        let body = data.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(data);
+        match std::str::from_utf8(body) {
+            Ok(text) => text.to_string(),
+            Err(_) => match encoding_rs::SHIFT_JIS
+                .decode_without_bom_handling_and_without_replacement(body)
+            {
+                Some(text) => text.into_owned(),
+                None => return 0.0,
+            },
+        }
+    };
+
+    let total = text.chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+
+    // Half-width katakana is what XORed ASCII usually decodes to in
+    // Shift-JIS, and is rare in real scripts. A CR outside of CRLF means
+    // line breaks were scrambled (XOR 0x07 swaps CR and LF).
+    let mut printable = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let readable = match c {
+            '\r' => chars.peek() == Some(&'\n'),
+            '\t' | '\n' => true,
+            '\u{FF61}'..='\u{FF9F}' => false,
+            c => !c.is_control(),
+        };
+        if readable {
+            printable += 1;
+        }
+    }
+
+    printable as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTUP: &[u8] = b"// startup.tjs\r\nKAGLoadScript(\"Initialize.tjs\");\r\n";
+
+    fn encrypt_with(scheme: Xp3CipherScheme, hash: u32, data: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        scheme.cipher().encrypt(hash, 0, &mut data);
+        data
+    }
+
+    #[test]
+    fn test_fixed_xor_symmetry() {
+        let cipher = FixedXorCipher::new(0x5A);
+        let mut data = STARTUP.to_vec();
+        cipher.encrypt(0, 0, &mut data);
+        assert_ne!(data, STARTUP);
+        cipher.decrypt(0, 0, &mut data);
+        assert_eq!(data, STARTUP);
+    }
+
+    #[test]
+    fn test_hash_xor_key() {
+        let cipher = HashXorCipher::new(12);
+        assert_eq!(cipher.key(0x0012_3456), 0x23);
+        assert_eq!(HashXorCipher::new(0).key(0x0012_3456), 0x56);
+        assert_eq!(HashXorCipher::new(40).key(0xFFFF_FFFF), 0);
+    }
+
+    #[test]
+    fn test_scheme_round_trip() {
+        for scheme in [
+            Xp3CipherScheme::None,
+            Xp3CipherScheme::FixedXor { key: 0xCD },
+            Xp3CipherScheme::HashXor { shift: 8 },
+        ] {
+            assert_eq!(scheme.cipher().scheme(), scheme);
+
+            let json = serde_json::to_string(&scheme).unwrap();
+            let parsed: Xp3CipherScheme = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, scheme);
+        }
+    }
+
+    #[test]
+    fn test_is_readable_text() {
+        assert!(is_readable_text(STARTUP));
+
+        let (sjis, _, _) =
+            encoding_rs::SHIFT_JIS.encode("@call storage=\"first.ks\"\n吾輩は猫である。");
+        assert!(is_readable_text(&sjis));
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        for unit in "*start\n始まり".encode_utf16() {
+            utf16.extend_from_slice(&unit.to_le_bytes());
+        }
+        assert!(is_readable_text(&utf16));
+
+        assert!(!is_readable_text(&[]));
+        assert!(!is_readable_text(&encrypt_with(
+            Xp3CipherScheme::FixedXor { key: 0xA5 },
+            0,
+            STARTUP
+        )));
+    }
+
+    #[test]
+    fn test_detect_plain() {
+        assert_eq!(
+            detect_scheme_for(STARTUP, 0x1234),
+            Some(Xp3CipherScheme::None)
+        );
+    }
+
+    #[test]
+    fn test_detect_fixed_xor() {
+        let scheme = Xp3CipherScheme::FixedXor { key: 0xA5 };
+        let raw = encrypt_with(scheme, 0, STARTUP);
+        assert_eq!(detect_scheme_for(&raw, 0), Some(scheme));
+    }
+
+    #[test]
+    fn test_detect_hash_xor() {
+        let hash = 0x89AB_CDEF;
+        let scheme = Xp3CipherScheme::HashXor { shift: 0 };
+        let raw = encrypt_with(scheme, hash, STARTUP);
+        assert_eq!(detect_scheme_for(&raw, hash), Some(scheme));
+    }
+
+    #[test]
+    fn test_detect_unknown() {
+        // Random-looking bytes never decode to readable text
+        let raw: Vec<u8> = (0..256u32).map(|i| (i * 73 + 41) as u8).collect();
+        assert_eq!(detect_scheme_for(&raw, 0), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! XP3 Archive module for KiriKiri (krkr2 / krkrz)
+//!
+//! This module provides functionality to read and write `.xp3` archives
+//! used by KiriKiri games (`data.xp3`, `patch.xp3`, ...).
+//!
+//! ## Archive Format
+//!
+//! - Bytes 0-10: Magic "XP3\r\n \n\x1A\x8B\x67\x01"
+//! - Bytes 11-18: Offset of the file index (u64 LE)
+//!
+//! Archives written by krkr 2.28+ start with a "cushion" header: the index
+//! offset points at a dummy index record flagged with `INDEX_CONTINUE`,
+//! which is followed by the offset of the real index.
+//!
+//! Each index record is a flag byte followed by either
+//! `size(u64) + data` (raw) or `packed(u64) + size(u64) + zlib data`.
+//! The index itself is a sequence of `File` chunks, each containing
+//! `info` (flags, sizes, UTF-16 name), `segm` (data segments) and
+//! `adlr` (adler32 checksum of the original data) sub-chunks.
+//!
+//! Encrypted archives are handled through the pluggable ciphers in
+//! [`cipher`], selected per project or detected from `startup.tjs`.
+
+pub mod cipher;
+mod reader;
+mod writer;
+
+pub use cipher::{detect_cipher, Xp3Cipher, Xp3CipherScheme};
+pub use reader::Xp3Reader;
+pub use writer::Xp3Writer;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::archiver::safe_output_path;
+
+/// Magic bytes for XP3 archives
+pub const MAGIC: &[u8; 11] = b"XP3\r\n \n\x1A\x8B\x67\x01";
+
+/// Mask for the encoding method in an index flag byte
+pub const INDEX_ENCODE_MASK: u8 = 0x07;
+/// Index is stored uncompressed
+pub const INDEX_ENCODE_RAW: u8 = 0;
+/// Index is zlib-compressed
+pub const INDEX_ENCODE_ZLIB: u8 = 1;
+/// Another index record follows this one
+pub const INDEX_CONTINUE: u8 = 0x80;
+
+/// Mask for the encoding method in a segment flag
+pub const SEGMENT_ENCODE_MASK: u32 = 0x07;
+/// Segment is stored uncompressed
+pub const SEGMENT_ENCODE_RAW: u32 = 0;
+/// Segment is zlib-compressed
+pub const SEGMENT_ENCODE_ZLIB: u32 = 1;
+
+/// `info` flag marking a file as protected (should not be extracted)
+pub const FILE_PROTECTED: u32 = 1 << 31;
+
+/// Chunk tags used in the index
+pub const CHUNK_FILE: &[u8; 4] = b"File";
+pub const CHUNK_INFO: &[u8; 4] = b"info";
+pub const CHUNK_SEGM: &[u8; 4] = b"segm";
+pub const CHUNK_ADLR: &[u8; 4] = b"adlr";
+
+/// Size of a single `segm` record in bytes
+pub const SEGMENT_RECORD_SIZE: usize = 28;
+
+/// A contiguous piece of file data inside an XP3 archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xp3Segment {
+    /// Whether the segment is zlib-compressed
+    pub compressed: bool,
+    /// Offset of the segment data in the archive
+    pub offset: u64,
+    /// Size of the segment after decompression
+    pub size: u64,
+    /// Size of the segment as stored in the archive
+    pub packed_size: u64,
+}
+
+/// File entry in an XP3 archive
+#[derive(Debug, Clone)]
+pub struct Xp3Entry {
+    /// File name (relative path within archive, `/`-separated)
+    pub name: String,
+    /// `info` flags
+    pub flags: u32,
+    /// Original file size in bytes
+    pub size: u64,
+    /// Stored file size in bytes
+    pub packed_size: u64,
+    /// Adler-32 checksum of the original data (from the `adlr` chunk)
+    pub adler32: Option<u32>,
+    /// Data segments, in file order
+    pub segments: Vec<Xp3Segment>,
+}
+
+impl Xp3Entry {
+    /// Get the output path for extraction
+    pub fn output_path(&self, base_dir: &Path) -> PathBuf {
+        safe_output_path(base_dir, &self.name)
+    }
+
+    /// Check if the entry is marked as protected
+    pub fn is_protected(&self) -> bool {
+        self.flags & FILE_PROTECTED != 0
+    }
+
+    /// Check if the entry is a KAG scenario or TJS script
+    pub fn is_script(&self) -> bool {
+        let lower = self.name.to_lowercase();
+        lower.ends_with(".ks") || lower.ends_with(".tjs")
+    }
+}
+
+/// Compute the Adler-32 checksum stored in `adlr` chunks
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    // Largest block that cannot overflow `b` before taking the modulus
+    const BLOCK_SIZE: usize = 5552;
+
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for block in data.chunks(BLOCK_SIZE) {
+        for &byte in block {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+
+    (b << 16) | a
+}
+
+/// Check whether a file starts with the XP3 magic bytes
+pub fn is_xp3_archive<P: AsRef<Path>>(path: P) -> bool {
+    let mut header = [0u8; 11];
+    match File::open(path) {
+        Ok(mut file) => file.read_exact(&mut header).is_ok() && &header == MAGIC,
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_entry_output_path() {
+        let entry = Xp3Entry {
+            name: "scenario/first.ks".to_string(),
+            flags: 0,
+            size: 0,
+            packed_size: 0,
+            adler32: None,
+            segments: Vec::new(),
+        };
+
+        let output = entry.output_path(Path::new("/output"));
+        assert!(output.to_string_lossy().contains("scenario"));
+        assert!(output.to_string_lossy().contains("first.ks"));
+        assert!(entry.is_script());
+        assert!(!entry.is_protected());
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+
+        // Exercise the block-wise modulus with a large input
+        let large = vec![0xFFu8; 100_000];
+        let expected = {
+            let (mut a, mut b) = (1u64, 0u64);
+            for &byte in &large {
+                a = (a + byte as u64) % 65521;
+                b = (b + a) % 65521;
+            }
+            ((b << 16) | a) as u32
+        };
+        assert_eq!(adler32(&large), expected);
+    }
+
+    #[test]
+    fn test_is_xp3_archive() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(MAGIC).unwrap();
+        file.write_all(&0u64.to_le_bytes()).unwrap();
+        file.flush().unwrap();
+        assert!(is_xp3_archive(file.path()));
+
+        let mut other = NamedTempFile::new().unwrap();
+        other.write_all(b"RGSSAD\0\x01").unwrap();
+        other.flush().unwrap();
+        assert!(!is_xp3_archive(other.path()));
+    }
+}
@@ -0,0 +1,848 @@
+//! XP3 Archive Reader (Unpacker)
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use flate2::read::ZlibDecoder;
+
+use super::cipher::{NoCipher, Xp3Cipher};
+use super::{
+    Xp3Entry, Xp3Segment, CHUNK_ADLR, CHUNK_FILE, CHUNK_INFO, CHUNK_SEGM, INDEX_CONTINUE,
+    INDEX_ENCODE_MASK, INDEX_ENCODE_RAW, INDEX_ENCODE_ZLIB, MAGIC, SEGMENT_ENCODE_MASK,
+    SEGMENT_ENCODE_RAW, SEGMENT_ENCODE_ZLIB, SEGMENT_RECORD_SIZE,
+};
+use crate::archiver::archive::{check_bounds, find_entry};
+use crate::archiver::{
+    Archive, ArchiveEntryInfo, ArchiveFormat, ArchiveReader, ArchiverError, ArchiverResult,
+};
+
+/// Maximum number of chained index records to follow
+const MAX_INDEX_RECORDS: usize = 16;
+
+/// Most bytes reserved per packed byte before extracting an entry
+///
+/// The original size in the index is untrusted; the buffer grows past this
+/// as segments actually inflate.
+const PREALLOCATION_RATIO: u64 = 8;
+
+/// XP3 Archive Reader for unpacking KiriKiri .xp3 files
+pub struct Xp3Reader {
+    /// Path to the archive file
+    path: std::path::PathBuf,
+    /// File entries in the archive
+    entries: Vec<Xp3Entry>,
+    /// Cipher applied to file contents
+    cipher: Box<dyn Xp3Cipher>,
+}
+
+impl Xp3Reader {
+    /// Use a cipher to decrypt file contents
+    pub fn with_cipher(mut self, cipher: Box<dyn Xp3Cipher>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Replace the cipher used to decrypt file contents
+    pub fn set_cipher(&mut self, cipher: Box<dyn Xp3Cipher>) {
+        self.cipher = cipher;
+    }
+
+    /// Get the cipher used to decrypt file contents
+    pub fn cipher(&self) -> &dyn Xp3Cipher {
+        self.cipher.as_ref()
+    }
+
+    /// Read and decode the (possibly chained) index records
+    fn read_index(path: &Path) -> ArchiverResult<Vec<u8>> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        // Check magic
+        let mut magic = [0u8; 11];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ArchiverError::InvalidFormat(
+                "Not a valid XP3 archive: invalid magic bytes".to_string(),
+            ));
+        }
+
+        let mut index_offset = read_u64(&mut reader)?;
+        let mut index = Vec::new();
+
+        for _ in 0..MAX_INDEX_RECORDS {
+            if index_offset >= file_len {
+                return Err(ArchiverError::InvalidFormat(format!(
+                    "XP3 index offset {:#x} is past the end of the file",
+                    index_offset
+                )));
+            }
+            reader.seek(SeekFrom::Start(index_offset))?;
+
+            let mut flag = [0u8; 1];
+            reader.read_exact(&mut flag)?;
+            let flag = flag[0];
+
+            match flag & INDEX_ENCODE_MASK {
+                INDEX_ENCODE_ZLIB => {
+                    let packed_size = read_u64(&mut reader)?;
+                    let size = read_u64(&mut reader)?;
+                    let packed = read_bytes(&mut reader, packed_size, file_len)?;
+
+                    let mut data = Vec::new();
+                    ZlibDecoder::new(&packed[..])
+                        .read_to_end(&mut data)
+                        .map_err(|e| {
+                            ArchiverError::InvalidFormat(format!(
+                                "Failed to decompress XP3 index: {}",
+                                e
+                            ))
+                        })?;
+
+                    if data.len() as u64 != size {
+                        return Err(ArchiverError::InvalidFormat(format!(
+                            "XP3 index size mismatch: expected {}, got {}",
+                            size,
+                            data.len()
+                        )));
+                    }
+                    index.extend_from_slice(&data);
+                }
+                INDEX_ENCODE_RAW => {
+                    let size = read_u64(&mut reader)?;
+                    let data = read_bytes(&mut reader, size, file_len)?;
+                    index.extend_from_slice(&data);
+                }
+                method => {
+                    return Err(ArchiverError::InvalidFormat(format!(
+                        "Unknown XP3 index encoding: {}",
+                        method
+                    )));
+                }
+            }
+
+            if flag & INDEX_CONTINUE == 0 {
+                return Ok(index);
+            }
+
+            // The next index record offset follows the current record
+            index_offset = read_u64(&mut reader)?;
+        }
+
+        Err(ArchiverError::InvalidFormat(
+            "Too many chained XP3 index records".to_string(),
+        ))
+    }
+
+    /// Parse the decoded index into file entries
+    pub(crate) fn parse_index(index: &[u8]) -> ArchiverResult<Vec<Xp3Entry>> {
+        let mut entries = Vec::new();
+
+        for (tag, body) in Chunks::new(index) {
+            let body = body?;
+            // Unknown top-level chunks (e.g. filename hash tables) are skipped
+            if &tag == CHUNK_FILE {
+                entries.push(Self::parse_file_chunk(body)?);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Parse a single `File` chunk
+    fn parse_file_chunk(data: &[u8]) -> ArchiverResult<Xp3Entry> {
+        let mut info = None;
+        let mut segments = Vec::new();
+        let mut adler32 = None;
+
+        for (tag, body) in Chunks::new(data) {
+            let body = body?;
+            match &tag {
+                t if t == CHUNK_INFO => info = Some(Self::parse_info_chunk(body)?),
+                t if t == CHUNK_SEGM => segments.extend(Self::parse_segm_chunk(body)?),
+                t if t == CHUNK_ADLR => {
+                    if body.len() < 4 {
+                        return Err(truncated("adlr"));
+                    }
+                    adler32 = Some(le_u32(&body[0..4]));
+                }
+                _ => {}
+            }
+        }
+
+        let (flags, size, packed_size, name) = info.ok_or_else(|| {
+            ArchiverError::InvalidFormat("XP3 File chunk without info".to_string())
+        })?;
+
+        Ok(Xp3Entry {
+            name,
+            flags,
+            size,
+            packed_size,
+            adler32,
+            segments,
+        })
+    }
+
+    /// Parse an `info` chunk into (flags, size, packed size, name)
+    fn parse_info_chunk(data: &[u8]) -> ArchiverResult<(u32, u64, u64, String)> {
+        if data.len() < 22 {
+            return Err(truncated("info"));
+        }
+
+        let flags = le_u32(&data[0..4]);
+        let size = le_u64(&data[4..12]);
+        let packed_size = le_u64(&data[12..20]);
+        let name_len = u16::from_le_bytes([data[20], data[21]]) as usize;
+
+        let name_end = 22 + name_len * 2;
+        if data.len() < name_end {
+            return Err(truncated("info"));
+        }
+
+        let units: Vec<u16> = data[22..name_end]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        let name = String::from_utf16_lossy(&units);
+
+        Ok((flags, size, packed_size, name))
+    }
+
+    /// Parse a `segm` chunk into its segment records
+    fn parse_segm_chunk(data: &[u8]) -> ArchiverResult<Vec<Xp3Segment>> {
+        if !data.len().is_multiple_of(SEGMENT_RECORD_SIZE) {
+            return Err(truncated("segm"));
+        }
+
+        data.chunks_exact(SEGMENT_RECORD_SIZE)
+            .map(|record| {
+                let flags = le_u32(&record[0..4]);
+                let compressed = match flags & SEGMENT_ENCODE_MASK {
+                    SEGMENT_ENCODE_RAW => false,
+                    SEGMENT_ENCODE_ZLIB => true,
+                    method => {
+                        return Err(ArchiverError::InvalidFormat(format!(
+                            "Unknown XP3 segment encoding: {}",
+                            method
+                        )))
+                    }
+                };
+
+                Ok(Xp3Segment {
+                    compressed,
+                    offset: le_u64(&record[4..12]),
+                    size: le_u64(&record[12..20]),
+                    packed_size: le_u64(&record[20..28]),
+                })
+            })
+            .collect()
+    }
+
+    /// Extract a single entry to a byte vector, decrypting it with the cipher
+    pub fn extract_to_memory(&self, entry: &Xp3Entry) -> ArchiverResult<Vec<u8>> {
+        let mut data = self.extract_raw_to_memory(entry)?;
+        self.cipher
+            .decrypt(entry.adler32.unwrap_or(0), 0, &mut data);
+        Ok(data)
+    }
+
+    /// Extract a single entry without decrypting it
+    pub fn extract_raw_to_memory(&self, entry: &Xp3Entry) -> ArchiverResult<Vec<u8>> {
+        let file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let packed_len = entry
+            .segments
+            .iter()
+            .fold(0u64, |len, s| len.saturating_add(s.packed_size))
+            .min(file_len);
+        let capacity = entry
+            .size
+            .min(packed_len.saturating_mul(PREALLOCATION_RATIO));
+        let mut data = Vec::with_capacity(capacity as usize);
+
+        for segment in &entry.segments {
+            reader.seek(SeekFrom::Start(segment.offset))?;
+            let packed = read_bytes(&mut reader, segment.packed_size, file_len)?;
+
+            if segment.compressed {
+                let start = data.len();
+                // Stop one byte past the recorded size so an oversized
+                // segment is caught without inflating all of it
+                ZlibDecoder::new(&packed[..])
+                    .take(segment.size.saturating_add(1))
+                    .read_to_end(&mut data)
+                    .map_err(|e| {
+                        ArchiverError::InvalidFormat(format!(
+                            "Failed to decompress segment of {}: {}",
+                            entry.name, e
+                        ))
+                    })?;
+
+                if (data.len() - start) as u64 != segment.size {
+                    return Err(ArchiverError::InvalidFormat(format!(
+                        "Segment size mismatch in {}",
+                        entry.name
+                    )));
+                }
+            } else {
+                data.extend_from_slice(&packed);
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Open a streaming reader that decompresses and decrypts an entry on the fly
+    pub fn entry_reader(&self, entry: &Xp3Entry) -> ArchiverResult<Xp3EntryReader<'_>> {
+        let file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+        for segment in &entry.segments {
+            check_bounds(&entry.name, segment.offset, segment.packed_size, file_len)?;
+        }
+
+        Ok(Xp3EntryReader {
+            file,
+            name: entry.name.clone(),
+            segments: entry.segments.clone().into_iter(),
+            current: None,
+            cipher: self.cipher.as_ref(),
+            hash: entry.adler32.unwrap_or(0),
+            position: 0,
+        })
+    }
+
+    /// Get the archive path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Iterate over KAG scenario (.ks) and TJS script (.tjs) entries
+    pub fn script_entries(&self) -> impl Iterator<Item = &Xp3Entry> {
+        self.entries.iter().filter(|e| e.is_script())
+    }
+}
+
+impl ArchiveReader for Xp3Reader {
+    type Entry = Xp3Entry;
+
+    fn open<P: AsRef<Path>>(path: P) -> ArchiverResult<Self> {
+        let path = path.as_ref();
+
+        let index = Self::read_index(path)?;
+        let entries = Self::parse_index(&index)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+            cipher: Box::new(NoCipher),
+        })
+    }
+
+    fn entries(&self) -> &[Xp3Entry] {
+        &self.entries
+    }
+
+    fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> ArchiverResult<usize> {
+        let output_dir = output_dir.as_ref();
+        let mut count = 0;
+
+        for entry in &self.entries {
+            self.extract_entry(&entry.name, output_dir)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn extract_entry<P: AsRef<Path>>(&self, entry_name: &str, output_dir: P) -> ArchiverResult<()> {
+        let output_dir = output_dir.as_ref();
+
+        // Find the entry
+        let entry = find_entry(&self.entries, entry_name, |e| &e.name)?;
+
+        // Extract to memory
+        let data = self.extract_to_memory(entry)?;
+
+        // Write to file
+        let output_path = entry.output_path(output_dir);
+
+        // Create parent directories
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&output_path, data)?;
+
+        Ok(())
+    }
+}
+
+impl Archive for Xp3Reader {
+    fn format(&self) -> ArchiveFormat {
+        ArchiveFormat::Xp3
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn list_entries(&self) -> Vec<ArchiveEntryInfo> {
+        self.entries
+            .iter()
+            .map(|e| ArchiveEntryInfo {
+                name: e.name.clone(),
+                size: e.size,
+                compressed: e.segments.iter().any(|s| s.compressed),
+            })
+            .collect()
+    }
+
+    fn open_entry(&self, name: &str) -> ArchiverResult<Box<dyn Read + Send + '_>> {
+        let entry = find_entry(&self.entries, name, |e| &e.name)?;
+        Ok(Box::new(self.entry_reader(entry)?))
+    }
+}
+
+/// Streaming reader over the decoded contents of an XP3 entry
+///
+/// Segments are read one after another; compressed segments are inflated
+/// and the reader's cipher is applied at the running file offset.
+pub struct Xp3EntryReader<'a> {
+    /// Archive file, cloned for each segment
+    file: File,
+    /// Entry name, for error messages
+    name: String,
+    /// Segments not started yet
+    segments: std::vec::IntoIter<Xp3Segment>,
+    /// Current segment reader and its remaining decoded size
+    current: Option<(Box<dyn Read + Send>, u64)>,
+    /// Cipher applied to the decoded data
+    cipher: &'a dyn Xp3Cipher,
+    /// Adler-32 hash of the entry
+    hash: u32,
+    /// Offset within the decoded file
+    position: u64,
+}
+
+impl Xp3EntryReader<'_> {
+    /// Start reading the next segment, returning `false` after the last one
+    fn next_segment(&mut self) -> io::Result<bool> {
+        let Some(segment) = self.segments.next() else {
+            return Ok(false);
+        };
+
+        let mut file = self.file.try_clone()?;
+        file.seek(SeekFrom::Start(segment.offset))?;
+        let packed = BufReader::new(file).take(segment.packed_size);
+
+        let reader: Box<dyn Read + Send> = if segment.compressed {
+            Box::new(ZlibDecoder::new(packed))
+        } else {
+            Box::new(packed)
+        };
+        self.current = Some((reader, segment.size));
+
+        Ok(true)
+    }
+}
+
+impl Read for Xp3EntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let Some((reader, remaining)) = &mut self.current else {
+                if self.next_segment()? {
+                    continue;
+                }
+                return Ok(0);
+            };
+
+            let limit = buf
+                .len()
+                .min(usize::try_from(*remaining).unwrap_or(usize::MAX));
+            let read = if limit == 0 {
+                0
+            } else {
+                reader.read(&mut buf[..limit])?
+            };
+
+            if read == 0 {
+                // The segment must decode to exactly its recorded size
+                let mut probe = [0u8; 1];
+                if *remaining != 0 || reader.read(&mut probe)? != 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Segment size mismatch in {}", self.name),
+                    ));
+                }
+                self.current = None;
+                continue;
+            }
+
+            *remaining -= read as u64;
+            self.cipher
+                .decrypt(self.hash, self.position, &mut buf[..read]);
+            self.position += read as u64;
+            return Ok(read);
+        }
+    }
+}
+
+/// Iterator over `tag(4) + size(u64) + body` chunks
+struct Chunks<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Chunks<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = ([u8; 4], ArchiverResult<&'a [u8]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos + 12 > self.data.len() {
+            return None;
+        }
+
+        let mut tag = [0u8; 4];
+        tag.copy_from_slice(&self.data[self.pos..self.pos + 4]);
+        let size = le_u64(&self.data[self.pos + 4..self.pos + 12]);
+        let start = self.pos + 12;
+
+        let end = match usize::try_from(size)
+            .ok()
+            .and_then(|s| start.checked_add(s))
+        {
+            Some(end) if end <= self.data.len() => end,
+            _ => {
+                // Stop iterating after reporting the broken chunk
+                self.pos = self.data.len();
+                return Some((tag, Err(truncated(&String::from_utf8_lossy(&tag)))));
+            }
+        };
+
+        self.pos = end;
+        Some((tag, Ok(&self.data[start..end])))
+    }
+}
+
+fn truncated(chunk: &str) -> ArchiverError {
+    ArchiverError::InvalidFormat(format!("Truncated XP3 {} chunk", chunk))
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn le_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[0..8]);
+    u64::from_le_bytes(buf)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> ArchiverResult<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Read `size` bytes, refusing sizes that cannot fit in the file
+fn read_bytes<R: Read>(reader: &mut R, size: u64, file_len: u64) -> ArchiverResult<Vec<u8>> {
+    if size > file_len {
+        return Err(ArchiverError::InvalidFormat(format!(
+            "XP3 record size {} exceeds archive size {}",
+            size, file_len
+        )));
+    }
+
+    let mut buf = vec![0u8; size as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archiver::xp3::FILE_PROTECTED;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn chunk(tag: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = tag.to_vec();
+        out.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn file_chunk(
+        name: &str,
+        flags: u32,
+        size: u64,
+        segments: &[(bool, u64, u64, u64)],
+    ) -> Vec<u8> {
+        let utf16: Vec<u16> = name.encode_utf16().collect();
+        let packed: u64 = segments.iter().map(|s| s.3).sum();
+
+        let mut info = flags.to_le_bytes().to_vec();
+        info.extend_from_slice(&size.to_le_bytes());
+        info.extend_from_slice(&packed.to_le_bytes());
+        info.extend_from_slice(&(utf16.len() as u16).to_le_bytes());
+        for unit in utf16 {
+            info.extend_from_slice(&unit.to_le_bytes());
+        }
+
+        let mut segm = Vec::new();
+        for &(compressed, offset, size, packed_size) in segments {
+            segm.extend_from_slice(&(compressed as u32).to_le_bytes());
+            segm.extend_from_slice(&offset.to_le_bytes());
+            segm.extend_from_slice(&size.to_le_bytes());
+            segm.extend_from_slice(&packed_size.to_le_bytes());
+        }
+
+        let mut body = chunk(CHUNK_INFO, &info);
+        body.extend(chunk(CHUNK_SEGM, &segm));
+        body.extend(chunk(CHUNK_ADLR, &0x1234_5678u32.to_le_bytes()));
+        chunk(CHUNK_FILE, &body)
+    }
+
+    /// Build a small archive by hand: one raw file and one two-segment
+    /// file (raw + zlib), optionally with a cushion header and zlib index
+    fn build_archive(cushion: bool, compress_index: bool) -> Vec<u8> {
+        let header_len: u64 = if cushion { 0x28 } else { 0x13 };
+
+        let first = b"*start\n[wait time=200]\n";
+        let second_a = b"Hello, ";
+        let second_b = zlib(b"KiriKiri!");
+
+        let mut data = Vec::new();
+        let first_offset = header_len;
+        data.extend_from_slice(first);
+        let second_a_offset = first_offset + first.len() as u64;
+        data.extend_from_slice(second_a);
+        let second_b_offset = second_a_offset + second_a.len() as u64;
+        data.extend_from_slice(&second_b);
+
+        let mut index = file_chunk(
+            "scenario/first.ks",
+            0,
+            first.len() as u64,
+            &[(false, first_offset, first.len() as u64, first.len() as u64)],
+        );
+        index.extend(chunk(b"hnfn", b"ignored"));
+        index.extend(file_chunk(
+            "system/テスト.tjs",
+            FILE_PROTECTED,
+            16,
+            &[
+                (false, second_a_offset, 7, 7),
+                (true, second_b_offset, 9, second_b.len() as u64),
+            ],
+        ));
+
+        let index_offset = header_len + data.len() as u64;
+
+        let mut out = MAGIC.to_vec();
+        if cushion {
+            out.extend_from_slice(&0x17u64.to_le_bytes());
+            out.extend_from_slice(&1u32.to_le_bytes());
+            out.push(INDEX_CONTINUE);
+            out.extend_from_slice(&0u64.to_le_bytes());
+            out.extend_from_slice(&index_offset.to_le_bytes());
+        } else {
+            out.extend_from_slice(&index_offset.to_le_bytes());
+        }
+        assert_eq!(out.len() as u64, header_len);
+
+        out.extend(data);
+        if compress_index {
+            let packed = zlib(&index);
+            out.push(INDEX_ENCODE_ZLIB);
+            out.extend_from_slice(&(packed.len() as u64).to_le_bytes());
+            out.extend_from_slice(&(index.len() as u64).to_le_bytes());
+            out.extend(packed);
+        } else {
+            out.push(INDEX_ENCODE_RAW);
+            out.extend_from_slice(&(index.len() as u64).to_le_bytes());
+            out.extend(index);
+        }
+        out
+    }
+
+    fn check_archive(bytes: Vec<u8>) {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("data.xp3");
+        fs::write(&archive_path, bytes).unwrap();
+
+        let reader = Xp3Reader::open(&archive_path).unwrap();
+        assert_eq!(reader.entries().len(), 2);
+        assert_eq!(reader.entries()[0].name, "scenario/first.ks");
+        assert_eq!(reader.entries()[1].name, "system/テスト.tjs");
+        assert!(reader.entries()[1].is_protected());
+        assert_eq!(reader.entries()[1].adler32, Some(0x1234_5678));
+        assert_eq!(reader.script_entries().count(), 2);
+
+        let first = reader.extract_to_memory(&reader.entries()[0]).unwrap();
+        assert_eq!(first, b"*start\n[wait time=200]\n");
+
+        let second = reader.extract_to_memory(&reader.entries()[1]).unwrap();
+        assert_eq!(second, b"Hello, KiriKiri!");
+
+        let mut streamed = Vec::new();
+        reader
+            .entry_reader(&reader.entries()[1])
+            .unwrap()
+            .read_to_end(&mut streamed)
+            .unwrap();
+        assert_eq!(streamed, second);
+
+        let output_dir = temp_dir.path().join("out");
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 2);
+        assert_eq!(
+            fs::read(output_dir.join("system/テスト.tjs")).unwrap(),
+            b"Hello, KiriKiri!"
+        );
+    }
+
+    #[test]
+    fn test_read_raw_index() {
+        check_archive(build_archive(false, false));
+    }
+
+    #[test]
+    fn test_read_compressed_index_with_cushion() {
+        check_archive(build_archive(true, true));
+    }
+
+    #[test]
+    fn test_entry_reader_with_cipher() {
+        use crate::archiver::xp3::Xp3CipherScheme;
+
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("data.xp3");
+        fs::write(&archive_path, build_archive(false, false)).unwrap();
+
+        let reader = Xp3Reader::open(&archive_path)
+            .unwrap()
+            .with_cipher(Xp3CipherScheme::HashXor { shift: 8 }.cipher());
+        let entry = &reader.entries()[1];
+
+        let mut streamed = Vec::new();
+        reader
+            .entry_reader(entry)
+            .unwrap()
+            .read_to_end(&mut streamed)
+            .unwrap();
+        assert_eq!(streamed, reader.extract_to_memory(entry).unwrap());
+        assert_ne!(streamed, b"Hello, KiriKiri!");
+    }
+
+    #[test]
+    fn test_entry_reader_size_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("data.xp3");
+        fs::write(&archive_path, build_archive(false, false)).unwrap();
+
+        let reader = Xp3Reader::open(&archive_path).unwrap();
+        let mut entry = reader.entries()[1].clone();
+        entry.segments[1].size += 1;
+
+        let mut data = Vec::new();
+        let result = reader.entry_reader(&entry).unwrap().read_to_end(&mut data);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_invalid_magic() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("bad.xp3");
+        fs::write(&archive_path, b"NOTXP3ARCHIVE\0\0\0\0\0\0\0\0").unwrap();
+
+        let result = Xp3Reader::open(&archive_path);
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_parse_truncated_index() {
+        let mut index = file_chunk("a.ks", 0, 1, &[(false, 0, 1, 1)]);
+        index.truncate(index.len() - 3);
+
+        let result = Xp3Reader::parse_index(&index);
+        assert!(matches!(result, Err(ArchiverError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_extract_crafted_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("data.xp3");
+
+        let packed = zlib(b"KiriKiri!");
+        let data_offset = MAGIC.len() as u64 + 8;
+        let index_offset = data_offset + packed.len() as u64;
+
+        // An absurd original size must not be preallocated, and a segment
+        // inflating past its recorded size must be rejected
+        let mut index = file_chunk(
+            "huge.ks",
+            0,
+            1 << 62,
+            &[(true, data_offset, 9, packed.len() as u64)],
+        );
+        index.extend(file_chunk(
+            "bomb.ks",
+            0,
+            4,
+            &[(true, data_offset, 4, packed.len() as u64)],
+        ));
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&index_offset.to_le_bytes());
+        bytes.extend_from_slice(&packed);
+        bytes.push(INDEX_ENCODE_RAW);
+        bytes.extend_from_slice(&(index.len() as u64).to_le_bytes());
+        bytes.extend(index);
+        fs::write(&archive_path, bytes).unwrap();
+
+        let reader = Xp3Reader::open(&archive_path).unwrap();
+        let huge = reader.extract_raw_to_memory(&reader.entries()[0]).unwrap();
+        assert_eq!(huge, b"KiriKiri!");
+
+        let bomb = reader.extract_raw_to_memory(&reader.entries()[1]);
+        assert!(matches!(bomb, Err(ArchiverError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_extract_entry_normalizes_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("data.xp3");
+        fs::write(&archive_path, build_archive(false, false)).unwrap();
+
+        let reader = Xp3Reader::open(&archive_path).unwrap();
+        let output_dir = temp_dir.path().join("out");
+        reader
+            .extract_entry("scenario\\first.ks", &output_dir)
+            .unwrap();
+        assert_eq!(
+            fs::read(output_dir.join("scenario/first.ks")).unwrap(),
+            b"*start\n[wait time=200]\n"
+        );
+    }
+}
@@ -0,0 +1,494 @@
+//! XP3 Archive Writer (Repacker)
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use super::cipher::{NoCipher, Xp3Cipher};
+use super::{
+    adler32, CHUNK_ADLR, CHUNK_FILE, CHUNK_INFO, CHUNK_SEGM, INDEX_CONTINUE, INDEX_ENCODE_ZLIB,
+    MAGIC, SEGMENT_ENCODE_RAW, SEGMENT_ENCODE_ZLIB,
+};
+use crate::archiver::{ArchiveWriter, ArchiverError, ArchiverResult, Operation};
+
+/// Offset of the cushion index record in the header
+const CUSHION_INDEX_OFFSET: u64 = 0x17;
+/// Total header size: magic + cushion pointer + minor version + cushion record
+const HEADER_SIZE: u64 = 0x28;
+
+/// File entry to be packed
+#[derive(Debug)]
+struct PackEntry {
+    /// Name in the archive (with forward slashes)
+    archive_name: String,
+    /// File data
+    data: Vec<u8>,
+}
+
+/// Stored form of a packed entry
+struct StoredEntry {
+    /// Data as written to the archive
+    data: Vec<u8>,
+    /// Whether `data` is zlib-compressed
+    compressed: bool,
+    /// Adler-32 of the original data
+    checksum: u32,
+}
+
+/// XP3 Archive Writer for creating KiriKiri .xp3 files (e.g. `patch.xp3`)
+pub struct Xp3Writer {
+    /// Files to pack
+    entries: Vec<PackEntry>,
+    /// Whether to zlib-compress file segments
+    compress: bool,
+    /// Cipher applied to file contents
+    cipher: Box<dyn Xp3Cipher>,
+}
+
+impl Xp3Writer {
+    /// Enable or disable zlib compression of file segments
+    ///
+    /// The index is always compressed.
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Use a cipher to encrypt file contents
+    ///
+    /// Contents are encrypted before compression, keyed by the plain data
+    /// checksum.
+    pub fn with_cipher(mut self, cipher: Box<dyn Xp3Cipher>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Compress data with zlib
+    fn deflate(data: &[u8]) -> ArchiverResult<Vec<u8>> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// Prepare the stored form of an entry
+    fn store(&self, entry: &PackEntry) -> ArchiverResult<StoredEntry> {
+        let checksum = adler32(&entry.data);
+
+        let mut data = entry.data.clone();
+        self.cipher.encrypt(checksum, 0, &mut data);
+
+        if self.compress {
+            let packed = Self::deflate(&data)?;
+            // Keep incompressible data raw, as krkrrel does
+            if packed.len() < data.len() {
+                return Ok(StoredEntry {
+                    data: packed,
+                    compressed: true,
+                    checksum,
+                });
+            }
+        }
+
+        Ok(StoredEntry {
+            data,
+            compressed: false,
+            checksum,
+        })
+    }
+
+    /// Build a `File` chunk for an entry
+    fn file_chunk(entry: &PackEntry, stored: &StoredEntry, offset: u64) -> ArchiverResult<Vec<u8>> {
+        let name: Vec<u16> = entry.archive_name.encode_utf16().collect();
+        let name_len = u16::try_from(name.len()).map_err(|_| {
+            ArchiverError::InvalidFormat(format!("File name too long: {}", entry.archive_name))
+        })?;
+
+        let size = entry.data.len() as u64;
+        let packed_size = stored.data.len() as u64;
+
+        // info: flags, original size, packed size, name
+        let mut info = Vec::with_capacity(22 + name.len() * 2);
+        info.extend_from_slice(&0u32.to_le_bytes());
+        info.extend_from_slice(&size.to_le_bytes());
+        info.extend_from_slice(&packed_size.to_le_bytes());
+        info.extend_from_slice(&name_len.to_le_bytes());
+        for unit in name {
+            info.extend_from_slice(&unit.to_le_bytes());
+        }
+
+        // segm: a single segment covering the whole file
+        let segment_flags = if stored.compressed {
+            SEGMENT_ENCODE_ZLIB
+        } else {
+            SEGMENT_ENCODE_RAW
+        };
+        let mut segm = Vec::with_capacity(28);
+        segm.extend_from_slice(&segment_flags.to_le_bytes());
+        segm.extend_from_slice(&offset.to_le_bytes());
+        segm.extend_from_slice(&size.to_le_bytes());
+        segm.extend_from_slice(&packed_size.to_le_bytes());
+
+        let mut body = chunk(CHUNK_INFO, &info);
+        body.extend(chunk(CHUNK_SEGM, &segm));
+        body.extend(chunk(CHUNK_ADLR, &stored.checksum.to_le_bytes()));
+
+        Ok(chunk(CHUNK_FILE, &body))
+    }
+
+    /// Write the archive, counting entries as they are stored
+    fn write_archive(&self, output_path: &Path, operation: &Operation) -> ArchiverResult<()> {
+        // Prepare stored data and the index
+        let mut index = Vec::new();
+        let mut stored_entries = Vec::with_capacity(self.entries.len());
+        let mut offset = HEADER_SIZE;
+
+        for entry in &self.entries {
+            let stored = self.store(entry)?;
+            index.extend(Self::file_chunk(entry, &stored, offset)?);
+            offset += stored.data.len() as u64;
+            stored_entries.push(stored);
+            operation.entry_done(&entry.archive_name, entry.data.len() as u64)?;
+        }
+
+        let index_offset = offset;
+        let packed_index = Self::deflate(&index)?;
+
+        let file = File::create(output_path)?;
+        operation.track_output(output_path.to_path_buf());
+        let mut writer = BufWriter::new(file);
+
+        // Write header with the cushion index record
+        writer.write_all(MAGIC)?;
+        writer.write_all(&CUSHION_INDEX_OFFSET.to_le_bytes())?;
+        writer.write_all(&1u32.to_le_bytes())?;
+        writer.write_all(&[INDEX_CONTINUE])?;
+        writer.write_all(&0u64.to_le_bytes())?;
+        writer.write_all(&index_offset.to_le_bytes())?;
+
+        // Write file data
+        for stored in &stored_entries {
+            operation.check()?;
+            writer.write_all(&stored.data)?;
+        }
+
+        // Write compressed index
+        writer.write_all(&[INDEX_ENCODE_ZLIB])?;
+        writer.write_all(&(packed_index.len() as u64).to_le_bytes())?;
+        writer.write_all(&(index.len() as u64).to_le_bytes())?;
+        writer.write_all(&packed_index)?;
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl ArchiveWriter for Xp3Writer {
+    fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            compress: true,
+            cipher: Box::new(NoCipher),
+        }
+    }
+
+    fn add_file<P: AsRef<Path>>(&mut self, path: P, archive_name: &str) -> ArchiverResult<()> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Err(ArchiverError::FileNotFound(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+
+        let mut file = File::open(path)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        // XP3 uses forward slashes for storage paths
+        let normalized_name = archive_name.replace('\\', "/");
+
+        self.entries.push(PackEntry {
+            archive_name: normalized_name,
+            data,
+        });
+
+        Ok(())
+    }
+
+    fn add_directory<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+    ) -> ArchiverResult<usize> {
+        self.add_directory_with(dir, base_path, &Operation::new())
+    }
+
+    fn add_directory_with<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        base_path: Option<&str>,
+        operation: &Operation,
+    ) -> ArchiverResult<usize> {
+        let dir = dir.as_ref();
+
+        if !dir.exists() {
+            return Err(ArchiverError::FileNotFound(
+                dir.to_string_lossy().to_string(),
+            ));
+        }
+
+        // List the files first so progress has totals
+        let mut files = Vec::new();
+        let mut total_bytes = 0;
+        for entry in walkdir::WalkDir::new(dir)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+
+            if path.is_file() {
+                // Calculate archive name
+                let relative_path = path.strip_prefix(dir).map_err(|e| {
+                    ArchiverError::InvalidFormat(format!("Failed to get relative path: {}", e))
+                })?;
+
+                let relative_name = relative_path.to_string_lossy().replace('\\', "/");
+                let archive_name = match base_path {
+                    Some(base) => format!("{}/{}", base.trim_end_matches('/'), relative_name),
+                    None => relative_name,
+                };
+
+                total_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                files.push((path.to_path_buf(), archive_name));
+            }
+        }
+
+        operation.begin(files.len(), total_bytes);
+        for (path, archive_name) in &files {
+            self.add_file(path, archive_name)?;
+            let size = self.entries.last().map_or(0, |e| e.data.len() as u64);
+            operation.entry_done(archive_name, size)?;
+        }
+
+        Ok(files.len())
+    }
+
+    fn write<P: AsRef<Path>>(self, output_path: P) -> ArchiverResult<()> {
+        self.write_with(output_path, &Operation::new())
+    }
+
+    fn write_with<P: AsRef<Path>>(
+        self,
+        output_path: P,
+        operation: &Operation,
+    ) -> ArchiverResult<()> {
+        let total_bytes = self.entries.iter().map(|e| e.data.len() as u64).sum();
+        operation.begin(self.entries.len(), total_bytes);
+
+        let result = self.write_archive(output_path.as_ref(), operation);
+        operation.finish(result)
+    }
+}
+
+/// Build a `tag(4) + size(u64) + body` chunk
+fn chunk(tag: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(12 + body.len());
+    out.extend_from_slice(tag);
+    out.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::cipher::{detect_cipher, Xp3CipherScheme};
+    use super::super::Xp3Reader;
+    use super::*;
+    use crate::archiver::ArchiveReader;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn pack_and_read(compress: bool) {
+        let temp_dir = TempDir::new().unwrap();
+
+        let scenario = "*start\n【アリス】こんにちは。[p]\n".repeat(20);
+        let source = temp_dir.path().join("first.ks");
+        fs::write(&source, &scenario).unwrap();
+
+        let archive_path = temp_dir.path().join("patch.xp3");
+        let mut writer = Xp3Writer::new().with_compression(compress);
+        writer.add_file(&source, "scenario\\first.ks").unwrap();
+        writer.write(&archive_path).unwrap();
+
+        let reader = Xp3Reader::open(&archive_path).unwrap();
+        assert_eq!(reader.entries().len(), 1);
+
+        let entry = &reader.entries()[0];
+        assert_eq!(entry.name, "scenario/first.ks");
+        assert_eq!(entry.size, scenario.len() as u64);
+        assert_eq!(entry.segments.len(), 1);
+        assert_eq!(entry.segments[0].compressed, compress);
+
+        let extracted = reader.extract_to_memory(entry).unwrap();
+        assert_eq!(extracted, scenario.as_bytes());
+        assert_eq!(entry.adler32, Some(adler32(&extracted)));
+    }
+
+    #[test]
+    fn test_pack_unpack_compressed() {
+        pack_and_read(true);
+    }
+
+    #[test]
+    fn test_pack_unpack_uncompressed() {
+        pack_and_read(false);
+    }
+
+    #[test]
+    fn test_incompressible_data_stored_raw() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("tiny.tjs");
+        fs::write(&source, b"x").unwrap();
+
+        let archive_path = temp_dir.path().join("patch.xp3");
+        let mut writer = Xp3Writer::new();
+        writer.add_file(&source, "tiny.tjs").unwrap();
+        writer.write(&archive_path).unwrap();
+
+        let reader = Xp3Reader::open(&archive_path).unwrap();
+        assert!(!reader.entries()[0].segments[0].compressed);
+        assert_eq!(
+            reader.extract_to_memory(&reader.entries()[0]).unwrap(),
+            b"x"
+        );
+    }
+
+    #[test]
+    fn test_pack_directory_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let source_dir = temp_dir.path().join("patch");
+        fs::create_dir_all(source_dir.join("scenario")).unwrap();
+        fs::create_dir_all(source_dir.join("system")).unwrap();
+        fs::write(source_dir.join("scenario/first.ks"), "*start\nHello[p]\n").unwrap();
+        fs::write(
+            source_dir.join("system/Config.tjs"),
+            "var title = \"Test\";",
+        )
+        .unwrap();
+        fs::write(source_dir.join("empty.txt"), b"").unwrap();
+
+        let archive_path = temp_dir.path().join("patch.xp3");
+        let mut writer = Xp3Writer::new();
+        assert_eq!(writer.add_directory(&source_dir, None).unwrap(), 3);
+        writer.write(&archive_path).unwrap();
+
+        let reader = Xp3Reader::open(&archive_path).unwrap();
+        assert_eq!(reader.entries().len(), 3);
+
+        let output_dir = temp_dir.path().join("out");
+        assert_eq!(reader.extract_all(&output_dir).unwrap(), 3);
+
+        for name in ["scenario/first.ks", "system/Config.tjs", "empty.txt"] {
+            assert_eq!(
+                fs::read(output_dir.join(name)).unwrap(),
+                fs::read(source_dir.join(name)).unwrap(),
+                "mismatch in {}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_add_directory_with_base_path() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("first.ks"), "text").unwrap();
+
+        let mut writer = Xp3Writer::new();
+        writer
+            .add_directory(temp_dir.path(), Some("scenario/"))
+            .unwrap();
+
+        assert_eq!(writer.entries[0].archive_name, "scenario/first.ks");
+    }
+
+    #[test]
+    fn test_pack_unpack_encrypted() {
+        let temp_dir = TempDir::new().unwrap();
+        let startup = "// startup.tjs\r\nStorages.addAutoPath(\"scenario/\");\r\n".repeat(4);
+        fs::write(temp_dir.path().join("startup.tjs"), &startup).unwrap();
+
+        let scheme = Xp3CipherScheme::HashXor { shift: 8 };
+        let archive_path = temp_dir.path().join("data.xp3");
+        let mut writer = Xp3Writer::new().with_cipher(scheme.cipher());
+        writer
+            .add_file(temp_dir.path().join("startup.tjs"), "startup.tjs")
+            .unwrap();
+        writer.write(&archive_path).unwrap();
+
+        // Without the cipher the contents stay scrambled
+        let reader = Xp3Reader::open(&archive_path).unwrap();
+        let entry = &reader.entries()[0];
+        assert_ne!(reader.extract_to_memory(entry).unwrap(), startup.as_bytes());
+        assert_eq!(detect_cipher(&reader).unwrap(), Some(scheme));
+
+        let reader = reader.with_cipher(scheme.cipher());
+        let extracted = reader.extract_to_memory(&reader.entries()[0]).unwrap();
+        assert_eq!(extracted, startup.as_bytes());
+        assert_eq!(reader.entries()[0].adler32, Some(adler32(&extracted)));
+    }
+
+    #[test]
+    fn test_add_missing_file() {
+        let mut writer = Xp3Writer::new();
+        let result = writer.add_file("/nonexistent/file.ks", "file.ks");
+        assert!(matches!(result, Err(ArchiverError::FileNotFound(_))));
+    }
+
+    #[test]
+    fn test_write_progress_and_cancel() {
+        use crate::archiver::{ArchiveProgress, CancellationToken};
+        use std::sync::{Arc, Mutex};
+
+        let temp_dir = TempDir::new().unwrap();
+        let source_dir = temp_dir.path().join("patch");
+        fs::create_dir_all(&source_dir).unwrap();
+        for i in 0..4 {
+            fs::write(source_dir.join(format!("scene{}.ks", i)), b"[p]\n").unwrap();
+        }
+
+        let mut writer = Xp3Writer::new();
+        writer.add_directory(&source_dir, None).unwrap();
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let sink_updates = Arc::clone(&updates);
+        let operation = Operation::new()
+            .with_sink(move |p: &ArchiveProgress| sink_updates.lock().unwrap().push(p.clone()));
+        let archive_path = temp_dir.path().join("patch.xp3");
+        writer.write_with(&archive_path, &operation).unwrap();
+        assert_eq!(updates.lock().unwrap().len(), 4);
+        assert_eq!(updates.lock().unwrap().last().unwrap().bytes_done, 16);
+
+        // Cancel after the second entry is stored
+        let mut writer = Xp3Writer::new();
+        writer.add_directory(&source_dir, None).unwrap();
+        let token = CancellationToken::new();
+        let sink_token = token.clone();
+        let operation = Operation::new()
+            .with_token(token)
+            .with_sink(move |p: &ArchiveProgress| {
+                if p.entries_done == 2 {
+                    sink_token.cancel();
+                }
+            });
+        let archive_path = temp_dir.path().join("cancelled.xp3");
+        assert!(matches!(
+            writer.write_with(&archive_path, &operation),
+            Err(ArchiverError::Cancelled)
+        ));
+        assert!(!archive_path.exists());
+    }
+}
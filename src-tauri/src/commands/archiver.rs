@@ -0,0 +1,337 @@
+//! Tauri commands for game archives
+
+use std::path::Path;
+
+use crate::archiver::diff::{diff_paths, ArchiveDiff};
+use crate::archiver::rgss::{
+    RgssPatchReport, RgssPatcher, RgssReadOptions, RgssReader, RgssReport,
+};
+use crate::archiver::rpgmv::RpgMvCrypter;
+use crate::archiver::xp3::{detect_cipher, Xp3CipherScheme, Xp3Reader};
+use crate::archiver::{
+    Archive, ArchiveEntryInfo, ArchiveFormat, ArchiveProgress, ArchiveReader, CancellationToken,
+    ExtractOptions, ExtractReport, OpenOptions, Operation,
+};
+use crate::commands::project::AppState;
+use crate::storage::ProjectStore;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+/// Project setting key for the selected XP3 cipher scheme
+const XP3_CIPHER_SETTING: &str = "xp3_cipher";
+
+/// Event emitted with the progress of an archive operation
+pub const ARCHIVE_PROGRESS_EVENT: &str = "archive-progress";
+
+/// Payload of [`ARCHIVE_PROGRESS_EVENT`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveProgressEvent {
+    /// Id given by the frontend when starting the operation
+    operation_id: Option<String>,
+    #[serde(flatten)]
+    progress: ArchiveProgress,
+}
+
+/// Create an operation that emits progress events and can be cancelled by id
+fn start_operation(
+    app: &AppHandle,
+    state: &AppState,
+    operation_id: Option<String>,
+) -> Result<Operation, String> {
+    let token = CancellationToken::new();
+    if let Some(id) = &operation_id {
+        state
+            .archive_operations
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(id.clone(), token.clone());
+    }
+
+    let app = app.clone();
+    Ok(Operation::new()
+        .with_token(token)
+        .with_sink(move |progress: &ArchiveProgress| {
+            let event = ArchiveProgressEvent {
+                operation_id: operation_id.clone(),
+                progress: progress.clone(),
+            };
+            if let Err(e) = app.emit(ARCHIVE_PROGRESS_EVENT, event) {
+                tracing::warn!("Failed to emit archive progress: {}", e);
+            }
+        }))
+}
+
+/// Forget the cancellation token of a finished operation
+fn end_operation(state: &AppState, operation_id: Option<&str>) {
+    if let (Some(id), Ok(mut operations)) = (operation_id, state.archive_operations.lock()) {
+        operations.remove(id);
+    }
+}
+
+/// Cancel a running archive operation
+///
+/// Returns `false` if no operation with this id is running.
+#[tauri::command]
+pub async fn cancel_archive_operation(
+    operation_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let operations = state.archive_operations.lock().map_err(|e| e.to_string())?;
+    match operations.get(&operation_id) {
+        Some(token) => {
+            token.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Get the XP3 cipher scheme stored for a project
+fn project_xp3_cipher(state: &AppState, id: &str) -> Result<Xp3CipherScheme, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let store = ProjectStore::new(&db);
+
+    match store.get_setting(id, XP3_CIPHER_SETTING)? {
+        Some(value) => serde_json::from_str(&value).map_err(|e| e.to_string()),
+        None => Ok(Xp3CipherScheme::default()),
+    }
+}
+
+/// Get the options for opening archives of a project, if given
+fn project_open_options(project_id: Option<&str>, state: &AppState) -> Result<OpenOptions, String> {
+    match project_id {
+        Some(id) => Ok(OpenOptions::default().with_xp3_cipher(project_xp3_cipher(state, id)?)),
+        None => Ok(OpenOptions::default()),
+    }
+}
+
+/// Open an archive with the settings of a project, if given
+fn open_project_archive(
+    archive_path: &str,
+    project_id: Option<&str>,
+    state: &AppState,
+) -> Result<Box<dyn Archive>, String> {
+    let options = project_open_options(project_id, state)?;
+    ArchiveFormat::open_with(archive_path, &options).map_err(|e| e.to_string())
+}
+
+/// List the entries of an archive of any supported format
+///
+/// XP3 archives are decrypted with the cipher selected for `project_id`.
+#[tauri::command]
+pub async fn list_archive_entries(
+    archive_path: String,
+    project_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ArchiveEntryInfo>, String> {
+    let archive = open_project_archive(&archive_path, project_id.as_deref(), &state)?;
+    Ok(archive.list_entries())
+}
+
+/// Extract the entries of an archive of any supported format
+///
+/// `options` selects entries by glob and decides whether existing files are
+/// overwritten; all entries are extracted if not given. With `dry_run`,
+/// nothing is written and the report lists what would be extracted.
+/// XP3 archives are decrypted with the cipher selected for `project_id`.
+///
+/// Progress is emitted as [`ARCHIVE_PROGRESS_EVENT`] tagged with
+/// `operation_id`, which can also be passed to [`cancel_archive_operation`].
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn extract_archive(
+    archive_path: String,
+    output_dir: String,
+    options: Option<ExtractOptions>,
+    dry_run: bool,
+    project_id: Option<String>,
+    operation_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ExtractReport, String> {
+    let archive = open_project_archive(&archive_path, project_id.as_deref(), &state)?;
+    extract_selected(
+        archive.as_ref(),
+        &output_dir,
+        options,
+        dry_run,
+        operation_id,
+        &app,
+        &state,
+    )
+}
+
+/// Compare two archives, or an archive and an unpacked folder
+///
+/// Either path may be a folder. Entries are compared by size and content
+/// hash. XP3 archives are decrypted with the cipher selected for
+/// `project_id`.
+#[tauri::command]
+pub async fn diff_archives(
+    old_path: String,
+    new_path: String,
+    project_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ArchiveDiff, String> {
+    let options = project_open_options(project_id.as_deref(), &state)?;
+    diff_paths(&old_path, &new_path, &options).map_err(|e| e.to_string())
+}
+
+/// Check an RGSS archive and report damaged or suspicious records
+#[tauri::command]
+pub async fn verify_rgss_archive(archive_path: String) -> Result<RgssReport, String> {
+    RgssReader::verify(&archive_path).map_err(|e| e.to_string())
+}
+
+/// Extract an RGSS archive, optionally skipping damaged records
+///
+/// Takes the same `options`, `dry_run` and `operation_id` as
+/// [`extract_archive`].
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn extract_rgss_archive(
+    archive_path: String,
+    output_dir: String,
+    salvage: bool,
+    options: Option<ExtractOptions>,
+    dry_run: bool,
+    operation_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ExtractReport, String> {
+    let read_options = if salvage {
+        RgssReadOptions::salvage()
+    } else {
+        RgssReadOptions::default()
+    };
+
+    let reader = RgssReader::open_with(&archive_path, read_options).map_err(|e| e.to_string())?;
+    extract_selected(
+        &reader,
+        &output_dir,
+        options,
+        dry_run,
+        operation_id,
+        &app,
+        &state,
+    )
+}
+
+/// Extract or plan the extraction of the entries selected by `options`
+fn extract_selected(
+    archive: &dyn Archive,
+    output_dir: &str,
+    options: Option<ExtractOptions>,
+    dry_run: bool,
+    operation_id: Option<String>,
+    app: &AppHandle,
+    state: &AppState,
+) -> Result<ExtractReport, String> {
+    let options = options.unwrap_or_default();
+    let output_dir = Path::new(output_dir);
+
+    if dry_run {
+        return archive
+            .plan_extract(output_dir, &options)
+            .map_err(|e| e.to_string());
+    }
+
+    let operation = start_operation(app, state, operation_id.clone())?;
+    let report = archive.extract_with(output_dir, &options, &operation);
+    end_operation(state, operation_id.as_deref());
+
+    report.map_err(|e| e.to_string())
+}
+
+/// Patch an RGSS archive with the files of a directory
+///
+/// Files replace the entries with the same relative path; other files are
+/// added. Writes to `output_path`, or over the archive if not given.
+///
+/// Progress is emitted as [`ARCHIVE_PROGRESS_EVENT`] tagged with
+/// `operation_id`, which can also be passed to [`cancel_archive_operation`].
+/// A cancelled patch leaves the output untouched.
+#[tauri::command]
+pub async fn patch_rgss_archive(
+    archive_path: String,
+    replacement_dir: String,
+    output_path: Option<String>,
+    operation_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<RgssPatchReport, String> {
+    let mut patcher = RgssPatcher::open(&archive_path).map_err(|e| e.to_string())?;
+    patcher
+        .replace_directory(&replacement_dir, None)
+        .map_err(|e| e.to_string())?;
+
+    let operation = start_operation(&app, &state, operation_id.clone())?;
+    let report = patcher.write_with(output_path.as_deref().unwrap_or(&archive_path), &operation);
+    end_operation(&state, operation_id.as_deref());
+
+    report.map_err(|e| e.to_string())
+}
+
+/// Detect the cipher scheme of an XP3 archive
+///
+/// Returns `None` if no known scheme produces readable scripts.
+#[tauri::command]
+pub async fn detect_xp3_cipher(archive_path: String) -> Result<Option<Xp3CipherScheme>, String> {
+    let reader = Xp3Reader::open(&archive_path).map_err(|e| e.to_string())?;
+    detect_cipher(&reader).map_err(|e| e.to_string())
+}
+
+/// Get the XP3 cipher scheme selected for a project
+#[tauri::command]
+pub async fn get_project_xp3_cipher(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Xp3CipherScheme, String> {
+    project_xp3_cipher(&state, &id)
+}
+
+/// Select the XP3 cipher scheme for a project
+#[tauri::command]
+pub async fn set_project_xp3_cipher(
+    id: String,
+    scheme: Xp3CipherScheme,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let store = ProjectStore::new(&db);
+
+    let value = serde_json::to_string(&scheme).map_err(|e| e.to_string())?;
+    store.set_setting(&id, XP3_CIPHER_SETTING, &value)
+}
+
+/// Decrypt encrypted MV/MZ assets of a game into an output folder
+///
+/// `input_dir` defaults to the game folder. Returns the number of decrypted files.
+#[tauri::command]
+pub async fn decrypt_rpgmv_assets(
+    game_path: String,
+    input_dir: Option<String>,
+    output_dir: String,
+) -> Result<usize, String> {
+    let crypter = RpgMvCrypter::from_game_dir(&game_path).map_err(|e| e.to_string())?;
+    let input_dir = input_dir.unwrap_or(game_path);
+    crypter
+        .decrypt_directory(&input_dir, &output_dir)
+        .map_err(|e| e.to_string())
+}
+
+/// Re-encrypt plain MV/MZ assets with a game's key into an output folder
+///
+/// Returns the number of encrypted files.
+#[tauri::command]
+pub async fn encrypt_rpgmv_assets(
+    game_path: String,
+    input_dir: String,
+    output_dir: String,
+) -> Result<usize, String> {
+    let crypter = RpgMvCrypter::from_game_dir(&game_path).map_err(|e| e.to_string())?;
+    crypter
+        .encrypt_directory(&input_dir, &output_dir)
+        .map_err(|e| e.to_string())
+}
@@ -1,7 +1,9 @@
 pub mod retriever;
 pub mod project;
 pub mod config;
+pub mod archiver;
 
 pub use retriever::*;
 pub use project::*;
 pub use config::*;
+pub use archiver::*;
@@ -1,21 +1,32 @@
 //! Tauri commands for project management
 
+use crate::archiver::CancellationToken;
+use crate::parser::rpg_maker_xp_vx::{
+    convert_data_dir_to_json, convert_json_dir_to_data, data_extension,
+};
 use crate::retriever::GameDetector;
 use crate::storage::{Database, ProjectStore};
 use crate::storage::project_store::ProjectInfo;
-use std::path::Path;
+use crate::types::engine::{GameEngine, RpgMakerVersion};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::State;
 
 /// Application state holding the database connection
 pub struct AppState {
     pub db: Mutex<Database>,
+    /// Cancellation tokens of running archive operations, by operation id
+    pub archive_operations: Mutex<HashMap<String, CancellationToken>>,
 }
 
 impl AppState {
     pub fn new() -> Result<Self, String> {
         let db = Database::open()?;
-        Ok(Self { db: Mutex::new(db) })
+        Ok(Self {
+            db: Mutex::new(db),
+            archive_operations: Mutex::new(HashMap::new()),
+        })
     }
 }
 
@@ -103,6 +114,53 @@ pub async fn detect_engine(path: String) -> Result<String, String> {
     }
 }
 
+/// Get the Data folder and version of an RPG Maker XP/VX/VX Ace project
+fn marshal_data_dir(
+    id: &str,
+    state: &AppState,
+) -> Result<(PathBuf, RpgMakerVersion), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let store = ProjectStore::new(&db);
+    let project = store.get_by_id(id)?
+        .ok_or_else(|| "Project not found".to_string())?;
+
+    let path = PathBuf::from(&project.path);
+    let engine = GameDetector::detect(&path).project.map(|p| p.engine);
+    match engine {
+        Some(GameEngine::RpgMaker(version)) if data_extension(version).is_some() => {
+            Ok((path.join("Data"), version))
+        }
+        _ => Err("Project is not an RPG Maker XP, VX or VX Ace game".to_string()),
+    }
+}
+
+/// Convert the Data folder of an RPG Maker XP/VX/VX Ace project to JSON
+///
+/// Writes one JSON file per data file into `output_dir`. Returns the number of converted files.
+#[tauri::command]
+pub async fn export_project_data_json(
+    id: String,
+    output_dir: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let (data_dir, version) = marshal_data_dir(&id, &state)?;
+    convert_data_dir_to_json(&data_dir, &output_dir, version).map_err(|e| e.to_string())
+}
+
+/// Rebuild the Data folder of an RPG Maker XP/VX/VX Ace project from JSON
+///
+/// Overwrites the data files converted from the JSON files of `input_dir`.
+/// Returns the number of converted files.
+#[tauri::command]
+pub async fn import_project_data_json(
+    id: String,
+    input_dir: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let (data_dir, version) = marshal_data_dir(&id, &state)?;
+    convert_json_dir_to_data(&input_dir, &data_dir, version).map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -42,6 +42,8 @@ pub fn run() {
             commands::delete_project,
             commands::open_project,
             commands::detect_engine,
+            commands::export_project_data_json,
+            commands::import_project_data_json,
             // Config commands
             commands::get_config,
             commands::set_language,
@@ -50,6 +52,19 @@ pub fn run() {
             commands::set_window_size,
             commands::get_app_data_path,
             commands::open_app_data_folder,
+            // Archive commands
+            commands::list_archive_entries,
+            commands::extract_archive,
+            commands::diff_archives,
+            commands::verify_rgss_archive,
+            commands::extract_rgss_archive,
+            commands::patch_rgss_archive,
+            commands::cancel_archive_operation,
+            commands::detect_xp3_cipher,
+            commands::get_project_xp3_cipher,
+            commands::set_project_xp3_cipher,
+            commands::decrypt_rpgmv_assets,
+            commands::encrypt_rpgmv_assets,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,113 @@
+//! Text encodings of KiriKiri scenario files
+//!
+//! KiriKiri reads text files as UTF-16LE when they start with a BOM, and
+//! as Shift-JIS (the system code page of Japanese Windows) otherwise.
+//! KiriKiri Z also reads UTF-8. Injection writes a file back in the
+//! encoding it was read in, BOM included, or as UTF-16LE when the
+//! translation has characters Shift-JIS lacks.
+
+use encoding_rs::{SHIFT_JIS, UTF_16LE};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+
+/// Text encoding of a scenario file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KagEncoding {
+    /// Shift-JIS, without BOM
+    ShiftJis,
+    /// UTF-8, without BOM
+    Utf8,
+    /// UTF-8 with a BOM
+    Utf8Bom,
+    /// UTF-16LE with a BOM
+    Utf16Le,
+}
+
+impl KagEncoding {
+    /// Guess the encoding of a file
+    ///
+    /// Files without a BOM are UTF-8 if they have non-ASCII text and all of
+    /// it decodes as UTF-8, since Shift-JIS text almost never does.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(UTF16LE_BOM) {
+            Self::Utf16Le
+        } else if data.starts_with(UTF8_BOM) {
+            Self::Utf8Bom
+        } else if !data.is_ascii() && std::str::from_utf8(data).is_ok() {
+            Self::Utf8
+        } else {
+            Self::ShiftJis
+        }
+    }
+
+    /// Decode a file, or `None` if it is not valid text
+    pub fn decode(&self, data: &[u8]) -> Option<String> {
+        match self {
+            Self::ShiftJis => {
+                let (text, had_errors) = SHIFT_JIS.decode_without_bom_handling(data);
+                (!had_errors).then(|| text.into_owned())
+            }
+            Self::Utf8 => String::from_utf8(data.to_vec()).ok(),
+            Self::Utf8Bom => String::from_utf8(data.strip_prefix(UTF8_BOM)?.to_vec()).ok(),
+            Self::Utf16Le => {
+                let data = data.strip_prefix(UTF16LE_BOM)?;
+                if data.len() % 2 != 0 {
+                    return None;
+                }
+                let (text, had_errors) = UTF_16LE.decode_without_bom_handling(data);
+                (!had_errors).then(|| text.into_owned())
+            }
+        }
+    }
+
+    /// Encode a file, or `None` if it has characters the encoding lacks
+    pub fn encode(&self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::ShiftJis => {
+                let (bytes, _, had_errors) = SHIFT_JIS.encode(text);
+                (!had_errors).then(|| bytes.into_owned())
+            }
+            Self::Utf8 => Some(text.as_bytes().to_vec()),
+            Self::Utf8Bom => Some([UTF8_BOM, text.as_bytes()].concat()),
+            Self::Utf16Le => {
+                let mut bytes = UTF16LE_BOM.to_vec();
+                bytes.extend(text.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+                Some(bytes)
+            }
+        }
+    }
+
+    /// Check whether text can be encoded
+    pub fn can_encode(&self, text: &str) -> bool {
+        match self {
+            Self::ShiftJis => !SHIFT_JIS.encode(text).2,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_round_trip() {
+        let text = "*start\r\n【アリス】こんにちは。[p]\r\n";
+        for encoding in [
+            KagEncoding::ShiftJis,
+            KagEncoding::Utf8,
+            KagEncoding::Utf8Bom,
+            KagEncoding::Utf16Le,
+        ] {
+            let data = encoding.encode(text).unwrap();
+            assert_eq!(KagEncoding::detect(&data), encoding);
+            assert_eq!(encoding.decode(&data).as_deref(), Some(text));
+        }
+
+        assert_eq!(KagEncoding::detect(b"[r]"), KagEncoding::ShiftJis);
+        assert!(!KagEncoding::ShiftJis.can_encode("안녕"));
+        assert!(KagEncoding::ShiftJis.encode("안녕").is_none());
+        assert!(KagEncoding::Utf16Le.decode(b"\xFF\xFEa").is_none());
+    }
+}
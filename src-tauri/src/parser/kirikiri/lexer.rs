@@ -0,0 +1,372 @@
+//! Tokenizer for KAG scenario files
+//!
+//! KAG scenarios are read line by line, with leading whitespace ignored:
+//!
+//! ```text
+//! ; comment
+//! *label|page title
+//! @tag attr=value
+//! text[tag attr="value" flag]more text[[not a tag][p]
+//! [iscript]
+//! TJS code
+//! [endscript]
+//! ```
+//!
+//! `[[` is an escaped `[` in text. Lines of `[iscript]` blocks are TJS
+//! code, returned as [`TokenKind::Script`] tokens. Every token keeps its
+//! byte range in the source, so text can be replaced in place.
+
+use std::ops::Range;
+
+/// Attribute of a tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    pub name: String,
+    /// Value without quotes, `None` for flags
+    pub value: Option<String>,
+    /// Byte range of the value in the source, including its quotes
+    pub value_range: Option<Range<usize>>,
+}
+
+/// Tag, written as `[name ...]` or as an `@name ...` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub attributes: Vec<Attribute>,
+}
+
+impl Tag {
+    /// Get an attribute by name
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    /// Check whether this is a line control tag (`[r]`, `[l]` or `[p]`)
+    pub fn is_line_control(&self) -> bool {
+        matches!(self.name.as_str(), "r" | "l" | "p")
+    }
+}
+
+/// Kind of a token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// `*name|title` label line
+    Label { name: String, title: Option<String> },
+    /// `;` comment line
+    Comment,
+    /// Tag
+    Tag(Tag),
+    /// Text, with `[[` unescaped
+    Text(String),
+    /// Line of TJS code in an `[iscript]` block
+    Script,
+}
+
+/// Token of a scenario
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Line index (0-based)
+    pub line: usize,
+    /// Byte range in the source
+    pub range: Range<usize>,
+}
+
+/// Escape text for a scenario (`[` as `[[`)
+pub fn escape_text(text: &str) -> String {
+    text.replace('[', "[[")
+}
+
+/// Tokenize a scenario
+///
+/// Blank lines produce no tokens.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut lexer = Lexer::default();
+    let mut offset = 0;
+
+    for (line_index, raw) in source.split_inclusive('\n').enumerate() {
+        let mut base = offset;
+        offset += raw.len();
+
+        let mut line = raw.trim_end_matches(['\n', '\r']);
+        if base == 0 {
+            if let Some(rest) = line.strip_prefix('\u{feff}') {
+                base += '\u{feff}'.len_utf8();
+                line = rest;
+            }
+        }
+        let trimmed = line.trim_start();
+        base += line.len() - trimmed.len();
+
+        lexer.line = line_index;
+        lexer.read_line(trimmed.trim_end(), base);
+    }
+
+    lexer.tokens
+}
+
+#[derive(Default)]
+struct Lexer {
+    tokens: Vec<Token>,
+    in_script: bool,
+    line: usize,
+}
+
+impl Lexer {
+    fn push(&mut self, kind: TokenKind, range: Range<usize>) {
+        self.tokens.push(Token {
+            kind,
+            line: self.line,
+            range,
+        });
+    }
+
+    /// Read a line, trimmed, starting at byte `base` of the source
+    fn read_line(&mut self, line: &str, base: usize) {
+        if line.is_empty() {
+            return;
+        }
+        let range = base..base + line.len();
+
+        if self.in_script {
+            if line.starts_with("@endscript") || line.contains("[endscript") {
+                self.in_script = false;
+            } else {
+                self.push(TokenKind::Script, range);
+                return;
+            }
+        }
+
+        match line.as_bytes()[0] {
+            b';' => self.push(TokenKind::Comment, range),
+            b'*' => {
+                let (name, title) = match line[1..].split_once('|') {
+                    Some((name, title)) => (name, Some(title.to_string())),
+                    None => (&line[1..], None),
+                };
+                let name = name.trim().to_string();
+                self.push(TokenKind::Label { name, title }, range);
+            }
+            b'@' => {
+                let tag = parse_tag(&line[1..], base + 1);
+                self.tag(tag, range);
+            }
+            _ => self.read_text(line, base),
+        }
+    }
+
+    fn tag(&mut self, tag: Tag, range: Range<usize>) {
+        match tag.name.as_str() {
+            "iscript" => self.in_script = true,
+            "endscript" => self.in_script = false,
+            _ => {}
+        }
+        self.push(TokenKind::Tag(tag), range);
+    }
+
+    /// Read a line of text and tags
+    fn read_text(&mut self, line: &str, base: usize) {
+        let bytes = line.as_bytes();
+        let mut text = String::new();
+        let mut text_start = 0;
+        let mut pos = 0;
+
+        while pos < bytes.len() {
+            if bytes[pos] != b'[' {
+                let len = line[pos..].chars().next().map_or(1, char::len_utf8);
+                text.push_str(&line[pos..pos + len]);
+                pos += len;
+                continue;
+            }
+            if bytes.get(pos + 1) == Some(&b'[') {
+                text.push('[');
+                pos += 2;
+                continue;
+            }
+
+            if pos > text_start {
+                let range = base + text_start..base + pos;
+                self.push(TokenKind::Text(std::mem::take(&mut text)), range);
+            }
+
+            let end = tag_end(line, pos);
+            let contents = line[pos + 1..end]
+                .strip_suffix(']')
+                .unwrap_or(&line[pos + 1..end]);
+            let tag = parse_tag(contents, base + pos + 1);
+            self.tag(tag, base + pos..base + end);
+            pos = end;
+            text_start = end;
+
+            // The rest of the line is script code
+            if self.in_script {
+                if pos < bytes.len() {
+                    self.push(TokenKind::Script, base + pos..base + bytes.len());
+                }
+                return;
+            }
+        }
+
+        if pos > text_start {
+            self.push(TokenKind::Text(text), base + text_start..base + pos);
+        }
+    }
+}
+
+/// Get the end of the tag starting at `start`, after its `]`
+fn tag_end(line: &str, start: usize) -> usize {
+    let mut quote = None;
+    for (i, c) in line[start + 1..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ']') => return start + 1 + i + 1,
+            _ => {}
+        }
+    }
+    line.len()
+}
+
+/// Parse the contents of a tag, starting at byte `base` of the source
+fn parse_tag(contents: &str, base: usize) -> Tag {
+    let bytes = contents.as_bytes();
+    let skip_whitespace = |mut pos: usize| {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        pos
+    };
+
+    let mut pos = skip_whitespace(0);
+    let name_start = pos;
+    while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    let name = contents[name_start..pos].to_string();
+
+    let mut attributes = Vec::new();
+    loop {
+        pos = skip_whitespace(pos);
+        if pos >= bytes.len() {
+            break;
+        }
+
+        let key_start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'=' {
+            pos += 1;
+        }
+        let key = contents[key_start..pos].to_string();
+
+        let after_key = skip_whitespace(pos);
+        if bytes.get(after_key) != Some(&b'=') {
+            attributes.push(Attribute {
+                name: key,
+                value: None,
+                value_range: None,
+            });
+            continue;
+        }
+        pos = skip_whitespace(after_key + 1);
+
+        let value_start = pos;
+        let value = match bytes.get(pos) {
+            Some(&q) if q == b'"' || q == b'\'' => {
+                let end = contents[pos + 1..]
+                    .find(q as char)
+                    .map_or(bytes.len(), |i| pos + 1 + i);
+                let value = &contents[pos + 1..end];
+                pos = (end + 1).min(bytes.len());
+                value
+            }
+            _ => {
+                while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                    pos += 1;
+                }
+                &contents[value_start..pos]
+            }
+        };
+
+        attributes.push(Attribute {
+            name: key,
+            value: Some(value.to_string()),
+            value_range: Some(base + value_start..base + pos),
+        });
+    }
+
+    Tag { name, attributes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source).into_iter().map(|t| t.kind).collect()
+    }
+
+    fn tag(name: &str) -> TokenKind {
+        TokenKind::Tag(Tag {
+            name: name.to_string(),
+            attributes: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_tokenize_lines() {
+        let source = "\
+; comment
+*start|Prologue
+\t@bg storage=room
+[iscript]
+f.flag = \"[p]\";
+[endscript]
+";
+        let tokens = tokenize(source);
+        assert_eq!(tokens[0].kind, TokenKind::Comment);
+        assert_eq!(
+            tokens[1].kind,
+            TokenKind::Label {
+                name: "start".to_string(),
+                title: Some("Prologue".to_string())
+            }
+        );
+        let TokenKind::Tag(bg) = &tokens[2].kind else {
+            panic!("expected a tag");
+        };
+        assert_eq!(bg.name, "bg");
+        assert_eq!(
+            bg.attribute("storage").unwrap().value.as_deref(),
+            Some("room")
+        );
+        assert_eq!(&source[tokens[2].range.clone()], "@bg storage=room");
+        assert_eq!(tokens[3].kind, tag("iscript"));
+        assert_eq!(tokens[4].kind, TokenKind::Script);
+        assert_eq!(tokens[4].line, 4);
+        assert_eq!(tokens[5].kind, tag("endscript"));
+    }
+
+    #[test]
+    fn test_tokenize_text_and_tags() {
+        let source = "Hello[r]a [[bracket][font color=\"0x[ff]\" bold]!";
+        let tokens = tokenize(source);
+        assert_eq!(
+            kinds(source)[..3],
+            [
+                TokenKind::Text("Hello".to_string()),
+                tag("r"),
+                TokenKind::Text("a [bracket]".to_string()),
+            ]
+        );
+        assert_eq!(&source[tokens[2].range.clone()], "a [[bracket]");
+
+        let TokenKind::Tag(font) = &tokens[3].kind else {
+            panic!("expected a tag");
+        };
+        let color = font.attribute("color").unwrap();
+        assert_eq!(color.value.as_deref(), Some("0x[ff]"));
+        assert_eq!(&source[color.value_range.clone().unwrap()], "\"0x[ff]\"");
+        assert_eq!(font.attribute("bold").unwrap().value, None);
+        assert_eq!(tokens[4].kind, TokenKind::Text("!".to_string()));
+    }
+}
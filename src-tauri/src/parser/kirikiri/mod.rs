@@ -0,0 +1,38 @@
+//! KiriKiri parser module
+//!
+//! This module provides parsing capabilities for KiriKiri games, whose
+//! story is written in KAG scenario files (`.ks`, usually packed into
+//! `.xp3` archives, see [`crate::archiver::xp3`]).
+//!
+//! - [`lexer`]: tokenizer for labels, tags, `@tag` lines, text and
+//!   `[iscript]` blocks
+//! - [`scenario`]: extraction of messages, speakers and link choices, and
+//!   injection back into the original text
+//! - [`encoding`]: Shift-JIS, UTF-8 and UTF-16LE scenario files
+//!
+//! Translations are injected by replacing text in place, leaving every
+//! tag untouched, and files are written back in their original encoding
+//! (as UTF-16LE if the translations do not fit in it).
+
+pub mod encoding;
+pub mod lexer;
+pub mod scenario;
+
+pub use encoding::KagEncoding;
+pub use lexer::{tokenize, Attribute, Tag, Token, TokenKind};
+pub use scenario::KagParser;
+
+use crate::archiver::ArchiverError;
+
+/// Error type for KiriKiri parsing
+#[derive(Debug, thiserror::Error)]
+pub enum KiriKiriError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Archive error: {0}")]
+    ArchiveError(#[from] ArchiverError),
+
+    #[error("Invalid structure: {0}")]
+    InvalidStructure(String),
+}
@@ -0,0 +1,704 @@
+//! Extraction and injection for KAG `.ks` scenario files
+//!
+//! Every run of text between tags is a message, or a choice inside
+//! `[link]`...`[endlink]`. Runs joined by `[r]` line breaks on the same
+//! line form one message, with `\n` in place of the tags.
+//!
+//! Speakers follow two common conventions:
+//!
+//! - `【name】` at the start of a line, extracted as its own unit
+//! - a line holding a single attribute-less tag that is not part of KAG,
+//!   `[name]`, which is a macro per character; it is only used as context
+//!   since renaming the tag would break the macro
+//!
+//! The speaker is kept until the end of the page (`[p]`, `[cm]`, `[ct]`
+//! or `[er]`). Units are identified by line and position
+//! (`lines.12.texts.0_message`), and injection replaces the exact ranges
+//! they were read from.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use super::encoding::KagEncoding;
+use super::lexer::{escape_text, tokenize, Token, TokenKind};
+use super::KiriKiriError;
+use crate::archiver::vfs::GameVfs;
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile,
+    TranslationPath, TranslationUnit,
+};
+
+/// Tags of KAG and its standard plugins, which are never speaker macros
+const KAG_TAGS: &[&str] = &[
+    "animstart",
+    "animstop",
+    "autowc",
+    "backlay",
+    "bgmopt",
+    "button",
+    "call",
+    "cancelautomode",
+    "cancelskip",
+    "ch",
+    "clearbgmlabel",
+    "clearbgmstop",
+    "clearsysvar",
+    "clickskip",
+    "close",
+    "cm",
+    "copybookmark",
+    "copylay",
+    "ct",
+    "cursor",
+    "deffont",
+    "defstyle",
+    "delay",
+    "disablestore",
+    "else",
+    "elsif",
+    "emb",
+    "endhact",
+    "endif",
+    "endignore",
+    "endindent",
+    "endlink",
+    "endmacro",
+    "endnowait",
+    "endscript",
+    "er",
+    "erasebookmark",
+    "erasemacro",
+    "eval",
+    "fadebgm",
+    "fadeinbgm",
+    "fadeinse",
+    "fadeoutbgm",
+    "fadeoutse",
+    "fadepausebgm",
+    "fadese",
+    "font",
+    "freeimage",
+    "glyph",
+    "goback",
+    "gotostart",
+    "graph",
+    "hact",
+    "hch",
+    "history",
+    "if",
+    "ignore",
+    "image",
+    "indent",
+    "input",
+    "iscript",
+    "jump",
+    "l",
+    "laycount",
+    "layopt",
+    "link",
+    "load",
+    "loadplugin",
+    "locate",
+    "locklink",
+    "locksnapshot",
+    "macro",
+    "mapaction",
+    "mapdisable",
+    "mapimage",
+    "mappfont",
+    "move",
+    "nextskip",
+    "nowait",
+    "p",
+    "pausebgm",
+    "pimage",
+    "playbgm",
+    "playse",
+    "playvideo",
+    "position",
+    "ptext",
+    "quake",
+    "r",
+    "rclick",
+    "record",
+    "resetfont",
+    "resetstyle",
+    "resetwait",
+    "resumebgm",
+    "return",
+    "ruby",
+    "s",
+    "save",
+    "seopt",
+    "setbgmlabel",
+    "setbgmstop",
+    "startanchor",
+    "stopbgm",
+    "stopmove",
+    "stopquake",
+    "stopse",
+    "stoptrans",
+    "stopvideo",
+    "store",
+    "style",
+    "tempload",
+    "tempsave",
+    "timeout",
+    "title",
+    "trans",
+    "unlocklink",
+    "unlocksnapshot",
+    "video",
+    "wa",
+    "wait",
+    "waitclick",
+    "waittrig",
+    "wb",
+    "wc",
+    "wf",
+    "wheel",
+    "wl",
+    "wm",
+    "wp",
+    "wq",
+    "ws",
+    "wt",
+    "wv",
+];
+
+/// Tags that end a message page
+const PAGE_TAGS: &[&str] = &["p", "cm", "ct", "er"];
+
+/// Kind of a text in a scenario
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextKind {
+    /// Speaker written as `【name】`
+    Name,
+    /// Message text
+    Message,
+    /// Text between `[link]` and `[endlink]`
+    Link,
+}
+
+/// Translatable text of a scenario
+#[derive(Debug, Clone)]
+pub struct ScenarioText {
+    pub kind: TextKind,
+    /// Line index (0-based)
+    pub line: usize,
+    /// Index of the text in its line
+    pub index: usize,
+    /// Byte range in the source
+    pub range: Range<usize>,
+    /// Text, unescaped, with `\n` for `[r]`
+    pub text: String,
+    /// Current speaker
+    pub speaker: Option<String>,
+    /// Last label
+    pub label: Option<String>,
+}
+
+impl ScenarioText {
+    /// Get the translation path of the text
+    pub fn path(&self) -> TranslationPath {
+        let path = TranslationPath::new()
+            .append_key("lines")
+            .append_index(self.line);
+        match self.kind {
+            TextKind::Name => path,
+            _ => path.append_key("texts").append_index(self.index),
+        }
+    }
+
+    /// Get the unit id of the text
+    pub fn unit_id(&self) -> String {
+        let suffix = match self.kind {
+            TextKind::Name => "speaker",
+            TextKind::Message => "message",
+            TextKind::Link => "choice",
+        };
+        self.path().to_unit_id(suffix)
+    }
+}
+
+/// Text being read, possibly spanning several `[r]`
+struct Run {
+    range: Range<usize>,
+    text: String,
+}
+
+/// Scenario reader state
+struct Scanner<'a> {
+    source: &'a str,
+    texts: Vec<ScenarioText>,
+    speaker: Option<String>,
+    label: Option<String>,
+    in_link: bool,
+    line: usize,
+    index: usize,
+    run: Option<Run>,
+    /// Number of `[r]` since the end of the run
+    breaks: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn push(&mut self, kind: TextKind, range: Range<usize>, text: String) {
+        let index = if kind == TextKind::Name {
+            0
+        } else {
+            self.index += 1;
+            self.index - 1
+        };
+        self.texts.push(ScenarioText {
+            kind,
+            line: self.line,
+            index,
+            range,
+            text,
+            speaker: self.speaker.clone(),
+            label: self.label.clone(),
+        });
+    }
+
+    fn flush(&mut self) {
+        self.breaks = 0;
+        if let Some(run) = self.run.take() {
+            let kind = if self.in_link {
+                TextKind::Link
+            } else {
+                TextKind::Message
+            };
+            self.push(kind, run.range, run.text);
+        }
+    }
+
+    /// Read the tokens of a line
+    fn read_line(&mut self, tokens: &[Token]) {
+        self.line = tokens[0].line;
+        self.index = 0;
+
+        if let [Token {
+            kind: TokenKind::Tag(tag),
+            ..
+        }] = tokens
+        {
+            if tag.attributes.is_empty() && !KAG_TAGS.contains(&tag.name.as_str()) {
+                self.speaker = Some(tag.name.clone());
+                return;
+            }
+        }
+
+        for (i, token) in tokens.iter().enumerate() {
+            match &token.kind {
+                TokenKind::Label { name, .. } => self.label = Some(name.clone()),
+                TokenKind::Tag(tag) if tag.name == "r" && tag.attributes.is_empty() => {
+                    if self.run.is_some() {
+                        self.breaks += 1;
+                    }
+                }
+                TokenKind::Tag(tag) => {
+                    self.flush();
+                    match tag.name.as_str() {
+                        "link" => self.in_link = true,
+                        "endlink" => self.in_link = false,
+                        name if PAGE_TAGS.contains(&name) => self.speaker = None,
+                        _ => {}
+                    }
+                }
+                TokenKind::Text(_) => {
+                    let mut range = token.range.clone();
+                    if i == 0 && !self.in_link {
+                        range.start = self.read_name(range.clone());
+                    }
+                    self.read_text(range);
+                }
+                TokenKind::Comment | TokenKind::Script => {}
+            }
+        }
+        self.flush();
+    }
+
+    /// Read a `【name】` speaker, returning where the text after it starts
+    fn read_name(&mut self, range: Range<usize>) -> usize {
+        let raw = &self.source[range.clone()];
+        let Some(rest) = raw.strip_prefix('【') else {
+            return range.start;
+        };
+        let Some(end) = rest.find('】') else {
+            return range.start;
+        };
+
+        let name = &rest[..end];
+        let start = range.start + '【'.len_utf8() + (name.len() - name.trim_start().len());
+        let name = name.trim();
+        if !name.is_empty() {
+            let text = name.replace("[[", "[");
+            self.speaker = Some(text.clone());
+            self.push(TextKind::Name, start..start + name.len(), text);
+        }
+        range.start + '【'.len_utf8() + end + '】'.len_utf8()
+    }
+
+    /// Read text between tags
+    fn read_text(&mut self, range: Range<usize>) {
+        let raw = &self.source[range.clone()];
+        let start = range.start + (raw.len() - raw.trim_start().len());
+        let raw = raw.trim();
+        if raw.is_empty() {
+            return;
+        }
+        let end = start + raw.len();
+        let text = raw.replace("[[", "[");
+
+        match &mut self.run {
+            Some(run) if self.breaks > 0 => {
+                run.text.push_str(&"\n".repeat(self.breaks));
+                run.text.push_str(&text);
+                run.range.end = end;
+                self.breaks = 0;
+            }
+            _ => {
+                self.flush();
+                self.run = Some(Run {
+                    range: start..end,
+                    text,
+                });
+            }
+        }
+    }
+}
+
+/// Parse the translatable texts of a scenario
+pub fn parse_scenario(source: &str) -> Vec<ScenarioText> {
+    let tokens = tokenize(source);
+    let mut scanner = Scanner {
+        source,
+        texts: Vec::new(),
+        speaker: None,
+        label: None,
+        in_link: false,
+        line: 0,
+        index: 0,
+        run: None,
+        breaks: 0,
+    };
+
+    for line in tokens.chunk_by(|a, b| a.line == b.line) {
+        scanner.read_line(line);
+    }
+
+    scanner.texts
+}
+
+/// Parser for KAG scenario files
+pub struct KagParser;
+
+impl KagParser {
+    /// Create a new KAG parser
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Decode a scenario file
+    pub fn decode(&self, data: &[u8]) -> Result<(String, KagEncoding), KiriKiriError> {
+        let encoding = KagEncoding::detect(data);
+        let source = encoding.decode(data).ok_or_else(|| {
+            KiriKiriError::InvalidStructure(format!("File is not valid {:?} text", encoding))
+        })?;
+        Ok((source, encoding))
+    }
+
+    /// Extract translations from scenario file content
+    pub fn extract(
+        &self,
+        data: &[u8],
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, KiriKiriError> {
+        let (source, _) = self.decode(data)?;
+        let mut result = FileExtractionResult::new(file_name);
+        let mut context =
+            ExtractionContext::new(file_name).with_max_preceding_lines(options.max_preceding_lines);
+        let mut units = Vec::new();
+
+        for text in parse_scenario(&source) {
+            let mut translation_context = context.to_translation_context();
+            if let Some(label) = &text.label {
+                translation_context = translation_context.with_event_name(label);
+            }
+
+            let code = match text.kind {
+                TextKind::Name => EventCode::ShowText,
+                TextKind::Message => EventCode::ShowTextBody,
+                TextKind::Link => {
+                    translation_context.add_tag("choice");
+                    EventCode::ShowChoices
+                }
+            };
+
+            let mut unit =
+                TranslationUnit::new(text.unit_id(), text.path(), code, text.text.clone())
+                    .with_context(translation_context);
+            if text.kind == TextKind::Message {
+                unit = unit.with_speaker(text.speaker.clone());
+                context.add_preceding_line(text.text.clone());
+            }
+            units.push(unit);
+        }
+
+        result.add_units(units);
+        Ok(result)
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, KiriKiriError> {
+        let data = fs::read(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("scenario.ks");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Extract from a file in a game's virtual filesystem
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, KiriKiriError> {
+        let data = vfs.read(path)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("scenario.ks");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Inject translations into scenario file content
+    ///
+    /// The file keeps its encoding, unless the translations have characters
+    /// it lacks (Hangul in a Shift-JIS file): then the whole file is written
+    /// as UTF-16LE with a BOM, which KiriKiri reads as well. Messages get
+    /// `[r]` at their line breaks, and are wrapped at `max_line_length`
+    /// when set.
+    pub fn inject(
+        &self,
+        data: &mut Vec<u8>,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, KiriKiriError> {
+        let (source, encoding) = self.decode(data)?;
+        let mut result = FileInjectionResult::new();
+        let mut output = String::with_capacity(source.len());
+        let mut last = 0;
+
+        for text in parse_scenario(&source) {
+            let id = text.unit_id();
+            let Some(translated) = translations.get(&id) else {
+                if text.kind != TextKind::Name && !options.skip_missing_translations {
+                    result.not_found += 1;
+                }
+                continue;
+            };
+
+            let replacement = match text.kind {
+                TextKind::Name => escape_text(&translated.replace(['\r', '\n'], " ")),
+                TextKind::Message | TextKind::Link => options
+                    .split_text(translated)
+                    .iter()
+                    .map(|line| escape_text(line))
+                    .collect::<Vec<_>>()
+                    .join("[r]"),
+            };
+
+            output.push_str(&source[last..text.range.start]);
+            output.push_str(&replacement);
+            last = text.range.end;
+            result.applied += 1;
+            result.commands_modified += 1;
+        }
+
+        result.modified = result.applied > 0;
+        if result.modified {
+            output.push_str(&source[last..]);
+            let encoding = if encoding.can_encode(&output) {
+                encoding
+            } else {
+                result.warnings.push(format!(
+                    "Translations cannot be encoded as {:?}, file written as {:?}",
+                    encoding,
+                    KagEncoding::Utf16Le
+                ));
+                KagEncoding::Utf16Le
+            };
+            *data = encoding.encode(&output).ok_or_else(|| {
+                KiriKiriError::InvalidStructure(format!(
+                    "Output cannot be encoded as {:?}",
+                    encoding
+                ))
+            })?;
+        }
+
+        Ok(result)
+    }
+
+    /// Inject translations into a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, KiriKiriError> {
+        let mut data = fs::read(path)?;
+
+        let result = self.inject(&mut data, translations, options)?;
+
+        if result.modified {
+            fs::write(path, data)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+impl Default for KagParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check whether a file is a scenario file
+pub fn is_scenario_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("ks"))
+}
+
+/// Get the scenario files in a directory of a game's virtual filesystem
+pub fn find_scenario_files_vfs(vfs: &GameVfs, dir: &str) -> Vec<String> {
+    vfs.list_dir(dir)
+        .into_iter()
+        .filter(|path| is_scenario_file(Path::new(path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENARIO: &str = "\
+*start|プロローグ\r
+@bg storage=room\r
+【アリス】おはよう。[l][r]\r
+今日は[[晴れ]だね。[r]出かけよう。[p]\r
+[ボブ]\r
+\tうん。[p]\r
+[link target=*go]行く[endlink][r]\r
+[link target=*stay]行かない[endlink]\r
+";
+
+    #[test]
+    fn test_extract() {
+        let data = KagEncoding::Utf16Le.encode(SCENARIO).unwrap();
+        let result = KagParser::new()
+            .extract(&data, "first.ks", &ExtractionOptions::default())
+            .unwrap();
+
+        let units: Vec<(&str, &str)> = result
+            .units
+            .iter()
+            .map(|u| (u.id.as_str(), u.original.as_str()))
+            .collect();
+        assert_eq!(
+            units,
+            vec![
+                ("lines.2_speaker", "アリス"),
+                ("lines.2.texts.0_message", "おはよう。"),
+                (
+                    "lines.3.texts.0_message",
+                    "今日は[晴れ]だね。\n出かけよう。"
+                ),
+                ("lines.5.texts.0_message", "うん。"),
+                ("lines.6.texts.0_choice", "行く"),
+                ("lines.7.texts.0_choice", "行かない"),
+            ]
+        );
+        assert_eq!(result.units[2].speaker.as_deref(), Some("アリス"));
+        assert_eq!(result.units[3].speaker.as_deref(), Some("ボブ"));
+        assert_eq!(result.units[4].speaker, None);
+        assert_eq!(result.units[1].context.event_name.as_deref(), Some("start"));
+        assert_eq!(result.units[4].code, EventCode::ShowChoices);
+    }
+
+    #[test]
+    fn test_inject_keeps_tags_and_encoding() {
+        let parser = KagParser::new();
+        let mut translations = HashMap::new();
+        translations.insert("lines.2_speaker".to_string(), "Alice".to_string());
+        translations.insert(
+            "lines.3.texts.0_message".to_string(),
+            "It's [sunny] today.\nLet's go out.\nNow!".to_string(),
+        );
+        translations.insert("lines.6.texts.0_choice".to_string(), "Go".to_string());
+
+        for encoding in [KagEncoding::ShiftJis, KagEncoding::Utf16Le] {
+            let mut data = encoding.encode(SCENARIO).unwrap();
+            let result = parser
+                .inject(&mut data, &translations, &InjectionOptions::default())
+                .unwrap();
+            assert_eq!(result.applied, 3);
+            assert!(result.modified);
+
+            assert_eq!(KagEncoding::detect(&data), encoding);
+            let expected = SCENARIO
+                .replace("【アリス】", "【Alice】")
+                .replace(
+                    "今日は[[晴れ]だね。[r]出かけよう。[p]",
+                    "It's [[sunny] today.[r]Let's go out.[r]Now![p]",
+                )
+                .replace("]行く[", "]Go[");
+            assert_eq!(encoding.decode(&data).unwrap(), expected);
+        }
+
+        // Hangul is not in Shift-JIS, so the file becomes UTF-16LE
+        let mut translations = HashMap::new();
+        translations.insert("lines.5.texts.0_message".to_string(), "응.".to_string());
+        let mut data = KagEncoding::ShiftJis.encode(SCENARIO).unwrap();
+        let result = parser
+            .inject(&mut data, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert_eq!(result.applied, 1);
+        assert!(result.modified);
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(KagEncoding::detect(&data), KagEncoding::Utf16Le);
+        assert_eq!(
+            KagEncoding::Utf16Le.decode(&data).unwrap(),
+            SCENARIO.replace("\tうん。[p]", "\t응.[p]")
+        );
+
+        let mut data = KagEncoding::Utf8Bom.encode(SCENARIO).unwrap();
+        parser
+            .inject(&mut data, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert!(KagEncoding::Utf8Bom
+            .decode(&data)
+            .unwrap()
+            .contains("\tうん。[p]".replace("うん。", "응.").as_str()));
+    }
+}
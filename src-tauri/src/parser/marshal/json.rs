@@ -0,0 +1,810 @@
+//! JSON form of Marshal values
+//!
+//! Values are written as JSON close to what RPG Maker MV/MZ uses, so that
+//! data can be kept as text and read by JSON-based tools:
+//!
+//! - `nil`, booleans, integers, arrays and floats are JSON values
+//! - strings with the file's usual encoding are JSON strings
+//! - objects are JSON objects with their class in `json_class` and their
+//!   instance variables without the `@`
+//! - hashes with positive integer keys (map events, map infos) are arrays
+//!   indexed by key, whose first element is a `{"$type": "Hash"}` marker
+//!   where MV/MZ has `null`
+//!
+//! Everything else is an object tagged with `$type`. The form keeps what
+//! Marshal needs to write the original bytes back, object links included,
+//! so JSON values must not be edited in ways that change the number of
+//! objects (see [`links`](super::links)).
+
+use super::rgss::{Color, Table, Tone};
+use super::value::{Ivars, RString, StringEncoding, Symbol, Value};
+use super::MarshalError;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::fmt;
+
+/// Key of the class of objects
+const CLASS_KEY: &str = "json_class";
+
+/// Key of the type of other tagged values
+const TYPE_KEY: &str = "$type";
+
+/// Largest key of a hash written as an array, relative to its length
+const MAX_SPARSE_RATIO: i64 = 4;
+
+/// Write a value as pretty-printed JSON
+///
+/// Strings with `encoding` (the encoding of the file's text, such as
+/// [`StringEncoding::Utf8`] for VX Ace) are written as JSON strings.
+pub fn to_json(value: &Value, encoding: &StringEncoding) -> String {
+    let node = to_node(value, &encoding_ivars(encoding));
+    // Serializing the node tree cannot fail
+    serde_json::to_string_pretty(&node).expect("JSON node")
+}
+
+/// Read a value written by [`to_json`] with the same encoding
+pub fn from_json(text: &str, encoding: &StringEncoding) -> Result<Value, MarshalError> {
+    let node: Node = serde_json::from_str(text)
+        .map_err(|e| MarshalError::InvalidStructure(format!("Invalid JSON: {}", e)))?;
+    from_node(&node, &encoding_ivars(encoding))
+}
+
+/// JSON tree keeping the order of object keys
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+impl Node {
+    fn tagged(tag: &str, fields: Vec<(&str, Node)>) -> Self {
+        let mut entries = vec![(TYPE_KEY.to_string(), Node::Str(tag.to_string()))];
+        entries.extend(fields.into_iter().map(|(k, v)| (k.to_string(), v)));
+        Node::Object(entries)
+    }
+
+    fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Node::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            Node::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Node::Int(i) => Some(*i as f64),
+            Node::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Node::Null => serializer.serialize_unit(),
+            Node::Bool(b) => serializer.serialize_bool(*b),
+            Node::Int(i) => serializer.serialize_i64(*i),
+            Node::Float(f) => serializer.serialize_f64(*f),
+            Node::Str(s) => serializer.serialize_str(s),
+            Node::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Node::Object(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Node, E> {
+        Ok(Node::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Node, E> {
+        Ok(Node::Null)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Node, E> {
+        Ok(Node::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Node, E> {
+        Ok(Node::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Node, E> {
+        i64::try_from(v)
+            .map(Node::Int)
+            .map_err(|_| E::custom(format!("integer {} out of range", v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Node, E> {
+        Ok(Node::Float(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Node, E> {
+        Ok(Node::Str(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Node, E> {
+        Ok(Node::Str(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Node::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Node::Object(entries))
+    }
+}
+
+/// Instance variables of strings with an encoding
+fn encoding_ivars(encoding: &StringEncoding) -> Ivars {
+    match encoding {
+        StringEncoding::None => Vec::new(),
+        StringEncoding::Utf8 => vec![(Symbol::new("E"), Value::True)],
+        StringEncoding::UsAscii => vec![(Symbol::new("E"), Value::False)],
+        StringEncoding::Named(name) => vec![(
+            Symbol::new("encoding"),
+            Value::String(RString::binary(name.clone())),
+        )],
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Field holding bytes: `text` if they are UTF-8, `hex` otherwise
+fn bytes_field(bytes: &[u8]) -> (&'static str, Node) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => ("text", Node::Str(text.to_string())),
+        Err(_) => ("hex", Node::Str(to_hex(bytes))),
+    }
+}
+
+fn name_node(name: &[u8]) -> Node {
+    Node::Str(String::from_utf8_lossy(name).into_owned())
+}
+
+fn ivars_node(ivars: &Ivars, strings: &Ivars) -> Node {
+    Node::Object(
+        ivars
+            .iter()
+            .map(|(name, value)| {
+                (
+                    String::from_utf8_lossy(&name.name).into_owned(),
+                    to_node(value, strings),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn symbol_node(symbol: &Symbol, strings: &Ivars) -> Node {
+    let mut fields = vec![("name", name_node(&symbol.name))];
+    if symbol.ivars != Symbol::new(&String::from_utf8_lossy(&symbol.name)).ivars {
+        fields.push(("ivars", ivars_node(&symbol.ivars, strings)));
+    }
+    Node::tagged("Symbol", fields)
+}
+
+/// Keys of a hash that can be written as an array indexed by key
+fn sparse_keys(entries: &[(Value, Value)]) -> Option<Vec<i64>> {
+    let keys = entries
+        .iter()
+        .map(|(key, value)| match key {
+            Value::Fixnum(k) if *k > 0 && !value.is_nil() => Some(*k),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let max = *keys.iter().max()?;
+    (max <= keys.len() as i64 * MAX_SPARSE_RATIO + 16).then_some(keys)
+}
+
+fn to_node(value: &Value, strings: &Ivars) -> Node {
+    match value {
+        Value::Nil => Node::Null,
+        Value::True => Node::Bool(true),
+        Value::False => Node::Bool(false),
+        Value::Fixnum(i) => Node::Int(*i),
+        Value::Bignum {
+            negative,
+            magnitude,
+        } => Node::tagged(
+            "Bignum",
+            vec![
+                ("negative", Node::Bool(*negative)),
+                ("hex", Node::Str(to_hex(magnitude))),
+            ],
+        ),
+        Value::Float(raw) => match value.as_f64() {
+            Some(f) if f.is_finite() && Value::float(f) == *value => Node::Float(f),
+            _ => Node::tagged("Float", vec![bytes_field(raw)]),
+        },
+        Value::Symbol(symbol) => symbol_node(symbol, strings),
+        Value::String(s) => match std::str::from_utf8(&s.bytes) {
+            Ok(text) if s.ivars == *strings => Node::Str(text.to_string()),
+            _ => Node::tagged(
+                "String",
+                vec![
+                    bytes_field(&s.bytes),
+                    ("ivars", ivars_node(&s.ivars, strings)),
+                ],
+            ),
+        },
+        Value::Regexp { source, options } => Node::tagged(
+            "Regexp",
+            vec![bytes_field(source), ("options", Node::Int(*options as i64))],
+        ),
+        Value::Array(items) => Node::Array(items.iter().map(|v| to_node(v, strings)).collect()),
+        Value::Hash { entries, default } => {
+            if let (None, Some(keys)) = (default, sparse_keys(entries)) {
+                let mut marker = Vec::new();
+                if !keys.windows(2).all(|w| w[0] < w[1]) {
+                    marker.push((
+                        "order",
+                        Node::Array(keys.iter().map(|&k| Node::Int(k)).collect()),
+                    ));
+                }
+                let mut items = vec![Node::Null; *keys.iter().max().unwrap_or(&0) as usize + 1];
+                items[0] = Node::tagged("Hash", marker);
+                for ((_, value), key) in entries.iter().zip(keys) {
+                    items[key as usize] = to_node(value, strings);
+                }
+                return Node::Array(items);
+            }
+
+            let mut fields = vec![(
+                "entries",
+                Node::Array(
+                    entries
+                        .iter()
+                        .map(|(k, v)| Node::Array(vec![to_node(k, strings), to_node(v, strings)]))
+                        .collect(),
+                ),
+            )];
+            if let Some(default) = default {
+                fields.push(("default", to_node(default, strings)));
+            }
+            Node::tagged("Hash", fields)
+        }
+        Value::Object { class, ivars } => {
+            let mut entries = vec![(CLASS_KEY.to_string(), name_node(&class.name))];
+            for (name, value) in ivars {
+                let name = String::from_utf8_lossy(&name.name);
+                // Instance variables without `@` are kept apart with a `:`
+                let key = match name.strip_prefix('@') {
+                    Some(key) => key.to_string(),
+                    None => format!(":{}", name),
+                };
+                entries.push((key, to_node(value, strings)));
+            }
+            Node::Object(entries)
+        }
+        Value::Struct { class, members } => Node::tagged(
+            "Struct",
+            vec![
+                ("class", name_node(&class.name)),
+                ("members", ivars_node(members, strings)),
+            ],
+        ),
+        Value::UserDefined { class, data, ivars } => {
+            let mut fields = vec![
+                ("class", name_node(&class.name)),
+                ("hex", Node::Str(to_hex(data))),
+            ];
+            if !ivars.is_empty() {
+                fields.push(("ivars", ivars_node(ivars, strings)));
+            }
+            Node::tagged("UserDefined", fields)
+        }
+        Value::UserMarshal { class, value } => {
+            wrapper_node("UserMarshal", "class", class, value, strings)
+        }
+        Value::Data { class, value } => wrapper_node("Data", "class", class, value, strings),
+        Value::Extended { module, value } => {
+            wrapper_node("Extended", "module", module, value, strings)
+        }
+        Value::UserClass { class, value } => {
+            wrapper_node("UserClass", "class", class, value, strings)
+        }
+        Value::WithIvars { value, ivars } => Node::tagged(
+            "WithIvars",
+            vec![
+                ("value", to_node(value, strings)),
+                ("ivars", ivars_node(ivars, strings)),
+            ],
+        ),
+        Value::Class(name) => Node::tagged("Class", vec![("name", name_node(name))]),
+        Value::Module(name) => Node::tagged("Module", vec![("name", name_node(name))]),
+        Value::OldModule(name) => Node::tagged("OldModule", vec![("name", name_node(name))]),
+        Value::Link(index) => Node::tagged("Link", vec![("index", Node::Int(*index as i64))]),
+        Value::Table(table) => {
+            let rows = if table.xsize == 0 {
+                Vec::new()
+            } else {
+                table
+                    .data
+                    .chunks(table.xsize)
+                    .map(|row| {
+                        let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+                        Node::Str(row.join(" "))
+                    })
+                    .collect()
+            };
+            Node::tagged(
+                "Table",
+                vec![
+                    ("dimensions", Node::Int(table.dimensions as i64)),
+                    ("xsize", Node::Int(table.xsize as i64)),
+                    ("ysize", Node::Int(table.ysize as i64)),
+                    ("zsize", Node::Int(table.zsize as i64)),
+                    ("rows", Node::Array(rows)),
+                ],
+            )
+        }
+        Value::Color(color) => Node::tagged(
+            "Color",
+            vec![
+                ("red", Node::Float(color.red)),
+                ("green", Node::Float(color.green)),
+                ("blue", Node::Float(color.blue)),
+                ("alpha", Node::Float(color.alpha)),
+            ],
+        ),
+        Value::Tone(tone) => Node::tagged(
+            "Tone",
+            vec![
+                ("red", Node::Float(tone.red)),
+                ("green", Node::Float(tone.green)),
+                ("blue", Node::Float(tone.blue)),
+                ("gray", Node::Float(tone.gray)),
+            ],
+        ),
+    }
+}
+
+fn wrapper_node(tag: &str, key: &str, class: &Symbol, value: &Value, strings: &Ivars) -> Node {
+    Node::tagged(
+        tag,
+        vec![
+            (key, name_node(&class.name)),
+            ("value", to_node(value, strings)),
+        ],
+    )
+}
+
+fn invalid(message: impl Into<String>) -> MarshalError {
+    MarshalError::InvalidStructure(message.into())
+}
+
+/// Get a field of a tagged value
+fn field<'a>(node: &'a Node, tag: &str, key: &str) -> Result<&'a Node, MarshalError> {
+    node.get(key)
+        .ok_or_else(|| invalid(format!("{} without {}", tag, key)))
+}
+
+fn str_field<'a>(node: &'a Node, tag: &str, key: &str) -> Result<&'a str, MarshalError> {
+    field(node, tag, key)?
+        .as_str()
+        .ok_or_else(|| invalid(format!("{} {} is not a string", tag, key)))
+}
+
+fn int_field(node: &Node, tag: &str, key: &str) -> Result<i64, MarshalError> {
+    field(node, tag, key)?
+        .as_i64()
+        .ok_or_else(|| invalid(format!("{} {} is not an integer", tag, key)))
+}
+
+fn float_field(node: &Node, tag: &str, key: &str) -> Result<f64, MarshalError> {
+    field(node, tag, key)?
+        .as_f64()
+        .ok_or_else(|| invalid(format!("{} {} is not a number", tag, key)))
+}
+
+fn hex_field(node: &Node, tag: &str, key: &str) -> Result<Vec<u8>, MarshalError> {
+    from_hex(str_field(node, tag, key)?).ok_or_else(|| invalid(format!("{} has invalid hex", tag)))
+}
+
+/// Read the bytes written by [`bytes_field`]
+fn bytes_of(node: &Node, tag: &str) -> Result<Vec<u8>, MarshalError> {
+    match node.get("text") {
+        Some(Node::Str(text)) => Ok(text.as_bytes().to_vec()),
+        _ => hex_field(node, tag, "hex"),
+    }
+}
+
+fn ivars_of(node: &Node, strings: &Ivars) -> Result<Ivars, MarshalError> {
+    match node {
+        Node::Object(entries) => entries
+            .iter()
+            .map(|(name, value)| Ok((Symbol::new(name), from_node(value, strings)?)))
+            .collect(),
+        _ => Err(invalid("Instance variables are not an object")),
+    }
+}
+
+fn symbol_of(node: &Node, strings: &Ivars) -> Result<Symbol, MarshalError> {
+    let mut symbol = Symbol::new(str_field(node, "Symbol", "name")?);
+    if let Some(ivars) = node.get("ivars") {
+        symbol.ivars = ivars_of(ivars, strings)?;
+    }
+    Ok(symbol)
+}
+
+/// Whether an array is a hash written by key
+fn is_sparse_hash(items: &[Node]) -> bool {
+    matches!(items.first(), Some(marker)
+        if marker.get(TYPE_KEY).and_then(Node::as_str) == Some("Hash")
+            && marker.get("entries").is_none())
+}
+
+fn from_node(node: &Node, strings: &Ivars) -> Result<Value, MarshalError> {
+    let entries = match node {
+        Node::Null => return Ok(Value::Nil),
+        Node::Bool(true) => return Ok(Value::True),
+        Node::Bool(false) => return Ok(Value::False),
+        Node::Int(i) => return Ok(Value::Fixnum(*i)),
+        Node::Float(f) => return Ok(Value::float(*f)),
+        Node::Str(text) => {
+            return Ok(Value::String(RString {
+                bytes: text.as_bytes().to_vec(),
+                ivars: strings.clone(),
+            }))
+        }
+        Node::Array(items) if is_sparse_hash(items) => {
+            let mut entries: Vec<(Value, Value)> = Vec::new();
+            for (key, item) in items.iter().enumerate().skip(1) {
+                if *item != Node::Null {
+                    entries.push((Value::Fixnum(key as i64), from_node(item, strings)?));
+                }
+            }
+            if let Some(Node::Array(order)) = items[0].get("order") {
+                let mut ordered = Vec::with_capacity(entries.len());
+                for key in order {
+                    let key = key
+                        .as_i64()
+                        .ok_or_else(|| invalid("Hash order is not integers"))?;
+                    let pos = entries
+                        .iter()
+                        .position(|(k, _)| k.as_i64() == Some(key))
+                        .ok_or_else(|| invalid(format!("Hash order has unknown key {}", key)))?;
+                    ordered.push(entries.swap_remove(pos));
+                }
+                ordered.append(&mut entries);
+                entries = ordered;
+            }
+            return Ok(Value::Hash {
+                entries,
+                default: None,
+            });
+        }
+        Node::Array(items) => {
+            return items
+                .iter()
+                .map(|item| from_node(item, strings))
+                .collect::<Result<_, _>>()
+                .map(Value::Array)
+        }
+        Node::Object(entries) => entries,
+    };
+
+    if let Some(class) = node.get(CLASS_KEY).and_then(Node::as_str) {
+        let ivars = entries
+            .iter()
+            .filter(|(key, _)| key != CLASS_KEY)
+            .map(|(key, value)| {
+                let name = match key.strip_prefix(':') {
+                    Some(name) => name.to_string(),
+                    None => format!("@{}", key),
+                };
+                Ok((Symbol::new(&name), from_node(value, strings)?))
+            })
+            .collect::<Result<_, MarshalError>>()?;
+        return Ok(Value::Object {
+            class: Symbol::new(class),
+            ivars,
+        });
+    }
+
+    let tag = node
+        .get(TYPE_KEY)
+        .and_then(Node::as_str)
+        .ok_or_else(|| invalid("Object without json_class or $type"))?;
+    let class = |key: &str| str_field(node, tag, key).map(Symbol::new);
+    let inner = || from_node(field(node, tag, "value")?, strings).map(Box::new);
+
+    Ok(match tag {
+        "Bignum" => Value::Bignum {
+            negative: field(node, tag, "negative")? == &Node::Bool(true),
+            magnitude: hex_field(node, tag, "hex")?,
+        },
+        "Float" => Value::Float(bytes_of(node, tag)?),
+        "Symbol" => Value::Symbol(symbol_of(node, strings)?),
+        "String" => Value::String(RString {
+            bytes: bytes_of(node, tag)?,
+            ivars: ivars_of(field(node, tag, "ivars")?, strings)?,
+        }),
+        "Regexp" => Value::Regexp {
+            source: bytes_of(node, tag)?,
+            options: int_field(node, tag, "options")? as u8,
+        },
+        "Hash" => {
+            let Node::Array(pairs) = field(node, tag, "entries")? else {
+                return Err(invalid("Hash entries are not an array"));
+            };
+            let entries = pairs
+                .iter()
+                .map(|pair| match pair {
+                    Node::Array(kv) if kv.len() == 2 => {
+                        Ok((from_node(&kv[0], strings)?, from_node(&kv[1], strings)?))
+                    }
+                    _ => Err(invalid("Hash entry is not a [key, value] pair")),
+                })
+                .collect::<Result<_, MarshalError>>()?;
+            let default = match node.get("default") {
+                Some(default) => Some(Box::new(from_node(default, strings)?)),
+                None => None,
+            };
+            Value::Hash { entries, default }
+        }
+        "Struct" => Value::Struct {
+            class: class("class")?,
+            members: ivars_of(field(node, tag, "members")?, strings)?,
+        },
+        "UserDefined" => Value::UserDefined {
+            class: class("class")?,
+            data: hex_field(node, tag, "hex")?,
+            ivars: match node.get("ivars") {
+                Some(ivars) => ivars_of(ivars, strings)?,
+                None => Vec::new(),
+            },
+        },
+        "UserMarshal" => Value::UserMarshal {
+            class: class("class")?,
+            value: inner()?,
+        },
+        "Data" => Value::Data {
+            class: class("class")?,
+            value: inner()?,
+        },
+        "Extended" => Value::Extended {
+            module: class("module")?,
+            value: inner()?,
+        },
+        "UserClass" => Value::UserClass {
+            class: class("class")?,
+            value: inner()?,
+        },
+        "WithIvars" => Value::WithIvars {
+            value: inner()?,
+            ivars: ivars_of(field(node, tag, "ivars")?, strings)?,
+        },
+        "Class" => Value::Class(str_field(node, tag, "name")?.as_bytes().to_vec()),
+        "Module" => Value::Module(str_field(node, tag, "name")?.as_bytes().to_vec()),
+        "OldModule" => Value::OldModule(str_field(node, tag, "name")?.as_bytes().to_vec()),
+        "Link" => Value::Link(int_field(node, tag, "index")? as usize),
+        "Table" => {
+            let size = |key| {
+                usize::try_from(int_field(node, tag, key)?)
+                    .map_err(|_| invalid(format!("Table {} is negative", key)))
+            };
+            let (xsize, ysize, zsize) = (size("xsize")?, size("ysize")?, size("zsize")?);
+            let Node::Array(rows) = field(node, tag, "rows")? else {
+                return Err(invalid("Table rows are not an array"));
+            };
+            let rows = rows
+                .iter()
+                .map(|row| {
+                    row.as_str()
+                        .ok_or_else(|| invalid("Table row is not a string"))
+                })
+                .collect::<Result<Vec<_>, MarshalError>>()?;
+            let count = xsize
+                .checked_mul(ysize)
+                .and_then(|n| n.checked_mul(zsize))
+                .ok_or_else(|| invalid("Table is too large"))?;
+            // Every value takes at least one character
+            let text_len: usize = rows.iter().map(|row| row.len()).sum();
+            if count > text_len {
+                return Err(invalid(format!(
+                    "Table has at most {} values instead of {}",
+                    text_len, count
+                )));
+            }
+
+            let mut data = Vec::with_capacity(count);
+            for row in rows {
+                for value in row.split_ascii_whitespace() {
+                    data.push(
+                        value
+                            .parse()
+                            .map_err(|_| invalid(format!("Invalid table value {:?}", value)))?,
+                    );
+                }
+            }
+            if data.len() != count {
+                return Err(invalid(format!(
+                    "Table has {} values instead of {}",
+                    data.len(),
+                    count
+                )));
+            }
+            Value::Table(Table {
+                dimensions: int_field(node, tag, "dimensions")? as u32,
+                xsize,
+                ysize,
+                zsize,
+                data,
+            })
+        }
+        "Color" => Value::Color(Color {
+            red: float_field(node, tag, "red")?,
+            green: float_field(node, tag, "green")?,
+            blue: float_field(node, tag, "blue")?,
+            alpha: float_field(node, tag, "alpha")?,
+        }),
+        "Tone" => Value::Tone(Tone {
+            red: float_field(node, tag, "red")?,
+            green: float_field(node, tag, "green")?,
+            blue: float_field(node, tag, "blue")?,
+            gray: float_field(node, tag, "gray")?,
+        }),
+        other => return Err(invalid(format!("Unknown $type {:?}", other))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::marshal::{dump, load};
+
+    #[test]
+    fn test_json_round_trip() {
+        let text = |s: &str| Value::String(RString::binary(s.as_bytes().to_vec()));
+        let event = Value::Object {
+            class: Symbol::new("RPG::Event"),
+            ivars: vec![
+                (Symbol::new("@id"), Value::Fixnum(2)),
+                (Symbol::new("@name"), text("EV002")),
+                (Symbol::new("@x"), Value::float(1.5)),
+            ],
+        };
+        let map = Value::Object {
+            class: Symbol::new("RPG::Map"),
+            ivars: vec![
+                (Symbol::new("@data"), Value::Table(Table::new(3, 2, 1))),
+                (
+                    Symbol::new("@tone"),
+                    Value::Tone(Tone {
+                        red: 0.0,
+                        green: -17.5,
+                        blue: 0.0,
+                        gray: 255.0,
+                    }),
+                ),
+                (
+                    Symbol::new("@events"),
+                    Value::Hash {
+                        entries: vec![(Value::Fixnum(2), event), (Value::Fixnum(1), text("x"))],
+                        default: None,
+                    },
+                ),
+                (
+                    Symbol::new("@misc"),
+                    Value::Array(vec![
+                        Value::Symbol(Symbol::new("sym")),
+                        Value::string("UTF-8"),
+                        Value::String(RString::binary(vec![0x78, 0x9c, 0xff])),
+                        Value::Float(b"0.1\0\x9a\x99".to_vec()),
+                        Value::Link(5),
+                        Value::Hash {
+                            entries: Vec::new(),
+                            default: Some(Box::new(Value::Fixnum(0))),
+                        },
+                    ]),
+                ),
+            ],
+        };
+        let bytes = dump(&map);
+
+        let json = to_json(&load(&bytes).unwrap(), &StringEncoding::None);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        // Map events read like MV/MZ events
+        assert_eq!(value["json_class"], "RPG::Map");
+        assert_eq!(value["events"][2]["name"], "EV002");
+        assert_eq!(value["events"][0]["order"], serde_json::json!([2, 1]));
+        assert_eq!(value["data"]["rows"], serde_json::json!(["0 0 0", "0 0 0"]));
+        assert_eq!(value["misc"][1]["$type"], "String");
+
+        let restored = from_json(&json, &StringEncoding::None).unwrap();
+        assert_eq!(dump(&restored), bytes);
+        assert!(from_json("{\"name\": 1}", &StringEncoding::None).is_err());
+    }
+
+    #[test]
+    fn test_table_sizes_are_checked() {
+        let table = |xsize: u64, ysize: u64, zsize: u64| {
+            format!(
+                "{{\"$type\": \"Table\", \"dimensions\": 3, \"xsize\": {}, \"ysize\": {}, \"zsize\": {}, \"rows\": [\"1 2\"]}}",
+                xsize, ysize, zsize
+            )
+        };
+
+        assert!(from_json(&table(2, 1, 1), &StringEncoding::None).is_ok());
+        for (xsize, ysize, zsize) in [
+            (1 << 32, 1 << 32, 2),
+            (i64::MAX as u64, 2, 1),
+            (1 << 20, 1 << 20, 1),
+            (3, 1, 1),
+        ] {
+            assert!(matches!(
+                from_json(&table(xsize, ysize, zsize), &StringEncoding::None),
+                Err(MarshalError::InvalidStructure(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Keeping object links valid across edits
+//!
+//! [`Value::Link`] refers to an earlier object by its index in the object
+//! table, so replacing objects with a different number of objects shifts
+//! every later index. Edits record each replacement as a [`Splice`], and
+//! [`relink`] updates the links once all edits are done.
+
+use super::value::{Ivars, Value};
+
+/// Replacement of objects in the object table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Splice {
+    /// Table index of the first replaced object, counted after the
+    /// splices recorded before this one
+    pub at: usize,
+    /// Number of table entries removed
+    pub removed: usize,
+    /// Number of table entries inserted
+    pub inserted: usize,
+}
+
+impl Splice {
+    /// Map a table index through the splice
+    fn map(&self, index: usize) -> usize {
+        if index >= self.at + self.removed {
+            index + self.inserted - self.removed
+        } else {
+            index
+        }
+    }
+}
+
+/// Update the links of a value after splices, in the order they were made
+///
+/// Replaced objects are assumed not to be linked to.
+pub fn relink(value: &mut Value, splices: &[Splice]) {
+    if splices.iter().all(|s| s.removed == s.inserted) {
+        return;
+    }
+    relink_value(value, splices);
+}
+
+fn relink_value(value: &mut Value, splices: &[Splice]) {
+    let relink_ivars = |ivars: &mut Ivars| {
+        for (_, value) in ivars {
+            relink_value(value, splices);
+        }
+    };
+    match value {
+        Value::Link(index) => {
+            *index = splices.iter().fold(*index, |index, s| s.map(index));
+        }
+        Value::String(string) => relink_ivars(&mut string.ivars),
+        Value::Array(items) => {
+            for item in items {
+                relink_value(item, splices);
+            }
+        }
+        Value::Hash { entries, default } => {
+            for (key, value) in entries {
+                relink_value(key, splices);
+                relink_value(value, splices);
+            }
+            if let Some(default) = default {
+                relink_value(default, splices);
+            }
+        }
+        Value::Object { ivars, .. }
+        | Value::Struct { members: ivars, .. }
+        | Value::UserDefined { ivars, .. } => relink_ivars(ivars),
+        Value::UserMarshal { value, .. }
+        | Value::Data { value, .. }
+        | Value::Extended { value, .. }
+        | Value::UserClass { value, .. } => relink_value(value, splices),
+        Value::WithIvars { value, ivars } => {
+            relink_value(value, splices);
+            relink_ivars(ivars);
+        }
+        _ => {}
+    }
+}
+
+/// Table index of an instance variable's value, given the object's index
+pub fn ivar_index(object: &Value, index: usize, name: &str) -> Option<usize> {
+    let Value::Object { ivars, .. } = object else {
+        return None;
+    };
+    let mut index = index + 1;
+    for (key, value) in ivars {
+        if key.is(name) {
+            return Some(index);
+        }
+        index += value.object_count();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{dump, load, Symbol};
+    use super::*;
+
+    #[test]
+    fn test_relink_after_splice() {
+        // s = "shared"; [["a", "b"], s, [s]]
+        let mut value = Value::Array(vec![
+            Value::Array(vec![Value::string("a"), Value::string("b")]),
+            Value::string("shared"),
+            Value::Array(vec![Value::Link(4)]),
+        ]);
+        assert_eq!(value.object_count(), 6);
+        assert_eq!(load(&dump(&value)).unwrap(), value);
+
+        // Replace "a" and "b" (entries 2 and 3) with one string
+        let inner = &mut value.as_array_mut().unwrap()[0];
+        *inner.as_array_mut().unwrap() = vec![Value::string("ab")];
+        relink(
+            &mut value,
+            &[Splice {
+                at: 2,
+                removed: 2,
+                inserted: 1,
+            }],
+        );
+        assert_eq!(
+            value.as_array().unwrap()[2],
+            Value::Array(vec![Value::Link(3)])
+        );
+
+        let object = Value::Object {
+            class: Symbol::new("RPG::Event::Page"),
+            ivars: vec![
+                (Symbol::new("@graphic"), value),
+                (Symbol::new("@list"), Value::Array(Vec::new())),
+            ],
+        };
+        assert_eq!(ivar_index(&object, 0, "@graphic"), Some(1));
+        assert_eq!(ivar_index(&object, 0, "@list"), Some(6));
+        assert_eq!(ivar_index(&object, 0, "@name"), None);
+    }
+}
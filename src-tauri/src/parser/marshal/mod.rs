@@ -0,0 +1,377 @@
+//! Ruby Marshal module
+//!
+//! RPG Maker XP, VX and VX Ace store their data (`.rxdata`, `.rvdata`,
+//! `.rvdata2`) as Ruby objects written with `Marshal.dump`, format 4.8.
+//! This module reads that format into a [`Value`] tree and writes it back.
+//!
+//! Writing an unmodified tree gives the original bytes back: symbols are
+//! linked as Ruby links them, and everything else whose encoding Ruby
+//! could choose (object links, float text, bignum digits, string
+//! encodings) is kept as read. RGSS `Table`, `Color` and `Tone` data is
+//! decoded into typed values (see [`rgss`]). Edits that change the number
+//! of objects must keep links valid (see [`links`]). Values can also be
+//! written as JSON and read back without loss (see [`json`]).
+
+pub mod json;
+pub mod links;
+mod reader;
+pub mod rgss;
+pub mod value;
+mod writer;
+
+pub use json::{from_json, to_json};
+pub use links::{ivar_index, relink, Splice};
+pub use reader::load;
+pub use rgss::{Color, Table, Tone};
+pub use value::{Ivars, RString, StringEncoding, Symbol, Value};
+pub use writer::dump;
+
+/// Major version of the Marshal format
+pub const MAJOR_VERSION: u8 = 4;
+
+/// Minor version of the Marshal format
+pub const MINOR_VERSION: u8 = 8;
+
+/// Error type for Marshal data
+#[derive(Debug, thiserror::Error)]
+pub enum MarshalError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Unsupported Marshal version {0}.{1}")]
+    UnsupportedVersion(u8, u8),
+
+    #[error("Unexpected end of data at offset {0:#x}")]
+    UnexpectedEof(usize),
+
+    #[error("Invalid type byte {0:#04x} at offset {1:#x}")]
+    InvalidType(u8, usize),
+
+    #[error("Link to unknown symbol {0}")]
+    InvalidSymlink(usize),
+
+    #[error("Invalid structure: {0}")]
+    InvalidStructure(String),
+}
+
+/// Load a value from a Marshal file
+pub fn load_file(path: &std::path::Path) -> Result<Value, MarshalError> {
+    load(&std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Data written by Ruby, with what it decodes to
+    fn fixtures() -> Vec<(&'static [u8], Value)> {
+        let utf8 = |text: &str| Value::string(text);
+        vec![
+            (
+                // [1, "a", :sym, :sym, nil]
+                b"\x04\x08[\x0ai\x06I\"\x06a\x06:\x06ET:\x08sym;\x06\x30",
+                Value::Array(vec![
+                    Value::Fixnum(1),
+                    utf8("a"),
+                    Value::Symbol(Symbol::new("sym")),
+                    Value::Symbol(Symbol::new("sym")),
+                    Value::Nil,
+                ]),
+            ),
+            (
+                // [-1, 300, -300, 2**40, -(2**40), 1.5]
+                b"\x04\x08[\x0bi\xfai\x02\x2c\x01i\xfe\xd4\xfel+\x08\x00\x00\x00\x00\x00\x01\
+                  l-\x08\x00\x00\x00\x00\x00\x01f\x081.5",
+                Value::Array(vec![
+                    Value::Fixnum(-1),
+                    Value::Fixnum(300),
+                    Value::Fixnum(-300),
+                    Value::Bignum {
+                        negative: false,
+                        magnitude: vec![0, 0, 0, 0, 0, 1],
+                    },
+                    Value::Bignum {
+                        negative: true,
+                        magnitude: vec![0, 0, 0, 0, 0, 1],
+                    },
+                    Value::float(1.5),
+                ]),
+            ),
+            (
+                // a = "x"; [a, a]
+                b"\x04\x08[\x07I\"\x06x\x06:\x06ET@\x06",
+                Value::Array(vec![utf8("x"), Value::Link(1)]),
+            ),
+            (
+                // :あ
+                b"\x04\x08I:\x08\xe3\x81\x82\x06:\x06ET",
+                Value::Symbol(Symbol::new("あ")),
+            ),
+            (
+                // h = Hash.new(0); h[:a] = 1
+                b"\x04\x08}\x06:\x06ai\x06i\x00",
+                Value::Hash {
+                    entries: vec![(Value::Symbol(Symbol::new("a")), Value::Fixnum(1))],
+                    default: Some(Box::new(Value::Fixnum(0))),
+                },
+            ),
+            (
+                // RPG Maker XP: Table.new(2) with [1, -1], and a Ruby 1.8 string
+                b"\x04\x08o:\x0dRPG::Map\x07:\x0a@datau:\x0aTable\x1d\
+                  \x01\x00\x00\x00\x02\x00\x00\x00\x01\x00\x00\x00\x01\x00\x00\x00\
+                  \x02\x00\x00\x00\x01\x00\xff\xff\
+                  :\x0b@bgm_n\"\x06x",
+                Value::Object {
+                    class: Symbol::new("RPG::Map"),
+                    ivars: vec![
+                        (
+                            Symbol::new("@data"),
+                            Value::Table(Table {
+                                dimensions: 1,
+                                xsize: 2,
+                                ysize: 1,
+                                zsize: 1,
+                                data: vec![1, -1],
+                            }),
+                        ),
+                        (
+                            Symbol::new("@bgm_n"),
+                            Value::String(RString::binary(b"x".to_vec())),
+                        ),
+                    ],
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_load_ruby_data() {
+        for (data, expected) in fixtures() {
+            let value = load(data).unwrap();
+            assert_eq!(value, expected);
+            assert_eq!(dump(&value), data);
+        }
+    }
+
+    #[test]
+    fn test_load_errors() {
+        assert!(matches!(
+            load(b"\x04\x070"),
+            Err(MarshalError::UnsupportedVersion(4, 7))
+        ));
+        assert!(matches!(
+            load(b"\x04\x08[\x07"),
+            Err(MarshalError::UnexpectedEof(_))
+        ));
+        assert!(matches!(
+            load(b"\x04\x08;\x00"),
+            Err(MarshalError::InvalidSymlink(0))
+        ));
+        assert!(matches!(
+            load(b"\x04\x08X"),
+            Err(MarshalError::InvalidType(b'X', 2))
+        ));
+        assert!(load(b"\x04\x0800").is_err());
+
+        let mut deep = b"\x04\x08".to_vec();
+        deep.extend(std::iter::repeat_n(b"[\x06", 100_000).flatten());
+        deep.push(b'0');
+        assert!(matches!(
+            load(&deep),
+            Err(MarshalError::InvalidStructure(_))
+        ));
+    }
+
+    /// Small deterministic generator (xorshift64)
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn bytes(&mut self, max: usize) -> Vec<u8> {
+            let len = self.below(max + 1);
+            (0..len).map(|_| self.next() as u8).collect()
+        }
+
+        fn symbol(&mut self) -> Symbol {
+            const NAMES: &[&str] = &["a", "@name", "@list", "RPG::Event", "日本", "E"];
+            Symbol::new(NAMES[self.below(NAMES.len())])
+        }
+
+        fn ivars(&mut self, depth: usize) -> Ivars {
+            (0..self.below(4))
+                .map(|_| (self.symbol(), self.value(depth)))
+                .collect()
+        }
+
+        fn string(&mut self) -> RString {
+            let bytes = self.bytes(12);
+            let ivars = match self.below(4) {
+                0 => Vec::new(),
+                1 => vec![(Symbol::new("E"), Value::True)],
+                2 => vec![(Symbol::new("E"), Value::False)],
+                _ => vec![(
+                    Symbol::new("encoding"),
+                    Value::String(RString::binary(b"Shift_JIS".to_vec())),
+                )],
+            };
+            RString { bytes, ivars }
+        }
+
+        fn f64(&mut self) -> f64 {
+            self.next() as i32 as f64 / 7.0
+        }
+
+        fn value(&mut self, depth: usize) -> Value {
+            let kinds = if depth == 0 { 12 } else { 26 };
+            let inner = depth.saturating_sub(1);
+            match self.below(kinds) {
+                0 => Value::Nil,
+                1 => Value::True,
+                2 => Value::False,
+                3 => Value::Fixnum(self.next() as i8 as i64),
+                4 => Value::Fixnum(self.next() as i32 as i64),
+                5 => Value::Bignum {
+                    negative: self.below(2) == 0,
+                    magnitude: (0..2 * (self.below(5) + 1))
+                        .map(|_| self.next() as u8)
+                        .collect(),
+                },
+                6 => {
+                    const FLOATS: &[&[u8]] = &[
+                        b"0",
+                        b"-1.25",
+                        b"inf",
+                        b"-inf",
+                        b"nan",
+                        b"1e+20",
+                        b"3\x00\x12\x34",
+                    ];
+                    Value::Float(FLOATS[self.below(FLOATS.len())].to_vec())
+                }
+                7 => Value::Symbol(self.symbol()),
+                8 => Value::String(self.string()),
+                9 => Value::Link(self.below(100_000)),
+                10 => Value::Color(Color {
+                    red: self.f64(),
+                    green: self.f64(),
+                    blue: self.f64(),
+                    alpha: self.f64(),
+                }),
+                11 => {
+                    let mut table =
+                        Table::new(self.below(4) + 1, self.below(3) + 1, self.below(2) + 1);
+                    for value in table.data.iter_mut() {
+                        *value = self.next() as i16;
+                    }
+                    Value::Table(table)
+                }
+                12 => Value::Array((0..self.below(5)).map(|_| self.value(inner)).collect()),
+                13 => Value::Hash {
+                    entries: (0..self.below(4))
+                        .map(|_| (self.value(inner), self.value(inner)))
+                        .collect(),
+                    default: (self.below(2) == 0).then(|| Box::new(self.value(inner))),
+                },
+                14 => Value::Object {
+                    class: self.symbol(),
+                    ivars: self.ivars(inner),
+                },
+                15 => Value::Struct {
+                    class: self.symbol(),
+                    members: self.ivars(inner),
+                },
+                16 => Value::UserDefined {
+                    class: Symbol::new("Custom"),
+                    data: self.bytes(40),
+                    ivars: self.ivars(inner),
+                },
+                17 => Value::UserMarshal {
+                    class: self.symbol(),
+                    value: Box::new(self.value(inner)),
+                },
+                18 => Value::Data {
+                    class: self.symbol(),
+                    value: Box::new(self.value(inner)),
+                },
+                19 => Value::Extended {
+                    module: self.symbol(),
+                    value: Box::new(self.value(inner)),
+                },
+                20 => Value::UserClass {
+                    class: self.symbol(),
+                    value: Box::new(Value::String(self.string())),
+                },
+                21 => Value::WithIvars {
+                    value: Box::new(Value::Regexp {
+                        source: self.bytes(8),
+                        options: self.next() as u8,
+                    }),
+                    ivars: self.ivars(inner),
+                },
+                22 => Value::Tone(Tone {
+                    red: self.f64(),
+                    green: self.f64(),
+                    blue: self.f64(),
+                    gray: self.f64(),
+                }),
+                23 => Value::Class(self.bytes(10)),
+                24 => Value::Module(self.bytes(10)),
+                _ => Value::OldModule(self.bytes(10)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_property() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..2000 {
+            let value = rng.value(4);
+            let data = dump(&value);
+            let loaded = load(&data).unwrap_or_else(|e| panic!("{}: {:?}", e, value));
+            assert_eq!(loaded, value);
+            assert_eq!(dump(&loaded), data);
+        }
+    }
+
+    #[test]
+    fn test_long_encoding() {
+        for x in [
+            0,
+            1,
+            122,
+            123,
+            -123,
+            -124,
+            255,
+            256,
+            -256,
+            -257,
+            65535,
+            65536,
+            i32::MAX as i64,
+            i32::MIN as i64,
+        ] {
+            let data = dump(&Value::Fixnum(x));
+            assert_eq!(load(&data).unwrap(), Value::Fixnum(x), "{}", x);
+        }
+        assert_eq!(dump(&Value::Fixnum(122)), b"\x04\x08i\x7f");
+        assert_eq!(dump(&Value::Fixnum(123)), b"\x04\x08i\x01\x7b");
+        assert_eq!(dump(&Value::Fixnum(-124)), b"\x04\x08i\xff\x84");
+
+        // Outside 32 bits, fixnums are written as bignums
+        assert_eq!(
+            load(&dump(&Value::Fixnum(1 << 40))).unwrap().as_i64(),
+            Some(1 << 40)
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Marshal reader
+
+use super::rgss::{Color, Table, Tone};
+use super::value::{Ivars, RString, Symbol, Value};
+use super::{MarshalError, MAJOR_VERSION, MINOR_VERSION};
+
+/// Deepest nesting accepted, to fail on hostile data instead of
+/// overflowing the stack
+const MAX_DEPTH: usize = 128;
+
+/// Load a value from Marshal data
+pub fn load(data: &[u8]) -> Result<Value, MarshalError> {
+    let mut reader = Reader {
+        data,
+        pos: 0,
+        symbols: Vec::new(),
+        depth: 0,
+    };
+
+    let version = [reader.byte()?, reader.byte()?];
+    if version != [MAJOR_VERSION, MINOR_VERSION] {
+        return Err(MarshalError::UnsupportedVersion(version[0], version[1]));
+    }
+
+    let value = reader.value()?;
+    if reader.pos != data.len() {
+        return Err(MarshalError::InvalidStructure(format!(
+            "{} trailing bytes",
+            data.len() - reader.pos
+        )));
+    }
+    Ok(value)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Symbols read so far, by index
+    symbols: Vec<Symbol>,
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, MarshalError> {
+        let byte = *self
+            .data
+            .get(self.pos)
+            .ok_or(MarshalError::UnexpectedEof(self.pos))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], MarshalError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(MarshalError::UnexpectedEof(self.data.len()))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Read a packed integer
+    fn long(&mut self) -> Result<i64, MarshalError> {
+        let c = self.byte()? as i8;
+        Ok(match c {
+            0 => 0,
+            5..=127 => c as i64 - 5,
+            -128..=-5 => c as i64 + 5,
+            1..=4 => {
+                let mut x = 0i64;
+                for i in 0..c {
+                    x |= (self.byte()? as i64) << (8 * i);
+                }
+                x
+            }
+            _ => {
+                let mut x = -1i64;
+                for i in 0..-c {
+                    x &= !(0xff << (8 * i));
+                    x |= (self.byte()? as i64) << (8 * i);
+                }
+                x
+            }
+        })
+    }
+
+    /// Read a non-negative packed integer
+    fn len(&mut self) -> Result<usize, MarshalError> {
+        let pos = self.pos;
+        let len = self.long()?;
+        usize::try_from(len)
+            .map_err(|_| MarshalError::InvalidStructure(format!("Negative length at {:#x}", pos)))
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, MarshalError> {
+        let len = self.len()?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    /// Read the body of a symbol (`:`), registering it
+    fn symbol_body(&mut self, with_ivars: bool) -> Result<Symbol, MarshalError> {
+        let name = self.bytes()?;
+        let index = self.symbols.len();
+        self.symbols.push(Symbol {
+            name,
+            ivars: Vec::new(),
+        });
+        if with_ivars {
+            let ivars = self.ivars()?;
+            self.symbols[index].ivars = ivars;
+        }
+        Ok(self.symbols[index].clone())
+    }
+
+    fn symlink(&mut self) -> Result<Symbol, MarshalError> {
+        let index = self.len()?;
+        self.symbols
+            .get(index)
+            .cloned()
+            .ok_or(MarshalError::InvalidSymlink(index))
+    }
+
+    /// Read a symbol, as a class name or hash key
+    fn symbol(&mut self) -> Result<Symbol, MarshalError> {
+        let pos = self.pos;
+        match self.byte()? {
+            b':' => self.symbol_body(false),
+            b';' => self.symlink(),
+            b'I' if self.data.get(self.pos) == Some(&b':') => {
+                self.pos += 1;
+                self.symbol_body(true)
+            }
+            byte => Err(MarshalError::InvalidType(byte, pos)),
+        }
+    }
+
+    fn ivars(&mut self) -> Result<Ivars, MarshalError> {
+        let count = self.len()?;
+        let mut ivars = Vec::with_capacity(count.min(1024));
+        for _ in 0..count {
+            let name = self.symbol()?;
+            let value = self.value()?;
+            ivars.push((name, value));
+        }
+        Ok(ivars)
+    }
+
+    fn value(&mut self) -> Result<Value, MarshalError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(MarshalError::InvalidStructure(format!(
+                "Nesting deeper than {} at {:#x}",
+                MAX_DEPTH, self.pos
+            )));
+        }
+        let value = self.value_inner();
+        self.depth -= 1;
+        value
+    }
+
+    fn value_inner(&mut self) -> Result<Value, MarshalError> {
+        let pos = self.pos;
+        let value = match self.byte()? {
+            b'0' => Value::Nil,
+            b'T' => Value::True,
+            b'F' => Value::False,
+            b'i' => Value::Fixnum(self.long()?),
+            b'l' => {
+                let negative = match self.byte()? {
+                    b'-' => true,
+                    b'+' => false,
+                    byte => return Err(MarshalError::InvalidType(byte, self.pos - 1)),
+                };
+                let words = self.len()?;
+                let magnitude = self
+                    .take(
+                        words
+                            .checked_mul(2)
+                            .ok_or(MarshalError::UnexpectedEof(pos))?,
+                    )?
+                    .to_vec();
+                Value::Bignum {
+                    negative,
+                    magnitude,
+                }
+            }
+            b'f' => Value::Float(self.bytes()?),
+            b':' => Value::Symbol(self.symbol_body(false)?),
+            b';' => Value::Symbol(self.symlink()?),
+            b'"' => Value::String(RString::binary(self.bytes()?)),
+            b'/' => {
+                let source = self.bytes()?;
+                let options = self.byte()?;
+                Value::Regexp { source, options }
+            }
+            b'[' => {
+                let len = self.len()?;
+                let mut items = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    items.push(self.value()?);
+                }
+                Value::Array(items)
+            }
+            byte @ (b'{' | b'}') => {
+                let len = self.len()?;
+                let mut entries = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    let key = self.value()?;
+                    let value = self.value()?;
+                    entries.push((key, value));
+                }
+                let default = if byte == b'}' {
+                    Some(Box::new(self.value()?))
+                } else {
+                    None
+                };
+                Value::Hash { entries, default }
+            }
+            b'o' => {
+                let class = self.symbol()?;
+                let ivars = self.ivars()?;
+                Value::Object { class, ivars }
+            }
+            b'S' => {
+                let class = self.symbol()?;
+                let members = self.ivars()?;
+                Value::Struct { class, members }
+            }
+            b'u' => {
+                let class = self.symbol()?;
+                let data = self.bytes()?;
+                user_defined(class, data)
+            }
+            b'U' => {
+                let class = self.symbol()?;
+                let value = Box::new(self.value()?);
+                Value::UserMarshal { class, value }
+            }
+            b'd' => {
+                let class = self.symbol()?;
+                let value = Box::new(self.value()?);
+                Value::Data { class, value }
+            }
+            b'e' => {
+                let module = self.symbol()?;
+                let value = Box::new(self.value()?);
+                Value::Extended { module, value }
+            }
+            b'C' => {
+                let class = self.symbol()?;
+                let value = Box::new(self.value()?);
+                Value::UserClass { class, value }
+            }
+            b'I' if self.data.get(self.pos) == Some(&b':') => {
+                self.pos += 1;
+                Value::Symbol(self.symbol_body(true)?)
+            }
+            // `_dump` data with ivars is never an RGSS class
+            b'I' if self.data.get(self.pos) == Some(&b'u') => {
+                self.pos += 1;
+                let class = self.symbol()?;
+                let data = self.bytes()?;
+                let ivars = self.ivars()?;
+                Value::UserDefined { class, data, ivars }
+            }
+            b'I' => {
+                let value = self.value()?;
+                let ivars = self.ivars()?;
+                match value {
+                    Value::String(mut string) if string.ivars.is_empty() => {
+                        string.ivars = ivars;
+                        Value::String(string)
+                    }
+                    value => Value::WithIvars {
+                        value: Box::new(value),
+                        ivars,
+                    },
+                }
+            }
+            b'c' => Value::Class(self.bytes()?),
+            b'm' => Value::Module(self.bytes()?),
+            b'M' => Value::OldModule(self.bytes()?),
+            b'@' => Value::Link(self.len()?),
+            byte => return Err(MarshalError::InvalidType(byte, pos)),
+        };
+        Ok(value)
+    }
+}
+
+/// Decode the RGSS classes, keeping other `_dump` data as is
+fn user_defined(class: Symbol, data: Vec<u8>) -> Value {
+    let typed = if class.is("Table") {
+        Table::from_dump(&data).map(Value::Table)
+    } else if class.is("Color") {
+        Color::from_dump(&data).map(Value::Color)
+    } else if class.is("Tone") {
+        Tone::from_dump(&data).map(Value::Tone)
+    } else {
+        None
+    };
+
+    typed.unwrap_or(Value::UserDefined {
+        class,
+        data,
+        ivars: Vec::new(),
+    })
+}
@@ -0,0 +1,213 @@
+//! RGSS classes written with `_dump`
+//!
+//! RPG Maker XP, VX and VX Ace store maps, colors and tones as
+//! user-defined Marshal data (`u`) with these layouts, all little-endian:
+//!
+//! ```text
+//! Table   dimensions(i32) xsize(i32) ysize(i32) zsize(i32) size(i32)
+//!         size x i16, x varying fastest
+//! Color   red(f64) green(f64) blue(f64) alpha(f64)
+//! Tone    red(f64) green(f64) blue(f64) gray(f64)
+//! ```
+
+/// RGSS `Table`: an array of up to three dimensions of `i16`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    /// Number of dimensions (1 to 3)
+    pub dimensions: u32,
+    pub xsize: usize,
+    pub ysize: usize,
+    pub zsize: usize,
+    /// Values, x varying fastest, then y, then z
+    pub data: Vec<i16>,
+}
+
+impl Table {
+    /// Create a table filled with zeros
+    pub fn new(xsize: usize, ysize: usize, zsize: usize) -> Self {
+        let dimensions = if zsize > 1 {
+            3
+        } else if ysize > 1 {
+            2
+        } else {
+            1
+        };
+        Self {
+            dimensions,
+            xsize,
+            ysize,
+            zsize,
+            data: vec![0; xsize * ysize * zsize],
+        }
+    }
+
+    /// Read a table from its `_dump` data
+    pub fn from_dump(data: &[u8]) -> Option<Self> {
+        let header = |i: usize| -> Option<u32> {
+            let bytes = data.get(i * 4..i * 4 + 4)?;
+            Some(u32::from_le_bytes(bytes.try_into().ok()?))
+        };
+        let dimensions = header(0)?;
+        let xsize = header(1)? as usize;
+        let ysize = header(2)? as usize;
+        let zsize = header(3)? as usize;
+        let size = header(4)? as usize;
+
+        if xsize.checked_mul(ysize)?.checked_mul(zsize)? != size
+            || data.len() != 20 + size.checked_mul(2)?
+        {
+            return None;
+        }
+
+        let data = data[20..]
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        Some(Self {
+            dimensions,
+            xsize,
+            ysize,
+            zsize,
+            data,
+        })
+    }
+
+    /// Get the `_dump` data of the table
+    pub fn dump(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(20 + self.data.len() * 2);
+        for value in [
+            self.dimensions,
+            self.xsize as u32,
+            self.ysize as u32,
+            self.zsize as u32,
+            self.data.len() as u32,
+        ] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in &self.data {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out
+    }
+
+    /// Get a value
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<i16> {
+        self.index(x, y, z).map(|i| self.data[i])
+    }
+
+    /// Set a value, returning false if out of range
+    pub fn set(&mut self, x: usize, y: usize, z: usize, value: i16) -> bool {
+        match self.index(x, y, z) {
+            Some(i) => {
+                self.data[i] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> Option<usize> {
+        (x < self.xsize && y < self.ysize && z < self.zsize)
+            .then(|| x + self.xsize * (y + self.ysize * z))
+    }
+}
+
+/// Read four `f64` from `_dump` data
+fn read_f64x4(data: &[u8]) -> Option<[f64; 4]> {
+    if data.len() != 32 {
+        return None;
+    }
+    let mut values = [0.0; 4];
+    for (value, bytes) in values.iter_mut().zip(data.chunks_exact(8)) {
+        *value = f64::from_le_bytes(bytes.try_into().ok()?);
+    }
+    Some(values)
+}
+
+/// Write four `f64` as `_dump` data
+fn write_f64x4(values: [f64; 4]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// RGSS `Color`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub alpha: f64,
+}
+
+impl Color {
+    /// Read a color from its `_dump` data
+    pub fn from_dump(data: &[u8]) -> Option<Self> {
+        let [red, green, blue, alpha] = read_f64x4(data)?;
+        Some(Self {
+            red,
+            green,
+            blue,
+            alpha,
+        })
+    }
+
+    /// Get the `_dump` data of the color
+    pub fn dump(&self) -> Vec<u8> {
+        write_f64x4([self.red, self.green, self.blue, self.alpha])
+    }
+}
+
+/// RGSS `Tone`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub gray: f64,
+}
+
+impl Tone {
+    /// Read a tone from its `_dump` data
+    pub fn from_dump(data: &[u8]) -> Option<Self> {
+        let [red, green, blue, gray] = read_f64x4(data)?;
+        Some(Self {
+            red,
+            green,
+            blue,
+            gray,
+        })
+    }
+
+    /// Get the `_dump` data of the tone
+    pub fn dump(&self) -> Vec<u8> {
+        write_f64x4([self.red, self.green, self.blue, self.gray])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let mut table = Table::new(3, 2, 1);
+        assert_eq!(table.dimensions, 2);
+        assert!(table.set(2, 1, 0, -5));
+        assert!(!table.set(3, 0, 0, 1));
+        assert_eq!(table.get(2, 1, 0), Some(-5));
+        assert_eq!(table.data[5], -5);
+
+        let data = table.dump();
+        assert_eq!(data.len(), 20 + 12);
+        assert_eq!(Table::from_dump(&data), Some(table));
+        assert_eq!(Table::from_dump(&data[..30]), None);
+
+        let tone = Tone {
+            red: -68.0,
+            green: 0.0,
+            blue: 34.5,
+            gray: 255.0,
+        };
+        assert_eq!(Tone::from_dump(&tone.dump()), Some(tone));
+        assert_eq!(Color::from_dump(&[0; 31]), None);
+    }
+}
@@ -0,0 +1,389 @@
+//! Value tree of Ruby Marshal data
+//!
+//! The tree keeps everything needed to write the data back byte for byte:
+//! floats keep their text, bignums their digits, and object links stay
+//! links ([`Value::Link`]) instead of being resolved. Symbol links are the
+//! exception: they are resolved on load, since the writer recreates them
+//! exactly as Ruby does.
+
+use super::rgss::{Color, Table, Tone};
+
+/// Instance variables or other `name => value` pairs, in stream order
+pub type Ivars = Vec<(Symbol, Value)>;
+
+/// Ruby symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    /// Symbol name bytes
+    pub name: Vec<u8>,
+    /// Instance variables, holding the encoding of non-ASCII symbols
+    pub ivars: Ivars,
+}
+
+impl Symbol {
+    /// Create a symbol from its name
+    pub fn new(name: &str) -> Self {
+        let ivars = if name.is_ascii() {
+            Vec::new()
+        } else {
+            vec![(Symbol::new("E"), Value::True)]
+        };
+        Self {
+            name: name.as_bytes().to_vec(),
+            ivars,
+        }
+    }
+
+    /// Get the name as text
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.name).ok()
+    }
+
+    /// Check whether the symbol has a name
+    pub fn is(&self, name: &str) -> bool {
+        self.name == name.as_bytes()
+    }
+}
+
+/// Encoding of a Ruby string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StringEncoding {
+    /// No encoding: binary data, or any string written by Ruby 1.8
+    None,
+    /// UTF-8 (`E` = true)
+    Utf8,
+    /// US-ASCII (`E` = false)
+    UsAscii,
+    /// Other encoding, by name
+    Named(Vec<u8>),
+}
+
+/// Ruby string
+#[derive(Debug, Clone, PartialEq)]
+pub struct RString {
+    /// String bytes
+    pub bytes: Vec<u8>,
+    /// Instance variables, holding the encoding since Ruby 1.9
+    pub ivars: Ivars,
+}
+
+impl RString {
+    /// Create a UTF-8 string, as written by Ruby 1.9 and later
+    pub fn new(text: &str) -> Self {
+        Self {
+            bytes: text.as_bytes().to_vec(),
+            ivars: vec![(Symbol::new("E"), Value::True)],
+        }
+    }
+
+    /// Create a string without encoding, as written by Ruby 1.8
+    pub fn binary(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            ivars: Vec::new(),
+        }
+    }
+
+    /// Get the encoding of the string
+    pub fn encoding(&self) -> StringEncoding {
+        for (name, value) in &self.ivars {
+            if name.is("E") {
+                return match value {
+                    Value::True => StringEncoding::Utf8,
+                    _ => StringEncoding::UsAscii,
+                };
+            }
+            if name.is("encoding") {
+                if let Value::String(encoding) = value {
+                    return StringEncoding::Named(encoding.bytes.clone());
+                }
+            }
+        }
+        StringEncoding::None
+    }
+
+    /// Get the string as text, if it is valid UTF-8
+    ///
+    /// Strings without encoding are read as UTF-8, which RPG Maker XP and
+    /// VX use for all their text.
+    pub fn as_str(&self) -> Option<&str> {
+        match self.encoding() {
+            StringEncoding::Named(_) => None,
+            _ => std::str::from_utf8(&self.bytes).ok(),
+        }
+    }
+
+    /// Replace the text, keeping the encoding
+    pub fn set_str(&mut self, text: &str) {
+        self.bytes = text.as_bytes().to_vec();
+    }
+}
+
+/// Value of Ruby Marshal data
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// `nil` (`0`)
+    Nil,
+    /// `true` (`T`)
+    True,
+    /// `false` (`F`)
+    False,
+    /// Fixnum (`i`)
+    Fixnum(i64),
+    /// Bignum (`l`), with its magnitude in little-endian bytes
+    Bignum { negative: bool, magnitude: Vec<u8> },
+    /// Float (`f`), as the text Ruby wrote
+    Float(Vec<u8>),
+    /// Symbol (`:`, or `;` for links to earlier symbols)
+    Symbol(Symbol),
+    /// String (`"`), with its instance variables (`I`)
+    String(RString),
+    /// Regular expression (`/`)
+    Regexp { source: Vec<u8>, options: u8 },
+    /// Array (`[`)
+    Array(Vec<Value>),
+    /// Hash (`{`), or hash with a default value (`}`)
+    Hash {
+        entries: Vec<(Value, Value)>,
+        default: Option<Box<Value>>,
+    },
+    /// Object (`o`)
+    Object { class: Symbol, ivars: Ivars },
+    /// Struct (`S`)
+    Struct { class: Symbol, members: Ivars },
+    /// Object written by `_dump` (`u`)
+    UserDefined {
+        class: Symbol,
+        data: Vec<u8>,
+        ivars: Ivars,
+    },
+    /// Object written by `marshal_dump` (`U`)
+    UserMarshal { class: Symbol, value: Box<Value> },
+    /// Object written by `_dump_data` (`d`)
+    Data { class: Symbol, value: Box<Value> },
+    /// Object extended with a module (`e`)
+    Extended { module: Symbol, value: Box<Value> },
+    /// Subclass of String, Array, Hash or Regexp (`C`)
+    UserClass { class: Symbol, value: Box<Value> },
+    /// Other value with instance variables (`I`)
+    WithIvars { value: Box<Value>, ivars: Ivars },
+    /// Class reference (`c`)
+    Class(Vec<u8>),
+    /// Module reference (`m`)
+    Module(Vec<u8>),
+    /// Class or module reference of old versions (`M`)
+    OldModule(Vec<u8>),
+    /// Link to an earlier object (`@`), by its index in the stream
+    Link(usize),
+    /// RGSS `Table`
+    Table(Table),
+    /// RGSS `Color`
+    Color(Color),
+    /// RGSS `Tone`
+    Tone(Tone),
+}
+
+impl Value {
+    /// Create a UTF-8 string
+    pub fn string(text: &str) -> Self {
+        Self::String(RString::new(text))
+    }
+
+    /// Create a float
+    pub fn float(value: f64) -> Self {
+        let text = if value.is_nan() {
+            "nan".to_string()
+        } else if value.is_infinite() {
+            if value > 0.0 { "inf" } else { "-inf" }.to_string()
+        } else {
+            format!("{}", value)
+        };
+        Self::Float(text.into_bytes())
+    }
+
+    /// Get an integer
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Fixnum(value) => Some(*value),
+            Self::Bignum {
+                negative,
+                magnitude,
+            } if magnitude.iter().skip(8).all(|&b| b == 0) => {
+                let mut bytes = [0u8; 8];
+                let len = magnitude.len().min(8);
+                bytes[..len].copy_from_slice(&magnitude[..len]);
+                let value = i64::try_from(u64::from_le_bytes(bytes)).ok()?;
+                Some(if *negative { -value } else { value })
+            }
+            _ => None,
+        }
+    }
+
+    /// Get a float
+    ///
+    /// Ruby 1.8 wrote extra mantissa bytes after a NUL, which are ignored.
+    pub fn as_f64(&self) -> Option<f64> {
+        let Self::Float(raw) = self else {
+            return None;
+        };
+        let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+        match std::str::from_utf8(&raw[..end]).ok()? {
+            "nan" => Some(f64::NAN),
+            "inf" => Some(f64::INFINITY),
+            "-inf" => Some(f64::NEG_INFINITY),
+            text => text.parse().ok(),
+        }
+    }
+
+    /// Get a string
+    pub fn as_string(&self) -> Option<&RString> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Get a mutable string
+    pub fn as_string_mut(&mut self) -> Option<&mut RString> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Get the text of a string
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_string()?.as_str()
+    }
+
+    /// Get the items of an array
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Get the mutable items of an array
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Get the class name of an object
+    pub fn class_name(&self) -> Option<&str> {
+        match self {
+            Self::Object { class, .. }
+            | Self::Struct { class, .. }
+            | Self::UserDefined { class, .. }
+            | Self::UserMarshal { class, .. }
+            | Self::Data { class, .. }
+            | Self::UserClass { class, .. } => class.as_str(),
+            _ => None,
+        }
+    }
+
+    /// Get an instance variable of an object (`"@name"`)
+    pub fn ivar(&self, name: &str) -> Option<&Value> {
+        match self {
+            Self::Object { ivars, .. } => ivars
+                .iter()
+                .find(|(key, _)| key.is(name))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Get a mutable instance variable of an object (`"@name"`)
+    pub fn ivar_mut(&mut self, name: &str) -> Option<&mut Value> {
+        match self {
+            Self::Object { ivars, .. } => ivars
+                .iter_mut()
+                .find(|(key, _)| key.is(name))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Check whether this is `nil`
+    pub fn is_nil(&self) -> bool {
+        matches!(self, Self::Nil)
+    }
+
+    /// Number of entries the value adds to the object table when written,
+    /// which is what [`Value::Link`] indices count
+    pub fn object_count(&self) -> usize {
+        let ivars = |ivars: &Ivars| -> usize { ivars.iter().map(|(_, v)| v.object_count()).sum() };
+        match self {
+            Self::Nil | Self::True | Self::False | Self::Symbol(_) | Self::Link(_) => 0,
+            Self::Fixnum(x) => usize::from(i32::try_from(*x).is_err()),
+            Self::Bignum { .. }
+            | Self::Float(_)
+            | Self::Regexp { .. }
+            | Self::Class(_)
+            | Self::Module(_)
+            | Self::OldModule(_)
+            | Self::Table(_)
+            | Self::Color(_)
+            | Self::Tone(_) => 1,
+            Self::String(string) => 1 + ivars(&string.ivars),
+            Self::Array(items) => 1 + items.iter().map(Self::object_count).sum::<usize>(),
+            Self::Hash { entries, default } => {
+                1 + entries
+                    .iter()
+                    .map(|(key, value)| key.object_count() + value.object_count())
+                    .sum::<usize>()
+                    + default.as_ref().map_or(0, |value| value.object_count())
+            }
+            Self::Object { ivars: fields, .. }
+            | Self::Struct {
+                members: fields, ..
+            }
+            | Self::UserDefined { ivars: fields, .. } => 1 + ivars(fields),
+            Self::UserMarshal { value, .. } | Self::Data { value, .. } => 1 + value.object_count(),
+            Self::Extended { value, .. } | Self::UserClass { value, .. } => value.object_count(),
+            Self::WithIvars {
+                value,
+                ivars: fields,
+            } => value.object_count() + ivars(fields),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accessors() {
+        let mut object = Value::Object {
+            class: Symbol::new("RPG::Actor"),
+            ivars: vec![
+                (Symbol::new("@name"), Value::string("アルシェス")),
+                (Symbol::new("@level"), Value::Fixnum(1)),
+            ],
+        };
+        assert_eq!(object.class_name(), Some("RPG::Actor"));
+        assert_eq!(object.ivar("@name").unwrap().as_str(), Some("アルシェス"));
+        assert_eq!(object.ivar("@level").unwrap().as_i64(), Some(1));
+
+        let name = object.ivar_mut("@name").unwrap().as_string_mut().unwrap();
+        name.set_str("Arshes");
+        assert_eq!(name.encoding(), StringEncoding::Utf8);
+        assert_eq!(object.ivar("@name").unwrap().as_str(), Some("Arshes"));
+
+        let big = Value::Bignum {
+            negative: true,
+            magnitude: vec![0, 0, 0, 0, 1, 0],
+        };
+        assert_eq!(big.as_i64(), Some(-(1 << 32)));
+        assert_eq!(Value::float(1.5).as_f64(), Some(1.5));
+        assert_eq!(Value::Float(b"100\x00\x12".to_vec()).as_f64(), Some(100.0));
+        assert_eq!(
+            RString::binary(b"abc".to_vec()).encoding(),
+            StringEncoding::None
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Marshal writer
+
+use std::collections::HashMap;
+
+use super::value::{Ivars, Symbol, Value};
+use super::{MAJOR_VERSION, MINOR_VERSION};
+
+/// Dump a value as Marshal data
+pub fn dump(value: &Value) -> Vec<u8> {
+    let mut writer = Writer {
+        out: vec![MAJOR_VERSION, MINOR_VERSION],
+        symbols: HashMap::new(),
+    };
+    writer.value(value);
+    writer.out
+}
+
+struct Writer {
+    out: Vec<u8>,
+    /// Index of the symbols written so far, by name
+    symbols: HashMap<Vec<u8>, usize>,
+}
+
+impl Writer {
+    /// Write a packed integer, which must fit in 32 bits
+    fn long(&mut self, x: i64) {
+        match x {
+            0 => self.out.push(0),
+            1..=122 => self.out.push((x + 5) as u8),
+            -123..=-1 => self.out.push((x - 5) as u8),
+            _ => {
+                let mut buf = [0u8; 5];
+                let mut x = x;
+                for i in 1..buf.len() {
+                    buf[i] = x as u8;
+                    x >>= 8;
+                    if x == 0 {
+                        buf[0] = i as u8;
+                        break;
+                    }
+                    if x == -1 {
+                        buf[0] = (-(i as i8)) as u8;
+                        break;
+                    }
+                }
+                let len = (buf[0] as i8).unsigned_abs() as usize;
+                self.out.extend_from_slice(&buf[..len + 1]);
+            }
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.long(bytes.len() as i64);
+        self.out.extend_from_slice(bytes);
+    }
+
+    /// Write a symbol, as a link if it was written before
+    fn symbol(&mut self, symbol: &Symbol) {
+        if let Some(&index) = self.symbols.get(&symbol.name) {
+            self.out.push(b';');
+            self.long(index as i64);
+            return;
+        }
+
+        self.symbols.insert(symbol.name.clone(), self.symbols.len());
+        if symbol.ivars.is_empty() {
+            self.out.push(b':');
+            self.bytes(&symbol.name);
+        } else {
+            self.out.extend_from_slice(b"I:");
+            self.bytes(&symbol.name);
+            self.ivars(&symbol.ivars);
+        }
+    }
+
+    fn ivars(&mut self, ivars: &Ivars) {
+        self.long(ivars.len() as i64);
+        for (name, value) in ivars {
+            self.symbol(name);
+            self.value(value);
+        }
+    }
+
+    fn user_defined(&mut self, class: &str, data: &[u8]) {
+        self.out.push(b'u');
+        self.symbol(&Symbol::new(class));
+        self.bytes(data);
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Nil => self.out.push(b'0'),
+            Value::True => self.out.push(b'T'),
+            Value::False => self.out.push(b'F'),
+            Value::Fixnum(x) if i32::try_from(*x).is_ok() => {
+                self.out.push(b'i');
+                self.long(*x);
+            }
+            Value::Fixnum(x) => {
+                // Too big for a fixnum, Ruby writes it as a bignum
+                let mut magnitude = x.unsigned_abs().to_le_bytes().to_vec();
+                while magnitude.len() > 2 && magnitude[magnitude.len() - 2..] == [0, 0] {
+                    magnitude.truncate(magnitude.len() - 2);
+                }
+                self.value(&Value::Bignum {
+                    negative: *x < 0,
+                    magnitude,
+                });
+            }
+            Value::Bignum {
+                negative,
+                magnitude,
+            } => {
+                self.out.push(b'l');
+                self.out.push(if *negative { b'-' } else { b'+' });
+                self.long(magnitude.len().div_ceil(2) as i64);
+                self.out.extend_from_slice(magnitude);
+                if magnitude.len() % 2 != 0 {
+                    self.out.push(0);
+                }
+            }
+            Value::Float(raw) => {
+                self.out.push(b'f');
+                self.bytes(raw);
+            }
+            Value::Symbol(symbol) => self.symbol(symbol),
+            Value::String(string) => {
+                if !string.ivars.is_empty() {
+                    self.out.push(b'I');
+                }
+                self.out.push(b'"');
+                self.bytes(&string.bytes);
+                if !string.ivars.is_empty() {
+                    self.ivars(&string.ivars);
+                }
+            }
+            Value::Regexp { source, options } => {
+                self.out.push(b'/');
+                self.bytes(source);
+                self.out.push(*options);
+            }
+            Value::Array(items) => {
+                self.out.push(b'[');
+                self.long(items.len() as i64);
+                for item in items {
+                    self.value(item);
+                }
+            }
+            Value::Hash { entries, default } => {
+                self.out.push(if default.is_some() { b'}' } else { b'{' });
+                self.long(entries.len() as i64);
+                for (key, value) in entries {
+                    self.value(key);
+                    self.value(value);
+                }
+                if let Some(default) = default {
+                    self.value(default);
+                }
+            }
+            Value::Object { class, ivars } => {
+                self.out.push(b'o');
+                self.symbol(class);
+                self.ivars(ivars);
+            }
+            Value::Struct { class, members } => {
+                self.out.push(b'S');
+                self.symbol(class);
+                self.ivars(members);
+            }
+            Value::UserDefined { class, data, ivars } => {
+                if !ivars.is_empty() {
+                    self.out.push(b'I');
+                }
+                self.out.push(b'u');
+                self.symbol(class);
+                self.bytes(data);
+                if !ivars.is_empty() {
+                    self.ivars(ivars);
+                }
+            }
+            Value::UserMarshal { class, value } => self.wrapped(b'U', class, value),
+            Value::Data { class, value } => self.wrapped(b'd', class, value),
+            Value::Extended { module, value } => self.wrapped(b'e', module, value),
+            Value::UserClass { class, value } => self.wrapped(b'C', class, value),
+            Value::WithIvars { value, ivars } => {
+                self.out.push(b'I');
+                self.value(value);
+                self.ivars(ivars);
+            }
+            Value::Class(name) => {
+                self.out.push(b'c');
+                self.bytes(name);
+            }
+            Value::Module(name) => {
+                self.out.push(b'm');
+                self.bytes(name);
+            }
+            Value::OldModule(name) => {
+                self.out.push(b'M');
+                self.bytes(name);
+            }
+            Value::Link(index) => {
+                self.out.push(b'@');
+                self.long(*index as i64);
+            }
+            Value::Table(table) => self.user_defined("Table", &table.dump()),
+            Value::Color(color) => self.user_defined("Color", &color.dump()),
+            Value::Tone(tone) => self.user_defined("Tone", &tone.dump()),
+        }
+    }
+
+    fn wrapped(&mut self, kind: u8, class: &Symbol, value: &Value) {
+        self.out.push(kind);
+        self.symbol(class);
+        self.value(value);
+    }
+}
@@ -1,9 +1,16 @@
 //! Parser module for extracting and injecting translations
 //! 
-//! This module provides parsers for different game engines,
-//! starting with RPG Maker MV/MZ.
+//! This module provides parsers for different game engines:
+//! RPG Maker 2000/2003, RPG Maker XP/VX/VX Ace, RPG Maker MV/MZ, Wolf RPG Editor, Ren'Py, TyranoScript and KiriKiri.
 
 pub mod types;
+pub mod kirikiri;
+pub mod marshal;
+pub mod renpy;
+pub mod rpg_maker_2000_2003;
 pub mod rpg_maker_mv_mz;
+pub mod rpg_maker_xp_vx;
+pub mod tyrano;
+pub mod wolf_rpg;
 
 pub use types::*;
@@ -0,0 +1,365 @@
+//! Logical lines and string literals of Ren'Py scripts
+//!
+//! Like Ren'Py's own lexer, a logical line continues while brackets are
+//! open or a string is unterminated, and comments are dropped. Lines are
+//! then grouped into blocks by indentation.
+
+use super::RenPyError;
+
+/// Logical line of a script with its indented block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogicalLine {
+    /// Physical line number the logical line starts on (1-based)
+    pub number: usize,
+    /// Indentation in spaces
+    pub indent: usize,
+    /// Text, without indentation and comments
+    pub text: String,
+    /// Lines indented under this one
+    pub block: Vec<LogicalLine>,
+}
+
+/// Split a script into logical lines grouped into blocks
+pub fn parse_lines(source: &str) -> Result<Vec<LogicalLine>, RenPyError> {
+    let lines = logical_lines(source)?;
+    let mut pos = 0;
+    let block = build_block(&lines, &mut pos)?;
+    if let Some(line) = lines.get(pos) {
+        return Err(inconsistent_indent(line.number));
+    }
+    Ok(block)
+}
+
+/// Collect the logical lines, without blocks
+fn logical_lines(source: &str) -> Result<Vec<LogicalLine>, RenPyError> {
+    let source = source.strip_prefix('\u{FEFF}').unwrap_or(source);
+    let chars: Vec<char> = source.replace("\r\n", "\n").chars().collect();
+
+    let mut lines = Vec::new();
+    let mut number = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = number;
+        let mut indent = 0;
+        while i < chars.len() && matches!(chars[i], ' ' | '\t') {
+            if chars[i] == '\t' {
+                return Err(RenPyError::InvalidStructure(format!(
+                    "tab in indentation on line {}",
+                    number
+                )));
+            }
+            indent += 1;
+            i += 1;
+        }
+
+        let mut text = String::new();
+        let mut depth = 0usize;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '\n' => {
+                    number += 1;
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    text.push('\n');
+                }
+                '#' => {
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                }
+                '\\' if chars.get(i + 1) == Some(&'\n') => {
+                    text.push(' ');
+                    number += 1;
+                    i += 2;
+                }
+                '"' | '\'' | '`' => {
+                    let end = string_end(&chars, i).ok_or_else(|| {
+                        RenPyError::InvalidStructure(format!(
+                            "unterminated string on line {}",
+                            number
+                        ))
+                    })?;
+                    number += chars[i..end].iter().filter(|&&c| c == '\n').count();
+                    text.extend(&chars[i..end]);
+                    i = end;
+                }
+                '(' | '[' | '{' => {
+                    depth += 1;
+                    text.push(c);
+                    i += 1;
+                }
+                ')' | ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                    text.push(c);
+                    i += 1;
+                }
+                _ => {
+                    text.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        let text = text.trim_end();
+        if !text.is_empty() {
+            lines.push(LogicalLine {
+                number: start,
+                indent,
+                text: text.to_string(),
+                block: Vec::new(),
+            });
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Find the end of the string literal starting at `start`
+fn string_end(chars: &[char], start: usize) -> Option<usize> {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let mut i = start + if triple { 3 } else { 1 };
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == quote => {
+                if !triple {
+                    return Some(i + 1);
+                }
+                if chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote) {
+                    return Some(i + 3);
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Group lines into a block and the blocks indented under them
+fn build_block(lines: &[LogicalLine], pos: &mut usize) -> Result<Vec<LogicalLine>, RenPyError> {
+    let Some(first) = lines.get(*pos) else {
+        return Ok(Vec::new());
+    };
+    let indent = first.indent;
+
+    let mut block = Vec::new();
+    while let Some(line) = lines.get(*pos) {
+        if line.indent < indent {
+            break;
+        }
+        if line.indent > indent {
+            return Err(inconsistent_indent(line.number));
+        }
+
+        *pos += 1;
+        let mut line = line.clone();
+        if lines.get(*pos).is_some_and(|next| next.indent > indent) {
+            line.block = build_block(lines, pos)?;
+        }
+        block.push(line);
+    }
+    Ok(block)
+}
+
+fn inconsistent_indent(number: usize) -> RenPyError {
+    RenPyError::InvalidStructure(format!("inconsistent indentation on line {}", number))
+}
+
+/// String literal of a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringLiteral {
+    /// Source text, with quotes
+    pub source: String,
+    /// Value after Ren'Py's processing
+    pub value: String,
+    /// Whether the string is triple-quoted
+    pub triple: bool,
+}
+
+/// Read the string literal at the start of `text`, returning it and the
+/// rest of the text
+pub fn read_string(text: &str) -> Option<(StringLiteral, &str)> {
+    let (raw, body_start) = match text.as_bytes() {
+        [b'r', b'"' | b'\'' | b'`', ..] => (true, 1),
+        [b'"' | b'\'' | b'`', ..] => (false, 0),
+        _ => return None,
+    };
+
+    let chars: Vec<char> = text[body_start..].chars().collect();
+    let end = string_end(&chars, 0)?;
+    let literal: String = chars[..end].iter().collect();
+    let source_len = body_start + literal.len();
+
+    let triple = end >= 6 && literal[1..].starts_with(&literal[..1].repeat(2));
+    let quote_len = if triple { 3 } else { 1 };
+    let body = &literal[quote_len..literal.len() - quote_len];
+
+    let value = if raw {
+        body.to_string()
+    } else {
+        dequote(&collapse_whitespace(body))
+    };
+
+    Some((
+        StringLiteral {
+            source: text[..source_len].to_string(),
+            value,
+            triple,
+        },
+        &text[source_len..],
+    ))
+}
+
+/// Collapse runs of spaces and newlines into one space
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c == ' ' || c == '\n' {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+/// Process the escapes of a string
+///
+/// Escaped text tag and interpolation brackets stay escaped, doubled, as
+/// Ren'Py leaves them for the text renderer.
+fn dequote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('{') => out.push_str("{{"),
+            Some('[') => out.push_str("[["),
+            Some('%') => out.push_str("%%"),
+            Some('n') => out.push('\n'),
+            Some('u') => {
+                let mut hex = String::new();
+                while hex.len() < 4 && chars.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+                    hex.extend(chars.next());
+                }
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(decoded) => out.push(decoded),
+                    None => {
+                        out.push('u');
+                        out.push_str(&hex);
+                    }
+                }
+            }
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Quote dialogue text the way Ren'Py writes it into translation files
+///
+/// Runs of spaces are escaped so that they survive whitespace collapsing.
+pub fn encode_say_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('"', "\\\"");
+
+    let mut out = String::with_capacity(escaped.len() + 2);
+    out.push('"');
+    let mut after_space = false;
+    for c in escaped.chars() {
+        if c == ' ' && after_space {
+            out.push('\\');
+        }
+        out.push(c);
+        after_space = c == ' ';
+    }
+    out.push('"');
+    out
+}
+
+/// Quote a string for `old` and `new` lines of string translations
+pub fn quote_string(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logical_lines() {
+        let source = "label start: # comment\n\n    e \"Hello,\n    world.\"\n    $ x = (1 +\n        2)\n    # only a comment\n    \"Bye\"\n";
+        let lines = parse_lines(source).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].text, "label start:");
+
+        let block = &lines[0].block;
+        let texts: Vec<(usize, &str)> = block.iter().map(|l| (l.number, l.text.as_str())).collect();
+        assert_eq!(
+            texts,
+            vec![
+                (3, "e \"Hello,\n    world.\""),
+                (5, "$ x = (1 +\n        2)"),
+                (8, "\"Bye\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_indentation_errors() {
+        assert!(parse_lines("label a:\n    \"x\"\n  \"y\"\n").is_err());
+        assert!(parse_lines("label a:\n\t\"x\"\n").is_err());
+        assert!(parse_lines("e \"unterminated\n").is_err());
+    }
+
+    #[test]
+    fn test_read_string() {
+        let (literal, rest) = read_string("\"Hello\\n  {b}world{/b}\\{\" with dissolve").unwrap();
+        assert_eq!(literal.value, "Hello\n {b}world{/b}{{");
+        assert_eq!(literal.source, "\"Hello\\n  {b}world{/b}\\{\"");
+        assert_eq!(rest, " with dissolve");
+
+        let (literal, _) = read_string("'It\\'s \\u00e9t\\u00E9'").unwrap();
+        assert_eq!(literal.value, "It's été");
+
+        let (literal, _) = read_string("r\"C:\\path\"").unwrap();
+        assert_eq!(literal.value, "C:\\path");
+
+        let (literal, _) = read_string("\"\"\"Long\ntext\"\"\"").unwrap();
+        assert!(literal.triple);
+        assert!(read_string("name").is_none());
+    }
+
+    #[test]
+    fn test_encode_say_string() {
+        assert_eq!(
+            encode_say_string("He said \"hi\"\nC:\\  two"),
+            "\"He said \\\"hi\\\"\\nC:\\\\ \\ two\""
+        );
+        assert_eq!(quote_string("a \"b\"  c"), "\"a \\\"b\\\"  c\"");
+    }
+}
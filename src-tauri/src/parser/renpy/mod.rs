@@ -0,0 +1,36 @@
+//! Ren'Py parser module
+//!
+//! This module provides parsing capabilities for Ren'Py games. Dialogue
+//! and menu choices are extracted from the `.rpy` script sources in
+//! `game/` (loose or packed into `.rpa` archives, see
+//! [`crate::archiver::rpa`]).
+//!
+//! Scripts are never patched. Translations are written as Ren'Py's own
+//! translation files, `game/tl/<language>/*.rpy`, which Ren'Py loads
+//! natively when the language is selected. Games shipping only compiled
+//! `.rpyc` scripts are not supported.
+
+pub mod lexer;
+pub mod script;
+pub mod translate;
+
+pub use script::{parse_script, Dialogue, MenuString, Say, Script};
+pub use translate::RenPyParser;
+
+use crate::archiver::ArchiverError;
+
+/// Error type for Ren'Py parsing
+#[derive(Debug, thiserror::Error)]
+pub enum RenPyError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Archive error: {0}")]
+    ArchiveError(#[from] ArchiverError),
+
+    #[error("Invalid structure: {0}")]
+    InvalidStructure(String),
+
+    #[error("Invalid language name: {0}")]
+    InvalidLanguage(String),
+}
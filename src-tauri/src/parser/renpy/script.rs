@@ -0,0 +1,622 @@
+//! Dialogue and menu choices of Ren'Py scripts
+//!
+//! The walk mirrors how Ren'Py assigns translation identifiers: each say
+//! statement, together with the `voice` and `nvl clear` statements right
+//! before it, forms a translatable block named after the last label and
+//! the MD5 of the block's code. Python, screen, style, transform and
+//! `translate` blocks are skipped.
+
+use std::collections::HashSet;
+
+use md5::{Digest, Md5};
+
+use super::lexer::{encode_say_string, parse_lines, read_string, LogicalLine};
+use super::RenPyError;
+
+/// Statements that start with a keyword and are never dialogue
+const NON_SAY_KEYWORDS: &[&str] = &[
+    "call",
+    "camera",
+    "default",
+    "define",
+    "early",
+    "hide",
+    "image",
+    "init",
+    "jump",
+    "layeredimage",
+    "nvl",
+    "pass",
+    "pause",
+    "play",
+    "python",
+    "queue",
+    "return",
+    "scene",
+    "screen",
+    "show",
+    "stop",
+    "style",
+    "testcase",
+    "transform",
+    "translate",
+    "voice",
+    "window",
+    "with",
+];
+
+/// Parsed say statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Say {
+    /// Speaker expression, as written
+    pub who: Option<String>,
+    /// Image attributes
+    pub attributes: Vec<String>,
+    /// Temporary image attributes (after `@`)
+    pub temporary_attributes: Vec<String>,
+    /// Dialogue text
+    pub what: String,
+    /// Whether the statement waits for the player (no `nointeract`)
+    pub interact: bool,
+    /// Transition of the `with` clause
+    pub with: Option<String>,
+    /// Arguments to the character, normalized
+    pub arguments: Option<String>,
+    /// Explicit translation identifier (`id` clause)
+    pub identifier: Option<String>,
+}
+
+impl Say {
+    /// Get the statement's code with the given dialogue text
+    ///
+    /// This is the form Ren'Py hashes and writes into translation files.
+    pub fn code(&self, what: &str) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        parts.extend(self.who.clone());
+        parts.extend(self.attributes.iter().cloned());
+        if !self.temporary_attributes.is_empty() {
+            parts.push("@".to_string());
+            parts.extend(self.temporary_attributes.iter().cloned());
+        }
+        parts.push(encode_say_string(what));
+        if !self.interact {
+            parts.push("nointeract".to_string());
+        }
+        if let Some(identifier) = &self.identifier {
+            parts.push("id".to_string());
+            parts.push(identifier.clone());
+        }
+        if let Some(with) = &self.with {
+            parts.push("with".to_string());
+            parts.push(with.clone());
+        }
+        parts.extend(self.arguments.clone());
+        parts.join(" ")
+    }
+}
+
+/// Dialogue line, with its translation identifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dialogue {
+    /// Line number of the first statement of the block
+    pub line: usize,
+    /// Translation identifier
+    pub identifier: String,
+    /// Label the dialogue is under
+    pub label: Option<String>,
+    /// Code of the translatable statements before the say statement
+    pub prefix: Vec<String>,
+    /// Say statement
+    pub say: Say,
+}
+
+/// Menu choice text, translated through `translate <language> strings`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuString {
+    /// Line number of the choice
+    pub line: usize,
+    /// Label the menu is under
+    pub label: Option<String>,
+    /// Choice text
+    pub text: String,
+}
+
+/// Translatable content of a script
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    /// Dialogue, in script order
+    pub dialogue: Vec<Dialogue>,
+    /// Menu choices, in script order, without duplicates
+    pub strings: Vec<MenuString>,
+    /// Statements that could not be handled
+    pub warnings: Vec<String>,
+}
+
+/// Parse the dialogue and menu choices of a script
+pub fn parse_script(source: &str) -> Result<Script, RenPyError> {
+    let lines = parse_lines(source)?;
+    let mut walker = Walker::default();
+    walker.block(&lines);
+    Ok(walker.script)
+}
+
+#[derive(Default)]
+struct Walker {
+    script: Script,
+    /// Last global label
+    global_label: Option<String>,
+    /// Label that names translation identifiers
+    label: Option<String>,
+    identifiers: HashSet<String>,
+    choices: HashSet<String>,
+}
+
+impl Walker {
+    fn block(&mut self, block: &[LogicalLine]) {
+        // Translatable statements waiting for a say statement
+        let mut group: Vec<&LogicalLine> = Vec::new();
+
+        for line in block {
+            let text = line.text.as_str();
+            match first_word(text) {
+                "label" => {
+                    group.clear();
+                    self.label_statement(text.trim_start_matches("label"));
+                    self.block(&line.block);
+                }
+                "menu" => {
+                    group.clear();
+                    self.menu(line);
+                }
+                "if" | "elif" | "else" | "while" | "for" => {
+                    group.clear();
+                    self.block(&line.block);
+                }
+                "voice" => group.push(line),
+                "nvl" if text.split_whitespace().eq(["nvl", "clear"]) => group.push(line),
+                word if NON_SAY_KEYWORDS.contains(&word) || text.starts_with('$') => {
+                    group.clear();
+                }
+                _ => {
+                    match parse_say(text) {
+                        Ok(Some(say)) => self.dialogue(&group, line, say),
+                        Ok(None) => {}
+                        Err(reason) => self
+                            .script
+                            .warnings
+                            .push(format!("line {}: {}", line.number, reason)),
+                    }
+                    group.clear();
+                }
+            }
+        }
+    }
+
+    /// Handle `label name[(parameters)]:`
+    fn label_statement(&mut self, rest: &str) {
+        let name: String = rest
+            .trim_start()
+            .chars()
+            .take_while(|&c| is_name_char(c) || c == '.')
+            .collect();
+        if name.is_empty() {
+            return;
+        }
+
+        let full = match name.strip_prefix('.') {
+            Some(local) => match &self.global_label {
+                Some(global) => format!("{}.{}", global, local),
+                None => local.to_string(),
+            },
+            None => {
+                let global = name.split('.').next().unwrap_or(&name);
+                self.global_label = Some(global.to_string());
+                name.clone()
+            }
+        };
+
+        // Labels starting with `_` are alternates that keep the identifier
+        if !full.starts_with('_') {
+            self.label = Some(full);
+        }
+    }
+
+    /// Handle a menu: its caption, choices and their blocks
+    fn menu(&mut self, line: &LogicalLine) {
+        let rest = line.text["menu".len()..].trim_start();
+        if rest.starts_with(|c: char| is_name_char(c)) {
+            self.label_statement(rest);
+        }
+
+        for item in &line.block {
+            if let Some((literal, after)) = read_string(&item.text) {
+                // Choice, optionally with arguments or a condition
+                if after.trim_end().ends_with(':') {
+                    if self.choices.insert(literal.value.clone()) {
+                        self.script.strings.push(MenuString {
+                            line: item.number,
+                            label: self.label.clone(),
+                            text: literal.value,
+                        });
+                    }
+                    self.block(&item.block);
+                    continue;
+                }
+            }
+
+            // Caption, shown while the choices are on screen
+            match parse_say(&item.text) {
+                Ok(Some(mut say)) => {
+                    say.interact = false;
+                    self.dialogue(&[], item, say);
+                }
+                Ok(None) => {}
+                Err(reason) => self
+                    .script
+                    .warnings
+                    .push(format!("line {}: {}", item.number, reason)),
+            }
+        }
+    }
+
+    fn dialogue(&mut self, group: &[&LogicalLine], line: &LogicalLine, say: Say) {
+        let prefix: Vec<String> = group.iter().map(|l| l.text.clone()).collect();
+
+        let identifier = match &say.identifier {
+            Some(identifier) => identifier.clone(),
+            None => {
+                let mut md5 = Md5::new();
+                for code in prefix.iter().cloned().chain([say.code(&say.what)]) {
+                    md5.update(code.as_bytes());
+                    md5.update(b"\r\n");
+                }
+                let digest = format!("{:x}", md5.finalize());
+                self.unique_identifier(&digest[..8])
+            }
+        };
+        self.identifiers.insert(identifier.clone());
+
+        self.script.dialogue.push(Dialogue {
+            line: group.first().map_or(line.number, |l| l.number),
+            identifier,
+            label: self.label.clone(),
+            prefix,
+            say,
+        });
+    }
+
+    fn unique_identifier(&self, digest: &str) -> String {
+        let base = match &self.label {
+            Some(label) => format!("{}_{}", label.replace('.', "_"), digest),
+            None => digest.to_string(),
+        };
+
+        let mut identifier = base.clone();
+        let mut i = 0;
+        while self.identifiers.contains(&identifier) {
+            i += 1;
+            identifier = format!("{}_{}", base, i);
+        }
+        identifier
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn first_word(text: &str) -> &str {
+    let end = text.find(|c: char| !is_name_char(c)).unwrap_or(text.len());
+    &text[..end]
+}
+
+/// Split off a dotted name at the start of `text`
+fn take_name(text: &str) -> Option<(&str, &str)> {
+    if !text.starts_with(|c: char| is_name_char(c) && !c.is_ascii_digit()) {
+        return None;
+    }
+    let end = text
+        .find(|c: char| !is_name_char(c) && c != '.')
+        .unwrap_or(text.len());
+    Some((&text[..end], &text[end..]))
+}
+
+/// Split off a bracketed expression at the start of `text`
+fn take_balanced(text: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if matches!(c, '"' | '\'' | '`') {
+            let (_, after) = read_string(rest)?;
+            rest = after;
+            continue;
+        }
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+        rest = &rest[c.len_utf8()..];
+        if depth == 0 {
+            let end = text.len() - rest.len();
+            return Some((&text[..end], rest));
+        }
+    }
+    None
+}
+
+/// Normalize call arguments the way Ren'Py re-renders them
+fn normalize_arguments(arguments: &str) -> String {
+    let inner = &arguments[1..arguments.len() - 1];
+    let mut parts = Vec::new();
+    let mut rest = inner;
+    let mut current = String::new();
+
+    while let Some(c) = rest.chars().next() {
+        if matches!(c, '"' | '\'' | '`' | '(' | '[' | '{') {
+            let taken = if matches!(c, '(' | '[' | '{') {
+                take_balanced(rest)
+            } else {
+                read_string(rest).map(|(_, after)| (&rest[..rest.len() - after.len()], after))
+            };
+            if let Some((token, after)) = taken {
+                current.push_str(token);
+                rest = after;
+                continue;
+            }
+        }
+        if c == ',' {
+            parts.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    parts.push(current);
+
+    let parts: Vec<String> = parts
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(|p| match p.split_once('=') {
+            Some((key, value))
+                if take_name(key.trim()).is_some_and(|(_, r)| r.is_empty())
+                    && !value.starts_with('=') =>
+            {
+                format!("{}={}", key.trim(), value.trim())
+            }
+            _ => p.to_string(),
+        })
+        .collect();
+    format!("({})", parts.join(", "))
+}
+
+/// Parse a say statement, `None` if the line is not one
+///
+/// Fails for dialogue that cannot be translated through identifiers, like
+/// triple-quoted (multi-statement) dialogue.
+pub fn parse_say(text: &str) -> Result<Option<Say>, String> {
+    let mut say = Say {
+        who: None,
+        attributes: Vec::new(),
+        temporary_attributes: Vec::new(),
+        what: String::new(),
+        interact: true,
+        with: None,
+        arguments: None,
+        identifier: None,
+    };
+
+    let what;
+    let mut rest;
+    if let Some((first, after)) = read_string(text) {
+        match read_string(after.trim_start()) {
+            Some((second, after)) => {
+                say.who = Some(first.source);
+                what = second;
+                rest = after;
+            }
+            None => {
+                what = first;
+                rest = after;
+            }
+        }
+    } else {
+        let Some((who, after)) = take_name(text) else {
+            return Ok(None);
+        };
+        say.who = Some(who.to_string());
+        rest = after.trim_start();
+
+        let mut temporary = false;
+        loop {
+            if let Some(after) = rest.strip_prefix('@') {
+                temporary = true;
+                rest = after.trim_start();
+                continue;
+            }
+            let negated = rest.starts_with('-');
+            let Some((name, after)) = take_name(&rest[negated as usize..]) else {
+                break;
+            };
+            let attribute = format!("{}{}", if negated { "-" } else { "" }, name);
+            if temporary {
+                say.temporary_attributes.push(attribute);
+            } else {
+                say.attributes.push(attribute);
+            }
+            rest = after.trim_start();
+        }
+
+        let Some((literal, after)) = read_string(rest) else {
+            return Ok(None);
+        };
+        what = literal;
+        rest = after;
+    }
+
+    if what.triple {
+        return Err("triple-quoted dialogue is not supported".to_string());
+    }
+    say.what = what.value;
+
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if rest.starts_with('(') {
+            let Some((arguments, after)) = take_balanced(rest) else {
+                return Ok(None);
+            };
+            say.arguments = Some(normalize_arguments(arguments));
+            rest = after;
+            continue;
+        }
+
+        let Some((keyword, after)) = take_name(rest) else {
+            return Ok(None);
+        };
+        let after = after.trim_start();
+        match keyword {
+            "nointeract" => {
+                say.interact = false;
+                rest = after;
+            }
+            "id" => {
+                let Some((identifier, after)) = take_name(after) else {
+                    return Ok(None);
+                };
+                say.identifier = Some(identifier.to_string());
+                rest = after;
+            }
+            "with" => {
+                let Some((name, mut after)) = take_name(after) else {
+                    return Ok(None);
+                };
+                let mut with = name.to_string();
+                if after.starts_with('(') {
+                    let Some((call, call_after)) = take_balanced(after) else {
+                        return Ok(None);
+                    };
+                    with.push_str(call);
+                    after = call_after;
+                }
+                say.with = Some(with);
+                rest = after;
+            }
+            _ => return Ok(None),
+        }
+    }
+
+    Ok(Some(say))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn say(text: &str) -> Say {
+        parse_say(text).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_parse_say() {
+        let s = say("\"Hello there.\"");
+        assert_eq!((s.who, s.what.as_str()), (None, "Hello there."));
+
+        let s = say("\"Eileen\" \"Hi!\"");
+        assert_eq!(s.who.as_deref(), Some("\"Eileen\""));
+
+        let s = say("e happy -sad @ surprised \"Wow!\" nointeract with Dissolve(0.5) (color = \"#fff\",  size=2) id intro_1");
+        assert_eq!(s.who.as_deref(), Some("e"));
+        assert_eq!(s.attributes, vec!["happy", "-sad"]);
+        assert_eq!(s.temporary_attributes, vec!["surprised"]);
+        assert_eq!(
+            s.code("Wow!"),
+            "e happy -sad @ surprised \"Wow!\" nointeract id intro_1 with Dissolve(0.5) (color=\"#fff\", size=2)"
+        );
+
+        assert!(parse_say("$ renpy.pause()").unwrap().is_none());
+        assert!(parse_say("e.name = \"x\"").unwrap().is_none());
+        assert!(parse_say("e \"\"\"Long\n\ntext\"\"\"").is_err());
+    }
+
+    #[test]
+    fn test_identifiers() {
+        // Ren'Py's tutorial: `e "Hello, world."` under `label start`
+        let source = "\
+label start:
+    e \"Hello, world.\"
+    e \"Hello, world.\"
+    voice \"e01.ogg\"
+    e \"Hi.\"
+label .local:
+    \"Hello, world.\"
+label _hidden:
+    \"Narration.\"
+";
+        let script = parse_script(source).unwrap();
+        let ids: Vec<&str> = script
+            .dialogue
+            .iter()
+            .map(|d| d.identifier.as_str())
+            .collect();
+
+        let md5 = |code: &str| format!("{:x}", Md5::digest(code.as_bytes()))[..8].to_string();
+        assert_eq!(md5("e \"Hello, world.\"\r\n"), "558d6c23");
+        assert_eq!(
+            ids,
+            vec![
+                "start_558d6c23".to_string(),
+                "start_558d6c23_1".to_string(),
+                format!("start_{}", md5("voice \"e01.ogg\"\r\ne \"Hi.\"\r\n")),
+                format!("start_local_{}", md5("\"Hello, world.\"\r\n")),
+                format!("start_local_{}", md5("\"Narration.\"\r\n")),
+            ]
+        );
+        assert_eq!(script.dialogue[2].prefix, vec!["voice \"e01.ogg\""]);
+        assert_eq!(script.dialogue[2].line, 4);
+    }
+
+    #[test]
+    fn test_menu_and_skipped_blocks() {
+        let source = "\
+init python:
+    x = \"not dialogue\"
+define e = Character(\"Eileen\")
+screen hello():
+    text \"Screen text\"
+label start:
+    menu choose:
+        e \"What now?\"
+        \"Go left\":
+            \"You went left.\"
+        \"Go right\" if x:
+            jump right
+        \"Go left\":
+            pass
+    if x:
+        \"Conditional.\"
+    show eileen happy:
+        xalign 0.5
+";
+        let script = parse_script(source).unwrap();
+        let dialogue: Vec<(Option<&str>, String)> = script
+            .dialogue
+            .iter()
+            .map(|d| (d.label.as_deref(), d.say.code(&d.say.what)))
+            .collect();
+        assert_eq!(
+            dialogue,
+            vec![
+                (Some("choose"), "e \"What now?\" nointeract".to_string()),
+                (Some("choose"), "\"You went left.\"".to_string()),
+                (Some("choose"), "\"Conditional.\"".to_string()),
+            ]
+        );
+
+        let choices: Vec<&str> = script.strings.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(choices, vec!["Go left", "Go right"]);
+    }
+}
@@ -0,0 +1,586 @@
+//! Extraction from Ren'Py scripts and generation of `tl` translation files
+//!
+//! Dialogue units are identified by Ren'Py's translation identifier
+//! (`dialogue.<identifier>`), and menu choices by the MD5 of their text
+//! (`strings.<digest>`). Injection never touches the script: it writes
+//! `game/tl/<language>/<script>.rpy`, holding a `translate <language>
+//! <identifier>:` block per translated line and a `translate <language>
+//! strings:` block for the choices.
+//!
+//! Ren'Py refuses to load a language with two translations of the same
+//! string, so the choices written are tracked across all the scripts of an
+//! injection pass and each one is only written to the first file using it.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use md5::{Digest, Md5};
+
+use super::lexer::quote_string;
+use super::script::{parse_script, Dialogue, Script};
+use super::RenPyError;
+use crate::archiver::vfs::GameVfs;
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile,
+    TranslationPath, TranslationUnit,
+};
+
+/// Get the path of a dialogue line
+fn dialogue_path(identifier: &str) -> TranslationPath {
+    TranslationPath::new()
+        .append_key("dialogue")
+        .append_key(identifier)
+}
+
+/// Get the path of a menu choice
+fn string_path(text: &str) -> TranslationPath {
+    let digest = format!("{:x}", Md5::digest(text.as_bytes()));
+    TranslationPath::new()
+        .append_key("strings")
+        .append_key(&digest[..8])
+}
+
+/// Check that a language name can be used in `translate` statements
+fn check_language(language: &str) -> Result<(), RenPyError> {
+    let valid = language
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && language != "None";
+    if valid {
+        Ok(())
+    } else {
+        Err(RenPyError::InvalidLanguage(language.to_string()))
+    }
+}
+
+/// Speaker shown to translators: the character or the quoted name
+fn speaker(dialogue: &Dialogue) -> Option<String> {
+    dialogue.say.who.as_ref().map(|who| {
+        who.trim_matches(|c| matches!(c, '"' | '\'' | '`'))
+            .to_string()
+    })
+}
+
+/// Parser for `.rpy` scripts
+pub struct RenPyParser;
+
+impl RenPyParser {
+    /// Create a new Ren'Py parser
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Extract translations from script source
+    pub fn extract(
+        &self,
+        source: &str,
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, RenPyError> {
+        let mut result = FileExtractionResult::new(file_name);
+        let script = parse_script(source)?;
+        result.warnings.extend(script.warnings);
+
+        let mut context =
+            ExtractionContext::new(file_name).with_max_preceding_lines(options.max_preceding_lines);
+        let mut units = Vec::new();
+
+        for dialogue in &script.dialogue {
+            let text = &dialogue.say.what;
+            if text.trim().is_empty() && !options.include_empty {
+                continue;
+            }
+
+            let mut translation_context = context.to_translation_context();
+            if let Some(label) = &dialogue.label {
+                translation_context = translation_context.with_event_name(label);
+            }
+            let path = dialogue_path(&dialogue.identifier);
+            units.push(
+                TranslationUnit::new(
+                    path.to_unit_id(""),
+                    path,
+                    EventCode::ShowTextBody,
+                    text.clone(),
+                )
+                .with_speaker(speaker(dialogue))
+                .with_context(translation_context),
+            );
+            context.add_preceding_line(text.clone());
+        }
+
+        for string in &script.strings {
+            let mut translation_context = context.to_translation_context();
+            if let Some(label) = &string.label {
+                translation_context = translation_context.with_event_name(label);
+            }
+            translation_context.add_tag("choice");
+            let path = string_path(&string.text);
+            units.push(
+                TranslationUnit::new(
+                    path.to_unit_id(""),
+                    path,
+                    EventCode::ShowChoices,
+                    string.text.clone(),
+                )
+                .with_context(translation_context),
+            );
+        }
+
+        result.add_units(units);
+        Ok(result)
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, RenPyError> {
+        let source = fs::read_to_string(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("script.rpy");
+
+        self.extract(&source, file_name, options)
+    }
+
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside a mounted `.rpa` archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, RenPyError> {
+        let source = vfs.read_to_string(path)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("script.rpy");
+
+        self.extract(&source, file_name, options)
+    }
+
+    /// Generate the translation file of a script
+    ///
+    /// `script_path` is the script's path from the game folder
+    /// (`game/script.rpy`), written in the comments Ren'Py uses to locate
+    /// the original lines. Lines without a translation are left out, so
+    /// Ren'Py shows the original text for them.
+    ///
+    /// `emitted` holds the choices already written for `language` by the
+    /// other scripts of the same pass; they are skipped here, and the ones
+    /// written are added to it. Use one set for all the scripts of a game.
+    pub fn inject(
+        &self,
+        source: &str,
+        script_path: &str,
+        language: &str,
+        translations: &HashMap<String, String>,
+        emitted: &mut HashSet<String>,
+        options: &InjectionOptions,
+    ) -> Result<(String, FileInjectionResult), RenPyError> {
+        check_language(language)?;
+        let script = parse_script(source)?;
+
+        let mut writer = TlWriter {
+            script_path,
+            language,
+            translations,
+            emitted,
+            options,
+            output: String::new(),
+            result: FileInjectionResult::new(),
+        };
+        writer
+            .result
+            .warnings
+            .extend(script.warnings.iter().cloned());
+        writer.dialogue(&script);
+        writer.strings(&script);
+
+        let mut result = writer.result;
+        result.modified = result.applied > 0;
+        Ok((writer.output, result))
+    }
+
+    /// Write the translation file of a script in a game folder
+    ///
+    /// `script_path` is relative to the game folder (`game/script.rpy`); the
+    /// script is read through `vfs`, so it may be inside an archive. The
+    /// output goes to `game/tl/<language>/script.rpy`, replacing any
+    /// earlier file, and is only written if something was translated.
+    /// `emitted` is shared by the scripts of a pass, as in [`Self::inject`].
+    pub fn inject_vfs(
+        &self,
+        vfs: &GameVfs,
+        script_path: &str,
+        language: &str,
+        translations: &HashMap<String, String>,
+        emitted: &mut HashSet<String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, RenPyError> {
+        let source = vfs.read_to_string(script_path)?;
+        let output_path = tl_path(vfs.root(), script_path, language)?;
+
+        let (output, result) = self.inject(
+            &source,
+            script_path,
+            language,
+            translations,
+            emitted,
+            options,
+        )?;
+
+        if result.modified {
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&output_path, output)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+/// Writer of a translation file
+struct TlWriter<'a> {
+    script_path: &'a str,
+    language: &'a str,
+    translations: &'a HashMap<String, String>,
+    emitted: &'a mut HashSet<String>,
+    options: &'a InjectionOptions,
+    output: String,
+    result: FileInjectionResult,
+}
+
+impl<'a> TlWriter<'a> {
+    /// Get the translation of a unit, counting missing ones
+    fn translation(&mut self, path: &TranslationPath) -> Option<&'a String> {
+        let translated = self.translations.get(&path.to_unit_id(""));
+        if translated.is_none() && !self.options.skip_missing_translations {
+            self.result.not_found += 1;
+        }
+        translated
+    }
+
+    /// Write a `translate` block per translated dialogue line
+    fn dialogue(&mut self, script: &Script) {
+        for dialogue in &script.dialogue {
+            let Some(translated) = self.translation(&dialogue_path(&dialogue.identifier)) else {
+                continue;
+            };
+            let text = if self.options.max_line_length.is_some() {
+                self.options.split_text(translated).join("\n")
+            } else {
+                translated.clone()
+            };
+            self.write_dialogue(dialogue, &text);
+        }
+    }
+
+    fn write_dialogue(&mut self, dialogue: &Dialogue, text: &str) {
+        let out = &mut self.output;
+        let _ = writeln!(out, "# {}:{}", self.script_path, dialogue.line);
+        let _ = writeln!(out, "translate {} {}:", self.language, dialogue.identifier);
+        out.push('\n');
+        for code in &dialogue.prefix {
+            let _ = writeln!(out, "    # {}", code);
+        }
+        let _ = writeln!(out, "    # {}", dialogue.say.code(&dialogue.say.what));
+        for code in &dialogue.prefix {
+            let _ = writeln!(out, "    {}", code);
+        }
+        let _ = writeln!(out, "    {}", dialogue.say.code(text));
+        out.push('\n');
+
+        self.result.applied += 1;
+        self.result.commands_modified += 1;
+    }
+
+    /// Write the `translate <language> strings` block of the menu choices
+    fn strings(&mut self, script: &Script) {
+        let mut block = String::new();
+        for string in &script.strings {
+            let Some(translated) = self.translation(&string_path(&string.text)) else {
+                continue;
+            };
+            self.result.applied += 1;
+            // Already translated by an earlier script of the pass
+            if !self.emitted.insert(string.text.clone()) {
+                continue;
+            }
+
+            let _ = writeln!(block, "    # {}:{}", self.script_path, string.line);
+            let _ = writeln!(block, "    old {}", quote_string(&string.text));
+            let _ = writeln!(block, "    new {}", quote_string(translated));
+            block.push('\n');
+        }
+
+        if !block.is_empty() {
+            let _ = writeln!(self.output, "translate {} strings:", self.language);
+            self.output.push('\n');
+            self.output.push_str(&block);
+            self.result.commands_modified += 1;
+        }
+    }
+}
+
+impl Default for RenPyParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the translation file path of a script (`game/x.rpy`)
+pub fn tl_path(game_dir: &Path, script_path: &str, language: &str) -> Result<PathBuf, RenPyError> {
+    check_language(language)?;
+    let relative = script_path
+        .strip_prefix("game/")
+        .map(Path::new)
+        .filter(|p| p.components().all(|c| matches!(c, Component::Normal(_))))
+        .ok_or_else(|| {
+            RenPyError::InvalidStructure(format!("{} is not a script in game/", script_path))
+        })?;
+
+    Ok(game_dir
+        .join("game")
+        .join("tl")
+        .join(language)
+        .join(relative)
+        .with_extension("rpy"))
+}
+
+/// Check whether a file is a Ren'Py script source
+pub fn is_script_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("rpy"))
+}
+
+/// Get the script sources in a directory of a game's virtual filesystem
+///
+/// Includes scripts stored in mounted archives.
+pub fn find_script_files_vfs(vfs: &GameVfs, dir: &str) -> Vec<String> {
+    vfs.list_dir(dir)
+        .into_iter()
+        .filter(|path| is_script_file(Path::new(path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "\
+define e = Character(\"Eileen\")
+
+label start:
+    scene bg room
+    e \"You've created a new Ren'Py game.\"
+    voice \"e02.ogg\"
+    e happy \"Once you add a story,  pictures, and music, you can release it!\"
+    menu:
+        \"Continue\":
+            \"Let's go.\"
+        \"Quit\":
+            return
+";
+
+    #[test]
+    fn test_extract() {
+        let result = RenPyParser::new()
+            .extract(SCRIPT, "script.rpy", &ExtractionOptions::default())
+            .unwrap();
+
+        let texts: Vec<&str> = result.units.iter().map(|u| u.original.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "You've created a new Ren'Py game.",
+                "Once you add a story, pictures, and music, you can release it!",
+                "Let's go.",
+                "Continue",
+                "Quit",
+            ]
+        );
+        assert!(result.units[0].id.starts_with("dialogue.start_"));
+        assert!(result.units[3].id.starts_with("strings."));
+        assert_eq!(result.units[0].speaker.as_deref(), Some("e"));
+        assert_eq!(result.units[1].context.event_name.as_deref(), Some("start"));
+        assert_eq!(result.speakers, vec!["e"]);
+    }
+
+    #[test]
+    fn test_inject_generates_tl_file() {
+        let parser = RenPyParser::new();
+        let units = parser
+            .extract(SCRIPT, "script.rpy", &ExtractionOptions::default())
+            .unwrap()
+            .units;
+
+        let mut translations = HashMap::new();
+        translations.insert(
+            units[1].id.clone(),
+            "이야기와  \"그림\"을 추가하세요!".to_string(),
+        );
+        translations.insert(units[3].id.clone(), "계속".to_string());
+
+        let (output, result) = parser
+            .inject(
+                SCRIPT,
+                "game/script.rpy",
+                "korean",
+                &translations,
+                &mut HashSet::new(),
+                &InjectionOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(result.applied, 2);
+        assert!(result.modified);
+
+        let identifier = units[1].id.trim_start_matches("dialogue.");
+        let expected = format!(
+            "\
+# game/script.rpy:6
+translate korean {}:
+
+    # voice \"e02.ogg\"
+    # e happy \"Once you add a story, pictures, and music, you can release it!\"
+    voice \"e02.ogg\"
+    e happy \"이야기와 \\ \\\"그림\\\"을 추가하세요!\"
+
+translate korean strings:
+
+    # game/script.rpy:9
+    old \"Continue\"
+    new \"계속\"
+
+",
+            identifier
+        );
+        assert_eq!(output, expected);
+
+        assert!(matches!(
+            parser.inject(
+                SCRIPT,
+                "game/script.rpy",
+                "ko-KR",
+                &translations,
+                &mut HashSet::new(),
+                &InjectionOptions::default()
+            ),
+            Err(RenPyError::InvalidLanguage(_))
+        ));
+    }
+
+    #[test]
+    fn test_inject_vfs() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("game/chapters")).unwrap();
+        fs::write(root.join("game/chapters/one.rpy"), SCRIPT).unwrap();
+
+        let vfs = GameVfs::new(root);
+        assert_eq!(
+            find_script_files_vfs(&vfs, "game/chapters"),
+            vec!["game/chapters/one.rpy"]
+        );
+
+        let parser = RenPyParser::new();
+        let units = parser
+            .extract_vfs(&vfs, "game/chapters/one.rpy", &ExtractionOptions::default())
+            .unwrap()
+            .units;
+        let mut translations = HashMap::new();
+        translations.insert(units[0].id.clone(), "새 게임을 만들었습니다.".to_string());
+
+        let result = parser
+            .inject_vfs(
+                &vfs,
+                "game/chapters/one.rpy",
+                "korean",
+                &translations,
+                &mut HashSet::new(),
+                &InjectionOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(result.applied, 1);
+
+        let tl = fs::read_to_string(root.join("game/tl/korean/chapters/one.rpy")).unwrap();
+        assert!(tl.contains("    e \"새 게임을 만들었습니다.\"\n"));
+        // The script itself is untouched
+        assert_eq!(
+            fs::read_to_string(root.join("game/chapters/one.rpy")).unwrap(),
+            SCRIPT
+        );
+
+        assert!(tl_path(root, "game/../x.rpy", "korean").is_err());
+    }
+
+    #[test]
+    fn test_inject_vfs_shared_choice() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("game")).unwrap();
+        fs::write(root.join("game/one.rpy"), SCRIPT).unwrap();
+        fs::write(
+            root.join("game/two.rpy"),
+            "label other:\n    menu:\n        \"Continue\":\n            return\n",
+        )
+        .unwrap();
+
+        let vfs = GameVfs::new(root);
+        let parser = RenPyParser::new();
+        let mut translations = HashMap::new();
+        for script in ["game/one.rpy", "game/two.rpy"] {
+            for unit in parser
+                .extract_vfs(&vfs, script, &ExtractionOptions::default())
+                .unwrap()
+                .units
+            {
+                if unit.original == "Continue" {
+                    translations.insert(unit.id, "계속".to_string());
+                }
+            }
+        }
+
+        let mut emitted = HashSet::new();
+        for script in ["game/one.rpy", "game/two.rpy"] {
+            let result = parser
+                .inject_vfs(
+                    &vfs,
+                    script,
+                    "korean",
+                    &translations,
+                    &mut emitted,
+                    &InjectionOptions::default(),
+                )
+                .unwrap();
+            assert_eq!(result.applied, 1);
+        }
+
+        // Ren'Py rejects a string translated twice in one language
+        let one = fs::read_to_string(root.join("game/tl/korean/one.rpy")).unwrap();
+        let two = fs::read_to_string(root.join("game/tl/korean/two.rpy")).unwrap();
+        assert_eq!(one.matches("old \"Continue\"").count(), 1);
+        assert!(!two.contains("old \"Continue\""));
+        assert!(!two.contains("translate korean strings:"));
+    }
+}
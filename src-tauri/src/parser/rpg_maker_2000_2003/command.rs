@@ -0,0 +1,583 @@
+//! Event commands of RPG Maker 2000/2003 map events and common events
+//!
+//! A command list is the raw data of a chunk, next to a chunk holding its
+//! size in bytes:
+//!
+//! ```text
+//! ber      command code
+//! ber      indent
+//! string   string argument
+//! ber      parameter count
+//! ber      parameters
+//! ```
+//!
+//! The list ends with four zero bytes, read as a command of code 0.
+//! Messages and comments continue over commands of their own code, one
+//! per line.
+
+use std::collections::HashMap;
+
+use super::lcf::{self, ber_bytes, Chunks, LcfEncoding, LcfReader};
+use super::LcfError;
+use crate::parser::rpg_maker_mv_mz::FileInjectionResult;
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, InjectionOptions, TranslationPath,
+    TranslationUnit,
+};
+
+/// Show a message, first line
+pub const CODE_MESSAGE: u32 = 10110;
+/// Show a message, following lines
+pub const CODE_MESSAGE_LINE: u32 = 20110;
+/// Show choices, separated by `/`
+pub const CODE_CHOICES: u32 = 10140;
+/// Branch of a choice, holding its text
+pub const CODE_CHOICE_BRANCH: u32 = 20140;
+/// End of the choice branches
+pub const CODE_CHOICES_END: u32 = 20141;
+/// Change a hero's name
+pub const CODE_CHANGE_HERO_NAME: u32 = 10610;
+/// Change a hero's title
+pub const CODE_CHANGE_HERO_TITLE: u32 = 10620;
+/// Comment, first line
+pub const CODE_COMMENT: u32 = 12410;
+/// Comment, following lines
+pub const CODE_COMMENT_LINE: u32 = 22410;
+
+/// Separator of the choices of a choices command
+const CHOICE_SEPARATOR: char = '/';
+
+/// Single event command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventCommand {
+    /// Command code
+    pub code: u32,
+    /// Indent level
+    pub indent: u32,
+    /// Raw string argument
+    pub string: Vec<u8>,
+    /// Integer parameters
+    pub parameters: Vec<i32>,
+}
+
+impl EventCommand {
+    /// Read a command
+    pub fn read(reader: &mut LcfReader) -> Result<Self, LcfError> {
+        let code = reader.ber()?;
+        let indent = reader.ber()?;
+        let string = reader.string()?.to_vec();
+        let count = reader.count()?;
+        let parameters = (0..count).map(|_| reader.int()).collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            code,
+            indent,
+            string,
+            parameters,
+        })
+    }
+
+    /// Write the command
+    pub fn write(&self, out: &mut Vec<u8>) {
+        lcf::write_ber(out, self.code);
+        lcf::write_ber(out, self.indent);
+        lcf::write_string(out, &self.string);
+        lcf::write_ber(out, self.parameters.len() as u32);
+        for &parameter in &self.parameters {
+            lcf::write_int(out, parameter);
+        }
+    }
+}
+
+/// Read the command list filling `data`
+pub fn read_commands(data: &[u8]) -> Result<Vec<EventCommand>, LcfError> {
+    let mut reader = LcfReader::new(data);
+    let mut commands = Vec::new();
+    while !reader.is_at_end() {
+        commands.push(EventCommand::read(&mut reader)?);
+    }
+    Ok(commands)
+}
+
+/// Write a command list
+pub fn write_commands(commands: &[EventCommand]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for command in commands {
+        command.write(&mut out);
+    }
+    out
+}
+
+/// Read the command list of a structure, if it has one
+pub fn read_command_chunk(chunks: &Chunks, list_id: u32) -> Result<Vec<EventCommand>, LcfError> {
+    chunks
+        .get(list_id)
+        .map(read_commands)
+        .unwrap_or_else(|| Ok(Vec::new()))
+}
+
+/// Replace the command list of a structure, updating its size chunk
+pub fn set_command_chunk(
+    chunks: &mut Chunks,
+    size_id: u32,
+    list_id: u32,
+    commands: &[EventCommand],
+) {
+    let data = write_commands(commands);
+    chunks.set(size_id, ber_bytes(data.len() as u32));
+    chunks.set(list_id, data);
+}
+
+/// Kind of text a command holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextKind {
+    Message,
+    Comment,
+    Choices,
+    HeroName,
+    HeroTitle,
+}
+
+impl TextKind {
+    fn of(code: u32) -> Option<Self> {
+        match code {
+            CODE_MESSAGE => Some(Self::Message),
+            CODE_COMMENT => Some(Self::Comment),
+            CODE_CHOICES => Some(Self::Choices),
+            CODE_CHANGE_HERO_NAME => Some(Self::HeroName),
+            CODE_CHANGE_HERO_TITLE => Some(Self::HeroTitle),
+            _ => None,
+        }
+    }
+
+    /// Code of the commands holding the following lines
+    fn line_code(&self) -> Option<u32> {
+        match self {
+            Self::Message => Some(CODE_MESSAGE_LINE),
+            Self::Comment => Some(CODE_COMMENT_LINE),
+            _ => None,
+        }
+    }
+
+    fn code(&self) -> EventCode {
+        match self {
+            Self::Message => EventCode::ShowTextBody,
+            Self::Comment => EventCode::CommentBody,
+            Self::Choices => EventCode::ShowChoices,
+            Self::HeroName => EventCode::Unknown(CODE_CHANGE_HERO_NAME as i32),
+            Self::HeroTitle => EventCode::Unknown(CODE_CHANGE_HERO_TITLE as i32),
+        }
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            Self::Message => "dialogue",
+            Self::Comment => "comment",
+            Self::Choices => "choice",
+            Self::HeroName => "hero_name",
+            Self::HeroTitle => "hero_title",
+        }
+    }
+}
+
+/// Text of a command list: a command and its following lines
+struct TextBlock {
+    /// Index of the first command
+    start: usize,
+    /// Number of commands
+    len: usize,
+    kind: TextKind,
+}
+
+fn text_blocks(commands: &[EventCommand]) -> Vec<TextBlock> {
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    while start < commands.len() {
+        let Some(kind) = TextKind::of(commands[start].code) else {
+            start += 1;
+            continue;
+        };
+        let mut len = 1;
+        if let Some(line_code) = kind.line_code() {
+            while commands
+                .get(start + len)
+                .is_some_and(|c| c.code == line_code)
+            {
+                len += 1;
+            }
+        }
+        blocks.push(TextBlock { start, len, kind });
+        start += len;
+    }
+    blocks
+}
+
+/// Path of a command
+fn command_path(base: &TranslationPath, command: usize) -> TranslationPath {
+    base.append_key("commands").append_index(command)
+}
+
+/// ID of a choice of a choices command
+fn choice_id(path: &TranslationPath, choice: usize) -> String {
+    format!(
+        "{}_{}_{}",
+        path.to_unit_id(""),
+        TextKind::Choices.suffix(),
+        choice
+    )
+}
+
+/// Decode the lines of a block
+fn block_lines(
+    commands: &[EventCommand],
+    block: &TextBlock,
+    encoding: LcfEncoding,
+) -> Option<Vec<String>> {
+    commands[block.start..block.start + block.len]
+        .iter()
+        .map(|c| lcf::decode_text(&c.string, encoding))
+        .collect()
+}
+
+/// Extract the text of a command list
+///
+/// Undecodable strings are skipped with a warning.
+pub fn extract_commands(
+    commands: &[EventCommand],
+    base: &TranslationPath,
+    context: &mut ExtractionContext,
+    options: &ExtractionOptions,
+    encoding: LcfEncoding,
+    warnings: &mut Vec<String>,
+) -> Vec<TranslationUnit> {
+    let mut units = Vec::new();
+
+    for block in text_blocks(commands) {
+        let path = command_path(base, block.start);
+        let Some(lines) = block_lines(commands, &block, encoding) else {
+            warnings.push(format!("Cannot decode string at {}", path));
+            continue;
+        };
+        let text = lines.join("\n");
+
+        if block.kind == TextKind::Comment
+            && (!options.extract_comments || options.should_skip_comment(&text))
+        {
+            continue;
+        }
+
+        let texts: Vec<(String, String)> = if block.kind == TextKind::Choices {
+            text.split(CHOICE_SEPARATOR)
+                .enumerate()
+                .map(|(i, choice)| (choice_id(&path, i), choice.to_string()))
+                .collect()
+        } else {
+            vec![(path.to_unit_id(block.kind.suffix()), text)]
+        };
+
+        for (id, text) in texts {
+            if text.trim().is_empty() && !options.include_empty {
+                continue;
+            }
+            let text = if options.trim_whitespace {
+                text.trim().to_string()
+            } else {
+                text
+            };
+
+            let unit = TranslationUnit::new(id, path.clone(), block.kind.code(), text)
+                .with_context(context.to_translation_context());
+            if block.kind == TextKind::Message {
+                context.add_preceding_line(unit.original.clone());
+            }
+            units.push(unit);
+        }
+    }
+
+    units
+}
+
+/// Apply the translations of a command list
+///
+/// Returns the new command list, or `None` if nothing was translated.
+/// Translated choices also replace the text of their branches.
+pub fn inject_commands(
+    commands: &[EventCommand],
+    base: &TranslationPath,
+    translations: &HashMap<String, String>,
+    options: &InjectionOptions,
+    encoding: LcfEncoding,
+    result: &mut FileInjectionResult,
+) -> Option<Vec<EventCommand>> {
+    // Replacement commands of blocks and strings of choice branches
+    let mut blocks = HashMap::new();
+    let mut strings = HashMap::new();
+
+    for block in text_blocks(commands) {
+        let path = command_path(base, block.start);
+        let head = &commands[block.start];
+
+        if block.kind == TextKind::Choices {
+            let Some(original) = lcf::decode_text(&head.string, encoding) else {
+                continue;
+            };
+            let mut choices: Vec<String> = original
+                .split(CHOICE_SEPARATOR)
+                .map(str::to_string)
+                .collect();
+            let mut applied = 0;
+            for (i, choice) in choices.iter_mut().enumerate() {
+                match translations.get(&choice_id(&path, i)) {
+                    Some(translated) => {
+                        *choice = translated.clone();
+                        applied += 1;
+                    }
+                    None if !options.skip_missing_translations => result.not_found += 1,
+                    None => {}
+                }
+            }
+            if applied == 0 {
+                continue;
+            }
+
+            let encoded: Option<Vec<Vec<u8>>> = choices
+                .iter()
+                .map(|c| lcf::encode_text(c, encoding))
+                .collect();
+            let Some(encoded) = encoded else {
+                result.warnings.push(format!(
+                    "Translation of {} cannot be encoded as {}",
+                    choice_id(&path, 0),
+                    encoding.name()
+                ));
+                continue;
+            };
+
+            let mut command = head.clone();
+            command.string = encoded.join(&(CHOICE_SEPARATOR as u8));
+            blocks.insert(block.start, (block.len, vec![command]));
+            for (index, branch) in commands.iter().enumerate().skip(block.start + 1) {
+                if branch.indent != head.indent {
+                    continue;
+                }
+                if branch.code == CODE_CHOICES_END {
+                    break;
+                }
+                let choice = branch.parameters.first().map(|&p| p as usize);
+                if let Some(raw) = choice.and_then(|c| encoded.get(c)) {
+                    if branch.code == CODE_CHOICE_BRANCH {
+                        strings.insert(index, raw.clone());
+                    }
+                }
+            }
+
+            result.applied += applied;
+            result.commands_modified += 1;
+            result.modified = true;
+            continue;
+        }
+
+        let id = path.to_unit_id(block.kind.suffix());
+        let Some(translated) = translations.get(&id) else {
+            if block.kind == TextKind::Message && !options.skip_missing_translations {
+                result.not_found += 1;
+            }
+            continue;
+        };
+
+        let lines: Vec<String> = match block.kind {
+            TextKind::Message => options.split_text(translated),
+            TextKind::Comment => translated.lines().map(str::to_string).collect(),
+            _ => vec![translated.clone()],
+        };
+        let encoded: Option<Vec<Vec<u8>>> = lines
+            .iter()
+            .map(|line| lcf::encode_text(line, encoding))
+            .collect();
+        let Some(mut encoded) = encoded else {
+            result.warnings.push(format!(
+                "Translation of {} cannot be encoded as {}",
+                id,
+                encoding.name()
+            ));
+            continue;
+        };
+        if encoded.is_empty() {
+            encoded.push(Vec::new());
+        }
+
+        let replacement = encoded
+            .into_iter()
+            .enumerate()
+            .map(|(i, string)| match i {
+                0 => EventCommand {
+                    string,
+                    ..head.clone()
+                },
+                _ => EventCommand {
+                    code: block.kind.line_code().unwrap_or(head.code),
+                    indent: head.indent,
+                    string,
+                    parameters: Vec::new(),
+                },
+            })
+            .collect();
+        blocks.insert(block.start, (block.len, replacement));
+
+        result.applied += 1;
+        result.commands_modified += 1;
+        result.modified = true;
+    }
+
+    if blocks.is_empty() {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(commands.len());
+    let mut index = 0;
+    while index < commands.len() {
+        if let Some((len, replacement)) = blocks.remove(&index) {
+            out.extend(replacement);
+            index += len;
+            continue;
+        }
+        let mut command = commands[index].clone();
+        if let Some(string) = strings.remove(&index) {
+            command.string = string;
+        }
+        out.push(command);
+        index += 1;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::parser::rpg_maker_2000_2003::lcf::{encode_text, DEFAULT_ENCODING};
+
+    /// Build a command with a Shift-JIS string
+    pub(in crate::parser::rpg_maker_2000_2003) fn command(
+        code: u32,
+        indent: u32,
+        string: &str,
+        parameters: &[i32],
+    ) -> EventCommand {
+        EventCommand {
+            code,
+            indent,
+            string: encode_text(string, DEFAULT_ENCODING).unwrap(),
+            parameters: parameters.to_vec(),
+        }
+    }
+
+    /// Command list ending with the terminator
+    pub(in crate::parser::rpg_maker_2000_2003) fn sample_commands() -> Vec<EventCommand> {
+        vec![
+            command(CODE_MESSAGE, 0, "こんにちは", &[]),
+            command(CODE_MESSAGE_LINE, 0, "元気？", &[]),
+            command(CODE_CHOICES, 0, "はい/いいえ", &[2]),
+            command(CODE_CHOICE_BRANCH, 0, "はい", &[0]),
+            command(CODE_MESSAGE, 1, "よかった", &[]),
+            command(10, 1, "", &[]),
+            command(CODE_CHOICE_BRANCH, 0, "いいえ", &[1]),
+            command(10, 1, "", &[]),
+            command(CODE_CHOICES_END, 0, "", &[]),
+            command(CODE_COMMENT, 0, "メモ", &[]),
+            command(CODE_CHANGE_HERO_NAME, 0, "アレックス", &[1]),
+            command(10, 0, "", &[]),
+            command(0, 0, "", &[]),
+        ]
+    }
+
+    #[test]
+    fn test_read_write_commands() {
+        let data = write_commands(&sample_commands());
+        assert_eq!(&data[data.len() - 4..], &[0, 0, 0, 0]);
+        assert_eq!(&data[..2], &[0xCE, 0x7E]);
+        let commands = read_commands(&data).unwrap();
+        assert_eq!(commands, sample_commands());
+        assert!(read_commands(&data[..data.len() - 5]).is_err());
+    }
+
+    #[test]
+    fn test_extract_commands() {
+        let mut context = ExtractionContext::new("Map0001.lmu");
+        let mut warnings = Vec::new();
+        let units = extract_commands(
+            &sample_commands(),
+            &TranslationPath::new(),
+            &mut context,
+            &ExtractionOptions::default(),
+            DEFAULT_ENCODING,
+            &mut warnings,
+        );
+
+        let texts: Vec<(&str, &str)> = units
+            .iter()
+            .map(|u| (u.id.as_str(), u.original.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("commands.0_dialogue", "こんにちは\n元気？"),
+                ("commands.2_choice_0", "はい"),
+                ("commands.2_choice_1", "いいえ"),
+                ("commands.4_dialogue", "よかった"),
+                ("commands.9_comment", "メモ"),
+                ("commands.10_hero_name", "アレックス"),
+            ]
+        );
+        assert_eq!(units[0].code, EventCode::ShowTextBody);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_inject_commands() {
+        let mut translations = HashMap::new();
+        translations.insert(
+            "commands.0_dialogue".to_string(),
+            "Hello\nHow are you?\nFine".to_string(),
+        );
+        translations.insert("commands.2_choice_1".to_string(), "No".to_string());
+        translations.insert("commands.4_dialogue".to_string(), "좋아".to_string());
+        translations.insert("commands.10_hero_name".to_string(), "Alex".to_string());
+
+        let mut result = FileInjectionResult::new();
+        let commands = inject_commands(
+            &sample_commands(),
+            &TranslationPath::new(),
+            &translations,
+            &InjectionOptions::default(),
+            DEFAULT_ENCODING,
+            &mut result,
+        )
+        .unwrap();
+        assert_eq!(result.applied, 3);
+        assert_eq!(result.commands_modified, 3);
+        assert_eq!(result.warnings.len(), 1);
+
+        assert_eq!(commands.len(), sample_commands().len() + 1);
+        assert_eq!(commands[0].string, b"Hello");
+        assert_eq!(commands[2].code, CODE_MESSAGE_LINE);
+        assert_eq!(commands[2].string, b"Fine");
+        assert_eq!(
+            lcf::decode_text(&commands[3].string, DEFAULT_ENCODING).unwrap(),
+            "はい/No"
+        );
+        assert_eq!(commands[7].string, b"No");
+        assert_eq!(commands[11].string, b"Alex");
+        assert_eq!(commands[11].parameters, vec![1]);
+
+        let mut result = FileInjectionResult::new();
+        assert!(inject_commands(
+            &sample_commands(),
+            &TranslationPath::new(),
+            &HashMap::new(),
+            &InjectionOptions::default(),
+            DEFAULT_ENCODING,
+            &mut result,
+        )
+        .is_none());
+    }
+}
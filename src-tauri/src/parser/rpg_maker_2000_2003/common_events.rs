@@ -0,0 +1,258 @@
+//! Parser for RPG Maker 2000/2003 common events
+//!
+//! Common events are the array in chunk 0x19 of the database
+//! (`RPG_RT.ldb`). Each holds its name, trigger settings and command list
+//! (see [`super::command`]) with the size of that list.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::command::{self, read_command_chunk, set_command_chunk};
+use super::database::HEADER;
+use super::lcf::{self, LcfEncoding, LcfFile, DEFAULT_ENCODING};
+use super::LcfError;
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile, TranslationPath,
+};
+
+/// Common events of the database
+const CHUNK_COMMON_EVENTS: u32 = 0x19;
+/// Name of a common event
+const CHUNK_NAME: u32 = 0x01;
+/// Size of the command list of a common event
+const CHUNK_COMMANDS_SIZE: u32 = 0x15;
+/// Command list of a common event
+const CHUNK_COMMANDS: u32 = 0x16;
+
+/// Parser for the common events of RPG_RT.ldb
+pub struct CommonEventsParser {
+    /// Text encoding
+    encoding: LcfEncoding,
+}
+
+impl CommonEventsParser {
+    /// Create a new common event parser for Shift-JIS games
+    pub fn new() -> Self {
+        Self {
+            encoding: DEFAULT_ENCODING,
+        }
+    }
+
+    /// Use another text encoding
+    pub fn with_encoding(mut self, encoding: LcfEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Extract translations from database content
+    pub fn extract(
+        &self,
+        data: &[u8],
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, LcfError> {
+        let mut result = FileExtractionResult::new(file_name);
+        let file = LcfFile::read(data, HEADER)?;
+        let Some(events) = file.chunks.get(CHUNK_COMMON_EVENTS) else {
+            return Ok(result);
+        };
+
+        let base_context =
+            ExtractionContext::new(file_name).with_max_preceding_lines(options.max_preceding_lines);
+
+        for event in lcf::read_array(events)? {
+            let name = event
+                .chunks
+                .get(CHUNK_NAME)
+                .and_then(|raw| lcf::decode_text(raw, self.encoding));
+            let mut context = base_context.for_event(event.index as usize, name);
+            let event_path = TranslationPath::new()
+                .append_key("common_events")
+                .append_index(event.index as usize);
+
+            let commands = read_command_chunk(&event.chunks, CHUNK_COMMANDS)?;
+            let units = command::extract_commands(
+                &commands,
+                &event_path,
+                &mut context,
+                options,
+                self.encoding,
+                &mut result.warnings,
+            );
+            result.add_units(units);
+        }
+
+        Ok(result)
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, LcfError> {
+        let data = fs::read(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("RPG_RT.ldb");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Inject translations into database content
+    pub fn inject(
+        &self,
+        data: &mut Vec<u8>,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, LcfError> {
+        let mut result = FileInjectionResult::new();
+        let mut file = LcfFile::read(data, HEADER)?;
+        let Some(events_data) = file.chunks.get(CHUNK_COMMON_EVENTS) else {
+            return Ok(result);
+        };
+
+        let mut events = lcf::read_array(events_data)?;
+        let mut modified = false;
+        for event in &mut events {
+            let event_path = TranslationPath::new()
+                .append_key("common_events")
+                .append_index(event.index as usize);
+            let commands = read_command_chunk(&event.chunks, CHUNK_COMMANDS)?;
+            if let Some(commands) = command::inject_commands(
+                &commands,
+                &event_path,
+                translations,
+                options,
+                self.encoding,
+                &mut result,
+            ) {
+                set_command_chunk(
+                    &mut event.chunks,
+                    CHUNK_COMMANDS_SIZE,
+                    CHUNK_COMMANDS,
+                    &commands,
+                );
+                modified = true;
+            }
+        }
+
+        if modified {
+            file.chunks
+                .set(CHUNK_COMMON_EVENTS, lcf::write_array(&events));
+            *data = file.write();
+        }
+        Ok(result)
+    }
+
+    /// Inject translations to a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, LcfError> {
+        let mut data = fs::read(path)?;
+
+        let result = self.inject(&mut data, translations, options)?;
+
+        if result.modified {
+            fs::write(path, data)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+impl Default for CommonEventsParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::command::tests::command;
+    use super::super::command::{write_commands, CODE_MESSAGE};
+    use super::super::lcf::{ber_bytes, encode_text, ArrayItem};
+    use super::super::map::tests::chunks;
+    use super::*;
+
+    fn sample_database() -> Vec<u8> {
+        let event = |index: u32, name: &str, text: &str| {
+            let commands =
+                write_commands(&[command(CODE_MESSAGE, 0, text, &[]), command(0, 0, "", &[])]);
+            ArrayItem {
+                index,
+                chunks: chunks(vec![
+                    (CHUNK_NAME, encode_text(name, DEFAULT_ENCODING).unwrap()),
+                    (0x0B, ber_bytes(5)),
+                    (CHUNK_COMMANDS_SIZE, ber_bytes(commands.len() as u32)),
+                    (CHUNK_COMMANDS, commands),
+                ]),
+            }
+        };
+        LcfFile {
+            header: HEADER.as_bytes().to_vec(),
+            chunks: chunks(vec![(
+                CHUNK_COMMON_EVENTS,
+                lcf::write_array(&[event(1, "会話", "やあ"), event(2, "戦闘", "敵が現れた！")]),
+            )]),
+        }
+        .write()
+    }
+
+    #[test]
+    fn test_extract_common_events() {
+        let data = sample_database();
+        let result = CommonEventsParser::new()
+            .extract(&data, "RPG_RT.ldb", &ExtractionOptions::default())
+            .unwrap();
+
+        let texts: Vec<(&str, &str)> = result
+            .units
+            .iter()
+            .map(|u| (u.id.as_str(), u.original.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("common_events.1.commands.0_dialogue", "やあ"),
+                ("common_events.2.commands.0_dialogue", "敵が現れた！"),
+            ]
+        );
+        assert_eq!(result.units[1].context.event_name.as_deref(), Some("戦闘"));
+    }
+
+    #[test]
+    fn test_inject_common_events() {
+        let mut data = sample_database();
+        let mut translations = HashMap::new();
+        translations.insert(
+            "common_events.2.commands.0_dialogue".to_string(),
+            "An enemy appears!".to_string(),
+        );
+
+        let result = CommonEventsParser::new()
+            .inject(&mut data, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert_eq!(result.applied, 1);
+
+        let result = CommonEventsParser::new()
+            .extract(&data, "RPG_RT.ldb", &ExtractionOptions::default())
+            .unwrap();
+        assert_eq!(result.units[0].original, "やあ");
+        assert_eq!(result.units[1].original, "An enemy appears!");
+    }
+}
@@ -0,0 +1,492 @@
+//! Parser for the RPG Maker 2000/2003 database (`RPG_RT.ldb`)
+//!
+//! ```text
+//! string   "LcfDataBase"
+//! chunks   actors, skills, items, ... as arrays of structures
+//!          terms (0x15) as a structure of strings
+//!          battle commands (0x1D, 2003) as a structure with an array
+//! ```
+//!
+//! Names, descriptions, battle messages and terms are extracted. Common
+//! events are handled by [`super::CommonEventsParser`].
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::lcf::{self, Chunks, LcfEncoding, LcfFile, DEFAULT_ENCODING};
+use super::LcfError;
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    EventCode, ExtractionOptions, InjectionOptions, TranslationContext, TranslationFile,
+    TranslationPath, TranslationUnit,
+};
+
+/// Header of the database
+pub const HEADER: &str = "LcfDataBase";
+
+/// Terms of the database
+pub const CHUNK_TERMS: u32 = 0x15;
+/// Battle commands of the database (2003)
+pub const CHUNK_BATTLE_COMMANDS: u32 = 0x1D;
+/// Classes of the database (2003)
+pub const CHUNK_CLASSES: u32 = 0x1E;
+/// Array of commands in the battle commands
+const CHUNK_BATTLE_COMMAND_LIST: u32 = 0x0A;
+/// Name of a battle command
+const CHUNK_BATTLE_COMMAND_NAME: u32 = 0x01;
+
+/// Array of the database holding text: chunk, key and text fields
+type ArraySection = (u32, &'static str, &'static [(u32, &'static str)]);
+
+/// Arrays of the database holding text
+const ARRAYS: &[ArraySection] = &[
+    (0x0B, "actors", &[(0x01, "name"), (0x02, "title")]),
+    (
+        0x0C,
+        "skills",
+        &[
+            (0x01, "name"),
+            (0x02, "description"),
+            (0x03, "message1"),
+            (0x04, "message2"),
+            (0x07, "failure_message"),
+        ],
+    ),
+    (0x0D, "items", &[(0x01, "name"), (0x02, "description")]),
+    (0x0E, "enemies", &[(0x01, "name")]),
+    (0x11, "attributes", &[(0x01, "name")]),
+    (
+        0x12,
+        "states",
+        &[
+            (0x01, "name"),
+            (0x33, "message_actor"),
+            (0x34, "message_enemy"),
+            (0x35, "message_already"),
+            (0x36, "message_affected"),
+            (0x37, "message_recovery"),
+        ],
+    ),
+    (CHUNK_CLASSES, "classes", &[(0x01, "name")]),
+];
+
+/// Text field of the database
+struct DatabaseField<'a> {
+    path: TranslationPath,
+    suffix: &'static str,
+    /// Section key, used as a context tag
+    key: &'static str,
+    raw: &'a [u8],
+}
+
+/// Text fields of an array of structures
+fn array_fields<'a>(
+    items: &'a [lcf::ArrayItem],
+    key: &'static str,
+    fields: &[(u32, &'static str)],
+) -> Vec<DatabaseField<'a>> {
+    let mut out = Vec::new();
+    for item in items {
+        let path = TranslationPath::new()
+            .append_key(key)
+            .append_index(item.index as usize);
+        for &(id, suffix) in fields {
+            if let Some(raw) = item.chunks.get(id) {
+                out.push(DatabaseField {
+                    path: path.clone(),
+                    suffix,
+                    key,
+                    raw,
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Parsed text sections of a database
+#[derive(Default)]
+struct Sections {
+    arrays: Vec<(&'static ArraySection, Vec<lcf::ArrayItem>)>,
+    terms: Option<Chunks>,
+    battle_commands: Option<(Chunks, Vec<lcf::ArrayItem>)>,
+}
+
+impl Sections {
+    fn read(file: &LcfFile) -> Result<Self, LcfError> {
+        let mut sections = Self::default();
+        for section in ARRAYS {
+            if let Some(data) = file.chunks.get(section.0) {
+                sections.arrays.push((section, lcf::read_array(data)?));
+            }
+        }
+        if let Some(data) = file.chunks.get(CHUNK_TERMS) {
+            sections.terms = Some(Chunks::parse(data)?);
+        }
+        if let Some(data) = file.chunks.get(CHUNK_BATTLE_COMMANDS) {
+            let chunks = Chunks::parse(data)?;
+            let commands = match chunks.get(CHUNK_BATTLE_COMMAND_LIST) {
+                Some(list) => lcf::read_array(list)?,
+                None => Vec::new(),
+            };
+            sections.battle_commands = Some((chunks, commands));
+        }
+        Ok(sections)
+    }
+
+    fn fields(&self) -> Vec<DatabaseField<'_>> {
+        let mut out = Vec::new();
+        for (&(_, key, fields), items) in &self.arrays {
+            out.extend(array_fields(items, key, fields));
+        }
+        if let Some(terms) = &self.terms {
+            for chunk in &terms.chunks {
+                out.push(DatabaseField {
+                    path: TranslationPath::new()
+                        .append_key("terms")
+                        .append_index(chunk.id as usize),
+                    suffix: "term",
+                    key: "terms",
+                    raw: &chunk.data,
+                });
+            }
+        }
+        if let Some((_, commands)) = &self.battle_commands {
+            out.extend(array_fields(
+                commands,
+                "battle_commands",
+                &[(CHUNK_BATTLE_COMMAND_NAME, "name")],
+            ));
+        }
+        out
+    }
+}
+
+/// Parser for the names, descriptions and terms of RPG_RT.ldb
+pub struct DatabaseParser {
+    /// Text encoding
+    encoding: LcfEncoding,
+}
+
+impl DatabaseParser {
+    /// Create a new database parser for Shift-JIS games
+    pub fn new() -> Self {
+        Self {
+            encoding: DEFAULT_ENCODING,
+        }
+    }
+
+    /// Use another text encoding
+    pub fn with_encoding(mut self, encoding: LcfEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Extract translations from database content
+    pub fn extract(
+        &self,
+        data: &[u8],
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, LcfError> {
+        let mut result = FileExtractionResult::new(file_name);
+        let file = LcfFile::read(data, HEADER)?;
+        let sections = Sections::read(&file)?;
+
+        for field in sections.fields() {
+            let Some(text) = lcf::decode_text(field.raw, self.encoding) else {
+                result.add_warning(format!("Cannot decode string at {}", field.path));
+                continue;
+            };
+            let text = if options.trim_whitespace {
+                text.trim().to_string()
+            } else {
+                text
+            };
+
+            let mut context = TranslationContext::new().with_file_name(file_name);
+            context.add_tag(field.key);
+            let unit = TranslationUnit::new(
+                field.path.to_unit_id(field.suffix),
+                field.path,
+                EventCode::Unknown(0),
+                text,
+            )
+            .with_context(context);
+            if unit.needs_translation() {
+                result.add_units(vec![unit]);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, LcfError> {
+        let data = fs::read(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("RPG_RT.ldb");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Replace a text field, warning if the text cannot be encoded
+    fn inject_field(
+        &self,
+        chunks: &mut Chunks,
+        id: u32,
+        unit_id: String,
+        translations: &HashMap<String, String>,
+        result: &mut FileInjectionResult,
+    ) -> bool {
+        if chunks.get(id).is_none() {
+            return false;
+        }
+        let Some(translated) = translations.get(&unit_id) else {
+            return false;
+        };
+        let Some(raw) = lcf::encode_text(translated, self.encoding) else {
+            result.warnings.push(format!(
+                "Translation of {} cannot be encoded as {}",
+                unit_id,
+                self.encoding.name()
+            ));
+            return false;
+        };
+
+        chunks.set(id, raw);
+        result.applied += 1;
+        result.modified = true;
+        true
+    }
+
+    /// Replace the text fields of an array, returning whether any changed
+    fn inject_array(
+        &self,
+        items: &mut [lcf::ArrayItem],
+        key: &str,
+        fields: &[(u32, &str)],
+        translations: &HashMap<String, String>,
+        result: &mut FileInjectionResult,
+    ) -> bool {
+        let mut modified = false;
+        for item in items {
+            let path = TranslationPath::new()
+                .append_key(key)
+                .append_index(item.index as usize);
+            for &(id, suffix) in fields {
+                let unit_id = path.to_unit_id(suffix);
+                if self.inject_field(&mut item.chunks, id, unit_id, translations, result) {
+                    modified = true;
+                }
+            }
+        }
+        modified
+    }
+
+    /// Inject translations into database content
+    pub fn inject(
+        &self,
+        data: &mut Vec<u8>,
+        translations: &HashMap<String, String>,
+        _options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, LcfError> {
+        let mut result = FileInjectionResult::new();
+        let mut file = LcfFile::read(data, HEADER)?;
+        let mut sections = Sections::read(&file)?;
+        let mut changed = Vec::new();
+
+        for (&(chunk, key, fields), items) in &mut sections.arrays {
+            if self.inject_array(items, key, fields, translations, &mut result) {
+                changed.push((chunk, lcf::write_array(items)));
+            }
+        }
+
+        if let Some(terms) = &mut sections.terms {
+            let ids: Vec<u32> = terms.chunks.iter().map(|c| c.id).collect();
+            let mut modified = false;
+            for id in ids {
+                let unit_id = TranslationPath::new()
+                    .append_key("terms")
+                    .append_index(id as usize)
+                    .to_unit_id("term");
+                if self.inject_field(terms, id, unit_id, translations, &mut result) {
+                    modified = true;
+                }
+            }
+            if modified {
+                changed.push((CHUNK_TERMS, terms.to_bytes()));
+            }
+        }
+
+        if let Some((chunks, commands)) = &mut sections.battle_commands {
+            if self.inject_array(
+                commands,
+                "battle_commands",
+                &[(CHUNK_BATTLE_COMMAND_NAME, "name")],
+                translations,
+                &mut result,
+            ) {
+                chunks.set(CHUNK_BATTLE_COMMAND_LIST, lcf::write_array(commands));
+                changed.push((CHUNK_BATTLE_COMMANDS, chunks.to_bytes()));
+            }
+        }
+
+        if !changed.is_empty() {
+            for (chunk, data) in changed {
+                file.chunks.set(chunk, data);
+            }
+            *data = file.write();
+        }
+        Ok(result)
+    }
+
+    /// Inject translations to a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, LcfError> {
+        let mut data = fs::read(path)?;
+
+        let result = self.inject(&mut data, translations, options)?;
+
+        if result.modified {
+            fs::write(path, data)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+impl Default for DatabaseParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::is_rm2003_database;
+    use super::super::lcf::{ber_bytes, encode_text, ArrayItem};
+    use super::super::map::tests::chunks;
+    use super::*;
+
+    fn sjis(text: &str) -> Vec<u8> {
+        encode_text(text, DEFAULT_ENCODING).unwrap()
+    }
+
+    fn sample_database(rm2003: bool) -> Vec<u8> {
+        let actor = ArrayItem {
+            index: 1,
+            chunks: chunks(vec![
+                (0x01, sjis("アレックス")),
+                (0x02, sjis("戦士")),
+                (0x07, ber_bytes(1)),
+            ]),
+        };
+        let item = ArrayItem {
+            index: 1,
+            chunks: chunks(vec![(0x01, sjis("薬草")), (0x02, b"HP +50".to_vec())]),
+        };
+        let terms = chunks(vec![(0x01, sjis("が出現！")), (0x5F, sjis("ゴールド"))]);
+
+        let mut fields = vec![
+            (0x0B, lcf::write_array(&[actor])),
+            (0x0D, lcf::write_array(&[item])),
+            (CHUNK_TERMS, terms.to_bytes()),
+        ];
+        if rm2003 {
+            let command = ArrayItem {
+                index: 1,
+                chunks: chunks(vec![(CHUNK_BATTLE_COMMAND_NAME, sjis("戦う"))]),
+            };
+            let battle_commands = chunks(vec![
+                (0x02, ber_bytes(0)),
+                (CHUNK_BATTLE_COMMAND_LIST, lcf::write_array(&[command])),
+            ]);
+            fields.push((CHUNK_BATTLE_COMMANDS, battle_commands.to_bytes()));
+        }
+        LcfFile {
+            header: HEADER.as_bytes().to_vec(),
+            chunks: chunks(fields),
+        }
+        .write()
+    }
+
+    #[test]
+    fn test_extract_database() {
+        let data = sample_database(true);
+        let result = DatabaseParser::new()
+            .extract(&data, "RPG_RT.ldb", &ExtractionOptions::default())
+            .unwrap();
+
+        let texts: Vec<(&str, &str)> = result
+            .units
+            .iter()
+            .map(|u| (u.id.as_str(), u.original.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("actors.1_name", "アレックス"),
+                ("actors.1_title", "戦士"),
+                ("items.1_name", "薬草"),
+                ("terms.1_term", "が出現！"),
+                ("terms.95_term", "ゴールド"),
+                ("battle_commands.1_name", "戦う"),
+            ]
+        );
+        assert_eq!(result.units[2].context.tags, vec!["items".to_string()]);
+    }
+
+    #[test]
+    fn test_inject_database() {
+        let mut data = sample_database(true);
+        let mut translations = HashMap::new();
+        translations.insert("actors.1_title".to_string(), "Warrior".to_string());
+        translations.insert("terms.95_term".to_string(), "Gold".to_string());
+        translations.insert("battle_commands.1_name".to_string(), "Fight".to_string());
+        translations.insert("items.1_name".to_string(), "약초".to_string());
+
+        let result = DatabaseParser::new()
+            .inject(&mut data, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert_eq!(result.applied, 3);
+        assert_eq!(result.warnings.len(), 1);
+
+        let result = DatabaseParser::new()
+            .extract(&data, "RPG_RT.ldb", &ExtractionOptions::default())
+            .unwrap();
+        let texts: Vec<&str> = result.units.iter().map(|u| u.original.as_str()).collect();
+        assert_eq!(texts, vec!["アレックス", "薬草", "が出現！"]);
+
+        let file = LcfFile::read(&data, HEADER).unwrap();
+        let actors = lcf::read_array(file.chunks.get(0x0B).unwrap()).unwrap();
+        assert_eq!(actors[0].chunks.get(0x02), Some(&b"Warrior"[..]));
+        assert_eq!(actors[0].chunks.get_int(0x07), Some(1));
+    }
+
+    #[test]
+    fn test_is_rm2003_database() {
+        assert!(is_rm2003_database(&sample_database(true)));
+        assert!(!is_rm2003_database(&sample_database(false)));
+        assert!(!is_rm2003_database(b"not a database"));
+    }
+}
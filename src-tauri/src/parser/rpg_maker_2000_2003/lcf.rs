@@ -0,0 +1,410 @@
+//! LCF data format of RPG Maker 2000/2003
+//!
+//! The database (`RPG_RT.ldb`), map tree (`RPG_RT.lmt`) and maps
+//! (`Map*.lmu`) start with a header string, followed by nested chunk
+//! lists:
+//!
+//! ```text
+//! file     header(string) chunks
+//! chunks   (id(ber) size(ber) data[size])* 0
+//! array    count(ber) (index(ber) chunks)*
+//! string   length(ber) bytes
+//! ```
+//!
+//! Integers are BER compressed: 7 bits per byte, most significant first,
+//! with the high bit set on every byte but the last. Negative integers are
+//! written as their 32-bit two's complement. Chunk data is kept as read,
+//! so writing an unmodified file gives its original bytes back and only
+//! edited chunks are re-encoded.
+
+use encoding_rs::{Encoding, SHIFT_JIS};
+
+use super::LcfError;
+
+/// Text encoding of a game
+pub type LcfEncoding = &'static Encoding;
+
+/// Encoding of the Japanese releases
+pub const DEFAULT_ENCODING: LcfEncoding = SHIFT_JIS;
+
+/// Decode raw string bytes, or `None` if they are not valid text
+pub fn decode_text(raw: &[u8], encoding: LcfEncoding) -> Option<String> {
+    let (text, had_errors) = encoding.decode_without_bom_handling(raw);
+    (!had_errors).then(|| text.into_owned())
+}
+
+/// Encode text, or `None` if it has characters the encoding lacks
+pub fn encode_text(text: &str, encoding: LcfEncoding) -> Option<Vec<u8>> {
+    let (bytes, _, had_errors) = encoding.encode(text);
+    (!had_errors).then(|| bytes.into_owned())
+}
+
+/// Cursor over LCF data
+pub struct LcfReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> LcfReader<'a> {
+    /// Create a reader at the start of `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Current position
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Whether all bytes have been read
+    pub fn is_at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    /// Read `len` raw bytes
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], LcfError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(LcfError::UnexpectedEof(self.pos))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Read a BER compressed integer
+    pub fn ber(&mut self) -> Result<u32, LcfError> {
+        let offset = self.pos;
+        let mut value: u64 = 0;
+        for _ in 0..5 {
+            let byte = self.bytes(1)?[0];
+            value = (value << 7) | u64::from(byte & 0x7F);
+            if byte & 0x80 == 0 {
+                return u32::try_from(value).map_err(|_| {
+                    LcfError::InvalidStructure(format!("integer too large at offset {:#x}", offset))
+                });
+            }
+        }
+        Err(LcfError::InvalidStructure(format!(
+            "integer too long at offset {:#x}",
+            offset
+        )))
+    }
+
+    /// Read a signed integer
+    pub fn int(&mut self) -> Result<i32, LcfError> {
+        self.ber().map(|v| v as i32)
+    }
+
+    /// Read a BER integer used as a count, checked against the bytes left
+    ///
+    /// Every counted item takes at least one byte, which stops corrupt
+    /// counts from causing huge allocations.
+    pub fn count(&mut self) -> Result<usize, LcfError> {
+        let offset = self.pos;
+        let count = self.ber()? as usize;
+        if count > self.data.len() - self.pos {
+            return Err(LcfError::InvalidStructure(format!(
+                "count {} at offset {:#x} exceeds the data size",
+                count, offset
+            )));
+        }
+        Ok(count)
+    }
+
+    /// Read a length-prefixed string
+    pub fn string(&mut self) -> Result<&'a [u8], LcfError> {
+        let len = self.ber()? as usize;
+        self.bytes(len)
+    }
+}
+
+/// Write a BER compressed integer
+pub fn write_ber(out: &mut Vec<u8>, value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        bytes.push((rest & 0x7F) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
+
+/// Write a signed integer
+pub fn write_int(out: &mut Vec<u8>, value: i32) {
+    write_ber(out, value as u32);
+}
+
+/// Write a length-prefixed string
+pub fn write_string(out: &mut Vec<u8>, raw: &[u8]) {
+    write_ber(out, raw.len() as u32);
+    out.extend_from_slice(raw);
+}
+
+/// Encode an integer as chunk data
+pub fn ber_bytes(value: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(5);
+    write_ber(&mut out, value);
+    out
+}
+
+/// Chunk of a chunk list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Field ID
+    pub id: u32,
+    /// Raw field data
+    pub data: Vec<u8>,
+}
+
+/// Chunk list of a structure
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Chunks {
+    /// Chunks, in file order
+    pub chunks: Vec<Chunk>,
+    /// Whether the list ends with a 0 terminator rather than the data end
+    pub terminated: bool,
+}
+
+impl Chunks {
+    /// Read chunks up to a 0 terminator or the end of the data
+    pub fn read(reader: &mut LcfReader) -> Result<Self, LcfError> {
+        let mut chunks = Vec::new();
+        while !reader.is_at_end() {
+            let id = reader.ber()?;
+            if id == 0 {
+                return Ok(Self {
+                    chunks,
+                    terminated: true,
+                });
+            }
+            let size = reader.ber()? as usize;
+            let data = reader.bytes(size)?.to_vec();
+            chunks.push(Chunk { id, data });
+        }
+        Ok(Self {
+            chunks,
+            terminated: false,
+        })
+    }
+
+    /// Read the chunk list filling `data`
+    pub fn parse(data: &[u8]) -> Result<Self, LcfError> {
+        let mut reader = LcfReader::new(data);
+        let chunks = Self::read(&mut reader)?;
+        if !reader.is_at_end() {
+            return Err(LcfError::InvalidStructure(format!(
+                "data after chunk list at offset {:#x}",
+                reader.position()
+            )));
+        }
+        Ok(chunks)
+    }
+
+    /// Write the chunks
+    pub fn write(&self, out: &mut Vec<u8>) {
+        for chunk in &self.chunks {
+            write_ber(out, chunk.id);
+            write_ber(out, chunk.data.len() as u32);
+            out.extend_from_slice(&chunk.data);
+        }
+        if self.terminated {
+            out.push(0);
+        }
+    }
+
+    /// Get the chunks as bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out);
+        out
+    }
+
+    /// Get the data of a chunk
+    pub fn get(&self, id: u32) -> Option<&[u8]> {
+        self.chunks
+            .iter()
+            .find(|c| c.id == id)
+            .map(|c| c.data.as_slice())
+    }
+
+    /// Set the data of a chunk, adding it in ID order if missing
+    pub fn set(&mut self, id: u32, data: Vec<u8>) {
+        if let Some(chunk) = self.chunks.iter_mut().find(|c| c.id == id) {
+            chunk.data = data;
+            return;
+        }
+        let pos = self
+            .chunks
+            .iter()
+            .position(|c| c.id > id)
+            .unwrap_or(self.chunks.len());
+        self.chunks.insert(pos, Chunk { id, data });
+    }
+
+    /// Get a chunk holding an integer
+    pub fn get_int(&self, id: u32) -> Option<i32> {
+        LcfReader::new(self.get(id)?).int().ok()
+    }
+}
+
+/// Item of an array of structures
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArrayItem {
+    /// Index of the item, usually its ID
+    pub index: u32,
+    /// Chunks of the item
+    pub chunks: Chunks,
+}
+
+/// Read the array of structures filling `data`
+pub fn read_array(data: &[u8]) -> Result<Vec<ArrayItem>, LcfError> {
+    let mut reader = LcfReader::new(data);
+    let items = read_array_from(&mut reader)?;
+    if !reader.is_at_end() {
+        return Err(LcfError::InvalidStructure(format!(
+            "data after array at offset {:#x}",
+            reader.position()
+        )));
+    }
+    Ok(items)
+}
+
+/// Read an array of structures
+pub fn read_array_from(reader: &mut LcfReader) -> Result<Vec<ArrayItem>, LcfError> {
+    let count = reader.count()?;
+    (0..count)
+        .map(|_| {
+            let index = reader.ber()?;
+            let chunks = Chunks::read(reader)?;
+            Ok(ArrayItem { index, chunks })
+        })
+        .collect()
+}
+
+/// Write an array of structures
+pub fn write_array(items: &[ArrayItem]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_ber(&mut out, items.len() as u32);
+    for item in items {
+        write_ber(&mut out, item.index);
+        item.chunks.write(&mut out);
+    }
+    out
+}
+
+/// LCF file: a header and the chunks of its root structure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LcfFile {
+    /// Header string (`LcfDataBase`, `LcfMapUnit`, ...)
+    pub header: Vec<u8>,
+    /// Chunks of the root structure
+    pub chunks: Chunks,
+}
+
+impl LcfFile {
+    /// Read a file, checking its header
+    pub fn read(data: &[u8], header: &str) -> Result<Self, LcfError> {
+        let mut reader = LcfReader::new(data);
+        let found = read_header(&mut reader, header)?;
+        let chunks = Chunks::read(&mut reader)?;
+        if !reader.is_at_end() {
+            return Err(LcfError::InvalidStructure(format!(
+                "data after the root structure at offset {:#x}",
+                reader.position()
+            )));
+        }
+        Ok(Self {
+            header: found.to_vec(),
+            chunks,
+        })
+    }
+
+    /// Write the file
+    pub fn write(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_string(&mut out, &self.header);
+        self.chunks.write(&mut out);
+        out
+    }
+}
+
+/// Read the header string of a file, checking it
+pub fn read_header<'a>(reader: &mut LcfReader<'a>, header: &str) -> Result<&'a [u8], LcfError> {
+    let found = reader.string()?;
+    if found != header.as_bytes() {
+        return Err(LcfError::InvalidStructure(format!(
+            "expected {} header, found {:?}",
+            header,
+            String::from_utf8_lossy(found)
+        )));
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ber() {
+        for (value, bytes) in [
+            (0u32, &[0x00][..]),
+            (0x7F, &[0x7F]),
+            (0x80, &[0x81, 0x00]),
+            (10110, &[0xCE, 0x7E]),
+            (u32::MAX, &[0x8F, 0xFF, 0xFF, 0xFF, 0x7F]),
+        ] {
+            assert_eq!(ber_bytes(value), bytes);
+            assert_eq!(LcfReader::new(bytes).ber().unwrap(), value);
+        }
+        assert_eq!(LcfReader::new(&ber_bytes(-1i32 as u32)).int().unwrap(), -1);
+        assert!(LcfReader::new(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00])
+            .ber()
+            .is_err());
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let mut item = Chunks {
+            chunks: Vec::new(),
+            terminated: true,
+        };
+        item.set(0x02, vec![0x82, 0xCD]);
+        item.set(0x01, b"Alex".to_vec());
+        assert_eq!(item.chunks[0].id, 0x01);
+        let array = write_array(&[ArrayItem {
+            index: 1,
+            chunks: item,
+        }]);
+
+        let file = LcfFile {
+            header: b"LcfDataBase".to_vec(),
+            chunks: Chunks {
+                chunks: vec![Chunk {
+                    id: 0x0B,
+                    data: array.clone(),
+                }],
+                terminated: true,
+            },
+        };
+        let data = file.write();
+        assert_eq!(&data[..12], b"\x0bLcfDataBase");
+
+        let read = LcfFile::read(&data, "LcfDataBase").unwrap();
+        assert_eq!(read, file);
+        let items = read_array(read.chunks.get(0x0B).unwrap()).unwrap();
+        assert_eq!(items[0].chunks.get(0x01), Some(&b"Alex"[..]));
+        assert_eq!(write_array(&items), array);
+        assert!(LcfFile::read(&data, "LcfMapUnit").is_err());
+    }
+
+    #[test]
+    fn test_encoding() {
+        let raw = encode_text("はい", DEFAULT_ENCODING).unwrap();
+        assert_eq!(raw, [0x82, 0xCD, 0x82, 0xA2]);
+        assert_eq!(decode_text(&raw, DEFAULT_ENCODING).unwrap(), "はい");
+        assert!(encode_text("한국어", DEFAULT_ENCODING).is_none());
+    }
+}
@@ -0,0 +1,391 @@
+//! Parser for RPG Maker 2000/2003 maps (`Map*.lmu`)
+//!
+//! ```text
+//! string   "LcfMapUnit"
+//! chunks   map settings, with the events array in chunk 0x51
+//! ```
+//!
+//! Events hold their name and an array of pages, each page holding its
+//! command list (see [`super::command`]) and the size of that list.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::command::{self, read_command_chunk, set_command_chunk};
+use super::lcf::{self, LcfEncoding, LcfFile, DEFAULT_ENCODING};
+use super::LcfError;
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile, TranslationPath,
+};
+
+/// Header of a map
+pub const HEADER: &str = "LcfMapUnit";
+
+/// Events of the map
+const CHUNK_EVENTS: u32 = 0x51;
+/// Name of an event
+const CHUNK_EVENT_NAME: u32 = 0x01;
+/// Pages of an event
+const CHUNK_EVENT_PAGES: u32 = 0x05;
+/// Size of the command list of a page
+const CHUNK_PAGE_COMMANDS_SIZE: u32 = 0x33;
+/// Command list of a page
+const CHUNK_PAGE_COMMANDS: u32 = 0x34;
+
+/// Parser for Map*.lmu files
+pub struct MapParser {
+    /// Text encoding
+    encoding: LcfEncoding,
+    /// Map names from the map tree, by map ID
+    map_names: HashMap<u32, String>,
+}
+
+impl MapParser {
+    /// Create a new map parser for Shift-JIS games
+    pub fn new() -> Self {
+        Self {
+            encoding: DEFAULT_ENCODING,
+            map_names: HashMap::new(),
+        }
+    }
+
+    /// Use another text encoding
+    pub fn with_encoding(mut self, encoding: LcfEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Use the map names of the map tree as context
+    ///
+    /// See [`read_map_names`](super::read_map_names). Maps without a name
+    /// use their file name.
+    pub fn with_map_names(mut self, map_names: HashMap<u32, String>) -> Self {
+        self.map_names = map_names;
+        self
+    }
+
+    fn map_name(&self, file_name: &str) -> String {
+        let stem = Path::new(file_name)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(file_name);
+        map_id(file_name)
+            .and_then(|id| self.map_names.get(&id))
+            .cloned()
+            .unwrap_or_else(|| stem.to_string())
+    }
+
+    /// Extract translations from map content
+    pub fn extract(
+        &self,
+        data: &[u8],
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, LcfError> {
+        let mut result = FileExtractionResult::new(file_name);
+        let file = LcfFile::read(data, HEADER)?;
+        let Some(events) = file.chunks.get(CHUNK_EVENTS) else {
+            return Ok(result);
+        };
+
+        let base_context = ExtractionContext::new(file_name)
+            .with_map_name(self.map_name(file_name))
+            .with_max_preceding_lines(options.max_preceding_lines);
+
+        for event in lcf::read_array(events)? {
+            let name = event
+                .chunks
+                .get(CHUNK_EVENT_NAME)
+                .and_then(|raw| lcf::decode_text(raw, self.encoding));
+            let event_context = base_context.for_event(event.index as usize, name);
+            let event_path = TranslationPath::new()
+                .append_key("events")
+                .append_index(event.index as usize);
+
+            let Some(pages) = event.chunks.get(CHUNK_EVENT_PAGES) else {
+                continue;
+            };
+            for page in lcf::read_array(pages)? {
+                let mut context = event_context.for_page(page.index as usize);
+                let page_path = event_path
+                    .append_key("pages")
+                    .append_index(page.index as usize);
+                let commands = read_command_chunk(&page.chunks, CHUNK_PAGE_COMMANDS)?;
+                let units = command::extract_commands(
+                    &commands,
+                    &page_path,
+                    &mut context,
+                    options,
+                    self.encoding,
+                    &mut result.warnings,
+                );
+                result.add_units(units);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, LcfError> {
+        let data = fs::read(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Map.lmu");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Inject translations into map content
+    pub fn inject(
+        &self,
+        data: &mut Vec<u8>,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, LcfError> {
+        let mut result = FileInjectionResult::new();
+        let mut file = LcfFile::read(data, HEADER)?;
+        let Some(events_data) = file.chunks.get(CHUNK_EVENTS) else {
+            return Ok(result);
+        };
+
+        let mut events = lcf::read_array(events_data)?;
+        let mut events_modified = false;
+        for event in &mut events {
+            let event_path = TranslationPath::new()
+                .append_key("events")
+                .append_index(event.index as usize);
+            let Some(pages_data) = event.chunks.get(CHUNK_EVENT_PAGES) else {
+                continue;
+            };
+
+            let mut pages = lcf::read_array(pages_data)?;
+            let mut pages_modified = false;
+            for page in &mut pages {
+                let page_path = event_path
+                    .append_key("pages")
+                    .append_index(page.index as usize);
+                let commands = read_command_chunk(&page.chunks, CHUNK_PAGE_COMMANDS)?;
+                if let Some(commands) = command::inject_commands(
+                    &commands,
+                    &page_path,
+                    translations,
+                    options,
+                    self.encoding,
+                    &mut result,
+                ) {
+                    set_command_chunk(
+                        &mut page.chunks,
+                        CHUNK_PAGE_COMMANDS_SIZE,
+                        CHUNK_PAGE_COMMANDS,
+                        &commands,
+                    );
+                    pages_modified = true;
+                }
+            }
+
+            if pages_modified {
+                event
+                    .chunks
+                    .set(CHUNK_EVENT_PAGES, lcf::write_array(&pages));
+                events_modified = true;
+            }
+        }
+
+        if events_modified {
+            file.chunks.set(CHUNK_EVENTS, lcf::write_array(&events));
+            *data = file.write();
+        }
+        Ok(result)
+    }
+
+    /// Inject translations to a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, LcfError> {
+        let mut data = fs::read(path)?;
+
+        let result = self.inject(&mut data, translations, options)?;
+
+        if result.modified {
+            fs::write(path, data)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+impl Default for MapParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Map ID of a `Map0001.lmu` file name
+pub fn map_id(file_name: &str) -> Option<u32> {
+    let stem = Path::new(file_name).file_stem()?.to_str()?;
+    let prefix = stem.get(..3)?;
+    if !prefix.eq_ignore_ascii_case("map") {
+        return None;
+    }
+    stem[prefix.len()..].parse().ok()
+}
+
+/// Check if a file is an RPG Maker 2000/2003 map file
+pub fn is_map_file(path: &Path) -> bool {
+    let is_lmu = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("lmu"));
+    is_lmu
+        && path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(map_id)
+            .is_some()
+}
+
+/// Get all map files of a game directory, sorted by name
+pub fn find_map_files(dir: &Path) -> Result<Vec<PathBuf>, LcfError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_map_file(&path) && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::super::command::tests::sample_commands;
+    use super::super::command::write_commands;
+    use super::super::lcf::{ber_bytes, encode_text, ArrayItem, Chunks};
+    use super::*;
+
+    /// Chunk list of a structure
+    pub(in crate::parser::rpg_maker_2000_2003) fn chunks(fields: Vec<(u32, Vec<u8>)>) -> Chunks {
+        let mut chunks = Chunks {
+            chunks: Vec::new(),
+            terminated: true,
+        };
+        for (id, data) in fields {
+            chunks.set(id, data);
+        }
+        chunks
+    }
+
+    fn sample_map() -> Vec<u8> {
+        let commands = write_commands(&sample_commands());
+        let page = ArrayItem {
+            index: 1,
+            chunks: chunks(vec![
+                (CHUNK_PAGE_COMMANDS_SIZE, ber_bytes(commands.len() as u32)),
+                (CHUNK_PAGE_COMMANDS, commands),
+            ]),
+        };
+        let event = ArrayItem {
+            index: 3,
+            chunks: chunks(vec![
+                (
+                    CHUNK_EVENT_NAME,
+                    encode_text("村人", DEFAULT_ENCODING).unwrap(),
+                ),
+                (0x24, ber_bytes(5)),
+                (CHUNK_EVENT_PAGES, lcf::write_array(&[page])),
+            ]),
+        };
+        LcfFile {
+            header: HEADER.as_bytes().to_vec(),
+            chunks: chunks(vec![
+                (0x01, ber_bytes(1)),
+                (CHUNK_EVENTS, lcf::write_array(&[event])),
+            ]),
+        }
+        .write()
+    }
+
+    #[test]
+    fn test_extract_map() {
+        let data = sample_map();
+        let mut names = HashMap::new();
+        names.insert(1, "はじまりの村".to_string());
+        let result = MapParser::new()
+            .with_map_names(names)
+            .extract(&data, "Map0001.lmu", &ExtractionOptions::default())
+            .unwrap();
+
+        assert_eq!(result.units.len(), 6);
+        let unit = &result.units[0];
+        assert_eq!(unit.id, "events.3.pages.1.commands.0_dialogue");
+        assert_eq!(unit.original, "こんにちは\n元気？");
+        assert_eq!(unit.context.map_name.as_deref(), Some("はじまりの村"));
+        assert_eq!(unit.context.event_name.as_deref(), Some("村人"));
+    }
+
+    #[test]
+    fn test_inject_map() {
+        let original = sample_map();
+        let mut data = original.clone();
+        let result = MapParser::new()
+            .inject(&mut data, &HashMap::new(), &InjectionOptions::default())
+            .unwrap();
+        assert!(!result.modified);
+        assert_eq!(data, original);
+
+        let mut translations = HashMap::new();
+        translations.insert(
+            "events.3.pages.1.commands.0_dialogue".to_string(),
+            "Hello\nHow are you?".to_string(),
+        );
+        let result = MapParser::new()
+            .inject(&mut data, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert_eq!(result.applied, 1);
+
+        let result = MapParser::new()
+            .extract(&data, "Map0001.lmu", &ExtractionOptions::default())
+            .unwrap();
+        assert_eq!(result.units[0].original, "Hello\nHow are you?");
+        assert_eq!(result.units[0].context.map_name.as_deref(), Some("Map0001"));
+
+        // The size chunk follows the new command list
+        let file = LcfFile::read(&data, HEADER).unwrap();
+        let events = lcf::read_array(file.chunks.get(CHUNK_EVENTS).unwrap()).unwrap();
+        let pages = lcf::read_array(events[0].chunks.get(CHUNK_EVENT_PAGES).unwrap()).unwrap();
+        let size = pages[0].chunks.get_int(CHUNK_PAGE_COMMANDS_SIZE).unwrap();
+        assert_eq!(
+            size as usize,
+            pages[0].chunks.get(CHUNK_PAGE_COMMANDS).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_is_map_file() {
+        assert!(is_map_file(Path::new("Map0001.lmu")));
+        assert!(is_map_file(Path::new("MAP0012.LMU")));
+        assert!(!is_map_file(Path::new("RPG_RT.lmt")));
+        assert!(!is_map_file(Path::new("Mapping.lmu")));
+        assert_eq!(map_id("Map0012.lmu"), Some(12));
+    }
+}
@@ -0,0 +1,72 @@
+//! Map tree of RPG Maker 2000/2003 (`RPG_RT.lmt`)
+//!
+//! ```text
+//! string   "LcfMapTree"
+//! array    map infos, by map ID (0 is the game itself)
+//!          tree order, active node and start position
+//! ```
+//!
+//! Only the map names are read, to give map events their map name as
+//! context.
+
+use std::collections::HashMap;
+
+use super::lcf::{self, LcfEncoding, LcfReader};
+use super::LcfError;
+
+/// Header of the map tree
+pub const HEADER: &str = "LcfMapTree";
+
+/// Name of a map info
+const CHUNK_NAME: u32 = 0x01;
+
+/// Read the map names of map tree content, by map ID
+///
+/// Undecodable names are left out.
+pub fn read_map_names(
+    data: &[u8],
+    encoding: LcfEncoding,
+) -> Result<HashMap<u32, String>, LcfError> {
+    let mut reader = LcfReader::new(data);
+    lcf::read_header(&mut reader, HEADER)?;
+
+    let names = lcf::read_array_from(&mut reader)?
+        .into_iter()
+        .filter_map(|info| {
+            let name = lcf::decode_text(info.chunks.get(CHUNK_NAME)?, encoding)?;
+            Some((info.index, name))
+        })
+        .collect();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::rpg_maker_2000_2003::lcf::{
+        encode_text, write_array, write_string, ArrayItem, Chunks, DEFAULT_ENCODING,
+    };
+
+    #[test]
+    fn test_read_map_names() {
+        let info = |index: u32, name: &str| {
+            let mut chunks = Chunks {
+                chunks: Vec::new(),
+                terminated: true,
+            };
+            chunks.set(CHUNK_NAME, encode_text(name, DEFAULT_ENCODING).unwrap());
+            ArrayItem { index, chunks }
+        };
+
+        let mut data = Vec::new();
+        write_string(&mut data, HEADER.as_bytes());
+        data.extend(write_array(&[info(0, "ゲーム"), info(1, "はじまりの村")]));
+        // Tree order and the rest are not read
+        data.extend_from_slice(&[2, 0, 1, 1, 0]);
+
+        let names = read_map_names(&data, DEFAULT_ENCODING).unwrap();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&1], "はじまりの村");
+        assert!(read_map_names(b"\x0aLcfMapUnit", DEFAULT_ENCODING).is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! RPG Maker 2000/2003 parser module
+//!
+//! This module provides parsing capabilities for RPG Maker 2000 and 2003
+//! games, which store their data in LCF files (see [`lcf`]) next to
+//! `RPG_RT.exe`:
+//!
+//! - `RPG_RT.ldb`: database, including common events and terms
+//! - `RPG_RT.lmt`: map tree, holding the map names
+//! - `Map*.lmu`: maps and their events
+//!
+//! Translations are injected by re-encoding the edited chunks, leaving the
+//! rest of the data as read.
+
+pub mod command;
+pub mod common_events;
+pub mod database;
+pub mod lcf;
+pub mod map;
+pub mod map_tree;
+
+pub use common_events::CommonEventsParser;
+pub use database::DatabaseParser;
+pub use lcf::LcfEncoding;
+pub use map::MapParser;
+pub use map_tree::read_map_names;
+
+/// Error type for RPG Maker 2000/2003 parsing
+#[derive(Debug, thiserror::Error)]
+pub enum LcfError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Unexpected end of data at offset {0:#x}")]
+    UnexpectedEof(usize),
+
+    #[error("Invalid structure: {0}")]
+    InvalidStructure(String),
+}
+
+/// Whether database content comes from RPG Maker 2003
+///
+/// Only the 2003 database has battle commands and classes.
+pub fn is_rm2003_database(data: &[u8]) -> bool {
+    lcf::LcfFile::read(data, database::HEADER).is_ok_and(|file| {
+        [database::CHUNK_BATTLE_COMMANDS, database::CHUNK_CLASSES]
+            .iter()
+            .any(|&id| file.chunks.get(id).is_some_and(|data| !data.is_empty()))
+    })
+}
@@ -4,6 +4,8 @@
 //! each with a list of commands that can be extracted for translation.
 
 use super::event_page::{EventPageParser, FileExtractionResult, FileInjectionResult};
+use crate::archiver::vfs::GameVfs;
+use crate::archiver::ArchiverError;
 use crate::parser::types::{
     ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile, TranslationPath,
 };
@@ -110,6 +112,23 @@ impl CommonEventsParser {
         Ok(self.extract(&json, file_name, options))
     }
 
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside a mounted archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, CommonEventsError> {
+        let content = vfs.read_to_string(path)?;
+        let json: Value = serde_json::from_str(&content)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("CommonEvents.json");
+
+        Ok(self.extract(&json, file_name, options))
+    }
+
     /// Inject translations back into CommonEvents.json content
     pub fn inject(
         &self,
@@ -200,6 +219,9 @@ pub enum CommonEventsError {
     
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
+
+    #[error("Archive error: {0}")]
+    ArchiveError(#[from] ArchiverError),
     
     #[error("Invalid structure: {0}")]
     InvalidStructure(String),
@@ -4,6 +4,8 @@
 //! Each event can have multiple pages with different conditions and commands.
 
 use super::event_page::{EventPageParser, FileExtractionResult, FileInjectionResult};
+use crate::archiver::vfs::GameVfs;
+use crate::archiver::ArchiverError;
 use crate::parser::types::{
     ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile, TranslationPath,
 };
@@ -129,6 +131,23 @@ impl MapParser {
         Ok(self.extract(&json, file_name, options))
     }
 
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside a mounted archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, MapError> {
+        let content = vfs.read_to_string(path)?;
+        let json: Value = serde_json::from_str(&content)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("Map.json");
+
+        Ok(self.extract(&json, file_name, options))
+    }
+
     /// Inject translations back into Map JSON content
     pub fn inject(
         &self,
@@ -222,6 +241,9 @@ pub enum MapError {
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    #[error("Archive error: {0}")]
+    ArchiveError(#[from] ArchiverError),
+
     #[error("Invalid structure: {0}")]
     InvalidStructure(String),
 }
@@ -254,6 +276,16 @@ pub fn find_map_files(dir: &Path) -> Vec<std::path::PathBuf> {
     maps
 }
 
+/// Get all map files in a directory of a game's virtual filesystem
+///
+/// Includes maps stored in mounted archives.
+pub fn find_map_files_vfs(vfs: &GameVfs, dir: &str) -> Vec<String> {
+    vfs.list_dir(dir)
+        .into_iter()
+        .filter(|path| is_map_file(Path::new(path)))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -415,4 +447,31 @@ mod tests {
         assert_eq!(trans_file.source_file, "Map001.json");
         assert!(trans_file.units.len() > 0);
     }
+
+    #[test]
+    fn test_extract_vfs_from_package() {
+        use crate::archiver::nwjs::NwWriter;
+        use crate::archiver::ArchiveWriter;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("Map001.json");
+        fs::write(&source, make_map_json().to_string()).unwrap();
+
+        let mut writer = NwWriter::new();
+        writer.add_file(&source, "www/data/Map001.json").unwrap();
+        writer.write(temp_dir.path().join("package.nw")).unwrap();
+        fs::remove_file(&source).unwrap();
+
+        let vfs = GameVfs::open_game_dir(temp_dir.path()).unwrap();
+        let maps = find_map_files_vfs(&vfs, "www/data");
+        assert_eq!(maps, vec!["www/data/Map001.json"]);
+
+        let parser = MapParser::new();
+        let options = ExtractionOptions::default();
+        let result = parser.extract_vfs(&vfs, &maps[0], &options).unwrap();
+        let expected = parser.extract(&make_map_json(), "Map001.json", &options);
+
+        assert_eq!(result.source_file, "Map001.json");
+        assert_eq!(result.unit_count(), expected.unit_count());
+    }
 }
@@ -0,0 +1,180 @@
+//! Event command structure for RPG Maker XP/VX/VX Ace
+//!
+//! Represents a single `RPG::EventCommand` object of a command list.
+
+use crate::parser::marshal::{RString, Symbol, Value};
+use crate::parser::types::EventCode;
+use crate::types::engine::RpgMakerVersion;
+
+/// Class name of event commands
+pub const EVENT_COMMAND_CLASS: &str = "RPG::EventCommand";
+
+/// A single event command from RPG Maker XP/VX/VX Ace
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventCommand {
+    /// Command code (e.g., 101, 401, 102)
+    pub code: i32,
+    /// Indent level (for nested commands like conditionals)
+    pub indent: i32,
+    /// Command parameters
+    pub parameters: Vec<Value>,
+}
+
+impl EventCommand {
+    /// Create a new event command
+    pub fn new(code: i32, indent: i32, parameters: Vec<Value>) -> Self {
+        Self {
+            code,
+            indent,
+            parameters,
+        }
+    }
+
+    /// Read a command from an `RPG::EventCommand` object
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let code = value.ivar("@code")?.as_i64()? as i32;
+        let indent = value.ivar("@indent").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+        let parameters = value
+            .ivar("@parameters")
+            .and_then(|v| v.as_array())
+            .map(|p| p.to_vec())
+            .unwrap_or_default();
+        Some(Self::new(code, indent, parameters))
+    }
+
+    /// Convert to an `RPG::EventCommand` object
+    pub fn to_value(&self) -> Value {
+        Value::Object {
+            class: Symbol::new(EVENT_COMMAND_CLASS),
+            ivars: vec![
+                (Symbol::new("@code"), Value::Fixnum(self.code as i64)),
+                (Symbol::new("@indent"), Value::Fixnum(self.indent as i64)),
+                (
+                    Symbol::new("@parameters"),
+                    Value::Array(self.parameters.clone()),
+                ),
+            ],
+        }
+    }
+
+    /// Get the event code as enum
+    pub fn event_code(&self) -> EventCode {
+        EventCode::from(self.code)
+    }
+
+    /// Get a string parameter at index
+    pub fn get_string_param(&self, index: usize) -> Option<&str> {
+        self.parameters.get(index)?.as_str()
+    }
+
+    /// Set a string parameter at index, keeping its encoding
+    pub fn set_string_param(&mut self, index: usize, value: &str) -> bool {
+        match self
+            .parameters
+            .get_mut(index)
+            .and_then(|v| v.as_string_mut())
+        {
+            Some(string) => {
+                string.set_str(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get choices array from a 102 command
+    /// Format: [["choice1", "choice2", ...], cancelType]
+    pub fn get_choices(&self) -> Option<Vec<&str>> {
+        if self.code != 102 {
+            return None;
+        }
+        self.parameters
+            .first()?
+            .as_array()?
+            .iter()
+            .map(|v| v.as_str())
+            .collect()
+    }
+
+    /// Get choice text from a 402 (When [Choice]) command
+    /// Format: [choiceIndex, "choice text"]
+    pub fn get_choice_text(&self) -> Option<&str> {
+        if self.code != 402 {
+            return None;
+        }
+        self.get_string_param(1)
+    }
+}
+
+/// Create a string value holding text
+///
+/// The string takes the encoding of `template`, the string it replaces.
+/// Without one, the version decides: RPG Maker XP and VX run on Ruby 1.8,
+/// which writes strings without encoding, while VX Ace writes UTF-8.
+pub fn text_value(version: RpgMakerVersion, template: Option<&Value>, text: &str) -> Value {
+    let ivars = match template.and_then(|v| v.as_string()) {
+        Some(template) => template.ivars.clone(),
+        None if version == RpgMakerVersion::VXAce => RString::new("").ivars,
+        None => Vec::new(),
+    };
+    Value::String(RString {
+        bytes: text.as_bytes().to_vec(),
+        ivars,
+    })
+}
+
+/// Parse the commands of a command list, one per item
+///
+/// Items that are not event commands become code 0 (end of list), which
+/// no handler processes.
+pub fn parse_commands(list: &Value) -> Vec<EventCommand> {
+    list.as_array()
+        .map(|arr| {
+            arr.iter()
+                .map(|v| {
+                    EventCommand::from_value(v)
+                        .unwrap_or_else(|| EventCommand::new(0, 0, Vec::new()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_text() {
+        let command = EventCommand::new(
+            102,
+            1,
+            vec![
+                Value::Array(vec![
+                    Value::String(RString::binary("はい".as_bytes().to_vec())),
+                    Value::string("いいえ"),
+                ]),
+                Value::Fixnum(2),
+            ],
+        );
+        let value = command.to_value();
+        assert_eq!(value.class_name(), Some(EVENT_COMMAND_CLASS));
+        assert_eq!(EventCommand::from_value(&value), Some(command.clone()));
+        assert_eq!(command.get_choices(), Some(vec!["はい", "いいえ"]));
+
+        let template = Value::string("x");
+        assert_eq!(
+            text_value(RpgMakerVersion::XP, Some(&template), "y"),
+            Value::string("y")
+        );
+        assert_eq!(
+            text_value(RpgMakerVersion::XP, None, "y"),
+            Value::String(RString::binary(b"y".to_vec()))
+        );
+        assert_eq!(
+            text_value(RpgMakerVersion::VXAce, None, "y"),
+            Value::string("y")
+        );
+        assert_eq!(parse_commands(&Value::Array(vec![Value::Nil]))[0].code, 0);
+    }
+}
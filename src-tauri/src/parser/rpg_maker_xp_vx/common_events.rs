@@ -0,0 +1,273 @@
+//! Parser for CommonEvents.rxdata / .rvdata / .rvdata2 files
+//!
+//! The file holds an array of `RPG::CommonEvent` objects (the first is
+//! `nil`), each with a list of commands that can be extracted for
+//! translation.
+
+use super::event_page::EventPageParser;
+use super::{is_data_file, XpVxError};
+use crate::archiver::vfs::GameVfs;
+use crate::parser::marshal::{self, ivar_index, Value};
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile, TranslationPath,
+};
+use crate::types::engine::RpgMakerVersion;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Parser for common event data files
+pub struct CommonEventsParser {
+    /// Event page parser
+    page_parser: EventPageParser,
+}
+
+impl CommonEventsParser {
+    /// Create a new CommonEvents parser for a version
+    pub fn new(version: RpgMakerVersion) -> Self {
+        Self {
+            page_parser: EventPageParser::new(version),
+        }
+    }
+
+    /// Create with a custom page parser
+    pub fn with_page_parser(page_parser: EventPageParser) -> Self {
+        Self { page_parser }
+    }
+
+    /// Extract translations from loaded common event data
+    pub fn extract(
+        &self,
+        data: &Value,
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> FileExtractionResult {
+        let mut result = FileExtractionResult::new(file_name);
+
+        let events = match data.as_array() {
+            Some(arr) => arr,
+            None => {
+                result.add_warning("Common events data is not an array");
+                return result;
+            }
+        };
+
+        for (event_idx, event) in events.iter().enumerate() {
+            if event.is_nil() {
+                continue;
+            }
+
+            let event_id = event
+                .ivar("@id")
+                .and_then(|v| v.as_i64())
+                .map(|id| id as usize)
+                .unwrap_or(event_idx);
+
+            let mut context = ExtractionContext::new(file_name)
+                .with_event_id(event_id)
+                .with_max_preceding_lines(options.max_preceding_lines);
+
+            if let Some(name) = event.ivar("@name").and_then(|v| v.as_str()) {
+                context = context.with_event_name(name.to_string());
+            }
+
+            if let Some(list) = event.ivar("@list") {
+                let event_path = TranslationPath::new().append_index(event_idx);
+                let units =
+                    self.page_parser
+                        .extract_from_list(list, &event_path, &mut context, options);
+                result.add_units(units);
+            }
+        }
+
+        result
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, XpVxError> {
+        let data = marshal::load_file(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("CommonEvents.rxdata");
+
+        Ok(self.extract(&data, file_name, options))
+    }
+
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside a mounted archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, XpVxError> {
+        let data = marshal::load(&vfs.read(path)?)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("CommonEvents.rxdata");
+
+        Ok(self.extract(&data, file_name, options))
+    }
+
+    /// Inject translations back into loaded common event data
+    pub fn inject(
+        &self,
+        data: &mut Value,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> FileInjectionResult {
+        let mut result = FileInjectionResult::new();
+        let mut splices = Vec::new();
+
+        let events = match data.as_array_mut() {
+            Some(arr) => arr,
+            None => {
+                result
+                    .warnings
+                    .push("Common events data is not an array".to_string());
+                return result;
+            }
+        };
+
+        // The array is the first entry of the object table
+        let mut index = 1;
+        for (event_idx, event) in events.iter_mut().enumerate() {
+            let event_id = event
+                .ivar("@id")
+                .and_then(|v| v.as_i64())
+                .map(|id| id as usize)
+                .unwrap_or(event_idx);
+
+            let context = ExtractionContext::new("CommonEvents").with_event_id(event_id);
+
+            let list_index = ivar_index(event, index, "@list");
+            if let (Some(list_index), Some(list)) = (list_index, event.ivar_mut("@list")) {
+                let event_path = TranslationPath::new().append_index(event_idx);
+                let inject_result = self.page_parser.inject_to_list(
+                    list,
+                    list_index,
+                    translations,
+                    &event_path,
+                    &context,
+                    options,
+                    &mut splices,
+                );
+                result.merge(inject_result);
+            }
+
+            index += event.object_count();
+        }
+
+        marshal::relink(data, &splices);
+        result
+    }
+
+    /// Inject translations to a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, XpVxError> {
+        let mut data = marshal::load_file(path)?;
+
+        let result = self.inject(&mut data, translations, options);
+
+        if result.modified {
+            fs::write(path, marshal::dump(&data))?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+/// Check if a file is a common events file
+pub fn is_common_events_file(path: &Path) -> bool {
+    is_data_file(path, "CommonEvents")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::marshal::Symbol;
+    use crate::parser::rpg_maker_xp_vx::command::EventCommand;
+
+    fn make_common_events() -> Value {
+        let list = [
+            EventCommand::new(
+                101,
+                0,
+                vec![
+                    Value::string("Actor1"),
+                    Value::Fixnum(0),
+                    Value::Fixnum(0),
+                    Value::Fixnum(2),
+                ],
+            ),
+            EventCommand::new(401, 0, vec![Value::string("ようこそ")]),
+            EventCommand::new(405, 0, vec![Value::string("昔々")]),
+            EventCommand::new(0, 0, Vec::new()),
+        ];
+        let event = Value::Object {
+            class: Symbol::new("RPG::CommonEvent"),
+            ivars: vec![
+                (Symbol::new("@id"), Value::Fixnum(1)),
+                (Symbol::new("@name"), Value::string("挨拶")),
+                (
+                    Symbol::new("@list"),
+                    Value::Array(list.iter().map(EventCommand::to_value).collect()),
+                ),
+            ],
+        };
+        Value::Array(vec![Value::Nil, event])
+    }
+
+    #[test]
+    fn test_extract_and_inject_common_events() {
+        let data = make_common_events();
+
+        // Scrolling text is new in VX Ace
+        let vx = CommonEventsParser::new(RpgMakerVersion::VX);
+        let result = vx.extract(&data, "CommonEvents.rvdata", &ExtractionOptions::default());
+        assert_eq!(result.units.len(), 1);
+
+        let parser = CommonEventsParser::new(RpgMakerVersion::VXAce);
+        let result = parser.extract(&data, "CommonEvents.rvdata2", &ExtractionOptions::default());
+        let ids: Vec<&str> = result.units.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(ids, ["1.list.1_dialogue", "1.list.2_scrolling"]);
+
+        let mut translations = HashMap::new();
+        translations.insert("1.list.1_dialogue".to_string(), "Welcome".to_string());
+        translations.insert(
+            "1.list.2_scrolling".to_string(),
+            "Once upon\na time".to_string(),
+        );
+
+        let mut data = data;
+        let result = parser.inject(&mut data, &translations, &InjectionOptions::default());
+        assert_eq!(result.applied, 2);
+
+        let data = marshal::load(&marshal::dump(&data)).unwrap();
+        let list = data.as_array().unwrap()[1].ivar("@list").unwrap();
+        let commands = super::super::command::parse_commands(list);
+        assert_eq!(commands.len(), 5);
+        assert_eq!(commands[0].get_string_param(0), Some("Actor1"));
+        assert_eq!(commands[1].parameters[0], Value::string("Welcome"));
+        assert_eq!(commands[3].code, 405);
+        assert_eq!(commands[3].get_string_param(0), Some("a time"));
+    }
+}
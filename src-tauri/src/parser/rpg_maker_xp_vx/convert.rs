@@ -0,0 +1,222 @@
+//! Conversion of a Data folder to JSON and back
+//!
+//! Each data file (`Map001.rxdata`, `Actors.rvdata2`, ...) becomes a
+//! pretty-printed JSON file of the same name (`Map001.json`), in the form
+//! of [`marshal::json`](crate::parser::marshal::json), so that games can be
+//! kept under version control with text diffs. Converting back gives the
+//! original files byte for byte, which is checked for every file before
+//! it is written. Every file of a folder is converted before any output is
+//! written, so one bad file leaves the output folder untouched.
+
+use super::{data_extension, XpVxError};
+use crate::parser::marshal::{self, StringEncoding};
+use crate::types::engine::RpgMakerVersion;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Encoding of the text of a version's data
+///
+/// RPG Maker XP and VX run on Ruby 1.8, whose strings have no encoding.
+pub fn text_encoding(version: RpgMakerVersion) -> StringEncoding {
+    match version {
+        RpgMakerVersion::VXAce => StringEncoding::Utf8,
+        _ => StringEncoding::None,
+    }
+}
+
+/// Files of a directory with an extension, sorted by name
+fn files_with_extension(dir: &Path, extension: &str) -> Result<Vec<PathBuf>, XpVxError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let matches = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case(extension));
+        if matches && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Name of a file with its extension replaced, keeping any other dots
+/// (`Scripts.bak.rvdata2` becomes `Scripts.bak.json`)
+fn renamed(path: &Path, extension: &str) -> OsString {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    name
+}
+
+fn unsupported(version: RpgMakerVersion) -> XpVxError {
+    XpVxError::InvalidStructure(format!("{:?} data is not Marshal data", version))
+}
+
+/// Convert one data file to JSON
+///
+/// Fails if the JSON would not convert back to the same bytes.
+pub fn data_to_json(data: &[u8], version: RpgMakerVersion) -> Result<String, XpVxError> {
+    let encoding = text_encoding(version);
+    let json = marshal::to_json(&marshal::load(data)?, &encoding);
+
+    if marshal::dump(&marshal::from_json(&json, &encoding)?) != data {
+        return Err(XpVxError::InvalidStructure(
+            "Data cannot be converted to JSON without loss".to_string(),
+        ));
+    }
+
+    Ok(json)
+}
+
+/// Convert one JSON file back to data
+pub fn json_to_data(json: &str, version: RpgMakerVersion) -> Result<Vec<u8>, XpVxError> {
+    Ok(marshal::dump(&marshal::from_json(
+        json,
+        &text_encoding(version),
+    )?))
+}
+
+/// Convert every data file of a Data folder to a JSON file
+///
+/// Returns the number of converted files.
+pub fn convert_data_dir_to_json<P: AsRef<Path>, Q: AsRef<Path>>(
+    data_dir: P,
+    json_dir: Q,
+    version: RpgMakerVersion,
+) -> Result<usize, XpVxError> {
+    let extension = data_extension(version).ok_or_else(|| unsupported(version))?;
+    let json_dir = json_dir.as_ref();
+    fs::create_dir_all(json_dir)?;
+
+    let files = files_with_extension(data_dir.as_ref(), extension)?;
+    let mut outputs = Vec::with_capacity(files.len());
+    for path in &files {
+        let json = data_to_json(&fs::read(path)?, version)
+            .map_err(|e| XpVxError::InvalidStructure(format!("{}: {}", path.display(), e)))?;
+        outputs.push((json_dir.join(renamed(path, "json")), json));
+    }
+
+    for (path, json) in &outputs {
+        fs::write(path, json)?;
+    }
+
+    Ok(outputs.len())
+}
+
+/// Convert every JSON file of a folder back to a data file
+///
+/// Returns the number of converted files.
+pub fn convert_json_dir_to_data<P: AsRef<Path>, Q: AsRef<Path>>(
+    json_dir: P,
+    data_dir: Q,
+    version: RpgMakerVersion,
+) -> Result<usize, XpVxError> {
+    let extension = data_extension(version).ok_or_else(|| unsupported(version))?;
+    let data_dir = data_dir.as_ref();
+    fs::create_dir_all(data_dir)?;
+
+    let files = files_with_extension(json_dir.as_ref(), "json")?;
+    let mut outputs = Vec::with_capacity(files.len());
+    for path in &files {
+        let data = json_to_data(&fs::read_to_string(path)?, version)
+            .map_err(|e| XpVxError::InvalidStructure(format!("{}: {}", path.display(), e)))?;
+        outputs.push((data_dir.join(renamed(path, extension)), data));
+    }
+
+    for (path, data) in &outputs {
+        fs::write(path, data)?;
+    }
+
+    Ok(outputs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::marshal::{RString, Symbol, Value};
+
+    #[test]
+    fn test_convert_data_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("Data");
+        let json_dir = dir.path().join("json");
+        fs::create_dir(&data_dir).unwrap();
+
+        let map_infos = Value::Hash {
+            entries: vec![(
+                Value::Fixnum(1),
+                Value::Object {
+                    class: Symbol::new("RPG::MapInfo"),
+                    ivars: vec![(Symbol::new("@name"), Value::string("はじまりの村"))],
+                },
+            )],
+            default: None,
+        };
+        let original = marshal::dump(&map_infos);
+        fs::write(data_dir.join("MapInfos.rvdata2"), &original).unwrap();
+        fs::write(data_dir.join("Notes.txt"), "not data").unwrap();
+
+        let count = convert_data_dir_to_json(&data_dir, &json_dir, RpgMakerVersion::VXAce).unwrap();
+        assert_eq!(count, 1);
+        let json = fs::read_to_string(json_dir.join("MapInfos.json")).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[1]["name"], "はじまりの村");
+
+        fs::remove_file(data_dir.join("MapInfos.rvdata2")).unwrap();
+        let count = convert_json_dir_to_data(&json_dir, &data_dir, RpgMakerVersion::VXAce).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            fs::read(data_dir.join("MapInfos.rvdata2")).unwrap(),
+            original
+        );
+
+        // XP text has no encoding, so VX Ace strings are tagged
+        let json = data_to_json(&original, RpgMakerVersion::XP).unwrap();
+        assert!(json.contains("\"$type\": \"String\""));
+        let xp = marshal::dump(&Value::String(RString::binary(b"x".to_vec())));
+        assert_eq!(data_to_json(&xp, RpgMakerVersion::XP).unwrap(), "\"x\"");
+        assert!(convert_data_dir_to_json(&data_dir, &json_dir, RpgMakerVersion::MV).is_err());
+    }
+
+    #[test]
+    fn test_convert_keeps_dotted_names_and_stops_on_bad_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("Data");
+        let json_dir = dir.path().join("json");
+        fs::create_dir(&data_dir).unwrap();
+
+        let scripts = marshal::dump(&Value::Array(vec![]));
+        let backup = marshal::dump(&Value::Array(vec![Value::Fixnum(1)]));
+        fs::write(data_dir.join("Scripts.rvdata2"), &scripts).unwrap();
+        fs::write(data_dir.join("Scripts.bak.rvdata2"), &backup).unwrap();
+
+        let count = convert_data_dir_to_json(&data_dir, &json_dir, RpgMakerVersion::VXAce).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            fs::read_to_string(json_dir.join("Scripts.json")).unwrap(),
+            "[]"
+        );
+        assert!(json_dir.join("Scripts.bak.json").is_file());
+
+        let count = convert_json_dir_to_data(&json_dir, &data_dir, RpgMakerVersion::VXAce).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(fs::read(data_dir.join("Scripts.rvdata2")).unwrap(), scripts);
+        assert_eq!(
+            fs::read(data_dir.join("Scripts.bak.rvdata2")).unwrap(),
+            backup
+        );
+
+        // A bad file stops the import before anything is written
+        fs::write(json_dir.join("Scripts.bak.json"), "[1, 2]").unwrap();
+        fs::write(json_dir.join("Zzz.json"), "{ not json").unwrap();
+        assert!(convert_json_dir_to_data(&json_dir, &data_dir, RpgMakerVersion::VXAce).is_err());
+        assert_eq!(
+            fs::read(data_dir.join("Scripts.bak.rvdata2")).unwrap(),
+            backup
+        );
+        assert!(!data_dir.join("Zzz.rvdata2").exists());
+    }
+}
@@ -0,0 +1,355 @@
+//! Event page parsing logic
+//!
+//! This module provides the core logic for extracting and injecting
+//! translations from the command lists of `RPG::Event::Page`,
+//! `RPG::CommonEvent` and `RPG::Troop::Page` objects.
+//!
+//! Injection takes the Marshal object table index of the value it edits
+//! and records a [`Splice`] whenever replaced commands hold a different
+//! number of objects, so the file parsers can [`relink`] the data before
+//! writing it.
+//!
+//! [`relink`]: crate::parser::marshal::relink
+
+use super::command::{parse_commands, EventCommand};
+use super::handlers::{CommandInjection, HandlerRegistry};
+use crate::parser::marshal::{ivar_index, Splice, Value};
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, InjectionOptions, InjectionResult,
+    TranslationPath, TranslationUnit,
+};
+use crate::types::engine::RpgMakerVersion;
+use std::collections::HashMap;
+
+/// Parser for event pages (command lists)
+pub struct EventPageParser {
+    /// Handler registry
+    handlers: HandlerRegistry,
+}
+
+impl EventPageParser {
+    /// Create a new event page parser for a version
+    pub fn new(version: RpgMakerVersion) -> Self {
+        Self {
+            handlers: HandlerRegistry::for_version(version),
+        }
+    }
+
+    /// Create with a custom handler registry
+    pub fn with_handlers(handlers: HandlerRegistry) -> Self {
+        Self { handlers }
+    }
+
+    /// Extract translation units from a command list
+    pub fn extract_from_list(
+        &self,
+        list: &Value,
+        path_prefix: &TranslationPath,
+        context: &mut ExtractionContext,
+        options: &ExtractionOptions,
+    ) -> Vec<TranslationUnit> {
+        let commands = parse_commands(list);
+        self.extract_from_commands(&commands, path_prefix, context, options)
+    }
+
+    /// Extract translation units from parsed commands
+    pub fn extract_from_commands(
+        &self,
+        commands: &[EventCommand],
+        path_prefix: &TranslationPath,
+        context: &mut ExtractionContext,
+        options: &ExtractionOptions,
+    ) -> Vec<TranslationUnit> {
+        let mut units = Vec::new();
+        let list_path = path_prefix.append_key("list");
+        let mut index = 0;
+
+        while index < commands.len() {
+            let code = EventCode::from(commands[index].code);
+
+            if let Some(handler) = self.handlers.get(code) {
+                let result = handler.extract(commands, index, &list_path, context, options);
+
+                // Update speaker if the handler provided one
+                if let Some(speaker) = result.speaker_update {
+                    context.set_speaker(speaker);
+                }
+
+                units.extend(result.units);
+
+                if let Some(text) = result.add_to_preceding {
+                    context.add_preceding_line(text);
+                }
+
+                index += result.consumed.max(1);
+            } else {
+                index += 1;
+            }
+        }
+
+        units
+    }
+
+    /// Inject translations into a command list
+    ///
+    /// `table_index` is the object table index of the list.
+    #[allow(clippy::too_many_arguments)]
+    pub fn inject_to_list(
+        &self,
+        list: &mut Value,
+        table_index: usize,
+        translations: &HashMap<String, String>,
+        path_prefix: &TranslationPath,
+        context: &ExtractionContext,
+        options: &InjectionOptions,
+        splices: &mut Vec<Splice>,
+    ) -> InjectionResult {
+        let mut result = InjectionResult::new();
+        let commands = parse_commands(list);
+        let Some(items) = list.as_array_mut() else {
+            return result;
+        };
+        let list_path = path_prefix.append_key("list");
+
+        // Rebuild the list, keeping the original objects of unchanged commands
+        let mut old_items = std::mem::take(items).into_iter();
+        let mut next_index = table_index + 1;
+        let mut index = 0;
+
+        while index < commands.len() {
+            let code = EventCode::from(commands[index].code);
+            let injection = match self.handlers.get(code) {
+                Some(handler) => {
+                    handler.inject(&commands, index, translations, &list_path, context, options)
+                }
+                None => CommandInjection::skip(1),
+            };
+            let consumed = injection.consumed.clamp(1, commands.len() - index);
+            result.merge(injection.result);
+
+            let old: Vec<Value> = old_items.by_ref().take(consumed).collect();
+            let removed: usize = old.iter().map(Value::object_count).sum();
+            match injection.replacement {
+                Some(replacement) => {
+                    let new: Vec<Value> = replacement.iter().map(EventCommand::to_value).collect();
+                    let inserted = new.iter().map(Value::object_count).sum();
+                    if removed != inserted {
+                        splices.push(Splice {
+                            at: next_index,
+                            removed,
+                            inserted,
+                        });
+                    }
+                    next_index += inserted;
+                    items.extend(new);
+                }
+                None => {
+                    next_index += removed;
+                    items.extend(old);
+                }
+            }
+
+            index += consumed;
+        }
+
+        result
+    }
+
+    /// Extract from multiple pages
+    pub fn extract_from_pages(
+        &self,
+        pages: &Value,
+        path_prefix: &TranslationPath,
+        base_context: &ExtractionContext,
+        options: &ExtractionOptions,
+    ) -> Vec<TranslationUnit> {
+        let mut units = Vec::new();
+
+        if let Some(pages_arr) = pages.as_array() {
+            for (page_idx, page) in pages_arr.iter().enumerate() {
+                if page.is_nil() {
+                    continue;
+                }
+
+                let page_path = path_prefix.append_key("pages").append_index(page_idx);
+                let mut context = base_context.for_page(page_idx);
+
+                if let Some(list) = page.ivar("@list") {
+                    let page_units =
+                        self.extract_from_list(list, &page_path, &mut context, options);
+                    units.extend(page_units);
+                }
+            }
+        }
+
+        units
+    }
+
+    /// Inject into multiple pages
+    ///
+    /// `table_index` is the object table index of the pages array.
+    #[allow(clippy::too_many_arguments)]
+    pub fn inject_to_pages(
+        &self,
+        pages: &mut Value,
+        table_index: usize,
+        translations: &HashMap<String, String>,
+        path_prefix: &TranslationPath,
+        base_context: &ExtractionContext,
+        options: &InjectionOptions,
+        splices: &mut Vec<Splice>,
+    ) -> InjectionResult {
+        let mut result = InjectionResult::new();
+
+        if let Some(pages_arr) = pages.as_array_mut() {
+            let mut page_index = table_index + 1;
+            for (page_idx, page) in pages_arr.iter_mut().enumerate() {
+                let page_path = path_prefix.append_key("pages").append_index(page_idx);
+                let context = base_context.for_page(page_idx);
+
+                if let Some(list_index) = ivar_index(page, page_index, "@list") {
+                    if let Some(list) = page.ivar_mut("@list") {
+                        let page_result = self.inject_to_list(
+                            list,
+                            list_index,
+                            translations,
+                            &page_path,
+                            &context,
+                            options,
+                            splices,
+                        );
+                        result.merge(page_result);
+                    }
+                }
+
+                page_index += page.object_count();
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::marshal::{dump, load, relink, Symbol};
+
+    fn make_command(code: i32, parameters: Vec<Value>) -> Value {
+        EventCommand::new(code, 0, parameters).to_value()
+    }
+
+    /// VX Ace pages whose move command is shared by a later 509 command
+    fn make_pages() -> Value {
+        let move_command = Value::Object {
+            class: Symbol::new("RPG::MoveCommand"),
+            ivars: vec![
+                (Symbol::new("@code"), Value::Fixnum(1)),
+                (Symbol::new("@parameters"), Value::Array(Vec::new())),
+            ],
+        };
+        let mut list = vec![
+            make_command(101, vec![Value::string(""), Value::Fixnum(0)]),
+            make_command(401, vec![Value::string("一行目")]),
+            make_command(401, vec![Value::string("二行目")]),
+            make_command(209, vec![Value::Fixnum(0), move_command]),
+        ];
+
+        // pages, page and list, the commands before 209, then the 209
+        // object and its parameters array
+        let target = 3 + list[..3].iter().map(Value::object_count).sum::<usize>() + 2;
+        list.push(make_command(509, vec![Value::Link(target)]));
+        list.push(make_command(0, Vec::new()));
+
+        Value::Array(vec![Value::Object {
+            class: Symbol::new("RPG::Event::Page"),
+            ivars: vec![(Symbol::new("@list"), Value::Array(list))],
+        }])
+    }
+
+    /// Class of the object a 509 command of a page links to
+    fn linked_class(pages: &Value) -> Option<String> {
+        let loaded = load(&dump(pages)).unwrap();
+        let list = loaded.as_array()?[0].ivar("@list")?;
+        let commands = parse_commands(list);
+        let move_index = commands.iter().position(|c| c.code == 509)?;
+        let Value::Link(target) = commands[move_index].parameters[0] else {
+            return None;
+        };
+
+        let mut index = 0;
+        let mut found = None;
+        find_object(&loaded, &mut index, target, &mut found);
+        found.and_then(|v| v.class_name()).map(|s| s.to_string())
+    }
+
+    #[test]
+    fn test_extract_and_inject_keep_links() {
+        let parser = EventPageParser::new(RpgMakerVersion::VXAce);
+        let mut pages = make_pages();
+        let context = ExtractionContext::new("Map001.rvdata2");
+        assert_eq!(linked_class(&pages).as_deref(), Some("RPG::MoveCommand"));
+
+        let units = parser.extract_from_pages(
+            &pages,
+            &TranslationPath::new(),
+            &context,
+            &ExtractionOptions::default(),
+        );
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].id, "pages.0.list.1_dialogue");
+        assert_eq!(units[0].original, "一行目\n二行目");
+
+        let mut translations = HashMap::new();
+        translations.insert(units[0].id.clone(), "One\nTwo\nThree".to_string());
+
+        let mut splices = Vec::new();
+        let result = parser.inject_to_pages(
+            &mut pages,
+            0,
+            &translations,
+            &TranslationPath::new(),
+            &context,
+            &InjectionOptions::default(),
+            &mut splices,
+        );
+        assert_eq!(result.applied, 1);
+        assert_eq!(splices.len(), 1);
+        relink(&mut pages, &splices);
+
+        let list = pages.as_array().unwrap()[0].ivar("@list").unwrap();
+        let commands = parse_commands(list);
+        assert_eq!(commands.len(), 7);
+        assert_eq!(commands[3].get_string_param(0), Some("Three"));
+        assert_eq!(linked_class(&pages).as_deref(), Some("RPG::MoveCommand"));
+    }
+
+    /// Find the object at a table index
+    fn find_object<'a>(
+        value: &'a Value,
+        index: &mut usize,
+        target: usize,
+        found: &mut Option<&'a Value>,
+    ) {
+        if value.object_count() == 0 {
+            return;
+        }
+        if *index == target {
+            *found = Some(value);
+        }
+        *index += 1;
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    find_object(item, index, target, found);
+                }
+            }
+            Value::Object { ivars, .. } => {
+                for (_, item) in ivars {
+                    find_object(item, index, target, found);
+                }
+            }
+            _ => *index += value.object_count() - 1,
+        }
+    }
+}
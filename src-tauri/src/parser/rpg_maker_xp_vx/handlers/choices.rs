@@ -0,0 +1,249 @@
+//! Choice handlers for Show Choices (102) and When [Choice] (402) commands
+
+use super::{generate_unit_id, CommandHandler, CommandInjection};
+use crate::parser::rpg_maker_xp_vx::command::{text_value, EventCommand};
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, ExtractionResult, InjectionOptions,
+    TranslationPath, TranslationUnit,
+};
+use crate::types::engine::RpgMakerVersion;
+use std::collections::HashMap;
+
+/// Handler for Show Choices command (102)
+/// Extracts all choice options from the choices array
+#[derive(Debug, Clone)]
+pub struct ChoicesHandler {
+    version: RpgMakerVersion,
+}
+
+impl ChoicesHandler {
+    /// Create a new choices handler
+    pub fn new(version: RpgMakerVersion) -> Self {
+        Self { version }
+    }
+}
+
+impl CommandHandler for ChoicesHandler {
+    fn handles(&self) -> Vec<EventCode> {
+        vec![EventCode::ShowChoices]
+    }
+
+    fn extract(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        path_prefix: &TranslationPath,
+        context: &mut ExtractionContext,
+        options: &ExtractionOptions,
+    ) -> ExtractionResult {
+        let cmd = &commands[index];
+
+        // Get choices array
+        let choices = match cmd.get_choices() {
+            Some(c) => c,
+            None => return ExtractionResult::empty(),
+        };
+
+        let mut units = Vec::new();
+        let base_path = path_prefix.append_index(index);
+
+        for (i, choice_text) in choices.iter().enumerate() {
+            // Skip empty choices unless configured to include them
+            if choice_text.trim().is_empty() && !options.include_empty {
+                continue;
+            }
+
+            let text = if options.trim_whitespace {
+                choice_text.trim().to_string()
+            } else {
+                choice_text.to_string()
+            };
+
+            let unit_id = format!("{}_choice_{}", base_path.to_unit_id(""), i);
+            let unit_path = base_path
+                .append_key("parameters")
+                .append_index(0)
+                .append_index(i);
+
+            let unit = TranslationUnit::new(unit_id, unit_path, EventCode::ShowChoices, text)
+                .with_speaker(context.current_speaker.clone())
+                .with_context(context.to_translation_context());
+
+            units.push(unit);
+        }
+
+        ExtractionResult::multiple(units, 1)
+    }
+
+    fn inject(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        translations: &HashMap<String, String>,
+        path_prefix: &TranslationPath,
+        _context: &ExtractionContext,
+        options: &InjectionOptions,
+    ) -> CommandInjection {
+        let mut injection = CommandInjection::skip(1);
+        let mut cmd = commands[index].clone();
+        let base_path = path_prefix.append_index(index);
+
+        let Some(choices) = cmd.parameters.first_mut().and_then(|p| p.as_array_mut()) else {
+            return injection;
+        };
+
+        for (i, choice) in choices.iter_mut().enumerate() {
+            let unit_id = format!("{}_choice_{}", base_path.to_unit_id(""), i);
+            match translations.get(&unit_id) {
+                Some(translated) => {
+                    let text = text_value(self.version, Some(&*choice), translated);
+                    *choice = text;
+                    injection.result.applied += 1;
+                }
+                None => {
+                    let has_text = choice.as_str().is_some_and(|t| !t.trim().is_empty());
+                    if has_text && !options.skip_missing_translations {
+                        injection.result.not_found += 1;
+                        injection
+                            .result
+                            .add_warning(format!("Translation not found for: {}", unit_id));
+                    }
+                }
+            }
+        }
+
+        if injection.result.applied > 0 {
+            injection.result.commands_modified += 1;
+            injection.replacement = Some(vec![cmd]);
+        }
+
+        injection
+    }
+}
+
+/// Handler for When [Choice] command (402)
+/// The branch keeps a copy of the choice text, shown in the editor
+#[derive(Debug, Clone)]
+pub struct ChoiceBranchHandler {
+    version: RpgMakerVersion,
+}
+
+impl ChoiceBranchHandler {
+    /// Create a new choice branch handler
+    pub fn new(version: RpgMakerVersion) -> Self {
+        Self { version }
+    }
+}
+
+impl CommandHandler for ChoiceBranchHandler {
+    fn handles(&self) -> Vec<EventCode> {
+        vec![EventCode::WhenChoice]
+    }
+
+    fn extract(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        path_prefix: &TranslationPath,
+        context: &mut ExtractionContext,
+        options: &ExtractionOptions,
+    ) -> ExtractionResult {
+        let cmd = &commands[index];
+
+        let choice_text = match cmd.get_choice_text() {
+            Some(t) if !t.trim().is_empty() || options.include_empty => t,
+            _ => return ExtractionResult::empty(),
+        };
+
+        let text = if options.trim_whitespace {
+            choice_text.trim().to_string()
+        } else {
+            choice_text.to_string()
+        };
+
+        let unit = TranslationUnit::new(
+            generate_unit_id(path_prefix, index, "choice_branch"),
+            path_prefix.append_index(index),
+            EventCode::WhenChoice,
+            text,
+        )
+        .with_speaker(context.current_speaker.clone())
+        .with_context(context.to_translation_context());
+
+        ExtractionResult::single(unit, 1)
+    }
+
+    fn inject(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        translations: &HashMap<String, String>,
+        path_prefix: &TranslationPath,
+        _context: &ExtractionContext,
+        _options: &InjectionOptions,
+    ) -> CommandInjection {
+        let mut injection = CommandInjection::skip(1);
+        let unit_id = generate_unit_id(path_prefix, index, "choice_branch");
+
+        if let Some(translated) = translations.get(&unit_id) {
+            let mut cmd = commands[index].clone();
+            if let Some(param) = cmd.parameters.get_mut(1) {
+                let text = text_value(self.version, Some(&*param), translated);
+                *param = text;
+                injection.result.applied += 1;
+                injection.result.commands_modified += 1;
+                injection.replacement = Some(vec![cmd]);
+            }
+        }
+
+        injection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::marshal::{RString, Value};
+
+    #[test]
+    fn test_choices_round_trip() {
+        let handler = ChoicesHandler::new(RpgMakerVersion::VX);
+        let binary = |text: &str| Value::String(RString::binary(text.as_bytes().to_vec()));
+        let commands = vec![EventCommand::new(
+            102,
+            0,
+            vec![
+                Value::Array(vec![binary("はい"), binary(""), binary("いいえ")]),
+                Value::Fixnum(2),
+            ],
+        )];
+        let path = TranslationPath::new();
+        let mut context = ExtractionContext::new("Map001.rvdata");
+
+        let result = handler.extract(
+            &commands,
+            0,
+            &path,
+            &mut context,
+            &ExtractionOptions::default(),
+        );
+        let ids: Vec<&str> = result.units.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(ids, ["0_choice_0", "0_choice_2"]);
+
+        let mut translations = HashMap::new();
+        translations.insert("0_choice_2".to_string(), "No".to_string());
+        let injection = handler.inject(
+            &commands,
+            0,
+            &translations,
+            &path,
+            &context,
+            &InjectionOptions::default(),
+        );
+        assert_eq!(injection.result.applied, 1);
+        let cmd = &injection.replacement.unwrap()[0];
+        assert_eq!(cmd.get_choices(), Some(vec!["はい", "", "No"]));
+        assert_eq!(cmd.parameters[0].as_array().unwrap()[2], binary("No"));
+        assert_eq!(cmd.parameters[1], Value::Fixnum(2));
+    }
+}
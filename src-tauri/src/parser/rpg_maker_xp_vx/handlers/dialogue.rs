@@ -0,0 +1,429 @@
+//! Dialogue handlers for Show Text (101), Text Body (401) and
+//! Scrolling Text Body (405) commands
+//!
+//! RPG Maker XP keeps the first line of a message in 101 itself, with the
+//! following lines in 401. VX and VX Ace give 101 the face parameters
+//! (`[faceName, faceIndex, background, position]`) and put every line in
+//! 401.
+
+use super::{generate_unit_id, CommandHandler, CommandInjection};
+use crate::parser::rpg_maker_xp_vx::command::{text_value, EventCommand};
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, ExtractionResult, InjectionOptions,
+    InjectionResult, TranslationPath, TranslationUnit,
+};
+use crate::types::engine::RpgMakerVersion;
+use std::collections::HashMap;
+
+/// Count the consecutive commands with a code and indent, from `start`
+fn run_length(commands: &[EventCommand], start: usize, code: i32, indent: i32) -> usize {
+    commands[start..]
+        .iter()
+        .take_while(|cmd| cmd.code == code && cmd.indent == indent)
+        .count()
+}
+
+/// Extract one unit from the lines of a message
+#[allow(clippy::too_many_arguments)]
+fn extract_lines(
+    commands: &[EventCommand],
+    index: usize,
+    consumed: usize,
+    event_code: EventCode,
+    suffix: &str,
+    path_prefix: &TranslationPath,
+    context: &ExtractionContext,
+    options: &ExtractionOptions,
+) -> ExtractionResult {
+    let lines: Vec<String> = commands[index..index + consumed]
+        .iter()
+        .filter_map(|cmd| cmd.get_string_param(0))
+        .map(|text| {
+            if options.trim_whitespace {
+                text.trim().to_string()
+            } else {
+                text.to_string()
+            }
+        })
+        .collect();
+
+    // Skip if no lines or all empty
+    if lines.is_empty() || (lines.iter().all(|l| l.trim().is_empty()) && !options.include_empty) {
+        return ExtractionResult::skip(consumed);
+    }
+
+    // Merge lines if option is set
+    let merged_text = if options.merge_dialogue_lines {
+        lines.join(&options.dialogue_line_separator)
+    } else {
+        lines.join("\n")
+    };
+
+    let unit = TranslationUnit::new(
+        generate_unit_id(path_prefix, index, suffix),
+        path_prefix.append_index(index),
+        event_code,
+        merged_text.clone(),
+    )
+    .with_speaker(context.current_speaker.clone())
+    .with_context(context.to_translation_context());
+
+    ExtractionResult::single(unit, consumed).with_preceding(merged_text)
+}
+
+/// Replace the lines of a message with its translation
+///
+/// The first new line goes to the first command, which keeps its other
+/// parameters; the others become `body_code` commands.
+#[allow(clippy::too_many_arguments)]
+fn inject_lines(
+    version: RpgMakerVersion,
+    commands: &[EventCommand],
+    index: usize,
+    consumed: usize,
+    body_code: i32,
+    suffix: &str,
+    translations: &HashMap<String, String>,
+    path_prefix: &TranslationPath,
+    options: &InjectionOptions,
+) -> CommandInjection {
+    let mut injection = CommandInjection::skip(consumed);
+    let old = &commands[index..index + consumed];
+    let unit_id = generate_unit_id(path_prefix, index, suffix);
+
+    let Some(translated) = translations.get(&unit_id) else {
+        // Nothing was extracted from messages without text
+        let has_text = old.iter().any(|cmd| {
+            cmd.get_string_param(0)
+                .is_some_and(|t| !t.trim().is_empty())
+        });
+        if has_text && !options.skip_missing_translations {
+            injection.result.not_found += 1;
+            injection
+                .result
+                .add_warning(format!("Translation not found for: {}", unit_id));
+        }
+        return injection;
+    };
+
+    let mut new_lines = options.split_text(translated);
+    if new_lines.is_empty() {
+        new_lines.push(String::new());
+    }
+
+    let template = old[0].parameters.first();
+    let body_template = old
+        .iter()
+        .find(|cmd| cmd.code == body_code)
+        .and_then(|cmd| cmd.parameters.first())
+        .or(template);
+
+    let mut first = old[0].clone();
+    let text = text_value(version, template, &new_lines[0]);
+    match first.parameters.first_mut() {
+        Some(param) => *param = text,
+        None => first.parameters.push(text),
+    }
+
+    let mut replacement = vec![first];
+    replacement.extend(new_lines[1..].iter().map(|line| {
+        EventCommand::new(
+            body_code,
+            old[0].indent,
+            vec![text_value(version, body_template, line)],
+        )
+    }));
+
+    injection.result = InjectionResult {
+        applied: 1,
+        commands_modified: consumed,
+        ..InjectionResult::new()
+    };
+    injection.replacement = Some(replacement);
+    injection
+}
+
+/// Handler for Show Text command (101) in VX and VX Ace
+/// This command only sets up the message window (face, position)
+#[derive(Debug, Clone)]
+pub struct ShowTextHandler;
+
+impl CommandHandler for ShowTextHandler {
+    fn handles(&self) -> Vec<EventCode> {
+        vec![EventCode::ShowText]
+    }
+
+    fn extract(
+        &self,
+        _commands: &[EventCommand],
+        _index: usize,
+        _path_prefix: &TranslationPath,
+        _context: &mut ExtractionContext,
+        _options: &ExtractionOptions,
+    ) -> ExtractionResult {
+        // There is no speaker name before MZ, a new message starts without one
+        ExtractionResult::empty().with_speaker_update(None)
+    }
+
+    fn inject(
+        &self,
+        _commands: &[EventCommand],
+        _index: usize,
+        _translations: &HashMap<String, String>,
+        _path_prefix: &TranslationPath,
+        _context: &ExtractionContext,
+        _options: &InjectionOptions,
+    ) -> CommandInjection {
+        CommandInjection::skip(1)
+    }
+}
+
+/// Handler for Show Text command (101) in XP
+/// The command holds the first line, continued by 401 commands
+#[derive(Debug, Clone)]
+pub struct XpShowTextHandler {
+    version: RpgMakerVersion,
+}
+
+impl XpShowTextHandler {
+    /// Create a new XP show text handler
+    pub fn new(version: RpgMakerVersion) -> Self {
+        Self { version }
+    }
+
+    /// Number of commands of the message at index
+    fn message_length(commands: &[EventCommand], index: usize) -> usize {
+        1 + run_length(commands, index + 1, 401, commands[index].indent)
+    }
+}
+
+impl CommandHandler for XpShowTextHandler {
+    fn handles(&self) -> Vec<EventCode> {
+        vec![EventCode::ShowText]
+    }
+
+    fn extract(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        path_prefix: &TranslationPath,
+        context: &mut ExtractionContext,
+        options: &ExtractionOptions,
+    ) -> ExtractionResult {
+        // There is no speaker name before MZ, a new message starts without one
+        context.set_speaker(None);
+        let consumed = Self::message_length(commands, index);
+        extract_lines(
+            commands,
+            index,
+            consumed,
+            EventCode::ShowTextBody,
+            "dialogue",
+            path_prefix,
+            context,
+            options,
+        )
+    }
+
+    fn inject(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        translations: &HashMap<String, String>,
+        path_prefix: &TranslationPath,
+        _context: &ExtractionContext,
+        options: &InjectionOptions,
+    ) -> CommandInjection {
+        let consumed = Self::message_length(commands, index);
+        inject_lines(
+            self.version,
+            commands,
+            index,
+            consumed,
+            401,
+            "dialogue",
+            translations,
+            path_prefix,
+            options,
+        )
+    }
+}
+
+/// Handler for Text Body (401) and Scrolling Text Body (405) commands
+/// This handles consecutive lines of the same code and indent as one unit
+#[derive(Debug, Clone)]
+pub struct DialogueHandler {
+    version: RpgMakerVersion,
+    /// Code of the lines (401 or 405)
+    code: i32,
+}
+
+impl DialogueHandler {
+    /// Create a handler for message text (401)
+    pub fn new(version: RpgMakerVersion) -> Self {
+        Self { version, code: 401 }
+    }
+
+    /// Create a handler for scrolling text (405)
+    pub fn scrolling(version: RpgMakerVersion) -> Self {
+        Self { version, code: 405 }
+    }
+
+    fn suffix(&self) -> &'static str {
+        if self.code == 405 {
+            "scrolling"
+        } else {
+            "dialogue"
+        }
+    }
+}
+
+impl CommandHandler for DialogueHandler {
+    fn handles(&self) -> Vec<EventCode> {
+        vec![EventCode::from(self.code)]
+    }
+
+    fn extract(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        path_prefix: &TranslationPath,
+        context: &mut ExtractionContext,
+        options: &ExtractionOptions,
+    ) -> ExtractionResult {
+        let consumed = run_length(commands, index, self.code, commands[index].indent);
+        extract_lines(
+            commands,
+            index,
+            consumed,
+            EventCode::from(self.code),
+            self.suffix(),
+            path_prefix,
+            context,
+            options,
+        )
+    }
+
+    fn inject(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        translations: &HashMap<String, String>,
+        path_prefix: &TranslationPath,
+        _context: &ExtractionContext,
+        options: &InjectionOptions,
+    ) -> CommandInjection {
+        let consumed = run_length(commands, index, self.code, commands[index].indent);
+        inject_lines(
+            self.version,
+            commands,
+            index,
+            consumed,
+            self.code,
+            self.suffix(),
+            translations,
+            path_prefix,
+            options,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::marshal::{RString, Value};
+
+    fn make_command(code: i32, indent: i32, text: &str) -> EventCommand {
+        EventCommand::new(
+            code,
+            indent,
+            vec![Value::String(RString::binary(text.as_bytes().to_vec()))],
+        )
+    }
+
+    #[test]
+    fn test_xp_show_text_holds_first_line() {
+        let handler = XpShowTextHandler::new(RpgMakerVersion::XP);
+        let commands = vec![
+            make_command(101, 0, "こんにちは"),
+            make_command(401, 0, "いい天気ですね"),
+            make_command(401, 1, "other"),
+        ];
+        let path = TranslationPath::new().append_key("list");
+        let mut context = ExtractionContext::new("Map001.rxdata");
+        let options = ExtractionOptions::default();
+
+        let result = handler.extract(&commands, 0, &path, &mut context, &options);
+        assert_eq!(result.consumed, 2);
+        assert_eq!(result.units[0].id, "list.0_dialogue");
+        assert_eq!(result.units[0].original, "こんにちは\nいい天気ですね");
+
+        let mut translations = HashMap::new();
+        translations.insert("list.0_dialogue".to_string(), "A\nB\nC".to_string());
+        let injection = handler.inject(
+            &commands,
+            0,
+            &translations,
+            &path,
+            &context,
+            &InjectionOptions::default(),
+        );
+        assert_eq!(injection.consumed, 2);
+        assert_eq!(injection.result.applied, 1);
+        let replacement = injection.replacement.unwrap();
+        assert_eq!(replacement.len(), 3);
+        assert_eq!(replacement[0].code, 101);
+        assert_eq!(replacement[0].get_string_param(0), Some("A"));
+        assert_eq!(replacement[2], make_command(401, 0, "C"));
+    }
+
+    #[test]
+    fn test_vx_dialogue_keeps_face_command() {
+        let handler = DialogueHandler::new(RpgMakerVersion::VXAce);
+        let face = EventCommand::new(
+            101,
+            0,
+            vec![
+                Value::string("Actor1"),
+                Value::Fixnum(0),
+                Value::Fixnum(0),
+                Value::Fixnum(2),
+            ],
+        );
+        let line = |text: &str| EventCommand::new(401, 0, vec![Value::string(text)]);
+        let commands = vec![face, line("一行目"), line("二行目")];
+        let path = TranslationPath::new();
+        let mut context = ExtractionContext::new("CommonEvents.rvdata2");
+
+        let result = handler.extract(
+            &commands,
+            1,
+            &path,
+            &mut context,
+            &ExtractionOptions::default(),
+        );
+        assert_eq!(result.consumed, 2);
+        assert_eq!(result.units[0].id, "1_dialogue");
+
+        let mut translations = HashMap::new();
+        translations.insert("1_dialogue".to_string(), "Only line".to_string());
+        let injection = handler.inject(
+            &commands,
+            1,
+            &translations,
+            &path,
+            &context,
+            &InjectionOptions::default(),
+        );
+        assert_eq!(injection.consumed, 2);
+        assert_eq!(injection.replacement, Some(vec![line("Only line")]));
+
+        let options = InjectionOptions {
+            skip_missing_translations: false,
+            ..InjectionOptions::default()
+        };
+        let missing = handler.inject(&commands, 1, &HashMap::new(), &path, &context, &options);
+        assert_eq!(missing.result.not_found, 1);
+        assert!(missing.replacement.is_none());
+    }
+}
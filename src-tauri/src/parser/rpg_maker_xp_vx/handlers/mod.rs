@@ -0,0 +1,182 @@
+//! Command handlers for RPG Maker XP/VX/VX Ace
+//!
+//! Each handler is responsible for extracting and injecting translations
+//! for specific event command types. Handlers never edit the command list
+//! in place: they return replacement commands, so that the event page
+//! parser can keep unit IDs on the original indices and record how the
+//! Marshal object table changes.
+
+pub mod choices;
+pub mod dialogue;
+
+use super::command::EventCommand;
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, ExtractionResult, InjectionOptions,
+    InjectionResult, TranslationPath,
+};
+use crate::types::engine::RpgMakerVersion;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Result of injecting translations at one command index
+#[derive(Debug, Clone)]
+pub struct CommandInjection {
+    /// Injection statistics
+    pub result: InjectionResult,
+    /// Number of original commands covered
+    pub consumed: usize,
+    /// Commands replacing the covered ones, if any changed
+    pub replacement: Option<Vec<EventCommand>>,
+}
+
+impl CommandInjection {
+    /// Leave commands unchanged
+    pub fn skip(consumed: usize) -> Self {
+        Self {
+            result: InjectionResult::new(),
+            consumed,
+            replacement: None,
+        }
+    }
+}
+
+/// Trait for handling specific event commands
+pub trait CommandHandler: Send + Sync {
+    /// Get the event codes this handler can process
+    fn handles(&self) -> Vec<EventCode>;
+
+    /// Extract translation units from command(s)
+    ///
+    /// # Arguments
+    /// * `commands` - Full list of commands in the page
+    /// * `index` - Current index in the command list
+    /// * `path_prefix` - Path prefix for the current location
+    /// * `context` - Extraction context (speaker, preceding lines, etc.)
+    /// * `options` - Extraction options
+    ///
+    /// # Returns
+    /// ExtractionResult containing extracted units and consumed count
+    fn extract(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        path_prefix: &TranslationPath,
+        context: &mut ExtractionContext,
+        options: &ExtractionOptions,
+    ) -> ExtractionResult;
+
+    /// Inject translations into command(s)
+    ///
+    /// # Arguments
+    /// * `commands` - Full, unmodified list of commands in the page
+    /// * `index` - Current index in the command list
+    /// * `translations` - Map of unit IDs to translated text
+    /// * `path_prefix` - Path prefix for the current location
+    /// * `context` - Extraction context
+    /// * `options` - Injection options
+    ///
+    /// # Returns
+    /// CommandInjection with the commands covered and their replacement
+    fn inject(
+        &self,
+        commands: &[EventCommand],
+        index: usize,
+        translations: &HashMap<String, String>,
+        path_prefix: &TranslationPath,
+        context: &ExtractionContext,
+        options: &InjectionOptions,
+    ) -> CommandInjection;
+}
+
+/// Registry of command handlers
+pub struct HandlerRegistry {
+    handlers: HashMap<EventCode, Arc<dyn CommandHandler>>,
+}
+
+impl HandlerRegistry {
+    /// Create a new empty registry
+    pub fn new() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Create a registry with the default handlers of a version
+    pub fn for_version(version: RpgMakerVersion) -> Self {
+        let mut registry = Self::new();
+
+        // Register dialogue handlers (101, 401)
+        if version == RpgMakerVersion::XP {
+            // XP has no face parameters: 101 holds the first line
+            registry.register_handler(Arc::new(dialogue::XpShowTextHandler::new(version)));
+        } else {
+            registry.register_handler(Arc::new(dialogue::ShowTextHandler));
+            registry.register_handler(Arc::new(dialogue::DialogueHandler::new(version)));
+        }
+
+        // Register scrolling text handler (405), new in VX Ace
+        if version == RpgMakerVersion::VXAce {
+            registry.register_handler(Arc::new(dialogue::DialogueHandler::scrolling(version)));
+        }
+
+        // Register choice handlers (102, 402)
+        registry.register_handler(Arc::new(choices::ChoicesHandler::new(version)));
+        registry.register_handler(Arc::new(choices::ChoiceBranchHandler::new(version)));
+
+        registry
+    }
+
+    /// Register a handler for all its supported codes
+    pub fn register_handler(&mut self, handler: Arc<dyn CommandHandler>) {
+        let codes = handler.handles();
+        for code in codes {
+            self.handlers.insert(code, Arc::clone(&handler));
+        }
+    }
+
+    /// Get a handler for a specific event code
+    pub fn get(&self, code: EventCode) -> Option<&dyn CommandHandler> {
+        self.handlers.get(&code).map(|h| h.as_ref())
+    }
+
+    /// Check if there's a handler for a specific code
+    pub fn has_handler(&self, code: EventCode) -> bool {
+        self.handlers.contains_key(&code)
+    }
+}
+
+impl Default for HandlerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Helper function to generate a unit ID
+pub fn generate_unit_id(path: &TranslationPath, index: usize, suffix: &str) -> String {
+    let base = path.append_index(index);
+    base.to_unit_id(suffix)
+}
+
+pub use choices::{ChoiceBranchHandler, ChoicesHandler};
+pub use dialogue::{DialogueHandler, ShowTextHandler, XpShowTextHandler};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_per_version() {
+        let xp = HandlerRegistry::for_version(RpgMakerVersion::XP);
+        assert!(xp.has_handler(EventCode::ShowText));
+        assert!(!xp.has_handler(EventCode::ShowTextBody));
+        assert!(!xp.has_handler(EventCode::ScrollingTextBody));
+
+        let vx = HandlerRegistry::for_version(RpgMakerVersion::VX);
+        assert!(vx.has_handler(EventCode::ShowTextBody));
+        assert!(!vx.has_handler(EventCode::ScrollingTextBody));
+
+        let vxace = HandlerRegistry::for_version(RpgMakerVersion::VXAce);
+        assert!(vxace.has_handler(EventCode::ScrollingTextBody));
+        assert!(vxace.has_handler(EventCode::WhenChoice));
+    }
+}
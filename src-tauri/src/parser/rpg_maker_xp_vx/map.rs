@@ -0,0 +1,375 @@
+//! Parser for Map*.rxdata / Map*.rvdata / Map*.rvdata2 files
+//!
+//! Map files hold an `RPG::Map` object whose `@events` hash maps event IDs
+//! to `RPG::Event` objects. Each event can have multiple pages with
+//! different conditions and commands.
+
+use super::event_page::EventPageParser;
+use super::{is_data_file, version_from_path, XpVxError};
+use crate::archiver::vfs::GameVfs;
+use crate::parser::marshal::{self, ivar_index, Value};
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile, TranslationPath,
+};
+use crate::types::engine::RpgMakerVersion;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Parser for map data files
+pub struct MapParser {
+    /// Event page parser
+    page_parser: EventPageParser,
+}
+
+impl MapParser {
+    /// Create a new Map parser for a version
+    pub fn new(version: RpgMakerVersion) -> Self {
+        Self {
+            page_parser: EventPageParser::new(version),
+        }
+    }
+
+    /// Create with a custom page parser
+    pub fn with_page_parser(page_parser: EventPageParser) -> Self {
+        Self { page_parser }
+    }
+
+    /// Extract translations from loaded map data
+    pub fn extract(
+        &self,
+        data: &Value,
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> FileExtractionResult {
+        let mut result = FileExtractionResult::new(file_name);
+
+        // Only VX Ace maps have a display name
+        let map_name = data
+            .ivar("@display_name")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .or_else(|| {
+                // Fallback to file name without extension
+                Path::new(file_name)
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .map(|s| s.to_string())
+            });
+
+        let events = match data.ivar("@events") {
+            Some(Value::Hash { entries, .. }) => entries,
+            _ => {
+                result.add_warning("Map file does not contain events hash");
+                return result;
+            }
+        };
+
+        for (key, event) in events {
+            let Some(event_id) = key.as_i64() else {
+                continue;
+            };
+
+            let event_name = event
+                .ivar("@name")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+
+            // Create base context for this event
+            let mut base_context = ExtractionContext::new(file_name)
+                .with_event_id(event_id as usize)
+                .with_max_preceding_lines(options.max_preceding_lines);
+
+            if let Some(name) = &map_name {
+                base_context = base_context.with_map_name(name.clone());
+            }
+
+            if let Some(name) = &event_name {
+                base_context = base_context.with_event_name(name.clone());
+            }
+
+            if let Some(pages) = event.ivar("@pages") {
+                let event_path = TranslationPath::new()
+                    .append_key("events")
+                    .append_index(event_id as usize);
+
+                let units =
+                    self.page_parser
+                        .extract_from_pages(pages, &event_path, &base_context, options);
+                result.add_units(units);
+            }
+        }
+
+        result
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, XpVxError> {
+        let data = marshal::load_file(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Map.rxdata");
+
+        Ok(self.extract(&data, file_name, options))
+    }
+
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside a mounted archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, XpVxError> {
+        let data = marshal::load(&vfs.read(path)?)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("Map.rxdata");
+
+        Ok(self.extract(&data, file_name, options))
+    }
+
+    /// Inject translations back into loaded map data
+    pub fn inject(
+        &self,
+        data: &mut Value,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> FileInjectionResult {
+        let mut result = FileInjectionResult::new();
+        let mut splices = Vec::new();
+
+        // The map object is the first entry of the object table
+        let events_index = ivar_index(data, 0, "@events").unwrap_or_default();
+        let events = match data.ivar_mut("@events") {
+            Some(Value::Hash { entries, .. }) => entries,
+            _ => {
+                result
+                    .warnings
+                    .push("Map file does not contain events hash".to_string());
+                return result;
+            }
+        };
+
+        let mut index = events_index + 1;
+        for (key, event) in events.iter_mut() {
+            index += key.object_count();
+
+            if let Some(event_id) = key.as_i64() {
+                let base_context = ExtractionContext::new("Map").with_event_id(event_id as usize);
+                let event_path = TranslationPath::new()
+                    .append_key("events")
+                    .append_index(event_id as usize);
+
+                let pages_index = ivar_index(event, index, "@pages");
+                if let (Some(pages_index), Some(pages)) = (pages_index, event.ivar_mut("@pages")) {
+                    let inject_result = self.page_parser.inject_to_pages(
+                        pages,
+                        pages_index,
+                        translations,
+                        &event_path,
+                        &base_context,
+                        options,
+                        &mut splices,
+                    );
+                    result.merge(inject_result);
+                }
+            }
+
+            index += event.object_count();
+        }
+
+        marshal::relink(data, &splices);
+        result
+    }
+
+    /// Inject translations to a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, XpVxError> {
+        let mut data = marshal::load_file(path)?;
+
+        let result = self.inject(&mut data, translations, options);
+
+        if result.modified {
+            fs::write(path, marshal::dump(&data))?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+/// Check if a file is a map file
+pub fn is_map_file(path: &Path) -> bool {
+    // Map files are named Map001.rxdata, Map002.rvdata2, etc.
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return false;
+    };
+    stem.strip_prefix("Map")
+        .is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+        && is_data_file(path, stem)
+}
+
+/// Get all map files in a directory of a game's virtual filesystem
+///
+/// Includes maps stored in mounted archives.
+pub fn find_map_files_vfs(vfs: &GameVfs, dir: &str) -> Vec<String> {
+    vfs.list_dir(dir)
+        .into_iter()
+        .filter(|path| is_map_file(Path::new(path)))
+        .collect()
+}
+
+/// Get the version of a game from the map files of its data directory
+pub fn detect_version_vfs(vfs: &GameVfs, dir: &str) -> Option<RpgMakerVersion> {
+    find_map_files_vfs(vfs, dir)
+        .first()
+        .and_then(|path| version_from_path(Path::new(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::marshal::{RString, Symbol};
+    use crate::parser::rpg_maker_xp_vx::command::EventCommand;
+
+    fn text(s: &str) -> Value {
+        Value::String(RString::binary(s.as_bytes().to_vec()))
+    }
+
+    fn object(class: &str, ivars: Vec<(&str, Value)>) -> Value {
+        Value::Object {
+            class: Symbol::new(class),
+            ivars: ivars
+                .into_iter()
+                .map(|(name, value)| (Symbol::new(name), value))
+                .collect(),
+        }
+    }
+
+    /// An RPG Maker XP map with one event of two pages
+    fn make_map() -> Value {
+        let list = |commands: Vec<EventCommand>| {
+            Value::Array(commands.iter().map(EventCommand::to_value).collect())
+        };
+        let page = |list| object("RPG::Event::Page", vec![("@list", list)]);
+        let pages = Value::Array(vec![
+            page(list(vec![
+                EventCommand::new(101, 0, vec![text("こんにちは！")]),
+                EventCommand::new(401, 0, vec![text("良い天気ですね。")]),
+                EventCommand::new(
+                    102,
+                    0,
+                    vec![
+                        Value::Array(vec![text("はい"), text("いいえ")]),
+                        Value::Fixnum(2),
+                    ],
+                ),
+                EventCommand::new(0, 0, Vec::new()),
+            ])),
+            page(list(vec![
+                EventCommand::new(101, 0, vec![text("また会いましたね！")]),
+                EventCommand::new(0, 0, Vec::new()),
+            ])),
+        ]);
+        let event = object(
+            "RPG::Event",
+            vec![
+                ("@id", Value::Fixnum(3)),
+                ("@name", text("村人A")),
+                ("@pages", pages),
+            ],
+        );
+        object(
+            "RPG::Map",
+            vec![
+                ("@bgm", object("RPG::AudioFile", vec![("@name", text(""))])),
+                (
+                    "@events",
+                    Value::Hash {
+                        entries: vec![(Value::Fixnum(3), event)],
+                        default: None,
+                    },
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_extract_map() {
+        let parser = MapParser::new(RpgMakerVersion::XP);
+        let data = marshal::load(&marshal::dump(&make_map())).unwrap();
+        let result = parser.extract(&data, "Map001.rxdata", &ExtractionOptions::default());
+
+        let ids: Vec<&str> = result.units.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "events.3.pages.0.list.0_dialogue",
+                "events.3.pages.0.list.2_choice_0",
+                "events.3.pages.0.list.2_choice_1",
+                "events.3.pages.1.list.0_dialogue",
+            ]
+        );
+        assert_eq!(result.units[0].original, "こんにちは！\n良い天気ですね。");
+    }
+
+    #[test]
+    fn test_inject_map() {
+        let parser = MapParser::new(RpgMakerVersion::XP);
+        let mut data = make_map();
+
+        let mut translations = HashMap::new();
+        translations.insert(
+            "events.3.pages.0.list.0_dialogue".to_string(),
+            "Hello!".to_string(),
+        );
+        translations.insert(
+            "events.3.pages.1.list.0_dialogue".to_string(),
+            "We meet again!\nHow are you?".to_string(),
+        );
+        translations.insert(
+            "events.3.pages.0.list.2_choice_1".to_string(),
+            "No".to_string(),
+        );
+
+        let result = parser.inject(&mut data, &translations, &InjectionOptions::default());
+        assert!(result.modified);
+        assert_eq!(result.applied, 3);
+
+        let data = marshal::load(&marshal::dump(&data)).unwrap();
+        let result = parser.extract(&data, "Map001.rxdata", &ExtractionOptions::default());
+        let texts: Vec<&str> = result.units.iter().map(|u| u.original.as_str()).collect();
+        assert_eq!(
+            texts,
+            ["Hello!", "はい", "No", "We meet again!\nHow are you?"]
+        );
+        assert_eq!(result.units[1].id, "events.3.pages.0.list.1_choice_0");
+    }
+
+    #[test]
+    fn test_is_map_file() {
+        assert!(is_map_file(Path::new("Data/Map001.rxdata")));
+        assert!(is_map_file(Path::new("Data/Map010.rvdata2")));
+        assert!(!is_map_file(Path::new("Data/MapInfos.rxdata")));
+        assert!(!is_map_file(Path::new("data/Map001.json")));
+    }
+}
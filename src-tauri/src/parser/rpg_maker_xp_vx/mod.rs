@@ -0,0 +1,103 @@
+//! RPG Maker XP/VX/VX Ace parser module
+//!
+//! This module provides parsing capabilities for RPG Maker XP, VX and VX Ace
+//! games, whose data files (`.rxdata`, `.rvdata`, `.rvdata2`) are Ruby
+//! Marshal data. Event command lists have the same layout as in MV/MZ, with
+//! per-version differences handled by the command handlers. The game's
+//! Ruby scripts can be unpacked, repacked and have their string literals
+//! translated, and whole Data folders converted to JSON and back.
+
+pub mod command;
+pub mod common_events;
+pub mod convert;
+pub mod event_page;
+pub mod handlers;
+pub mod map;
+pub mod ruby_strings;
+pub mod scripts;
+pub mod troops;
+
+pub use command::*;
+pub use common_events::*;
+pub use convert::*;
+pub use event_page::*;
+pub use handlers::*;
+pub use map::*;
+pub use ruby_strings::*;
+pub use scripts::*;
+pub use troops::*;
+
+use crate::archiver::ArchiverError;
+use crate::parser::marshal::MarshalError;
+use crate::types::engine::RpgMakerVersion;
+use std::path::Path;
+
+/// Error type for RPG Maker XP/VX/VX Ace data
+#[derive(Debug, thiserror::Error)]
+pub enum XpVxError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Marshal error: {0}")]
+    MarshalError(#[from] MarshalError),
+
+    #[error("Archive error: {0}")]
+    ArchiveError(#[from] ArchiverError),
+
+    #[error("Invalid structure: {0}")]
+    InvalidStructure(String),
+}
+
+/// Extension of the data files of a version, without the dot
+pub fn data_extension(version: RpgMakerVersion) -> Option<&'static str> {
+    match version {
+        RpgMakerVersion::XP => Some("rxdata"),
+        RpgMakerVersion::VX => Some("rvdata"),
+        RpgMakerVersion::VXAce => Some("rvdata2"),
+        RpgMakerVersion::MV
+        | RpgMakerVersion::MZ
+        | RpgMakerVersion::Rm2000
+        | RpgMakerVersion::Rm2003 => None,
+    }
+}
+
+/// Get the version from the extension of a data file
+pub fn version_from_path(path: &Path) -> Option<RpgMakerVersion> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "rxdata" => Some(RpgMakerVersion::XP),
+        "rvdata" => Some(RpgMakerVersion::VX),
+        "rvdata2" => Some(RpgMakerVersion::VXAce),
+        _ => None,
+    }
+}
+
+/// Check if a file is a data file named `<name>.<ext>` of any version
+fn is_data_file(path: &Path, name: &str) -> bool {
+    path.file_stem().and_then(|s| s.to_str()) == Some(name) && version_from_path(path).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_from_path() {
+        assert_eq!(
+            version_from_path(Path::new("Data/Map001.rxdata")),
+            Some(RpgMakerVersion::XP)
+        );
+        assert_eq!(
+            version_from_path(Path::new("Data/Troops.rvdata")),
+            Some(RpgMakerVersion::VX)
+        );
+        assert_eq!(
+            version_from_path(Path::new("Data/CommonEvents.rvdata2")),
+            Some(RpgMakerVersion::VXAce)
+        );
+        assert_eq!(version_from_path(Path::new("data/Map001.json")), None);
+        assert_eq!(data_extension(RpgMakerVersion::VXAce), Some("rvdata2"));
+        assert!(is_data_file(Path::new("Troops.rxdata"), "Troops"));
+        assert!(!is_data_file(Path::new("Troops.json"), "Troops"));
+    }
+}
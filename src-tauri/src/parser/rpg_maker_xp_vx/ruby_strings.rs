@@ -0,0 +1,436 @@
+//! Ruby string literal scanner
+//!
+//! Finds the `"..."` and `'...'` literals of Ruby source, with the byte
+//! range of their content, so that text can be replaced without touching
+//! the code around it. The scanner skips what could contain quotes
+//! without being a string: comments, `=begin`/`=end` blocks, heredocs,
+//! regular expressions, `%` literals, character literals (`?"`), quoted
+//! symbols (`:"..."`) and the code of `#{...}` interpolation.
+//!
+//! Ruby's grammar decides between `/` as division or regexp (and `%` as
+//! modulo or literal) from what precedes it; like most highlighters, this
+//! scanner looks at the previous token only.
+
+use std::ops::Range;
+
+/// A string literal in Ruby source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringLiteral {
+    /// Byte range of the content, between the quotes
+    pub range: Range<usize>,
+    /// Quote character (`"` or `'`)
+    pub quote: u8,
+    /// Content as written in the source, escapes included
+    pub text: String,
+}
+
+/// Keywords after which an expression starts
+const KEYWORDS: &[&str] = &[
+    "and", "case", "do", "else", "elsif", "if", "in", "not", "or", "puts", "print", "return",
+    "then", "unless", "until", "when", "while", "yield",
+];
+
+/// Scan the string literals of Ruby source, in source order
+///
+/// Literals inside the interpolation of another literal are not listed.
+pub fn scan_strings(source: &str) -> Vec<StringLiteral> {
+    let mut scanner = Scanner {
+        src: source.as_bytes(),
+        pos: 0,
+        literals: Vec::new(),
+        heredocs: Vec::new(),
+        value_end: false,
+    };
+    scanner.code(false, true);
+    scanner
+        .literals
+        .into_iter()
+        .map(|(range, quote)| StringLiteral {
+            text: source[range.clone()].to_string(),
+            range,
+            quote,
+        })
+        .collect()
+}
+
+/// Escape text to be the content of a literal
+///
+/// Text is kept as written, escapes included; only quotes that would end
+/// the literal and a trailing lone backslash are escaped.
+pub fn escape_literal(text: &str, quote: u8) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut escaped = false;
+    for c in text.chars() {
+        if c == quote as char && !escaped {
+            out.push('\\');
+        }
+        escaped = c == '\\' && !escaped;
+        out.push(c);
+    }
+    if escaped {
+        out.push('\\');
+    }
+    out
+}
+
+/// Heredoc waiting for the end of its line
+struct Heredoc {
+    id: Vec<u8>,
+    /// Whether the terminator may be indented (`<<-` and `<<~`)
+    indented: bool,
+}
+
+struct Scanner<'a> {
+    src: &'a [u8],
+    pos: usize,
+    /// Content range and quote of the literals found
+    literals: Vec<(Range<usize>, u8)>,
+    heredocs: Vec<Heredoc>,
+    /// Whether the previous token ends a value (so `/` divides)
+    value_end: bool,
+}
+
+fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80
+}
+
+/// Closing delimiter of a `%` literal
+fn closing(open: u8) -> u8 {
+    match open {
+        b'(' => b')',
+        b'[' => b']',
+        b'{' => b'}',
+        b'<' => b'>',
+        other => other,
+    }
+}
+
+impl Scanner<'_> {
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.src.get(self.pos + offset).copied()
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.pos == 0 || self.src[self.pos - 1] == b'\n'
+    }
+
+    fn starts_with(&self, text: &[u8]) -> bool {
+        self.src[self.pos..].starts_with(text)
+    }
+
+    fn skip_line(&mut self) {
+        while self.pos < self.src.len() && self.src[self.pos] != b'\n' {
+            self.pos += 1;
+        }
+    }
+
+    /// Whether an expression starts here, rather than an operator
+    fn expects_value(&self) -> bool {
+        !self.value_end
+    }
+
+    /// Scan code, up to the `}` closing an interpolation if `in_braces`
+    fn code(&mut self, in_braces: bool, collect: bool) {
+        let mut depth = 0usize;
+        while self.pos < self.src.len() {
+            if self.at_line_start() {
+                if self.starts_with(b"__END__") {
+                    self.pos = self.src.len();
+                    return;
+                }
+                if self.starts_with(b"=begin") {
+                    while self.pos < self.src.len()
+                        && !(self.at_line_start() && self.starts_with(b"=end"))
+                    {
+                        self.pos += 1;
+                    }
+                    self.skip_line();
+                    continue;
+                }
+            }
+
+            let b = self.src[self.pos];
+            match b {
+                b'\n' => {
+                    self.pos += 1;
+                    self.heredoc_bodies();
+                    continue;
+                }
+                b' ' | b'\t' | b'\r' => {
+                    self.pos += 1;
+                    continue;
+                }
+                b'#' => {
+                    self.skip_line();
+                    continue;
+                }
+                b'{' => depth += 1,
+                b'}' if in_braces && depth == 0 => {
+                    self.pos += 1;
+                    return;
+                }
+                b'}' => depth = depth.saturating_sub(1),
+                b'"' | b'\'' | b'`' => {
+                    let start = self.pos + 1;
+                    self.pos = start;
+                    self.quoted(b, b != b'\'');
+                    if collect && b != b'`' {
+                        self.literals.push((start..self.pos - 1, b));
+                    }
+                    self.value_end = true;
+                    continue;
+                }
+                b':' if matches!(self.peek(1), Some(b'"' | b'\''))
+                    && (self.pos == 0 || self.src[self.pos - 1] != b':') =>
+                {
+                    // Quoted symbol
+                    let quote = self.src[self.pos + 1];
+                    self.pos += 2;
+                    self.quoted(quote, quote == b'"');
+                    self.value_end = true;
+                    continue;
+                }
+                b'?' if self.expects_value() && self.peek(1).is_some_and(|c| c > b' ') => {
+                    // Character literal
+                    self.pos += if self.peek(1) == Some(b'\\') { 3 } else { 2 };
+                    self.value_end = true;
+                    continue;
+                }
+                b'$' => {
+                    // Global variables like $" and $'
+                    self.pos += 2;
+                    self.value_end = true;
+                    continue;
+                }
+                b'/' if self.expects_value() => {
+                    self.pos += 1;
+                    self.regexp(b'/');
+                    self.value_end = true;
+                    continue;
+                }
+                b'%' if (self.expects_value() || self.percent_type().is_some())
+                    && self.percent_literal() =>
+                {
+                    self.value_end = true;
+                    continue;
+                }
+                b'<' if self.starts_with(b"<<") && self.heredoc_start() => {
+                    self.value_end = true;
+                    continue;
+                }
+                _ if is_ident(b) => {
+                    let start = self.pos;
+                    while self.pos < self.src.len() && is_ident(self.src[self.pos]) {
+                        self.pos += 1;
+                    }
+                    // Method names may end with ? or !
+                    if matches!(self.peek(0), Some(b'?' | b'!')) && self.peek(1) != Some(b'=') {
+                        self.pos += 1;
+                    }
+                    let word = &self.src[start..self.pos];
+                    self.value_end = !KEYWORDS.iter().any(|k| k.as_bytes() == word);
+                    continue;
+                }
+                _ => {}
+            }
+
+            self.value_end = matches!(b, b')' | b']' | b'}');
+            self.pos += 1;
+        }
+    }
+
+    /// Skip a quoted string body, after the opening quote
+    fn quoted(&mut self, quote: u8, interpolates: bool) {
+        self.delimited(quote, quote, interpolates);
+    }
+
+    /// Skip a delimited body up to the closing delimiter, nesting pairs
+    fn delimited(&mut self, open: u8, close: u8, interpolates: bool) {
+        let mut depth = 0usize;
+        while self.pos < self.src.len() {
+            let b = self.src[self.pos];
+            self.pos += 1;
+            match b {
+                b'\\' => self.pos += 1,
+                b'#' if interpolates && self.peek(0) == Some(b'{') => {
+                    self.pos += 1;
+                    self.code(true, false);
+                }
+                _ if b == close && depth == 0 => return,
+                _ if b == close => depth -= 1,
+                _ if b == open && open != close => depth += 1,
+                _ => {}
+            }
+        }
+        self.pos = self.pos.min(self.src.len());
+    }
+
+    /// Skip a regexp body and its flags, after the opening delimiter
+    fn regexp(&mut self, close: u8) {
+        let mut in_class = false;
+        while self.pos < self.src.len() {
+            let b = self.src[self.pos];
+            self.pos += 1;
+            match b {
+                b'\\' => self.pos += 1,
+                b'[' => in_class = true,
+                b']' => in_class = false,
+                b'#' if self.peek(0) == Some(b'{') => {
+                    self.pos += 1;
+                    self.code(true, false);
+                }
+                _ if b == close && !in_class => break,
+                _ => {}
+            }
+        }
+        while self.pos < self.src.len() && self.src[self.pos].is_ascii_alphabetic() {
+            self.pos += 1;
+        }
+        self.pos = self.pos.min(self.src.len());
+    }
+
+    /// Type letter of a `%q(...)`-style literal at the current position
+    fn percent_type(&self) -> Option<u8> {
+        let kind = self.peek(1)?;
+        let open = self.peek(2)?;
+        (b"qQwWiIrsx".contains(&kind) && !open.is_ascii_alphanumeric() && open > b' ')
+            .then_some(kind)
+    }
+
+    /// Skip a `%` literal, returning false if `%` is an operator
+    fn percent_literal(&mut self) -> bool {
+        let (kind, open) = match self.percent_type() {
+            Some(kind) => (kind, self.src[self.pos + 2]),
+            None => match self.peek(1) {
+                Some(open) if b"([{<|!/".contains(&open) => (b'Q', open),
+                _ => return false,
+            },
+        };
+        self.pos += if self.percent_type().is_some() { 3 } else { 2 };
+        if kind == b'r' {
+            self.regexp(closing(open));
+        } else {
+            self.delimited(open, closing(open), b"QWIrx".contains(&kind));
+        }
+        true
+    }
+
+    /// Record a heredoc started at the current `<<`, returning false if
+    /// `<<` is an operator
+    fn heredoc_start(&mut self) -> bool {
+        let mut pos = self.pos + 2;
+        let indented = matches!(self.src.get(pos), Some(b'-' | b'~'));
+        if indented {
+            pos += 1;
+        } else if !self.expects_value() {
+            return false;
+        }
+
+        let (id, end) = match self.src.get(pos) {
+            Some(&quote @ (b'"' | b'\'' | b'`')) => {
+                let start = pos + 1;
+                let Some(len) = self.src[start..]
+                    .iter()
+                    .position(|&b| b == quote || b == b'\n')
+                else {
+                    return false;
+                };
+                (self.src[start..start + len].to_vec(), start + len + 1)
+            }
+            Some(b) if b.is_ascii_uppercase() || *b == b'_' || (indented && is_ident(*b)) => {
+                let len = self.src[pos..].iter().take_while(|&&b| is_ident(b)).count();
+                (self.src[pos..pos + len].to_vec(), pos + len)
+            }
+            _ => return false,
+        };
+
+        self.heredocs.push(Heredoc { id, indented });
+        self.pos = end;
+        true
+    }
+
+    /// Skip the bodies of the heredocs started on the previous line
+    fn heredoc_bodies(&mut self) {
+        for heredoc in std::mem::take(&mut self.heredocs) {
+            while self.pos < self.src.len() {
+                let start = self.pos;
+                self.skip_line();
+                let mut line = &self.src[start..self.pos];
+                if line.last() == Some(&b'\r') {
+                    line = &line[..line.len() - 1];
+                }
+                if heredoc.indented {
+                    line = line.trim_ascii_start();
+                }
+                self.pos = (self.pos + 1).min(self.src.len());
+                if line == heredoc.id.as_slice() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(source: &str) -> Vec<String> {
+        scan_strings(source).into_iter().map(|l| l.text).collect()
+    }
+
+    #[test]
+    fn test_scan_strings() {
+        let source = r##"
+# "コメント"
+=begin
+"ブロックコメント"
+=end
+class Window_Help < Window_Base
+  def refresh
+    text = "#{actor.name}は倒れた！\n" + 'それは\'秘密\''
+    x = width / 2 + @a / 3
+    y = text.gsub(/"[^"]*"/, "") if text =~ %r{"}
+    z = [?", :"シンボル", %w(a "b"), $", 10 % 3]
+    self.contents.draw_text(0, 0, 100, 32, "ゴールド")
+    s = <<-EOS
+      "ヒアドキュメント"
+    EOS
+    @list << "追加"
+  end
+end
+__END__
+"終わり"
+"##;
+        assert_eq!(
+            texts(source),
+            [
+                r#"#{actor.name}は倒れた！\n"#,
+                r#"それは\'秘密\'"#,
+                "",
+                "ゴールド",
+                "追加",
+            ]
+        );
+
+        let literals = scan_strings(source);
+        let gold = &literals[3];
+        assert_eq!(&source[gold.range.clone()], "ゴールド");
+        assert_eq!(source.as_bytes()[gold.range.start - 1], b'"');
+        assert_eq!(gold.quote, b'"');
+    }
+
+    #[test]
+    fn test_interpolation_and_escape() {
+        let source = r##"p "a#{h["キー"]}b", 'c'"##;
+        assert_eq!(texts(source), [r#"a#{h["キー"]}b"#, "c"]);
+
+        assert_eq!(escape_literal(r#"say "hi"\n"#, b'"'), r#"say \"hi\"\n"#);
+        assert_eq!(
+            escape_literal(r#"already \" escaped"#, b'"'),
+            r#"already \" escaped"#
+        );
+        assert_eq!(escape_literal("it's", b'\''), r"it\'s");
+        assert_eq!(escape_literal(r"end\", b'\''), r"end\\");
+    }
+}
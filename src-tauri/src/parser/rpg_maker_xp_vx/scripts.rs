@@ -0,0 +1,463 @@
+//! Parser for Scripts.rxdata / .rvdata / .rvdata2 files
+//!
+//! The file holds an array of script sections, each an array of
+//! `[id, title, source]` where the source is zlib-deflated Ruby. Menus and
+//! messages of the default scripts and of third-party add-ons are string
+//! literals of these sources, so they are extracted through the
+//! [`ruby_strings`](super::ruby_strings) scanner and replaced in place.
+
+use super::command::text_value;
+use super::ruby_strings::{escape_literal, scan_strings};
+use super::{is_data_file, XpVxError};
+use crate::archiver::vfs::GameVfs;
+use crate::parser::marshal::{self, RString, Value};
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile,
+    TranslationPath, TranslationUnit,
+};
+use crate::types::engine::RpgMakerVersion;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Name of the manifest written next to extracted scripts
+pub const SCRIPTS_MANIFEST: &str = "scripts.json";
+
+/// A script section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptSection {
+    /// Section ID (a random number in the editor)
+    pub id: i64,
+    /// Title shown in the script editor
+    pub title: String,
+    /// Ruby source
+    pub source: String,
+}
+
+/// Entry of the manifest of extracted scripts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestEntry {
+    id: i64,
+    title: String,
+    file: String,
+}
+
+/// The script sections of a game
+#[derive(Debug, Clone, Default)]
+pub struct ScriptsArchive {
+    /// Sections, in editor order
+    pub sections: Vec<ScriptSection>,
+}
+
+impl ScriptsArchive {
+    /// Read the sections of loaded script data
+    pub fn from_value(data: &Value) -> Result<Self, XpVxError> {
+        let items = data
+            .as_array()
+            .ok_or_else(|| XpVxError::InvalidStructure("Scripts data is not an array".into()))?;
+
+        let sections = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let (id, title, data) = section_parts(item).ok_or_else(|| {
+                    XpVxError::InvalidStructure(format!("Script section {} is malformed", index))
+                })?;
+                let source = String::from_utf8(inflate(data)?).map_err(|_| {
+                    XpVxError::InvalidStructure(format!("Script {:?} is not valid UTF-8", title))
+                })?;
+                Ok(ScriptSection {
+                    id,
+                    title: title.to_string(),
+                    source,
+                })
+            })
+            .collect::<Result<_, XpVxError>>()?;
+
+        Ok(Self { sections })
+    }
+
+    /// Load the sections of a script file
+    pub fn load_file(path: &Path) -> Result<Self, XpVxError> {
+        Self::from_value(&marshal::load_file(path)?)
+    }
+
+    /// Build the script data of a version
+    pub fn to_value(&self, version: RpgMakerVersion) -> Value {
+        Value::Array(
+            self.sections
+                .iter()
+                .map(|section| {
+                    Value::Array(vec![
+                        Value::Fixnum(section.id),
+                        text_value(version, None, &section.title),
+                        Value::String(RString::binary(deflate(section.source.as_bytes()))),
+                    ])
+                })
+                .collect(),
+        )
+    }
+
+    /// Write the script data of a version to a file
+    pub fn save_file(&self, path: &Path, version: RpgMakerVersion) -> Result<(), XpVxError> {
+        fs::write(path, marshal::dump(&self.to_value(version)))?;
+        Ok(())
+    }
+
+    /// Write every section to a `.rb` file of a directory
+    ///
+    /// Files are named after the position and title of their section, and
+    /// a `scripts.json` manifest records the order, IDs and titles for
+    /// [`from_dir`](Self::from_dir). Returns the number of sections written.
+    pub fn extract_all<P: AsRef<Path>>(&self, output_dir: P) -> Result<usize, XpVxError> {
+        let output_dir = output_dir.as_ref();
+        fs::create_dir_all(output_dir)?;
+
+        let mut manifest = Vec::with_capacity(self.sections.len());
+        for (index, section) in self.sections.iter().enumerate() {
+            let file = format!("{:03}_{}.rb", index, sanitize_title(&section.title));
+            fs::write(output_dir.join(&file), &section.source)?;
+            manifest.push(ManifestEntry {
+                id: section.id,
+                title: section.title.clone(),
+                file,
+            });
+        }
+
+        let json = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| XpVxError::InvalidStructure(e.to_string()))?;
+        fs::write(output_dir.join(SCRIPTS_MANIFEST), json)?;
+
+        Ok(manifest.len())
+    }
+
+    /// Read sections written by [`extract_all`](Self::extract_all)
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, XpVxError> {
+        let dir = dir.as_ref();
+        let json = fs::read_to_string(dir.join(SCRIPTS_MANIFEST))?;
+        let manifest: Vec<ManifestEntry> = serde_json::from_str(&json)
+            .map_err(|e| XpVxError::InvalidStructure(format!("Invalid manifest: {}", e)))?;
+
+        let sections = manifest
+            .into_iter()
+            .map(|entry| {
+                Ok(ScriptSection {
+                    id: entry.id,
+                    title: entry.title,
+                    source: fs::read_to_string(dir.join(&entry.file))?,
+                })
+            })
+            .collect::<Result<_, XpVxError>>()?;
+
+        Ok(Self { sections })
+    }
+}
+
+/// Get the ID, title and deflated source of a section
+fn section_parts(item: &Value) -> Option<(i64, &str, &[u8])> {
+    match item.as_array()? {
+        [id, title, data, ..] => Some((
+            id.as_i64()?,
+            title.as_str()?,
+            data.as_string()?.bytes.as_slice(),
+        )),
+        _ => None,
+    }
+}
+
+/// Decompress a script source
+fn inflate(data: &[u8]) -> Result<Vec<u8>, XpVxError> {
+    let mut source = Vec::new();
+    ZlibDecoder::new(data).read_to_end(&mut source)?;
+    Ok(source)
+}
+
+/// Compress a script source
+fn deflate(source: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Writing to a Vec cannot fail
+    encoder.write_all(source).expect("write to Vec");
+    encoder.finish().expect("write to Vec")
+}
+
+/// Make a section title usable in a file name
+fn sanitize_title(title: &str) -> String {
+    let name: String = title
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    if name.is_empty() {
+        "untitled".to_string()
+    } else {
+        name
+    }
+}
+
+/// Path of the string literals of a section
+fn strings_path(section: usize) -> TranslationPath {
+    TranslationPath::new()
+        .append_index(section)
+        .append_key("strings")
+}
+
+/// Parser for script data files
+pub struct ScriptsParser;
+
+impl Default for ScriptsParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptsParser {
+    /// Create a new Scripts parser
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Extract the string literals of loaded script data
+    ///
+    /// Only literals with Japanese, Chinese or Korean text are extracted.
+    /// Units hold the literal as written, escapes included.
+    pub fn extract(
+        &self,
+        data: &Value,
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, XpVxError> {
+        let archive = ScriptsArchive::from_value(data)?;
+        let mut result = FileExtractionResult::new(file_name);
+
+        for (section_idx, section) in archive.sections.iter().enumerate() {
+            let context = ExtractionContext::new(file_name)
+                .with_event_id(section_idx)
+                .with_event_name(section.title.clone())
+                .with_max_preceding_lines(options.max_preceding_lines);
+            let path = strings_path(section_idx);
+
+            let units = scan_strings(&section.source)
+                .into_iter()
+                .enumerate()
+                .map(|(literal_idx, literal)| {
+                    let path = path.append_index(literal_idx);
+                    TranslationUnit::new(
+                        path.to_unit_id("string"),
+                        path,
+                        EventCode::Script,
+                        literal.text,
+                    )
+                    .with_context(context.to_translation_context())
+                })
+                .filter(|unit| unit.needs_translation())
+                .collect();
+            result.add_units(units);
+        }
+
+        Ok(result)
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, XpVxError> {
+        let data = marshal::load_file(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Scripts.rxdata");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside a mounted archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, XpVxError> {
+        let data = marshal::load(&vfs.read(path)?)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("Scripts.rxdata");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Inject translations into the string literals of loaded script data
+    ///
+    /// Translations replace the content of their literal, with quotes
+    /// escaped as needed. Only the sections that change are recompressed.
+    pub fn inject(
+        &self,
+        data: &mut Value,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, XpVxError> {
+        let archive = ScriptsArchive::from_value(data)?;
+        let mut result = FileInjectionResult::new();
+        let items = data
+            .as_array_mut()
+            .ok_or_else(|| XpVxError::InvalidStructure("Scripts data is not an array".into()))?;
+
+        for ((section_idx, section), item) in archive.sections.iter().enumerate().zip(items) {
+            let source = &section.source;
+            let path = strings_path(section_idx);
+            let mut output = String::with_capacity(source.len());
+            let mut last = 0;
+            let mut applied = 0;
+
+            for (literal_idx, literal) in scan_strings(source).into_iter().enumerate() {
+                let id = path.append_index(literal_idx).to_unit_id("string");
+                let Some(translated) = translations.get(&id) else {
+                    if !options.skip_missing_translations
+                        && TranslationUnit::new(id, path.clone(), EventCode::Script, literal.text)
+                            .needs_translation()
+                    {
+                        result.not_found += 1;
+                    }
+                    continue;
+                };
+
+                output.push_str(&source[last..literal.range.start]);
+                output.push_str(&escape_literal(translated, literal.quote));
+                last = literal.range.end;
+                applied += 1;
+            }
+
+            if applied == 0 {
+                continue;
+            }
+            output.push_str(&source[last..]);
+
+            if let Some(Value::String(data)) = item.as_array_mut().and_then(|a| a.get_mut(2)) {
+                data.bytes = deflate(output.as_bytes());
+            }
+            result.applied += applied;
+            result.commands_modified += 1;
+        }
+
+        result.modified = result.commands_modified > 0;
+        Ok(result)
+    }
+
+    /// Inject translations to a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, XpVxError> {
+        let mut data = marshal::load_file(path)?;
+
+        let result = self.inject(&mut data, translations, options)?;
+
+        if result.modified {
+            fs::write(path, marshal::dump(&data))?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+/// Check if a file is a scripts file
+pub fn is_scripts_file(path: &Path) -> bool {
+    is_data_file(path, "Scripts")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_archive() -> ScriptsArchive {
+        ScriptsArchive {
+            sections: vec![
+                ScriptSection {
+                    id: 12345,
+                    title: "Vocab".to_string(),
+                    source: "module Vocab\n  ShopBuy = \"購入する\"\n  Save = 'save'\nend\n"
+                        .to_string(),
+                },
+                ScriptSection {
+                    id: 67890,
+                    title: "Window/Help".to_string(),
+                    source: "p \"は#{x}\" # 「コメント」\n".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let archive = make_archive();
+        let data =
+            marshal::load(&marshal::dump(&archive.to_value(RpgMakerVersion::VXAce))).unwrap();
+        let loaded = ScriptsArchive::from_value(&data).unwrap();
+        assert_eq!(loaded.sections, archive.sections);
+
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(archive.extract_all(dir.path()).unwrap(), 2);
+        assert!(dir.path().join("001_Window_Help.rb").exists());
+        let repacked = ScriptsArchive::from_dir(dir.path()).unwrap();
+        assert_eq!(repacked.sections, archive.sections);
+        assert!(is_scripts_file(Path::new("Data/Scripts.rvdata2")));
+    }
+
+    #[test]
+    fn test_extract_and_inject_strings() {
+        let parser = ScriptsParser::new();
+        let mut data = make_archive().to_value(RpgMakerVersion::XP);
+        let result = parser
+            .extract(&data, "Scripts.rxdata", &ExtractionOptions::default())
+            .unwrap();
+        let units: Vec<(&str, &str)> = result
+            .units
+            .iter()
+            .map(|u| (u.id.as_str(), u.original.as_str()))
+            .collect();
+        assert_eq!(
+            units,
+            [
+                ("0.strings.0_string", "購入する"),
+                ("1.strings.0_string", "は#{x}")
+            ]
+        );
+
+        let mut translations = HashMap::new();
+        translations.insert("0.strings.0_string".to_string(), "Buy \"now\"".to_string());
+        let result = parser
+            .inject(&mut data, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert_eq!(result.applied, 1);
+        assert_eq!(result.commands_modified, 1);
+
+        let archive = ScriptsArchive::from_value(&data).unwrap();
+        assert_eq!(
+            archive.sections[0].source,
+            "module Vocab\n  ShopBuy = \"Buy \\\"now\\\"\"\n  Save = 'save'\nend\n"
+        );
+        assert_eq!(archive.sections[1], make_archive().sections[1]);
+    }
+}
@@ -0,0 +1,236 @@
+//! Parser for Troops.rxdata / .rvdata / .rvdata2 files
+//!
+//! The file holds an array of `RPG::Troop` objects (the first is `nil`).
+//! Each troop has battle event pages with command lists, like map events.
+
+use super::event_page::EventPageParser;
+use super::{is_data_file, XpVxError};
+use crate::archiver::vfs::GameVfs;
+use crate::parser::marshal::{self, ivar_index, Value};
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile, TranslationPath,
+};
+use crate::types::engine::RpgMakerVersion;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Parser for troop data files
+pub struct TroopsParser {
+    /// Event page parser
+    page_parser: EventPageParser,
+}
+
+impl TroopsParser {
+    /// Create a new Troops parser for a version
+    pub fn new(version: RpgMakerVersion) -> Self {
+        Self {
+            page_parser: EventPageParser::new(version),
+        }
+    }
+
+    /// Create with a custom page parser
+    pub fn with_page_parser(page_parser: EventPageParser) -> Self {
+        Self { page_parser }
+    }
+
+    /// Extract translations from loaded troop data
+    pub fn extract(
+        &self,
+        data: &Value,
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> FileExtractionResult {
+        let mut result = FileExtractionResult::new(file_name);
+
+        let troops = match data.as_array() {
+            Some(arr) => arr,
+            None => {
+                result.add_warning("Troops data is not an array");
+                return result;
+            }
+        };
+
+        for (troop_idx, troop) in troops.iter().enumerate() {
+            if troop.is_nil() {
+                continue;
+            }
+
+            let mut base_context = ExtractionContext::new(file_name)
+                .with_event_id(troop_idx)
+                .with_max_preceding_lines(options.max_preceding_lines);
+
+            if let Some(name) = troop.ivar("@name").and_then(|v| v.as_str()) {
+                base_context = base_context.with_event_name(name.to_string());
+            }
+
+            if let Some(pages) = troop.ivar("@pages") {
+                let troop_path = TranslationPath::new().append_index(troop_idx);
+                let units =
+                    self.page_parser
+                        .extract_from_pages(pages, &troop_path, &base_context, options);
+                result.add_units(units);
+            }
+        }
+
+        result
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, XpVxError> {
+        let data = marshal::load_file(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Troops.rxdata");
+
+        Ok(self.extract(&data, file_name, options))
+    }
+
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside a mounted archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, XpVxError> {
+        let data = marshal::load(&vfs.read(path)?)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("Troops.rxdata");
+
+        Ok(self.extract(&data, file_name, options))
+    }
+
+    /// Inject translations back into loaded troop data
+    pub fn inject(
+        &self,
+        data: &mut Value,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> FileInjectionResult {
+        let mut result = FileInjectionResult::new();
+        let mut splices = Vec::new();
+
+        let troops = match data.as_array_mut() {
+            Some(arr) => arr,
+            None => {
+                result
+                    .warnings
+                    .push("Troops data is not an array".to_string());
+                return result;
+            }
+        };
+
+        // The array is the first entry of the object table
+        let mut index = 1;
+        for (troop_idx, troop) in troops.iter_mut().enumerate() {
+            let base_context = ExtractionContext::new("Troops").with_event_id(troop_idx);
+
+            let pages_index = ivar_index(troop, index, "@pages");
+            if let (Some(pages_index), Some(pages)) = (pages_index, troop.ivar_mut("@pages")) {
+                let troop_path = TranslationPath::new().append_index(troop_idx);
+                let inject_result = self.page_parser.inject_to_pages(
+                    pages,
+                    pages_index,
+                    translations,
+                    &troop_path,
+                    &base_context,
+                    options,
+                    &mut splices,
+                );
+                result.merge(inject_result);
+            }
+
+            index += troop.object_count();
+        }
+
+        marshal::relink(data, &splices);
+        result
+    }
+
+    /// Inject translations to a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, XpVxError> {
+        let mut data = marshal::load_file(path)?;
+
+        let result = self.inject(&mut data, translations, options);
+
+        if result.modified {
+            fs::write(path, marshal::dump(&data))?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+/// Check if a file is a troops file
+pub fn is_troops_file(path: &Path) -> bool {
+    is_data_file(path, "Troops")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::marshal::{RString, Symbol};
+    use crate::parser::rpg_maker_xp_vx::command::EventCommand;
+
+    #[test]
+    fn test_troop_pages_round_trip() {
+        let text = |s: &str| Value::String(RString::binary(s.as_bytes().to_vec()));
+        let list = [
+            EventCommand::new(101, 0, vec![text("覚悟しろ！")]),
+            EventCommand::new(0, 0, Vec::new()),
+        ];
+        let page = Value::Object {
+            class: Symbol::new("RPG::Troop::Page"),
+            ivars: vec![(
+                Symbol::new("@list"),
+                Value::Array(list.iter().map(EventCommand::to_value).collect()),
+            )],
+        };
+        let troop = Value::Object {
+            class: Symbol::new("RPG::Troop"),
+            ivars: vec![
+                (Symbol::new("@name"), text("スライム*2")),
+                (Symbol::new("@pages"), Value::Array(vec![page])),
+            ],
+        };
+        let mut data = Value::Array(vec![Value::Nil, troop]);
+
+        let parser = TroopsParser::new(RpgMakerVersion::XP);
+        let result = parser.extract(&data, "Troops.rxdata", &ExtractionOptions::default());
+        assert_eq!(result.units.len(), 1);
+        assert_eq!(result.units[0].id, "1.pages.0.list.0_dialogue");
+
+        let mut translations = HashMap::new();
+        translations.insert(result.units[0].id.clone(), "Prepare yourself!".to_string());
+        assert!(
+            parser
+                .inject(&mut data, &translations, &InjectionOptions::default())
+                .modified
+        );
+
+        let result = parser.extract(&data, "Troops.rxdata", &ExtractionOptions::default());
+        assert_eq!(result.units[0].original, "Prepare yourself!");
+        assert!(is_troops_file(Path::new("Data/Troops.rxdata")));
+    }
+}
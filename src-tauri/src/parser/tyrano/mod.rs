@@ -0,0 +1,32 @@
+//! TyranoScript parser module
+//!
+//! TyranoScript games are NW.js or Electron apps whose story is written in
+//! `.ks` scenario files under `data/scenario/`. A scenario mixes plain text
+//! lines with `[tag]` and `@tag` commands:
+//!
+//! ```text
+//! *start|Opening
+//! #akane:happy
+//! Good morning![p]
+//! [glink text="Go outside" target=*outside]
+//! ```
+//!
+//! Message text, `#name` speaker lines and `[link]`/`[glink]` choice labels
+//! are extracted. Translations are injected by replacing the text in the
+//! original source, leaving tags, comments and scripts untouched.
+
+pub mod scenario;
+
+pub use scenario::ScenarioParser;
+
+use crate::archiver::ArchiverError;
+
+/// Error type for TyranoScript parsing
+#[derive(Debug, thiserror::Error)]
+pub enum TyranoError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Archive error: {0}")]
+    ArchiveError(#[from] ArchiverError),
+}
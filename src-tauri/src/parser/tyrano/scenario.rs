@@ -0,0 +1,666 @@
+//! Extraction and injection for `.ks` scenario files
+//!
+//! Scenarios are read line by line. Comments (`;`, `/* */`), labels,
+//! `@tag` lines and `[iscript]`/`[html]` blocks hold no text. A `#name`
+//! line sets the speaker of the following messages. Any other line is
+//! split at its `[tags]`, and every non-blank piece of text in between
+//! is a message, or a choice inside `[link]`...`[endlink]`. The `text`
+//! attribute of `[glink]` is a choice as well.
+//!
+//! Units are identified by line and position (`lines.12.texts.0_message`),
+//! and injection replaces the exact byte ranges they were read from.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use super::TyranoError;
+use crate::archiver::vfs::GameVfs;
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile,
+    TranslationPath, TranslationUnit,
+};
+
+/// Kind of a text in a scenario
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextKind {
+    /// Speaker of a `#name` line
+    Name,
+    /// Message text
+    Message,
+    /// Text between `[link]` and `[endlink]`
+    Link,
+    /// `text` attribute of a `[glink]` tag
+    Glink,
+}
+
+/// Translatable text of a scenario
+#[derive(Debug, Clone)]
+pub struct ScenarioText {
+    pub kind: TextKind,
+    /// Line index (0-based)
+    pub line: usize,
+    /// Index of the text in its line
+    pub index: usize,
+    /// Byte range in the source, including the quotes of attributes
+    pub range: Range<usize>,
+    /// Text, without quotes
+    pub text: String,
+    /// Speaker set by the last `#name` line
+    pub speaker: Option<String>,
+    /// Last label
+    pub label: Option<String>,
+}
+
+impl ScenarioText {
+    /// Get the translation path of the text
+    pub fn path(&self) -> TranslationPath {
+        let path = TranslationPath::new()
+            .append_key("lines")
+            .append_index(self.line);
+        match self.kind {
+            TextKind::Name => path,
+            _ => path.append_key("texts").append_index(self.index),
+        }
+    }
+
+    /// Get the unit id of the text
+    pub fn unit_id(&self) -> String {
+        let suffix = match self.kind {
+            TextKind::Name => "speaker",
+            TextKind::Message => "message",
+            TextKind::Link | TextKind::Glink => "choice",
+        };
+        self.path().to_unit_id(suffix)
+    }
+}
+
+/// Block of lines holding no text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Comment,
+    Script,
+    Html,
+}
+
+impl Block {
+    /// Check whether a line ends the block
+    fn ends_at(self, line: &str) -> bool {
+        match self {
+            Self::Comment => line.contains("*/"),
+            Self::Script => line.contains("[endscript") || line.starts_with("@endscript"),
+            Self::Html => line.contains("[endhtml") || line.starts_with("@endhtml"),
+        }
+    }
+}
+
+/// Scenario reader state
+#[derive(Default)]
+struct Scanner {
+    texts: Vec<ScenarioText>,
+    block: Option<Block>,
+    in_link: bool,
+    speaker: Option<String>,
+    label: Option<String>,
+    line: usize,
+    index: usize,
+}
+
+impl Scanner {
+    fn push(&mut self, kind: TextKind, range: Range<usize>, text: &str) {
+        let index = if kind == TextKind::Name {
+            0
+        } else {
+            self.index += 1;
+            self.index - 1
+        };
+        self.texts.push(ScenarioText {
+            kind,
+            line: self.line,
+            index,
+            range,
+            text: text.to_string(),
+            speaker: self.speaker.clone(),
+            label: self.label.clone(),
+        });
+    }
+
+    /// Read a line, trimmed, starting at byte `base` of the source
+    fn line(&mut self, line: &str, base: usize) {
+        if let Some(block) = self.block {
+            if block.ends_at(line) {
+                self.block = None;
+            }
+            return;
+        }
+
+        match line.as_bytes().first() {
+            None | Some(b';') => {}
+            Some(b'/') if line.starts_with("/*") => {
+                if !line[2..].contains("*/") {
+                    self.block = Some(Block::Comment);
+                }
+            }
+            Some(b'*') => {
+                let name = line[1..].split('|').next().unwrap_or("").trim();
+                self.label = (!name.is_empty()).then(|| name.to_string());
+            }
+            Some(b'@') => self.tag(&line[1..], base + 1),
+            Some(b'#') => self.name(&line[1..], base + 1),
+            Some(_) => self.text(line, base),
+        }
+    }
+
+    /// Read a `#name[:face]` line
+    fn name(&mut self, rest: &str, base: usize) {
+        let name = rest.split(':').next().unwrap_or("");
+        let start = base + (name.len() - name.trim_start().len());
+        let name = name.trim();
+
+        if name.is_empty() {
+            self.speaker = None;
+            return;
+        }
+        self.speaker = Some(name.to_string());
+        // `&` names are expressions, not text
+        if !name.starts_with('&') {
+            self.push(TextKind::Name, start..start + name.len(), name);
+        }
+    }
+
+    /// Read a text line, split at its tags
+    fn text(&mut self, line: &str, base: usize) {
+        let bytes = line.as_bytes();
+        let mut segment = 0;
+        let mut pos = 0;
+
+        while pos < bytes.len() {
+            if bytes[pos] != b'[' {
+                pos += 1;
+                continue;
+            }
+
+            self.segment(&line[segment..pos], base + segment);
+            let end = tag_end(line, pos);
+            let tag = line[pos + 1..end].trim_end_matches(']');
+            self.tag(tag, base + pos + 1);
+            pos = end;
+            segment = end;
+
+            if self.block.is_some() {
+                return;
+            }
+        }
+        self.segment(&line[segment..], base + segment);
+    }
+
+    /// Read the text between two tags
+    fn segment(&mut self, segment: &str, base: usize) {
+        let start = base + (segment.len() - segment.trim_start().len());
+        let text = segment.trim();
+        if text.is_empty() {
+            return;
+        }
+        let kind = if self.in_link {
+            TextKind::Link
+        } else {
+            TextKind::Message
+        };
+        self.push(kind, start..start + text.len(), text);
+    }
+
+    /// Read the contents of a tag, starting at byte `base` of the source
+    fn tag(&mut self, tag: &str, base: usize) {
+        let name = tag.split_whitespace().next().unwrap_or("");
+        match name {
+            "link" => self.in_link = true,
+            "endlink" => self.in_link = false,
+            "iscript" => self.block = Some(Block::Script),
+            "html" => self.block = Some(Block::Html),
+            "glink" => {
+                if let Some((range, text)) = find_attribute(tag, "text") {
+                    // `&` values are expressions, not text
+                    if !text.trim().is_empty() && !text.starts_with('&') {
+                        let range = base + range.start..base + range.end;
+                        self.push(TextKind::Glink, range, text);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Get the end of the tag starting at `start`, after its `]`
+fn tag_end(line: &str, start: usize) -> usize {
+    let mut quote = None;
+    for (i, c) in line[start + 1..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ']') => return start + 1 + i + 1,
+            _ => {}
+        }
+    }
+    line.len()
+}
+
+/// Find an attribute of a tag
+///
+/// Returns the range of the value in `tag`, including its quotes, and the
+/// value without them.
+fn find_attribute<'a>(tag: &'a str, name: &str) -> Option<(Range<usize>, &'a str)> {
+    let bytes = tag.as_bytes();
+    let skip_whitespace = |mut pos: usize| {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        pos
+    };
+
+    // Skip the tag name
+    let mut pos = skip_whitespace(0);
+    while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+
+    loop {
+        pos = skip_whitespace(pos);
+        if pos >= bytes.len() {
+            return None;
+        }
+
+        let key_start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'=' {
+            pos += 1;
+        }
+        let key = &tag[key_start..pos];
+
+        pos = skip_whitespace(pos);
+        if bytes.get(pos) != Some(&b'=') {
+            // Attribute without a value
+            continue;
+        }
+        pos = skip_whitespace(pos + 1);
+
+        let value_start = pos;
+        let value = match bytes.get(pos) {
+            Some(&q) if q == b'"' || q == b'\'' => {
+                let end = tag[pos + 1..]
+                    .find(q as char)
+                    .map_or(bytes.len(), |i| pos + 1 + i);
+                let value = &tag[pos + 1..end];
+                pos = (end + 1).min(bytes.len());
+                value
+            }
+            _ => {
+                while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                    pos += 1;
+                }
+                &tag[value_start..pos]
+            }
+        };
+
+        if key == name {
+            return Some((value_start..pos, value));
+        }
+    }
+}
+
+/// Parse the translatable texts of a scenario
+pub fn parse_scenario(source: &str) -> Vec<ScenarioText> {
+    let mut scanner = Scanner::default();
+    let mut offset = 0;
+
+    for (line_index, raw) in source.split_inclusive('\n').enumerate() {
+        let mut base = offset;
+        offset += raw.len();
+
+        let mut line = raw.trim_end_matches(['\n', '\r']);
+        if base == 0 {
+            if let Some(rest) = line.strip_prefix('\u{feff}') {
+                base += '\u{feff}'.len_utf8();
+                line = rest;
+            }
+        }
+        let trimmed = line.trim_start();
+        base += line.len() - trimmed.len();
+
+        scanner.line = line_index;
+        scanner.index = 0;
+        scanner.line(trimmed.trim_end(), base);
+    }
+
+    scanner.texts
+}
+
+/// Quote a `[glink]` attribute value
+fn quote_attribute(value: &str) -> Option<String> {
+    if !value.contains('"') {
+        Some(format!("\"{}\"", value))
+    } else if !value.contains('\'') {
+        Some(format!("'{}'", value))
+    } else {
+        None
+    }
+}
+
+/// Parser for `.ks` scenario files
+pub struct ScenarioParser;
+
+impl ScenarioParser {
+    /// Create a new scenario parser
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Extract translations from scenario source
+    pub fn extract(
+        &self,
+        source: &str,
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, TyranoError> {
+        let mut result = FileExtractionResult::new(file_name);
+        let mut context =
+            ExtractionContext::new(file_name).with_max_preceding_lines(options.max_preceding_lines);
+        let mut units = Vec::new();
+
+        for text in parse_scenario(source) {
+            let mut translation_context = context.to_translation_context();
+            if let Some(label) = &text.label {
+                translation_context = translation_context.with_event_name(label);
+            }
+
+            let code = match text.kind {
+                TextKind::Name => EventCode::ShowText,
+                TextKind::Message => EventCode::ShowTextBody,
+                TextKind::Link | TextKind::Glink => {
+                    translation_context.add_tag("choice");
+                    EventCode::ShowChoices
+                }
+            };
+
+            let mut unit =
+                TranslationUnit::new(text.unit_id(), text.path(), code, text.text.clone())
+                    .with_context(translation_context);
+            if text.kind == TextKind::Message {
+                unit = unit.with_speaker(text.speaker.clone());
+                context.add_preceding_line(text.text.clone());
+            }
+            units.push(unit);
+        }
+
+        result.add_units(units);
+        Ok(result)
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, TyranoError> {
+        let source = fs::read_to_string(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("scene.ks");
+
+        self.extract(&source, file_name, options)
+    }
+
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside the game's package.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, TyranoError> {
+        let source = vfs.read_to_string(path)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("scene.ks");
+
+        self.extract(&source, file_name, options)
+    }
+
+    /// Inject translations into scenario source
+    ///
+    /// Messages get `[r]` at their line breaks, and are wrapped at
+    /// `max_line_length` when set.
+    pub fn inject(
+        &self,
+        source: &mut String,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, TyranoError> {
+        let mut result = FileInjectionResult::new();
+        let mut output = String::with_capacity(source.len());
+        let mut last = 0;
+
+        for text in parse_scenario(source) {
+            let id = text.unit_id();
+            let Some(translated) = translations.get(&id) else {
+                if text.kind != TextKind::Name && !options.skip_missing_translations {
+                    result.not_found += 1;
+                }
+                continue;
+            };
+
+            let replacement = match text.kind {
+                TextKind::Name => translated.replace(['\r', '\n'], " "),
+                TextKind::Message | TextKind::Link => options.split_text(translated).join("[r]"),
+                TextKind::Glink => match quote_attribute(translated) {
+                    Some(value) => value,
+                    None => {
+                        result.warnings.push(format!(
+                            "{}: choice text contains both quote characters, skipped",
+                            id
+                        ));
+                        continue;
+                    }
+                },
+            };
+
+            output.push_str(&source[last..text.range.start]);
+            output.push_str(&replacement);
+            last = text.range.end;
+            result.applied += 1;
+            result.commands_modified += 1;
+        }
+
+        result.modified = result.applied > 0;
+        if result.modified {
+            output.push_str(&source[last..]);
+            *source = output;
+        }
+
+        Ok(result)
+    }
+
+    /// Inject translations into a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, TyranoError> {
+        let mut source = fs::read_to_string(path)?;
+
+        let result = self.inject(&mut source, translations, options)?;
+
+        if result.modified {
+            fs::write(path, source)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+impl Default for ScenarioParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check whether a file is a scenario file
+pub fn is_scenario_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("ks"))
+}
+
+/// Get the scenario files in a directory of a game's virtual filesystem
+///
+/// Includes scenarios stored in the game's package.
+pub fn find_scenario_files_vfs(vfs: &GameVfs, dir: &str) -> Vec<String> {
+    vfs.list_dir(dir)
+        .into_iter()
+        .filter(|path| is_scenario_file(Path::new(path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENARIO: &str = "\u{feff};Opening scene\r
+*start|Opening\r
+[cm]\r
+@bg storage=room.jpg time=500\r
+#akane:happy\r
+Good morning![l][r]\r
+  It's a [font color=\"0xff0000\"]lovely[resetfont] day.[p]\r
+#\r
+/*\r
+Unused line\r
+*/\r
+[iscript]\r
+f.count = 1;\r
+[endscript]\r
+[link target=*yes]Sure[endlink] [link target=*no]No thanks[endlink]\r
+[glink text=\"Go outside\" target=*outside]\r
+[glink target=*stay text='Stay \"inside\"']\r
+";
+
+    #[test]
+    fn test_extract() {
+        let result = ScenarioParser::new()
+            .extract(SCENARIO, "first.ks", &ExtractionOptions::default())
+            .unwrap();
+
+        let units: Vec<(&str, &str)> = result
+            .units
+            .iter()
+            .map(|u| (u.id.as_str(), u.original.as_str()))
+            .collect();
+        assert_eq!(
+            units,
+            vec![
+                ("lines.4_speaker", "akane"),
+                ("lines.5.texts.0_message", "Good morning!"),
+                ("lines.6.texts.0_message", "It's a"),
+                ("lines.6.texts.1_message", "lovely"),
+                ("lines.6.texts.2_message", "day."),
+                ("lines.14.texts.0_choice", "Sure"),
+                ("lines.14.texts.1_choice", "No thanks"),
+                ("lines.15.texts.0_choice", "Go outside"),
+                ("lines.16.texts.0_choice", "Stay \"inside\""),
+            ]
+        );
+        assert_eq!(result.units[1].speaker.as_deref(), Some("akane"));
+        assert_eq!(result.units[1].context.event_name.as_deref(), Some("start"));
+        assert_eq!(result.units[5].speaker, None);
+        assert_eq!(result.units[5].code, EventCode::ShowChoices);
+        assert_eq!(result.speakers, vec!["akane"]);
+    }
+
+    #[test]
+    fn test_inject_keeps_tags() {
+        let parser = ScenarioParser::new();
+        let mut translations = HashMap::new();
+        translations.insert("lines.4_speaker".to_string(), "아카네".to_string());
+        translations.insert(
+            "lines.5.texts.0_message".to_string(),
+            "좋은 아침!\n일어났어?".to_string(),
+        );
+        translations.insert("lines.6.texts.1_message".to_string(), "멋진".to_string());
+        translations.insert("lines.14.texts.1_choice".to_string(), "됐어".to_string());
+        translations.insert(
+            "lines.15.texts.0_choice".to_string(),
+            "밖으로 \"나가기\"".to_string(),
+        );
+        translations.insert(
+            "lines.16.texts.0_choice".to_string(),
+            "\"안\"에 '있기'".to_string(),
+        );
+
+        let mut source = SCENARIO.to_string();
+        let result = parser
+            .inject(&mut source, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert_eq!(result.applied, 5);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.modified);
+
+        let expected = SCENARIO
+            .replace("#akane:happy", "#아카네:happy")
+            .replace("Good morning![l]", "좋은 아침![r]일어났어?[l]")
+            .replace("]lovely[", "]멋진[")
+            .replace("]No thanks[", "]됐어[")
+            .replace("\"Go outside\"", "'밖으로 \"나가기\"'");
+        assert_eq!(source, expected);
+        assert!(source.starts_with('\u{feff}'));
+
+        // Lines are kept, so other lines' ids still match
+        let reinjected = parser
+            .extract(&source, "first.ks", &ExtractionOptions::default())
+            .unwrap();
+        let unit = |id: &str| reinjected.units.iter().find(|u| u.id == id).unwrap();
+        assert_eq!(unit("lines.4_speaker").original, "아카네");
+        assert_eq!(unit("lines.14.texts.1_choice").original, "됐어");
+    }
+
+    #[test]
+    fn test_find_scenario_files_vfs() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("data/scenario")).unwrap();
+        fs::write(root.join("data/scenario/first.ks"), SCENARIO).unwrap();
+        fs::write(root.join("data/scenario/notes.txt"), "").unwrap();
+
+        let vfs = GameVfs::new(root);
+        let files = find_scenario_files_vfs(&vfs, "data/scenario");
+        assert_eq!(files, vec!["data/scenario/first.ks"]);
+
+        let parser = ScenarioParser::new();
+        let result = parser
+            .extract_vfs(&vfs, &files[0], &ExtractionOptions::default())
+            .unwrap();
+        assert_eq!(result.units.len(), 9);
+
+        let mut translations = HashMap::new();
+        translations.insert("lines.6.texts.2_message".to_string(), "날이네.".to_string());
+        let path = root.join("data/scenario/first.ks");
+        let result = parser
+            .inject_file(&path, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert_eq!(result.applied, 1);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("[resetfont] 날이네.[p]\r\n"));
+    }
+}
@@ -0,0 +1,325 @@
+//! Binary reading and string splicing for Wolf RPG Editor data files
+//!
+//! Wolf RPG Editor files are little-endian. Strings are stored as a `u32`
+//! length (including the trailing NUL), the encoded bytes and a NUL. The
+//! parsers keep the position of every string they read, so translations
+//! can be written back by splicing re-encoded strings into the original
+//! bytes without rebuilding the rest of the file.
+
+use encoding_rs::SHIFT_JIS;
+
+use super::WolfRpgError;
+
+/// Text encoding of a Wolf RPG Editor data file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WolfEncoding {
+    /// Shift-JIS, used by Wolf RPG Editor 2.x
+    ShiftJis,
+    /// UTF-8, used by the international releases of 3.x
+    Utf8,
+}
+
+impl WolfEncoding {
+    /// Guess the encoding from the raw strings of a file
+    ///
+    /// Shift-JIS text is almost never valid UTF-8, so the file is UTF-8 if
+    /// it has non-ASCII strings and all of them decode as UTF-8.
+    pub fn detect<'a>(strings: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut non_ascii = false;
+        for raw in strings {
+            if raw.is_ascii() {
+                continue;
+            }
+            if std::str::from_utf8(raw).is_err() {
+                return Self::ShiftJis;
+            }
+            non_ascii = true;
+        }
+
+        if non_ascii {
+            Self::Utf8
+        } else {
+            Self::ShiftJis
+        }
+    }
+
+    /// Decode raw string bytes, or `None` if they are not valid text
+    pub fn decode(&self, raw: &[u8]) -> Option<String> {
+        match self {
+            Self::ShiftJis => {
+                let (text, had_errors) = SHIFT_JIS.decode_without_bom_handling(raw);
+                (!had_errors).then(|| text.into_owned())
+            }
+            Self::Utf8 => String::from_utf8(raw.to_vec()).ok(),
+        }
+    }
+
+    /// Encode text, or `None` if it has characters the encoding lacks
+    pub fn encode(&self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::ShiftJis => {
+                let (bytes, _, had_errors) = SHIFT_JIS.encode(text);
+                (!had_errors).then(|| bytes.into_owned())
+            }
+            Self::Utf8 => Some(text.as_bytes().to_vec()),
+        }
+    }
+}
+
+/// String read from a data file, with its location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WolfString {
+    /// Offset of the length prefix in the file
+    pub offset: usize,
+    /// Encoded size in the file, including the length prefix and NUL
+    pub size: usize,
+    /// Raw string bytes, without the NUL
+    pub raw: Vec<u8>,
+}
+
+impl WolfString {
+    /// Decode the string
+    pub fn decode(&self, encoding: WolfEncoding) -> Option<String> {
+        encoding.decode(&self.raw)
+    }
+}
+
+/// Cursor over the bytes of a data file
+pub struct BinaryReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+    /// Create a reader at the start of `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Current position
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Whether all bytes have been read
+    pub fn is_at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    /// Read `len` raw bytes
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], WolfRpgError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(WolfRpgError::UnexpectedEof(self.pos))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Skip `len` bytes
+    pub fn skip(&mut self, len: usize) -> Result<(), WolfRpgError> {
+        self.bytes(len).map(|_| ())
+    }
+
+    /// Read a byte
+    pub fn u8(&mut self) -> Result<u8, WolfRpgError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Read a little-endian `u32`
+    pub fn u32(&mut self) -> Result<u32, WolfRpgError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read a `u32` used as a count, checked against the bytes left
+    ///
+    /// Every counted item takes at least `min_item_size` bytes, which stops
+    /// corrupt counts from causing huge allocations.
+    pub fn count(&mut self, min_item_size: usize) -> Result<usize, WolfRpgError> {
+        let offset = self.pos;
+        let count = self.u32()? as usize;
+        let left = self.data.len() - self.pos;
+        if count.saturating_mul(min_item_size.max(1)) > left {
+            return Err(WolfRpgError::InvalidStructure(format!(
+                "count {} at offset {:#x} exceeds the file size",
+                count, offset
+            )));
+        }
+        Ok(count)
+    }
+
+    /// Read the expected bytes, failing with `what` if they differ
+    pub fn expect(&mut self, expected: &[u8], what: &str) -> Result<(), WolfRpgError> {
+        let offset = self.pos;
+        if self.bytes(expected.len())? != expected {
+            return Err(WolfRpgError::InvalidStructure(format!(
+                "expected {} at offset {:#x}",
+                what, offset
+            )));
+        }
+        Ok(())
+    }
+
+    /// Read a length-prefixed string
+    pub fn string(&mut self) -> Result<WolfString, WolfRpgError> {
+        let offset = self.pos;
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?;
+
+        // The length includes the NUL; a zero length is an empty string too
+        let raw = match bytes.split_last() {
+            Some((0, raw)) => raw.to_vec(),
+            Some(_) => {
+                return Err(WolfRpgError::InvalidStructure(format!(
+                    "unterminated string at offset {:#x}",
+                    offset
+                )))
+            }
+            None => Vec::new(),
+        };
+
+        Ok(WolfString {
+            offset,
+            size: self.pos - offset,
+            raw,
+        })
+    }
+
+    /// Read `count` strings
+    pub fn strings(&mut self, count: usize) -> Result<Vec<WolfString>, WolfRpgError> {
+        (0..count).map(|_| self.string()).collect()
+    }
+}
+
+/// Encode raw bytes as a length-prefixed string
+pub fn encode_string(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len() + 5);
+    out.extend_from_slice(&(raw.len() as u32 + 1).to_le_bytes());
+    out.extend_from_slice(raw);
+    out.push(0);
+    out
+}
+
+/// Replacements of strings in a data file
+#[derive(Debug, Default)]
+pub struct Splicer {
+    /// (offset, old size, new encoded string)
+    replacements: Vec<(usize, usize, Vec<u8>)>,
+}
+
+impl Splicer {
+    /// Create an empty splicer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace a string with new raw bytes
+    pub fn replace(&mut self, string: &WolfString, raw: &[u8]) {
+        self.replacements
+            .push((string.offset, string.size, encode_string(raw)));
+    }
+
+    /// Whether there is nothing to replace
+    pub fn is_empty(&self) -> bool {
+        self.replacements.is_empty()
+    }
+
+    /// Apply the replacements to the file the strings were read from
+    pub fn apply(mut self, data: &[u8]) -> Vec<u8> {
+        self.replacements.sort_by_key(|(offset, _, _)| *offset);
+
+        let mut out = Vec::with_capacity(data.len());
+        let mut pos = 0;
+        for (offset, size, bytes) in self.replacements {
+            out.extend_from_slice(&data[pos..offset]);
+            out.extend(bytes);
+            pos = offset + size;
+        }
+        out.extend_from_slice(&data[pos..]);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_strings() {
+        let mut data = encode_string(b"abc");
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend(encode_string(b""));
+        data.extend_from_slice(&[3, 0, 0, 0, b'x', b'y', b'z']);
+
+        let mut reader = BinaryReader::new(&data);
+        let first = reader.string().unwrap();
+        assert_eq!(
+            (first.offset, first.size, &first.raw[..]),
+            (0, 8, &b"abc"[..])
+        );
+        assert!(reader.string().unwrap().raw.is_empty());
+        assert!(reader.string().unwrap().raw.is_empty());
+        assert!(matches!(
+            reader.string(),
+            Err(WolfRpgError::InvalidStructure(_))
+        ));
+    }
+
+    #[test]
+    fn test_count_checks_size() {
+        let data = [0xFF, 0xFF, 0, 0, 1, 2, 3, 4];
+        assert!(BinaryReader::new(&data).count(4).is_err());
+        assert_eq!(BinaryReader::new(&[1, 0, 0, 0, 9]).count(1).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_splice() {
+        let mut data = vec![0xAA];
+        data.extend(encode_string(b"one"));
+        data.push(0xBB);
+        data.extend(encode_string(b"two"));
+        data.push(0xCC);
+
+        let mut reader = BinaryReader::new(&data);
+        reader.skip(1).unwrap();
+        let one = reader.string().unwrap();
+        reader.skip(1).unwrap();
+        let two = reader.string().unwrap();
+
+        let mut splicer = Splicer::new();
+        splicer.replace(&two, b"2");
+        splicer.replace(&one, b"eins");
+        let out = splicer.apply(&data);
+
+        let mut expected = vec![0xAA];
+        expected.extend(encode_string(b"eins"));
+        expected.push(0xBB);
+        expected.extend(encode_string(b"2"));
+        expected.push(0xCC);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_encoding() {
+        let sjis = WolfEncoding::ShiftJis.encode("はい").unwrap();
+        assert_eq!(sjis, vec![0x82, 0xCD, 0x82, 0xA2]);
+        assert_eq!(WolfEncoding::ShiftJis.decode(&sjis).unwrap(), "はい");
+        assert!(WolfEncoding::ShiftJis.encode("한국어").is_none());
+
+        assert_eq!(
+            WolfEncoding::detect([&b"ascii"[..], &sjis[..]]),
+            WolfEncoding::ShiftJis
+        );
+        assert_eq!(
+            WolfEncoding::detect([&b"ascii"[..], "はい".as_bytes()]),
+            WolfEncoding::Utf8
+        );
+        assert_eq!(
+            WolfEncoding::detect([&b"ascii"[..]]),
+            WolfEncoding::ShiftJis
+        );
+    }
+}
@@ -0,0 +1,443 @@
+//! Event commands of Wolf RPG Editor maps and common events
+//!
+//! ```text
+//! u8   argument count + 1
+//! u32  command code
+//! u32  arguments
+//! u8   indent
+//! u8   string count
+//!      strings
+//! u8   0, or 1 followed by a move route (move commands)
+//! ```
+
+use std::collections::HashMap;
+
+use super::binary::{BinaryReader, Splicer, WolfEncoding, WolfString};
+use super::WolfRpgError;
+use crate::parser::rpg_maker_mv_mz::FileInjectionResult;
+use crate::parser::types::{
+    EventCode, ExtractionContext, ExtractionOptions, InjectionOptions, TranslationPath,
+    TranslationUnit,
+};
+
+/// Show a message
+pub const CODE_MESSAGE: u32 = 101;
+/// Show choices, one string per choice
+pub const CODE_CHOICES: u32 = 102;
+/// Comment
+pub const CODE_COMMENT: u32 = 103;
+/// Set a string variable
+pub const CODE_SET_STRING: u32 = 122;
+/// Show a picture (text pictures carry their text)
+pub const CODE_PICTURE: u32 = 150;
+
+/// Picture type of a text picture, in bits 4-6 of the first argument
+const PICTURE_TYPE_TEXT: u32 = 2;
+
+/// Terminator of a plain command
+const TERMINATOR: u8 = 0;
+/// Terminator of a command followed by a move route
+const TERMINATOR_MOVE: u8 = 1;
+
+/// Single event command
+#[derive(Debug, Clone)]
+pub struct Command {
+    /// Command code
+    pub code: u32,
+    /// Integer arguments
+    pub args: Vec<u32>,
+    /// Indent level
+    pub indent: u8,
+    /// String arguments
+    pub strings: Vec<WolfString>,
+}
+
+impl Command {
+    /// Read a command
+    pub fn read(reader: &mut BinaryReader) -> Result<Self, WolfRpgError> {
+        let arg_count = (reader.u8()? as usize).checked_sub(1).ok_or_else(|| {
+            WolfRpgError::InvalidStructure(format!(
+                "command without a code at offset {:#x}",
+                reader.position() - 1
+            ))
+        })?;
+        let code = reader.u32()?;
+        let args = (0..arg_count)
+            .map(|_| reader.u32())
+            .collect::<Result<_, _>>()?;
+        let indent = reader.u8()?;
+        let string_count = reader.u8()? as usize;
+        let strings = reader.strings(string_count)?;
+
+        match reader.u8()? {
+            TERMINATOR => {}
+            TERMINATOR_MOVE => {
+                // Unknown bytes and route flags, then the route
+                reader.skip(6)?;
+                read_route(reader)?;
+            }
+            other => {
+                return Err(WolfRpgError::InvalidStructure(format!(
+                    "unexpected command terminator {:#x} at offset {:#x}",
+                    other,
+                    reader.position() - 1
+                )))
+            }
+        }
+
+        Ok(Self {
+            code,
+            args,
+            indent,
+            strings,
+        })
+    }
+
+    /// Read a counted command list
+    pub fn read_list(reader: &mut BinaryReader) -> Result<Vec<Self>, WolfRpgError> {
+        let count = reader.count(8)?;
+        (0..count).map(|_| Self::read(reader)).collect()
+    }
+
+    /// Strings of this command that may hold text, with their kind
+    fn text_strings(&self) -> Vec<(usize, TextKind)> {
+        match self.code {
+            CODE_MESSAGE if !self.strings.is_empty() => vec![(0, TextKind::Message)],
+            CODE_CHOICES => (0..self.strings.len())
+                .map(|i| (i, TextKind::Choice))
+                .collect(),
+            CODE_COMMENT if !self.strings.is_empty() => vec![(0, TextKind::Comment)],
+            CODE_SET_STRING if !self.strings.is_empty() => vec![(0, TextKind::String)],
+            CODE_PICTURE
+                if !self.strings.is_empty()
+                    && self
+                        .args
+                        .first()
+                        .is_some_and(|a| (a >> 4) & 0x07 == PICTURE_TYPE_TEXT) =>
+            {
+                vec![(0, TextKind::Picture)]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Read a counted move route
+///
+/// Each route command is an id byte, a counted list of `u32` arguments
+/// and the terminator `[1, 0]`.
+pub fn read_route(reader: &mut BinaryReader) -> Result<(), WolfRpgError> {
+    let count = reader.count(4)?;
+    for _ in 0..count {
+        reader.u8()?;
+        let arg_count = reader.u8()? as usize;
+        reader.skip(arg_count * 4)?;
+        reader.expect(&[1, 0], "move route command terminator")?;
+    }
+    Ok(())
+}
+
+/// Kind of text a command string holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextKind {
+    Message,
+    Choice,
+    Comment,
+    /// String variable; only extracted when it looks like text
+    String,
+    /// Text picture; only extracted when it looks like text
+    Picture,
+}
+
+impl TextKind {
+    fn code(&self) -> EventCode {
+        match self {
+            Self::Message => EventCode::ShowTextBody,
+            Self::Choice => EventCode::ShowChoices,
+            Self::Comment => EventCode::CommentBody,
+            Self::String => EventCode::Unknown(CODE_SET_STRING as i32),
+            Self::Picture => EventCode::Unknown(CODE_PICTURE as i32),
+        }
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            Self::Message => "message",
+            Self::Choice => "choice",
+            Self::Comment => "comment",
+            Self::String => "string",
+            Self::Picture => "picture",
+        }
+    }
+}
+
+/// Path of a command string
+fn string_path(base: &TranslationPath, command: usize, string: usize) -> TranslationPath {
+    base.append_key("commands")
+        .append_index(command)
+        .append_key("strings")
+        .append_index(string)
+}
+
+/// Extract the text of a command list
+///
+/// Undecodable strings are skipped with a warning.
+pub fn extract_commands(
+    commands: &[Command],
+    base: &TranslationPath,
+    context: &mut ExtractionContext,
+    options: &ExtractionOptions,
+    encoding: WolfEncoding,
+    warnings: &mut Vec<String>,
+) -> Vec<TranslationUnit> {
+    let mut units = Vec::new();
+
+    for (cmd_idx, command) in commands.iter().enumerate() {
+        for (str_idx, kind) in command.text_strings() {
+            let path = string_path(base, cmd_idx, str_idx);
+            let Some(text) = command.strings[str_idx].decode(encoding) else {
+                warnings.push(format!("Cannot decode string at {}", path));
+                continue;
+            };
+
+            if kind == TextKind::Comment
+                && (!options.extract_comments || options.should_skip_comment(&text))
+            {
+                continue;
+            }
+            if text.trim().is_empty() && !options.include_empty {
+                continue;
+            }
+            let text = if options.trim_whitespace {
+                text.trim().to_string()
+            } else {
+                text
+            };
+
+            let unit =
+                TranslationUnit::new(path.to_unit_id(kind.suffix()), path, kind.code(), text)
+                    .with_context(context.to_translation_context());
+            if matches!(kind, TextKind::String | TextKind::Picture) && !unit.needs_translation() {
+                continue;
+            }
+
+            if kind == TextKind::Message {
+                context.add_preceding_line(unit.original.clone());
+            }
+            units.push(unit);
+        }
+    }
+
+    units
+}
+
+/// Queue the translations of a command list for splicing
+pub fn inject_commands(
+    commands: &[Command],
+    base: &TranslationPath,
+    translations: &HashMap<String, String>,
+    options: &InjectionOptions,
+    encoding: WolfEncoding,
+    splicer: &mut Splicer,
+    result: &mut FileInjectionResult,
+) {
+    for (cmd_idx, command) in commands.iter().enumerate() {
+        let mut modified = false;
+
+        for (str_idx, kind) in command.text_strings() {
+            let id = string_path(base, cmd_idx, str_idx).to_unit_id(kind.suffix());
+            let Some(translated) = translations.get(&id) else {
+                // Strings and comments are only extracted when they hold text
+                let always_extracted = matches!(kind, TextKind::Message | TextKind::Choice);
+                if always_extracted && !options.skip_missing_translations {
+                    result.not_found += 1;
+                }
+                continue;
+            };
+
+            let text = if kind == TextKind::Message && options.max_line_length.is_some() {
+                options.split_text(translated).join("\n")
+            } else {
+                translated.clone()
+            };
+            if replace_string(
+                &command.strings[str_idx],
+                &id,
+                &text,
+                encoding,
+                splicer,
+                result,
+            ) {
+                modified = true;
+            }
+        }
+
+        if modified {
+            result.commands_modified += 1;
+        }
+    }
+}
+
+/// Queue a string replacement, warning if the text cannot be encoded
+pub(super) fn replace_string(
+    string: &WolfString,
+    id: &str,
+    text: &str,
+    encoding: WolfEncoding,
+    splicer: &mut Splicer,
+    result: &mut FileInjectionResult,
+) -> bool {
+    let Some(raw) = encoding.encode(text) else {
+        result.warnings.push(format!(
+            "Translation of {} cannot be encoded as {:?}",
+            id, encoding
+        ));
+        return false;
+    };
+
+    splicer.replace(string, &raw);
+    result.applied += 1;
+    result.modified = true;
+    true
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::super::binary::encode_string;
+    use super::*;
+
+    /// Encode a plain command
+    pub(in crate::parser::wolf_rpg) fn command(
+        code: u32,
+        args: &[u32],
+        strings: &[&str],
+    ) -> Vec<u8> {
+        let mut out = vec![args.len() as u8 + 1];
+        out.extend_from_slice(&code.to_le_bytes());
+        for arg in args {
+            out.extend_from_slice(&arg.to_le_bytes());
+        }
+        out.push(0);
+        out.push(strings.len() as u8);
+        for string in strings {
+            out.extend(encode_string(
+                &WolfEncoding::ShiftJis.encode(string).unwrap(),
+            ));
+        }
+        out.push(TERMINATOR);
+        out
+    }
+
+    /// Encode a move command with a one-step route
+    pub(in crate::parser::wolf_rpg) fn move_command() -> Vec<u8> {
+        let mut out = command(201, &[0], &[]);
+        *out.last_mut().unwrap() = TERMINATOR_MOVE;
+        out.extend_from_slice(&[0; 5]);
+        out.push(0x01);
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.extend_from_slice(&[0x02, 1]);
+        out.extend_from_slice(&5u32.to_le_bytes());
+        out.extend_from_slice(&[1, 0]);
+        out
+    }
+
+    /// Encode a counted command list
+    pub(in crate::parser::wolf_rpg) fn command_list(commands: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = (commands.len() as u32).to_le_bytes().to_vec();
+        for command in commands {
+            out.extend_from_slice(command);
+        }
+        out
+    }
+
+    fn sample_list() -> Vec<u8> {
+        command_list(&[
+            command(CODE_MESSAGE, &[], &["こんにちは\n元気？"]),
+            move_command(),
+            command(CODE_CHOICES, &[2], &["はい", "いいえ"]),
+            command(CODE_COMMENT, &[], &[";メモ"]),
+            command(CODE_SET_STRING, &[1600000, 0], &["Picture/face.png"]),
+            command(CODE_SET_STRING, &[1600001, 0], &["勇者"]),
+            command(CODE_PICTURE, &[0x20, 1], &["看板の文字"]),
+            command(CODE_PICTURE, &[0x00, 1], &["Picture/看板.png"]),
+            command(0, &[], &[]),
+        ])
+    }
+
+    #[test]
+    fn test_extract_commands() {
+        let data = sample_list();
+        let commands = Command::read_list(&mut BinaryReader::new(&data)).unwrap();
+        assert_eq!(commands.len(), 9);
+
+        let mut context = ExtractionContext::new("Map001.mps");
+        let mut warnings = Vec::new();
+        let units = extract_commands(
+            &commands,
+            &TranslationPath::new(),
+            &mut context,
+            &ExtractionOptions::default(),
+            WolfEncoding::ShiftJis,
+            &mut warnings,
+        );
+
+        let texts: Vec<(&str, &str)> = units
+            .iter()
+            .map(|u| (u.id.as_str(), u.original.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("commands.0.strings.0_message", "こんにちは\n元気？"),
+                ("commands.2.strings.0_choice", "はい"),
+                ("commands.2.strings.1_choice", "いいえ"),
+                ("commands.5.strings.0_string", "勇者"),
+                ("commands.6.strings.0_picture", "看板の文字"),
+            ]
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_inject_commands() {
+        let data = sample_list();
+        let commands = Command::read_list(&mut BinaryReader::new(&data)).unwrap();
+
+        let mut translations = HashMap::new();
+        translations.insert(
+            "commands.0.strings.0_message".to_string(),
+            "Hello\nHow are you?".to_string(),
+        );
+        translations.insert("commands.2.strings.1_choice".to_string(), "No".to_string());
+        translations.insert(
+            "commands.5.strings.0_string".to_string(),
+            "영웅".to_string(),
+        );
+
+        let mut splicer = Splicer::new();
+        let mut result = FileInjectionResult::new();
+        inject_commands(
+            &commands,
+            &TranslationPath::new(),
+            &translations,
+            &InjectionOptions::default(),
+            WolfEncoding::ShiftJis,
+            &mut splicer,
+            &mut result,
+        );
+        assert_eq!(result.applied, 2);
+        assert_eq!(result.commands_modified, 2);
+        assert_eq!(result.warnings.len(), 1);
+
+        let out = splicer.apply(&data);
+        let commands = Command::read_list(&mut BinaryReader::new(&out)).unwrap();
+        assert_eq!(commands[0].strings[0].raw, b"Hello\nHow are you?");
+        assert_eq!(commands[2].strings[1].raw, b"No");
+        assert_eq!(
+            commands[5].strings[0]
+                .decode(WolfEncoding::ShiftJis)
+                .unwrap(),
+            "勇者"
+        );
+    }
+}
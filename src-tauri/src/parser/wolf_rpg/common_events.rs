@@ -0,0 +1,408 @@
+//! Parser for Wolf RPG Editor common events (`BasicData/CommonEvent.dat`)
+//!
+//! The file is a magic number, the event count, the events and a final
+//! `0x8F`. Each event is framed by `0x8E` and `0x8F` around its id, name,
+//! command list (see [`super::command`]) and description, followed by the
+//! argument and return value settings, ended by `0x91` or `0x92` blocks.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::binary::{BinaryReader, Splicer, WolfEncoding, WolfString};
+use super::command::{self, Command};
+use super::WolfRpgError;
+use crate::archiver::vfs::GameVfs;
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile, TranslationPath,
+};
+
+/// Magic at the start of the file
+const MAGIC: &[u8] = &[
+    0x00, 0x57, 0x00, 0x00, 0x4F, 0x4C, 0x00, 0x46, 0x43, 0x00, 0x8F,
+];
+/// Marker of the start of an event
+const EVENT_START: u8 = 0x8E;
+/// Marker after the event body, and at the end of the file
+const EVENT_END: u8 = 0x8F;
+/// Header of each block of the ten argument settings
+const ARGUMENT_BLOCK: &[u8] = &[0x0A, 0x00, 0x00, 0x00];
+/// Marker of the end of the event settings
+const SETTINGS_END: u8 = 0x91;
+/// Marker framing the return value settings of newer versions
+const RETURN_VALUE: u8 = 0x92;
+
+/// Common event
+#[derive(Debug, Clone)]
+pub struct CommonEvent {
+    /// Event id
+    pub id: u32,
+    /// Event name
+    pub name: WolfString,
+    /// Command list
+    pub commands: Vec<Command>,
+}
+
+/// Parse the events of a common event file
+pub fn parse_common_events(data: &[u8]) -> Result<Vec<CommonEvent>, WolfRpgError> {
+    let mut reader = BinaryReader::new(data);
+    reader.expect(MAGIC, "common event magic")?;
+
+    let count = reader.count(1)?;
+    let events = (0..count)
+        .map(|_| read_event(&mut reader))
+        .collect::<Result<_, _>>()?;
+    reader.expect(&[EVENT_END], "common event file terminator")?;
+
+    Ok(events)
+}
+
+fn read_event(reader: &mut BinaryReader) -> Result<CommonEvent, WolfRpgError> {
+    reader.expect(&[EVENT_START], "common event marker")?;
+    let id = reader.u32()?;
+    reader.skip(4 + 7)?;
+    let name = reader.string()?;
+    let commands = Command::read_list(reader)?;
+    // Unknown string and the description
+    reader.strings(2)?;
+    reader.expect(&[EVENT_END], "common event body terminator")?;
+
+    // Argument names, types, choice lists and choice values
+    reader.expect(ARGUMENT_BLOCK, "argument names")?;
+    reader.strings(10)?;
+    reader.expect(ARGUMENT_BLOCK, "argument types")?;
+    reader.skip(10)?;
+    reader.expect(ARGUMENT_BLOCK, "argument choices")?;
+    for _ in 0..10 {
+        let choices = reader.count(4)?;
+        reader.strings(choices)?;
+    }
+    reader.expect(ARGUMENT_BLOCK, "argument choice values")?;
+    for _ in 0..10 {
+        let values = reader.count(4)?;
+        reader.skip(values * 4)?;
+    }
+
+    // Default values and flags, then the self variable names
+    reader.skip(0x1D)?;
+    reader.strings(100)?;
+    reader.expect(&[SETTINGS_END], "common event settings terminator")?;
+    // Memo color or label
+    reader.string()?;
+
+    match reader.u8()? {
+        SETTINGS_END => {}
+        RETURN_VALUE => {
+            reader.string()?;
+            reader.u32()?;
+            reader.expect(&[RETURN_VALUE], "return value terminator")?;
+        }
+        other => {
+            return Err(WolfRpgError::InvalidStructure(format!(
+                "unexpected common event terminator {:#x} at offset {:#x}",
+                other,
+                reader.position() - 1
+            )))
+        }
+    }
+
+    Ok(CommonEvent { id, name, commands })
+}
+
+/// Parser for CommonEvent.dat
+pub struct CommonEventsParser {
+    /// Text encoding, detected per file if not set
+    encoding: Option<WolfEncoding>,
+}
+
+impl CommonEventsParser {
+    /// Create a new common event parser
+    pub fn new() -> Self {
+        Self { encoding: None }
+    }
+
+    /// Use a fixed text encoding instead of detecting it
+    pub fn with_encoding(mut self, encoding: WolfEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    fn encoding_for(&self, events: &[CommonEvent]) -> WolfEncoding {
+        self.encoding.unwrap_or_else(|| {
+            WolfEncoding::detect(events.iter().flat_map(|event| {
+                std::iter::once(&event.name.raw[..]).chain(
+                    event
+                        .commands
+                        .iter()
+                        .flat_map(|c| c.strings.iter().map(|s| &s.raw[..])),
+                )
+            }))
+        })
+    }
+
+    /// Extract translations from CommonEvent.dat content
+    pub fn extract(
+        &self,
+        data: &[u8],
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, WolfRpgError> {
+        let mut result = FileExtractionResult::new(file_name);
+        let events = parse_common_events(data)?;
+        let encoding = self.encoding_for(&events);
+
+        let base_context =
+            ExtractionContext::new(file_name).with_max_preceding_lines(options.max_preceding_lines);
+
+        for (event_idx, event) in events.iter().enumerate() {
+            let mut context =
+                base_context.for_event(event.id as usize, event.name.decode(encoding));
+            let event_path = TranslationPath::new()
+                .append_key("events")
+                .append_index(event_idx);
+
+            let units = command::extract_commands(
+                &event.commands,
+                &event_path,
+                &mut context,
+                options,
+                encoding,
+                &mut result.warnings,
+            );
+            result.add_units(units);
+        }
+
+        Ok(result)
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, WolfRpgError> {
+        let data = fs::read(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("CommonEvent.dat");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside a mounted archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, WolfRpgError> {
+        let data = vfs.read(path)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("CommonEvent.dat");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Inject translations into CommonEvent.dat content
+    pub fn inject(
+        &self,
+        data: &mut Vec<u8>,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, WolfRpgError> {
+        let mut result = FileInjectionResult::new();
+        let events = parse_common_events(data)?;
+        let encoding = self.encoding_for(&events);
+
+        let mut splicer = Splicer::new();
+        for (event_idx, event) in events.iter().enumerate() {
+            let event_path = TranslationPath::new()
+                .append_key("events")
+                .append_index(event_idx);
+
+            command::inject_commands(
+                &event.commands,
+                &event_path,
+                translations,
+                options,
+                encoding,
+                &mut splicer,
+                &mut result,
+            );
+        }
+
+        if !splicer.is_empty() {
+            *data = splicer.apply(data);
+        }
+        Ok(result)
+    }
+
+    /// Inject translations to a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, WolfRpgError> {
+        let mut data = fs::read(path)?;
+
+        let result = self.inject(&mut data, translations, options)?;
+
+        if result.modified {
+            fs::write(path, data)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+impl Default for CommonEventsParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::binary::encode_string;
+    use super::super::command::tests::{command, command_list};
+    use super::super::command::{CODE_COMMENT, CODE_MESSAGE};
+    use super::*;
+
+    fn encode_event(
+        out: &mut Vec<u8>,
+        id: u32,
+        name: &str,
+        commands: &[Vec<u8>],
+        return_value: bool,
+    ) {
+        out.push(EVENT_START);
+        out.extend_from_slice(&id.to_le_bytes());
+        out.extend_from_slice(&[0; 4 + 7]);
+        out.extend(encode_string(&WolfEncoding::ShiftJis.encode(name).unwrap()));
+        out.extend(command_list(commands));
+        out.extend(encode_string(b""));
+        out.extend(encode_string(
+            &WolfEncoding::ShiftJis.encode("説明").unwrap(),
+        ));
+        out.push(EVENT_END);
+
+        out.extend_from_slice(ARGUMENT_BLOCK);
+        for _ in 0..10 {
+            out.extend(encode_string(b""));
+        }
+        out.extend_from_slice(ARGUMENT_BLOCK);
+        out.extend_from_slice(&[0; 10]);
+        out.extend_from_slice(ARGUMENT_BLOCK);
+        out.extend_from_slice(&[0; 10 * 4]);
+        out.extend_from_slice(ARGUMENT_BLOCK);
+        out.extend_from_slice(&[0; 10 * 4]);
+        out.extend_from_slice(&[0; 0x1D]);
+        for _ in 0..100 {
+            out.extend(encode_string(b""));
+        }
+        out.push(SETTINGS_END);
+        out.extend(encode_string(b""));
+
+        if return_value {
+            out.push(RETURN_VALUE);
+            out.extend(encode_string(b""));
+            out.extend_from_slice(&0u32.to_le_bytes());
+            out.push(RETURN_VALUE);
+        } else {
+            out.push(SETTINGS_END);
+        }
+    }
+
+    fn sample_common_events() -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.extend_from_slice(&2u32.to_le_bytes());
+        encode_event(
+            &mut out,
+            0,
+            "○会話",
+            &[
+                command(CODE_COMMENT, &[], &["会話イベント"]),
+                command(CODE_MESSAGE, &[], &["やあ"]),
+            ],
+            false,
+        );
+        encode_event(
+            &mut out,
+            1,
+            "○戦闘",
+            &[command(CODE_MESSAGE, &[], &["敵が現れた！"])],
+            true,
+        );
+        out.push(EVENT_END);
+        out
+    }
+
+    #[test]
+    fn test_extract_common_events() {
+        let data = sample_common_events();
+        let result = CommonEventsParser::new()
+            .extract(&data, "CommonEvent.dat", &ExtractionOptions::default())
+            .unwrap();
+
+        let texts: Vec<(&str, &str)> = result
+            .units
+            .iter()
+            .map(|u| (u.id.as_str(), u.original.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("events.0.commands.0.strings.0_comment", "会話イベント"),
+                ("events.0.commands.1.strings.0_message", "やあ"),
+                ("events.1.commands.0.strings.0_message", "敵が現れた！"),
+            ]
+        );
+        assert_eq!(result.units[2].context.event_name.as_deref(), Some("○戦闘"));
+    }
+
+    #[test]
+    fn test_inject_common_events() {
+        let mut data = sample_common_events();
+        let mut translations = HashMap::new();
+        translations.insert(
+            "events.0.commands.1.strings.0_message".to_string(),
+            "Hi".to_string(),
+        );
+        translations.insert(
+            "events.1.commands.0.strings.0_message".to_string(),
+            "An enemy appears!".to_string(),
+        );
+
+        let result = CommonEventsParser::new()
+            .inject(&mut data, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert_eq!(result.applied, 2);
+
+        let events = parse_common_events(&data).unwrap();
+        assert_eq!(events[0].commands[1].strings[0].raw, b"Hi");
+        assert_eq!(events[1].commands[0].strings[0].raw, b"An enemy appears!");
+    }
+
+    #[test]
+    fn test_invalid_common_events() {
+        let mut data = sample_common_events();
+        data[0] = 0xFF;
+        assert!(matches!(
+            parse_common_events(&data),
+            Err(WolfRpgError::InvalidStructure(_))
+        ));
+    }
+}
@@ -0,0 +1,569 @@
+//! Parser for Wolf RPG Editor databases (`BasicData/*DataBase.project/.dat`)
+//!
+//! Each database (`DataBase`, `CDataBase`, `SysDatabase`) is split in two
+//! files. The `.project` file lists the types with their field names, data
+//! names and field settings:
+//!
+//! ```text
+//! u32 type count, then per type:
+//!   name, counted field names, counted data names, description
+//!   counted field type bytes, then counted lists of per-field strings,
+//!   string parameters, int parameters and default values
+//! ```
+//!
+//! The `.dat` file holds the values. After a magic number and the type
+//! count, each type is `[0xFE, 0xFF, 0xFF, 0xFF]`, an unknown `u32`, the
+//! counted field index infos and the counted data. A datum stores the
+//! values of its int fields (index info 1000-1999) followed by those of its
+//! string fields (2000 and above).
+//!
+//! Data names and string values are extracted when they contain CJK text;
+//! databases also hold file names and identifiers that must stay as-is.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::binary::{BinaryReader, Splicer, WolfEncoding, WolfString};
+use super::command::replace_string;
+use super::WolfRpgError;
+use crate::archiver::vfs::GameVfs;
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    EventCode, ExtractionOptions, InjectionOptions, TranslationContext, TranslationFile,
+    TranslationPath, TranslationUnit,
+};
+
+/// Magic at the start of a `.dat` file
+const DAT_MAGIC: &[u8] = &[0x57, 0x00, 0x00, 0x4F, 0x4C, 0x00, 0x46, 0x4D, 0x00];
+/// Separator before each type of a `.dat` file
+const DAT_TYPE_SEPARATOR: &[u8] = &[0xFE, 0xFF, 0xFF, 0xFF];
+/// First index info of int fields
+const INT_FIELD_START: u32 = 1000;
+/// First index info of string fields
+const STRING_FIELD_START: u32 = 2000;
+
+/// Type of a database, from the `.project` file
+#[derive(Debug, Clone)]
+pub struct ProjectType {
+    /// Type name
+    pub name: WolfString,
+    /// Field names
+    pub fields: Vec<WolfString>,
+    /// Data names
+    pub data: Vec<WolfString>,
+}
+
+/// Values of a database type, from the `.dat` file
+#[derive(Debug, Clone)]
+pub struct DatType {
+    /// Index info of each field
+    pub fields: Vec<u32>,
+    /// String values of each datum, with their field index
+    pub data: Vec<Vec<(usize, WolfString)>>,
+}
+
+/// Parse the types of a `.project` file
+pub fn parse_project(data: &[u8]) -> Result<Vec<ProjectType>, WolfRpgError> {
+    let mut reader = BinaryReader::new(data);
+    let count = reader.count(4)?;
+    (0..count).map(|_| read_project_type(&mut reader)).collect()
+}
+
+fn read_project_type(reader: &mut BinaryReader) -> Result<ProjectType, WolfRpgError> {
+    let name = reader.string()?;
+    let field_count = reader.count(4)?;
+    let fields = reader.strings(field_count)?;
+    let data_count = reader.count(4)?;
+    let data = reader.strings(data_count)?;
+    // Description
+    reader.string()?;
+
+    // Field types
+    let type_list_size = reader.count(1)?;
+    reader.skip(type_list_size)?;
+    // Per-field strings
+    let count = reader.count(4)?;
+    reader.strings(count)?;
+    // String parameters
+    for _ in 0..reader.count(4)? {
+        let count = reader.count(4)?;
+        reader.strings(count)?;
+    }
+    // Int parameters
+    for _ in 0..reader.count(4)? {
+        let count = reader.count(4)?;
+        reader.skip(count * 4)?;
+    }
+    // Default values
+    let count = reader.count(4)?;
+    reader.skip(count * 4)?;
+
+    Ok(ProjectType { name, fields, data })
+}
+
+/// Parse the types of a `.dat` file
+pub fn parse_dat(data: &[u8]) -> Result<Vec<DatType>, WolfRpgError> {
+    let mut reader = BinaryReader::new(data);
+    reader.expect(DAT_MAGIC, "database magic")?;
+    let count = reader.count(4)?;
+    (0..count).map(|_| read_dat_type(&mut reader)).collect()
+}
+
+fn read_dat_type(reader: &mut BinaryReader) -> Result<DatType, WolfRpgError> {
+    reader.expect(DAT_TYPE_SEPARATOR, "database type separator")?;
+    reader.u32()?;
+
+    let field_count = reader.count(4)?;
+    let fields = (0..field_count)
+        .map(|_| reader.u32())
+        .collect::<Result<Vec<_>, _>>()?;
+    let int_count = fields
+        .iter()
+        .filter(|&&info| (INT_FIELD_START..STRING_FIELD_START).contains(&info))
+        .count();
+    let string_fields: Vec<usize> = (0..fields.len())
+        .filter(|&i| fields[i] >= STRING_FIELD_START)
+        .collect();
+
+    let data_count = reader.count((int_count + string_fields.len()) * 4)?;
+    let mut data = Vec::with_capacity(data_count);
+    for _ in 0..data_count {
+        reader.skip(int_count * 4)?;
+        let values = string_fields
+            .iter()
+            .map(|&field| Ok((field, reader.string()?)))
+            .collect::<Result<_, WolfRpgError>>()?;
+        data.push(values);
+    }
+
+    Ok(DatType { fields, data })
+}
+
+/// Translatable string of a database
+struct DatabaseText<'a> {
+    unit: TranslationUnit,
+    string: &'a WolfString,
+    /// Whether the string is in the `.project` file
+    in_project: bool,
+}
+
+/// Parser for database `.project`/`.dat` pairs
+pub struct DatabaseParser {
+    /// Text encoding, detected per database if not set
+    encoding: Option<WolfEncoding>,
+}
+
+impl DatabaseParser {
+    /// Create a new database parser
+    pub fn new() -> Self {
+        Self { encoding: None }
+    }
+
+    /// Use a fixed text encoding instead of detecting it
+    pub fn with_encoding(mut self, encoding: WolfEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    fn encoding_for(&self, project: &[ProjectType], dat: &[DatType]) -> WolfEncoding {
+        self.encoding.unwrap_or_else(|| {
+            let names = project
+                .iter()
+                .flat_map(|t| std::iter::once(&t.name).chain(&t.fields).chain(&t.data));
+            let values = dat
+                .iter()
+                .flat_map(|t| t.data.iter().flatten().map(|(_, s)| s));
+            WolfEncoding::detect(names.chain(values).map(|s| &s.raw[..]))
+        })
+    }
+
+    /// Collect the strings of a database that need translation
+    fn texts<'a>(
+        &self,
+        project: &'a [ProjectType],
+        dat: &'a [DatType],
+        encoding: WolfEncoding,
+        file_name: &str,
+        warnings: &mut Vec<String>,
+    ) -> Result<Vec<DatabaseText<'a>>, WolfRpgError> {
+        if project.len() != dat.len() {
+            return Err(WolfRpgError::InvalidStructure(format!(
+                "project has {} types but dat has {}",
+                project.len(),
+                dat.len()
+            )));
+        }
+
+        let mut texts = Vec::new();
+        let mut push = |path: TranslationPath,
+                        suffix: &str,
+                        string: &'a WolfString,
+                        tags: &[&str]| {
+            let Some(text) = string.decode(encoding) else {
+                warnings.push(format!("Cannot decode string at {}", path));
+                return;
+            };
+            let mut context = TranslationContext::new().with_file_name(file_name);
+            for tag in tags.iter().filter(|tag| !tag.is_empty()) {
+                context.add_tag(*tag);
+            }
+            let unit =
+                TranslationUnit::new(path.to_unit_id(suffix), path, EventCode::Unknown(0), text)
+                    .with_context(context);
+            if unit.needs_translation() {
+                texts.push(DatabaseText {
+                    unit,
+                    string,
+                    in_project: suffix == "name",
+                });
+            }
+        };
+
+        for (type_idx, (project_type, dat_type)) in project.iter().zip(dat).enumerate() {
+            let type_path = TranslationPath::new()
+                .append_key("types")
+                .append_index(type_idx);
+            let type_name = project_type.name.decode(encoding).unwrap_or_default();
+
+            for (data_idx, name) in project_type.data.iter().enumerate() {
+                let path = type_path.append_key("data").append_index(data_idx);
+                push(path, "name", name, &[&type_name]);
+            }
+
+            for (data_idx, values) in dat_type.data.iter().enumerate() {
+                for (field_idx, value) in values {
+                    let path = type_path
+                        .append_key("data")
+                        .append_index(data_idx)
+                        .append_key("values")
+                        .append_index(*field_idx);
+                    let field_name = project_type
+                        .fields
+                        .get(*field_idx)
+                        .and_then(|f| f.decode(encoding))
+                        .unwrap_or_default();
+                    push(path, "value", value, &[&type_name, &field_name]);
+                }
+            }
+        }
+
+        Ok(texts)
+    }
+
+    /// Extract translations from the content of a `.project`/`.dat` pair
+    pub fn extract(
+        &self,
+        project: &[u8],
+        dat: &[u8],
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, WolfRpgError> {
+        let mut result = FileExtractionResult::new(file_name);
+        let project = parse_project(project)?;
+        let dat = parse_dat(dat)?;
+        let encoding = self.encoding_for(&project, &dat);
+        let texts = self.texts(&project, &dat, encoding, file_name, &mut result.warnings)?;
+
+        let units = texts
+            .into_iter()
+            .map(|t| {
+                let mut unit = t.unit;
+                if options.trim_whitespace {
+                    unit.original = unit.original.trim().to_string();
+                }
+                unit
+            })
+            .collect();
+        result.add_units(units);
+
+        Ok(result)
+    }
+
+    /// Extract from a `.project` file and the `.dat` file next to it
+    pub fn extract_file(
+        &self,
+        project_path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, WolfRpgError> {
+        let project = fs::read(project_path)?;
+        let dat = fs::read(project_path.with_extension("dat"))?;
+
+        let file_name = project_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("DataBase.project");
+
+        self.extract(&project, &dat, file_name, options)
+    }
+
+    /// Extract from a `.project` file in a game's virtual filesystem
+    ///
+    /// The files may be loose on disk or inside a mounted archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        project_path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, WolfRpgError> {
+        let project = vfs.read(project_path)?;
+        let dat_path = Path::new(project_path).with_extension("dat");
+        let dat = vfs.read(&dat_path.to_string_lossy())?;
+
+        let file_name = project_path
+            .rsplit('/')
+            .next()
+            .unwrap_or("DataBase.project");
+
+        self.extract(&project, &dat, file_name, options)
+    }
+
+    /// Inject translations into the content of a `.project`/`.dat` pair
+    pub fn inject(
+        &self,
+        project: &mut Vec<u8>,
+        dat: &mut Vec<u8>,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, WolfRpgError> {
+        let mut result = FileInjectionResult::new();
+        let project_types = parse_project(project)?;
+        let dat_types = parse_dat(dat)?;
+        let encoding = self.encoding_for(&project_types, &dat_types);
+        let texts = self.texts(
+            &project_types,
+            &dat_types,
+            encoding,
+            "",
+            &mut result.warnings,
+        )?;
+
+        let mut project_splicer = Splicer::new();
+        let mut dat_splicer = Splicer::new();
+        for text in &texts {
+            let id = &text.unit.id;
+            let Some(translated) = translations.get(id) else {
+                if !options.skip_missing_translations {
+                    result.not_found += 1;
+                }
+                continue;
+            };
+
+            let splicer = if text.in_project {
+                &mut project_splicer
+            } else {
+                &mut dat_splicer
+            };
+            if replace_string(text.string, id, translated, encoding, splicer, &mut result) {
+                result.commands_modified += 1;
+            }
+        }
+
+        if !project_splicer.is_empty() {
+            *project = project_splicer.apply(project);
+        }
+        if !dat_splicer.is_empty() {
+            *dat = dat_splicer.apply(dat);
+        }
+        Ok(result)
+    }
+
+    /// Inject translations to a `.project` file and the `.dat` file next to it
+    pub fn inject_file(
+        &self,
+        project_path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, WolfRpgError> {
+        let dat_path = project_path.with_extension("dat");
+        let mut project = fs::read(project_path)?;
+        let mut dat = fs::read(&dat_path)?;
+
+        let result = self.inject(&mut project, &mut dat, translations, options)?;
+
+        if result.modified {
+            fs::write(project_path, project)?;
+            fs::write(&dat_path, dat)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+impl Default for DatabaseParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::binary::encode_string;
+    use super::*;
+
+    fn sjis(text: &str) -> Vec<u8> {
+        encode_string(&WolfEncoding::ShiftJis.encode(text).unwrap())
+    }
+
+    fn build_project(types: &[(&str, &[&str], &[&str])]) -> Vec<u8> {
+        let mut out = (types.len() as u32).to_le_bytes().to_vec();
+        for (name, fields, data) in types {
+            out.extend(sjis(name));
+            out.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+            fields.iter().for_each(|f| out.extend(sjis(f)));
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            data.iter().for_each(|d| out.extend(sjis(d)));
+            out.extend(sjis("説明"));
+
+            out.extend_from_slice(&0x64u32.to_le_bytes());
+            out.extend_from_slice(&[0; 0x64]);
+            out.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+            fields.iter().for_each(|_| out.extend(sjis("")));
+            out.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+            fields.iter().for_each(|_| {
+                out.extend_from_slice(&1u32.to_le_bytes());
+                out.extend(sjis("選択肢"));
+            });
+            out.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+            fields.iter().for_each(|_| {
+                out.extend_from_slice(&1u32.to_le_bytes());
+                out.extend_from_slice(&7u32.to_le_bytes());
+            });
+            out.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+            fields
+                .iter()
+                .for_each(|_| out.extend_from_slice(&0u32.to_le_bytes()));
+        }
+        out
+    }
+
+    /// Field index infos of a type, and its data as int and string values
+    type TestType<'a> = (&'a [u32], Vec<(Vec<u32>, Vec<&'a str>)>);
+
+    fn build_dat(types: &[TestType]) -> Vec<u8> {
+        let mut out = DAT_MAGIC.to_vec();
+        out.extend_from_slice(&(types.len() as u32).to_le_bytes());
+        for (fields, data) in types {
+            out.extend_from_slice(DAT_TYPE_SEPARATOR);
+            out.extend_from_slice(&0u32.to_le_bytes());
+            out.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+            fields
+                .iter()
+                .for_each(|f| out.extend_from_slice(&f.to_le_bytes()));
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            for (ints, strings) in data {
+                ints.iter()
+                    .for_each(|i| out.extend_from_slice(&i.to_le_bytes()));
+                strings.iter().for_each(|s| out.extend(sjis(s)));
+            }
+        }
+        out
+    }
+
+    fn sample_database() -> (Vec<u8>, Vec<u8>) {
+        let project = build_project(&[
+            (
+                "アイテム",
+                &["名前", "価格", "画像", "説明"],
+                &["薬草", "Potion"],
+            ),
+            ("空", &[], &[]),
+        ]);
+        let dat = build_dat(&[
+            (
+                &[
+                    STRING_FIELD_START,
+                    INT_FIELD_START,
+                    STRING_FIELD_START + 1,
+                    STRING_FIELD_START + 2,
+                ],
+                vec![
+                    (vec![10], vec!["薬草", "Icon/herb.png", "HPを回復する"]),
+                    (vec![50], vec!["Potion", "Icon/potion.png", ""]),
+                ],
+            ),
+            (&[], vec![]),
+        ]);
+        (project, dat)
+    }
+
+    #[test]
+    fn test_extract_database() {
+        let (project, dat) = sample_database();
+        let result = DatabaseParser::new()
+            .extract(
+                &project,
+                &dat,
+                "DataBase.project",
+                &ExtractionOptions::default(),
+            )
+            .unwrap();
+
+        let texts: Vec<(&str, &str)> = result
+            .units
+            .iter()
+            .map(|u| (u.id.as_str(), u.original.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("types.0.data.0_name", "薬草"),
+                ("types.0.data.0.values.0_value", "薬草"),
+                ("types.0.data.0.values.3_value", "HPを回復する"),
+            ]
+        );
+        assert_eq!(result.units[2].context.tags, vec!["アイテム", "説明"]);
+    }
+
+    #[test]
+    fn test_inject_database() {
+        let (mut project, mut dat) = sample_database();
+        let mut translations = HashMap::new();
+        translations.insert("types.0.data.0_name".to_string(), "Herb".to_string());
+        translations.insert(
+            "types.0.data.0.values.3_value".to_string(),
+            "Restores HP".to_string(),
+        );
+
+        let result = DatabaseParser::new()
+            .inject(
+                &mut project,
+                &mut dat,
+                &translations,
+                &InjectionOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(result.applied, 2);
+
+        let project_types = parse_project(&project).unwrap();
+        assert_eq!(project_types[0].data[0].raw, b"Herb");
+        assert_eq!(project_types[0].data[1].raw, b"Potion");
+        let dat_types = parse_dat(&dat).unwrap();
+        let values: Vec<&[u8]> = dat_types[0].data[0]
+            .iter()
+            .map(|(_, s)| &s.raw[..])
+            .collect();
+        assert_eq!(values[1..], [&b"Icon/herb.png"[..], &b"Restores HP"[..]]);
+    }
+
+    #[test]
+    fn test_type_count_mismatch() {
+        let (project, _) = sample_database();
+        let dat = build_dat(&[(&[], vec![])]);
+        assert!(DatabaseParser::new()
+            .extract(
+                &project,
+                &dat,
+                "DataBase.project",
+                &ExtractionOptions::default()
+            )
+            .is_err());
+    }
+}
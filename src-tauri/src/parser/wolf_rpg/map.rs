@@ -0,0 +1,449 @@
+//! Parser for Wolf RPG Editor map files (`MapData/*.mps`)
+//!
+//! ```text
+//! 10 bytes       unknown
+//! "WOLFM\0"      magic, followed by 8 unknown bytes and a version byte
+//! string         unknown
+//! u32 x 4        tileset, width, height, event count
+//! u32 x w*h*3    tiles (three layers)
+//! events         0x6F + event, repeated; 0x66 ends the file
+//! ```
+//!
+//! An event is `[0x39, 0x30, 0, 0]`, its id, name, position and page
+//! count, four zero bytes, then pages (`0x79` + page) ended by `0x70`.
+//! Pages hold the graphic, conditions and move route of the event page,
+//! followed by the command list (see [`super::command`]).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::binary::{BinaryReader, Splicer, WolfEncoding, WolfString};
+use super::command::{self, Command};
+use super::WolfRpgError;
+use crate::archiver::vfs::GameVfs;
+use crate::parser::rpg_maker_mv_mz::{FileExtractionResult, FileInjectionResult};
+use crate::parser::types::{
+    ExtractionContext, ExtractionOptions, InjectionOptions, TranslationFile, TranslationPath,
+};
+
+/// Magic after the leading unknown bytes
+const MAGIC: &[u8] = b"WOLFM\0";
+/// Marker of another event
+const EVENT_START: u8 = 0x6F;
+/// Marker of the end of the events
+const EVENTS_END: u8 = 0x66;
+/// Marker of another event page
+const PAGE_START: u8 = 0x79;
+/// Marker of the end of an event's pages
+const PAGES_END: u8 = 0x70;
+/// Marker of the end of a page
+const PAGE_END: u8 = 0x7A;
+
+/// Event of a map
+#[derive(Debug, Clone)]
+pub struct MapEvent {
+    /// Event id
+    pub id: u32,
+    /// Event name
+    pub name: WolfString,
+    /// Command lists of the event pages
+    pub pages: Vec<Vec<Command>>,
+}
+
+/// Parse the events of a map file
+pub fn parse_map(data: &[u8]) -> Result<Vec<MapEvent>, WolfRpgError> {
+    let mut reader = BinaryReader::new(data);
+    reader.skip(10)?;
+    reader.expect(MAGIC, "map magic")?;
+    reader.skip(9)?;
+    reader.string()?;
+
+    reader.u32()?;
+    let width = reader.u32()? as usize;
+    let height = reader.u32()? as usize;
+    reader.u32()?;
+    let tiles = width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(3 * 4))
+        .ok_or_else(|| WolfRpgError::InvalidStructure("map size overflow".to_string()))?;
+    reader.skip(tiles)?;
+
+    let mut events = Vec::new();
+    loop {
+        match reader.u8()? {
+            EVENT_START => events.push(read_event(&mut reader)?),
+            EVENTS_END => break,
+            other => {
+                return Err(WolfRpgError::InvalidStructure(format!(
+                    "unexpected event marker {:#x} at offset {:#x}",
+                    other,
+                    reader.position() - 1
+                )))
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+fn read_event(reader: &mut BinaryReader) -> Result<MapEvent, WolfRpgError> {
+    reader.expect(&[0x39, 0x30, 0x00, 0x00], "event header")?;
+    let id = reader.u32()?;
+    let name = reader.string()?;
+    // Position, page count
+    reader.skip(12)?;
+    reader.expect(&[0; 4], "event header padding")?;
+
+    let mut pages = Vec::new();
+    loop {
+        match reader.u8()? {
+            PAGE_START => pages.push(read_page(reader)?),
+            PAGES_END => break,
+            other => {
+                return Err(WolfRpgError::InvalidStructure(format!(
+                    "unexpected page marker {:#x} at offset {:#x}",
+                    other,
+                    reader.position() - 1
+                )))
+            }
+        }
+    }
+
+    Ok(MapEvent { id, name, pages })
+}
+
+fn read_page(reader: &mut BinaryReader) -> Result<Vec<Command>, WolfRpgError> {
+    reader.u32()?;
+    // Graphic name, direction, frame, opacity and render mode
+    reader.string()?;
+    reader.skip(4)?;
+    // Conditions, movement, flags and route flags
+    reader.skip(1 + 4 + 16 + 16)?;
+    reader.skip(4 + 2)?;
+    command::read_route(reader)?;
+
+    let commands = Command::read_list(reader)?;
+    reader.expect(&[0x03, 0x00, 0x00, 0x00], "command list terminator")?;
+    // Shadow graphic and collision size
+    reader.skip(3)?;
+    reader.expect(&[PAGE_END], "page terminator")?;
+
+    Ok(commands)
+}
+
+/// Parser for Wolf RPG Editor maps
+pub struct MapParser {
+    /// Text encoding, detected per file if not set
+    encoding: Option<WolfEncoding>,
+}
+
+impl MapParser {
+    /// Create a new map parser
+    pub fn new() -> Self {
+        Self { encoding: None }
+    }
+
+    /// Use a fixed text encoding instead of detecting it
+    pub fn with_encoding(mut self, encoding: WolfEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    fn encoding_for(&self, events: &[MapEvent]) -> WolfEncoding {
+        self.encoding.unwrap_or_else(|| {
+            WolfEncoding::detect(events.iter().flat_map(|event| {
+                std::iter::once(&event.name.raw[..]).chain(
+                    event
+                        .pages
+                        .iter()
+                        .flatten()
+                        .flat_map(|c| c.strings.iter().map(|s| &s.raw[..])),
+                )
+            }))
+        })
+    }
+
+    /// Extract translations from map file content
+    pub fn extract(
+        &self,
+        data: &[u8],
+        file_name: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, WolfRpgError> {
+        let mut result = FileExtractionResult::new(file_name);
+        let events = parse_map(data)?;
+        let encoding = self.encoding_for(&events);
+
+        let map_name = Path::new(file_name)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(file_name)
+            .to_string();
+        let base_context = ExtractionContext::new(file_name)
+            .with_map_name(map_name)
+            .with_max_preceding_lines(options.max_preceding_lines);
+
+        for (event_idx, event) in events.iter().enumerate() {
+            let event_context =
+                base_context.for_event(event.id as usize, event.name.decode(encoding));
+            let event_path = TranslationPath::new()
+                .append_key("events")
+                .append_index(event_idx);
+
+            for (page_idx, commands) in event.pages.iter().enumerate() {
+                let mut context = event_context.for_page(page_idx);
+                let page_path = event_path.append_key("pages").append_index(page_idx);
+                let units = command::extract_commands(
+                    commands,
+                    &page_path,
+                    &mut context,
+                    options,
+                    encoding,
+                    &mut result.warnings,
+                );
+                result.add_units(units);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Extract from a file path
+    pub fn extract_file(
+        &self,
+        path: &Path,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, WolfRpgError> {
+        let data = fs::read(path)?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Map.mps");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Extract from a file in a game's virtual filesystem
+    ///
+    /// The file may be loose on disk or inside a mounted archive.
+    pub fn extract_vfs(
+        &self,
+        vfs: &GameVfs,
+        path: &str,
+        options: &ExtractionOptions,
+    ) -> Result<FileExtractionResult, WolfRpgError> {
+        let data = vfs.read(path)?;
+
+        let file_name = path.rsplit('/').next().unwrap_or("Map.mps");
+
+        self.extract(&data, file_name, options)
+    }
+
+    /// Inject translations into map file content
+    pub fn inject(
+        &self,
+        data: &mut Vec<u8>,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, WolfRpgError> {
+        let mut result = FileInjectionResult::new();
+        let events = parse_map(data)?;
+        let encoding = self.encoding_for(&events);
+
+        let mut splicer = Splicer::new();
+        for (event_idx, event) in events.iter().enumerate() {
+            let event_path = TranslationPath::new()
+                .append_key("events")
+                .append_index(event_idx);
+
+            for (page_idx, commands) in event.pages.iter().enumerate() {
+                let page_path = event_path.append_key("pages").append_index(page_idx);
+                command::inject_commands(
+                    commands,
+                    &page_path,
+                    translations,
+                    options,
+                    encoding,
+                    &mut splicer,
+                    &mut result,
+                );
+            }
+        }
+
+        if !splicer.is_empty() {
+            *data = splicer.apply(data);
+        }
+        Ok(result)
+    }
+
+    /// Inject translations to a file
+    pub fn inject_file(
+        &self,
+        path: &Path,
+        translations: &HashMap<String, String>,
+        options: &InjectionOptions,
+    ) -> Result<FileInjectionResult, WolfRpgError> {
+        let mut data = fs::read(path)?;
+
+        let result = self.inject(&mut data, translations, options)?;
+
+        if result.modified {
+            fs::write(path, data)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Convert extraction result to TranslationFile
+    pub fn to_translation_file(&self, result: FileExtractionResult) -> TranslationFile {
+        let mut file = TranslationFile::new(&result.source_file);
+        file.add_units(result.units);
+        file
+    }
+}
+
+impl Default for MapParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check if a file is a Wolf RPG Editor map file
+pub fn is_map_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("mps"))
+}
+
+/// Get all map files in a directory of a game's virtual filesystem
+///
+/// Includes maps stored in mounted archives.
+pub fn find_map_files_vfs(vfs: &GameVfs, dir: &str) -> Vec<String> {
+    vfs.list_dir(dir)
+        .into_iter()
+        .filter(|path| is_map_file(Path::new(path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::binary::encode_string;
+    use super::super::command::tests::{command, command_list, move_command};
+    use super::super::command::{CODE_CHOICES, CODE_MESSAGE};
+    use super::*;
+
+    /// Encode a map with the given events, each a name and its pages
+    fn build_map(events: &[(&str, Vec<Vec<Vec<u8>>>)]) -> Vec<u8> {
+        let mut out = vec![0; 10];
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&[0, 0, 0, 0, 0x64, 0, 0, 0, 0x65]);
+        out.extend(encode_string(b""));
+        for value in [1u32, 2, 1, events.len() as u32] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&[0; 2 * 3 * 4]);
+
+        for (id, (name, pages)) in events.iter().enumerate() {
+            out.push(EVENT_START);
+            out.extend_from_slice(&[0x39, 0x30, 0, 0]);
+            out.extend_from_slice(&(id as u32).to_le_bytes());
+            out.extend(encode_string(&WolfEncoding::ShiftJis.encode(name).unwrap()));
+            for value in [3u32, 4, pages.len() as u32, 0] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+
+            for commands in pages {
+                out.push(PAGE_START);
+                out.extend_from_slice(&0u32.to_le_bytes());
+                out.extend(encode_string("キャラ.png".as_bytes()));
+                out.extend_from_slice(&[0; 4 + 37 + 4 + 2]);
+                // Route with one step
+                out.extend_from_slice(&1u32.to_le_bytes());
+                out.extend_from_slice(&[0x0A, 0, 1, 0]);
+                out.extend(command_list(commands));
+                out.extend_from_slice(&[0x03, 0, 0, 0, 0, 1, 1, PAGE_END]);
+            }
+            out.push(PAGES_END);
+        }
+        out.push(EVENTS_END);
+        out
+    }
+
+    fn sample_map() -> Vec<u8> {
+        build_map(&[
+            (
+                "村長",
+                vec![
+                    vec![command(CODE_MESSAGE, &[], &["ようこそ！"]), move_command()],
+                    vec![command(CODE_CHOICES, &[2], &["はい", "いいえ"])],
+                ],
+            ),
+            (
+                "宝箱",
+                vec![vec![command(CODE_MESSAGE, &[], &["薬草を手に入れた"])]],
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_extract_map() {
+        let data = sample_map();
+        let result = MapParser::new()
+            .extract(&data, "Map001.mps", &ExtractionOptions::default())
+            .unwrap();
+
+        let ids: Vec<&str> = result.units.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "events.0.pages.0.commands.0.strings.0_message",
+                "events.0.pages.1.commands.0.strings.0_choice",
+                "events.0.pages.1.commands.0.strings.1_choice",
+                "events.1.pages.0.commands.0.strings.0_message",
+            ]
+        );
+        assert_eq!(result.units[0].original, "ようこそ！");
+        assert_eq!(result.units[0].context.event_name.as_deref(), Some("村長"));
+        assert_eq!(result.units[0].context.map_name.as_deref(), Some("Map001"));
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_inject_map() {
+        let mut data = sample_map();
+        let mut translations = HashMap::new();
+        translations.insert(
+            "events.0.pages.1.commands.0.strings.1_choice".to_string(),
+            "No".to_string(),
+        );
+        translations.insert(
+            "events.1.pages.0.commands.0.strings.0_message".to_string(),
+            "Got a herb".to_string(),
+        );
+
+        let result = MapParser::new()
+            .inject(&mut data, &translations, &InjectionOptions::default())
+            .unwrap();
+        assert_eq!(result.applied, 2);
+        assert!(result.modified);
+
+        let result = MapParser::new()
+            .extract(&data, "Map001.mps", &ExtractionOptions::default())
+            .unwrap();
+        let texts: Vec<&str> = result.units.iter().map(|u| u.original.as_str()).collect();
+        assert_eq!(texts, vec!["ようこそ！", "はい", "No", "Got a herb"]);
+    }
+
+    #[test]
+    fn test_invalid_map() {
+        let mut data = sample_map();
+        data.truncate(data.len() - 1);
+        assert!(matches!(
+            parse_map(&data),
+            Err(WolfRpgError::UnexpectedEof(_))
+        ));
+        assert!(parse_map(b"not a map file at all").is_err());
+    }
+}
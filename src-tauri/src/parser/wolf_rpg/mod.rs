@@ -0,0 +1,42 @@
+//! Wolf RPG Editor parser module
+//!
+//! This module provides parsing capabilities for Wolf RPG Editor games,
+//! which store their data in binary files under `Data/` (usually packed
+//! into `.wolf` archives, see [`crate::archiver::wolf`]):
+//!
+//! - `MapData/*.mps`: maps and their events
+//! - `BasicData/CommonEvent.dat`: common events
+//! - `BasicData/*DataBase.project` and `.dat`: user, changeable and
+//!   system databases
+//!
+//! Translations are injected by splicing re-encoded strings into the
+//! original bytes, leaving everything else untouched.
+
+pub mod binary;
+pub mod command;
+pub mod common_events;
+pub mod database;
+pub mod map;
+
+pub use binary::WolfEncoding;
+pub use common_events::CommonEventsParser;
+pub use database::DatabaseParser;
+pub use map::MapParser;
+
+use crate::archiver::ArchiverError;
+
+/// Error type for Wolf RPG Editor parsing
+#[derive(Debug, thiserror::Error)]
+pub enum WolfRpgError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Archive error: {0}")]
+    ArchiveError(#[from] ArchiverError),
+
+    #[error("Unexpected end of data at offset {0:#x}")]
+    UnexpectedEof(usize),
+
+    #[error("Invalid structure: {0}")]
+    InvalidStructure(String),
+}
@@ -1,7 +1,11 @@
 use std::path::Path;
 
+use crate::archiver::evb;
 use crate::types::DetectionResult;
-use super::{rpg_maker::RpgMakerDetector, kirikiri::KiriKiriDetector, v8_engine::V8EngineDetector};
+use super::{
+    rpg_maker::RpgMakerDetector, kirikiri::KiriKiriDetector, v8_engine::V8EngineDetector,
+    wolf_rpg::WolfRpgDetector, renpy::RenPyDetector, tyrano::TyranoDetector,
+};
 
 /// 게임 엔진 통합 감지기
 pub struct GameDetector;
@@ -53,7 +57,22 @@ impl GameDetector {
             }
         }
 
-        // 3. V8 엔진 감지 시도
+        // 3. TyranoScript 감지 시도 (NW.js/Electron 위에서 동작하므로 V8보다 먼저)
+        details.push("Checking for TyranoScript...".to_string());
+        match TyranoDetector::detect(path) {
+            Ok(Some(project)) => {
+                details.push(format!("✓ Detected: {}", project.engine.name()));
+                return DetectionResult::success(project).with_details(details);
+            }
+            Ok(None) => {
+                details.push("✗ Not a TyranoScript project".to_string());
+            }
+            Err(e) => {
+                details.push(format!("✗ TyranoScript detection error: {}", e));
+            }
+        }
+
+        // 4. V8 엔진 감지 시도
         details.push("Checking for V8 Engine...".to_string());
         match V8EngineDetector::detect(path) {
             Ok(Some(project)) => {
@@ -68,6 +87,45 @@ impl GameDetector {
             }
         }
 
+        // 5. Wolf RPG Editor 감지 시도
+        details.push("Checking for Wolf RPG Editor...".to_string());
+        match WolfRpgDetector::detect(path) {
+            Ok(Some(project)) => {
+                details.push(format!("✓ Detected: {}", project.engine.name()));
+                return DetectionResult::success(project).with_details(details);
+            }
+            Ok(None) => {
+                details.push("✗ Not a Wolf RPG Editor project".to_string());
+            }
+            Err(e) => {
+                details.push(format!("✗ Wolf RPG Editor detection error: {}", e));
+            }
+        }
+
+        // 6. Ren'Py 감지 시도
+        details.push("Checking for Ren'Py...".to_string());
+        match RenPyDetector::detect(path) {
+            Ok(Some(project)) => {
+                details.push(format!("✓ Detected: {}", project.engine.name()));
+                return DetectionResult::success(project).with_details(details);
+            }
+            Ok(None) => {
+                details.push("✗ Not a Ren'Py project".to_string());
+            }
+            Err(e) => {
+                details.push(format!("✗ Ren'Py detection error: {}", e));
+            }
+        }
+
+        // 7. Enigma Virtual Box로 패킹된 실행 파일 확인
+        //    (압축을 풀어야 위의 감지기가 동작함)
+        if let Some(package) = evb::find_package(path) {
+            details.push(format!("✓ Found Enigma Virtual Box package: {:?}", package));
+            details.push("Extract the package and run detection on the output".to_string());
+            return DetectionResult::failure("Game is packed with Enigma Virtual Box")
+                .with_details(details);
+        }
+
         // 감지 실패
         details.push("✗ Unknown or unsupported game engine".to_string());
         DetectionResult::failure("Could not detect game engine")
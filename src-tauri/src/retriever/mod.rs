@@ -2,8 +2,14 @@ pub mod detector;
 pub mod rpg_maker;
 pub mod kirikiri;
 pub mod v8_engine;
+pub mod wolf_rpg;
+pub mod renpy;
+pub mod tyrano;
 
 pub use detector::*;
 pub use rpg_maker::RpgMakerDetector;
 pub use kirikiri::KiriKiriDetector;
 pub use v8_engine::V8EngineDetector;
+pub use wolf_rpg::WolfRpgDetector;
+pub use renpy::RenPyDetector;
+pub use tyrano::TyranoDetector;
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use crate::types::{
+    GameEngine, ProjectMetadata, GameProject, Result,
+};
+
+/// Ren'Py 프로젝트 감지기
+pub struct RenPyDetector;
+
+impl RenPyDetector {
+    /// 디렉토리에서 Ren'Py 프로젝트 감지
+    pub fn detect(path: &Path) -> Result<Option<GameProject>> {
+        tracing::info!("Detecting Ren'Py project at: {:?}", path);
+
+        if !Self::has_renpy_data(path) {
+            return Ok(None);
+        }
+
+        let metadata = Self::extract_metadata(path);
+
+        Ok(Some(GameProject::new(
+            path.to_path_buf(),
+            GameEngine::RenPy,
+            GameEngine::RenPy.name(),
+            metadata,
+        )))
+    }
+
+    /// Ren'Py 데이터 확인 (renpy/ 폴더와 game/ 안의 스크립트 또는 아카이브)
+    fn has_renpy_data(path: &Path) -> bool {
+        if !path.join("renpy").is_dir() {
+            return false;
+        }
+
+        let Ok(entries) = fs::read_dir(path.join("game")) else {
+            return false;
+        };
+
+        // .rpy, .rpyc, .rpym, .rpymc 또는 .rpa
+        let found = entries.filter_map(|e| e.ok()).any(|e| {
+            e.path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_lowercase())
+                .is_some_and(|ext| ext.starts_with("rpy") || ext == "rpa")
+        });
+
+        if found {
+            tracing::info!("Found renpy/ with game scripts");
+        }
+        found
+    }
+
+    /// 메타데이터 추출 (디렉토리 이름을 제목으로 사용)
+    fn extract_metadata(path: &Path) -> ProjectMetadata {
+        let mut metadata = ProjectMetadata::new();
+
+        if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
+            metadata = metadata.with_title(dir_name.to_string());
+        }
+
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_renpy() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let game = temp_dir.path().join("MyNovel");
+        fs::create_dir_all(game.join("renpy")).unwrap();
+        fs::create_dir_all(game.join("game")).unwrap();
+        fs::write(game.join("game/options.txt"), b"").unwrap();
+        assert!(RenPyDetector::detect(&game).unwrap().is_none());
+
+        fs::write(game.join("game/script.rpyc"), b"RENPY RPC2").unwrap();
+        let project = RenPyDetector::detect(&game).unwrap().unwrap();
+        assert_eq!(project.engine, GameEngine::RenPy);
+        assert_eq!(project.metadata.title.as_deref(), Some("MyNovel"));
+
+        let no_engine = temp_dir.path().join("NoEngine");
+        fs::create_dir_all(no_engine.join("game")).unwrap();
+        fs::write(no_engine.join("game/script.rpy"), b"label start:").unwrap();
+        assert!(RenPyDetector::detect(&no_engine).unwrap().is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::{Path, PathBuf};
 use std::fs;
 
+use crate::parser::rpg_maker_2000_2003::{is_rm2003_database, map::is_map_file};
 use crate::types::{
     GameEngine, RpgMakerVersion, ProjectMetadata, GameProject, HayoTransError, Result,
 };
@@ -33,6 +34,11 @@ impl RpgMakerDetector {
             return Ok(Some(project));
         }
 
+        // 5. RPG_RT 파일로 감지 (2000/2003)
+        if let Some(project) = Self::detect_by_rpg_rt(path)? {
+            return Ok(Some(project));
+        }
+
         Ok(None)
     }
 
@@ -180,6 +186,55 @@ impl RpgMakerDetector {
         )))
     }
 
+    /// RPG_RT 파일로 감지 (RPG_RT.ldb, 또는 RPG_RT.exe와 Map*.lmu)
+    fn detect_by_rpg_rt(path: &Path) -> Result<Option<GameProject>> {
+        let database_path = path.join("RPG_RT.ldb");
+        if !database_path.is_file() {
+            let has_maps = fs::read_dir(path)
+                .map(|entries| entries.flatten().any(|e| is_map_file(&e.path())))
+                .unwrap_or(false);
+            if !(path.join("RPG_RT.exe").is_file() && has_maps) {
+                return Ok(None);
+            }
+        }
+
+        tracing::info!("Found RPG_RT files: {:?}", path);
+
+        // 2003 데이터베이스에만 전투 커맨드와 직업이 있음 (데이터베이스가 없으면 2000)
+        let version = match fs::read(&database_path) {
+            Ok(data) if is_rm2003_database(&data) => RpgMakerVersion::Rm2003,
+            _ => RpgMakerVersion::Rm2000,
+        };
+
+        let metadata = Self::extract_metadata_from_rpg_rt_ini(path);
+
+        Ok(Some(GameProject::new(
+            path.to_path_buf(),
+            GameEngine::RpgMaker(version),
+            version.to_string(),
+            metadata,
+        )))
+    }
+
+    /// RPG_RT.ini에서 메타데이터 추출 (GameTitle이 없으면 디렉토리 이름 사용)
+    fn extract_metadata_from_rpg_rt_ini(path: &Path) -> ProjectMetadata {
+        let title = fs::read(path.join("RPG_RT.ini"))
+            .ok()
+            .and_then(|data| {
+                // ini 파일은 Shift-JIS
+                let (content, _, _) = encoding_rs::SHIFT_JIS.decode(&data);
+                content.lines().find_map(|line| {
+                    line.trim().strip_prefix("GameTitle=").map(|t| t.trim().to_string())
+                })
+            })
+            .filter(|title| !title.is_empty())
+            .or_else(|| path.file_name().and_then(|n| n.to_str()).map(|n| n.to_string()));
+
+        ProjectMetadata::new()
+            .with_title(title.unwrap_or_else(|| "Unknown".to_string()))
+            .with_language("ja_JP")
+    }
+
     /// 프로젝트 파일에서 메타데이터 추출
     fn extract_metadata_from_project_file(
         _content: &str,
@@ -291,4 +346,29 @@ mod tests {
         fs::remove_file(&test_file).ok();
         fs::remove_file(&output_path).ok();
     }
+
+    #[test]
+    fn test_detect_rpg_rt() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        // RPG_RT.exe와 맵 파일만 있는 경우 (2000)
+        let game = temp_dir.path().join("game");
+        fs::create_dir_all(&game).unwrap();
+        fs::write(game.join("RPG_RT.exe"), b"MZ").unwrap();
+        assert!(RpgMakerDetector::detect(&game).unwrap().is_none());
+        fs::write(game.join("Map0001.lmu"), b"\x0aLcfMapUnit").unwrap();
+        let project = RpgMakerDetector::detect(&game).unwrap().unwrap();
+        assert_eq!(project.engine, GameEngine::RpgMaker(RpgMakerVersion::Rm2000));
+        assert_eq!(project.metadata.title.as_deref(), Some("game"));
+
+        // 직업 청크(0x1E)가 있는 데이터베이스 (2003)
+        let mut database = b"\x0bLcfDataBase".to_vec();
+        database.extend_from_slice(&[0x1E, 0x02, 0x00, 0x00, 0x00]);
+        fs::write(game.join("RPG_RT.ldb"), database).unwrap();
+        let (ini, _, _) = encoding_rs::SHIFT_JIS.encode("[RPG_RT]\r\nGameTitle=勇者の冒険\r\n");
+        fs::write(game.join("RPG_RT.ini"), ini).unwrap();
+        let project = RpgMakerDetector::detect(&game).unwrap().unwrap();
+        assert_eq!(project.engine, GameEngine::RpgMaker(RpgMakerVersion::Rm2003));
+        assert_eq!(project.metadata.title.as_deref(), Some("勇者の冒険"));
+    }
 }
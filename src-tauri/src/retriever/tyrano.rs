@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use crate::archiver::vfs::GameVfs;
+use crate::types::{
+    GameEngine, ProjectMetadata, GameProject, Result,
+};
+
+/// TyranoScript 데이터가 있을 수 있는 위치 (NW.js 루트 또는 패키지, Electron app.asar)
+const DATA_ROOTS: &[&str] = &["", "resources/app"];
+
+/// TyranoScript 프로젝트 감지기
+pub struct TyranoDetector;
+
+impl TyranoDetector {
+    /// 디렉토리에서 TyranoScript 프로젝트 감지
+    pub fn detect(path: &Path) -> Result<Option<GameProject>> {
+        tracing::info!("Detecting TyranoScript project at: {:?}", path);
+
+        // 패키지나 app.asar 안의 파일도 확인
+        let vfs = GameVfs::open_game_dir(path).unwrap_or_else(|_| GameVfs::new(path));
+
+        let Some(root) = Self::find_data_root(path, &vfs) else {
+            return Ok(None);
+        };
+
+        let metadata = Self::extract_metadata(path, &vfs, root);
+
+        Ok(Some(GameProject::new(
+            path.to_path_buf(),
+            GameEngine::TyranoScript,
+            GameEngine::TyranoScript.name(),
+            metadata,
+        )))
+    }
+
+    /// tyrano/ 폴더 또는 data/system/Config.tjs가 있는 위치 찾기
+    fn find_data_root(path: &Path, vfs: &GameVfs) -> Option<&'static str> {
+        DATA_ROOTS.iter().copied().find(|root| {
+            let join = |name: &str| {
+                if root.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}/{}", root, name)
+                }
+            };
+
+            if vfs.exists(&join("data/system/Config.tjs")) {
+                tracing::info!("Found data/system/Config.tjs");
+                return true;
+            }
+            if path.join(join("tyrano")).is_dir() || !vfs.list_dir(&join("tyrano")).is_empty() {
+                tracing::info!("Found tyrano/ folder");
+                return true;
+            }
+            false
+        })
+    }
+
+    /// 메타데이터 추출 (Config.tjs의 System.title, 없으면 디렉토리 이름)
+    fn extract_metadata(path: &Path, vfs: &GameVfs, root: &str) -> ProjectMetadata {
+        let config_path = if root.is_empty() {
+            "data/system/Config.tjs".to_string()
+        } else {
+            format!("{}/data/system/Config.tjs", root)
+        };
+
+        let title = vfs
+            .read(&config_path)
+            .ok()
+            .and_then(|data| Self::parse_title(&String::from_utf8_lossy(&data)))
+            .or_else(|| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|s| s.to_string())
+            });
+
+        let mut metadata = ProjectMetadata::new();
+        if let Some(title) = title {
+            metadata = metadata.with_title(title);
+        }
+        metadata
+    }
+
+    /// Config.tjs에서 제목 추출 (";System.title = 제목;" 형식)
+    fn parse_title(config: &str) -> Option<String> {
+        config.lines().find_map(|line| {
+            let (key, value) = line.trim().trim_start_matches(';').split_once('=')?;
+            if key.trim() != "System.title" {
+                return None;
+            }
+            let title = value.trim().trim_end_matches(';').trim();
+            (!title.is_empty()).then(|| title.to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_detect_tyrano() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let game = temp_dir.path().join("game");
+        fs::create_dir_all(game.join("data/system")).unwrap();
+        fs::create_dir_all(game.join("data/scenario")).unwrap();
+        fs::write(game.join("package.json"), r#"{"name": "tyrano", "main": "index.html"}"#).unwrap();
+        fs::write(
+            game.join("data/system/Config.tjs"),
+            ";System.title = 私のノベル;\n;System.version = 1;\n",
+        )
+        .unwrap();
+
+        let project = TyranoDetector::detect(&game).unwrap().unwrap();
+        assert_eq!(project.engine, GameEngine::TyranoScript);
+        assert_eq!(project.metadata.title.as_deref(), Some("私のノベル"));
+
+        let electron = temp_dir.path().join("electron");
+        fs::create_dir_all(electron.join("resources/app/tyrano")).unwrap();
+        let project = TyranoDetector::detect(&electron).unwrap().unwrap();
+        assert_eq!(project.metadata.title.as_deref(), Some("electron"));
+
+        let other = temp_dir.path().join("other");
+        fs::create_dir_all(other.join("data")).unwrap();
+        assert!(TyranoDetector::detect(&other).unwrap().is_none());
+    }
+}
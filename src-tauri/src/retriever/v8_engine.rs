@@ -1,6 +1,8 @@
 use std::path::Path;
 use std::fs;
 
+use crate::archiver::nwjs::{self, NwReader};
+use crate::archiver::ArchiveReader;
 use crate::types::{
     GameEngine, V8Engine, ProjectMetadata, GameProject, Result,
 };
@@ -14,14 +16,9 @@ impl V8EngineDetector {
         tracing::info!("Detecting V8 Engine project at: {:?}", path);
 
         // package.json 확인
-        let package_json_path = path.join("package.json");
-        if !package_json_path.exists() {
-            return Ok(None);
-        }
-
-        let content = match fs::read_to_string(&package_json_path) {
-            Ok(content) => content,
-            Err(_) => return Ok(None),
+        let content = match Self::read_package_json(path) {
+            Some(content) => content,
+            None => return Ok(None),
         };
 
         let json: serde_json::Value = match serde_json::from_str(&content) {
@@ -95,6 +92,12 @@ impl V8EngineDetector {
             return Ok(Some(V8Engine::Electron));
         }
 
+        // package.nw 또는 실행 파일에 붙은 zip 확인 (NW.js)
+        if nwjs::find_package(path).is_some() {
+            tracing::info!("Detected NW.js project (from package)");
+            return Ok(Some(V8Engine::NwJs));
+        }
+
         // resources/app.asar 확인 (Electron)
         if path.join("resources/app.asar").exists() {
             tracing::info!("Detected Electron project (from app.asar)");
@@ -112,6 +115,20 @@ impl V8EngineDetector {
         Ok(None)
     }
 
+    /// package.json 읽기 (폴더에 없으면 NW.js 패키지 안에서 읽기)
+    fn read_package_json(path: &Path) -> Option<String> {
+        let package_json_path = path.join("package.json");
+        if package_json_path.exists() {
+            return fs::read_to_string(&package_json_path).ok();
+        }
+
+        let package = nwjs::find_package(path)?;
+        let reader = NwReader::open(&package).ok()?;
+        let entry = reader.entries().iter().find(|e| e.name == "package.json")?;
+        let data = reader.extract_to_memory(entry).ok()?;
+        String::from_utf8(data).ok()
+    }
+
     /// 메타데이터 추출
     fn extract_metadata(json: &serde_json::Value) -> ProjectMetadata {
         let title = json
@@ -184,4 +201,25 @@ mod tests {
         
         assert_eq!(engine, Some(V8Engine::Electron));
     }
+
+    #[test]
+    fn test_detect_packaged_nwjs() {
+        use crate::archiver::nwjs::NwWriter;
+        use crate::archiver::ArchiveWriter;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("source.json");
+        fs::write(&source, r#"{"name": "packed-game", "main": "www/index.html"}"#).unwrap();
+
+        let game_dir = temp_dir.path().join("game");
+        fs::create_dir_all(&game_dir).unwrap();
+
+        let mut writer = NwWriter::new().with_stub(b"MZ stub".to_vec());
+        writer.add_file(&source, "package.json").unwrap();
+        writer.write(game_dir.join("Game.exe")).unwrap();
+
+        let project = V8EngineDetector::detect(&game_dir).unwrap().unwrap();
+        assert_eq!(project.engine, GameEngine::V8Engine(V8Engine::NwJs));
+        assert_eq!(project.metadata.title.as_deref(), Some("packed-game"));
+    }
 }
@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use crate::types::{
+    GameEngine, ProjectMetadata, GameProject, Result,
+};
+
+/// Wolf RPG Editor 프로젝트 감지기
+pub struct WolfRpgDetector;
+
+impl WolfRpgDetector {
+    /// 디렉토리에서 Wolf RPG Editor 프로젝트 감지
+    pub fn detect(path: &Path) -> Result<Option<GameProject>> {
+        tracing::info!("Detecting Wolf RPG Editor project at: {:?}", path);
+
+        if !Self::has_wolf_data(path) {
+            return Ok(None);
+        }
+
+        let metadata = Self::extract_metadata(path);
+
+        Ok(Some(GameProject::new(
+            path.to_path_buf(),
+            GameEngine::WolfRpg,
+            GameEngine::WolfRpg.name(),
+            metadata,
+        )))
+    }
+
+    /// Wolf RPG Editor 데이터 확인
+    fn has_wolf_data(path: &Path) -> bool {
+        // 1. Data.wolf 아카이브 (배포용으로 묶인 게임)
+        if path.join("Data.wolf").is_file() {
+            tracing::info!("Found Data.wolf archive");
+            return true;
+        }
+
+        // 2. Game.exe와 Data/BasicData (폴더 또는 아카이브)
+        if !path.join("Game.exe").is_file() {
+            return false;
+        }
+
+        let data_dir = path.join("Data");
+        if data_dir.join("BasicData").is_dir() || data_dir.join("BasicData.wolf").is_file() {
+            tracing::info!("Found Game.exe with Data/BasicData");
+            return true;
+        }
+
+        false
+    }
+
+    /// 메타데이터 추출 (디렉토리 이름을 제목으로 사용)
+    fn extract_metadata(path: &Path) -> ProjectMetadata {
+        let mut metadata = ProjectMetadata::new();
+
+        if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
+            metadata = metadata.with_title(dir_name.to_string());
+        }
+
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_detect_wolf_rpg() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let packed = temp_dir.path().join("packed");
+        fs::create_dir_all(&packed).unwrap();
+        fs::write(packed.join("Data.wolf"), b"DX").unwrap();
+        let project = WolfRpgDetector::detect(&packed).unwrap().unwrap();
+        assert_eq!(project.engine, GameEngine::WolfRpg);
+        assert_eq!(project.metadata.title.as_deref(), Some("packed"));
+
+        let loose = temp_dir.path().join("loose");
+        fs::create_dir_all(loose.join("Data/BasicData")).unwrap();
+        assert!(WolfRpgDetector::detect(&loose).unwrap().is_none());
+        fs::write(loose.join("Game.exe"), b"MZ").unwrap();
+        assert!(WolfRpgDetector::detect(&loose).unwrap().is_some());
+    }
+}
@@ -120,6 +120,16 @@ impl Database {
                 FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
             );
 
+            -- Project settings table (per-project key/value options)
+            CREATE TABLE IF NOT EXISTS project_settings (
+                project_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (project_id, key),
+                FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+            );
+
             -- Indexes for better query performance
             CREATE INDEX IF NOT EXISTS idx_translation_files_project ON translation_files(project_id);
             CREATE INDEX IF NOT EXISTS idx_translation_units_file ON translation_units(file_id);
@@ -191,6 +201,7 @@ mod tests {
         assert!(tables.contains(&"projects".to_string()));
         assert!(tables.contains(&"translation_files".to_string()));
         assert!(tables.contains(&"translation_units".to_string()));
+        assert!(tables.contains(&"project_settings".to_string()));
 
         // Cleanup
         fs::remove_file(&temp_path).ok();
@@ -29,6 +29,8 @@ impl From<&GameEngine> for EngineInfo {
                     RpgMakerVersion::VXAce => "RPG Maker VX Ace".to_string(),
                     RpgMakerVersion::VX => "RPG Maker VX".to_string(),
                     RpgMakerVersion::XP => "RPG Maker XP".to_string(),
+                    RpgMakerVersion::Rm2003 => "RPG Maker 2003".to_string(),
+                    RpgMakerVersion::Rm2000 => "RPG Maker 2000".to_string(),
                 },
             },
             GameEngine::KiriKiri(version) => Self {
@@ -48,6 +50,21 @@ impl From<&GameEngine> for EngineInfo {
                     V8Engine::Generic => "V8 Engine".to_string(),
                 },
             },
+            GameEngine::WolfRpg => Self {
+                engine_type: "WolfRPG".to_string(),
+                version: None,
+                display_name: "Wolf RPG Editor".to_string(),
+            },
+            GameEngine::RenPy => Self {
+                engine_type: "RenPy".to_string(),
+                version: None,
+                display_name: "Ren'Py".to_string(),
+            },
+            GameEngine::TyranoScript => Self {
+                engine_type: "Tyrano".to_string(),
+                version: None,
+                display_name: "TyranoScript".to_string(),
+            },
             GameEngine::Unknown => Self {
                 engine_type: "Unknown".to_string(),
                 version: None,
@@ -290,6 +307,49 @@ impl<'a> ProjectStore<'a> {
         })
     }
 
+    /// Get a per-project setting value
+    pub fn get_setting(&self, id: &str, key: &str) -> Result<Option<String>, String> {
+        self.db.with_connection(|conn| {
+            let result = conn.query_row(
+                "SELECT value FROM project_settings WHERE project_id = ? AND key = ?",
+                params![id, key],
+                |row| row.get(0),
+            );
+
+            match result {
+                Ok(value) => Ok(Some(value)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(e),
+            }
+        })
+    }
+
+    /// Set a per-project setting value (insert or replace)
+    pub fn set_setting(&self, id: &str, key: &str, value: &str) -> Result<(), String> {
+        let now = Utc::now().to_rfc3339();
+
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO project_settings (project_id, key, value, updated_at)
+                 VALUES (?, ?, ?, ?)
+                 ON CONFLICT(project_id, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                params![id, key, value, now],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Remove a per-project setting
+    pub fn remove_setting(&self, id: &str, key: &str) -> Result<(), String> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "DELETE FROM project_settings WHERE project_id = ? AND key = ?",
+                params![id, key],
+            )?;
+            Ok(())
+        })
+    }
+
     /// Helper to create display name from engine type and version
     fn make_display_name(engine_type: &str, version: Option<&str>) -> String {
         match engine_type {
@@ -300,6 +360,8 @@ impl<'a> ProjectStore<'a> {
                     Some("VXAce") => "RPG Maker VX Ace".to_string(),
                     Some("VX") => "RPG Maker VX".to_string(),
                     Some("XP") => "RPG Maker XP".to_string(),
+                    Some("Rm2003") => "RPG Maker 2003".to_string(),
+                    Some("Rm2000") => "RPG Maker 2000".to_string(),
                     _ => "RPG Maker".to_string(),
                 }
             }
@@ -348,6 +410,13 @@ mod tests {
                     thumbnail_base64 TEXT,
                     progress_state TEXT DEFAULT 'initial'
                 );
+                CREATE TABLE project_settings (
+                    project_id TEXT NOT NULL,
+                    key TEXT NOT NULL,
+                    value TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    PRIMARY KEY (project_id, key)
+                );
             "#)?;
             Ok(())
         }).unwrap();
@@ -402,4 +471,23 @@ mod tests {
         let found = store.get_by_id(&project.id).unwrap();
         assert!(found.is_none());
     }
+
+    #[test]
+    fn test_project_settings() {
+        let db = create_test_db();
+        let store = ProjectStore::new(&db);
+
+        let project = store.add("Test", "/path", &GameEngine::KiriKiri(KiriKiriVersion::Z)).unwrap();
+        assert_eq!(store.get_setting(&project.id, "xp3_cipher").unwrap(), None);
+
+        store.set_setting(&project.id, "xp3_cipher", "none").unwrap();
+        store.set_setting(&project.id, "xp3_cipher", "fixed").unwrap();
+        assert_eq!(
+            store.get_setting(&project.id, "xp3_cipher").unwrap(),
+            Some("fixed".to_string())
+        );
+
+        store.remove_setting(&project.id, "xp3_cipher").unwrap();
+        assert_eq!(store.get_setting(&project.id, "xp3_cipher").unwrap(), None);
+    }
 }
@@ -3,6 +3,10 @@ use serde::{Deserialize, Serialize};
 /// RPG Maker 버전
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RpgMakerVersion {
+    /// RPG Maker 2000 (RPG_RT.exe, RPG_RT.ldb, Map*.lmu)
+    Rm2000,
+    /// RPG Maker 2003 (RPG_RT.ldb에 2003 전용 데이터)
+    Rm2003,
     /// RPG Maker XP (.rgssad, Game.rxproj)
     XP,
     /// RPG Maker VX (.rgss2a, Game.rvproj)
@@ -33,6 +37,7 @@ impl RpgMakerVersion {
             Self::VX => "Game.rvproj",
             Self::VXAce => "Game.rvproj2",
             Self::MV | Self::MZ => "package.json",
+            Self::Rm2000 | Self::Rm2003 => "RPG_RT.ldb",
         }
     }
 
@@ -42,7 +47,7 @@ impl RpgMakerVersion {
             Self::XP => Some("RPGXP 1.02"),
             Self::VX => Some("RPGVX 1.02"),
             Self::VXAce => Some("RPGVXAce 1.00"),
-            Self::MV | Self::MZ => None,
+            Self::MV | Self::MZ | Self::Rm2000 | Self::Rm2003 => None,
         }
     }
 
@@ -51,6 +56,8 @@ impl RpgMakerVersion {
         match self {
             Self::XP | Self::VX | Self::VXAce => "Data",
             Self::MV | Self::MZ => "www/data",
+            // 데이터 파일이 게임 폴더에 바로 있음
+            Self::Rm2000 | Self::Rm2003 => "",
         }
     }
 
@@ -63,11 +70,18 @@ impl RpgMakerVersion {
     pub fn uses_json(&self) -> bool {
         matches!(self, Self::MV | Self::MZ)
     }
+
+    /// LCF 포맷 사용 여부 (.ldb, .lmu, .lmt)
+    pub fn uses_lcf(&self) -> bool {
+        matches!(self, Self::Rm2000 | Self::Rm2003)
+    }
 }
 
 impl std::fmt::Display for RpgMakerVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Rm2000 => write!(f, "RPG Maker 2000"),
+            Self::Rm2003 => write!(f, "RPG Maker 2003"),
             Self::XP => write!(f, "RPG Maker XP"),
             Self::VX => write!(f, "RPG Maker VX"),
             Self::VXAce => write!(f, "RPG Maker VX Ace"),
@@ -125,6 +139,12 @@ pub enum GameEngine {
     KiriKiri(KiriKiriVersion),
     /// V8 기반 엔진
     V8Engine(V8Engine),
+    /// Wolf RPG Editor (Data.wolf, Data/BasicData)
+    WolfRpg,
+    /// Ren'Py (renpy/, game/*.rpy, game/*.rpa)
+    RenPy,
+    /// TyranoScript (tyrano/, data/system/Config.tjs)
+    TyranoScript,
     /// 알 수 없는 엔진
     Unknown,
 }
@@ -136,6 +156,9 @@ impl GameEngine {
             Self::RpgMaker(v) => v.to_string(),
             Self::KiriKiri(v) => v.to_string(),
             Self::V8Engine(v) => v.to_string(),
+            Self::WolfRpg => "Wolf RPG Editor".to_string(),
+            Self::RenPy => "Ren'Py".to_string(),
+            Self::TyranoScript => "TyranoScript".to_string(),
             Self::Unknown => "Unknown Engine".to_string(),
         }
     }
@@ -189,12 +212,18 @@ mod tests {
         assert!(RpgMakerVersion::VXAce.uses_marshal());
         assert!(!RpgMakerVersion::MV.uses_marshal());
         assert!(!RpgMakerVersion::MZ.uses_marshal());
+        assert!(!RpgMakerVersion::Rm2000.uses_marshal());
+        assert!(RpgMakerVersion::Rm2003.uses_lcf());
+        assert!(!RpgMakerVersion::XP.uses_lcf());
     }
 
     #[test]
     fn test_game_engine_is_supported() {
         assert!(GameEngine::RpgMaker(RpgMakerVersion::MV).is_supported());
         assert!(GameEngine::KiriKiri(KiriKiriVersion::Z).is_supported());
+        assert!(GameEngine::WolfRpg.is_supported());
+        assert!(GameEngine::RenPy.is_supported());
+        assert!(GameEngine::TyranoScript.is_supported());
         assert!(!GameEngine::Unknown.is_supported());
     }
 }
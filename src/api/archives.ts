@@ -0,0 +1,261 @@
+/**
+ * API functions for game archives
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+// Archive entry metadata - matches Rust backend
+export interface ArchiveEntryInfo {
+  name: string;
+  size: number;
+  compressed: boolean;
+}
+
+/**
+ * List the entries of an archive of any supported format
+ *
+ * XP3 archives are decrypted with the cipher selected for projectId.
+ */
+export async function listArchiveEntries(
+  archivePath: string,
+  projectId?: string
+): Promise<ArchiveEntryInfo[]> {
+  return invoke<ArchiveEntryInfo[]>('list_archive_entries', {
+    archivePath,
+    projectId: projectId ?? null,
+  });
+}
+
+// Entry selection for extraction - matches Rust backend
+export type OverwritePolicy = 'overwrite' | 'skip_existing';
+
+export interface ExtractOptions {
+  /** Globs of entries to extract, e.g. `Data/**` (all entries if empty) */
+  include?: string[];
+  /** Globs of entries to leave out */
+  exclude?: string[];
+  overwrite?: OverwritePolicy;
+}
+
+export interface ExtractReport {
+  entries: ArchiveEntryInfo[];
+  totalBytes: number;
+  skipped: number;
+}
+
+// Progress of an archive operation - matches Rust backend
+export interface ArchiveProgress {
+  operationId: string | null;
+  entriesDone: number;
+  entriesTotal: number;
+  bytesDone: number;
+  bytesTotal: number;
+  current: string;
+}
+
+/**
+ * Listen to the progress of archive operations
+ */
+export async function onArchiveProgress(
+  handler: (progress: ArchiveProgress) => void
+): Promise<UnlistenFn> {
+  return listen<ArchiveProgress>('archive-progress', (event) => handler(event.payload));
+}
+
+/**
+ * Cancel a running archive operation (false if it is not running)
+ */
+export async function cancelArchiveOperation(operationId: string): Promise<boolean> {
+  return invoke<boolean>('cancel_archive_operation', { operationId });
+}
+
+/**
+ * Extract the entries of an archive into a folder
+ * (with dryRun, only reports what would be extracted)
+ *
+ * Pass an operationId to match progress events and to cancel the extraction.
+ * XP3 archives are decrypted with the cipher selected for projectId.
+ */
+export async function extractArchive(
+  archivePath: string,
+  outputDir: string,
+  options?: ExtractOptions,
+  dryRun = false,
+  operationId?: string,
+  projectId?: string
+): Promise<ExtractReport> {
+  return invoke<ExtractReport>('extract_archive', {
+    archivePath,
+    outputDir,
+    options: options ?? null,
+    dryRun,
+    projectId: projectId ?? null,
+    operationId: operationId ?? null,
+  });
+}
+
+// Difference between two archives - matches Rust backend
+export interface ArchiveChange {
+  name: string;
+  kind: 'added' | 'removed' | 'modified';
+  oldSize: number | null;
+  newSize: number | null;
+}
+
+export interface ArchiveDiff {
+  changes: ArchiveChange[];
+  unchanged: number;
+}
+
+/**
+ * Compare two archives, or an archive and an unpacked folder
+ *
+ * XP3 archives are decrypted with the cipher selected for projectId.
+ */
+export async function diffArchives(
+  oldPath: string,
+  newPath: string,
+  projectId?: string
+): Promise<ArchiveDiff> {
+  return invoke<ArchiveDiff>('diff_archives', {
+    oldPath,
+    newPath,
+    projectId: projectId ?? null,
+  });
+}
+
+// Problem found in an RGSS archive - matches Rust backend
+export type RgssIssueKind =
+  | { type: 'modified_magic' }
+  | { type: 'truncated_record' }
+  | { type: 'bad_name_length'; length: number }
+  | { type: 'bad_name' }
+  | { type: 'undecodable_name' }
+  | { type: 'data_past_eof'; data_offset: number; size: number }
+  | { type: 'overlapping_data'; other: string };
+
+export interface RgssIssue {
+  offset: number;
+  name: string | null;
+  kind: RgssIssueKind;
+}
+
+export interface RgssReport {
+  version: number;
+  fileSize: number;
+  entryCount: number;
+  issues: RgssIssue[];
+}
+
+/**
+ * Check an RGSS archive and report damaged or suspicious records
+ */
+export async function verifyRgssArchive(archivePath: string): Promise<RgssReport> {
+  return invoke<RgssReport>('verify_rgss_archive', { archivePath });
+}
+
+/**
+ * Extract an RGSS archive, optionally skipping damaged records
+ * (with dryRun, only reports what would be extracted)
+ */
+export async function extractRgssArchive(
+  archivePath: string,
+  outputDir: string,
+  salvage = false,
+  options?: ExtractOptions,
+  dryRun = false,
+  operationId?: string
+): Promise<ExtractReport> {
+  return invoke<ExtractReport>('extract_rgss_archive', {
+    archivePath,
+    outputDir,
+    salvage,
+    options: options ?? null,
+    dryRun,
+    operationId: operationId ?? null,
+  });
+}
+
+// Result of patching an RGSS archive - matches Rust backend
+export interface RgssEntryChange {
+  name: string;
+  kind: 'added' | 'modified';
+  oldSize: number | null;
+  newSize: number;
+}
+
+export interface RgssPatchReport {
+  changes: RgssEntryChange[];
+  copied: number;
+  reencrypted: number;
+}
+
+/**
+ * Patch an RGSS archive with the files of a folder, rewriting only changed entries
+ * (writes over the archive if no output path is given)
+ *
+ * Pass an operationId to match progress events and to cancel the patch.
+ */
+export async function patchRgssArchive(
+  archivePath: string,
+  replacementDir: string,
+  outputPath?: string,
+  operationId?: string
+): Promise<RgssPatchReport> {
+  return invoke<RgssPatchReport>('patch_rgss_archive', {
+    archivePath,
+    replacementDir,
+    outputPath: outputPath ?? null,
+    operationId: operationId ?? null,
+  });
+}
+
+// XP3 cipher scheme - matches Rust backend
+export type Xp3CipherScheme =
+  | { type: 'none' }
+  | { type: 'fixed_xor'; key: number }
+  | { type: 'hash_xor'; shift: number };
+
+/**
+ * Detect the cipher scheme of an XP3 archive (null if unknown)
+ */
+export async function detectXp3Cipher(archivePath: string): Promise<Xp3CipherScheme | null> {
+  return invoke<Xp3CipherScheme | null>('detect_xp3_cipher', { archivePath });
+}
+
+/**
+ * Get the XP3 cipher scheme selected for a project
+ */
+export async function getProjectXp3Cipher(id: string): Promise<Xp3CipherScheme> {
+  return invoke<Xp3CipherScheme>('get_project_xp3_cipher', { id });
+}
+
+/**
+ * Select the XP3 cipher scheme for a project
+ */
+export async function setProjectXp3Cipher(id: string, scheme: Xp3CipherScheme): Promise<void> {
+  return invoke<void>('set_project_xp3_cipher', { id, scheme });
+}
+
+/**
+ * Decrypt encrypted MV/MZ assets of a game into an output folder
+ */
+export async function decryptRpgMvAssets(
+  gamePath: string,
+  outputDir: string,
+  inputDir?: string
+): Promise<number> {
+  return invoke<number>('decrypt_rpgmv_assets', { gamePath, inputDir, outputDir });
+}
+
+/**
+ * Re-encrypt plain MV/MZ assets with a game's key into an output folder
+ */
+export async function encryptRpgMvAssets(
+  gamePath: string,
+  inputDir: string,
+  outputDir: string
+): Promise<number> {
+  return invoke<number>('encrypt_rpgmv_assets', { gamePath, inputDir, outputDir });
+}
@@ -20,6 +20,8 @@ export type RPGMakerVersion =
   | 'VXAce'
   | 'VX'
   | 'XP'
+  | 'Rm2003'
+  | 'Rm2000'
   | 'Unknown';
 
 // Progress state - matches Rust backend